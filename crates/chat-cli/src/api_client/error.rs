@@ -118,6 +118,17 @@ impl ApiClientError {
             Self::Credentials(_e) => None,
         }
     }
+
+    /// Whether the request was refused because the login token expired or was revoked, or
+    /// couldn't be loaded for it, so that it can be sent again once the token is refreshed.
+    pub fn is_expired_token(&self) -> bool {
+        match self {
+            Self::CodewhispererGenerateAssistantResponse(e) => sdk_expired_token(e),
+            Self::CodewhispererChatResponseStream(e) => sdk_expired_token(e),
+            Self::AuthError(e) => e.is_invalid_token(),
+            _ => false,
+        }
+    }
 }
 
 impl ReasonCode for ApiClientError {
@@ -151,6 +162,28 @@ fn sdk_error_code<T: ProvideErrorMetadata, R>(e: &SdkError<T, R>) -> String {
         .unwrap_or_else(|| e.to_string())
 }
 
+fn sdk_expired_token<T, R>(e: &SdkError<T, R>) -> bool
+where
+    T: ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    let refused = e.as_service_error().is_some_and(|se| match se.meta().code() {
+        Some("ExpiredTokenException") => true,
+        Some("AccessDeniedException") => se.meta().message().is_some_and(|message| {
+            let message = message.to_lowercase();
+            message.contains("expired") || message.contains("bearer token")
+        }),
+        _ => false,
+    });
+    // The token was missing or refused when loading or refreshing it before the request was sent.
+    let unresolved = std::iter::successors(std::error::Error::source(e), |source| source.source()).any(|source| {
+        source
+            .downcast_ref::<AuthError>()
+            .is_some_and(AuthError::is_invalid_token)
+    });
+    refused || unresolved
+}
+
 fn sdk_status_code<E>(e: &SdkError<E, Response>) -> Option<u16> {
     e.raw_response().map(|res| res.status().as_u16())
 }
@@ -223,4 +256,34 @@ mod tests {
             println!("{error} {error:?}");
         }
     }
+
+    #[test]
+    fn test_is_expired_token() {
+        let service_error = |code: &str, message: &str| {
+            ApiClientError::CodewhispererGenerateAssistantResponse(SdkError::service_error(
+                GenerateAssistantResponseError::generic(
+                    aws_smithy_types::error::ErrorMetadata::builder()
+                        .code(code)
+                        .message(message)
+                        .build(),
+                ),
+                response(),
+            ))
+        };
+        assert!(service_error("ExpiredTokenException", "The token has expired").is_expired_token());
+        assert!(
+            service_error(
+                "AccessDeniedException",
+                "The bearer token included in the request is invalid."
+            )
+            .is_expired_token()
+        );
+        assert!(!service_error("AccessDeniedException", "You don't have a subscription").is_expired_token());
+        assert!(!service_error("ValidationException", "Input is too long.").is_expired_token());
+        assert!(ApiClientError::AuthError(AuthError::NoToken).is_expired_token());
+        // A token that failed to refresh for now, e.g. without network, isn't refused.
+        let io_error = std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out");
+        assert!(!ApiClientError::AuthError(AuthError::Io(io_error)).is_expired_token());
+        assert!(!ApiClientError::ContextWindowOverflow { status_code: None }.is_expired_token());
+    }
 }
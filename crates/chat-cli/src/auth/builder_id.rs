@@ -419,6 +419,11 @@ impl BuilderIdToken {
         is_expired(&self.expires_at)
     }
 
+    /// Whether the token expires within `duration` from now
+    pub fn expires_within(&self, duration: time::Duration) -> bool {
+        time::OffsetDateTime::now_utc() + duration > self.expires_at
+    }
+
     /// Save the token to the keychain
     pub async fn save(&self, database: &Database) -> Result<(), AuthError> {
         database
@@ -523,10 +528,46 @@ pub async fn poll_create_token(
     }
 }
 
+/// Whether requests are signed with IAM credentials from the environment instead of a Builder ID
+/// or IAM Identity Center token
+pub fn is_sigv4() -> bool {
+    std::env::var("AMAZON_Q_SIGV4").is_ok_and(|v| !v.is_empty())
+}
+
 pub async fn is_logged_in(database: &mut Database) -> bool {
     // Check for BuilderId if not using Sigv4
-    std::env::var("AMAZON_Q_SIGV4").is_ok_and(|v| !v.is_empty())
-        || matches!(BuilderIdToken::load(database).await, Ok(Some(_)))
+    is_sigv4() || matches!(BuilderIdToken::load(database).await, Ok(Some(_)))
+}
+
+/// Load the token and refresh it if it expires within `margin`, rather than only once it expired,
+/// so that it doesn't expire during the requests of a long running session. A token that can't be
+/// refreshed yet, e.g. without a refresh token or client registration, is kept until it expires.
+pub async fn refresh_if_expiring(
+    database: &Database,
+    margin: time::Duration,
+) -> Result<Option<BuilderIdToken>, AuthError> {
+    match BuilderIdToken::load(database).await? {
+        Some(token) if token.refresh_token.is_some() && token.expires_within(margin) => {
+            let region = token.region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
+            match token.refresh_token(&client(region.clone()), database, &region).await? {
+                Some(refreshed) => Ok(Some(refreshed)),
+                None => Ok(Some(token)),
+            }
+        },
+        token => Ok(token),
+    }
+}
+
+/// Refreshes the token whatever its expiry, e.g. after a request was refused with it. `None` when
+/// there's no token or it can't be refreshed, and logging in again is needed.
+pub async fn refresh(database: &Database) -> Result<Option<BuilderIdToken>, AuthError> {
+    match BuilderIdToken::load(database).await? {
+        Some(token) => {
+            let region = token.region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
+            token.refresh_token(&client(region.clone()), database, &region).await
+        },
+        None => Ok(None),
+    }
 }
 
 pub async fn logout(database: &mut Database) -> Result<(), AuthError> {
@@ -625,6 +666,36 @@ mod tests {
         assert!(token.is_expired());
     }
 
+    #[test]
+    fn test_expires_within() {
+        let mut token = BuilderIdToken::test();
+        assert!(!token.expires_within(time::Duration::minutes(10)));
+
+        token.expires_at = time::OffsetDateTime::now_utc() + time::Duration::minutes(5);
+        assert!(!token.is_expired());
+        assert!(token.expires_within(time::Duration::minutes(10)));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_if_expiring_keeps_valid_token() {
+        let database = Database::new().await.unwrap();
+        let mut token = BuilderIdToken::test();
+        token.expires_at = time::OffsetDateTime::now_utc() + time::Duration::minutes(5);
+        token.refresh_token = None;
+        token.save(&database).await.unwrap();
+
+        // Without a refresh token, the token isn't deleted while it's still valid.
+        let margin = time::Duration::minutes(10);
+        let loaded = refresh_if_expiring(&database, margin).await.unwrap().unwrap();
+        assert_eq!(loaded.access_token.0, token.access_token.0);
+        assert!(BuilderIdToken::load(&database).await.unwrap().is_some());
+
+        // Without a client registration to refresh it with, it's kept as well.
+        token.refresh_token = Some(Secret("test_refresh_token".to_string()));
+        token.save(&database).await.unwrap();
+        assert!(refresh_if_expiring(&database, margin).await.unwrap().is_some());
+    }
+
     #[test]
     fn test_token_type() {
         let mut token = BuilderIdToken::test();
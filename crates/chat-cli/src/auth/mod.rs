@@ -48,6 +48,20 @@ pub enum AuthError {
     DatabaseError(#[from] crate::database::DatabaseError),
 }
 
+impl AuthError {
+    /// Whether there's no login token or it was refused for good, e.g. its refresh token expired or
+    /// was revoked, rather than failing to load or refresh for now, e.g. without network.
+    pub fn is_invalid_token(&self) -> bool {
+        match self {
+            Self::NoToken => true,
+            Self::SdkCreateToken(err) => err
+                .as_service_error()
+                .is_some_and(|err| err.is_invalid_grant_exception() || err.is_expired_token_exception()),
+            _ => false,
+        }
+    }
+}
+
 impl From<aws_sdk_ssooidc::Error> for AuthError {
     fn from(value: aws_sdk_ssooidc::Error) -> Self {
         Self::Ssooidc(Box::new(value))
@@ -0,0 +1,297 @@
+//! `q changelog` drafts release notes from the commits since a tag and adds them to
+//! `CHANGELOG.md`.
+//!
+//! Commits are grouped by their conventional-commit type (`feat:`, `fix(scope):`, ...) before the
+//! model turns them into release notes, and merged pull requests are listed by their titles.
+
+use std::collections::BTreeMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+use anstream::println;
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+};
+use time::OffsetDateTime;
+use tokio::process::Command;
+
+use crate::api_client::model::{
+    ChatResponseStream,
+    ConversationState,
+    UserInputMessage,
+};
+use crate::os::Os;
+
+/// Separates the fields of each commit in the `git log` output.
+const FIELD_SEPARATOR: char = '\u{1f}';
+/// Separates commits in the `git log` output.
+const COMMIT_SEPARATOR: char = '\u{1e}';
+
+#[derive(Debug, PartialEq, Args)]
+pub struct ChangelogArgs {
+    /// Tag to collect changes since, defaults to the most recent tag
+    #[arg(long, value_name = "TAG")]
+    pub since: Option<String>,
+    /// Heading of the new release section
+    #[arg(long, default_value = "Unreleased")]
+    pub version: String,
+    /// Changelog file to update
+    #[arg(long, default_value = "CHANGELOG.md")]
+    pub file: PathBuf,
+    /// Write the release notes without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl ChangelogArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let cwd = os.env.current_dir()?;
+        let since = match self.since {
+            Some(since) => since,
+            None => git(&cwd, &["describe", "--tags", "--abbrev=0"])
+                .await?
+                .trim()
+                .to_string(),
+        };
+        let log = git(&cwd, &[
+            "log",
+            "--no-color",
+            &format!("--format=%s{FIELD_SEPARATOR}%b{COMMIT_SEPARATOR}"),
+            &format!("{since}..HEAD"),
+        ])
+        .await?;
+        let groups = group_commits(&log);
+        if groups.is_empty() {
+            bail!("There are no commits since {since}");
+        }
+
+        println!(
+            "Writing release notes for {} commits since {since}...",
+            groups.values().map(Vec::len).sum::<usize>()
+        );
+        let notes = generate_notes(os, &release_prompt(&since, &groups)).await?;
+        if notes.is_empty() {
+            bail!("The model didn't return any release notes");
+        }
+        println!("\n{notes}\n");
+
+        let path = cwd.join(&self.file);
+        if !self.yes {
+            let confirmed = dialoguer::Confirm::with_theme(&crate::util::dialoguer_theme())
+                .with_prompt(format!("Add these notes to {}?", self.file.display()))
+                .default(true)
+                .interact()?;
+            if !confirmed {
+                println!("Not updating {}", self.file.display());
+                return Ok(ExitCode::SUCCESS);
+            }
+        }
+
+        let existing = match os.fs.exists(&path) {
+            true => os.fs.read_to_string(&path).await?,
+            false => String::new(),
+        };
+        let section = format!("## {} - {}\n\n{notes}", self.version, OffsetDateTime::now_utc().date());
+        os.fs.write(&path, insert_release(&existing, &section)).await?;
+        println!("{} {}", "Updated".green(), self.file.display());
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+async fn git(cwd: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(cwd).output().await?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The changelog section a conventional-commit type is listed under.
+fn section_for(kind: &str) -> &'static str {
+    match kind {
+        "feat" => "Features",
+        "fix" => "Bug Fixes",
+        "perf" => "Performance",
+        "refactor" => "Refactoring",
+        "docs" => "Documentation",
+        "test" | "build" | "ci" | "chore" | "style" => "Maintenance",
+        "revert" => "Reverts",
+        _ => "Other Changes",
+    }
+}
+
+/// Groups the commits of a `git log` by changelog section. Merge commits of pull requests are
+/// listed by the pull request's title, and breaking changes get their own section.
+fn group_commits(log: &str) -> BTreeMap<&'static str, Vec<String>> {
+    let mut groups: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+    for commit in log.split(COMMIT_SEPARATOR) {
+        let (subject, body) = commit.split_once(FIELD_SEPARATOR).unwrap_or((commit, ""));
+        let subject = subject.trim();
+        if subject.is_empty() {
+            continue;
+        }
+
+        // GitHub merge commits carry the pull request title as the first line of the body.
+        let title = match subject.strip_prefix("Merge pull request #") {
+            Some(rest) => {
+                let number = rest.split_whitespace().next().unwrap_or_default();
+                match body.lines().map(str::trim).find(|line| !line.is_empty()) {
+                    Some(title) => format!("{title} (#{number})"),
+                    None => subject.to_string(),
+                }
+            },
+            None if subject.starts_with("Merge ") => continue,
+            None => subject.to_string(),
+        };
+
+        let (section, description) = match title.split_once(": ") {
+            Some((prefix, description))
+                if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphanumeric() || "()!-_".contains(c)) =>
+            {
+                let breaking = prefix.ends_with('!') || body.contains("BREAKING CHANGE");
+                let kind = prefix.split(['(', '!']).next().unwrap_or(prefix);
+                let section = if breaking {
+                    "Breaking Changes"
+                } else {
+                    section_for(kind)
+                };
+                (section, description.to_string())
+            },
+            _ => (section_for(""), title.clone()),
+        };
+        groups.entry(section).or_default().push(description);
+    }
+    groups
+}
+
+fn release_prompt(since: &str, groups: &BTreeMap<&'static str, Vec<String>>) -> String {
+    let mut commits = String::new();
+    for (section, descriptions) in groups {
+        commits.push_str(&format!("### {section}\n"));
+        for description in descriptions {
+            commits.push_str(&format!("- {description}\n"));
+        }
+    }
+    format!(
+        "Write release notes for the changes since {since}, for the people who use this project.
+
+Keep the sections below, dropping any without a noteworthy change. Rewrite each change as a short,
+plain sentence about its effect for users, merge related changes, and leave out changes that only
+matter to maintainers. Reply with only the markdown sections, as ### headings with bullet lists,
+without a title.
+
+{commits}"
+    )
+}
+
+/// Sends a single prompt to the model and returns its reply.
+async fn generate_notes(os: &Os, prompt: &str) -> Result<String> {
+    let mut response = os
+        .client
+        .send_message(ConversationState {
+            conversation_id: None,
+            user_input_message: UserInputMessage {
+                content: prompt.to_string(),
+                user_input_message_context: None,
+                user_intent: None,
+                images: None,
+                model_id: None,
+            },
+            history: None,
+        })
+        .await?;
+
+    let mut notes = String::new();
+    while let Some(event) = response.recv().await? {
+        if let ChatResponseStream::AssistantResponseEvent { content } = event {
+            notes.push_str(&content);
+        }
+    }
+    Ok(notes.trim().to_string())
+}
+
+/// Adds `section` above the newest release in `changelog`, keeping a leading `# ` title.
+fn insert_release(changelog: &str, section: &str) -> String {
+    let title_end = match changelog.starts_with("# ") {
+        true => changelog.find("\n## ").map_or(changelog.len(), |i| i + 1),
+        false => 0,
+    };
+    let (head, rest) = changelog.split_at(title_end);
+    let head = match head.trim() {
+        "" => "# Changelog".to_string(),
+        head => head.to_string(),
+    };
+    let rest = rest.trim();
+    match rest.is_empty() {
+        true => format!("{head}\n\n{section}\n"),
+        false => format!("{head}\n\n{section}\n\n{rest}\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_commits() {
+        let log = [
+            "feat(chat): add /speak\u{1f}\u{1e}",
+            "fix: handle empty diffs\u{1f}\u{1e}",
+            "feat!: drop the old config format\u{1f}\u{1e}",
+            "refactor: tidy up\u{1f}BREAKING CHANGE: settings moved\n\u{1e}",
+            "Merge pull request #12 from someone/branch\u{1f}docs: explain groups\n\u{1e}",
+            "Merge branch 'main' into topic\u{1f}\u{1e}",
+            "Bump version\u{1f}\u{1e}",
+        ]
+        .join("\n");
+
+        let groups = group_commits(&log);
+        assert_eq!(groups["Features"], ["add /speak"]);
+        assert_eq!(groups["Bug Fixes"], ["handle empty diffs"]);
+        assert_eq!(groups["Breaking Changes"], ["drop the old config format", "tidy up"]);
+        assert_eq!(groups["Documentation"], ["explain groups (#12)"]);
+        assert_eq!(groups["Other Changes"], ["Bump version"]);
+        assert_eq!(groups.len(), 5);
+    }
+
+    #[test]
+    fn test_insert_release() {
+        let section = "## 1.1.0 - 2026-10-14\n\n### Features\n- Speak";
+        assert_eq!(
+            insert_release("", section),
+            "# Changelog\n\n## 1.1.0 - 2026-10-14\n\n### Features\n- Speak\n"
+        );
+        assert_eq!(
+            insert_release("# Changelog\n\nAll notable changes.\n\n## 1.0.0\n\n- First\n", section),
+            "# Changelog\n\nAll notable changes.\n\n## 1.1.0 - 2026-10-14\n\n### Features\n- Speak\n\n## 1.0.0\n\n- First\n"
+        );
+        assert_eq!(
+            insert_release("## 1.0.0\n- First\n", section),
+            "# Changelog\n\n## 1.1.0 - 2026-10-14\n\n### Features\n- Speak\n\n## 1.0.0\n- First\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_notes() {
+        let mut os = Os::new().await.unwrap();
+        os.client.set_mock_output(serde_json::json!([[
+            "### Features\n",
+            "- Responses can be read aloud\n"
+        ]]));
+        assert_eq!(
+            generate_notes(&os, "prompt").await.unwrap(),
+            "### Features\n- Responses can be read aloud"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Opt-in usage counts kept on this machine, enabled with the `chat.enableLocalAnalytics` setting.
+//!
+//! Counts are written to [directories::chat_analytics_path] as they change and are never sent
+//! anywhere. `/analytics show` prints them and `/analytics export` writes them to a file the user
+//! can choose to share.
+
+use std::collections::BTreeMap;
+
+use eyre::Result;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::directories;
+
+/// How the user answered a tool confirmation prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolConfirmation {
+    Accepted,
+    Trusted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolConfirmations {
+    pub accepted: u64,
+    pub trusted: u64,
+    pub rejected: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AnalyticsCounts {
+    /// When counting started, or was last reset.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub since: Option<OffsetDateTime>,
+    /// Uses of each slash command, by its name without aliases.
+    pub commands: BTreeMap<String, u64>,
+    /// Times completions were offered.
+    pub completions_shown: u64,
+    /// Completions that were picked.
+    pub completions_accepted: u64,
+    pub tool_confirmations: BTreeMap<String, ToolConfirmations>,
+}
+
+impl AnalyticsCounts {
+    pub fn new() -> Self {
+        Self {
+            since: Some(OffsetDateTime::now_utc()),
+            ..Default::default()
+        }
+    }
+
+    pub fn record_command(&mut self, name: &str) {
+        *self.commands.entry(name.to_string()).or_default() += 1;
+    }
+
+    pub fn record_completions(&mut self, shown: u64, accepted: u64) {
+        self.completions_shown += shown;
+        self.completions_accepted += accepted;
+    }
+
+    pub fn record_tool_confirmation(&mut self, tool: &str, confirmation: ToolConfirmation) {
+        let counts = self.tool_confirmations.entry(tool.to_string()).or_default();
+        match confirmation {
+            ToolConfirmation::Accepted => counts.accepted += 1,
+            ToolConfirmation::Trusted => counts.trusted += 1,
+            ToolConfirmation::Rejected => counts.rejected += 1,
+        }
+    }
+
+    /// The share of offered completions that were picked, as a percentage.
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        (self.completions_shown > 0).then(|| self.completions_accepted as f64 / self.completions_shown as f64 * 100.0)
+    }
+}
+
+pub fn is_enabled(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ChatEnableLocalAnalytics)
+        .unwrap_or(false)
+}
+
+/// Reads the stored counts, or returns `None` if nothing has been recorded yet.
+pub async fn load(os: &Os) -> Result<Option<AnalyticsCounts>> {
+    let path = directories::chat_analytics_path(os)?;
+    if !os.fs.exists(&path) {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&os.fs.read(&path).await?)?))
+}
+
+pub async fn save(os: &Os, counts: &AnalyticsCounts) -> Result<()> {
+    let path = directories::chat_analytics_path(os)?;
+    if let Some(parent) = path.parent() {
+        os.fs.create_dir_all(parent).await?;
+    }
+    os.fs.write(&path, serde_json::to_vec_pretty(counts)?).await?;
+    Ok(())
+}
+
+pub async fn reset(os: &Os) -> Result<()> {
+    let path = directories::chat_analytics_path(os)?;
+    if os.fs.exists(&path) {
+        os.fs.remove_file(&path).await?;
+    }
+    Ok(())
+}
+
+/// Updates the stored counts with `record` if analytics are enabled. Failures are logged rather
+/// than interrupting the session.
+pub async fn record(os: &Os, record: impl FnOnce(&mut AnalyticsCounts)) {
+    if !is_enabled(os) {
+        return;
+    }
+    let mut counts = match load(os).await {
+        Ok(counts) => counts.unwrap_or_else(AnalyticsCounts::new),
+        Err(err) => {
+            warn!(?err, "failed to read local analytics, starting over");
+            AnalyticsCounts::new()
+        },
+    };
+    record(&mut counts);
+    if let Err(err) = save(os, &counts).await {
+        warn!(?err, "failed to write local analytics");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record() {
+        let mut os = Os::new().await.unwrap();
+        record(&os, |counts| counts.record_command("/context")).await;
+        assert_eq!(load(&os).await.unwrap(), None);
+
+        os.database
+            .settings
+            .set(Setting::ChatEnableLocalAnalytics, true)
+            .await
+            .unwrap();
+        record(&os, |counts| counts.record_command("/context")).await;
+        record(&os, |counts| {
+            counts.record_command("/context");
+            counts.record_completions(4, 1);
+            counts.record_tool_confirmation("fs_write", ToolConfirmation::Trusted);
+        })
+        .await;
+
+        let counts = load(&os).await.unwrap().unwrap();
+        assert!(counts.since.is_some());
+        assert_eq!(counts.commands["/context"], 2);
+        assert_eq!(counts.acceptance_rate(), Some(25.0));
+        assert_eq!(counts.tool_confirmations["fs_write"], ToolConfirmations {
+            accepted: 0,
+            trusted: 1,
+            rejected: 0,
+        });
+
+        reset(&os).await.unwrap();
+        assert_eq!(load(&os).await.unwrap(), None);
+    }
+}
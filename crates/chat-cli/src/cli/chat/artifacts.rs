@@ -0,0 +1,248 @@
+//! Large tool outputs are stored on disk as artifacts instead of entering the conversation history
+//! in full. The tool result sent to the model is a preview of the output along with the artifact's
+//! id, and the model pages through the rest with `read_artifact` or gets a condensed version with
+//! `summarize_output`.
+//!
+//! Artifacts are kept under [directories::chat_artifacts_dir], encrypted when `storage.encrypt` is
+//! on, and removed a week after they're stored, so ids in a resumed conversation keep working for a
+//! while.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+use serde_json::Value;
+use tracing::warn;
+
+use super::tools::OutputKind;
+use crate::os::Os;
+use crate::util::{
+    directories,
+    encryption,
+};
+
+/// Outputs longer than this are stored as an artifact.
+pub const ARTIFACT_THRESHOLD: usize = 40_000;
+
+/// Lines of a stored output kept at its start and end in the preview.
+const PREVIEW_HEAD_LINES: usize = 30;
+const PREVIEW_TAIL_LINES: usize = 50;
+
+/// Longest preview line, the rest of a line is cut.
+pub const MAX_LINE_CHARS: usize = 300;
+
+/// Artifacts are removed this long after they're stored.
+const ARTIFACT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The path of the artifact `id`, which must be an id handed out by [store].
+pub fn artifact_path(os: &Os, id: &str) -> Result<PathBuf> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("'{id}' is not an artifact id");
+    }
+    Ok(directories::chat_artifacts_dir(os)?.join(format!("{id}.txt")))
+}
+
+/// Reads the artifact `id`.
+pub async fn read(os: &Os, id: &str) -> Result<String> {
+    let path = artifact_path(os, id)?;
+    if !os.fs.exists(&path) {
+        bail!("There is no artifact '{id}', it may have expired");
+    }
+    let contents = encryption::decrypt(os, os.fs.read(&path).await?).await?;
+    Ok(String::from_utf8_lossy(&contents).to_string())
+}
+
+/// Stores `text` as a new artifact, returning its id.
+pub async fn store(os: &Os, text: &str) -> Result<String> {
+    let dir = directories::chat_artifacts_dir(os)?;
+    os.fs.create_dir_all(&dir).await?;
+    remove_expired(os, &dir).await;
+
+    let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let contents = encryption::encrypt_if_enabled(os, text.as_bytes().to_vec()).await?;
+    os.fs.write(dir.join(format!("{id}.txt")), contents).await?;
+    Ok(id)
+}
+
+/// Stores a large tool output as an artifact and replaces it with a preview that names the
+/// artifact.
+///
+/// The text fields of a JSON output, such as a command's `stdout`, are stored on their own so the
+/// rest of the output is kept as is. JSON that is still too large is stored as a whole.
+pub async fn store_large_output(os: &Os, output: &mut OutputKind) {
+    match output {
+        OutputKind::Text(text) => shorten(os, text).await,
+        OutputKind::Json(json) => {
+            if let Value::Object(fields) = json {
+                for value in fields.values_mut() {
+                    if let Value::String(text) = value {
+                        shorten(os, text).await;
+                    }
+                }
+            }
+            let Ok(mut text) = serde_json::to_string_pretty(json) else {
+                return;
+            };
+            if text.len() > ARTIFACT_THRESHOLD {
+                shorten(os, &mut text).await;
+                *output = OutputKind::Text(text);
+            }
+        },
+        _ => (),
+    }
+}
+
+async fn shorten(os: &Os, text: &mut String) {
+    if text.len() <= ARTIFACT_THRESHOLD {
+        return;
+    }
+    match store(os, text).await {
+        Ok(id) => *text = preview(text, &id),
+        Err(err) => warn!(?err, "failed to store a large output as an artifact"),
+    }
+}
+
+async fn remove_expired(os: &Os, dir: &Path) {
+    let Ok(mut entries) = os.fs.read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let expired = entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| SystemTime::now().duration_since(modified).unwrap_or_default() > ARTIFACT_TTL);
+        if expired {
+            if let Err(err) = os.fs.remove_file(dir.join(entry.file_name())).await {
+                warn!(?err, path = ?entry.path(), "failed to remove an expired artifact");
+            }
+        }
+    }
+}
+
+/// Cuts `line` to [MAX_LINE_CHARS].
+pub fn cut_line(line: &str) -> &str {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    }
+}
+
+/// The first and last lines of a stored output, with a note on how to read the rest.
+fn preview(text: &str, id: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let head = lines.len().min(PREVIEW_HEAD_LINES);
+    let tail = lines.len().saturating_sub(PREVIEW_TAIL_LINES).max(head);
+    // An output of few but long lines is only shortened by cutting them.
+    let omitted = if tail > head {
+        format!("lines {}-{tail} of {} omitted", head + 1, lines.len())
+    } else {
+        format!("lines cut to {MAX_LINE_CHARS} characters")
+    };
+    let mut preview = String::new();
+    for line in &lines[..head] {
+        preview.push_str(cut_line(line));
+        preview.push('\n');
+    }
+    preview.push_str(&format!(
+        "\n[... {omitted}. The full output of {} bytes is stored as artifact {id}: call read_artifact \
+with artifact_id \"{id}\" and an offset to page through it, or summarize_output with artifact_id \
+\"{id}\" for a condensed version ...]\n\n",
+        text.len()
+    ));
+    for line in &lines[tail..] {
+        preview.push_str(cut_line(line));
+        preview.push('\n');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn artifact_id(preview: &str) -> String {
+        Regex::new(r#"artifact_id "([0-9a-f]+)""#)
+            .unwrap()
+            .captures(preview)
+            .unwrap()[1]
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_store_large_output() {
+        let os = Os::new().await.unwrap();
+        let stdout = (0..5000).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let mut output = OutputKind::Json(serde_json::json!({
+            "exit_status": "0",
+            "stdout": stdout,
+            "stderr": "",
+        }));
+        store_large_output(&os, &mut output).await;
+
+        let OutputKind::Json(json) = &output else {
+            panic!("the output should still be JSON");
+        };
+        assert_eq!(json["exit_status"], "0");
+        let preview = json["stdout"].as_str().unwrap();
+        assert!(preview.len() < 2_000);
+        assert!(preview.starts_with("line 0\n"));
+        assert!(preview.contains("lines 31-4950 of 5000 omitted"));
+        assert!(preview.ends_with("line 4999\n"));
+        assert_eq!(read(&os, &artifact_id(preview)).await.unwrap(), stdout);
+
+        let mut output = OutputKind::Text(vec!["x".repeat(1_000); 60].join("\n"));
+        store_large_output(&os, &mut output).await;
+        let OutputKind::Text(preview) = &output else {
+            panic!("text should stay text");
+        };
+        assert!(preview.contains("[... lines cut to 300 characters."));
+        assert_eq!(preview.lines().filter(|line| line.starts_with('x')).count(), 60);
+
+        let mut output = OutputKind::Text("short".to_string());
+        store_large_output(&os, &mut output).await;
+        assert!(matches!(output, OutputKind::Text(text) if text == "short"));
+
+        assert!(read(&os, "../secrets").await.is_err());
+        assert!(read(&os, "abcdef12").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store_large_json() {
+        let os = Os::new().await.unwrap();
+        let items: Vec<Value> = (0..5000).map(|i| serde_json::json!({ "id": i })).collect();
+        let mut output = OutputKind::Json(Value::Array(items));
+        store_large_output(&os, &mut output).await;
+
+        let OutputKind::Text(preview) = &output else {
+            panic!("large JSON should be replaced by a preview");
+        };
+        assert!(preview.starts_with("[\n"));
+        assert!(read(&os, &artifact_id(preview)).await.unwrap().contains("\"id\": 4999"));
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_artifact() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(crate::database::settings::Setting::StorageEncrypt, "on")
+            .await
+            .unwrap();
+        let id = store(&os, "proprietary output").await.unwrap();
+        let contents = os.fs.read(artifact_path(&os, &id).unwrap()).await.unwrap();
+        assert!(encryption::is_encrypted(&contents));
+        assert_eq!(read(&os, &id).await.unwrap(), "proprietary output");
+    }
+}
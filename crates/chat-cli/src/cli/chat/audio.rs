@@ -0,0 +1,174 @@
+//! Reads assistant responses aloud with the platform's text-to-speech engine, toggled with
+//! `/speak on|off`.
+//!
+//! Speech uses `say` on macOS, `espeak` on Linux and System.Speech through PowerShell on Windows.
+//! The voice and rate in words per minute come from the `chat.speakVoice` and `chat.speakRate`
+//! settings.
+
+use std::process::Stdio;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{
+    Child,
+    Command,
+};
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Speaks responses one at a time, cutting off the previous one when the next arrives.
+#[derive(Debug, Default)]
+pub struct Speaker {
+    current: Option<Child>,
+}
+
+impl Speaker {
+    /// Starts reading `markdown` aloud without waiting for it to finish. Code blocks are skipped.
+    pub async fn speak(&mut self, os: &Os, markdown: &str) -> Result<()> {
+        self.stop();
+        let text = speakable_text(markdown);
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let voice = os.database.settings.get_string(Setting::ChatSpeakVoice);
+        let rate = os.database.settings.get_int(Setting::ChatSpeakRate);
+        let mut command = tts_command(voice.as_deref(), rate);
+        let mut child = match command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let program = command.as_std().get_program().to_string_lossy().to_string();
+                bail!("'{program}' was not found, is a text-to-speech engine installed?")
+            },
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).await?;
+        }
+        self.current = Some(child);
+        Ok(())
+    }
+
+    /// Stops the response being read, if any.
+    pub fn stop(&mut self) {
+        if let Some(mut child) = self.current.take() {
+            let _ = child.start_kill();
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn tts_command(voice: Option<&str>, rate: Option<i64>) -> Command {
+    let mut command = Command::new("say");
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    if let Some(rate) = rate {
+        command.args(["-r", &rate.to_string()]);
+    }
+    command
+}
+
+#[cfg(windows)]
+fn tts_command(voice: Option<&str>, rate: Option<i64>) -> Command {
+    let mut script = String::from("Add-Type -AssemblyName System.Speech; ");
+    script.push_str("$s = New-Object System.Speech.Synthesis.SpeechSynthesizer; ");
+    if let Some(voice) = voice {
+        script.push_str(&format!("$s.SelectVoice('{}'); ", voice.replace('\'', "''")));
+    }
+    if let Some(rate) = rate {
+        // System.Speech rates run from -10 to 10, with 0 at roughly 180 words per minute.
+        script.push_str(&format!("$s.Rate = {}; ", ((rate - 180) / 20).clamp(-10, 10)));
+    }
+    script.push_str("$s.Speak([Console]::In.ReadToEnd())");
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn tts_command(voice: Option<&str>, rate: Option<i64>) -> Command {
+    let mut command = Command::new("espeak");
+    command.arg("--stdin");
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    if let Some(rate) = rate {
+        command.args(["-s", &rate.to_string()]);
+    }
+    command
+}
+
+/// Turns a markdown response into plain text worth reading aloud, dropping code blocks and
+/// formatting characters and keeping only the text of links.
+pub fn speakable_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || trimmed.is_empty() {
+            continue;
+        }
+        let trimmed = trimmed.trim_start_matches('#').trim_start_matches("> ");
+        let trimmed = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .unwrap_or(trimmed);
+        lines.push(strip_inline(trimmed.trim()));
+    }
+    lines.join("\n")
+}
+
+/// Removes `*` emphasis and code markers and replaces `[text](url)` links with their text.
+fn strip_inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if let Some((text, after)) = split_link(rest) {
+            out.push_str(text);
+            rest = after;
+            continue;
+        }
+        if !matches!(c, '*' | '`') {
+            out.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Splits a `[text](url)` link at the start of `s` into its text and what follows it.
+fn split_link(s: &str) -> Option<(&str, &str)> {
+    let (text, after) = s.strip_prefix('[')?.split_once("](")?;
+    let (_, after) = after.split_once(')')?;
+    Some((text, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speakable_text() {
+        let markdown = "## Summary\n\nRun **cargo test** in the `crates` dir, see [the docs](https://example.com).\n\n```rust\nfn main() {}\n```\n\n- First\n* Second\n> Quoted";
+        assert_eq!(
+            speakable_text(markdown),
+            "Summary\nRun cargo test in the crates dir, see the docs.\nFirst\nSecond\nQuoted"
+        );
+        assert_eq!(speakable_text("```\nonly code\n```"), "");
+    }
+}
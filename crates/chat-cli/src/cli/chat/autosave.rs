@@ -0,0 +1,279 @@
+//! Session persistence for interactive sessions.
+//!
+//! After every turn the conversation is written to the [ConversationStore] in the same format as
+//! `/save`, tagged with the directory it was started in and the id of the process running it.
+//! The process id is cleared when the session exits normally, so an entry still holding the id of
+//! a process that is no longer running means the session was interrupted, and the next `q chat` in
+//! the same directory offers to resume it. Finished sessions can be reopened with `/resume` or
+//! `q chat --resume`.
+//!
+//! Autosaving can be turned off with `q settings chat.enableAutosave false`.
+
+use std::path::Path;
+
+use eyre::{
+    Result,
+    bail,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::cli::chat::ChatSession;
+use crate::cli::chat::cli::persist::{
+    LoadedConversation,
+    SavedConversation,
+};
+use crate::cli::chat::store::{
+    ConversationStore,
+    EntryMetadata,
+    StoreEntry,
+};
+use crate::cli::chat::token_counter::{
+    CharCount,
+    CharCounter,
+    TokenCount,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Length of the title kept in the index, taken from the first prompt.
+const TITLE_CHARS: usize = 60;
+
+pub struct StoredSession {
+    pub id: String,
+    pub entry: StoreEntry,
+    pub conversation: LoadedConversation,
+}
+
+pub fn is_enabled(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ChatEnableAutosave)
+        .unwrap_or(true)
+}
+
+/// Writes the session's conversation to the conversation store.
+///
+/// `running` should be false once the session is exiting, so that the entry isn't mistaken for an
+/// interrupted session.
+///
+/// An empty history, e.g. after `/clear`, isn't written, so the last conversation that had any
+/// turns can still be resumed.
+pub async fn write(os: &Os, session: &ChatSession, running: bool) -> Result<()> {
+    let store = ConversationStore::new(os)?;
+    let id = session.conversation.conversation_id();
+    if session.conversation.history().is_empty() {
+        if !running {
+            store.release(os, id).await?;
+        }
+        return Ok(());
+    }
+
+    let history = session.conversation.history();
+    let title = history
+        .iter()
+        .find_map(|(user, _)| user.prompt())
+        .and_then(|prompt| prompt.lines().map(str::trim).find(|line| !line.is_empty()))
+        .map(|line| match line.char_indices().nth(TITLE_CHARS) {
+            Some((end, _)) => format!("{}…", &line[..end]),
+            None => line.to_string(),
+        });
+    let chars = history
+        .iter()
+        .map(|(user, assistant)| user.char_count() + assistant.char_count())
+        .fold(CharCount::from(0), |total, count| total + count);
+
+    store
+        .write(os, id, &SavedConversation::new(session), EntryMetadata {
+            cwd: Some(os.env.current_dir()?),
+            pid: running.then(std::process::id),
+            tags: session.conversation.tags.iter().cloned().collect(),
+            title,
+            model: session.conversation.model.clone(),
+            tokens: Some(TokenCount::from(chars).value()),
+        })
+        .await?;
+    Ok(())
+}
+
+/// Marks a stored session as no longer running without otherwise changing it.
+pub async fn release(os: &Os, conversation_id: &str) {
+    if let Err(err) = async { ConversationStore::new(os)?.release(os, conversation_id).await }.await {
+        warn!(?err, conversation_id, "failed to update the stored session");
+    }
+}
+
+/// Whether the entry's session is running in a process other than this one.
+fn open_elsewhere(os: &Os, entry: &StoreEntry) -> bool {
+    entry
+        .pid
+        .is_some_and(|pid| pid != std::process::id() && os.sysinfo.is_pid_running(pid))
+}
+
+/// Returns the newest non-empty session in the store matching `filter`.
+async fn find_newest(os: &Os, filter: impl Fn(&str, &StoreEntry) -> bool) -> Option<StoredSession> {
+    let store = ConversationStore::new(os).ok()?;
+    let mut candidates = store
+        .entries(os)
+        .await
+        .ok()?
+        .into_iter()
+        .filter(|(id, entry)| filter(id, entry))
+        .collect::<Vec<_>>();
+    // Newest first
+    candidates.sort_by(|(_, a), (_, b)| b.updated_at.cmp(&a.updated_at));
+
+    for (id, entry) in candidates {
+        match store.read::<LoadedConversation>(os, &id).await {
+            Ok(conversation) if !conversation.conversation.history().is_empty() => {
+                return Some(StoredSession {
+                    id,
+                    entry,
+                    conversation,
+                });
+            },
+            Ok(_) => (),
+            Err(err) => warn!(?err, id, "ignoring unreadable stored session"),
+        }
+    }
+    None
+}
+
+/// Finds the most recent interrupted session that was started in `cwd`.
+pub async fn find_interrupted(os: &Os, cwd: &Path) -> Option<StoredSession> {
+    find_newest(os, |_, entry| {
+        entry.cwd.as_deref() == Some(cwd) && entry.pid.is_some() && !entry.is_running(os)
+    })
+    .await
+}
+
+/// Finds the most recent session started in `cwd` that isn't running in another process, other
+/// than the session with id `exclude`.
+pub async fn find_latest(os: &Os, cwd: &Path, exclude: Option<&str>) -> Option<StoredSession> {
+    find_newest(os, |id, entry| {
+        entry.cwd.as_deref() == Some(cwd) && Some(id) != exclude && !open_elsewhere(os, entry)
+    })
+    .await
+}
+
+/// Finds a stored session by its id, or by a prefix matching exactly one id.
+pub async fn find_by_id(os: &Os, id: &str) -> Result<StoredSession> {
+    let store = ConversationStore::new(os)?;
+    let (id, entry) = store.find(os, id).await?;
+    if open_elsewhere(os, &entry) {
+        bail!("Session {id} is open in another q chat process");
+    }
+
+    let conversation = store.read(os, &id).await?;
+    Ok(StoredSession {
+        id,
+        entry,
+        conversation,
+    })
+}
+
+/// Describes how long ago `saved_at` was, e.g. `3 minutes ago`.
+pub fn format_age(saved_at: OffsetDateTime, now: OffsetDateTime) -> String {
+    let minutes = (now - saved_at).whole_minutes().max(0);
+    let (value, unit) = match minutes {
+        0 => return "less than a minute ago".to_string(),
+        1..60 => (minutes, "minute"),
+        60..1440 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+    format!("{value} {unit}{} ago", if value == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use time::Duration;
+
+    use super::*;
+    use crate::cli::chat::conversation::ConversationState;
+    use crate::cli::chat::message::AssistantMessage;
+    use crate::cli::chat::tool_manager::ToolManager;
+    use crate::cli::chat::tools::{
+        ToolPermissions,
+        ToolSpec,
+    };
+
+    async fn write_autosave(os: &mut Os, id: &str, cwd: &str, pid: u32) {
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        let mut conversation = ConversationState::new(os, id, tool_config, None, ToolManager::default(), None).await;
+        conversation.set_next_user_message("hello".to_string()).await;
+        conversation.push_assistant_message(os, AssistantMessage::new_response(None, "hi".to_string()));
+
+        let saved = SavedConversation {
+            conversation: &conversation,
+            tool_permissions: &ToolPermissions::new(0),
+        };
+        ConversationStore::new(os)
+            .unwrap()
+            .write(os, id, &saved, EntryMetadata {
+                cwd: Some(PathBuf::from(cwd)),
+                pid: Some(pid),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_interrupted() {
+        let mut os = Os::new().await.unwrap();
+        assert!(find_interrupted(&os, Path::new("/project")).await.is_none());
+
+        write_autosave(&mut os, "old", "/project", 1001).await;
+        write_autosave(&mut os, "new", "/project", 1002).await;
+        write_autosave(&mut os, "elsewhere", "/other", 1003).await;
+        write_autosave(&mut os, "running", "/project", 1004).await;
+        os.sysinfo.add_running_pids(&[1004]);
+
+        let interrupted = find_interrupted(&os, Path::new("/project")).await.unwrap();
+        assert_eq!(interrupted.id, "new");
+        assert_eq!(interrupted.conversation.conversation.conversation_id(), "new");
+        assert_eq!(interrupted.entry.pid, Some(1002));
+
+        release(&os, "new").await;
+        assert_eq!(find_interrupted(&os, Path::new("/project")).await.unwrap().id, "old");
+    }
+
+    #[tokio::test]
+    async fn test_find_latest() {
+        let mut os = Os::new().await.unwrap();
+        write_autosave(&mut os, "abc1", "/project", 1001).await;
+        write_autosave(&mut os, "abc2", "/project", 1002).await;
+        write_autosave(&mut os, "running", "/project", 1003).await;
+        write_autosave(&mut os, "def", "/other", 1004).await;
+        os.sysinfo.add_running_pids(&[1003]);
+        release(&os, "abc1").await;
+
+        let project = Path::new("/project");
+        assert_eq!(find_latest(&os, project, None).await.unwrap().id, "abc2");
+        assert_eq!(find_latest(&os, project, Some("abc2")).await.unwrap().id, "abc1");
+        assert!(find_latest(&os, Path::new("/missing"), None).await.is_none());
+
+        assert_eq!(find_by_id(&os, "abc1").await.unwrap().id, "abc1");
+        assert_eq!(find_by_id(&os, "de").await.unwrap().id, "def");
+        assert_eq!(
+            find_by_id(&os, "ab").await.err().unwrap().to_string(),
+            "More than one stored session starts with ab"
+        );
+        assert!(find_by_id(&os, "running").await.is_err());
+        assert!(find_by_id(&os, "missing").await.is_err());
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(format_age(now - Duration::seconds(20), now), "less than a minute ago");
+        assert_eq!(format_age(now - Duration::minutes(1), now), "1 minute ago");
+        assert_eq!(format_age(now - Duration::minutes(3), now), "3 minutes ago");
+        assert_eq!(format_age(now - Duration::hours(5), now), "5 hours ago");
+        assert_eq!(format_age(now - Duration::days(2), now), "2 days ago");
+    }
+}
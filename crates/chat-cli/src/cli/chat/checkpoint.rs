@@ -0,0 +1,379 @@
+//! Checkpoints taken before tools that change the user's machine run, so that `/checkpoint
+//! restore` can rewind the conversation and the files the tools were going to write.
+//!
+//! Checkpoints are kept in memory for the lifetime of the session. Files are only captured for
+//! `fs_write`, since there is no way to know in advance what a shell command will touch.
+//!
+//! Since every checkpoint holds the files as they were just before they were written, the state of
+//! a file at any checkpoint is the first snapshot of it taken at or after that checkpoint, or the
+//! file on disk if it hasn't been written since. `/checkpoint diff` relies on this.
+
+use std::collections::{
+    BTreeSet,
+    VecDeque,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::Result;
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::cli::chat::message::{
+    AssistantMessage,
+    UserMessage,
+};
+use crate::cli::chat::tools::{
+    QueuedTool,
+    Tool,
+    sanitize_path_tool_arg,
+};
+use crate::os::Os;
+
+/// Oldest checkpoints are dropped once there are more than this many.
+const MAX_CHECKPOINTS: usize = 20;
+/// Files larger than this aren't captured, to keep memory use bounded.
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// The contents of a file before a tool wrote to it.
+#[derive(Debug, Clone)]
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    /// `None` if the file didn't exist.
+    pub contents: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub id: usize,
+    pub created_at: OffsetDateTime,
+    pub history: VecDeque<(UserMessage, AssistantMessage)>,
+    /// The tool uses that were about to run.
+    pub tool_uses: Vec<QueuedTool>,
+    pub files: Vec<FileSnapshot>,
+    /// Files that changing tools would have written to but that were too large to capture.
+    pub skipped_files: Vec<PathBuf>,
+}
+
+impl Checkpoint {
+    /// One line per tool that was about to run, e.g. `fs_write src/main.rs`.
+    pub fn describe_tools(&self) -> Vec<String> {
+        self.tool_uses
+            .iter()
+            .filter(|tool_use| is_mutative(&tool_use.tool))
+            .map(|tool_use| match &tool_use.tool {
+                Tool::FsWrite(fs_write) => format!("{} {}", tool_use.name, fs_write.path()),
+                Tool::ExecuteCommand(command) => format!("{} {}", tool_use.name, command.command),
+                _ => tool_use.name.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Whether running `tool` can change files or other state on the user's machine.
+pub fn is_mutative(tool: &Tool) -> bool {
+    match tool {
+        Tool::FsWrite(_) => true,
+        Tool::ExecuteCommand(command) => command.requires_acceptance(),
+        _ => false,
+    }
+}
+
+/// Either side of a `/checkpoint diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiffPoint {
+    Checkpoint(usize),
+    /// The session as it is now.
+    Current,
+}
+
+impl std::str::FromStr for DiffPoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "current" | "now" => Ok(Self::Current),
+            _ => s
+                .parse()
+                .map(Self::Checkpoint)
+                .map_err(|_err| format!("expected a checkpoint id or 'current', got '{s}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for DiffPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Checkpoint(id) => write!(f, "checkpoint {id}"),
+            Self::Current => f.write_str("the current session"),
+        }
+    }
+}
+
+/// The number of leading exchanges that `a` and `b` have in common.
+pub fn shared_turns(
+    a: &VecDeque<(UserMessage, AssistantMessage)>,
+    b: &VecDeque<(UserMessage, AssistantMessage)>,
+) -> usize {
+    let key = |(user, assistant): &(UserMessage, AssistantMessage)| {
+        (
+            user.prompt().map(str::to_string),
+            assistant.content().to_string(),
+            assistant
+                .tool_uses()
+                .map(|tool_uses| tool_uses.iter().map(|tool_use| tool_use.id.clone()).collect::<Vec<_>>()),
+        )
+    };
+    a.iter().zip(b).take_while(|(a, b)| key(a) == key(b)).count()
+}
+
+#[derive(Debug, Default)]
+pub struct CheckpointManager {
+    checkpoints: VecDeque<Checkpoint>,
+    next_id: usize,
+}
+
+impl CheckpointManager {
+    pub fn checkpoints(&self) -> impl Iterator<Item = &Checkpoint> {
+        self.checkpoints.iter()
+    }
+
+    /// Takes a checkpoint if any of `tool_uses` is mutative, returning its id.
+    pub async fn create(
+        &mut self,
+        os: &Os,
+        history: &VecDeque<(UserMessage, AssistantMessage)>,
+        tool_uses: &[QueuedTool],
+    ) -> Option<usize> {
+        if !tool_uses.iter().any(|tool_use| is_mutative(&tool_use.tool)) {
+            return None;
+        }
+
+        let mut files = Vec::new();
+        let mut skipped_files = Vec::new();
+        for tool_use in tool_uses {
+            let Tool::FsWrite(fs_write) = &tool_use.tool else {
+                continue;
+            };
+            let path = sanitize_path_tool_arg(os, fs_write.path());
+            if files.iter().any(|file: &FileSnapshot| file.path == path) {
+                continue;
+            }
+            match snapshot_file(os, &path).await {
+                Ok(Some(contents)) => files.push(FileSnapshot { path, contents }),
+                Ok(None) => skipped_files.push(path),
+                Err(err) => {
+                    warn!(?err, ?path, "failed to capture file for checkpoint");
+                    skipped_files.push(path);
+                },
+            }
+        }
+
+        self.next_id += 1;
+        self.checkpoints.push_back(Checkpoint {
+            id: self.next_id,
+            created_at: OffsetDateTime::now_utc(),
+            history: history.clone(),
+            tool_uses: tool_uses.to_vec(),
+            files,
+            skipped_files,
+        });
+        if self.checkpoints.len() > MAX_CHECKPOINTS {
+            self.checkpoints.pop_front();
+        }
+        Some(self.next_id)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Checkpoint> {
+        self.checkpoints.iter().find(|checkpoint| checkpoint.id == id)
+    }
+
+    /// Files written by tools between two points, in either order.
+    pub fn changed_files(&self, a: DiffPoint, b: DiffPoint) -> BTreeSet<PathBuf> {
+        let (from, to) = (a.min(b), a.max(b));
+        self.checkpoints
+            .iter()
+            .filter(|checkpoint| {
+                let point = DiffPoint::Checkpoint(checkpoint.id);
+                from <= point && point < to
+            })
+            .flat_map(|checkpoint| checkpoint.files.iter().map(|file| file.path.clone()))
+            .collect()
+    }
+
+    /// The contents of `path` at `point`, or `None` if it didn't exist.
+    pub async fn file_at(&self, os: &Os, point: DiffPoint, path: &Path) -> Result<Option<Vec<u8>>> {
+        let snapshot = self
+            .checkpoints
+            .iter()
+            .filter(|checkpoint| DiffPoint::Checkpoint(checkpoint.id) >= point)
+            .find_map(|checkpoint| checkpoint.files.iter().find(|file| file.path == path));
+        match snapshot {
+            Some(file) => Ok(file.contents.clone()),
+            None if os.fs.exists(path) => Ok(Some(os.fs.read(path).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes checkpoint `id` and every checkpoint taken after it, returning checkpoint `id`.
+    ///
+    /// Later checkpoints describe a future that no longer happened once `id` is restored.
+    pub fn take(&mut self, id: usize) -> Option<Checkpoint> {
+        let index = self.checkpoints.iter().position(|checkpoint| checkpoint.id == id)?;
+        self.checkpoints.drain(index..).next()
+    }
+}
+
+/// Reads the current contents of `path`. Returns `Ok(None)` if it is too large to capture, and
+/// `Ok(Some(None))` if it doesn't exist.
+async fn snapshot_file(os: &Os, path: &PathBuf) -> Result<Option<Option<Vec<u8>>>> {
+    if !os.fs.exists(path) {
+        return Ok(Some(None));
+    }
+    if os.fs.symlink_metadata(path).await?.len() > MAX_FILE_BYTES {
+        return Ok(None);
+    }
+    Ok(Some(Some(os.fs.read(path).await?)))
+}
+
+/// Writes the captured files back, deleting the ones that didn't exist. Returns the paths that
+/// were restored.
+pub async fn restore_files(os: &Os, files: &[FileSnapshot]) -> Result<Vec<PathBuf>> {
+    let mut restored = Vec::new();
+    for file in files {
+        match &file.contents {
+            Some(contents) => {
+                if let Some(parent) = file.path.parent() {
+                    os.fs.create_dir_all(parent).await?;
+                }
+                os.fs.write(&file.path, contents).await?;
+            },
+            None if os.fs.exists(&file.path) => os.fs.remove_file(&file.path).await?,
+            None => continue,
+        }
+        restored.push(file.path.clone());
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::execute::ExecuteCommand;
+    use crate::cli::chat::tools::fs_write::FsWrite;
+
+    fn queued(name: &str, tool: Tool) -> QueuedTool {
+        QueuedTool {
+            id: format!("{name}-id"),
+            name: name.to_string(),
+            accepted: true,
+            tool,
+        }
+    }
+
+    fn fs_write(path: &str) -> QueuedTool {
+        queued(
+            "fs_write",
+            Tool::FsWrite(FsWrite::Create {
+                path: path.to_string(),
+                file_text: Some("new".to_string()),
+                new_str: None,
+                summary: None,
+            }),
+        )
+    }
+
+    #[test]
+    fn test_diff_point() {
+        assert_eq!("3".parse::<DiffPoint>(), Ok(DiffPoint::Checkpoint(3)));
+        assert_eq!("current".parse::<DiffPoint>(), Ok(DiffPoint::Current));
+        assert!("latest".parse::<DiffPoint>().is_err());
+        assert!(DiffPoint::Checkpoint(10) < DiffPoint::Current);
+    }
+
+    #[test]
+    fn test_shared_turns() {
+        let turn = |prompt: &str, response: &str| {
+            (
+                UserMessage::new_prompt(prompt.to_string()),
+                AssistantMessage::new_response(None, response.to_string()),
+            )
+        };
+        let a = VecDeque::from([turn("one", "1"), turn("two", "2")]);
+        let b = VecDeque::from([turn("one", "1"), turn("two", "deux"), turn("three", "3")]);
+        assert_eq!(shared_turns(&a, &b), 1);
+        assert_eq!(shared_turns(&a, &a), 2);
+        assert_eq!(shared_turns(&VecDeque::new(), &a), 0);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_and_restore() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/existing.txt", "old").await.unwrap();
+        let mut manager = CheckpointManager::default();
+
+        let read_only = queued(
+            "execute_bash",
+            Tool::ExecuteCommand(ExecuteCommand {
+                command: "ls".to_string(),
+                summary: None,
+            }),
+        );
+        assert_eq!(manager.create(&os, &VecDeque::new(), &[read_only]).await, None);
+
+        let id = manager
+            .create(&os, &VecDeque::new(), &[
+                fs_write("/existing.txt"),
+                fs_write("/new.txt"),
+            ])
+            .await
+            .unwrap();
+        let checkpoint = manager.checkpoints().find(|checkpoint| checkpoint.id == id).unwrap();
+        assert_eq!(checkpoint.describe_tools(), vec![
+            "fs_write /existing.txt".to_string(),
+            "fs_write /new.txt".to_string()
+        ]);
+
+        os.fs.write("/existing.txt", "changed").await.unwrap();
+        os.fs.write("/new.txt", "created").await.unwrap();
+        let later = manager
+            .create(&os, &VecDeque::new(), &[fs_write("/new.txt")])
+            .await
+            .unwrap();
+
+        // Paths are as sanitized by fs_write, so they're compared by file name.
+        let changed = manager
+            .changed_files(DiffPoint::Current, DiffPoint::Checkpoint(id))
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(changed.len(), 2);
+        let (existing, new) = (&changed[0], &changed[1]);
+        assert!(existing.ends_with("existing.txt") && new.ends_with("new.txt"));
+        assert_eq!(
+            manager.changed_files(DiffPoint::Checkpoint(later), DiffPoint::Current),
+            BTreeSet::from([new.clone()])
+        );
+        let file_at = async |point, path| manager.file_at(&os, point, path).await.unwrap();
+        assert_eq!(
+            file_at(DiffPoint::Checkpoint(id), existing).await,
+            Some(b"old".to_vec())
+        );
+        assert_eq!(file_at(DiffPoint::Checkpoint(id), new).await, None);
+        assert_eq!(
+            file_at(DiffPoint::Checkpoint(later), existing).await,
+            Some(b"changed".to_vec())
+        );
+        assert_eq!(
+            file_at(DiffPoint::Checkpoint(later), new).await,
+            Some(b"created".to_vec())
+        );
+
+        let checkpoint = manager.take(id).unwrap();
+        assert!(manager.take(later).is_none(), "later checkpoints are discarded");
+        restore_files(&os, &checkpoint.files).await.unwrap();
+        assert_eq!(os.fs.read_to_string("/existing.txt").await.unwrap(), "old");
+        assert!(!os.fs.exists("/new.txt"));
+    }
+}
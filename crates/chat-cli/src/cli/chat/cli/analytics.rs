@@ -0,0 +1,223 @@
+use std::io::Write;
+
+use clap::Subcommand;
+use crossterm::style::{
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+    style,
+};
+
+use crate::cli::chat::analytics::{
+    self,
+    AnalyticsCounts,
+};
+use crate::cli::chat::util::format::LocaleFormatter;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Local analytics count the slash commands you use, how often offered completions are
+picked and how you answer tool confirmations. They are off unless enabled with
+\"q settings chat.enableLocalAnalytics true\", are stored only on this machine and are never sent
+anywhere. Use /analytics export to write them to a file you can choose to share."
+)]
+pub enum AnalyticsSubcommand {
+    /// Show the usage counts recorded so far
+    Show,
+    /// Write the usage counts to a JSON file
+    Export {
+        /// Path to write the counts to
+        path: String,
+        /// Overwrite the file if it already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Delete the usage counts recorded so far
+    Reset,
+}
+
+impl AnalyticsSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let counts = match analytics::load(os).await {
+            Ok(counts) => counts,
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to read local analytics: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+        };
+
+        match self {
+            Self::Show => {
+                if !analytics::is_enabled(os) {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nLocal analytics are off. To start counting, run "),
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("q settings chat.enableLocalAnalytics true"),
+                        style::SetForegroundColor(Color::Reset),
+                        style::Print("\n"),
+                    )?;
+                }
+                match counts {
+                    Some(counts) => print_counts(os, session, &counts)?,
+                    None => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNothing has been recorded yet.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Export { path, force } => {
+                let Some(counts) = counts else {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNothing has been recorded yet.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                };
+                if os.fs.exists(&path) && !force {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!(
+                            "\nFile at {path} already exists. To overwrite, use -f or --force\n\n"
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+                let contents =
+                    serde_json::to_string_pretty(&counts).map_err(|err| ChatError::Custom(err.to_string().into()))?;
+                match os.fs.write(&path, contents).await {
+                    Ok(()) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("\n✔ Exported local analytics to {path}\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                    Err(err) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to export to {path}: {err}\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Reset => match analytics::reset(os).await {
+                Ok(()) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("\n✔ Deleted local analytics\n\n"),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+                Err(err) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to delete local analytics: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+fn print_counts(os: &Os, session: &mut ChatSession, counts: &AnalyticsCounts) -> Result<(), ChatError> {
+    if let Some(since) = counts.since {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "\nCounted since {}, stored only on this machine\n",
+                LocaleFormatter::new(os).date(since.date())
+            )),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+
+    queue!(
+        session.stderr,
+        style::SetAttribute(Attribute::Bold),
+        style::Print("\nCommands\n"),
+        style::SetAttribute(Attribute::Reset),
+    )?;
+    let mut commands = counts.commands.iter().collect::<Vec<_>>();
+    commands.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let width = commands.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+    if commands.is_empty() {
+        queue!(session.stderr, style::Print("  <none>\n"))?;
+    }
+    for (name, count) in commands {
+        queue!(session.stderr, style::Print(format!("  {name:width$}  {count}\n")))?;
+    }
+
+    queue!(
+        session.stderr,
+        style::SetAttribute(Attribute::Bold),
+        style::Print("\nCompletions\n"),
+        style::SetAttribute(Attribute::Reset),
+        style::Print(format!(
+            "  {} picked of {} offered",
+            counts.completions_accepted, counts.completions_shown
+        )),
+    )?;
+    if let Some(rate) = counts.acceptance_rate() {
+        queue!(session.stderr, style::Print(format!(" ({rate:.0}%)")))?;
+    }
+
+    queue!(
+        session.stderr,
+        style::SetAttribute(Attribute::Bold),
+        style::Print("\n\nTool confirmations\n"),
+        style::SetAttribute(Attribute::Reset),
+    )?;
+    let width = counts
+        .tool_confirmations
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or_default();
+    if counts.tool_confirmations.is_empty() {
+        queue!(session.stderr, style::Print("  <none>\n"))?;
+    }
+    for (tool, confirmations) in &counts.tool_confirmations {
+        queue!(
+            session.stderr,
+            style::Print(format!(
+                "  {tool:width$}  {} accepted, {} trusted, {} rejected\n",
+                confirmations.accepted, confirmations.trusted, confirmations.rejected
+            )),
+        )?;
+    }
+    queue!(session.stderr, style::Print("\n"))?;
+    session.stderr.flush()?;
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+use clap::Subcommand;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::cli::chat::cli::replay::render_markdown;
+use crate::cli::chat::message::{
+    AssistantMessage,
+    UserMessage,
+};
+use crate::cli::chat::parse::ParseState;
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+
+/// Longest prompt line shown in a listing before it's truncated.
+const PREVIEW_BYTES: usize = 80;
+
+/// A turn marked with `/bookmark add`, kept with the conversation when it's saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub label: String,
+    /// 1-based position of the turn in the history when it was bookmarked.
+    pub turn: usize,
+    /// Prompt of the turn, used to find it again once earlier turns are dropped from the history.
+    pub prompt: String,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Bookmarks mark a turn of the conversation so you can get back to it in a long session.
+'/bookmark goto <label>' prints the conversation again from the bookmarked turn on. Bookmarks are
+included when the conversation is saved with /save."
+)]
+pub enum BookmarkSubcommand {
+    /// Bookmark the latest turn
+    Add {
+        /// Label of the bookmark, "turn <number>" by default
+        #[arg(num_args = 0..)]
+        label: Vec<String>,
+    },
+    /// List the bookmarks
+    List,
+    /// Print the conversation again from a bookmarked turn
+    Goto {
+        /// Label of the bookmark
+        #[arg(required = true, num_args = 1..)]
+        label: Vec<String>,
+    },
+    /// Remove a bookmark
+    #[command(name = "rm")]
+    Remove {
+        /// Label of the bookmark
+        #[arg(required = true, num_args = 1..)]
+        label: Vec<String>,
+    },
+}
+
+impl BookmarkSubcommand {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let result = match self {
+            Self::Add { label } => add(session, label.join(" ")),
+            Self::List => {
+                list(session)?;
+                Ok(None)
+            },
+            Self::Goto { label } => goto(session, &label.join(" ")).map(|_| None),
+            Self::Remove { label } => {
+                let label = label.join(" ");
+                let bookmarks = &mut session.conversation.bookmarks;
+                match bookmarks.iter().position(|bookmark| bookmark.label == label) {
+                    Some(i) => {
+                        bookmarks.remove(i);
+                        Ok(Some(format!("Removed bookmark '{label}'")))
+                    },
+                    None => Err(no_bookmark(&label)),
+                }
+            },
+        };
+
+        match result {
+            Ok(Some(message)) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ {message}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+            Ok(None) => (),
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nError: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+fn no_bookmark(label: &str) -> eyre::Report {
+    eyre::eyre!("There is no bookmark '{label}', see /bookmark list for the bookmarks")
+}
+
+fn add(session: &mut ChatSession, label: String) -> eyre::Result<Option<String>> {
+    let history = session.conversation.history();
+    let Some(i) = history.iter().rposition(|(user, _)| user.prompt().is_some()) else {
+        eyre::bail!("There are no turns to bookmark yet");
+    };
+    let turn = i + 1;
+    let prompt = history[i].0.prompt().unwrap_or_default().to_string();
+    let label = match label.trim() {
+        "" => format!("turn {turn}"),
+        label => label.to_string(),
+    };
+
+    let bookmarks = &mut session.conversation.bookmarks;
+    if bookmarks.iter().any(|bookmark| bookmark.label == label) {
+        eyre::bail!("There is already a bookmark '{label}', remove it with /bookmark rm first");
+    }
+    bookmarks.push(Bookmark {
+        label: label.clone(),
+        turn,
+        prompt,
+    });
+    Ok(Some(format!("Bookmarked turn {turn} as '{label}'")))
+}
+
+fn list(session: &mut ChatSession) -> Result<(), ChatError> {
+    if session.conversation.bookmarks.is_empty() {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\nNo bookmarks. Bookmark the latest turn with /bookmark add [label].\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        return Ok(());
+    }
+
+    execute!(session.stderr, style::Print("\n"))?;
+    for bookmark in &session.conversation.bookmarks {
+        let line = bookmark
+            .prompt
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default();
+        let turn = match find_turn(session.conversation.history(), bookmark) {
+            Some(i) => format!("turn {}", i + 1),
+            None => "no longer in the history".to_string(),
+        };
+        execute!(
+            session.stderr,
+            style::Print(format!("  {}", bookmark.label).cyan()),
+            style::Print(format!(" ({turn})\n").dark_grey()),
+            style::Print(format!("    > {}\n", truncate_safe(line.trim(), PREVIEW_BYTES))),
+        )?;
+    }
+    execute!(
+        session.stderr,
+        style::Print("\nUse '/bookmark goto <label>' to print the conversation from a bookmark.\n\n".dark_grey())
+    )?;
+    Ok(())
+}
+
+/// The history index of the bookmarked turn: where it was bookmarked if the prompt there is still
+/// the same, or where its prompt is now after earlier turns were dropped.
+fn find_turn(history: &VecDeque<(UserMessage, AssistantMessage)>, bookmark: &Bookmark) -> Option<usize> {
+    let is_bookmarked = |(user, _): &(UserMessage, AssistantMessage)| user.prompt() == Some(bookmark.prompt.as_str());
+    match history.get(bookmark.turn.wrapping_sub(1)) {
+        Some(entry) if is_bookmarked(entry) => Some(bookmark.turn - 1),
+        _ => history.iter().take(bookmark.turn).rposition(is_bookmarked),
+    }
+}
+
+fn goto(session: &mut ChatSession, label: &str) -> eyre::Result<()> {
+    let Some(bookmark) = session
+        .conversation
+        .bookmarks
+        .iter()
+        .find(|bookmark| bookmark.label == label)
+    else {
+        return Err(no_bookmark(label));
+    };
+    let Some(start) = find_turn(session.conversation.history(), bookmark) else {
+        eyre::bail!("The turn bookmarked as '{label}' is no longer in the history, it was compacted or undone");
+    };
+
+    let entries: Vec<_> = session.conversation.history().iter().skip(start).cloned().collect();
+    execute!(
+        session.stderr,
+        style::Print(format!("\n── From bookmark '{label}', turn {} ──\n", start + 1).dark_grey())
+    )?;
+    for (user, assistant) in entries {
+        if let Some(prompt) = user.prompt() {
+            execute!(
+                session.stdout,
+                style::Print(format!("\n{}\n\n", format!("> {prompt}").magenta()))
+            )?;
+        }
+        if !assistant.content().trim().is_empty() {
+            let mut state = ParseState::new(Some(session.terminal_width()));
+            let mut offset = 0;
+            render_markdown(session, &format!("{}\n", assistant.content()), &mut offset, &mut state)?;
+            execute!(session.stdout, style::ResetColor, style::Print("\n"))?;
+        }
+        for tool_use in assistant.tool_uses().unwrap_or_default() {
+            execute!(
+                session.stdout,
+                style::Print(format!("🛠️  Using tool: {}\n", tool_use.name).magenta())
+            )?;
+        }
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_turn() {
+        let turn = |prompt: &str| {
+            (
+                UserMessage::new_prompt(prompt.to_string()),
+                AssistantMessage::new_response(None, "ok".to_string()),
+            )
+        };
+        let bookmark = Bookmark {
+            label: "setup".to_string(),
+            turn: 3,
+            prompt: "c".to_string(),
+        };
+
+        let mut history: VecDeque<_> = ["a", "b", "c", "d"].into_iter().map(turn).collect();
+        assert_eq!(find_turn(&history, &bookmark), Some(2));
+
+        // Earlier turns dropped from the history move the bookmarked one forward.
+        history.pop_front();
+        assert_eq!(find_turn(&history, &bookmark), Some(1));
+
+        history.truncate(1);
+        assert_eq!(find_turn(&history, &bookmark), None);
+    }
+}
@@ -0,0 +1,270 @@
+use clap::Subcommand;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use time::OffsetDateTime;
+
+use crate::cli::chat::checkpoint::{
+    DiffPoint,
+    restore_files,
+    shared_turns,
+};
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    autosave,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "A checkpoint is taken before any tool that can change files or run changing shell commands.
+Restoring one rewinds the conversation to that point and puts back the files fs_write was about
+to change. Changes made by shell commands are not captured and have to be undone by hand.
+
+Checkpoints are kept for the current session only."
+)]
+pub enum CheckpointSubcommand {
+    /// List the checkpoints taken in this session
+    List,
+    /// Rewind the conversation and files to a checkpoint, discarding later checkpoints
+    Restore {
+        /// Id of the checkpoint, as shown by /checkpoint list
+        id: usize,
+    },
+    /// Show the exchanges and file changes that differ between two checkpoints
+    Diff {
+        /// Id of a checkpoint, or 'current' for the session as it is now
+        a: DiffPoint,
+        /// Id of a checkpoint, or 'current' for the session as it is now
+        #[arg(default_value = "current")]
+        b: DiffPoint,
+    },
+}
+
+impl CheckpointSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::List => {
+                if session.checkpoints.checkpoints().next().is_none() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo checkpoints have been taken in this session.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                } else {
+                    let now = OffsetDateTime::now_utc();
+                    execute!(session.stderr, style::Print("\n"))?;
+                    for checkpoint in session.checkpoints.checkpoints() {
+                        execute!(
+                            session.stderr,
+                            style::Print(format!("{:>3}. ", checkpoint.id).cyan()),
+                            style::Print(format!(
+                                "{}, {} exchanges\n",
+                                autosave::format_age(checkpoint.created_at, now),
+                                checkpoint.history.len()
+                            )),
+                        )?;
+                        for tool in checkpoint.describe_tools() {
+                            execute!(session.stderr, style::Print(format!("     {tool}\n").dark_grey()))?;
+                        }
+                        for path in &checkpoint.skipped_files {
+                            execute!(
+                                session.stderr,
+                                style::Print(format!("     {} was not captured\n", path.display()).yellow())
+                            )?;
+                        }
+                    }
+                    execute!(
+                        session.stderr,
+                        style::Print(
+                            "\nChanges made by shell commands are not captured by checkpoints.\n\n".dark_grey()
+                        )
+                    )?;
+                }
+            },
+            Self::Restore { id } => {
+                let Some(checkpoint) = session.checkpoints.take(id) else {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!(
+                            "\nThere is no checkpoint {id}. Run /checkpoint list to see them.\n\n"
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                };
+
+                let restored = restore_files(os, &checkpoint.files)
+                    .await
+                    .map_err(|err| ChatError::Custom(format!("Failed to restore files: {err}").into()))?;
+                session.conversation.restore_history(
+                    checkpoint.history,
+                    &checkpoint.tool_uses,
+                    "The user restored a checkpoint taken before these tools ran, undoing their changes to files"
+                        .to_string(),
+                );
+                session.tool_uses.clear();
+                session.pending_tool_index = None;
+
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\n✔ Restored checkpoint {id}\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                for path in restored {
+                    execute!(
+                        session.stderr,
+                        style::Print(format!("  {}\n", path.display()).dark_grey())
+                    )?;
+                }
+                for path in checkpoint.skipped_files {
+                    execute!(
+                        session.stderr,
+                        style::Print(format!("  {} was not captured and is unchanged\n", path.display()).yellow())
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Diff { a, b } => {
+                for point in [a, b] {
+                    let DiffPoint::Checkpoint(id) = point else {
+                        continue;
+                    };
+                    if session.checkpoints.get(id).is_none() {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print(format!(
+                                "\nThere is no checkpoint {id}. Run /checkpoint list to see them.\n\n"
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    }
+                }
+
+                let history = |point| match point {
+                    DiffPoint::Checkpoint(id) => session.checkpoints.get(id).map(|checkpoint| &checkpoint.history),
+                    DiffPoint::Current => Some(session.conversation.history()),
+                };
+                let (a_history, b_history) = (
+                    history(a).cloned().unwrap_or_default(),
+                    history(b).cloned().unwrap_or_default(),
+                );
+                let shared = shared_turns(&a_history, &b_history);
+
+                queue!(
+                    session.stderr,
+                    style::Print(format!("\nComparing {a} with {b}\n\n").bold()),
+                    style::Print(match shared {
+                        0 => "No exchanges are shared.\n".to_string(),
+                        1 => "The first exchange is shared.\n".to_string(),
+                        n => format!("The first {n} exchanges are shared.\n"),
+                    }),
+                )?;
+                for (point, history) in [(a, &a_history), (b, &b_history)] {
+                    if history.len() == shared {
+                        continue;
+                    }
+                    queue!(session.stderr, style::Print(format!("\nOnly in {point}:\n").cyan()))?;
+                    for (index, (user, _)) in history.iter().enumerate().skip(shared) {
+                        let summary = match user.prompt() {
+                            Some(prompt) => truncate_safe(prompt.lines().next().unwrap_or_default(), 80).to_string(),
+                            None => "(tool results)".to_string(),
+                        };
+                        queue!(session.stderr, style::Print(format!("  {:>3}. {summary}\n", index + 1)))?;
+                    }
+                }
+
+                let mut any_changes = false;
+                for path in session.checkpoints.changed_files(a, b) {
+                    let read = async |point| {
+                        session.checkpoints.file_at(os, point, &path).await.map_err(|err| {
+                            ChatError::Custom(format!("Failed to read {}: {err}", path.display()).into())
+                        })
+                    };
+                    let (old, new) = (read(a).await?, read(b).await?);
+                    if old == new {
+                        continue;
+                    }
+                    any_changes = true;
+                    print_file_diff(&mut session.stderr, &path.display().to_string(), (a, old), (b, new))?;
+                }
+                if !any_changes {
+                    queue!(
+                        session.stderr,
+                        style::Print("\nNo files were changed by tools between them.\n".dark_grey())
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Prints a colored unified diff of a file between two points. `None` means the file didn't exist.
+fn print_file_diff(
+    output: &mut impl std::io::Write,
+    path: &str,
+    (a, old): (DiffPoint, Option<Vec<u8>>),
+    (b, new): (DiffPoint, Option<Vec<u8>>),
+) -> Result<(), ChatError> {
+    let label = |point: DiffPoint, contents: &Option<Vec<u8>>| match contents {
+        Some(_) => format!("{path} ({point})"),
+        None => format!("/dev/null ({point})"),
+    };
+    queue!(
+        output,
+        style::Print("\n"),
+        style::Print(format!("--- {}\n", label(a, &old)).bold()),
+        style::Print(format!("+++ {}\n", label(b, &new)).bold()),
+    )?;
+
+    let (Ok(old), Ok(new)) = (
+        String::from_utf8(old.unwrap_or_default()),
+        String::from_utf8(new.unwrap_or_default()),
+    ) else {
+        queue!(output, style::Print("Binary file differs\n".dark_grey()))?;
+        return Ok(());
+    };
+    let diff = similar::TextDiff::from_lines(&old, &new);
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        queue!(output, style::Print(format!("{}\n", hunk.header()).cyan()))?;
+        for change in hunk.iter_changes() {
+            let (sign, color) = match change.tag() {
+                similar::ChangeTag::Equal => (" ", Color::Reset),
+                similar::ChangeTag::Delete => ("-", Color::Red),
+                similar::ChangeTag::Insert => ("+", Color::Green),
+            };
+            queue!(
+                output,
+                style::SetForegroundColor(color),
+                style::Print(format!("{sign}{}", change.value().trim_end_matches('\n'))),
+                style::SetForegroundColor(Color::Reset),
+                style::Print("\n"),
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use clap::Args;
+use clap::{
+    Args,
+    Subcommand,
+};
 use crossterm::style::{
     self,
     Color,
@@ -7,6 +10,7 @@ use crossterm::style::{
 use crossterm::{
     cursor,
     execute,
+    terminal,
 };
 
 use crate::cli::chat::{
@@ -17,49 +21,112 @@ use crate::cli::chat::{
 
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
-pub struct ClearArgs;
+#[command(
+    before_long_help = "Without a subcommand, /clear erases the conversation history, the same as /clear history.
+
+Notes
+• 'history' and 'all' ask for confirmation, since what they erase can't be restored
+• Context rules and profiles are never cleared, use /context clear for those"
+)]
+pub struct ClearArgs {
+    #[command(subcommand)]
+    target: Option<ClearTarget>,
+}
+
+/// What `/clear` erases.
+#[deny(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum ClearTarget {
+    /// Clear the terminal, keeping the conversation
+    Screen,
+    /// Erase the conversation history and the cached output of hooks
+    History,
+    /// Reset every tool to its default permission level
+    Tools,
+    /// Erase the conversation history, reset tool permissions and clear the terminal
+    All,
+}
 
 impl ClearArgs {
     pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
-        execute!(
-            session.stderr,
-            style::SetForegroundColor(Color::DarkGrey),
-            style::Print(
-                "\nAre you sure? This will erase the conversation history and context from hooks for the current session. "
+        let target = self.target.unwrap_or(ClearTarget::History);
+        let question = match target {
+            ClearTarget::Screen | ClearTarget::Tools => None,
+            ClearTarget::History => {
+                Some("This will erase the conversation history and context from hooks for the current session.")
+            },
+            ClearTarget::All => Some(
+                "This will erase the conversation history and context from hooks, reset tool permissions and clear the screen.",
             ),
-            style::Print("["),
-            style::SetForegroundColor(Color::Green),
-            style::Print("y"),
-            style::SetForegroundColor(Color::DarkGrey),
-            style::Print("/"),
-            style::SetForegroundColor(Color::Green),
-            style::Print("n"),
-            style::SetForegroundColor(Color::DarkGrey),
-            style::Print("]:\n\n"),
-            style::SetForegroundColor(Color::Reset),
-            cursor::Show,
-        )?;
-
-        // Setting `exit_on_single_ctrl_c` for better ux: exit the confirmation dialog rather than the CLI
-        let user_input = match session.read_user_input("> ".yellow().to_string().as_str(), true) {
-            Some(input) => input,
-            None => "".to_string(),
         };
+        if let Some(question) = question {
+            if !confirm(session, question)? {
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            }
+        }
 
-        if ["y", "Y"].contains(&user_input.as_str()) {
+        if matches!(target, ClearTarget::Screen | ClearTarget::All) {
+            execute!(
+                session.stderr,
+                terminal::Clear(terminal::ClearType::All),
+                terminal::Clear(terminal::ClearType::Purge),
+                cursor::MoveTo(0, 0),
+            )?;
+        }
+        if matches!(target, ClearTarget::History | ClearTarget::All) {
             session.conversation.clear(true);
             if let Some(cm) = session.conversation.context_manager.as_mut() {
                 cm.hook_executor.global_cache.clear();
                 cm.hook_executor.profile_cache.clear();
             }
-            execute!(
-                session.stderr,
-                style::SetForegroundColor(Color::Green),
-                style::Print("\nConversation history cleared.\n\n"),
-                style::SetForegroundColor(Color::Reset)
-            )?;
         }
+        if matches!(target, ClearTarget::Tools | ClearTarget::All) {
+            session.tool_permissions.reset();
+        }
+
+        let cleared = match target {
+            ClearTarget::Screen => return Ok(ChatState::default()),
+            ClearTarget::History => "Conversation history cleared.",
+            ClearTarget::Tools => "Reset all tools to the default permission levels.",
+            ClearTarget::All => "Conversation history cleared and tool permissions reset.",
+        };
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\n{cleared}\n\n")),
+            style::SetForegroundColor(Color::Reset)
+        )?;
 
         Ok(ChatState::default())
     }
 }
+
+/// Asks `question`, returning whether the user answered yes.
+pub fn confirm(session: &mut ChatSession, question: &str) -> Result<bool, ChatError> {
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!("\nAre you sure? {question} ")),
+        style::Print("["),
+        style::SetForegroundColor(Color::Green),
+        style::Print("y"),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("/"),
+        style::SetForegroundColor(Color::Green),
+        style::Print("n"),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("]:\n\n"),
+        style::SetForegroundColor(Color::Reset),
+        cursor::Show,
+    )?;
+
+    // Setting `exit_on_single_ctrl_c` for better ux: exit the confirmation dialog rather than the CLI
+    let user_input = match session.read_user_input("> ".yellow().to_string().as_str(), true) {
+        Some(input) => input,
+        None => "".to_string(),
+    };
+
+    Ok(["y", "Y"].contains(&user_input.as_str()))
+}
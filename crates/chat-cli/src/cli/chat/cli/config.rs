@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use crossterm::style::{
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+    style,
+};
+
+use crate::cli::chat::cli::hooks::HookFilter;
+use crate::cli::chat::context::{
+    is_exclusion,
+    profile_context_path,
+};
+use crate::cli::chat::tool_manager::{
+    McpServerConfig,
+    global_mcp_config_path,
+    workspace_mcp_config_path,
+};
+use crate::cli::chat::tools::custom_tool::CustomToolConfig;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::util::directories;
+
+/// Where settings that aren't read from a file come from.
+const SESSION_SOURCE: &str = "this session";
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum ConfigSubcommand {
+    /// Show the merged configuration in use, with the file each value comes from
+    Effective,
+}
+
+/// A line of the effective configuration and where its value comes from.
+#[derive(Debug)]
+struct ConfigLine {
+    text: String,
+    source: String,
+}
+
+impl ConfigLine {
+    fn new(text: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl ConfigSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::Effective => {
+                let sections = [
+                    ("Context rules", context_lines(os, session).await),
+                    ("Hooks", hook_lines(os, session)),
+                    ("MCP servers", mcp_lines(os).await),
+                    ("Tool permissions", permission_lines(session)),
+                    ("Settings", setting_lines(os, session)),
+                ];
+                for (title, lines) in sections {
+                    print_section(session, title, &lines)?;
+                }
+                session.stderr.flush()?;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+fn display_path<E>(path: Result<PathBuf, E>) -> String {
+    path.map_or_else(|_err| "unknown path".to_string(), |path| path.display().to_string())
+}
+
+async fn context_lines(os: &Os, session: &ChatSession) -> Vec<ConfigLine> {
+    let Some(context_manager) = &session.conversation.context_manager else {
+        return Vec::new();
+    };
+
+    let global_path = directories::chat_global_context_path(os);
+    let global_source = match &global_path {
+        Ok(path) if os.fs.exists(path) => path.display().to_string(),
+        _ => "built-in default".to_string(),
+    };
+    let profile_source = display_path(profile_context_path(os, &context_manager.current_profile));
+    let dropped = context_manager
+        .collect_context_files_with_limit(os)
+        .await
+        .map(|(_, dropped)| dropped)
+        .unwrap_or_default();
+
+    let mut lines = Vec::new();
+    let rules = [
+        (&context_manager.global_config.paths, global_source),
+        (&context_manager.profile_config.paths, profile_source),
+        (&context_manager.session_paths, SESSION_SOURCE.to_string()),
+    ];
+    for (paths, source) in rules {
+        for path in paths {
+            lines.push(ConfigLine::new(path, &source));
+            if is_exclusion(path) {
+                let count = context_manager.count_excluded_files(os, path).await.unwrap_or_default();
+                lines.push(ConfigLine::new(format!("    (excludes {count} files)"), &source));
+                continue;
+            }
+            // Rules that match nothing are an error when added, but can go stale afterwards.
+            let files = context_manager
+                .get_context_files_by_path(os, path)
+                .await
+                .unwrap_or_default();
+            if files.is_empty() {
+                lines.push(ConfigLine::new("    (no matches)", &source));
+            }
+            for (file, _) in files {
+                let note = if dropped.iter().any(|(dropped, _)| *dropped == file) {
+                    " (dropped, over the context size limit)"
+                } else {
+                    ""
+                };
+                lines.push(ConfigLine::new(format!("    {file}{note}"), &source));
+            }
+        }
+    }
+    lines
+}
+
+fn hook_lines(os: &Os, session: &ChatSession) -> Vec<ConfigLine> {
+    let Some(context_manager) = &session.conversation.context_manager else {
+        return Vec::new();
+    };
+
+    let global_source = display_path(directories::chat_global_context_path(os));
+    let profile_source = display_path(profile_context_path(os, &context_manager.current_profile));
+    context_manager
+        .list_hooks(&HookFilter::default())
+        .into_iter()
+        .map(|(name, is_global, hook)| {
+            let disabled = if hook.disabled { ", disabled" } else { "" };
+            let command = hook.command_line();
+            let source = if is_global { &global_source } else { &profile_source };
+            ConfigLine::new(format!("{name} ({}{disabled}): {command}", hook.trigger), source)
+        })
+        .collect()
+}
+
+/// Merges the global and workspace MCP configs the way the tool manager does: a workspace server
+/// replaces a global one with the same name.
+fn merge_mcp_servers<'a>(
+    global: &'a McpServerConfig,
+    workspace: &'a McpServerConfig,
+) -> BTreeMap<&'a str, (&'a CustomToolConfig, bool, bool)> {
+    let mut servers = BTreeMap::new();
+    for (name, config) in &global.mcp_servers {
+        servers.insert(name.as_str(), (config, false, false));
+    }
+    for (name, config) in &workspace.mcp_servers {
+        let overrides = servers.contains_key(name.as_str());
+        servers.insert(name.as_str(), (config, true, overrides));
+    }
+    servers
+}
+
+async fn mcp_lines(os: &Os) -> Vec<ConfigLine> {
+    let global_path = global_mcp_config_path(os);
+    let workspace_path = workspace_mcp_config_path(os);
+    let load = async |path: &eyre::Result<PathBuf>| match path {
+        Ok(path) if os.fs.exists(path) => McpServerConfig::load_from_file(os, path).await.unwrap_or_default(),
+        _ => McpServerConfig::default(),
+    };
+    let global = load(&global_path).await;
+    let workspace = load(&workspace_path).await;
+
+    let global_source = display_path(global_path);
+    let workspace_source = display_path(workspace_path);
+    merge_mcp_servers(&global, &workspace)
+        .into_iter()
+        .map(|(name, (config, in_workspace, overrides))| {
+            let mut text = format!("{name}: {}", config.command);
+            for arg in &config.args {
+                text.push(' ');
+                text.push_str(arg);
+            }
+            if config.disabled {
+                text.push_str(" (disabled)");
+            }
+            if overrides {
+                text.push_str(" (overrides global)");
+            }
+            let source = if in_workspace {
+                &workspace_source
+            } else {
+                &global_source
+            };
+            ConfigLine::new(text, source)
+        })
+        .collect()
+}
+
+fn permission_lines(session: &ChatSession) -> Vec<ConfigLine> {
+    let permissions = &session.tool_permissions;
+    let mut lines = Vec::new();
+    if permissions.trust_all {
+        lines.push(ConfigLine::new("all tools trusted", SESSION_SOURCE));
+    }
+    let mut tools = permissions.permissions.iter().collect::<Vec<_>>();
+    tools.sort_by_key(|(name, _)| name.as_str());
+    for (name, permission) in tools {
+        let trust = if permission.trusted { "trusted" } else { "per-request" };
+        lines.push(ConfigLine::new(format!("{name}: {trust}"), SESSION_SOURCE));
+    }
+    lines
+}
+
+fn setting_lines(os: &Os, session: &ChatSession) -> Vec<ConfigLine> {
+    let source = display_path(directories::settings_path());
+    let mut settings = os.database.settings.map().iter().collect::<Vec<_>>();
+    settings.sort_by_key(|(key, _)| key.as_str());
+    let mut lines: Vec<_> = settings
+        .into_iter()
+        .map(|(key, value)| ConfigLine::new(format!("{key} = {value}"), &source))
+        .collect();
+
+    // Settings of the profile, set with /settings.
+    if let Some(context_manager) = &session.conversation.context_manager {
+        let source = display_path(profile_context_path(os, &context_manager.current_profile));
+        if let Some(language) = context_manager.response_language() {
+            lines.push(ConfigLine::new(format!("response.language = {language}"), &source));
+        }
+        let lists = [
+            ("response.stop_sequences", context_manager.stop_sequences()),
+            ("response.filters", context_manager.output_filters()),
+        ];
+        for (key, values) in lists.into_iter().filter(|(_, values)| !values.is_empty()) {
+            lines.push(ConfigLine::new(format!("{key} = {}", values.join(", ")), &source));
+        }
+    }
+    lines
+}
+
+fn print_section(session: &mut ChatSession, title: &str, lines: &[ConfigLine]) -> Result<(), ChatError> {
+    queue!(
+        session.stderr,
+        style::SetAttribute(Attribute::Bold),
+        style::Print(format!("\n{title}\n")),
+        style::SetAttribute(Attribute::Reset),
+    )?;
+    if lines.is_empty() {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("  <none>\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(());
+    }
+
+    let width = lines
+        .iter()
+        .map(|line| line.text.chars().count())
+        .max()
+        .unwrap_or_default();
+    for line in lines {
+        queue!(
+            session.stderr,
+            style::Print(format!("  {:width$}  ", line.text)),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("# {}\n", line.source)),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+    execute!(session.stderr, style::Print(""))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_mcp_servers() {
+        let server = |command: &str| CustomToolConfig {
+            command: command.to_string(),
+            args: Vec::new(),
+            env: None,
+            timeout: 0,
+            disabled: false,
+        };
+        let global = McpServerConfig {
+            mcp_servers: HashMap::from([
+                ("git".to_string(), server("git-mcp")),
+                ("fetch".to_string(), server("fetch-mcp")),
+            ]),
+        };
+        let workspace = McpServerConfig {
+            mcp_servers: HashMap::from([("git".to_string(), server("./git-mcp"))]),
+        };
+
+        let merged = merge_mcp_servers(&global, &workspace);
+        assert_eq!(merged.keys().copied().collect::<Vec<_>>(), vec!["fetch", "git"]);
+        let (config, in_workspace, overrides) = merged["git"];
+        assert_eq!(config.command, "./git-mcp");
+        assert!(in_workspace && overrides);
+        let (config, in_workspace, overrides) = merged["fetch"];
+        assert_eq!(config.command, "fetch-mcp");
+        assert!(!in_workspace && !overrides);
+    }
+}
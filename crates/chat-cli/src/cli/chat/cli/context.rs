@@ -1,6 +1,11 @@
 use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
 
-use clap::Subcommand;
+use clap::{
+    ArgGroup,
+    Subcommand,
+};
 use crossterm::style::{
     Attribute,
     Color,
@@ -9,19 +14,43 @@ use crossterm::{
     execute,
     style,
 };
+use time::OffsetDateTime;
 
+use crate::cli::chat::autosave::format_age;
 use crate::cli::chat::cli::hooks::{
+    HookPack,
     HookTrigger,
     map_chat_error,
     print_hook_section,
 };
-use crate::cli::chat::consts::CONTEXT_FILES_MAX_SIZE;
+use crate::cli::chat::consts::{
+    CONTEXT_FILES_MAX_SIZE,
+    CONTEXT_WINDOW_SIZE,
+};
+use crate::cli::chat::context::{
+    ContextChange,
+    ContextFileDigest,
+    ContextManager,
+    RuleCheck,
+    RuleOptions,
+    RulePosition,
+    RuleProblem,
+    context_changes,
+    is_exclusion,
+    validate_rules,
+};
+use crate::cli::chat::context_templates::ProjectType;
+use crate::cli::chat::dynamic_context::DynamicSource;
+use crate::cli::chat::store::format_size;
 use crate::cli::chat::token_counter::TokenCounter;
-use crate::cli::chat::util::drop_matched_context_files;
+use crate::cli::chat::util::documents::DocumentKind;
+use crate::cli::chat::util::format::LocaleFormatter;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
+    s3_context,
+    url_context,
 };
 use crate::os::Os;
 
@@ -35,8 +64,18 @@ more accurate and helpful responses.
 
 Notes:
 • You can add specific files or use glob patterns (e.g., \"*.py\", \"src/**/*.js\")
+• --types adds a glob for each extension under a directory (e.g., src/ --types rs,toml)
+• Globs skip files ignored by git and node_modules unless added with --gitignore false
 • Profile rules apply only to the current profile
 • Global rules apply across all profiles
+• URLs (e.g., \"https://example.com/design.md\") are fetched and cached, see /context refresh and --refresh
+• S3 objects (e.g., \"s3://team-runbooks/oncall/**.md\") are read with your AWS credentials and cached
+• Dynamic sources (e.g., --dynamic git-diff, or --dynamic tree --depth 3) are generated for each prompt
+• Rules starting with ! (e.g., \"!src/**/*.test.ts\") exclude files matched by other rules
+• When the context is over its limit, earlier rules are kept first, see /context prioritize
+• Groups (e.g., /context group create backend \"src/api/**\") are sets of rules turned on and off together
+• /context init adds rules and hooks suited to a Rust, Node.js, Python or Terraform project
+• /context validate finds rules that match nothing, can't be read or don't fit in the context
 • Context is preserved between chat sessions"
 )]
 pub enum ContextSubcommand {
@@ -55,6 +94,31 @@ pub enum ContextSubcommand {
         /// Include even if matched files exceed size limits
         #[arg(short, long)]
         force: bool,
+        /// Add sources generated for each prompt, e.g. git-diff, instead of files
+        #[arg(short, long)]
+        dynamic: bool,
+        /// Levels of the current directory listed by the tree source
+        #[arg(long, requires = "dynamic")]
+        depth: Option<usize>,
+        /// Tokens the rule's files may use, dropping the largest files beyond it
+        #[arg(long, value_name = "TOKENS")]
+        max_tokens: Option<usize>,
+        /// Rules with a lower priority are dropped first when the context exceeds its limit
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i32,
+        /// Match files with these extensions under the given directories, e.g. rs,toml
+        #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',', conflicts_with = "dynamic")]
+        types: Vec<String>,
+        /// Whether globs skip files ignored by git and node_modules, instead of
+        /// chat.contextRespectGitignore
+        #[arg(long, value_name = "BOOL")]
+        gitignore: Option<bool>,
+        /// Whether globs follow symlinks, instead of chat.contextFollowSymlinks
+        #[arg(long, value_name = "BOOL")]
+        follow_symlinks: Option<bool>,
+        /// Fetch URL and S3 rules even if they are cached
+        #[arg(long)]
+        refresh: bool,
         #[arg(required = true)]
         paths: Vec<String>,
     },
@@ -64,6 +128,9 @@ pub enum ContextSubcommand {
         /// Remove specified rules globally
         #[arg(short, long)]
         global: bool,
+        /// Remove dynamic sources, e.g. git-diff
+        #[arg(short, long)]
+        dynamic: bool,
         #[arg(required = true)]
         paths: Vec<String>,
     },
@@ -73,10 +140,123 @@ pub enum ContextSubcommand {
         #[arg(short, long)]
         global: bool,
     },
+    /// Move a rule earlier or later, earlier rules are kept first when the context is trimmed
+    #[command(group(ArgGroup::new("position").required(true).args(["first", "last", "before", "after"])))]
+    Prioritize {
+        /// Reorder global rules
+        #[arg(short, long)]
+        global: bool,
+        /// The rule to move
+        rule: String,
+        /// Move the rule to the top
+        #[arg(long)]
+        first: bool,
+        /// Move the rule to the bottom
+        #[arg(long)]
+        last: bool,
+        /// Move the rule just before another rule
+        #[arg(long, value_name = "RULE")]
+        before: Option<String>,
+        /// Move the rule just after another rule
+        #[arg(long, value_name = "RULE")]
+        after: Option<String>,
+    },
+    /// Manage named groups of rules that are turned on and off together
+    #[command(subcommand)]
+    Group(ContextGroupSubcommand),
+    /// Cache matched files, reading them again only when they change on disk
+    #[command(subcommand)]
+    Watch(ContextWatchSubcommand),
+    /// Fetch URL and S3 rules again instead of using their cached copies
+    Refresh,
+    /// Show how the matched files differ from the context files sent with the last prompt
+    Diff,
+    /// Add the context rules and hooks of a template for a kind of project
+    Init {
+        /// Kind of project, detected from the manifest files in the current directory if not given
+        #[arg(value_enum)]
+        project: Option<ProjectType>,
+    },
+    /// Check the rules for matching nothing, paths outside the current directory, unreadable
+    /// files and more context than fits
+    Validate,
+    /// Rank the matched files by estimated tokens and share of the context window
+    Usage {
+        /// How many of the largest files to show
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
     #[command(hide = true)]
     Hooks,
 }
 
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum ContextGroupSubcommand {
+    /// Create a group of rules in the current profile, disabled until enabled
+    Create {
+        /// Name of the group
+        name: String,
+        /// Include even if the rules don't match any files
+        #[arg(short, long)]
+        force: bool,
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Delete a group and its rules
+    #[command(alias = "rm")]
+    Delete {
+        /// Name of the group
+        name: String,
+    },
+    /// Use the rules of a group
+    Enable {
+        /// Name of the group
+        name: String,
+    },
+    /// Stop using the rules of a group
+    Disable {
+        /// Name of the group
+        name: String,
+    },
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum ContextWatchSubcommand {
+    /// Start watching matched files for changes
+    On,
+    /// Stop watching and read matched files for each prompt
+    Off,
+}
+
+impl ContextGroupSubcommand {
+    /// Updates the groups of the current profile, returning the message to show on success.
+    async fn apply(self, os: &Os, context_manager: &mut ContextManager) -> eyre::Result<String> {
+        match self {
+            Self::Create { name, force, paths } => {
+                let count = paths.len();
+                context_manager.create_group(os, &name, paths, force).await?;
+                Ok(format!(
+                    "Created group '{name}' with {count} rule(s). Use /context group enable {name} to use it."
+                ))
+            },
+            Self::Delete { name } => {
+                context_manager.delete_group(os, &name).await?;
+                Ok(format!("Deleted group '{name}'."))
+            },
+            Self::Enable { name } => {
+                context_manager.set_group_enabled(os, &name, true).await?;
+                Ok(format!("Enabled group '{name}'."))
+            },
+            Self::Disable { name } => {
+                context_manager.set_group_enabled(os, &name, false).await?;
+                Ok(format!("Disabled group '{name}'."))
+            },
+        }
+    }
+}
+
 impl ContextSubcommand {
     pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         let Some(context_manager) = &mut session.conversation.context_manager else {
@@ -94,6 +274,26 @@ impl ContextSubcommand {
 
         match self {
             Self::Show { expand } => {
+                if context_manager.is_watching() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("\nWatching matched files for changes, see /context watch\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                if !context_manager.pinned.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!(
+                            "\n📌 {} pinned message{}, see /pin\n",
+                            context_manager.pinned.len(),
+                            if context_manager.pinned.len() == 1 { "" } else { "s" }
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
                 // Display global context
                 execute!(
                     session.stderr,
@@ -112,9 +312,19 @@ impl ContextSubcommand {
                         style::SetForegroundColor(Color::Reset)
                     )?;
                 } else {
-                    for path in &context_manager.global_config.paths {
-                        execute!(session.stderr, style::Print(format!("    {} ", path)))?;
-                        if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
+                    for (i, path) in context_manager.global_config.paths.iter().enumerate() {
+                        print_rule(&mut session.stderr, i + 1, path)?;
+                        if let Some(label) = rule_options_label(context_manager.rule_options(path)) {
+                            execute!(
+                                session.stderr,
+                                style::SetForegroundColor(Color::DarkGrey),
+                                style::Print(format!("[{label}] ")),
+                                style::SetForegroundColor(Color::Reset)
+                            )?;
+                        }
+                        if is_exclusion(path) {
+                            print_exclusion_status(os, &mut session.stderr, context_manager, path).await?;
+                        } else if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
                             execute!(
                                 session.stderr,
                                 style::SetForegroundColor(Color::Green),
@@ -127,6 +337,7 @@ impl ContextSubcommand {
                             )?;
                             global_context_files.extend(context_files);
                         }
+                        print_url_status(os, &mut session.stderr, path).await?;
                         execute!(session.stderr, style::Print("\n"))?;
                     }
                 }
@@ -151,6 +362,16 @@ impl ContextSubcommand {
                         HookTrigger::PerPrompt,
                     )
                     .map_err(map_chat_error)?;
+                    // Hooks of events are only listed when there are any.
+                    let hooks = &context_manager.global_config.hooks;
+                    for trigger in HookTrigger::ALL
+                        .into_iter()
+                        .filter(|trigger| !trigger.runs_with_prompt())
+                    {
+                        if hooks.values().any(|hook| hook.trigger == trigger) {
+                            print_hook_section(&mut session.stderr, hooks, trigger).map_err(map_chat_error)?;
+                        }
+                    }
                 }
 
                 // Display profile context
@@ -170,9 +391,20 @@ impl ContextSubcommand {
                         style::SetForegroundColor(Color::Reset)
                     )?;
                 } else {
-                    for path in &context_manager.profile_config.paths {
-                        execute!(session.stderr, style::Print(format!("    {} ", path)))?;
-                        if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
+                    let offset = context_manager.global_config.paths.len();
+                    for (i, path) in context_manager.profile_config.paths.iter().enumerate() {
+                        print_rule(&mut session.stderr, offset + i + 1, path)?;
+                        if let Some(label) = rule_options_label(context_manager.rule_options(path)) {
+                            execute!(
+                                session.stderr,
+                                style::SetForegroundColor(Color::DarkGrey),
+                                style::Print(format!("[{label}] ")),
+                                style::SetForegroundColor(Color::Reset)
+                            )?;
+                        }
+                        if is_exclusion(path) {
+                            print_exclusion_status(os, &mut session.stderr, context_manager, path).await?;
+                        } else if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
                             execute!(
                                 session.stderr,
                                 style::SetForegroundColor(Color::Green),
@@ -185,11 +417,57 @@ impl ContextSubcommand {
                             )?;
                             profile_context_files.extend(context_files);
                         }
+                        print_url_status(os, &mut session.stderr, path).await?;
                         execute!(session.stderr, style::Print("\n"))?;
                     }
                     execute!(session.stderr, style::Print("\n"))?;
                 }
 
+                if !context_manager.profile_config.groups.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::SetForegroundColor(Color::DarkYellow),
+                        style::Print("    📦 Groups:\n"),
+                        style::SetAttribute(Attribute::Reset),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    for (name, group) in &context_manager.profile_config.groups {
+                        let (color, state) = if group.enabled {
+                            (Color::Green, "enabled")
+                        } else {
+                            (Color::DarkGrey, "disabled")
+                        };
+                        execute!(
+                            session.stderr,
+                            style::Print(format!("    {name} ")),
+                            style::SetForegroundColor(color),
+                            style::Print(format!("({state})\n")),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        for path in &group.paths {
+                            execute!(session.stderr, style::Print(format!("        {path} ")))?;
+                            if group.enabled && !is_exclusion(path) {
+                                if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
+                                    execute!(
+                                        session.stderr,
+                                        style::SetForegroundColor(Color::Green),
+                                        style::Print(format!(
+                                            "({} match{})",
+                                            context_files.len(),
+                                            if context_files.len() == 1 { "" } else { "es" }
+                                        )),
+                                        style::SetForegroundColor(Color::Reset)
+                                    )?;
+                                    profile_context_files.extend(context_files);
+                                }
+                            }
+                            execute!(session.stderr, style::Print("\n"))?;
+                        }
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                }
+
                 if expand {
                     execute!(
                         session.stderr,
@@ -209,6 +487,16 @@ impl ContextSubcommand {
                         HookTrigger::PerPrompt,
                     )
                     .map_err(map_chat_error)?;
+                    // Hooks of events are only listed when there are any.
+                    let hooks = &context_manager.profile_config.hooks;
+                    for trigger in HookTrigger::ALL
+                        .into_iter()
+                        .filter(|trigger| !trigger.runs_with_prompt())
+                    {
+                        if hooks.values().any(|hook| hook.trigger == trigger) {
+                            print_hook_section(&mut session.stderr, hooks, trigger).map_err(map_chat_error)?;
+                        }
+                    }
                     execute!(session.stderr, style::Print("\n"))?;
                 }
 
@@ -243,12 +531,12 @@ impl ContextSubcommand {
                     )?;
 
                     for (filename, content) in &global_context_files {
-                        let est_tokens = TokenCounter::count_tokens(content);
+                        let tokens = tokens_label(os, filename, content, expand).await;
                         execute!(
                             session.stderr,
                             style::Print(format!("🌍 {} ", filename)),
                             style::SetForegroundColor(Color::DarkGrey),
-                            style::Print(format!("(~{} tkns)\n", est_tokens)),
+                            style::Print(format!("({})\n", tokens)),
                             style::SetForegroundColor(Color::Reset),
                         )?;
                         if expand {
@@ -262,12 +550,12 @@ impl ContextSubcommand {
                     }
 
                     for (filename, content) in &profile_context_files {
-                        let est_tokens = TokenCounter::count_tokens(content);
+                        let tokens = tokens_label(os, filename, content, expand).await;
                         execute!(
                             session.stderr,
                             style::Print(format!("👤 {} ", filename)),
                             style::SetForegroundColor(Color::DarkGrey),
-                            style::Print(format!("(~{} tkns)\n", est_tokens)),
+                            style::Print(format!("({})\n", tokens)),
                             style::SetForegroundColor(Color::Reset),
                         )?;
                         if expand {
@@ -284,13 +572,11 @@ impl ContextSubcommand {
                         execute!(session.stderr, style::Print(format!("{}\n\n", "▔".repeat(3))),)?;
                     }
 
-                    let mut combined_files: Vec<(String, String)> = global_context_files
-                        .iter()
-                        .chain(profile_context_files.iter())
-                        .cloned()
-                        .collect();
-
-                    let dropped_files = drop_matched_context_files(&mut combined_files, CONTEXT_FILES_MAX_SIZE).ok();
+                    let dropped_files = context_manager
+                        .collect_context_files_with_limit(os)
+                        .await
+                        .ok()
+                        .map(|(_, dropped)| dropped);
 
                     execute!(
                         session.stderr,
@@ -303,14 +589,14 @@ impl ContextSubcommand {
                                 session.stderr,
                                 style::SetForegroundColor(Color::DarkYellow),
                                 style::Print(format!(
-                                    "Total token count exceeds limit: {}. The following files will be automatically dropped when interacting with Q. Consider removing them. \n\n",
+                                    "Total token count exceeds limit: {}, or a rule's --max-tokens. The following files will be automatically dropped when interacting with Q, lowest priority first. Consider removing them. \n\n",
                                     CONTEXT_FILES_MAX_SIZE
                                 )),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
                             let total_files = dropped_files.len();
 
-                            let truncated_dropped_files = &dropped_files[..10];
+                            let truncated_dropped_files = &dropped_files[..total_files.min(10)];
 
                             for (filename, content) in truncated_dropped_files {
                                 let est_tokens = TokenCounter::count_tokens(content);
@@ -357,9 +643,46 @@ impl ContextSubcommand {
                     }
                 }
             },
-            Self::Add { global, force, paths } => {
-                match context_manager.add_paths(os, paths.clone(), global, force).await {
-                    Ok(_) => {
+            Self::Add {
+                global,
+                force,
+                dynamic,
+                depth,
+                max_tokens,
+                priority,
+                types,
+                gitignore,
+                follow_symlinks,
+                refresh,
+                paths,
+            } => {
+                let paths = if dynamic {
+                    dynamic_rules(paths, depth)
+                } else if !types.is_empty() {
+                    match typed_rules(&paths, &types) {
+                        Ok(rules) if force => Ok(rules),
+                        Ok(rules) => Ok(matching_rules(os, context_manager, rules).await),
+                        Err(err) => Err(err),
+                    }
+                } else {
+                    Ok(paths)
+                };
+                let options = RuleOptions {
+                    max_tokens,
+                    priority,
+                    gitignore,
+                    follow_symlinks,
+                };
+                let paths = match paths {
+                    Ok(paths) if refresh => refresh_remote_rules(os, &paths).await.map(|()| paths),
+                    paths => paths,
+                };
+                let result = match paths {
+                    Ok(paths) => add_rules(os, context_manager, paths, global, force, options).await,
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok(paths) => {
                         let target = if global { "global" } else { "profile" };
                         execute!(
                             session.stderr,
@@ -378,28 +701,105 @@ impl ContextSubcommand {
                     },
                 }
             },
-            Self::Remove { global, paths } => match context_manager.remove_paths(os, paths.clone(), global).await {
-                Ok(_) => {
-                    let target = if global { "global" } else { "profile" };
-                    execute!(
+            Self::Remove { global, dynamic, paths } => {
+                let paths = if dynamic { dynamic_rules(paths, None) } else { Ok(paths) };
+                let result = match paths {
+                    Ok(paths) => context_manager
+                        .remove_paths(os, paths.clone(), global)
+                        .await
+                        .map(|()| paths),
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Ok(paths) => {
+                        let target = if global { "global" } else { "profile" };
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(format!(
+                                "\nRemoved {} path(s) from {} context.\n\n",
+                                paths.len(),
+                                target
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
+                    Err(e) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\nError: {}\n\n", e)),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
+                }
+            },
+            Self::Prioritize {
+                global,
+                rule,
+                first,
+                last: _,
+                before,
+                after,
+            } => {
+                let position = match (before, after) {
+                    (Some(other), _) => RulePosition::Before(other),
+                    (_, Some(other)) => RulePosition::After(other),
+                    _ if first => RulePosition::First,
+                    _ => RulePosition::Last,
+                };
+                match context_manager.move_path(os, &rule, global, position).await {
+                    Ok(()) => execute!(
                         session.stderr,
                         style::SetForegroundColor(Color::Green),
                         style::Print(format!(
-                            "\nRemoved {} path(s) from {} context.\n\n",
-                            paths.len(),
-                            target
+                            "\nMoved {rule} in {} context. Use /context show to see the new order.\n\n",
+                            if global { "global" } else { "profile" }
                         )),
                         style::SetForegroundColor(Color::Reset)
-                    )?;
-                },
-                Err(e) => {
-                    execute!(
+                    )?,
+                    Err(e) => execute!(
                         session.stderr,
                         style::SetForegroundColor(Color::Red),
                         style::Print(format!("\nError: {}\n\n", e)),
                         style::SetForegroundColor(Color::Reset)
-                    )?;
-                },
+                    )?,
+                }
+            },
+            Self::Watch(subcommand) => {
+                let watching = subcommand == ContextWatchSubcommand::On;
+                match context_manager.set_watching(watching) {
+                    Ok(()) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(if watching {
+                            "\n✔ Watching matched files, they are read again only when they change\n\n"
+                        } else {
+                            "\n✔ Stopped watching, matched files are read for each prompt\n\n"
+                        }),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                    Err(e) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nCouldn't watch context files: {e}\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Group(subcommand) => match subcommand.apply(os, context_manager).await {
+                Ok(message) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\n{message}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+                Err(e) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {}\n\n", e)),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
             },
             Self::Clear { global } => match context_manager.clear(os, global).await {
                 Ok(_) => {
@@ -424,6 +824,94 @@ impl ContextSubcommand {
                     )?;
                 },
             },
+            Self::Refresh => {
+                let urls = context_manager.url_rules();
+                let s3_rules = context_manager.s3_rules();
+                if urls.is_empty() && s3_rules.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo URL or S3 rules to refresh.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                for url in urls {
+                    let refreshed = url_context::fetch(os, url, true)
+                        .await
+                        .map(|content| format!("~{} tkns", TokenCounter::count_tokens(&content)));
+                    print_refreshed(&mut session.stderr, url, refreshed)?;
+                }
+                for rule in s3_rules {
+                    let refreshed = s3_context::fetch(os, rule, true).await.map(|objects| {
+                        let tokens: usize = objects
+                            .iter()
+                            .map(|(_, content)| TokenCounter::count_tokens(content))
+                            .sum();
+                        format!("{} objects, ~{tokens} tkns", objects.len())
+                    });
+                    print_refreshed(&mut session.stderr, rule, refreshed)?;
+                }
+            },
+            Self::Init { project } => match init_template(os, context_manager, project).await {
+                Ok(message) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\n{message}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+                Err(err) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            },
+            Self::Diff => {
+                let current = match context_manager.collect_context_files_with_limit(os).await {
+                    Ok((files, _)) => files
+                        .iter()
+                        .map(|(path, content)| ContextFileDigest::new(path, content))
+                        .collect::<Vec<_>>(),
+                    Err(e) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\nError: {}\n\n", e)),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+                match session.conversation.sent_context_files() {
+                    Some(sent) => print_context_changes(&mut session.stderr, sent, &current)?,
+                    None => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo prompt has been sent yet, there is nothing to compare against.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Validate => match context_manager.validate(os).await {
+                Ok(checks) => print_validation(os, &mut session.stderr, &checks)?,
+                Err(e) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {}\n\n", e)),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            },
+            Self::Usage { limit } => match context_manager.collect_context_files_with_limit(os).await {
+                Ok((used, dropped)) => print_context_usage(os, &mut session.stderr, &used, &dropped, limit)?,
+                Err(e) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {}\n\n", e)),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            },
             Self::Hooks => {
                 execute!(
                     session.stderr,
@@ -443,3 +931,606 @@ impl ContextSubcommand {
         })
     }
 }
+
+/// Prints a rule numbered by its place in the order rules are kept when the context is trimmed.
+fn print_rule(output: &mut impl Write, number: usize, rule: &str) -> Result<(), ChatError> {
+    execute!(
+        output,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!("    {number}. ")),
+        style::SetForegroundColor(Color::Reset),
+        style::Print(format!("{rule} "))
+    )?;
+    Ok(())
+}
+
+/// Adds `paths` as rules with `options`, returning the rules added.
+/// Fetches the URL and S3 rules among `rules` again, so that they aren't added with a stale copy.
+async fn refresh_remote_rules(os: &Os, rules: &[String]) -> eyre::Result<()> {
+    for rule in rules {
+        if url_context::is_url(rule) {
+            url_context::fetch(os, rule, true).await?;
+        } else if s3_context::is_s3_uri(rule) {
+            s3_context::fetch(os, rule, true).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn add_rules(
+    os: &Os,
+    context_manager: &mut ContextManager,
+    paths: Vec<String>,
+    global: bool,
+    force: bool,
+    options: RuleOptions,
+) -> eyre::Result<Vec<String>> {
+    // Rules are validated with their own options, e.g. a rule that only matches git-ignored files
+    // is valid with --gitignore false.
+    if !force {
+        validate_rules(os, &paths, options).await?;
+    }
+    context_manager.add_paths(os, paths.clone(), global, true).await?;
+    if options != RuleOptions::default() {
+        for path in &paths {
+            context_manager.set_rule_options(os, path, global, options).await?;
+        }
+    }
+    Ok(paths)
+}
+
+/// The estimated tokens of a matched file. When `expand` is set, PDFs and images also show the size
+/// of the file their text was extracted from.
+async fn tokens_label(os: &Os, filename: &str, content: &str, expand: bool) -> String {
+    let tokens = format!("~{} tkns", TokenCounter::count_tokens(content));
+    let Some(kind) = DocumentKind::of(Path::new(filename)).filter(|_| expand) else {
+        return tokens;
+    };
+    match os.fs.symlink_metadata(filename).await {
+        Ok(metadata) => format!(
+            "{tokens} extracted from a {} {}",
+            format_size(metadata.len()),
+            kind.name()
+        ),
+        Err(_) => format!("{tokens} extracted from a {}", kind.name()),
+    }
+}
+
+/// Describes a rule's budget, priority and walk options, or returns `None` if it has the defaults.
+fn rule_options_label(options: RuleOptions) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(max_tokens) = options.max_tokens {
+        parts.push(format!("max {max_tokens} tkns"));
+    }
+    if options.priority != 0 {
+        parts.push(format!("priority {}", options.priority));
+    }
+    match options.gitignore {
+        Some(true) => parts.push("skips git-ignored".to_string()),
+        Some(false) => parts.push("includes git-ignored".to_string()),
+        None => (),
+    }
+    match options.follow_symlinks {
+        Some(true) => parts.push("follows symlinks".to_string()),
+        Some(false) => parts.push("no symlinks".to_string()),
+        None => (),
+    }
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// `files` as `(filename, tokens)`, largest first.
+fn rank_by_tokens(files: &[(String, String)]) -> Vec<(&str, usize)> {
+    let mut ranked: Vec<(&str, usize)> = files
+        .iter()
+        .map(|(filename, content)| (filename.as_str(), TokenCounter::count_tokens(content)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ranked
+}
+
+/// What to do about a problem with `rule`.
+fn suggestion(rule: &str, problem: &RuleProblem) -> String {
+    match problem {
+        RuleProblem::NoMatches if is_exclusion(rule) => String::new(),
+        RuleProblem::NoMatches => format!(
+            "Check the path from the current directory with /context show --expand, or remove it with /context rm \"{rule}\". \
+If it only matches files ignored by git, add it again with --gitignore false."
+        ),
+        RuleProblem::OutsideWorkspace(_) => {
+            "Check it's meant for this project. Use a path under the current directory, or if it's the same for \
+every project, make it a global rule with /context add --global."
+                .to_string()
+        },
+        RuleProblem::Unreadable(_) => format!(
+            "Only text, PDF and image files can be read. Fix the file's permissions, or exclude it with /context add \"!<path>\" \
+so that \"{rule}\" matches only readable files."
+        ),
+        RuleProblem::Failed(_) if url_context::is_url(rule) || s3_context::is_s3_uri(rule) => {
+            "Check the address and your network or AWS credentials, then fetch it again with /context refresh."
+                .to_string()
+        },
+        RuleProblem::Failed(_) => format!("Fix the rule, or remove it with /context rm \"{rule}\"."),
+    }
+}
+
+/// Prints the problems found with each rule and what to do about them, then whether the matched
+/// files fit in the context.
+fn print_validation(os: &Os, output: &mut impl Write, checks: &[RuleCheck]) -> Result<(), ChatError> {
+    if checks.is_empty() {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\nThere are no context rules to validate, add some with /context add.\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        return Ok(());
+    }
+
+    let fmt = LocaleFormatter::new(os);
+    execute!(output, style::Print("\n"))?;
+    for check in checks {
+        if check.problems.is_empty() {
+            let summary = if is_exclusion(&check.rule) {
+                "exclusion".to_string()
+            } else {
+                format!(
+                    "{} file{}, ~{} tkns",
+                    check.files,
+                    if check.files == 1 { "" } else { "s" },
+                    fmt.integer(check.tokens)
+                )
+            };
+            execute!(
+                output,
+                style::SetForegroundColor(Color::Green),
+                style::Print("  ✔ "),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(&check.rule),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(" ({summary})\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            continue;
+        }
+
+        execute!(
+            output,
+            style::SetForegroundColor(Color::Red),
+            style::Print("  ✘ "),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!("{}\n", check.rule))
+        )?;
+        for problem in &check.problems {
+            let description = match problem {
+                RuleProblem::NoMatches => {
+                    "Matches no files, or only files excluded or matched by an earlier rule".to_string()
+                },
+                RuleProblem::OutsideWorkspace(path) => format!("{path} is outside the current directory"),
+                RuleProblem::Unreadable(files) => {
+                    let mut description = format!(
+                        "{} matched file{} can't be read:",
+                        files.len(),
+                        if files.len() == 1 { "" } else { "s" }
+                    );
+                    for (file, err) in files {
+                        description.push_str(&format!("\n        {file}: {err}"));
+                    }
+                    description
+                },
+                RuleProblem::Failed(err) => err.clone(),
+            };
+            execute!(
+                output,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!("      {description}\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            let suggestion = suggestion(&check.rule, problem);
+            if !suggestion.is_empty() {
+                execute!(
+                    output,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("      → {suggestion}\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            }
+        }
+    }
+
+    let total = checks.iter().map(|check| check.tokens).sum::<usize>();
+    let problems = checks.iter().filter(|check| !check.problems.is_empty()).count();
+    execute!(
+        output,
+        style::SetAttribute(Attribute::Bold),
+        style::Print(format!(
+            "\n{} rule{} checked, {} with problems. The rules match ~{} tkns, {} of the {}k context window.\n",
+            checks.len(),
+            if checks.len() == 1 { "" } else { "s" },
+            problems,
+            fmt.integer(total),
+            fmt.percent(total as f64 / CONTEXT_WINDOW_SIZE as f64 * 100.0, 2),
+            fmt.integer(CONTEXT_WINDOW_SIZE / 1000)
+        )),
+        style::SetAttribute(Attribute::Reset)
+    )?;
+
+    if total > CONTEXT_FILES_MAX_SIZE {
+        let mut largest: Vec<&RuleCheck> = checks.iter().filter(|check| check.tokens > 0).collect();
+        largest.sort_by(|a, b| b.tokens.cmp(&a.tokens));
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkYellow),
+            style::Print(format!(
+                "\nThat's more than the ~{} tkns of context files sent with a prompt, so files are dropped, lowest priority first. The largest rules:\n",
+                fmt.integer(CONTEXT_FILES_MAX_SIZE)
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        for check in largest.iter().take(5) {
+            execute!(
+                output,
+                style::Print(format!("      {} ", check.rule)),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("(~{} tkns)\n", fmt.integer(check.tokens))),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(
+                "      → Give large rules a budget by removing them and adding them again with --max-tokens, exclude files with \"!<pattern>\" rules, or move the rules that matter most first with /context prioritize. /context usage shows which files are dropped.\n"
+            ),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+    }
+    execute!(output, style::Print("\n"))?;
+    Ok(())
+}
+
+/// Prints the `limit` largest context files in use with their share of the context window, then
+/// the files dropped for the context limit.
+fn print_context_usage(
+    os: &Os,
+    output: &mut impl Write,
+    used: &[(String, String)],
+    dropped: &[(String, String)],
+    limit: usize,
+) -> Result<(), ChatError> {
+    if used.is_empty() && dropped.is_empty() {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\nNo files match the context rules.\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        return Ok(());
+    }
+
+    let fmt = LocaleFormatter::new(os);
+    let percent_of_window = |tokens: usize| fmt.percent(tokens as f64 / CONTEXT_WINDOW_SIZE as f64 * 100.0, 2);
+    let ranked = rank_by_tokens(used);
+    let total = ranked.iter().map(|(_, tokens)| tokens).sum::<usize>();
+    execute!(
+        output,
+        style::SetAttribute(Attribute::Bold),
+        style::Print(format!(
+            "\n{} context file{} in use, ~{} tkns ({} of the {}k context window):\n\n",
+            ranked.len(),
+            if ranked.len() == 1 { "" } else { "s" },
+            fmt.integer(total),
+            percent_of_window(total),
+            fmt.integer(CONTEXT_WINDOW_SIZE / 1000)
+        )),
+        style::SetAttribute(Attribute::Reset)
+    )?;
+
+    for (i, (filename, tokens)) in ranked.iter().take(limit).enumerate() {
+        let share = if total == 0 {
+            0.0
+        } else {
+            *tokens as f64 / total as f64 * 100.0
+        };
+        execute!(
+            output,
+            style::Print(format!("{:>4}. ", i + 1)),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print(format!("{:>10} ", format!("~{} tkns", fmt.integer(*tokens)))),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "{:>7} of window, {:>7} of context  ",
+                percent_of_window(*tokens),
+                fmt.percent(share, 1)
+            )),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!("{filename}\n")),
+        )?;
+    }
+    if ranked.len() > limit {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "      ({} smaller files, use --limit to show more)\n",
+                ranked.len() - limit
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+    }
+
+    if !dropped.is_empty() {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkYellow),
+            style::Print(format!(
+                "\n{} file{} dropped for the context limit or a rule's --max-tokens:\n",
+                dropped.len(),
+                if dropped.len() == 1 { "" } else { "s" }
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        for (filename, tokens) in rank_by_tokens(dropped).iter().take(limit) {
+            execute!(
+                output,
+                style::Print(format!("      {filename} ")),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("(~{} tkns)\n", fmt.integer(*tokens))),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+    }
+    execute!(output, style::Print("\n"))?;
+    Ok(())
+}
+
+/// Prints how many files an exclusion rule filters out of the other rules.
+async fn print_exclusion_status(
+    os: &Os,
+    output: &mut impl Write,
+    context_manager: &ContextManager,
+    rule: &str,
+) -> Result<(), ChatError> {
+    let status = match context_manager.count_excluded_files(os, rule).await {
+        Ok(count) => format!("(excludes {} file{})", count, if count == 1 { "" } else { "s" }),
+        Err(e) => format!("(invalid exclusion: {e})"),
+    };
+    execute!(
+        output,
+        style::SetForegroundColor(Color::DarkYellow),
+        style::Print(status),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    Ok(())
+}
+
+/// Prints the files added, removed and changed since the context files in `sent` were sent.
+fn print_context_changes(
+    output: &mut impl Write,
+    sent: &[ContextFileDigest],
+    current: &[ContextFileDigest],
+) -> Result<(), ChatError> {
+    let (changes, unchanged) = context_changes(sent, current);
+    if changes.is_empty() {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "\nThe {unchanged} context file(s) are unchanged since the last prompt.\n\n"
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        return Ok(());
+    }
+
+    execute!(output, style::Print("\nContext changes since the last prompt:\n"))?;
+    for change in changes {
+        let (color, marker, path, tokens) = match change {
+            ContextChange::Added(file) => (Color::Green, '+', &file.path, format!("~{} tkns", file.tokens)),
+            ContextChange::Removed(file) => (Color::Red, '-', &file.path, format!("was ~{} tkns", file.tokens)),
+            ContextChange::Changed { sent, current } => (
+                Color::Yellow,
+                '~',
+                &current.path,
+                format!("~{} → ~{} tkns", sent.tokens, current.tokens),
+            ),
+        };
+        execute!(
+            output,
+            style::SetForegroundColor(color),
+            style::Print(format!("    {marker} {path} ")),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("({tokens})\n")),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+    }
+    execute!(
+        output,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!("    {unchanged} unchanged\n\n")),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    Ok(())
+}
+
+/// Prints the result of refreshing a URL or S3 rule.
+fn print_refreshed(output: &mut impl Write, rule: &str, refreshed: eyre::Result<String>) -> Result<(), ChatError> {
+    match refreshed {
+        Ok(summary) => execute!(
+            output,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\nRefreshed {rule} ")),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("({summary})\n\n")),
+            style::SetForegroundColor(Color::Reset)
+        )?,
+        Err(e) => execute!(
+            output,
+            style::SetForegroundColor(Color::Red),
+            style::Print(format!("\nError: {}\n\n", e)),
+            style::SetForegroundColor(Color::Reset)
+        )?,
+    }
+    Ok(())
+}
+
+/// Prints when a URL or S3 rule was fetched, or nothing for other rules.
+async fn print_url_status(os: &Os, output: &mut impl Write, path: &str) -> Result<(), ChatError> {
+    let fetched_at = if url_context::is_url(path) {
+        url_context::cached(os, path).await.map(|cached| cached.fetched_at)
+    } else if s3_context::is_s3_uri(path) {
+        s3_context::cached(os, path).await.map(|cached| cached.fetched_at)
+    } else {
+        return Ok(());
+    };
+    let status = match fetched_at {
+        Some(fetched_at) => format!(" fetched {}", format_age(fetched_at, OffsetDateTime::now_utc())),
+        None => " not fetched yet".to_string(),
+    };
+    execute!(
+        output,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(status),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    Ok(())
+}
+
+/// Turns directories and the extensions given with `--types` into a glob rule for each pair, e.g.
+/// `src/` and `rs` into `src/**/*.rs`.
+fn typed_rules(paths: &[String], types: &[String]) -> eyre::Result<Vec<String>> {
+    let mut rules = Vec::new();
+    for path in paths {
+        if path.contains(['*', '?', '[']) || url_context::is_url(path) || s3_context::is_s3_uri(path) {
+            eyre::bail!("--types matches files under directories, but {path} is not a directory");
+        }
+        let dir = path.trim_end_matches('/');
+        for extension in types {
+            let extension = extension.trim().trim_start_matches("*.").trim_start_matches('.');
+            if extension.is_empty() || extension.contains(['/', '*', '?', '[']) {
+                eyre::bail!("'{extension}' is not a file extension");
+            }
+            rules.push(match dir {
+                "" | "." => format!("**/*.{extension}"),
+                dir => format!("{dir}/**/*.{extension}"),
+            });
+        }
+    }
+    Ok(rules)
+}
+
+/// Drops the rules made from `--types` that match no files, such as `src/**/*.toml` when `src/`
+/// has none, so that the others can still be added. If none match, all are kept for validation to
+/// report.
+async fn matching_rules(os: &Os, context_manager: &ContextManager, rules: Vec<String>) -> Vec<String> {
+    let mut matching = Vec::new();
+    for rule in &rules {
+        if context_manager
+            .get_context_files_by_path(os, rule)
+            .await
+            .is_ok_and(|files| !files.is_empty())
+        {
+            matching.push(rule.clone());
+        }
+    }
+    if matching.is_empty() { rules } else { matching }
+}
+
+/// Adds the rules and hooks of the template for `project` to the profile, leaving out rules that
+/// match no files and rules and hooks the context already has. Returns the message to show.
+async fn init_template(
+    os: &Os,
+    context_manager: &mut ContextManager,
+    project: Option<ProjectType>,
+) -> eyre::Result<String> {
+    let project = match project {
+        Some(project) => project,
+        None => {
+            let cwd = os.env.current_dir()?;
+            ProjectType::detect(&cwd).ok_or_else(|| {
+                eyre::eyre!(
+                    "Couldn't tell what kind of project {} is, name it with /context init <rust|node|python|terraform>",
+                    cwd.display()
+                )
+            })?
+        },
+    };
+
+    let mut added = Vec::new();
+    for &(rule, max_tokens) in project.rules() {
+        let rule = rule.to_string();
+        let exists = context_manager
+            .global_config
+            .paths
+            .iter()
+            .chain(&context_manager.profile_config.paths)
+            .any(|path| *path == rule);
+        let matches = context_manager
+            .get_context_files_by_path(os, &rule)
+            .await
+            .is_ok_and(|files| !files.is_empty());
+        if exists || !matches {
+            continue;
+        }
+        let options = RuleOptions {
+            max_tokens,
+            ..Default::default()
+        };
+        add_rules(os, context_manager, vec![rule.clone()], false, true, options).await?;
+        added.push(rule);
+    }
+
+    let pack = HookPack {
+        hooks: project
+            .hooks()
+            .iter()
+            .map(|hook| (hook.name.to_string(), hook.hook()))
+            .collect(),
+    };
+    let import = context_manager.import_hooks(os, pack, false, false).await?;
+
+    if added.is_empty() && import.added.is_empty() {
+        return Ok(format!(
+            "The {} template has nothing to add, the profile already has its rules and hooks or they match no files.",
+            project.name()
+        ));
+    }
+    let mut message = format!(
+        "✔ Added {} rule(s) and {} hook(s) for a {} project to profile context.",
+        added.len(),
+        import.added.len(),
+        project.name()
+    );
+    for name in added.iter().chain(&import.added) {
+        message.push_str(&format!("\n    {name}"));
+    }
+    Ok(message)
+}
+
+/// Turns the names of dynamic sources into the rules stored for them, with `depth` for a tree.
+fn dynamic_rules(names: Vec<String>, depth: Option<usize>) -> eyre::Result<Vec<String>> {
+    names
+        .iter()
+        .map(|name| Ok(name.parse::<DynamicSource>()?.with_depth(depth)?.rule()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_by_tokens() {
+        let files = vec![
+            ("b.md".to_string(), "x".repeat(400)),
+            ("a.md".to_string(), "x".repeat(400)),
+            ("big.md".to_string(), "x".repeat(4000)),
+            ("small.md".to_string(), "x".repeat(8)),
+        ];
+        assert_eq!(rank_by_tokens(&files), vec![
+            ("big.md", TokenCounter::count_tokens(&files[2].1)),
+            ("a.md", TokenCounter::count_tokens(&files[1].1)),
+            ("b.md", TokenCounter::count_tokens(&files[0].1)),
+            ("small.md", TokenCounter::count_tokens(&files[3].1)),
+        ]);
+    }
+}
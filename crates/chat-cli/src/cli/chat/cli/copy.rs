@@ -0,0 +1,61 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::util::clipboard::{
+    ClipboardMethod,
+    copy_to_clipboard,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct CopyArgs;
+
+impl CopyArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Some(response) = session
+            .conversation
+            .history()
+            .iter()
+            .rev()
+            .map(|(_, assistant)| assistant.content())
+            .find(|content| !content.trim().is_empty())
+            .map(str::to_string)
+        else {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nThere is no response to copy yet.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let method = copy_to_clipboard(os, &mut session.stderr, &response)?;
+        let message = match method {
+            ClipboardMethod::System => "\nCopied the last response to the clipboard.\n\n",
+            ClipboardMethod::Osc52 => "\nSent the last response to your terminal's clipboard (OSC 52).\n\n",
+        };
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(message),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -0,0 +1,509 @@
+use std::path::Path;
+
+use clap::Subcommand;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde_json::Value;
+use tokio::process::Command;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Collects the outdated dependencies and known advisories of the project in the current
+directory, and asks Q for an upgrade plan. Rust projects use cargo outdated and cargo audit, Node.js
+projects use npm outdated and npm audit. With --apply, Q upgrades one dependency at a time, editing
+the manifest (shown as a diff for review) and running the verification command after each upgrade."
+)]
+pub enum DepsSubcommand {
+    /// Review outdated dependencies and advisories, and propose an upgrade plan
+    Review {
+        /// Apply the plan one dependency at a time, verifying each upgrade
+        #[arg(long)]
+        apply: bool,
+        /// Command that verifies an upgrade, such as "cargo test", detected from the project if not
+        /// given
+        #[arg(long, value_name = "COMMAND")]
+        verify: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ecosystem {
+    Cargo,
+    Npm,
+}
+
+impl Ecosystem {
+    fn detect(os: &Os, cwd: &Path) -> Option<Self> {
+        if os.fs.exists(cwd.join("Cargo.toml")) {
+            Some(Self::Cargo)
+        } else if os.fs.exists(cwd.join("package.json")) {
+            Some(Self::Npm)
+        } else {
+            None
+        }
+    }
+
+    fn manifest(self) -> &'static str {
+        match self {
+            Self::Cargo => "Cargo.toml",
+            Self::Npm => "package.json",
+        }
+    }
+
+    fn outdated_command(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["cargo", "outdated", "--root-deps-only", "--format", "json"],
+            Self::Npm => &["npm", "outdated", "--json"],
+        }
+    }
+
+    fn audit_command(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["cargo", "audit", "--json"],
+            Self::Npm => &["npm", "audit", "--json"],
+        }
+    }
+
+    fn verify_command(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo build && cargo test",
+            Self::Npm => "npm install && npm test",
+        }
+    }
+}
+
+/// A dependency with a newer version available.
+#[derive(Debug, PartialEq)]
+struct Outdated {
+    name: String,
+    current: String,
+    /// Newest version allowed by the current requirement, if it differs from the latest.
+    compatible: Option<String>,
+    latest: String,
+}
+
+/// A known vulnerability or warning for a dependency.
+#[derive(Debug, PartialEq)]
+struct Advisory {
+    package: String,
+    id: String,
+    title: String,
+    severity: Option<String>,
+    /// The versions that fix the advisory.
+    fixed_in: Option<String>,
+}
+
+impl DepsSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Self::Review { apply, verify } = self;
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\nCollecting outdated dependencies and advisories...\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        match review_prompt(os, apply, verify).await {
+            Ok((prompt, notes)) => {
+                for note in notes {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!("{note}\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+                Ok(ChatState::HandleInput { input: prompt })
+            },
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                })
+            },
+        }
+    }
+}
+
+/// The review prompt, along with notes about the reports that couldn't be collected.
+async fn review_prompt(os: &Os, apply: bool, verify: Option<String>) -> Result<(String, Vec<String>)> {
+    let cwd = os.env.current_dir()?;
+    let Some(ecosystem) = Ecosystem::detect(os, &cwd) else {
+        bail!("/deps review needs a Cargo.toml or package.json in the current directory");
+    };
+
+    let mut notes = Vec::new();
+    let outdated = match run_json(&cwd, ecosystem.outdated_command()).await {
+        Ok(report) => match ecosystem {
+            Ecosystem::Cargo => parse_cargo_outdated(&report),
+            Ecosystem::Npm => parse_npm_outdated(&report),
+        },
+        Err(err) => {
+            notes.push(format!("Skipped outdated dependencies: {err}"));
+            None
+        },
+    };
+    let advisories = match run_json(&cwd, ecosystem.audit_command()).await {
+        Ok(report) => match ecosystem {
+            Ecosystem::Cargo => parse_cargo_audit(&report),
+            Ecosystem::Npm => parse_npm_audit(&report),
+        },
+        Err(err) => {
+            notes.push(format!("Skipped advisories: {err}"));
+            None
+        },
+    };
+    if outdated.is_none() && advisories.is_none() {
+        bail!("Neither report could be collected:\n{}", notes.join("\n"));
+    }
+
+    let verify = verify.unwrap_or_else(|| ecosystem.verify_command().to_string());
+    Ok((
+        deps_prompt(ecosystem, outdated.as_deref(), advisories.as_deref(), apply, &verify),
+        notes,
+    ))
+}
+
+/// Runs a report command in `cwd` and parses its JSON output. The exit status is ignored, since
+/// `npm outdated` and the audit commands fail when they find something.
+async fn run_json(cwd: &Path, command: &[&str]) -> Result<Value> {
+    let output = match Command::new(command[0])
+        .args(&command[1..])
+        .current_dir(cwd)
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => bail!("{} was not found", command[0]),
+        Err(err) => return Err(err.into()),
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if let Ok(value) = serde_json::from_str(stdout.trim()) {
+        return Ok(value);
+    }
+    // cargo outdated prints one JSON document per workspace member.
+    let documents: Result<Vec<Value>, _> = stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect();
+    match documents {
+        Ok(documents) if !documents.is_empty() => Ok(Value::Array(documents)),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr
+                .lines()
+                .rfind(|line| !line.trim().is_empty())
+                .unwrap_or("no JSON output");
+            bail!("`{}` failed: {}", command.join(" "), reason.trim())
+        },
+    }
+}
+
+fn string(value: &Value, pointer: &str) -> Option<String> {
+    value.pointer(pointer).and_then(Value::as_str).map(str::to_string)
+}
+
+/// Reads `cargo outdated --format json`, which is a list of documents for workspaces.
+fn parse_cargo_outdated(report: &Value) -> Option<Vec<Outdated>> {
+    let documents = match report {
+        Value::Array(documents) => documents.iter().collect(),
+        document => vec![document],
+    };
+    let mut dependencies = Vec::new();
+    for document in documents {
+        dependencies.extend(document.get("dependencies")?.as_array()?);
+    }
+    Some(
+        dependencies
+            .into_iter()
+            .filter_map(|dependency| {
+                let latest = string(dependency, "/latest")?;
+                let compatible = string(dependency, "/compat").filter(|compat| *compat != latest && compat != "---");
+                Some(Outdated {
+                    name: string(dependency, "/name")?,
+                    current: string(dependency, "/project")?,
+                    compatible,
+                    latest,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Reads `npm outdated --json`, an object of package names.
+fn parse_npm_outdated(report: &Value) -> Option<Vec<Outdated>> {
+    let packages = report.as_object()?;
+    Some(
+        packages
+            .iter()
+            .filter_map(|(name, package)| {
+                let latest = string(package, "/latest")?;
+                Some(Outdated {
+                    name: name.clone(),
+                    current: string(package, "/current").unwrap_or_else(|| "not installed".to_string()),
+                    compatible: string(package, "/wanted").filter(|wanted| *wanted != latest),
+                    latest,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Reads `cargo audit --json`, including its warnings such as unmaintained crates.
+fn parse_cargo_audit(report: &Value) -> Option<Vec<Advisory>> {
+    let advisory = |entry: &Value| {
+        Some(Advisory {
+            package: string(entry, "/package/name")?,
+            id: string(entry, "/advisory/id")?,
+            title: string(entry, "/advisory/title").unwrap_or_default(),
+            // Warnings have a kind, such as unmaintained, while vulnerabilities may have a CVSS vector.
+            severity: string(entry, "/advisory/cvss")
+                .or_else(|| string(entry, "/kind"))
+                .or_else(|| Some("vulnerability".to_string())),
+            fixed_in: entry
+                .pointer("/versions/patched")
+                .and_then(Value::as_array)
+                .filter(|patched| !patched.is_empty())
+                .map(|patched| {
+                    patched
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                }),
+        })
+    };
+
+    let vulnerabilities = report.pointer("/vulnerabilities/list")?.as_array()?;
+    let warnings = report
+        .get("warnings")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|warnings| warnings.values())
+        .filter_map(Value::as_array)
+        .flatten();
+    Some(vulnerabilities.iter().chain(warnings).filter_map(advisory).collect())
+}
+
+/// Reads `npm audit --json`. Each vulnerable package lists the advisories it is affected by in
+/// `via`, which may also name the dependencies it's vulnerable through.
+fn parse_npm_audit(report: &Value) -> Option<Vec<Advisory>> {
+    let packages = report.get("vulnerabilities")?.as_object()?;
+    let mut advisories = Vec::new();
+    for (name, package) in packages {
+        let fixed_in = match package.get("fixAvailable") {
+            Some(Value::Bool(true)) => Some("a compatible version".to_string()),
+            Some(fix @ Value::Object(_)) => string(fix, "/name")
+                .zip(string(fix, "/version"))
+                .map(|(name, version)| format!("{name}@{version}")),
+            _ => None,
+        };
+        for via in package.get("via").and_then(Value::as_array).into_iter().flatten() {
+            // Vulnerable only through another package, which has its own entry.
+            if via.is_string() {
+                continue;
+            }
+            advisories.push(Advisory {
+                package: name.clone(),
+                id: string(via, "/url")
+                    .or_else(|| via.get("source").map(Value::to_string))
+                    .unwrap_or_default(),
+                title: string(via, "/title").unwrap_or_default(),
+                severity: string(via, "/severity").or_else(|| string(package, "/severity")),
+                fixed_in: fixed_in.clone(),
+            });
+        }
+    }
+    Some(advisories)
+}
+
+fn deps_prompt(
+    ecosystem: Ecosystem,
+    outdated: Option<&[Outdated]>,
+    advisories: Option<&[Advisory]>,
+    apply: bool,
+    verify: &str,
+) -> String {
+    let manifest = ecosystem.manifest();
+    let mut prompt = format!("Review the dependencies of this project ({manifest}) and plan their upgrades.\n\n");
+
+    match outdated {
+        Some([]) => prompt.push_str("No dependencies are outdated.\n\n"),
+        Some(outdated) => {
+            prompt.push_str("Outdated dependencies (current -> latest):\n");
+            for dependency in outdated {
+                let compatible = match &dependency.compatible {
+                    Some(compatible) => format!(", {compatible} within the current requirement"),
+                    None => String::new(),
+                };
+                prompt.push_str(&format!(
+                    "- {} {} -> {}{compatible}\n",
+                    dependency.name, dependency.current, dependency.latest
+                ));
+            }
+            prompt.push('\n');
+        },
+        None => prompt.push_str("Outdated dependencies could not be collected.\n\n"),
+    }
+    match advisories {
+        Some([]) => prompt.push_str("There are no known advisories.\n\n"),
+        Some(advisories) => {
+            prompt.push_str("Known advisories:\n");
+            for advisory in advisories {
+                let severity = advisory
+                    .severity
+                    .as_ref()
+                    .map(|severity| format!(" [{severity}]"))
+                    .unwrap_or_default();
+                let fixed_in = advisory
+                    .fixed_in
+                    .as_ref()
+                    .map(|fixed_in| format!(", fixed in {fixed_in}"))
+                    .unwrap_or_default();
+                prompt.push_str(&format!(
+                    "- {} {}{severity}: {}{fixed_in}\n",
+                    advisory.package, advisory.id, advisory.title
+                ));
+            }
+            prompt.push('\n');
+        },
+        None => prompt.push_str("Advisories could not be collected.\n\n"),
+    }
+
+    prompt.push_str(
+        "Propose an upgrade plan: fix advisories first, then group the other upgrades by risk, separating
+upgrades within the current requirements from major version bumps. For major bumps, read how the
+project uses the dependency and note the breaking changes likely to affect it.",
+    );
+    if apply {
+        prompt.push_str(&format!(
+            "
+
+Then apply the plan one dependency at a time. For each one, edit {manifest} with fs_write's
+str_replace command so the change can be reviewed as a diff, then run `{verify}` with execute_bash.
+If verification fails and the fix isn't a small change to how the project uses the dependency,
+revert that upgrade before moving on to the next. Finish with a table of each dependency, its old
+and new version, and whether verification passed."
+        ));
+    } else {
+        prompt.push_str(" Don't change any files yet, the plan can be applied with /deps review --apply.");
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_cargo_reports() {
+        let outdated = json!({"crate_name": "app", "dependencies": [
+            {"name": "serde", "project": "1.0.100", "compat": "1.0.200", "latest": "1.0.200", "kind": "Normal"},
+            {"name": "clap", "project": "3.2.0", "compat": "3.2.25", "latest": "4.5.0", "kind": "Normal"}
+        ]});
+        let workspace = json!([outdated.clone(), {"crate_name": "lib", "dependencies": []}]);
+        assert_eq!(parse_cargo_outdated(&workspace).unwrap().len(), 2);
+        assert_eq!(parse_cargo_outdated(&outdated).unwrap(), [
+            Outdated {
+                name: "serde".into(),
+                current: "1.0.100".into(),
+                compatible: None,
+                latest: "1.0.200".into(),
+            },
+            Outdated {
+                name: "clap".into(),
+                current: "3.2.0".into(),
+                compatible: Some("3.2.25".into()),
+                latest: "4.5.0".into(),
+            },
+        ]);
+
+        let audit = json!({
+            "vulnerabilities": {"found": true, "count": 1, "list": [{
+                "advisory": {"id": "RUSTSEC-2023-0001", "title": "Memory corruption", "cvss": null},
+                "versions": {"patched": [">=1.2.3"]},
+                "package": {"name": "tokio", "version": "1.0.0"}
+            }]},
+            "warnings": {"unmaintained": [{
+                "kind": "unmaintained",
+                "advisory": {"id": "RUSTSEC-2021-0139", "title": "ansi_term is unmaintained"},
+                "versions": {"patched": []},
+                "package": {"name": "ansi_term", "version": "0.12.1"}
+            }]}
+        });
+        let advisories = parse_cargo_audit(&audit).unwrap();
+        assert_eq!(advisories.len(), 2);
+        assert_eq!(advisories[0].package, "tokio");
+        assert_eq!(advisories[0].severity.as_deref(), Some("vulnerability"));
+        assert_eq!(advisories[0].fixed_in.as_deref(), Some(">=1.2.3"));
+        assert_eq!(advisories[1].severity.as_deref(), Some("unmaintained"));
+        assert_eq!(advisories[1].fixed_in, None);
+    }
+
+    #[test]
+    fn test_parse_npm_reports() {
+        let outdated = json!({"react": {"current": "17.0.2", "wanted": "17.0.2", "latest": "18.3.1"}});
+        assert_eq!(parse_npm_outdated(&outdated).unwrap(), [Outdated {
+            name: "react".into(),
+            current: "17.0.2".into(),
+            compatible: Some("17.0.2".into()),
+            latest: "18.3.1".into(),
+        }]);
+
+        let audit = json!({"vulnerabilities": {
+            "minimist": {"severity": "critical", "via": [{
+                "source": 1179, "title": "Prototype Pollution", "url": "https://github.com/advisories/GHSA-xvch-5gv4-984h", "severity": "critical"
+            }], "fixAvailable": true},
+            "mkdirp": {"severity": "critical", "via": ["minimist"], "fixAvailable": true}
+        }});
+        assert_eq!(parse_npm_audit(&audit).unwrap(), [Advisory {
+            package: "minimist".into(),
+            id: "https://github.com/advisories/GHSA-xvch-5gv4-984h".into(),
+            title: "Prototype Pollution".into(),
+            severity: Some("critical".into()),
+            fixed_in: Some("a compatible version".into()),
+        }]);
+    }
+
+    #[test]
+    fn test_deps_prompt() {
+        let outdated = [Outdated {
+            name: "clap".into(),
+            current: "3.2.0".into(),
+            compatible: Some("3.2.25".into()),
+            latest: "4.5.0".into(),
+        }];
+        let prompt = deps_prompt(Ecosystem::Cargo, Some(&outdated), None, false, "cargo test");
+        assert!(prompt.contains("- clap 3.2.0 -> 4.5.0, 3.2.25 within the current requirement\n"));
+        assert!(prompt.contains("Advisories could not be collected."));
+        assert!(prompt.contains("/deps review --apply"));
+
+        let prompt = deps_prompt(Ecosystem::Npm, Some(&[]), Some(&[]), true, "npm test");
+        assert!(prompt.contains("edit package.json with fs_write's"));
+        assert!(prompt.contains("run `npm test` with execute_bash"));
+    }
+}
@@ -17,11 +17,38 @@ use crate::cli::chat::{
 #[derive(Debug, PartialEq, Args)]
 pub struct EditorArgs {
     pub initial_text: Option<String>,
+    /// Edit the previous prompt and send it in place of the previous turn
+    #[arg(long, conflicts_with = "initial_text")]
+    pub last: bool,
 }
 
 impl EditorArgs {
     pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
-        let content = match open_editor(self.initial_text) {
+        let initial_text = match self.last {
+            true => match session
+                .conversation
+                .history()
+                .iter()
+                .rev()
+                .find_map(|(user, _)| user.prompt())
+            {
+                Some(prompt) => Some(prompt.to_string()),
+                None => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nThere is no previous prompt to edit.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                },
+            },
+            false => self.initial_text,
+        };
+
+        let content = match open_editor(initial_text) {
             Ok(content) => content,
             Err(err) => {
                 execute!(
@@ -51,6 +78,14 @@ impl EditorArgs {
                 }
             },
             false => {
+                if self.last {
+                    // The edited prompt replaces the previous turn, along with its response and any
+                    // tool uses that followed it.
+                    session.conversation.undo_turns(1);
+                    session.tool_uses.clear();
+                    session.pending_tool_index = None;
+                }
+
                 execute!(
                     session.stderr,
                     style::SetForegroundColor(Color::Green),
@@ -77,7 +112,7 @@ impl EditorArgs {
 }
 
 /// Opens the user's preferred editor to compose a prompt
-fn open_editor(initial_text: Option<String>) -> Result<String, ChatError> {
+pub fn open_editor(initial_text: Option<String>) -> Result<String, ChatError> {
     // Create a temporary file with a unique name
     let temp_dir = std::env::temp_dir();
     let file_name = format!("q_prompt_{}.md", Uuid::new_v4());
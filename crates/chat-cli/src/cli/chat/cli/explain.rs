@@ -0,0 +1,193 @@
+use clap::{
+    Args,
+    ValueEnum,
+};
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::cli::chat::tools::sanitize_path_tool_arg;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Lines shown before and after the explained region.
+const CONTEXT_LINES: usize = 20;
+/// Characters of a file that can be explained at once.
+const MAX_EXPLAIN_CHARS: usize = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExplainLevel {
+    /// New to programming or to the language
+    Beginner,
+    /// Comfortable with the language, new to the code
+    Intermediate,
+    /// Only the non-obvious parts
+    Expert,
+}
+
+impl ExplainLevel {
+    fn audience(self) -> &'static str {
+        match self {
+            Self::Beginner => {
+                "someone new to programming: explain the language features and terms the code uses as they come up"
+            },
+            Self::Intermediate => {
+                "a developer who knows the language but not this codebase: focus on what the code does and why"
+            },
+            Self::Expert => {
+                "an experienced developer: be brief, skip the obvious and point out subtle behavior, edge cases and pitfalls"
+            },
+        }
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Asks Q to explain a file or a range of its lines, such as /explain src/main.rs:10-40.
+The lines around the range are sent along for context, and the explanation refers to the code by
+path:line. Set the default level with \"q settings chat.explainLevel <beginner|intermediate|expert>\"."
+)]
+pub struct ExplainArgs {
+    /// File to explain, optionally followed by :LINE or :START-END
+    pub target: String,
+    /// How much background the explanation assumes, defaults to intermediate
+    #[arg(long, value_enum)]
+    pub level: Option<ExplainLevel>,
+}
+
+impl ExplainArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.explain_prompt(os).await {
+            Ok(prompt) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("\nAsking Q to explain {}...\n\n", self.target)),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::HandleInput { input: prompt })
+            },
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                })
+            },
+        }
+    }
+
+    async fn explain_prompt(&self, os: &Os) -> Result<String> {
+        let (path, range) = parse_target(&self.target);
+        let content = os.fs.read_to_string(sanitize_path_tool_arg(os, path)).await?;
+        let level = match self.level {
+            Some(level) => level,
+            None => match os.database.settings.get_string(Setting::ChatExplainLevel) {
+                Some(level) => ExplainLevel::from_str(&level, true)
+                    .map_err(|err| eyre::eyre!("chat.explainLevel must be beginner, intermediate or expert: {err}"))?,
+                None => ExplainLevel::Intermediate,
+            },
+        };
+        explain_prompt(path, &content, range, level)
+    }
+}
+
+/// Splits `path:START-END` or `path:LINE` into the path and its 1-based, inclusive line range.
+fn parse_target(target: &str) -> (&str, Option<(usize, usize)>) {
+    let Some((path, lines)) = target.rsplit_once(':') else {
+        return (target, None);
+    };
+    let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+    match (start.trim().parse::<usize>(), end.trim().parse::<usize>()) {
+        (Ok(start), Ok(end)) if start > 0 && !path.is_empty() => (path, Some((start, end.max(start)))),
+        _ => (target, None),
+    }
+}
+
+fn explain_prompt(path: &str, content: &str, range: Option<(usize, usize)>, level: ExplainLevel) -> Result<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, end) = range.unwrap_or((1, lines.len()));
+    if start > lines.len() {
+        bail!("{path} has {} lines, line {start} is past its end", lines.len());
+    }
+    let end = end.min(lines.len());
+    let first = start.saturating_sub(CONTEXT_LINES).max(1);
+    let last = (end + CONTEXT_LINES).min(lines.len());
+
+    // Lines being explained are marked with `>`, the surrounding ones are only context.
+    let width = last.to_string().len();
+    let mut numbered = String::new();
+    for (number, line) in lines
+        .iter()
+        .enumerate()
+        .take(last)
+        .skip(first - 1)
+        .map(|(i, line)| (i + 1, line))
+    {
+        let marker = if (start..=end).contains(&number) { '>' } else { ' ' };
+        numbered.push_str(&format!("{marker}{number:>width$} | {line}\n"));
+    }
+    if numbered.len() > MAX_EXPLAIN_CHARS {
+        bail!("{path}:{start}-{end} is too long to explain at once, pick a smaller range of lines");
+    }
+
+    let region = match range {
+        Some(_) => format!("lines {start}-{end} of {path}"),
+        None => path.to_string(),
+    };
+    Ok(format!(
+        "Explain {region} for {audience}.
+
+The lines to explain are marked with > below, the other lines are only there for context. Walk
+through the code in order and refer to it by location in the form `{path}:LINE` or
+`{path}:START-END`, so each part of the explanation can be traced back to the code. Don't repeat
+the code.
+
+```
+{numbered}```",
+        audience = level.audience(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("src/main.rs"), ("src/main.rs", None));
+        assert_eq!(parse_target("src/main.rs:10-40"), ("src/main.rs", Some((10, 40))));
+        assert_eq!(parse_target("src/main.rs:7"), ("src/main.rs", Some((7, 7))));
+        assert_eq!(parse_target("C:\\src\\main.rs"), ("C:\\src\\main.rs", None));
+        assert_eq!(parse_target("a.rs:0-3"), ("a.rs:0-3", None));
+    }
+
+    #[test]
+    fn test_explain_prompt() {
+        let content = (1..=50).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let prompt = explain_prompt("a.rs", &content, Some((30, 31)), ExplainLevel::Expert).unwrap();
+        assert!(prompt.contains("lines 30-31 of a.rs"));
+        assert!(prompt.contains(">30 | line 30\n>31 | line 31\n 32 | line 32\n"));
+        assert!(prompt.contains(" 10 | line 10\n"));
+        assert!(!prompt.contains("line 9\n"));
+        assert!(prompt.contains("`a.rs:LINE`"));
+
+        assert!(explain_prompt("a.rs", &content, Some((51, 60)), ExplainLevel::Expert).is_err());
+    }
+}
@@ -0,0 +1,563 @@
+use std::collections::VecDeque;
+use std::sync::LazyLock;
+
+use clap::{
+    Args,
+    ValueEnum,
+};
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use serde::Serialize;
+use serde_json::Value;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::api_client::model::ToolResultStatus;
+use crate::cli::chat::message::{
+    AssistantMessage,
+    ToolUseResult,
+    ToolUseResultBlock,
+    UserMessage,
+    UserMessageContent,
+};
+use crate::cli::chat::util::format::LocaleFormatter;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::util::redact::Redactor;
+use crate::util::system_info::os_version;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Exports the conversation as a readable transcript, including tool uses and their output.
+Unlike /save, an exported transcript cannot be loaded back into a session.
+
+The html format writes a standalone page, with highlighted code blocks and tool uses folded away,
+for sharing with people who don't use the CLI.
+
+With --share, secrets and account ids are redacted, local paths are generalized and a summary of the
+environment is appended, so the transcript can be attached to a bug report. Review it before sharing:
+redaction is pattern based and can miss things."
+)]
+pub struct ExportArgs {
+    /// Path to write the transcript to
+    path: String,
+    /// Output format. Defaults to json for .json files, html for .html files and md otherwise
+    #[arg(long, value_enum)]
+    format: Option<ExportFormat>,
+    /// Overwrite the file if it already exists
+    #[arg(short, long)]
+    force: bool,
+    /// Redact the transcript for sharing and append an environment summary
+    #[arg(long)]
+    share: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown
+    #[value(name = "md", alias = "markdown")]
+    Markdown,
+    /// JSON
+    Json,
+    /// Standalone HTML page
+    Html,
+}
+
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// Styles for HTML exports. Code blocks are colored inline by syntect.
+const HTML_STYLE: &str = "body { max-width: 900px; margin: 2em auto; padding: 0 1em; font-family: -apple-system, \
+                          sans-serif; line-height: 1.5; color: #1f2328; }
+h2 { font-size: 1.1em; margin: 1.5em 0 0.5em; }
+.meta { color: #656d76; }
+.text { white-space: pre-wrap; }
+.user { border-left: 3px solid #8250df; padding-left: 1em; }
+pre { padding: 0.75em; overflow-x: auto; border-radius: 6px; }
+details { margin: 0.5em 0; border: 1px solid #d0d7de; border-radius: 6px; padding: 0.25em 0.75em; }
+details pre { background-color: #f6f8fa; }
+summary { cursor: pointer; font-family: monospace; }
+details.error summary { color: #cf222e; }";
+
+impl ExportArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let format = self.format.unwrap_or(match self.path.to_lowercase().as_str() {
+            path if path.ends_with(".json") => ExportFormat::Json,
+            path if path.ends_with(".html") || path.ends_with(".htm") => ExportFormat::Html,
+            _ => ExportFormat::Markdown,
+        });
+
+        if os.fs.exists(&self.path) && !self.force {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!(
+                    "\nFile at {} already exists. To overwrite, use -f or --force\n\n",
+                    &self.path
+                )),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let mut transcript = Transcript::new(session.conversation.conversation_id(), session.conversation.history());
+        if self.share {
+            transcript.environment = Some(EnvironmentSummary {
+                cli_version: env!("CARGO_PKG_VERSION"),
+                os: os_version().map(|version| version.to_string()),
+                model: session.conversation.model.clone(),
+                exchanges: session.conversation.history().len(),
+            });
+        }
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let mut contents = match format {
+            ExportFormat::Markdown => transcript.to_markdown(&LocaleFormatter::new(os).date(now.date())),
+            ExportFormat::Json => transcript.to_json(&now.format(&Rfc3339).unwrap_or_default())?,
+            ExportFormat::Html => transcript.to_html(&LocaleFormatter::new(os).date(now.date())),
+        };
+        if self.share {
+            contents = Redactor::new(os).redact(&contents);
+        }
+
+        match os.fs.write(&self.path, contents).await {
+            Ok(()) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ Exported conversation to {}\n\n", &self.path)),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nFailed to export to {}: {}\n\n", &self.path, err)),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// A flattened, display-oriented view of the conversation history.
+#[derive(Debug, Serialize)]
+struct Transcript<'a> {
+    conversation_id: &'a str,
+    messages: Vec<TranscriptEntry<'a>>,
+    /// Included in transcripts exported with `--share`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<EnvironmentSummary>,
+}
+
+#[derive(Debug, Serialize)]
+struct EnvironmentSummary {
+    cli_version: &'static str,
+    os: Option<String>,
+    model: Option<String>,
+    exchanges: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TranscriptEntry<'a> {
+    User {
+        prompt: &'a str,
+    },
+    Assistant {
+        content: &'a str,
+    },
+    ToolUse {
+        id: &'a str,
+        name: &'a str,
+        input: &'a Value,
+    },
+    ToolResult {
+        tool_use_id: &'a str,
+        status: &'static str,
+        output: String,
+    },
+}
+
+impl<'a> Transcript<'a> {
+    fn new(conversation_id: &'a str, history: &'a VecDeque<(UserMessage, AssistantMessage)>) -> Self {
+        let mut messages = Vec::new();
+        for (user, assistant) in history {
+            match user.content() {
+                UserMessageContent::Prompt { prompt } => messages.push(TranscriptEntry::User { prompt }),
+                UserMessageContent::CancelledToolUses {
+                    prompt,
+                    tool_use_results,
+                } => {
+                    messages.extend(tool_use_results.iter().map(TranscriptEntry::from));
+                    if let Some(prompt) = prompt {
+                        messages.push(TranscriptEntry::User { prompt });
+                    }
+                },
+                UserMessageContent::ToolUseResults { tool_use_results } => {
+                    messages.extend(tool_use_results.iter().map(TranscriptEntry::from));
+                },
+            }
+
+            if !assistant.content().trim().is_empty() {
+                messages.push(TranscriptEntry::Assistant {
+                    content: assistant.content(),
+                });
+            }
+            for tool_use in assistant.tool_uses().unwrap_or_default() {
+                messages.push(TranscriptEntry::ToolUse {
+                    id: &tool_use.id,
+                    name: &tool_use.name,
+                    input: &tool_use.args,
+                });
+            }
+        }
+
+        Self {
+            conversation_id,
+            messages,
+            environment: None,
+        }
+    }
+
+    fn to_markdown(&self, exported_on: &str) -> String {
+        let mut out = format!("# Amazon Q conversation\n\nExported on {exported_on}\n");
+        for entry in &self.messages {
+            match entry {
+                TranscriptEntry::User { prompt } => {
+                    out.push_str(&format!("\n## User\n\n{}\n", prompt.trim_end()));
+                },
+                TranscriptEntry::Assistant { content } => {
+                    out.push_str(&format!("\n## Amazon Q\n\n{}\n", content.trim_end()));
+                },
+                TranscriptEntry::ToolUse { name, input, .. } => {
+                    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                    out.push_str(&format!("\n### Tool use: {name}\n\n{}", fenced("json", &input)));
+                },
+                TranscriptEntry::ToolResult { status, output, .. } => {
+                    out.push_str(&format!("\n### Tool result ({status})\n\n{}", fenced("", output)));
+                },
+            }
+        }
+        if let Some(env) = &self.environment {
+            out.push_str(&format!(
+                "\n## Environment\n\n- Amazon Q CLI: {}\n- OS: {}\n- Model: {}\n- Exchanges: {}\n",
+                env.cli_version,
+                env.os.as_deref().unwrap_or("unknown"),
+                env.model.as_deref().unwrap_or("default"),
+                env.exchanges
+            ));
+        }
+        out
+    }
+
+    fn to_html(&self, exported_on: &str) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Amazon Q \
+             conversation</title>\n<style>\n{HTML_STYLE}\n</style>\n</head>\n<body>\n<h1>Amazon Q \
+             conversation</h1>\n<p class=\"meta\">Exported on {}</p>\n",
+            escape_html(exported_on)
+        );
+        for entry in &self.messages {
+            match entry {
+                TranscriptEntry::User { prompt } => {
+                    out.push_str(&format!(
+                        "<section class=\"user\">\n<h2>User</h2>\n{}</section>\n",
+                        message_to_html(prompt)
+                    ));
+                },
+                TranscriptEntry::Assistant { content } => {
+                    out.push_str(&format!(
+                        "<section class=\"assistant\">\n<h2>Amazon Q</h2>\n{}</section>\n",
+                        message_to_html(content)
+                    ));
+                },
+                TranscriptEntry::ToolUse { name, input, .. } => {
+                    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                    out.push_str(&format!(
+                        "<details class=\"tool-use\">\n<summary>Tool use: {}</summary>\n{}</details>\n",
+                        escape_html(name),
+                        highlight_code("json", &input)
+                    ));
+                },
+                TranscriptEntry::ToolResult { status, output, .. } => {
+                    out.push_str(&format!(
+                        "<details class=\"{status}\">\n<summary>Tool result ({status})</summary>\n<pre>{}</pre>\n</details>\n",
+                        escape_html(output.trim_end())
+                    ));
+                },
+            }
+        }
+        if let Some(env) = &self.environment {
+            out.push_str(&format!(
+                "<section class=\"environment\">\n<h2>Environment</h2>\n<ul>\n<li>Amazon Q CLI: {}</li>\n<li>OS: \
+                 {}</li>\n<li>Model: {}</li>\n<li>Exchanges: {}</li>\n</ul>\n</section>\n",
+                env.cli_version,
+                escape_html(env.os.as_deref().unwrap_or("unknown")),
+                escape_html(env.model.as_deref().unwrap_or("default")),
+                env.exchanges
+            ));
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    fn to_json(&self, exported_at: &str) -> Result<String, ChatError> {
+        #[derive(Serialize)]
+        struct Export<'a> {
+            exported_at: &'a str,
+            #[serde(flatten)]
+            transcript: &'a Transcript<'a>,
+        }
+
+        serde_json::to_string_pretty(&Export {
+            exported_at,
+            transcript: self,
+        })
+        .map_err(|err| ChatError::Custom(format!("Failed to serialize the conversation: {err}").into()))
+    }
+}
+
+impl<'a> From<&'a ToolUseResult> for TranscriptEntry<'a> {
+    fn from(result: &'a ToolUseResult) -> Self {
+        let output = result
+            .content
+            .iter()
+            .map(|block| match block {
+                ToolUseResultBlock::Text(text) => text.clone(),
+                ToolUseResultBlock::Json(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Self::ToolResult {
+            tool_use_id: &result.tool_use_id,
+            status: match result.status {
+                ToolResultStatus::Success => "success",
+                ToolResultStatus::Error => "error",
+            },
+            output,
+        }
+    }
+}
+
+/// Wraps `content` in a code fence long enough that backticks inside it can't close it early.
+fn fenced(lang: &str, content: &str) -> String {
+    let mut longest_run = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest_run = longest_run.max(run);
+    }
+    let fence = "`".repeat((longest_run + 1).max(3));
+    format!("{fence}{lang}\n{}\n{fence}\n", content.trim_end_matches('\n'))
+}
+
+/// Renders a prompt or response, highlighting its fenced code blocks. The rest of the text is
+/// escaped and shown with its line breaks rather than interpreted as markdown.
+fn message_to_html(message: &str) -> String {
+    fn push_text(out: &mut String, text: &mut String) {
+        if !text.trim().is_empty() {
+            out.push_str(&format!(
+                "<div class=\"text\">{}</div>\n",
+                escape_html(text.trim_matches('\n'))
+            ));
+        }
+        text.clear();
+    }
+
+    let mut out = String::new();
+    let mut text = String::new();
+    // The opening fence, language and contents of the code block being read.
+    let mut code: Option<(usize, String, String)> = None;
+    for line in message.trim_end().lines() {
+        if let Some((fence_len, _, contents)) = &mut code {
+            let trimmed = line.trim();
+            if trimmed.len() >= *fence_len && trimmed.chars().all(|c| c == '`') {
+                if let Some((_, lang, contents)) = code.take() {
+                    out.push_str(&highlight_code(&lang, &contents));
+                }
+            } else {
+                contents.push_str(line);
+                contents.push('\n');
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let fence_len = trimmed.chars().take_while(|c| *c == '`').count();
+        if fence_len >= 3 {
+            push_text(&mut out, &mut text);
+            code = Some((fence_len, trimmed[fence_len..].trim().to_string(), String::new()));
+        } else {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    // An unterminated block runs to the end of the message.
+    if let Some((_, lang, contents)) = code {
+        out.push_str(&highlight_code(&lang, &contents));
+    }
+    push_text(&mut out, &mut text);
+    out
+}
+
+/// Highlights `code` as `lang`, falling back to plain text for unknown languages.
+fn highlight_code(lang: &str, code: &str) -> String {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    highlighted_html_for_string(code, &SYNTAX_SET, syntax, &THEME_SET.themes["base16-ocean.dark"])
+        .unwrap_or_else(|_err| format!("<pre>{}</pre>\n", escape_html(code)))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::cli::chat::message::AssistantToolUse;
+
+    fn history() -> VecDeque<(UserMessage, AssistantMessage)> {
+        VecDeque::from([
+            (
+                UserMessage::new_prompt("What's in main.rs?".to_string()),
+                AssistantMessage::new_tool_use(None, "Let me look.".to_string(), vec![AssistantToolUse {
+                    id: "t1".to_string(),
+                    name: "fs_read".to_string(),
+                    args: json!({ "path": "main.rs" }),
+                    ..Default::default()
+                }]),
+            ),
+            (
+                UserMessage::new_tool_use_results(vec![ToolUseResult {
+                    tool_use_id: "t1".to_string(),
+                    content: vec![ToolUseResultBlock::Text("fn main() {}\n```".to_string())],
+                    status: ToolResultStatus::Success,
+                }]),
+                AssistantMessage::new_response(None, "It's empty:\n\n```rust\nfn main() {}\n```".to_string()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_transcript_entries() {
+        let history = history();
+        let transcript = Transcript::new("id", &history);
+        assert_eq!(transcript.messages.len(), 5);
+        assert_eq!(transcript.messages[0], TranscriptEntry::User {
+            prompt: "What's in main.rs?"
+        });
+        assert!(matches!(transcript.messages[2], TranscriptEntry::ToolUse {
+            name: "fs_read",
+            ..
+        }));
+        assert!(matches!(transcript.messages[3], TranscriptEntry::ToolResult {
+            status: "success",
+            ..
+        }));
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let history = history();
+        let markdown = Transcript::new("id", &history).to_markdown("06/30/2025");
+        assert!(markdown.starts_with("# Amazon Q conversation\n\nExported on 06/30/2025\n"));
+        assert!(markdown.contains("\n## User\n\nWhat's in main.rs?\n"));
+        assert!(markdown.contains("\n### Tool use: fs_read\n\n```json\n{\n  \"path\": \"main.rs\"\n}\n```\n"));
+        // The tool output contains a fence of its own, so it needs a longer one.
+        assert!(markdown.contains("\n### Tool result (success)\n\n````\nfn main() {}\n```\n````\n"));
+        // Assistant responses are already markdown and are kept as is.
+        assert!(markdown.contains("\n## Amazon Q\n\nIt's empty:\n\n```rust\nfn main() {}\n```\n"));
+    }
+
+    #[test]
+    fn test_to_html() {
+        let history = history();
+        let html = Transcript::new("id", &history).to_html("06/30/2025");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<p class=\"meta\">Exported on 06/30/2025</p>"));
+        assert!(html.contains("<section class=\"user\">\n<h2>User</h2>\n<div class=\"text\">What's in main.rs?</div>"));
+        // Tool uses and results are collapsed, and their output is escaped rather than rendered.
+        assert!(html.contains("<details class=\"tool-use\">\n<summary>Tool use: fs_read</summary>\n<pre style="));
+        assert!(html.contains("<summary>Tool result (success)</summary>\n<pre>fn main() {}\n```</pre>"));
+        // Fenced code in responses is highlighted instead of shown with its fence.
+        assert!(html.contains("<h2>Amazon Q</h2>\n<div class=\"text\">It's empty:</div>\n<pre style="));
+        assert!(!html.contains("```rust"));
+        assert!(html.contains("<span style="));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_message_to_html() {
+        assert_eq!(
+            message_to_html("a < b\n\nand \"c\""),
+            "<div class=\"text\">a &lt; b\n\nand &quot;c&quot;</div>\n"
+        );
+        let html = message_to_html("before\n````\nlet x = 1;\n```\n````\nafter");
+        assert!(html.starts_with("<div class=\"text\">before</div>\n<pre"));
+        assert!(html.contains("```"), "a shorter fence doesn't close the block");
+        assert!(html.ends_with("<div class=\"text\">after</div>\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let history = history();
+        let json: Value =
+            serde_json::from_str(&Transcript::new("id", &history).to_json("2025-06-30T12:00:00Z").unwrap()).unwrap();
+        assert_eq!(json["conversation_id"], "id");
+        assert_eq!(json["exported_at"], "2025-06-30T12:00:00Z");
+        assert_eq!(
+            json["messages"][2],
+            json!({
+                "type": "tool_use",
+                "id": "t1",
+                "name": "fs_read",
+                "input": { "path": "main.rs" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_environment_summary() {
+        let history = history();
+        let mut transcript = Transcript::new("id", &history);
+        transcript.environment = Some(EnvironmentSummary {
+            cli_version: "1.2.3",
+            os: Some("macOS 15.5".to_string()),
+            model: None,
+            exchanges: 2,
+        });
+        assert!(transcript.to_markdown("06/30/2025").ends_with(
+            "\n## Environment\n\n- Amazon Q CLI: 1.2.3\n- OS: macOS 15.5\n- Model: default\n- Exchanges: 2\n"
+        ));
+        let json: Value = serde_json::from_str(&transcript.to_json("2025-06-30T12:00:00Z").unwrap()).unwrap();
+        assert_eq!(json["environment"]["cli_version"], "1.2.3");
+    }
+
+    #[test]
+    fn test_fenced() {
+        assert_eq!(fenced("", "plain"), "```\nplain\n```\n");
+        assert_eq!(fenced("rust", "a ```` b\n"), "`````rust\na ```` b\n`````\n");
+    }
+}
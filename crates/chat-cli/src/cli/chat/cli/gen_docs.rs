@@ -0,0 +1,396 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use syn::{
+    Attribute,
+    Block,
+    Fields,
+    ImplItem,
+    Item,
+    TraitItem,
+    Visibility,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Public items listed in the prompt, the rest are left for a later run.
+const MAX_ITEMS: usize = 300;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Asks Q to write or update the doc comments of the public API under a path, and the README
+sections that describe it. The public items of Rust sources are read with syn and sent along, marked
+when they have no doc comment. Each change is shown as a diff for review before it's written."
+)]
+pub struct GenDocsArgs {
+    /// Crate, directory or file to document, defaults to the current directory
+    pub path: Option<PathBuf>,
+}
+
+/// A public item's signature, and whether it has a doc comment.
+#[derive(Debug, PartialEq)]
+struct PublicItem {
+    signature: String,
+    documented: bool,
+}
+
+impl GenDocsArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.gen_docs_prompt(os).await {
+            Ok((prompt, items, undocumented)) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!(
+                        "\nFound {items} public items, {undocumented} without docs. Asking Q to document them...\n\n"
+                    )),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::HandleInput { input: prompt })
+            },
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                })
+            },
+        }
+    }
+
+    /// The prompt, along with the number of public items and how many of them have no docs.
+    async fn gen_docs_prompt(&self, os: &Os) -> Result<(String, usize, usize)> {
+        let cwd = os.env.current_dir()?;
+        let path = self.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let root = cwd.join(&path);
+        if !os.fs.exists(&root) {
+            bail!("{} does not exist", path.display());
+        }
+
+        let sources: Vec<PathBuf> = match root.is_file() {
+            true => vec![root.clone()],
+            false => glob::glob(&root.join("**/*.rs").to_string_lossy())?
+                .filter_map(Result::ok)
+                .filter(|file| !file.components().any(|part| part.as_os_str() == "target"))
+                .collect(),
+        };
+        if sources.is_empty() {
+            bail!(
+                "/gen-docs reads the public API of Rust sources, and there are none under {}",
+                path.display()
+            );
+        }
+
+        let mut files = Vec::new();
+        for source in sources {
+            let items = match public_items(&os.fs.read_to_string(&source).await?) {
+                Ok(items) => items,
+                Err(err) => {
+                    tracing::warn!(?err, ?source, "failed to parse a Rust source for /gen-docs");
+                    continue;
+                },
+            };
+            if !items.is_empty() {
+                let relative = source.strip_prefix(&cwd).unwrap_or(&source).to_path_buf();
+                files.push((relative, items));
+            }
+        }
+        if files.is_empty() {
+            bail!("There is no public API under {}", path.display());
+        }
+
+        let readme = [root.join("README.md"), cwd.join("README.md")]
+            .into_iter()
+            .find(|readme| os.fs.exists(readme))
+            .map(|readme| readme.strip_prefix(&cwd).unwrap_or(&readme).to_path_buf());
+        let items = files.iter().map(|(_, items)| items.len()).sum();
+        let undocumented = files
+            .iter()
+            .flat_map(|(_, items)| items)
+            .filter(|item| !item.documented)
+            .count();
+        Ok((gen_docs_prompt(&path, &files, readme.as_deref()), items, undocumented))
+    }
+}
+
+/// The public items of a Rust source file, including the public methods of its impl blocks and
+/// the items of inline modules.
+fn public_items(source: &str) -> Result<Vec<PublicItem>> {
+    let file = syn::parse_file(source)?;
+    let mut items = Vec::new();
+    collect_items(&file.items, "", &mut items);
+    Ok(items)
+}
+
+fn collect_items(items: &[Item], prefix: &str, out: &mut Vec<PublicItem>) {
+    for item in items {
+        let (attrs, signature) = match item {
+            Item::Fn(item) if is_pub(&item.vis) => {
+                let mut stub = item.clone();
+                stub.attrs.clear();
+                stub.block = Box::new(empty_block());
+                (&item.attrs, signature(Item::Fn(stub)))
+            },
+            Item::Struct(item) if is_pub(&item.vis) => {
+                let mut stub = item.clone();
+                stub.attrs.clear();
+                stub.fields = Fields::Unit;
+                stub.semi_token = Some(Default::default());
+                (&item.attrs, signature(Item::Struct(stub)))
+            },
+            Item::Enum(item) if is_pub(&item.vis) => {
+                let mut stub = item.clone();
+                stub.attrs.clear();
+                stub.variants.clear();
+                (&item.attrs, signature(Item::Enum(stub)))
+            },
+            Item::Trait(item) if is_pub(&item.vis) => {
+                let mut stub = item.clone();
+                stub.attrs.clear();
+                stub.items.clear();
+                out.push(PublicItem {
+                    signature: format!("{prefix}{}", signature(Item::Trait(stub.clone()))),
+                    documented: has_docs(&item.attrs),
+                });
+                for trait_item in &item.items {
+                    if let TraitItem::Fn(method) = trait_item {
+                        let mut method_stub = method.clone();
+                        method_stub.attrs.clear();
+                        method_stub.default = None;
+                        method_stub.semi_token = Some(Default::default());
+                        out.push(PublicItem {
+                            signature: format!("{prefix}{}: {}", item.ident, trait_fn_signature(&stub, method_stub)),
+                            documented: has_docs(&method.attrs),
+                        });
+                    }
+                }
+                continue;
+            },
+            Item::Type(item) if is_pub(&item.vis) => (&item.attrs, format!("pub type {}", item.ident)),
+            Item::Const(item) if is_pub(&item.vis) => (&item.attrs, format!("pub const {}", item.ident)),
+            Item::Static(item) if is_pub(&item.vis) => (&item.attrs, format!("pub static {}", item.ident)),
+            Item::Mod(item) if is_pub(&item.vis) => {
+                out.push(PublicItem {
+                    signature: format!("{prefix}pub mod {}", item.ident),
+                    documented: has_docs(&item.attrs),
+                });
+                if let Some((_, items)) = &item.content {
+                    collect_items(items, &format!("{prefix}{}::", item.ident), out);
+                }
+                continue;
+            },
+            Item::Impl(item) if item.trait_.is_none() => {
+                let self_ty = match &*item.self_ty {
+                    syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+                    _ => None,
+                };
+                for impl_item in &item.items {
+                    if let ImplItem::Fn(method) = impl_item {
+                        if !is_pub(&method.vis) {
+                            continue;
+                        }
+                        let mut stub = method.clone();
+                        stub.attrs.clear();
+                        let stub = syn::ItemFn {
+                            attrs: Vec::new(),
+                            vis: stub.vis,
+                            sig: stub.sig,
+                            block: Box::new(empty_block()),
+                        };
+                        let owner = self_ty.as_deref().unwrap_or("impl");
+                        out.push(PublicItem {
+                            signature: format!("{prefix}{owner}: {}", signature(Item::Fn(stub))),
+                            documented: has_docs(&method.attrs),
+                        });
+                    }
+                }
+                continue;
+            },
+            _ => continue,
+        };
+        out.push(PublicItem {
+            signature: format!("{prefix}{signature}"),
+            documented: has_docs(attrs),
+        });
+    }
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+fn has_docs(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("doc"))
+}
+
+fn empty_block() -> Block {
+    Block {
+        brace_token: Default::default(),
+        stmts: Vec::new(),
+    }
+}
+
+/// An item without its body, formatted on one line.
+fn signature(item: Item) -> String {
+    let signature = flatten(&prettyplease::unparse(&syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item],
+    }));
+    signature
+        .trim_end_matches("{}")
+        .trim_end_matches(';')
+        .trim_end()
+        .to_string()
+}
+
+/// Trait methods are formatted through a copy of their trait holding only that method.
+fn trait_fn_signature(item: &syn::ItemTrait, method: syn::TraitItemFn) -> String {
+    let mut stub = item.clone();
+    stub.items = vec![TraitItem::Fn(method)];
+    let text = flatten(&prettyplease::unparse(&syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![Item::Trait(stub)],
+    }));
+    let start = text.find('{').map_or(0, |i| i + 1);
+    let end = text.rfind('}').unwrap_or(text.len());
+    text[start..end].trim().trim_end_matches(';').to_string()
+}
+
+/// Joins a formatted item onto one line.
+fn flatten(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .replace(",)", ")")
+}
+
+fn gen_docs_prompt(path: &Path, files: &[(PathBuf, Vec<PublicItem>)], readme: Option<&Path>) -> String {
+    let mut listing = String::new();
+    let mut listed = 0;
+    for (file, items) in files {
+        if listed >= MAX_ITEMS {
+            break;
+        }
+        listing.push_str(&format!("{}\n", file.display()));
+        for item in items.iter().take(MAX_ITEMS - listed) {
+            let marker = if item.documented { "" } else { "  [no docs]" };
+            listing.push_str(&format!("- {}{marker}\n", item.signature));
+            listed += 1;
+        }
+    }
+    let total: usize = files.iter().map(|(_, items)| items.len()).sum();
+    if total > listed {
+        listing.push_str(&format!("- and {} more items, leave them for later\n", total - listed));
+    }
+
+    let readme = match readme {
+        Some(readme) => format!(
+            "Then update the sections of {} that describe this API where they are missing or out of date, \
+keeping the rest of it as is.",
+            readme.display()
+        ),
+        None => "There is no README to update.".to_string(),
+    };
+    format!(
+        "Write or update the documentation of the public API in {}.
+
+Add doc comments to the items marked [no docs], and fix existing doc comments that no longer match
+the code. Read each file first and match the length and tone of its existing comments: say what an
+item is for and anything surprising about using it, without restating the signature. {readme}
+
+Make each change with fs_write's str_replace command so it can be reviewed as a diff, and only
+change comments and documentation, never code.
+
+{listing}",
+        path.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_items() {
+        let source = r#"
+/// Parses things.
+pub fn parse(input: &str, strict: bool) -> Result<Vec<u8>, String> { todo!() }
+fn private() {}
+pub struct Parser<T> { inner: T }
+impl<T> Parser<T> {
+    /// Makes a parser.
+    pub fn new(inner: T) -> Self { Self { inner } }
+    fn helper(&self) {}
+}
+pub enum Mode { Fast, Slow }
+pub trait Render {
+    fn render(&self) -> String;
+}
+pub mod nested {
+    pub const LIMIT: usize = 3;
+}
+"#;
+        let items = public_items(source).unwrap();
+        let signatures: Vec<(&str, bool)> = items
+            .iter()
+            .map(|item| (item.signature.as_str(), item.documented))
+            .collect();
+        assert_eq!(signatures, [
+            (
+                "pub fn parse(input: &str, strict: bool) -> Result<Vec<u8>, String>",
+                true
+            ),
+            ("pub struct Parser<T>", false),
+            ("Parser: pub fn new(inner: T) -> Self", true),
+            ("pub enum Mode", false),
+            ("pub trait Render", false),
+            ("Render: fn render(&self) -> String", false),
+            ("pub mod nested", false),
+            ("nested::pub const LIMIT", false),
+        ]);
+        assert!(public_items("fn broken(").is_err());
+    }
+
+    #[test]
+    fn test_gen_docs_prompt() {
+        let files = vec![(PathBuf::from("src/lib.rs"), vec![
+            PublicItem {
+                signature: "pub fn parse()".to_string(),
+                documented: true,
+            },
+            PublicItem {
+                signature: "pub struct Parser".to_string(),
+                documented: false,
+            },
+        ])];
+        let prompt = gen_docs_prompt(Path::new("."), &files, Some(Path::new("README.md")));
+        assert!(prompt.contains("src/lib.rs\n- pub fn parse()\n- pub struct Parser  [no docs]\n"));
+        assert!(prompt.contains("sections of README.md"));
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde_json::Value;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Files listed in the prompt, the rest are left for a later run.
+const MAX_FILES: usize = 20;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Asks Q to write tests for a file or directory, then run the tests and fix the new ones until
+they pass. With --coverage, the tests target the functions and lines the coverage report marks as
+never run. Both lcov reports (cargo llvm-cov --lcov, jest, c8, coverage.py) and tarpaulin JSON
+reports (cargo tarpaulin --out Json) are read."
+)]
+pub struct GenTestsArgs {
+    /// File or directory to write tests for
+    pub path: PathBuf,
+    /// Coverage report to find untested code in, as lcov or tarpaulin JSON
+    #[arg(long, value_name = "FILE")]
+    pub coverage: Option<PathBuf>,
+    /// Command that runs the tests, detected from the project if not given
+    #[arg(long, value_name = "COMMAND")]
+    pub test_command: Option<String>,
+}
+
+/// Code in a file that the coverage report marks as never run.
+#[derive(Debug, Default, PartialEq)]
+struct Uncovered {
+    /// Uncovered functions by the line they start on.
+    functions: BTreeMap<usize, String>,
+    lines: Vec<usize>,
+}
+
+impl GenTestsArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.gen_tests_prompt(os).await {
+            Ok(prompt) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("\nAsking Q to write tests for {}...\n\n", self.path.display())),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::HandleInput { input: prompt })
+            },
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                })
+            },
+        }
+    }
+
+    async fn gen_tests_prompt(&self, os: &Os) -> Result<String> {
+        let cwd = os.env.current_dir()?;
+        let target = cwd.join(&self.path);
+        if !os.fs.exists(&target) {
+            bail!("{} does not exist", self.path.display());
+        }
+
+        let uncovered = match &self.coverage {
+            Some(report) => {
+                let text = os.fs.read_to_string(cwd.join(report)).await?;
+                let files = match text.trim_start().starts_with('{') {
+                    true => parse_tarpaulin(&text)?,
+                    false => parse_lcov(&text),
+                };
+                let files = files
+                    .into_iter()
+                    .filter(|(file, uncovered)| {
+                        cwd.join(file).starts_with(&target)
+                            && (!uncovered.functions.is_empty() || !uncovered.lines.is_empty())
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                if files.is_empty() {
+                    bail!(
+                        "{} has no uncovered code under {}",
+                        report.display(),
+                        self.path.display()
+                    );
+                }
+                Some(files)
+            },
+            None => None,
+        };
+
+        let test_command = match &self.test_command {
+            Some(command) => Some(command.clone()),
+            None => default_test_command(os, &cwd),
+        };
+        Ok(gen_tests_prompt(
+            &self.path,
+            uncovered.as_ref(),
+            test_command.as_deref(),
+        ))
+    }
+}
+
+/// Reads an lcov tracefile, using its function (`FN`/`FNDA`) and line (`DA`) records.
+fn parse_lcov(text: &str) -> BTreeMap<String, Uncovered> {
+    let mut files = BTreeMap::new();
+    let mut file: Option<(String, Uncovered, BTreeMap<String, usize>)> = None;
+    for line in text.lines().map(str::trim) {
+        let (record, value) = line.split_once(':').unwrap_or((line, ""));
+        match (record, &mut file) {
+            ("SF", _) => file = Some((value.to_string(), Uncovered::default(), BTreeMap::new())),
+            ("FN", Some((_, _, starts))) => {
+                if let Some((start, name)) = value.split_once(',') {
+                    if let Ok(start) = start.parse() {
+                        starts.insert(name.to_string(), start);
+                    }
+                }
+            },
+            ("FNDA", Some((_, uncovered, starts))) => {
+                if let Some(("0", name)) = value.split_once(',') {
+                    let start = starts.get(name).copied().unwrap_or_default();
+                    uncovered.functions.insert(start, name.to_string());
+                }
+            },
+            ("DA", Some((_, uncovered, _))) => {
+                let mut fields = value.split(',');
+                if let (Some(Ok(number)), Some("0")) = (fields.next().map(str::parse), fields.next()) {
+                    uncovered.lines.push(number);
+                }
+            },
+            ("end_of_record", Some(_)) => {
+                if let Some((path, uncovered, _)) = file.take() {
+                    files.insert(path, uncovered);
+                }
+            },
+            _ => (),
+        }
+    }
+    files
+}
+
+/// Reads the JSON report of `cargo tarpaulin --out Json`, which only has line coverage.
+fn parse_tarpaulin(text: &str) -> Result<BTreeMap<String, Uncovered>> {
+    let report: Value = serde_json::from_str(text)?;
+    let Some(files) = report.get("files").and_then(Value::as_array) else {
+        bail!("The coverage report is neither lcov nor tarpaulin JSON");
+    };
+
+    let mut uncovered = BTreeMap::new();
+    for file in files {
+        let path = file
+            .get("path")
+            .and_then(Value::as_array)
+            .map(|parts| parts.iter().filter_map(Value::as_str).collect::<PathBuf>())
+            .unwrap_or_default();
+        let lines = file
+            .get("traces")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter(|trace| trace.pointer("/stats/Line").and_then(Value::as_u64) == Some(0))
+            .filter_map(|trace| trace.get("line").and_then(Value::as_u64))
+            .map(|line| line as usize)
+            .collect();
+        uncovered.insert(path.to_string_lossy().to_string(), Uncovered {
+            functions: BTreeMap::new(),
+            lines,
+        });
+    }
+    Ok(uncovered)
+}
+
+/// Formats sorted line numbers as ranges, such as `3-5, 9`.
+fn line_ranges(lines: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if line <= *end + 1 => *end = line.max(*end),
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The test command of the project in `cwd`, picked by its build files.
+fn default_test_command(os: &Os, cwd: &Path) -> Option<String> {
+    [
+        ("Cargo.toml", "cargo test"),
+        ("package.json", "npm test"),
+        ("go.mod", "go test ./..."),
+        ("pyproject.toml", "pytest"),
+        ("setup.py", "pytest"),
+        ("pom.xml", "mvn test"),
+        ("build.gradle", "gradle test"),
+        ("build.gradle.kts", "gradle test"),
+    ]
+    .into_iter()
+    .find(|(file, _)| os.fs.exists(cwd.join(file)))
+    .map(|(_, command)| command.to_string())
+}
+
+fn gen_tests_prompt(
+    path: &Path,
+    uncovered: Option<&BTreeMap<String, Uncovered>>,
+    test_command: Option<&str>,
+) -> String {
+    let mut prompt = format!("Write tests for {}.\n\n", path.display());
+    if let Some(files) = uncovered {
+        prompt.push_str("A coverage report shows this code is never run by the current tests, focus on it:\n");
+        for (file, code) in files.iter().take(MAX_FILES) {
+            prompt.push_str(&format!("- {file}\n"));
+            if !code.functions.is_empty() {
+                let functions = code
+                    .functions
+                    .iter()
+                    .map(|(line, name)| format!("{name} (line {line})"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                prompt.push_str(&format!("  functions: {functions}\n"));
+            }
+            if !code.lines.is_empty() {
+                prompt.push_str(&format!("  lines: {}\n", line_ranges(&code.lines)));
+            }
+        }
+        if files.len() > MAX_FILES {
+            prompt.push_str(&format!(
+                "- and {} more files, leave them for later\n",
+                files.len() - MAX_FILES
+            ));
+        }
+        prompt.push('\n');
+    }
+
+    let run = match test_command {
+        Some(command) => format!("`{command}`"),
+        None => "the project's test command".to_string(),
+    };
+    prompt.push_str(&format!(
+        "Read the code and the existing tests first, and put the new tests where this project keeps its tests,
+in the same style. Test behavior that matters, including edge cases and errors, rather than
+restating the implementation. Write the tests with fs_write, then run {run} with execute_bash.
+If a new test fails, fix the test, or stop and explain if the failure shows a bug in the code. Don't
+change the code under test. Finish with the tests you added and the result of the last test run."
+    ));
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lcov() {
+        let lcov = "TN:
+SF:src/lib.rs
+FN:3,parse
+FN:10,render
+FNDA:4,parse
+FNDA:0,render
+DA:3,4
+DA:10,0
+DA:11,0
+DA:12,0
+DA:20,0
+end_of_record
+SF:src/main.rs
+DA:1,1
+end_of_record
+";
+        let files = parse_lcov(lcov);
+        assert_eq!(
+            files["src/lib.rs"].functions,
+            BTreeMap::from([(10, "render".to_string())])
+        );
+        assert_eq!(line_ranges(&files["src/lib.rs"].lines), "10-12, 20");
+        assert_eq!(files["src/main.rs"], Uncovered::default());
+    }
+
+    #[test]
+    fn test_parse_tarpaulin() {
+        let report = r#"{"files": [{
+            "path": ["/", "repo", "src", "lib.rs"],
+            "traces": [
+                {"line": 4, "stats": {"Line": 2}},
+                {"line": 7, "stats": {"Line": 0}},
+                {"line": 8, "stats": {"Line": 0}}
+            ]
+        }]}"#;
+        let files = parse_tarpaulin(report).unwrap();
+        assert_eq!(files["/repo/src/lib.rs"].lines, [7, 8]);
+        assert!(parse_tarpaulin("{}").is_err());
+    }
+
+    #[test]
+    fn test_gen_tests_prompt() {
+        let uncovered = BTreeMap::from([("src/lib.rs".to_string(), Uncovered {
+            functions: BTreeMap::from([(10, "render".to_string())]),
+            lines: vec![10, 11],
+        })]);
+        let prompt = gen_tests_prompt(Path::new("src"), Some(&uncovered), Some("cargo test"));
+        assert!(prompt.contains("- src/lib.rs\n  functions: render (line 10)\n  lines: 10-11\n"));
+        assert!(prompt.contains("run `cargo test` with execute_bash"));
+    }
+}
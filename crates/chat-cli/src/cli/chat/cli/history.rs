@@ -0,0 +1,300 @@
+use std::collections::VecDeque;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use tracing::warn;
+
+use crate::cli::chat::cli::persist::LoadedConversation;
+use crate::cli::chat::message::{
+    AssistantMessage,
+    UserMessage,
+};
+use crate::cli::chat::store::ConversationStore;
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Longest prompt or response line shown in a listing before it's truncated.
+const PREVIEW_BYTES: usize = 100;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Lists and searches the prompts and responses of earlier turns. Results are numbered, and
+'/history use <number>' adds a result to the context of your next prompt."
+)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    subcommand: Option<HistorySubcommand>,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum HistorySubcommand {
+    /// List the prompts in the current conversation
+    List,
+    /// Search prompts and responses for turns containing every search term
+    Search {
+        /// Terms to search for, matched case-insensitively
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+        /// Also search sessions in the session store
+        #[arg(long)]
+        all: bool,
+    },
+    /// Add a result from the last listing or search to the context of the next prompt
+    Use {
+        /// Number of the result
+        number: usize,
+    },
+}
+
+/// A turn listed by `/history`, numbered by its position in [ChatSession::history_results].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMatch {
+    /// Id of the stored session the turn is from, or `None` for the current conversation.
+    pub session_id: Option<String>,
+    /// 1-based position of the turn in its conversation.
+    pub turn: usize,
+    pub prompt: String,
+    pub response: String,
+}
+
+impl HistoryMatch {
+    /// The turn as it is added to the context of the next prompt.
+    pub fn as_context(&self) -> String {
+        let source = match &self.session_id {
+            Some(id) => format!("turn {} of session {id}", self.turn),
+            None => format!("turn {} of this conversation", self.turn),
+        };
+        format!(
+            "--- Earlier exchange ({source}) ---\nUser: {}\nAssistant: {}\n--- End of earlier exchange ---",
+            self.prompt, self.response
+        )
+    }
+}
+
+/// Returns the turns in `history` whose prompt or response contains every term in `terms`, or
+/// every turn with a prompt if `terms` is empty.
+fn search(
+    history: &VecDeque<(UserMessage, AssistantMessage)>,
+    session_id: Option<&str>,
+    terms: &[String],
+) -> Vec<HistoryMatch> {
+    let terms = terms.iter().map(|term| term.to_lowercase()).collect::<Vec<_>>();
+    history
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (user, assistant))| {
+            let prompt = user.prompt()?;
+            let haystack = format!("{prompt}\n{}", assistant.content()).to_lowercase();
+            terms.iter().all(|term| haystack.contains(term)).then(|| HistoryMatch {
+                session_id: session_id.map(str::to_string),
+                turn: i + 1,
+                prompt: prompt.to_string(),
+                response: assistant.content().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The first line of `text` containing one of `terms`, or its first line if none do.
+fn preview<'a>(text: &'a str, terms: &[String]) -> &'a str {
+    let line = text
+        .lines()
+        .find(|line| {
+            let line = line.to_lowercase();
+            terms.iter().any(|term| line.contains(&term.to_lowercase()))
+        })
+        .or_else(|| text.lines().find(|line| !line.trim().is_empty()))
+        .unwrap_or_default()
+        .trim();
+    truncate_safe(line, PREVIEW_BYTES)
+}
+
+impl HistoryArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let (terms, all) = match self.subcommand.unwrap_or(HistorySubcommand::List) {
+            HistorySubcommand::List => (Vec::new(), false),
+            HistorySubcommand::Search { query, all } => (query, all),
+            HistorySubcommand::Use { number } => return use_result(session, number),
+        };
+
+        let mut results = search(session.conversation.history(), None, &terms);
+        if all {
+            results.extend(search_store(os, session.conversation.conversation_id(), &terms).await);
+        }
+
+        if results.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(match terms.is_empty() {
+                    true => "\nThere are no prompts in this conversation yet.\n\n",
+                    false => "\nNo matching turns found.\n\n",
+                }),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        } else {
+            execute!(session.stderr, style::Print("\n"))?;
+            for (i, result) in results.iter().enumerate() {
+                let source = match &result.session_id {
+                    Some(id) => format!("{id} #{}", result.turn),
+                    None => format!("#{}", result.turn),
+                };
+                execute!(
+                    session.stderr,
+                    style::Print(format!("{:>3}. ", i + 1).cyan()),
+                    style::Print(format!("> {}", preview(&result.prompt, &terms))),
+                    style::Print(format!("  {source}\n").dark_grey()),
+                )?;
+                if !terms.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::Print(format!("     {}\n", preview(&result.response, &terms)).dark_grey())
+                    )?;
+                }
+            }
+            execute!(
+                session.stderr,
+                style::Print(
+                    "\nUse '/history use <number>' to add a turn to the context of your next prompt.\n\n".dark_grey()
+                )
+            )?;
+        }
+        session.history_results = results;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Searches every stored session other than the current one, newest first.
+async fn search_store(os: &Os, current_id: &str, terms: &[String]) -> Vec<HistoryMatch> {
+    let Ok(store) = ConversationStore::new(os) else {
+        return Vec::new();
+    };
+    let mut entries = match store.entries(os).await {
+        Ok(entries) => entries
+            .into_iter()
+            .filter(|(id, _)| id != current_id)
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            warn!(?err, "failed to list stored sessions");
+            return Vec::new();
+        },
+    };
+    entries.sort_by(|(_, a), (_, b)| b.updated_at.cmp(&a.updated_at));
+
+    let mut results = Vec::new();
+    for (id, _) in entries {
+        match store.read::<LoadedConversation>(os, &id).await {
+            Ok(loaded) => results.extend(search(loaded.conversation.history(), Some(&id), terms)),
+            Err(err) => warn!(?err, id, "skipping unreadable stored session"),
+        }
+    }
+    results
+}
+
+fn use_result(session: &mut ChatSession, number: usize) -> Result<ChatState, ChatError> {
+    match number.checked_sub(1).and_then(|i| session.history_results.get(i)) {
+        Some(result) => {
+            session.pending_context.push(result.as_context());
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!(
+                    "\n✔ Turn {} will be included with your next prompt\n\n",
+                    result.turn
+                )),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        },
+        None => execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!(
+                "\nThere is no result {number}. Run /history or /history search first.\n\n"
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?,
+    }
+
+    Ok(ChatState::PromptUser {
+        skip_printing_tools: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> VecDeque<(UserMessage, AssistantMessage)> {
+        [
+            (
+                "How do I write an S3 bucket policy?",
+                "Here is a bucket policy:\n```json\n{}\n```",
+            ),
+            ("And for DynamoDB?", "Use an IAM policy instead."),
+            ("Thanks", "You're welcome"),
+        ]
+        .into_iter()
+        .map(|(prompt, response)| {
+            (
+                UserMessage::new_prompt(prompt.to_string()),
+                AssistantMessage::new_response(None, response.to_string()),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_search() {
+        let history = history();
+        let terms = |terms: &[&str]| terms.iter().map(|t| (*t).to_string()).collect::<Vec<_>>();
+
+        assert_eq!(search(&history, None, &[]).len(), 3);
+
+        let results = search(&history, Some("abc"), &terms(&["s3", "POLICY"]));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].turn, 1);
+        assert_eq!(results[0].session_id.as_deref(), Some("abc"));
+
+        let results = search(&history, None, &terms(&["policy"]));
+        assert_eq!(results.iter().map(|r| r.turn).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            preview(&results[0].response, &terms(&["policy"])),
+            "Here is a bucket policy:"
+        );
+
+        assert!(search(&history, None, &terms(&["lambda"])).is_empty());
+    }
+
+    #[test]
+    fn test_as_context() {
+        let result = HistoryMatch {
+            session_id: None,
+            turn: 2,
+            prompt: "And for DynamoDB?".to_string(),
+            response: "Use an IAM policy instead.".to_string(),
+        };
+        assert_eq!(
+            result.as_context(),
+            "--- Earlier exchange (turn 2 of this conversation) ---\nUser: And for DynamoDB?\nAssistant: Use an IAM \
+             policy instead.\n--- End of earlier exchange ---"
+        );
+    }
+}
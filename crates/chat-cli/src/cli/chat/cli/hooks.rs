@@ -1,9 +1,27 @@
-use std::collections::HashMap;
+use std::collections::{
+    BTreeMap,
+    HashMap,
+    VecDeque,
+};
+use std::hash::{
+    DefaultHasher,
+    Hash,
+    Hasher,
+};
 use std::io::Write;
-use std::process::Stdio;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::{
+    ExitStatus,
+    Stdio,
+};
+use std::sync::Arc;
 use std::time::{
     Duration,
     Instant,
+    SystemTime,
 };
 
 use bstr::ByteSlice;
@@ -40,18 +58,50 @@ use spinners::{
     Spinner,
     Spinners,
 };
+use time::OffsetDateTime;
+use tokio::io::{
+    AsyncRead,
+    AsyncReadExt,
+    AsyncWriteExt,
+};
+use tracing::warn;
 
-use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::autosave::format_age;
+use crate::cli::chat::cli::editor::open_editor;
+use crate::cli::chat::context_walk::{
+    WalkFilter,
+    WalkOptions,
+};
+use crate::cli::chat::conversation::format_hook_context;
+use crate::cli::chat::hook_scripts::{
+    HOOK_SCRIPTS_DIR,
+    HookScript,
+};
+use crate::cli::chat::hook_watch::HookWatcher;
+use crate::cli::chat::json_select::JsonSelector;
+use crate::cli::chat::store::parse_size;
+use crate::cli::chat::token_counter::TokenCounter;
+use crate::cli::chat::util::{
+    layout,
+    truncate_safe,
+};
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
+    hook_sandbox,
 };
 use crate::os::Os;
 
 const DEFAULT_TIMEOUT_MS: u64 = 30_000;
 const DEFAULT_MAX_OUTPUT_SIZE: usize = 1024 * 10;
 const DEFAULT_CACHE_TTL_SECONDS: u64 = 0;
+/// Runs kept in the [HookExecutor]'s log for each hook.
+const HOOK_LOG_SIZE: usize = 20;
+/// Characters of output kept with each run in the log.
+const HOOK_LOG_OUTPUT_CHARS: usize = 500;
+/// Bytes of output kept from the commands of a hook with a filter, for the filter to read.
+const FILTER_INPUT_LIMIT: usize = 16 << 20;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hook {
@@ -62,6 +112,12 @@ pub struct Hook {
     #[serde(default = "Hook::default_disabled")]
     pub disabled: bool,
 
+    /// Whether the command runs sandboxed, without network access, only able to read the
+    /// workspace and system directories and to write to the workspace. Without it, the
+    /// `chat.sandboxHooks` setting decides.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<bool>,
+
     /// Max time the hook can run before it throws a timeout error
     #[serde(default = "Hook::default_timeout_ms")]
     pub timeout_ms: u64,
@@ -74,19 +130,44 @@ pub struct Hook {
     #[serde(default = "Hook::default_cache_ttl_seconds")]
     pub cache_ttl_seconds: u64,
 
+    /// Glob of the files the hook depends on. When set, the hook only runs again once a file
+    /// matching it was added, removed or modified, and its last output is used until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+
+    /// Globs of the files a `file_change` hook watches. Its output is refreshed in the background
+    /// when one of them changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch: Vec<String>,
+
     // Type-specific fields
-    /// The bash command to execute
-    pub command: Option<String>, // For inline hooks
+    /// The bash command to execute, or the commands of a pipeline run one after another
+    pub command: Option<HookCommand>, // For inline hooks
+
+    /// Whether each command of a pipeline gets the output of the command before it on stdin,
+    /// rather than the commands' outputs being concatenated
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pipe: bool,
+
+    /// Applied to the output of the command before it's truncated and added to the context: a
+    /// command reading the output on stdin, e.g. `head -n 50`, or a [JsonSelector] starting with
+    /// `.`, e.g. `.items[].name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
 
     // Internal data
     #[serde(skip)]
     pub name: String,
     #[serde(skip)]
     pub is_global: bool,
+    /// The script the hook was discovered from, for hooks of a workspace's
+    /// [HOOK_SCRIPTS_DIR](crate::cli::chat::hook_scripts::HOOK_SCRIPTS_DIR) rather than a config.
+    #[serde(skip)]
+    pub script: Option<HookScript>,
 }
 
 impl Hook {
-    pub fn new_inline_hook(trigger: HookTrigger, command: String) -> Self {
+    pub fn new_inline_hook(trigger: HookTrigger, command: impl Into<HookCommand>) -> Self {
         Self {
             trigger,
             r#type: HookType::Inline,
@@ -94,9 +175,34 @@ impl Hook {
             timeout_ms: Self::default_timeout_ms(),
             max_output_size: Self::default_max_output_size(),
             cache_ttl_seconds: Self::default_cache_ttl_seconds(),
-            command: Some(command),
+            when: None,
+            watch: Vec::new(),
+            command: Some(command.into()),
+            pipe: false,
+            filter: None,
+            sandbox: None,
             is_global: false,
             name: "new hook".to_string(),
+            script: None,
+        }
+    }
+
+    /// The hook's command as shown, with the commands of a pipeline joined by `|` when they're
+    /// piped and by `&&` otherwise.
+    pub fn command_line(&self) -> String {
+        let separator = if self.pipe { " | " } else { " && " };
+        self.command
+            .as_ref()
+            .map(|command| command.commands().join(separator))
+            .unwrap_or_default()
+    }
+
+    /// Whether the hook's command runs sandboxed, `sandbox_hooks` being the `chat.sandboxHooks`
+    /// setting. Hooks of workspace scripts can only turn the sandbox on, never off.
+    pub fn runs_sandboxed(&self, sandbox_hooks: bool) -> bool {
+        match self.script {
+            Some(_) => sandbox_hooks || self.sandbox == Some(true),
+            None => self.sandbox.unwrap_or(sandbox_hooks),
         }
     }
 
@@ -117,6 +223,40 @@ impl Hook {
     }
 }
 
+/// The command of an inline hook, written in the config as a string, or as a list of the commands
+/// of a pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum HookCommand {
+    Single(String),
+    Pipeline(Vec<String>),
+}
+
+impl HookCommand {
+    /// The commands in the order they run.
+    pub fn commands(&self) -> &[String] {
+        match self {
+            Self::Single(command) => std::slice::from_ref(command),
+            Self::Pipeline(commands) => commands,
+        }
+    }
+}
+
+impl From<String> for HookCommand {
+    fn from(command: String) -> Self {
+        Self::Single(command)
+    }
+}
+
+impl From<Vec<String>> for HookCommand {
+    fn from(mut commands: Vec<String>) -> Self {
+        match commands.len() {
+            1 => Self::Single(commands.remove(0)),
+            _ => Self::Pipeline(commands),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum HookType {
@@ -129,11 +269,122 @@ pub enum HookType {
 pub enum HookTrigger {
     ConversationStart,
     PerPrompt,
+    /// Before each tool is used, with the tool's name and input in `Q_TOOL_NAME` and
+    /// `Q_TOOL_INPUT`. The output is added to the context with the tool's result.
+    PerToolUse,
+    /// After the assistant finishes responding. The output is only shown.
+    PostResponse,
+    /// When a request or a tool fails, with the error in `Q_ERROR`. The output is only shown.
+    OnError,
+    /// With the first prompt, and again in the background whenever a file matching the hook's
+    /// `watch` globs changes. Its latest output is added to each prompt.
+    FileChange,
+}
+
+impl HookTrigger {
+    pub const ALL: [Self; 6] = [
+        Self::ConversationStart,
+        Self::PerPrompt,
+        Self::PerToolUse,
+        Self::PostResponse,
+        Self::OnError,
+        Self::FileChange,
+    ];
+
+    /// Parses a `--trigger` argument, which clap has already limited to the valid names.
+    fn from_arg(trigger: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|value| value.to_string() == trigger)
+            .unwrap_or(Self::PerPrompt)
+    }
+
+    /// Whether the hook runs when a prompt is sent, rather than on an event of the conversation.
+    pub fn runs_with_prompt(&self) -> bool {
+        matches!(self, Self::ConversationStart | Self::PerPrompt | Self::FileChange)
+    }
+
+    /// The trigger of the context the hook's output is added with. The output of `file_change`
+    /// hooks is added to each prompt, with that of `per_prompt` hooks.
+    pub fn context_trigger(&self) -> Self {
+        match self {
+            Self::FileChange => Self::PerPrompt,
+            trigger => trigger.clone(),
+        }
+    }
+
+    /// Whether the hook's output is added to the context, rather than only shown.
+    pub fn adds_context(&self) -> bool {
+        !matches!(self, Self::PostResponse | Self::OnError)
+    }
+}
+
+impl std::fmt::Display for HookTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ConversationStart => "conversation_start",
+            Self::PerPrompt => "per_prompt",
+            Self::PerToolUse => "per_tool_use",
+            Self::PostResponse => "post_response",
+            Self::OnError => "on_error",
+            Self::FileChange => "file_change",
+        })
+    }
+}
+
+/// Which hooks [ContextManager::list_hooks](crate::cli::chat::context::ContextManager::list_hooks)
+/// returns. `None` matches any value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HookFilter {
+    pub global: Option<bool>,
+    pub trigger: Option<HookTrigger>,
+    pub disabled: Option<bool>,
+}
+
+impl HookFilter {
+    pub fn matches(&self, hook: &Hook) -> bool {
+        self.trigger.as_ref().is_none_or(|trigger| *trigger == hook.trigger)
+            && self.disabled.is_none_or(|disabled| disabled == hook.disabled)
+    }
+}
+
+/// A set of hooks written by `/hooks export`, so hooks can be shared without the rest of a profile.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HookPack {
+    pub hooks: BTreeMap<String, Hook>,
+}
+
+/// Names of the hooks added, replaced and left alone by
+/// [ContextManager::import_hooks](crate::cli::chat::context::ContextManager::import_hooks).
+#[derive(Debug, Default, PartialEq)]
+pub struct HookImport {
+    pub added: Vec<String>,
+    pub replaced: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// The outcome of the last time a hook was executed, rather than read from the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookRun {
+    Succeeded(Duration),
+    Failed(String),
+}
+
+/// A run of a hook kept in the [HookExecutor]'s log, listed by `/hooks log`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookExecution {
+    pub started_at: OffsetDateTime,
+    pub duration: Duration,
+    /// `None` when the command couldn't be started, timed out or was terminated by a signal.
+    pub exit_code: Option<i32>,
+    /// The start of the command's stdout and stderr, or the error that stopped it.
+    pub output: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct CachedHook {
     output: String,
+    cached_at: Instant,
     expiry: Option<Instant>,
 }
 
@@ -142,6 +393,18 @@ pub struct CachedHook {
 pub struct HookExecutor {
     pub global_cache: HashMap<String, CachedHook>,
     pub profile_cache: HashMap<String, CachedHook>,
+    /// Keyed by whether the hook is global, and its name
+    last_runs: HashMap<(bool, String), HookRun>,
+    /// The last [HOOK_LOG_SIZE] runs of each hook, oldest first. Keyed by whether the hook is
+    /// global, and its name.
+    log: HashMap<(bool, String), VecDeque<HookExecution>>,
+    /// The [files_fingerprint] of the `when` glob of hooks that have one, and their output, the
+    /// last time they ran. Keyed by whether the hook is global, and its name.
+    conditions: HashMap<(bool, String), (u64, String)>,
+    /// Whether hooks that don't set `sandbox` run sandboxed, the `chat.sandboxHooks` setting.
+    pub sandbox_hooks: bool,
+    /// Refreshes `file_change` hooks, started with the first one that runs.
+    watcher: Option<Arc<HookWatcher>>,
 }
 
 impl HookExecutor {
@@ -149,9 +412,26 @@ impl HookExecutor {
         Self {
             global_cache: HashMap::new(),
             profile_cache: HashMap::new(),
+            last_runs: HashMap::new(),
+            log: HashMap::new(),
+            conditions: HashMap::new(),
+            sandbox_hooks: false,
+            watcher: None,
         }
     }
 
+    pub fn last_run(&self, is_global: bool, name: &str) -> Option<&HookRun> {
+        self.last_runs.get(&(is_global, name.to_string()))
+    }
+
+    /// The logged runs of a hook, newest first.
+    pub fn log(&self, is_global: bool, name: &str) -> impl Iterator<Item = &HookExecution> {
+        self.log
+            .get(&(is_global, name.to_string()))
+            .into_iter()
+            .flat_map(|runs| runs.iter().rev())
+    }
+
     /// Run and cache [`Hook`]s. Any hooks that are already cached will be returned without
     /// executing. Hooks that fail to execute will not be returned.
     ///
@@ -159,13 +439,21 @@ impl HookExecutor {
     /// Errors encountered with write operations to `updates` are ignored.
     ///
     /// Note: [`HookTrigger::ConversationStart`] hooks never leave the cache.
+    ///
+    /// [`HookTrigger::FileChange`] hooks are executed the first time, and then return their latest
+    /// output from the [HookWatcher] refreshing them.
+    ///
+    /// The hooks' commands run with `env` set. Hooks of events are never cached, since they run
+    /// with a different `env` each time.
     pub async fn run_hooks(
         &mut self,
         hooks: Vec<&Hook>,
+        env: &[(&str, String)],
         output: &mut impl Write,
     ) -> Result<Vec<(Hook, String)>, ChatError> {
         let mut results = Vec::with_capacity(hooks.len());
         let mut futures = FuturesUnordered::new();
+        let mut fingerprints = HashMap::new();
 
         // Start all hook future OR fetch from cache if available
         // Why enumerate? We want to return the hook results in the order of hooks that we received,
@@ -176,11 +464,40 @@ impl HookExecutor {
                 continue;
             }
 
-            if let Some(cached) = self.get_cache(hook) {
+            if hook.trigger == HookTrigger::FileChange {
+                if let Some(output) = self.watcher.as_ref().and_then(|watcher| watcher.output(hook)) {
+                    results.push((index, (hook.clone(), output)));
+                    continue;
+                }
+            }
+
+            // A hook with a condition runs regardless of the cache once its files change. Until
+            // then, a prompt hook reuses its last output and an event hook is skipped.
+            if let Some(when) = &hook.when {
+                // Walking the files can take a while in a large workspace. If it panics, the
+                // fingerprint is unlikely to match and the hook runs.
+                let pattern = when.clone();
+                let fingerprint = tokio::task::spawn_blocking(move || files_fingerprint(&pattern))
+                    .await
+                    .unwrap_or_default();
+                match self.conditions.get(&(hook.is_global, hook.name.clone())) {
+                    Some((last, output)) if *last == fingerprint => {
+                        if hook.trigger.runs_with_prompt() {
+                            results.push((index, (hook.clone(), output.clone())));
+                        }
+                        continue;
+                    },
+                    _ => {
+                        fingerprints.insert(index, fingerprint);
+                    },
+                }
+            }
+
+            if let Some(cached) = self.get_cache(hook).filter(|_| !fingerprints.contains_key(&index)) {
                 results.push((index, (hook.clone(), cached.clone())));
                 continue;
             }
-            let future = self.execute_hook(hook);
+            let future = self.execute_hook(hook, env);
             futures.push(async move { (index, future.await) });
         }
 
@@ -189,6 +506,8 @@ impl HookExecutor {
 
         let mut succeeded = 0;
         let total = futures.len();
+        let mut runs = Vec::with_capacity(total);
+        let mut executions = Vec::with_capacity(total);
 
         let mut spinner = None;
         let spinner_text = |complete: usize, total: usize| {
@@ -205,7 +524,10 @@ impl HookExecutor {
 
         // Process results as they complete
         let start_time = Instant::now();
-        while let Some((index, (hook, result, duration))) = futures.next().await {
+        while let Some((index, (hook, result, execution))) = futures.next().await {
+            let duration = execution.duration;
+            executions.push(((hook.is_global, hook.name.clone()), execution));
+
             // If output is enabled, handle that first
             if let Some(spinner) = spinner.as_mut() {
                 spinner.stop();
@@ -251,6 +573,11 @@ impl HookExecutor {
                 },
             }
 
+            runs.push(((hook.is_global, hook.name.clone()), match &result {
+                Ok(_) => HookRun::Succeeded(duration),
+                Err(e) => HookRun::Failed(e.to_string()),
+            }));
+
             // Process results regardless of output enabled
             if let Ok(output) = result {
                 succeeded += 1;
@@ -280,99 +607,141 @@ impl HookExecutor {
         }
 
         drop(futures);
+        self.last_runs.extend(runs);
+        for (key, execution) in executions {
+            let log = self.log.entry(key).or_default();
+            if log.len() == HOOK_LOG_SIZE {
+                log.pop_front();
+            }
+            log.push_back(execution);
+        }
 
         // Fill cache with executed results, skipping what was already from cache
-        results.iter().skip(start_cache_index).for_each(|(_, (hook, output))| {
-            let expiry = match hook.trigger {
-                HookTrigger::ConversationStart => None,
-                HookTrigger::PerPrompt => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
-            };
-            self.insert_cache(hook, CachedHook {
-                output: output.clone(),
-                expiry,
+        results
+            .iter()
+            .skip(start_cache_index)
+            .for_each(|(index, (hook, output))| {
+                if let Some(fingerprint) = fingerprints.get(index) {
+                    self.conditions
+                        .insert((hook.is_global, hook.name.clone()), (*fingerprint, output.clone()));
+                    return;
+                }
+                let expiry = match hook.trigger {
+                    HookTrigger::ConversationStart => None,
+                    HookTrigger::PerPrompt => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
+                    HookTrigger::FileChange => {
+                        self.watch(hook, output.clone());
+                        return;
+                    },
+                    HookTrigger::PerToolUse | HookTrigger::PostResponse | HookTrigger::OnError => return,
+                };
+                self.insert_cache(hook, CachedHook {
+                    output: output.clone(),
+                    cached_at: Instant::now(),
+                    expiry,
+                });
             });
-        });
 
         // Return back to order at request start
         results.sort_by_key(|(idx, _)| *idx);
         Ok(results.into_iter().map(|(_, r)| r).collect())
     }
 
-    async fn execute_hook<'a>(&self, hook: &'a Hook) -> (&'a Hook, Result<String>, Duration) {
+    async fn execute_hook<'a>(
+        &self,
+        hook: &'a Hook,
+        env: &[(&str, String)],
+    ) -> (&'a Hook, Result<String>, HookExecution) {
+        let started_at = OffsetDateTime::now_utc();
         let start_time = Instant::now();
-        let result = match hook.r#type {
-            HookType::Inline => self.execute_inline_hook(hook).await,
+        let (result, exit_code, output) = match hook.r#type {
+            HookType::Inline => match run_hook_command(hook, env, hook.runs_sandboxed(self.sandbox_hooks)).await {
+                Ok(result) => {
+                    let mut output = result.stdout.to_str_lossy().trim_end().to_string();
+                    let stderr = result.stderr.to_str_lossy();
+                    if !stderr.trim().is_empty() {
+                        output.push_str(&format!("\nstderr: {}", stderr.trim_end()));
+                    }
+                    (command_output(hook, &result), result.status.code(), output)
+                },
+                Err(e) => {
+                    let output = e.to_string();
+                    (Err(e), None, output)
+                },
+            },
         };
 
-        (hook, result, start_time.elapsed())
+        (hook, result, HookExecution {
+            started_at,
+            duration: start_time.elapsed(),
+            exit_code,
+            output: truncate_hook_log_output(&output),
+        })
     }
 
-    async fn execute_inline_hook(&self, hook: &Hook) -> Result<String> {
-        let command = hook.command.as_ref().ok_or_else(|| eyre!("no command specified"))?;
-
-        #[cfg(unix)]
-        let command_future = tokio::process::Command::new("bash")
-            .arg("-c")
-            .arg(command)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output();
-
-        #[cfg(windows)]
-        let command_future = tokio::process::Command::new("cmd")
-            .arg("/C")
-            .arg(command)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output();
-
-        let timeout = Duration::from_millis(hook.timeout_ms);
-
-        // Run with timeout
-        match tokio::time::timeout(timeout, command_future).await {
-            Ok(result) => {
-                let result = result?;
-                if result.status.success() {
-                    let stdout = result.stdout.to_str_lossy();
-                    let stdout = format!(
-                        "{}{}",
-                        truncate_safe(&stdout, hook.max_output_size),
-                        if stdout.len() > hook.max_output_size {
-                            " ... truncated"
-                        } else {
-                            ""
-                        }
-                    );
-                    Ok(stdout)
-                } else {
-                    Err(eyre!("command returned non-zero exit code: {}", result.status))
-                }
-            },
-            Err(_) => Err(eyre!("command timed out after {} ms", timeout.as_millis())),
+    /// Will return a cached hook's output if it exists and isn't expired.
+    fn get_cache(&self, hook: &Hook) -> Option<String> {
+        if !hook.trigger.runs_with_prompt() {
+            return None;
         }
+        self.cached(hook.is_global, &hook.name)
+            .map(|cached| cached.output.clone())
     }
 
-    /// Will return a cached hook's output if it exists and isn't expired.
-    fn get_cache(&self, hook: &Hook) -> Option<String> {
-        let cache = if hook.is_global {
+    fn cached(&self, is_global: bool, name: &str) -> Option<&CachedHook> {
+        let cache = if is_global {
             &self.global_cache
         } else {
             &self.profile_cache
         };
 
-        cache.get(&hook.name).and_then(|o| {
-            if let Some(expiry) = o.expiry {
-                if Instant::now() < expiry {
-                    Some(o.output.clone())
-                } else {
-                    None
-                }
-            } else {
-                Some(o.output.clone())
+        cache
+            .get(name)
+            .filter(|cached| cached.expiry.is_none_or(|expiry| Instant::now() < expiry))
+    }
+
+    /// How long ago the output of a hook was cached, if it's used instead of running the hook.
+    pub fn cache_age(&self, is_global: bool, name: &str) -> Option<Duration> {
+        self.cached(is_global, name).map(|cached| cached.cached_at.elapsed())
+    }
+
+    /// Drops the cached output of a hook so it runs again with the next prompt. Returns whether
+    /// any was cached.
+    pub fn clear_cache(&mut self, is_global: bool, name: &str) -> bool {
+        let fresh = self.cached(is_global, name).is_some();
+        let cache = if is_global {
+            &mut self.global_cache
+        } else {
+            &mut self.profile_cache
+        };
+        cache.remove(name);
+        fresh
+    }
+
+    /// Starts refreshing a `file_change` hook whose command just ran with `output`.
+    fn watch(&mut self, hook: &Hook, output: String) {
+        if self.watcher.is_none() {
+            match HookWatcher::new() {
+                Ok(watcher) => self.watcher = Some(Arc::new(watcher)),
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        "failed to start the hook watcher, running file_change hooks with each prompt"
+                    );
+                    return;
+                },
             }
-        })
+        }
+        if let Some(watcher) = &self.watcher {
+            watcher.watch(hook, hook.runs_sandboxed(self.sandbox_hooks), output);
+        }
+    }
+
+    /// Stops refreshing the `file_change` hooks that aren't among `hooks`.
+    pub fn retain_watched(&self, hooks: &[&Hook]) {
+        if let Some(watcher) = &self.watcher {
+            watcher.retain(hooks);
+        }
     }
 
     fn insert_cache(&mut self, hook: &Hook, hook_output: CachedHook) {
@@ -386,6 +755,226 @@ impl HookExecutor {
     }
 }
 
+/// A fingerprint of the files matching `pattern`, which changes when one is added, removed or
+/// modified. Files ignored by git are left out, as they are for context rules.
+///
+/// This walks the file system, so it's called on a blocking thread.
+fn files_fingerprint(pattern: &str) -> u64 {
+    let mut filter = WalkFilter::new(WalkOptions::default(), pattern);
+    let mut files: Vec<(PathBuf, Option<SystemTime>, u64)> = glob::glob(pattern)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file() && filter.includes(path))
+        .map(|path| {
+            let metadata = path.metadata().ok();
+            let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+            let len = metadata.map_or(0, |metadata| metadata.len());
+            (path, modified, len)
+        })
+        .collect();
+    files.sort();
+    let mut hasher = DefaultHasher::new();
+    files.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Runs an inline hook's command with the hook's timeout and `env`, returning its output whether
+/// or not it succeeded. Output past the hook's `max_output_size` is read and dropped rather than
+/// kept, and a command that runs past the timeout is killed.
+///
+/// With `sandbox`, the commands run in the restricted mode of [hook_sandbox].
+///
+/// The commands of a pipeline run one after another, and stop at the first one that fails, whose
+/// status is returned. Their outputs are concatenated, or with [Hook::pipe] passed on to the next
+/// command's stdin, so that only the last command's output is returned.
+///
+/// The output of commands that succeed goes through the hook's [Hook::filter], whose output is
+/// returned instead. Only the filtered output is limited to `max_output_size`.
+pub async fn run_hook_command(hook: &Hook, env: &[(&str, String)], sandbox: bool) -> Result<std::process::Output> {
+    let commands = hook
+        .command
+        .as_ref()
+        .map(HookCommand::commands)
+        .filter(|commands| !commands.is_empty())
+        .ok_or_else(|| eyre!("no command specified"))?;
+
+    let selector = match hook.filter.as_deref() {
+        Some(filter) if JsonSelector::is_selector(filter) => Some(JsonSelector::parse(filter)?),
+        _ => None,
+    };
+
+    // One byte past the limit is kept, so that truncated output can be told apart.
+    let limit = hook.max_output_size.saturating_add(1);
+    let command_limit = match hook.filter {
+        Some(_) => limit.max(FILTER_INPUT_LIMIT),
+        None => limit,
+    };
+    let timeout = Duration::from_millis(hook.timeout_ms);
+    let workspace = match sandbox {
+        true => Some(std::env::current_dir()?),
+        false => None,
+    };
+    let command_future = async move {
+        let mut output = std::process::Output {
+            status: ExitStatus::default(),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        };
+        for command in commands {
+            let input = match hook.pipe {
+                true => std::mem::take(&mut output.stdout),
+                false => Vec::new(),
+            };
+            let sandbox = workspace.as_deref().map(|workspace| (workspace, timeout));
+            let result = run_command(command, env, input, command_limit, sandbox).await?;
+            output.status = result.status;
+            output.stdout.extend(result.stdout);
+            output.stderr.extend(result.stderr);
+            if !output.status.success() {
+                break;
+            }
+        }
+        match (&hook.filter, &selector) {
+            _ if !output.status.success() => (),
+            (_, Some(selector)) => {
+                output.stdout = selector.apply(&output.stdout.to_str_lossy())?.into_bytes();
+            },
+            (Some(filter), None) => {
+                let input = std::mem::take(&mut output.stdout);
+                let sandbox = workspace.as_deref().map(|workspace| (workspace, timeout));
+                let result = run_command(filter, env, input, limit, sandbox).await?;
+                output.status = result.status;
+                output.stdout = result.stdout;
+                output.stderr.extend(result.stderr);
+            },
+            (None, None) => (),
+        }
+        output.stdout.truncate(limit);
+        output.stderr.truncate(limit);
+        Ok::<_, ErrReport>(output)
+    };
+
+    // Run with timeout, dropping the future on timeout kills the command.
+    match tokio::time::timeout(timeout, command_future).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(eyre!("command timed out after {} ms", timeout.as_millis())),
+    }
+}
+
+/// Runs a single command of a hook with `input` on its stdin, keeping the first `limit` bytes of
+/// its stdout and stderr. With `sandbox`, the command runs sandboxed in its workspace, with its
+/// CPU time limited to its timeout.
+async fn run_command(
+    command: &str,
+    env: &[(&str, String)],
+    input: Vec<u8>,
+    limit: usize,
+    sandbox: Option<(&Path, Duration)>,
+) -> std::io::Result<std::process::Output> {
+    let mut process = match sandbox {
+        Some((workspace, timeout)) => hook_sandbox::command(command, workspace, timeout)?,
+        #[cfg(unix)]
+        None => {
+            let mut process = tokio::process::Command::new("bash");
+            process.arg("-c").arg(command);
+            process
+        },
+        #[cfg(windows)]
+        None => {
+            let mut process = tokio::process::Command::new("cmd");
+            process.arg("/C").arg(command);
+            process
+        },
+    };
+    let mut child = process
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(
+            |err| match sandbox.is_some() && err.kind() == std::io::ErrorKind::NotFound {
+                true => hook_sandbox::unavailable(),
+                false => err,
+            },
+        )?;
+
+    let stdin = child.stdin.take();
+    let write_input = async move {
+        if let Some(mut stdin) = stdin {
+            // Commands that don't read their input close stdin before it's written.
+            let _ = stdin.write_all(&input).await;
+            // Closing stdin keeps a command that reads it from waiting.
+            drop(stdin);
+        }
+    };
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    let ((), stdout, stderr, status) = tokio::join!(
+        write_input,
+        read_limited(stdout, limit),
+        read_limited(stderr, limit),
+        child.wait()
+    );
+    Ok(std::process::Output {
+        status: status?,
+        stdout: stdout?,
+        stderr: stderr?,
+    })
+}
+
+/// Reads `reader` to the end, keeping the first `limit` bytes.
+async fn read_limited(reader: Option<impl AsyncRead + Unpin>, limit: usize) -> std::io::Result<Vec<u8>> {
+    let Some(reader) = reader else {
+        return Ok(Vec::new());
+    };
+    let mut kept = Vec::new();
+    let mut reader = reader.take(limit as u64);
+    reader.read_to_end(&mut kept).await?;
+    tokio::io::copy(&mut reader.into_inner(), &mut tokio::io::sink()).await?;
+    Ok(kept)
+}
+
+/// The output a hook adds from the `output` of its command, or an error if the command failed.
+pub fn command_output(hook: &Hook, output: &std::process::Output) -> Result<String> {
+    match output.status.success() {
+        true => Ok(truncate_hook_output(hook, &output.stdout.to_str_lossy())),
+        false => Err(eyre!("command returned non-zero exit code: {}", output.status)),
+    }
+}
+
+/// Opens the definition of `hook` in $EDITOR, returning the hook as saved. Whether the hook is
+/// disabled isn't part of the definition, it's kept as is.
+fn edit_hook(hook: &Hook) -> Result<Hook> {
+    let mut definition = serde_json::to_value(hook)?;
+    if let Some(definition) = definition.as_object_mut() {
+        definition.remove("disabled");
+    }
+    let edited = open_editor(Some(serde_json::to_string_pretty(&definition)?)).map_err(|err| eyre!("{err}"))?;
+    serde_json::from_str(&edited).map_err(|err| eyre!("invalid hook definition: {err}"))
+}
+
+/// Truncates the output of a successful hook to the hook's `max_output_size`.
+fn truncate_hook_output(hook: &Hook, stdout: &str) -> String {
+    format!(
+        "{}{}",
+        truncate_safe(stdout, hook.max_output_size),
+        if stdout.len() > hook.max_output_size {
+            " ... truncated"
+        } else {
+            ""
+        }
+    )
+}
+
+fn truncate_hook_log_output(output: &str) -> String {
+    match output.char_indices().nth(HOOK_LOG_OUTPUT_CHARS) {
+        Some((end, _)) => format!("{} ... truncated", &output[..end]),
+        None => output.to_string(),
+    }
+}
+
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
 #[command(
@@ -395,8 +984,31 @@ in global or local profiles.
 
 Notes:
 • Hooks are executed in parallel
+• /hooks test, or /hooks run, runs a single hook now and shows its output and token estimate, without sending a request
+• /hooks log shows the last runs of each hook in this session, with their exit code, duration and the start of their output
 • 'conversation_start' hooks run on the first user prompt and are attached once to the conversation history sent to Amazon Q
-• 'per_prompt' hooks run on each user prompt and are attached to the prompt, but are not stored in conversation history"
+• 'per_prompt' hooks run on each user prompt and are attached to the prompt, but are not stored in conversation history
+• 'per_prompt' hooks added with --cache-ttl reuse their output until it expires, /hooks list shows its age and --refresh drops it
+• Hooks run with Q_USER_PROMPT, Q_PROFILE, Q_SESSION_ID and Q_TURN_NUMBER set, describing the prompt being answered
+• Hooks added with --when only run again once files matching its glob change, until then prompt hooks reuse their last output
+• 'per_tool_use' hooks run before each tool is used, with Q_TOOL_NAME and Q_TOOL_INPUT set, and are attached to the tool's result
+• 'post_response' hooks run after Amazon Q finishes responding, their output is only shown
+• 'on_error' hooks run when a request or a tool fails, with Q_ERROR set, their output is only shown
+• 'file_change' hooks added with --watch run with the first prompt, then again in the background whenever a file matching
+  their globs changes, so each prompt gets their latest output without waiting for them
+• Scripts in .amazonq/hooks/ are hooks of the workspace, named after the file and run with the interpreter of their shebang
+  or extension. Comments at their top set the hook, e.g. '# trigger: conversation_start' or '# timeout_ms: 5000'.
+  They only run once /hooks trust trusted them, and again after they change. They can't turn off chat.sandboxHooks
+• /hooks edit opens the hook in $EDITOR, or with --trigger or --command only changes those, the hook stays enabled or disabled
+• Hooks added with --sandbox, or every hook with the chat.sandboxHooks setting, run without network access, only reading
+  the workspace and system directories such as /usr and /etc, not the home directory, only writing to the workspace,
+  and with limits on CPU time and memory. They need bubblewrap (bwrap) on Linux
+• /hooks off sends prompts without 'per_prompt' hooks until /hooks on, and a message starting with --no-hooks
+  is sent without them, e.g. '--no-hooks what does this error mean?'
+• Hooks added with --filter pass their output through it before it's truncated and added, either a command reading it
+  on stdin, e.g. 'head -n 50', or a JSON selector starting with '.', e.g. '.items[].metadata.name'
+• Repeating --command adds a pipeline, its commands run one after another until one fails and their outputs are joined,
+  or with --pipe each command's output is passed to the next one's stdin"
 )]
 pub struct HooksArgs {
     #[command(subcommand)]
@@ -409,65 +1021,14 @@ impl HooksArgs {
             return subcommand.execute(os, session).await;
         }
 
-        let Some(context_manager) = &mut session.conversation.context_manager else {
-            return Ok(ChatState::PromptUser {
-                skip_printing_tools: true,
-            });
-        };
-
-        queue!(
-            session.stderr,
-            style::SetAttribute(Attribute::Bold),
-            style::SetForegroundColor(Color::Magenta),
-            style::Print("\n🌍 global:\n"),
-            style::SetAttribute(Attribute::Reset),
-        )?;
-
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.global_config.hooks,
-            HookTrigger::ConversationStart,
-        )
-        .map_err(map_chat_error)?;
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.global_config.hooks,
-            HookTrigger::PerPrompt,
-        )
-        .map_err(map_chat_error)?;
-
-        queue!(
-            session.stderr,
-            style::SetAttribute(Attribute::Bold),
-            style::SetForegroundColor(Color::Magenta),
-            style::Print(format!("\n👤 profile ({}):\n", &context_manager.current_profile)),
-            style::SetAttribute(Attribute::Reset),
-        )?;
-
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.profile_config.hooks,
-            HookTrigger::ConversationStart,
-        )
-        .map_err(map_chat_error)?;
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.profile_config.hooks,
-            HookTrigger::PerPrompt,
-        )
-        .map_err(map_chat_error)?;
-
-        execute!(
-            session.stderr,
-            style::Print(format!(
-                "\nUse {} to manage hooks.\n\n",
-                "/hooks help".to_string().dark_green()
-            )),
-        )?;
-
-        Ok(ChatState::PromptUser {
-            skip_printing_tools: true,
-        })
+        HooksSubcommand::List {
+            global: false,
+            trigger: None,
+            disabled: false,
+            refresh: false,
+        }
+        .execute(os, session)
+        .await
     }
 }
 
@@ -478,12 +1039,43 @@ pub enum HooksSubcommand {
     Add {
         /// The name of the hook
         name: String,
-        /// When to trigger the hook, valid options: `per_prompt` or `conversation_start`
-        #[arg(long, value_parser = ["per_prompt", "conversation_start"])]
+        /// When to trigger the hook, valid options: `per_prompt`, `conversation_start`,
+        /// `per_tool_use`, `post_response`, `on_error` or `file_change`
+        #[arg(long, value_parser = ["per_prompt", "conversation_start", "per_tool_use", "post_response", "on_error", "file_change"])]
         trigger: String,
-        /// Shell command to execute
-        #[arg(long, value_parser = clap::value_parser!(String))]
-        command: String,
+        /// Shell command to execute, repeat to run a pipeline of commands one after another
+        #[arg(long, value_parser = clap::value_parser!(String), required = true)]
+        command: Vec<String>,
+        /// Pass the output of each command of a pipeline to the next one's stdin, rather than
+        /// concatenating their outputs
+        #[arg(long)]
+        pipe: bool,
+        /// Filter applied to the output before it's added to the context: a command reading it on
+        /// stdin, e.g. "head -n 50", or a JSON selector such as ".items[].name"
+        #[arg(long, value_parser = parse_filter)]
+        filter: Option<String>,
+        /// Milliseconds the command can run before it's stopped and the hook fails [default: 30000]
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        timeout: Option<u64>,
+        /// Largest output kept from the command, e.g. 4096 or 64KB, the rest is truncated
+        /// [default: 10KiB]
+        #[arg(long, value_parser = parse_size)]
+        max_output: Option<u64>,
+        /// Seconds the output of a `per_prompt` hook is used again before the command reruns
+        #[arg(long)]
+        cache_ttl: Option<u64>,
+        /// Only run the hook when files matching this glob changed since it last ran, e.g.
+        /// "migrations/**/*.sql"
+        #[arg(long, value_parser = parse_when)]
+        when: Option<String>,
+        /// Glob of the files a `file_change` hook watches, e.g. "src/**/*.rs", repeat to watch
+        /// more
+        #[arg(long, value_parser = parse_when)]
+        watch: Vec<String>,
+        /// Run the command sandboxed, without network access, only able to read the workspace and
+        /// system directories and to write to the workspace, whatever the chat.sandboxHooks setting
+        #[arg(long)]
+        sandbox: bool,
         /// Add to global hooks
         #[arg(long)]
         global: bool,
@@ -497,6 +1089,22 @@ pub enum HooksSubcommand {
         #[arg(long)]
         global: bool,
     },
+    /// Change an existing context hook, in $EDITOR or with the given settings, keeping whether
+    /// it's enabled
+    Edit {
+        /// The name of the hook
+        name: String,
+        /// When to trigger the hook, valid options: `per_prompt`, `conversation_start`,
+        /// `per_tool_use`, `post_response`, `on_error` or `file_change`
+        #[arg(long, value_parser = ["per_prompt", "conversation_start", "per_tool_use", "post_response", "on_error", "file_change"])]
+        trigger: Option<String>,
+        /// Shell command to execute, repeat to run a pipeline of commands one after another
+        #[arg(long, value_parser = clap::value_parser!(String))]
+        command: Vec<String>,
+        /// Edit a global hook
+        #[arg(long)]
+        global: bool,
+    },
     /// Enable an existing context hook
     Enable {
         /// The name of the hook
@@ -513,6 +1121,16 @@ pub enum HooksSubcommand {
         #[arg(long)]
         global: bool,
     },
+    /// Skip `per_prompt` hooks until /hooks on, sending prompts without their output
+    Off,
+    /// Run `per_prompt` hooks with each prompt again, after /hooks off
+    On,
+    /// Trust the scripts in .amazonq/hooks/ that aren't, so that their hooks run, until a script
+    /// changes
+    Trust {
+        /// Only trust the script of this hook
+        name: Option<String>,
+    },
     /// Enable all existing context hooks
     EnableAll {
         /// Enable all in global hooks
@@ -525,12 +1143,74 @@ pub enum HooksSubcommand {
         #[arg(long)]
         global: bool,
     },
-    /// Display the context rule configuration and matched files
-    Show,
+    /// Write hooks to a file that can be shared and added elsewhere with /hooks import
+    Export {
+        /// The file to write
+        path: String,
+        /// Export global hooks
+        #[arg(long)]
+        global: bool,
+        /// Overwrite the file if it exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Add the hooks in a file written by /hooks export
+    Import {
+        /// The file to read
+        path: String,
+        /// Add to global hooks
+        #[arg(long)]
+        global: bool,
+        /// Replace hooks that have the same name without asking
+        #[arg(long, conflicts_with = "merge")]
+        overwrite: bool,
+        /// Keep hooks that have the same name without asking
+        #[arg(long)]
+        merge: bool,
+    },
+    /// Run a hook now and show what it would add to the next prompt and how many tokens that is,
+    /// without changing the conversation
+    #[command(alias = "run")]
+    Test {
+        /// The name of the hook
+        name: String,
+        /// Test a global hook
+        #[arg(long)]
+        global: bool,
+    },
+    /// Show the last runs of hooks, with when they ran, their duration, exit code and the start
+    /// of their output
+    Log {
+        /// Only show the runs of this hook
+        name: Option<String>,
+        /// Only show global hooks
+        #[arg(long)]
+        global: bool,
+        /// Runs shown for each hook, newest first
+        #[arg(short = 'n', long, default_value_t = 5)]
+        limit: usize,
+    },
+    /// List hooks with their trigger, command, state and the outcome of their last run
+    #[command(alias = "show")]
+    List {
+        /// Only list global hooks
+        #[arg(long)]
+        global: bool,
+        /// Only list hooks with this trigger
+        #[arg(long, value_parser = ["per_prompt", "conversation_start", "per_tool_use", "post_response", "on_error", "file_change"])]
+        trigger: Option<String>,
+        /// Only list disabled hooks
+        #[arg(long)]
+        disabled: bool,
+        /// Drop the cached output of the listed hooks, so they run again with the next prompt
+        #[arg(long)]
+        refresh: bool,
+    },
 }
 
 impl HooksSubcommand {
     pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let terminal_width = session.terminal_width();
         let Some(context_manager) = &mut session.conversation.context_manager else {
             return Ok(ChatState::PromptUser {
                 skip_printing_tools: true,
@@ -538,23 +1218,69 @@ impl HooksSubcommand {
         };
 
         let scope = |g: bool| if g { "global" } else { "profile" };
+        let scope_title = |g: bool| if g { "Global" } else { "Profile" };
 
         match self {
             Self::Add {
                 name,
                 trigger,
                 command,
+                pipe,
+                filter,
+                timeout,
+                max_output,
+                cache_ttl,
+                when,
+                watch,
+                sandbox,
                 global,
             } => {
-                let trigger = if trigger == "conversation_start" {
-                    HookTrigger::ConversationStart
-                } else {
-                    HookTrigger::PerPrompt
-                };
+                let mut hook = Hook::new_inline_hook(HookTrigger::from_arg(&trigger), command);
+                hook.pipe = pipe;
+                hook.filter = filter;
+                hook.when = when;
+                if (hook.trigger == HookTrigger::FileChange) == watch.is_empty() {
+                    let reason = match watch.is_empty() {
+                        true => "file_change hooks need the files to watch, set with --watch",
+                        false => "--watch is only used by file_change hooks",
+                    };
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nCannot add {} hook '{name}': {reason}\n\n", scope(global))),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+                hook.watch = watch;
+                hook.sandbox = sandbox.then_some(true);
+                if let Some(cache_ttl) = cache_ttl {
+                    if hook.trigger != HookTrigger::PerPrompt {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!(
+                                "\nCannot add {} hook '{name}': --cache-ttl is only used by per_prompt hooks\n\n",
+                                scope(global)
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    }
+                    hook.cache_ttl_seconds = cache_ttl;
+                }
+                if let Some(timeout) = timeout {
+                    hook.timeout_ms = timeout;
+                }
+                if let Some(max_output) = max_output {
+                    hook.max_output_size = usize::try_from(max_output).unwrap_or(usize::MAX);
+                }
 
-                let result = context_manager
-                    .add_hook(os, name.clone(), Hook::new_inline_hook(trigger, command), global)
-                    .await;
+                let result = context_manager.add_hook(os, name.clone(), hook, global).await;
                 match result {
                     Ok(_) => {
                         execute!(
@@ -595,14 +1321,36 @@ impl HooksSubcommand {
                     },
                 }
             },
-            Self::Enable { name, global } => {
-                let result = context_manager.set_hook_disabled(os, &name, global, false).await;
+            Self::Edit {
+                name,
+                trigger,
+                command,
+                global,
+            } => {
+                let result = match context_manager.hook(&name, global) {
+                    Ok(hook) if trigger.is_none() && command.is_empty() => edit_hook(hook),
+                    Ok(hook) => {
+                        let mut hook = hook.clone();
+                        if let Some(trigger) = trigger {
+                            hook.trigger = HookTrigger::from_arg(&trigger);
+                        }
+                        if !command.is_empty() {
+                            hook.command = Some(HookCommand::from(command));
+                        }
+                        Ok(hook)
+                    },
+                    Err(e) => Err(e),
+                };
+                let result = match result {
+                    Ok(hook) => context_manager.replace_hook(os, &name, hook, global).await,
+                    Err(e) => Err(e),
+                };
                 match result {
-                    Ok(_) => {
+                    Ok(()) => {
                         execute!(
                             session.stderr,
                             style::SetForegroundColor(Color::Green),
-                            style::Print(format!("\nEnabled {} hook '{name}'.\n\n", scope(global))),
+                            style::Print(format!("\nUpdated {} hook '{name}'.\n\n", scope(global))),
                             style::SetForegroundColor(Color::Reset)
                         )?;
                     },
@@ -610,16 +1358,48 @@ impl HooksSubcommand {
                         execute!(
                             session.stderr,
                             style::SetForegroundColor(Color::Red),
-                            style::Print(format!("\nCannot enable {} hook '{name}': {}\n\n", scope(global), e)),
+                            style::Print(format!("\nCannot edit {} hook '{name}': {}\n\n", scope(global), e)),
                             style::SetForegroundColor(Color::Reset)
                         )?;
                     },
                 }
             },
-            Self::Disable { name, global } => {
-                let result = context_manager.set_hook_disabled(os, &name, global, true).await;
+            Self::Enable { name, global } => {
+                let result = context_manager.set_hook_disabled(os, &name, global, false).await;
                 match result {
-                    Ok(_) => {
+                    Ok(true) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(format!("\nEnabled {} hook '{name}'.\n\n", scope(global))),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
+                    Ok(false) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print(format!(
+                                "\n{} hook '{name}' is already enabled.\n\n",
+                                scope_title(global)
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
+                    Err(e) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\nCannot enable {} hook '{name}': {}\n\n", scope(global), e)),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
+                }
+            },
+            Self::Disable { name, global } => {
+                let result = context_manager.set_hook_disabled(os, &name, global, true).await;
+                match result {
+                    Ok(true) => {
                         execute!(
                             session.stderr,
                             style::SetForegroundColor(Color::Green),
@@ -627,6 +1407,17 @@ impl HooksSubcommand {
                             style::SetForegroundColor(Color::Reset)
                         )?;
                     },
+                    Ok(false) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print(format!(
+                                "\n{} hook '{name}' is already disabled.\n\n",
+                                scope_title(global)
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
                     Err(e) => {
                         execute!(
                             session.stderr,
@@ -637,75 +1428,539 @@ impl HooksSubcommand {
                     },
                 }
             },
+            Self::Off | Self::On => {
+                let off = self == Self::Off;
+                let changed = context_manager.prompt_hooks_off != off;
+                context_manager.prompt_hooks_off = off;
+                let message = match (off, changed) {
+                    (true, true) => "Prompts are sent without 'per_prompt' hooks until /hooks on.",
+                    (true, false) => "The 'per_prompt' hooks are already off.",
+                    (false, true) => "The 'per_prompt' hooks run with each prompt again.",
+                    (false, false) => "The 'per_prompt' hooks are already on.",
+                };
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(if changed { Color::Green } else { Color::Yellow }),
+                    style::Print(format!("\n{message}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            },
+            Self::Trust { name } => match context_manager.trust_workspace_hooks(os, name.as_deref()).await {
+                Ok(trusted) if trusted.is_empty() => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nThere are no hook scripts to trust.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                },
+                Ok(trusted) => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!(
+                            "\nTrusted the scripts of {} until they change:\n",
+                            trusted.len()
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    for (name, path) in trusted {
+                        queue!(session.stderr, style::Print(format!("  {name}: {}\n", path.display())))?;
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                },
+                Err(e) => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nCannot trust hook '{}': {e}\n\n", name.unwrap_or_default())),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                },
+            },
             Self::EnableAll { global } => {
-                context_manager
+                let changed = context_manager
                     .set_all_hooks_disabled(os, global, false)
                     .await
                     .map_err(map_chat_error)?;
                 execute!(
                     session.stderr,
-                    style::SetForegroundColor(Color::Green),
-                    style::Print(format!("\nEnabled all {} hooks.\n\n", scope(global))),
+                    style::SetForegroundColor(if changed > 0 { Color::Green } else { Color::Yellow }),
+                    style::Print(match changed {
+                        0 => format!("\nThere are no disabled {} hooks.\n\n", scope(global)),
+                        1 => format!("\nEnabled 1 {} hook.\n\n", scope(global)),
+                        n => format!("\nEnabled {n} {} hooks.\n\n", scope(global)),
+                    }),
                     style::SetForegroundColor(Color::Reset)
                 )?;
             },
             Self::DisableAll { global } => {
-                context_manager
+                let changed = context_manager
                     .set_all_hooks_disabled(os, global, true)
                     .await
                     .map_err(map_chat_error)?;
                 execute!(
                     session.stderr,
-                    style::SetForegroundColor(Color::Green),
-                    style::Print(format!("\nDisabled all {} hooks.\n\n", scope(global))),
+                    style::SetForegroundColor(if changed > 0 { Color::Green } else { Color::Yellow }),
+                    style::Print(match changed {
+                        0 => format!("\nThere are no enabled {} hooks.\n\n", scope(global)),
+                        1 => format!("\nDisabled 1 {} hook.\n\n", scope(global)),
+                        n => format!("\nDisabled {n} {} hooks.\n\n", scope(global)),
+                    }),
                     style::SetForegroundColor(Color::Reset)
                 )?;
             },
-            Self::Show => {
-                // Display global context
+            Self::Export { path, global, force } => {
+                if os.fs.exists(&path) && !force {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!(
+                            "\nFile at {path} already exists. To overwrite, use -f or --force\n\n"
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+                let config = if global {
+                    &context_manager.global_config
+                } else {
+                    &context_manager.profile_config
+                };
+                if config.hooks.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!("\nThere are no {} hooks to export.\n\n", scope(global))),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                let pack = HookPack {
+                    hooks: config.hooks.clone().into_iter().collect(),
+                };
+                let result = async {
+                    os.fs.write(&path, serde_json::to_string_pretty(&pack)?).await?;
+                    Ok::<_, ErrReport>(())
+                }
+                .await;
+                match result {
+                    Ok(()) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!(
+                            "\nExported {} {} hooks to {path}.\n\n",
+                            pack.hooks.len(),
+                            scope(global)
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                    Err(e) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nCannot export {} hooks to {path}: {e}\n\n", scope(global))),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Import {
+                path,
+                global,
+                overwrite,
+                merge,
+            } => {
+                let result = async {
+                    let contents = os.fs.read_to_string(&path).await?;
+                    Ok::<_, ErrReport>(serde_json::from_str::<HookPack>(&contents)?)
+                }
+                .await;
+                let pack = match result {
+                    Ok(pack) => pack,
+                    Err(e) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\nCannot import hooks from {path}: {e}\n\n")),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+
+                let config = if global {
+                    &context_manager.global_config
+                } else {
+                    &context_manager.profile_config
+                };
+                let conflicts = pack
+                    .hooks
+                    .keys()
+                    .filter(|name| config.hooks.contains_key(*name))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let overwrite = if conflicts.is_empty() || overwrite || merge {
+                    overwrite
+                } else {
+                    let prompt = format!(
+                        "\n{} {} hooks already exist: {}\nOverwrite them, keep the existing ones, or cancel? [o/k/c]: ",
+                        conflicts.len(),
+                        scope(global),
+                        conflicts.join(", ")
+                    );
+                    loop {
+                        match session.input_source.read_line(Some(&prompt)) {
+                            Ok(Some(answer)) => match answer.trim().to_lowercase().as_str() {
+                                "o" | "overwrite" => break true,
+                                "k" | "keep" => break false,
+                                "c" | "cancel" => {
+                                    execute!(session.stderr, style::Print("\nImport cancelled.\n\n"))?;
+                                    return Ok(ChatState::PromptUser {
+                                        skip_printing_tools: true,
+                                    });
+                                },
+                                _ => (),
+                            },
+                            _ => {
+                                execute!(session.stderr, style::Print("\nImport cancelled.\n\n"))?;
+                                return Ok(ChatState::PromptUser {
+                                    skip_printing_tools: true,
+                                });
+                            },
+                        }
+                    }
+                };
+
+                let import = context_manager
+                    .import_hooks(os, pack, global, overwrite)
+                    .await
+                    .map_err(map_chat_error)?;
+                let list = |names: &[String]| names.join(", ");
                 execute!(
                     session.stderr,
-                    style::SetAttribute(Attribute::Bold),
-                    style::SetForegroundColor(Color::Magenta),
-                    style::Print("\n🌍 global:\n"),
-                    style::SetAttribute(Attribute::Reset),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!(
+                        "\nImported {} hooks into the {} hooks.\n",
+                        import.added.len() + import.replaced.len(),
+                        scope(global)
+                    )),
+                    style::SetForegroundColor(Color::Reset)
                 )?;
+                for (label, names) in [
+                    ("Added", &import.added),
+                    ("Replaced", &import.replaced),
+                    ("Kept existing", &import.skipped),
+                ] {
+                    if !names.is_empty() {
+                        execute!(session.stderr, style::Print(format!("  {label}: {}\n", list(names))))?;
+                    }
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Test { name, global } => {
+                let config = if global {
+                    &context_manager.global_config
+                } else {
+                    &context_manager.profile_config
+                };
+                let Some(hook) = config.hooks.get(&name) else {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!(
+                            "\nCannot test {} hook '{name}': does not exist.\n\n",
+                            scope(global)
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                };
+                let mut hook = hook.clone();
+                hook.name = name;
+                hook.is_global = global;
+                let sandbox = hook.runs_sandboxed(context_manager.hook_executor.sandbox_hooks);
+                // The variables it would run with for the next prompt, those of the last one.
+                let hook_env = session.conversation.hook_env();
 
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.global_config.hooks,
-                    HookTrigger::ConversationStart,
-                )
-                .map_err(map_chat_error)?;
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.global_config.hooks,
-                    HookTrigger::PerPrompt,
-                )
-                .map_err(map_chat_error)?;
-
-                // Display profile hooks
                 execute!(
                     session.stderr,
-                    style::SetAttribute(Attribute::Bold),
-                    style::SetForegroundColor(Color::Magenta),
-                    style::Print(format!("\n👤 profile ({}):\n", context_manager.current_profile)),
-                    style::SetAttribute(Attribute::Reset),
+                    style::Print(format!(
+                        "\nRunning {} hook '{}' with a timeout of {} ms...\n\n",
+                        scope(global),
+                        hook.name,
+                        hook.timeout_ms
+                    )),
                 )?;
+                let start_time = Instant::now();
+                let result = run_hook_command(&hook, &hook_env, sandbox).await;
+                let duration = start_time.elapsed();
 
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.profile_config.hooks,
-                    HookTrigger::ConversationStart,
-                )
-                .map_err(map_chat_error)?;
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.profile_config.hooks,
-                    HookTrigger::PerPrompt,
-                )
-                .map_err(map_chat_error)?;
-                execute!(session.stderr, style::Print("\n"))?;
+                let output = match result {
+                    Ok(output) => output,
+                    Err(e) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!(
+                                "✗ Failed after {:.2} s: {e}\n\nNothing would be added to the next prompt.\n\n",
+                                duration.as_secs_f32()
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+
+                let exit_code = output
+                    .status
+                    .code()
+                    .map_or("terminated by a signal".to_string(), |code| code.to_string());
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(if output.status.success() {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    }),
+                    style::Print(format!(
+                        "{} Exit code {exit_code} after {:.2} s\n",
+                        if output.status.success() { "✓" } else { "✗" },
+                        duration.as_secs_f32()
+                    )),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                for (label, stream) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+                    let text = stream.to_str_lossy();
+                    if text.trim().is_empty() {
+                        continue;
+                    }
+                    queue!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(format!("\n{label}:\n")),
+                        style::SetAttribute(Attribute::Reset),
+                        style::Print(format!("{}\n", text.trim_end())),
+                    )?;
+                }
+
+                if !output.status.success() {
+                    execute!(
+                        session.stderr,
+                        style::Print("\nThe hook failed, so nothing would be added to the next prompt.\n\n"),
+                    )?;
+                } else if hook.disabled {
+                    execute!(
+                        session.stderr,
+                        style::Print(format!(
+                            "\nThe hook is disabled, so nothing would be added to the next prompt. Enable it with {}.\n\n",
+                            format!("/hooks enable {}{}", hook.name, if global { " --global" } else { "" })
+                                .dark_green()
+                        )),
+                    )?;
+                } else {
+                    let stdout = truncate_hook_output(&hook, &output.stdout.to_str_lossy());
+                    let (context, tokens) = if hook.trigger.adds_context() {
+                        let context = format_hook_context([&(hook.clone(), stdout)], hook.trigger.context_trigger());
+                        let tokens = format!(" (~{} tokens)", TokenCounter::count_tokens(&context));
+                        (context, tokens)
+                    } else {
+                        (stdout, String::new())
+                    };
+                    let heading = match hook.trigger {
+                        HookTrigger::ConversationStart => "Added once to the conversation context",
+                        HookTrigger::PerPrompt => "Added to the next prompt",
+                        HookTrigger::PerToolUse => "Added to the result of each tool",
+                        HookTrigger::PostResponse => "Shown after each response",
+                        HookTrigger::OnError => "Shown when a request or tool fails",
+                        HookTrigger::FileChange => "Added to the next prompts, until the watched files change",
+                    };
+                    execute!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(format!("\n{heading}{tokens}:\n")),
+                        style::SetAttribute(Attribute::Reset),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("{context}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+            },
+            Self::Log { name, global, limit } => {
+                let filter = HookFilter {
+                    global: global.then_some(true),
+                    ..Default::default()
+                };
+                let hooks: Vec<_> = context_manager
+                    .list_hooks(&filter)
+                    .into_iter()
+                    .filter(|(hook_name, _, _)| name.as_deref().is_none_or(|name| name == *hook_name))
+                    .collect();
+                if hooks.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo hooks match.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                let now = OffsetDateTime::now_utc();
+                queue!(session.stderr, style::Print("\n"))?;
+                for (hook_name, global, hook) in hooks {
+                    queue!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(hook_name),
+                        style::SetAttribute(Attribute::Reset),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!(" ({}, {})\n", scope(global), hook.trigger)),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    let mut runs = context_manager
+                        .hook_executor
+                        .log(global, hook_name)
+                        .take(limit)
+                        .peekable();
+                    if runs.peek().is_none() {
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print("  Not run in this session\n"),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                    }
+                    for run in runs {
+                        let (symbol, color) = match run.exit_code {
+                            Some(0) => ("✓", Color::Green),
+                            _ => ("✗", Color::Red),
+                        };
+                        let exit_code = run
+                            .exit_code
+                            .map_or("no exit code".to_string(), |code| format!("exit code {code}"));
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(color),
+                            style::Print(format!("  {symbol} ")),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print(format!(
+                                "{}, {exit_code} after {:.2} s\n",
+                                format_age(run.started_at, now),
+                                run.duration.as_secs_f32()
+                            )),
+                            style::SetForegroundColor(Color::DarkGrey),
+                        )?;
+                        for line in run.output.lines().filter(|line| !line.trim().is_empty()) {
+                            queue!(session.stderr, style::Print(format!("    {line}\n")))?;
+                        }
+                        queue!(session.stderr, style::SetForegroundColor(Color::Reset))?;
+                    }
+                    queue!(session.stderr, style::Print("\n"))?;
+                }
+                session.stderr.flush()?;
+            },
+            Self::List {
+                global,
+                trigger,
+                disabled,
+                refresh,
+            } => {
+                let filter = HookFilter {
+                    global: global.then_some(true),
+                    trigger: trigger.as_deref().map(HookTrigger::from_arg),
+                    disabled: disabled.then_some(true),
+                };
+                if refresh {
+                    let listed: Vec<(String, bool)> = context_manager
+                        .list_hooks(&filter)
+                        .into_iter()
+                        .map(|(name, global, _)| (name.to_string(), global))
+                        .collect();
+                    for (name, global) in listed {
+                        context_manager.hook_executor.clear_cache(global, &name);
+                    }
+                }
+                let hooks = context_manager.list_hooks(&filter);
+                if hooks.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo hooks match.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                let mut rows = vec![HOOK_TABLE_HEADER.map(str::to_string)];
+                rows.extend(hook_table_rows(&hooks, &context_manager.hook_executor));
+                let table = layout::table(&rows, terminal_width, 2);
+
+                queue!(session.stderr, style::Print("\n"))?;
+                for (index, lines) in table.iter().enumerate() {
+                    let text = lines.join("\n");
+                    if index == 0 {
+                        queue!(
+                            session.stderr,
+                            style::SetAttribute(Attribute::Bold),
+                            style::Print(text),
+                            style::SetAttribute(Attribute::Reset),
+                            style::Print("\n"),
+                        )?;
+                    } else if hooks[index - 1].2.disabled {
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(text),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print("\n"),
+                        )?;
+                    } else {
+                        queue!(session.stderr, style::Print(format!("{text}\n")))?;
+                    }
+                }
+                if hooks
+                    .iter()
+                    .any(|(_, _, hook)| hook.script.as_ref().is_some_and(|script| !script.trusted))
+                {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!(
+                            "\nHooks of untrusted scripts in {HOOK_SCRIPTS_DIR}/ don't run, check them and run {} to trust them.\n",
+                            "/hooks trust".dark_green()
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                if context_manager.prompt_hooks_off {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nThe 'per_prompt' hooks are off, /hooks on runs them again.\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                execute!(
+                    session.stderr,
+                    style::Print(format!(
+                        "\nUse {} to manage hooks.\n\n",
+                        "/hooks help".to_string().dark_green()
+                    )),
+                )?;
             },
         }
 
@@ -720,8 +1975,13 @@ pub fn print_hook_section(output: &mut impl Write, hooks: &HashMap<String, Hook>
     let section = match trigger {
         HookTrigger::ConversationStart => "On Session Start",
         HookTrigger::PerPrompt => "Per User Message",
+        HookTrigger::PerToolUse => "Per Tool Use",
+        HookTrigger::PostResponse => "After Each Response",
+        HookTrigger::OnError => "On Error",
+        HookTrigger::FileChange => "On File Change",
     };
-    let hooks: Vec<(&String, &Hook)> = hooks.iter().filter(|(_, h)| h.trigger == trigger).collect();
+    let mut hooks: Vec<(&String, &Hook)> = hooks.iter().filter(|(_, h)| h.trigger == trigger).collect();
+    hooks.sort_by(|(a, _), (b, _)| a.cmp(b));
 
     queue!(
         output,
@@ -754,6 +2014,82 @@ pub fn print_hook_section(output: &mut impl Write, hooks: &HashMap<String, Hook>
     Ok(())
 }
 
+const HOOK_TABLE_HEADER: [&str; 6] = ["Name", "Trigger", "Command", "Enabled", "Scope", "Last run"];
+/// Longest command shown in full by `/hooks list`.
+const MAX_COMMAND_CHARS: usize = 40;
+
+/// The cells of the `/hooks list` table for each hook, in [HOOK_TABLE_HEADER] order.
+fn hook_table_rows(hooks: &[(&str, bool, &Hook)], executor: &HookExecutor) -> Vec<[String; 6]> {
+    hooks
+        .iter()
+        .map(|(name, global, hook)| {
+            let command = hook.command_line().replace('\n', " ");
+            let command = match command.char_indices().nth(MAX_COMMAND_CHARS) {
+                Some((end, _)) => format!("{}…", &command[..end]),
+                None => command,
+            };
+            let mut last_run = match executor.last_run(*global, name) {
+                Some(HookRun::Succeeded(duration)) => format!("✓ {:.2} s", duration.as_secs_f32()),
+                Some(HookRun::Failed(err)) => format!("✗ {err}"),
+                None => "-".to_string(),
+            };
+            if let Some(age) = executor.cache_age(*global, name) {
+                last_run.push_str(&format!(", cached {} ago", format_cache_age(age)));
+            }
+            [
+                (*name).to_string(),
+                match (&hook.when, hook.watch.is_empty()) {
+                    (Some(when), _) => format!("{} when {when}", hook.trigger),
+                    (None, false) => format!("{} of {}", hook.trigger, hook.watch.join(", ")),
+                    (None, true) => hook.trigger.to_string(),
+                },
+                command,
+                match (&hook.script, hook.disabled) {
+                    (Some(HookScript { trusted: false, .. }), _) => "untrusted",
+                    (_, true) => "no",
+                    (_, false) => "yes",
+                }
+                .to_string(),
+                match (global, &hook.script) {
+                    (true, _) => "global",
+                    (false, Some(_)) => "workspace",
+                    (false, None) => "profile",
+                }
+                .to_string(),
+                last_run,
+            ]
+        })
+        .collect()
+}
+
+/// Checks that a `--when` condition is a glob.
+fn parse_when(when: &str) -> Result<String, String> {
+    match glob::Pattern::new(when) {
+        Ok(_) => Ok(when.to_string()),
+        Err(e) => Err(format!("invalid glob: {e}")),
+    }
+}
+
+/// Checks that a `--filter` starting with `.` is a valid JSON selector.
+fn parse_filter(filter: &str) -> Result<String, String> {
+    match JsonSelector::is_selector(filter) {
+        true => match JsonSelector::parse(filter) {
+            Ok(_) => Ok(filter.to_string()),
+            Err(e) => Err(format!("invalid JSON selector: {e}")),
+        },
+        false => Ok(filter.to_string()),
+    }
+}
+
+/// Describes the age of cached output, e.g. `42 s` or `5 min`.
+fn format_cache_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs @ 0..60 => format!("{secs} s"),
+        secs @ 60..3600 => format!("{} min", secs / 60),
+        secs => format!("{} h", secs / 3600),
+    }
+}
+
 pub fn map_chat_error(e: ErrReport) -> ChatError {
     ChatError::Custom(e.to_string().into())
 }
@@ -814,6 +2150,33 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_replace_hook() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        let hook = Hook::new_inline_hook(HookTrigger::ConversationStart, "echo test".to_string());
+
+        manager.add_hook(&os, "test_hook".to_string(), hook, false).await?;
+        manager.set_hook_disabled(&os, "test_hook", false, true).await?;
+
+        // The edited hook keeps being disabled.
+        let mut hook = manager.hook("test_hook", false)?.clone();
+        hook.trigger = HookTrigger::PerPrompt;
+        hook.disabled = false;
+        manager.replace_hook(&os, "test_hook", hook, false).await?;
+        manager.reload_config(&os).await?;
+        let hook = manager.hook("test_hook", false)?;
+        assert_eq!(hook.trigger, HookTrigger::PerPrompt);
+        assert!(hook.disabled);
+
+        // Hooks are only edited in the config they're in.
+        assert!(manager.hook("test_hook", true).is_err());
+        let hook = hook.clone();
+        assert!(manager.replace_hook(&os, "test_hook", hook, true).await.is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_set_hook_disabled() -> Result<()> {
         let os = Os::new().await.unwrap();
@@ -823,11 +2186,16 @@ mod tests {
         manager.add_hook(&os, "test_hook".to_string(), hook, false).await?;
 
         // Test disabling hook
-        manager.set_hook_disabled(&os, "test_hook", false, true).await?;
+        assert!(manager.set_hook_disabled(&os, "test_hook", false, true).await?);
+        assert!(manager.profile_config.hooks.get("test_hook").unwrap().disabled);
+        assert!(!manager.set_hook_disabled(&os, "test_hook", false, true).await?);
+
+        // The state is saved to the profile config
+        manager.reload_config(&os).await?;
         assert!(manager.profile_config.hooks.get("test_hook").unwrap().disabled);
 
         // Test enabling hook
-        manager.set_hook_disabled(&os, "test_hook", false, false).await?;
+        assert!(manager.set_hook_disabled(&os, "test_hook", false, false).await?);
         assert!(!manager.profile_config.hooks.get("test_hook").unwrap().disabled);
 
         // Test with non-existent hook
@@ -852,11 +2220,13 @@ mod tests {
         manager.add_hook(&os, "hook2".to_string(), hook2, false).await?;
 
         // Test disabling all hooks
-        manager.set_all_hooks_disabled(&os, false, true).await?;
+        manager.set_hook_disabled(&os, "hook1", false, true).await?;
+        assert_eq!(manager.set_all_hooks_disabled(&os, false, true).await?, 1);
         assert!(manager.profile_config.hooks.values().all(|h| h.disabled));
+        assert_eq!(manager.set_all_hooks_disabled(&os, false, true).await?, 0);
 
         // Test enabling all hooks
-        manager.set_all_hooks_disabled(&os, false, false).await?;
+        assert_eq!(manager.set_all_hooks_disabled(&os, false, false).await?, 2);
         assert!(manager.profile_config.hooks.values().all(|h| !h.disabled));
 
         Ok(())
@@ -873,12 +2243,140 @@ mod tests {
         manager.add_hook(&os, "hook2".to_string(), hook2, false).await?;
 
         // Run the hooks
-        let results = manager.run_hooks(&mut vec![]).await.unwrap();
+        let results = manager.run_hooks(&[], &mut vec![]).await.unwrap();
         assert_eq!(results.len(), 2); // Should include both hooks
 
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_skip_prompt_hooks() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        let hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "echo prompt".to_string());
+        manager.add_hook(&os, "prompt".to_string(), hook, false).await?;
+        let hook = Hook::new_inline_hook(HookTrigger::ConversationStart, "echo start".to_string());
+        manager.add_hook(&os, "start".to_string(), hook, false).await?;
+
+        // Only the next prompt is sent without them.
+        manager.skip_next_prompt_hooks = true;
+        let results = manager.run_hooks(&[], &mut vec![]).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.name, "start");
+        assert_eq!(manager.run_hooks(&[], &mut vec![]).await.unwrap().len(), 2);
+
+        manager.prompt_hooks_off = true;
+        assert_eq!(manager.run_hooks(&[], &mut vec![]).await.unwrap().len(), 1);
+        assert_eq!(manager.run_hooks(&[], &mut vec![]).await.unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_hooks() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        manager
+            .add_hook(
+                &os,
+                "kubectl".to_string(),
+                Hook::new_inline_hook(HookTrigger::PerPrompt, "kubectl config current-context".to_string()),
+                false,
+            )
+            .await?;
+
+        let pack = serde_json::to_string(&HookPack {
+            hooks: BTreeMap::from([
+                (
+                    "kubectl".to_string(),
+                    Hook::new_inline_hook(HookTrigger::PerPrompt, "kubectl get ns".to_string()),
+                ),
+                (
+                    "helm".to_string(),
+                    Hook::new_inline_hook(HookTrigger::ConversationStart, "helm list".to_string()),
+                ),
+            ]),
+        })?;
+        let pack = || serde_json::from_str::<HookPack>(&pack).unwrap();
+
+        let import = manager.import_hooks(&os, pack(), false, false).await?;
+        assert_eq!(import, HookImport {
+            added: vec!["helm".to_string()],
+            replaced: vec![],
+            skipped: vec!["kubectl".to_string()],
+        });
+        assert_eq!(
+            manager.profile_config.hooks["kubectl"].command_line(),
+            "kubectl config current-context"
+        );
+
+        let import = manager.import_hooks(&os, pack(), false, true).await?;
+        assert_eq!(import.replaced, vec!["helm".to_string(), "kubectl".to_string()]);
+        manager.reload_config(&os).await?;
+        assert_eq!(manager.profile_config.hooks["kubectl"].command_line(), "kubectl get ns");
+        assert!(manager.profile_config.hooks.contains_key("helm"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_hooks() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        let failing = Hook::new_inline_hook(HookTrigger::PerPrompt, "exit 1".to_string());
+        let mut disabled = Hook::new_inline_hook(HookTrigger::ConversationStart, "echo off".to_string());
+        disabled.disabled = true;
+        manager.add_hook(&os, "b_fails".to_string(), failing, false).await?;
+        manager.add_hook(&os, "a_off".to_string(), disabled, false).await?;
+        manager
+            .add_hook(
+                &os,
+                "greet".to_string(),
+                Hook::new_inline_hook(HookTrigger::ConversationStart, "echo hi".to_string()),
+                true,
+            )
+            .await?;
+
+        let names = |filter: HookFilter| {
+            manager
+                .list_hooks(&filter)
+                .into_iter()
+                .map(|(name, _, _)| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(HookFilter::default()), vec!["greet", "a_off", "b_fails"]);
+        assert_eq!(
+            names(HookFilter {
+                global: Some(false),
+                trigger: Some(HookTrigger::ConversationStart),
+                disabled: None,
+            }),
+            vec!["a_off"]
+        );
+        assert_eq!(
+            names(HookFilter {
+                disabled: Some(true),
+                ..Default::default()
+            }),
+            vec!["a_off"]
+        );
+
+        manager.run_hooks(&[], &mut vec![]).await?;
+        let rows = hook_table_rows(&manager.list_hooks(&HookFilter::default()), &manager.hook_executor);
+        assert_eq!(rows[0][..5], [
+            "greet",
+            "conversation_start",
+            "echo hi",
+            "yes",
+            "global"
+        ]);
+        assert!(rows[0][5].starts_with('✓'));
+        assert_eq!(rows[1][3..], ["no", "profile", "-"]);
+        assert_eq!(rows[2][1], "per_prompt");
+        assert!(rows[2][5].starts_with("✗ command returned non-zero exit code"));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_hooks_across_profiles() -> Result<()> {
         let os = Os::new().await.unwrap();
@@ -889,14 +2387,14 @@ mod tests {
         manager.add_hook(&os, "profile_hook".to_string(), hook1, false).await?;
         manager.add_hook(&os, "global_hook".to_string(), hook2, true).await?;
 
-        let results = manager.run_hooks(&mut vec![]).await.unwrap();
+        let results = manager.run_hooks(&[], &mut vec![]).await.unwrap();
         assert_eq!(results.len(), 2); // Should include both hooks
 
         // Create and switch to a new profile
         manager.create_profile(&os, "test_profile").await?;
         manager.switch_profile(&os, "test_profile").await?;
 
-        let results = manager.run_hooks(&mut vec![]).await.unwrap();
+        let results = manager.run_hooks(&[], &mut vec![]).await.unwrap();
         assert_eq!(results.len(), 1); // Should include global hook
         assert_eq!(results[0].0.name, "global_hook");
 
@@ -913,7 +2411,7 @@ mod tests {
         assert_eq!(hook.timeout_ms, DEFAULT_TIMEOUT_MS);
         assert_eq!(hook.max_output_size, DEFAULT_MAX_OUTPUT_SIZE);
         assert_eq!(hook.cache_ttl_seconds, DEFAULT_CACHE_TTL_SECONDS);
-        assert_eq!(hook.command, Some(command.to_string()));
+        assert_eq!(hook.command, Some(HookCommand::Single(command.to_string())));
         assert_eq!(hook.trigger, HookTrigger::PerPrompt);
         assert!(!hook.is_global);
     }
@@ -929,7 +2427,10 @@ mod tests {
 
         // First execution should run the command
         let mut output = vec![];
-        let results = executor.run_hooks(vec![&hook1, &hook2], &mut output).await.unwrap();
+        let results = executor
+            .run_hooks(vec![&hook1, &hook2], &[], &mut output)
+            .await
+            .unwrap();
 
         assert_eq!(results.len(), 2);
         assert!(results[0].1.contains("test1"));
@@ -938,7 +2439,10 @@ mod tests {
 
         // Second execution should use cache
         let mut output = Vec::new();
-        let results = executor.run_hooks(vec![&hook1, &hook2], &mut output).await.unwrap();
+        let results = executor
+            .run_hooks(vec![&hook1, &hook2], &[], &mut output)
+            .await
+            .unwrap();
 
         assert_eq!(results.len(), 2);
         assert!(results[0].1.contains("test1"));
@@ -959,7 +2463,10 @@ mod tests {
 
         // First execution should run the command
         let mut output = vec![];
-        let results = executor.run_hooks(vec![&hook1, &hook2], &mut output).await.unwrap();
+        let results = executor
+            .run_hooks(vec![&hook1, &hook2], &[], &mut output)
+            .await
+            .unwrap();
 
         assert_eq!(results.len(), 2);
         assert!(results[0].1.contains("test1"));
@@ -968,7 +2475,10 @@ mod tests {
 
         // Second execution should use cache
         let mut output = Vec::new();
-        let results = executor.run_hooks(vec![&hook1, &hook2], &mut output).await.unwrap();
+        let results = executor
+            .run_hooks(vec![&hook1, &hook2], &[], &mut output)
+            .await
+            .unwrap();
 
         assert_eq!(results.len(), 2);
         assert!(results[0].1.contains("test1"));
@@ -987,7 +2497,10 @@ mod tests {
 
         // First execution should run the command
         let mut output = Vec::new();
-        let results = executor.run_hooks(vec![&hook1, &hook2], &mut output).await.unwrap();
+        let results = executor
+            .run_hooks(vec![&hook1, &hook2], &[], &mut output)
+            .await
+            .unwrap();
 
         assert_eq!(results.len(), 2);
         assert!(results[0].1.contains("test1"));
@@ -996,7 +2509,10 @@ mod tests {
 
         // Second execution should use cache
         let mut output = Vec::new();
-        let results = executor.run_hooks(vec![&hook1, &hook2], &mut output).await.unwrap();
+        let results = executor
+            .run_hooks(vec![&hook1, &hook2], &[], &mut output)
+            .await
+            .unwrap();
 
         assert_eq!(results.len(), 2);
         assert!(results[0].1.contains("test1"));
@@ -1010,7 +2526,7 @@ mod tests {
         let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "sleep 2".to_string());
         hook.timeout_ms = 100; // Set very short timeout
 
-        let results = executor.run_hooks(vec![&hook], &mut vec![]).await.unwrap();
+        let results = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
 
         assert_eq!(results.len(), 0); // Should fail due to timeout
     }
@@ -1021,7 +2537,7 @@ mod tests {
         let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "echo 'test'".to_string());
         hook.disabled = true;
 
-        let results = executor.run_hooks(vec![&hook], &mut vec![]).await.unwrap();
+        let results = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
 
         assert_eq!(results.len(), 0); // Disabled hook should not run
     }
@@ -1033,14 +2549,14 @@ mod tests {
         hook.cache_ttl_seconds = 1;
 
         // First execution
-        let results1 = executor.run_hooks(vec![&hook], &mut vec![]).await.unwrap();
+        let results1 = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
         assert_eq!(results1.len(), 1);
 
         // Wait for cache to expire
         sleep(Duration::from_millis(1001)).await;
 
         // Second execution should run command again
-        let results2 = executor.run_hooks(vec![&hook], &mut vec![]).await.unwrap();
+        let results2 = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
         assert_eq!(results2.len(), 1);
     }
 
@@ -1051,12 +2567,95 @@ mod tests {
 
         let cached_hook = CachedHook {
             output: "test output".to_string(),
+            cached_at: Instant::now(),
             expiry: None,
         };
 
         executor.insert_cache(&hook, cached_hook.clone());
 
         assert_eq!(executor.get_cache(&hook), Some("test output".to_string()));
+        assert!(executor.cache_age(hook.is_global, &hook.name).is_some());
+
+        assert!(executor.clear_cache(hook.is_global, &hook.name));
+        assert_eq!(executor.get_cache(&hook), None);
+        assert_eq!(executor.cache_age(hook.is_global, &hook.name), None);
+        assert!(!executor.clear_cache(hook.is_global, &hook.name));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = dir.path().join("*.sql").to_string_lossy().to_string();
+        let mut executor = HookExecutor::new();
+        let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "echo dumped".to_string());
+        hook.name = "schema".to_string();
+        hook.when = Some(pattern);
+
+        // The number of results, and whether the hook ran.
+        async fn run(executor: &mut HookExecutor, hook: &Hook) -> (usize, bool) {
+            let mut output = Vec::new();
+            let results = executor.run_hooks(vec![hook], &[], &mut output).await.unwrap();
+            (results.len(), !output.is_empty())
+        }
+
+        // Runs the first time, then reuses its output until a matching file changes.
+        assert_eq!(run(&mut executor, &hook).await, (1, true));
+        assert_eq!(run(&mut executor, &hook).await, (1, false));
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        assert_eq!(run(&mut executor, &hook).await, (1, false));
+        std::fs::write(dir.path().join("001.sql"), "create table t;").unwrap();
+        assert_eq!(run(&mut executor, &hook).await, (1, true));
+        assert_eq!(run(&mut executor, &hook).await, (1, false));
+
+        // Event hooks are skipped instead.
+        hook.trigger = HookTrigger::PostResponse;
+        hook.name = "notify".to_string();
+        assert_eq!(run(&mut executor, &hook).await, (1, true));
+        assert_eq!(run(&mut executor, &hook).await, (0, false));
+    }
+
+    #[tokio::test]
+    async fn test_file_change_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("version.txt");
+        std::fs::write(&file, "1").unwrap();
+        let mut executor = HookExecutor::new();
+        let command = format!("cat {}", shlex::try_quote(&file.to_string_lossy()).unwrap());
+        let mut hook = Hook::new_inline_hook(HookTrigger::FileChange, command);
+        hook.name = "version".to_string();
+        hook.watch = vec![dir.path().join("*.txt").to_string_lossy().to_string()];
+
+        // The output, and whether the hook ran with the prompt.
+        async fn run(executor: &mut HookExecutor, hook: &Hook) -> (String, bool) {
+            let mut output = Vec::new();
+            let mut results = executor.run_hooks(vec![hook], &[], &mut output).await.unwrap();
+            (results.remove(0).1, !output.is_empty())
+        }
+
+        assert_eq!(run(&mut executor, &hook).await, ("1".to_string(), true));
+        assert_eq!(run(&mut executor, &hook).await, ("1".to_string(), false));
+
+        // The output is refreshed in the background once the file changes.
+        std::fs::write(&file, "2").unwrap();
+        let watcher = executor.watcher.clone().unwrap();
+        for _ in 0..50 {
+            if watcher.output(&hook).as_deref() == Some("2") {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(run(&mut executor, &hook).await, ("2".to_string(), false));
+
+        // A hook that's no longer configured stops being refreshed.
+        executor.retain_watched(&[]);
+        assert!(watcher.output(&hook).is_none());
+    }
+
+    #[test]
+    fn test_format_cache_age() {
+        assert_eq!(format_cache_age(Duration::from_secs(42)), "42 s");
+        assert_eq!(format_cache_age(Duration::from_secs(300)), "5 min");
+        assert_eq!(format_cache_age(Duration::from_secs(7200)), "2 h");
     }
 
     #[test]
@@ -1066,6 +2665,7 @@ mod tests {
 
         let cached_hook = CachedHook {
             output: "test output".to_string(),
+            cached_at: Instant::now(),
             expiry: Some(Instant::now()),
         };
 
@@ -1089,11 +2689,148 @@ mod tests {
         let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, command.to_string());
         hook.max_output_size = 100;
 
-        let results = executor.run_hooks(vec![&hook], &mut vec![]).await.unwrap();
+        let results = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
 
         assert!(results[0].1.len() <= hook.max_output_size + " ... truncated".len());
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_hook_command() {
+        let hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "echo out; echo err >&2; exit 3".to_string());
+        let output = run_hook_command(&hook, &[], false).await.unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout.to_str_lossy().trim(), "out");
+        assert_eq!(output.stderr.to_str_lossy().trim(), "err");
+
+        let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "sleep 1".to_string());
+        hook.timeout_ms = 10;
+        assert!(run_hook_command(&hook, &[], false).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_hook_command_limits_output() {
+        // Output past the limit is drained, so the command still finishes.
+        let mut hook = Hook::new_inline_hook(
+            HookTrigger::PerPrompt,
+            "head -c 1000000 /dev/zero; echo done >&2".to_string(),
+        );
+        hook.max_output_size = 100;
+        let output = run_hook_command(&hook, &[], false).await.unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 101);
+        assert_eq!(output.stderr.to_str_lossy().trim(), "done");
+
+        let hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "cat".to_string());
+        assert!(run_hook_command(&hook, &[], false).await.unwrap().stdout.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_hook_pipeline() {
+        let mut hook: Hook = serde_json::from_value(serde_json::json!({
+            "trigger": "per_prompt",
+            "type": "inline",
+            "command": ["echo one", "echo two | tr a-z A-Z"]
+        }))
+        .unwrap();
+        assert_eq!(hook.command_line(), "echo one && echo two | tr a-z A-Z");
+        let output = run_hook_command(&hook, &[], false).await.unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.to_str_lossy(), "one\nTWO\n");
+
+        // Piped, each command reads the output of the one before it.
+        hook.pipe = true;
+        hook.command = Some(vec!["echo one".to_string(), "tr a-z A-Z".to_string(), "rev".to_string()].into());
+        assert_eq!(hook.command_line(), "echo one | tr a-z A-Z | rev");
+        assert_eq!(
+            run_hook_command(&hook, &[], false).await.unwrap().stdout.to_str_lossy(),
+            "ENO\n"
+        );
+
+        // A failing command stops the pipeline.
+        hook.pipe = false;
+        hook.command = Some(vec!["echo one".to_string(), "exit 2".to_string(), "echo three".to_string()].into());
+        let output = run_hook_command(&hook, &[], false).await.unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(output.stdout.to_str_lossy(), "one\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_hook_filter() {
+        let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "seq 1 1000".to_string());
+        hook.max_output_size = 10;
+        hook.filter = Some("tail -n 2".to_string());
+        let output = run_hook_command(&hook, &[], false).await.unwrap();
+        // The filter reads all of the output, only its own output is truncated.
+        assert_eq!(output.stdout.to_str_lossy(), "999\n1000\n");
+
+        hook.command = Some(r#"echo '{"pods": [{"name": "api"}, {"name": "web"}]}'"#.to_string().into());
+        hook.filter = Some(".pods[].name".to_string());
+        let output = run_hook_command(&hook, &[], false).await.unwrap();
+        assert_eq!(output.stdout.to_str_lossy(), "api\nweb");
+
+        hook.command = Some("echo plain".to_string().into());
+        assert!(run_hook_command(&hook, &[], false).await.is_err());
+        assert!(parse_filter(".pods[").is_err());
+        assert!(parse_filter("./trim.sh").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_event_hooks_with_env() {
+        let mut executor = HookExecutor::new();
+        let mut hook = Hook::new_inline_hook(HookTrigger::PerToolUse, "echo $Q_TOOL_NAME".to_string());
+        hook.cache_ttl_seconds = 60;
+
+        let env = [("Q_TOOL_NAME", "fs_read".to_string())];
+        let results = executor.run_hooks(vec![&hook], &env, &mut vec![]).await.unwrap();
+        assert_eq!(results[0].1.trim(), "fs_read");
+
+        // Hooks of events run again for each event, whatever their cache TTL.
+        let env = [("Q_TOOL_NAME", "fs_write".to_string())];
+        let results = executor.run_hooks(vec![&hook], &env, &mut vec![]).await.unwrap();
+        assert_eq!(results[0].1.trim(), "fs_write");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hook_log() {
+        let mut executor = HookExecutor::new();
+        let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "echo out; echo err >&2; exit 3".to_string());
+        hook.name = "flaky".to_string();
+
+        let results = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
+        assert!(results.is_empty());
+        let run = executor.log(false, "flaky").next().unwrap();
+        assert_eq!(run.exit_code, Some(3));
+        assert_eq!(run.output, "out\nstderr: err");
+
+        // Only the last runs are kept, newest first.
+        hook.command = Some(HookCommand::Single("echo $Q_RUN".to_string()));
+        for i in 0..HOOK_LOG_SIZE {
+            let env = [("Q_RUN", i.to_string())];
+            executor.run_hooks(vec![&hook], &env, &mut vec![]).await.unwrap();
+        }
+        let runs: Vec<_> = executor.log(false, "flaky").collect();
+        assert_eq!(runs.len(), HOOK_LOG_SIZE);
+        assert_eq!(runs[0].output, (HOOK_LOG_SIZE - 1).to_string());
+        assert!(runs.iter().all(|run| run.exit_code == Some(0)));
+        assert_eq!(executor.log(true, "flaky").count(), 0);
+    }
+
+    #[test]
+    fn test_trigger_names() {
+        for trigger in HookTrigger::ALL {
+            assert_eq!(HookTrigger::from_arg(&trigger.to_string()), trigger);
+        }
+        assert!(!HookTrigger::PerToolUse.runs_with_prompt());
+        assert!(HookTrigger::PerToolUse.adds_context());
+        assert!(!HookTrigger::OnError.adds_context());
+    }
+
     #[tokio::test]
     async fn test_os_specific_command_execution() {
         let mut executor = HookExecutor::new();
@@ -1107,7 +2844,7 @@ mod tests {
 
         let hook = Hook::new_inline_hook(HookTrigger::PerPrompt, command.to_string());
 
-        let results = executor.run_hooks(vec![&hook], &mut vec![]).await.unwrap();
+        let results = executor.run_hooks(vec![&hook], &[], &mut vec![]).await.unwrap();
 
         assert_eq!(results.len(), 1, "Command execution should succeed");
 
@@ -0,0 +1,78 @@
+//! `/login`, `/logout` and `/whoami`, the commands of `q login`, `q logout` and `q whoami` run
+//! without leaving the chat. Requests read the login token as they're sent, so the conversation
+//! continues with the account logged in to.
+
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::cli::user::{
+    LoginArgs,
+    WhoamiArgs,
+};
+use crate::os::Os;
+
+pub async fn login(args: LoginArgs, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    if crate::auth::is_logged_in(&mut os.database).await {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Red),
+            style::Print("\nAlready logged in. Run /logout first to log in with another account.\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        return Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        });
+    }
+
+    match args.execute(os).await {
+        Ok(_) => execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print("\nThe conversation continues with the account you logged in to.\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?,
+        Err(err) => execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Red),
+            style::Print(format!("\nFailed to log in: {err}\n\n")),
+            style::SetForegroundColor(Color::Reset)
+        )?,
+    }
+    Ok(ChatState::PromptUser {
+        skip_printing_tools: true,
+    })
+}
+
+pub async fn logout(os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    if let Err(err) = crate::auth::logout(&mut os.database).await {
+        return Err(ChatError::Custom(err.to_string().into()));
+    }
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Green),
+        style::Print("\nYou are now logged out. Run /login to log in again and continue the conversation.\n\n"),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    Ok(ChatState::PromptUser {
+        skip_printing_tools: true,
+    })
+}
+
+pub async fn whoami(args: WhoamiArgs, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    execute!(session.stderr, style::Print("\n"))?;
+    if let Err(err) = args.execute(os).await {
+        return Err(ChatError::Custom(err.to_string().into()));
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+    Ok(ChatState::PromptUser {
+        skip_printing_tools: true,
+    })
+}
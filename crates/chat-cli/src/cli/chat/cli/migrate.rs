@@ -0,0 +1,154 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::cli::clear::confirm;
+use crate::cli::chat::migrate::{
+    self,
+    FileMigration,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Finds commands, flags and hook settings that were removed or renamed, explains what replaced them
+and offers to rewrite the files that use them.
+
+Without paths, the context configs of every profile are checked, including the hooks' settings and
+commands. Given paths, such as scripts running q chat or recipes, are checked for removed commands and
+flags, and .json files are checked as context configs.
+
+Notes
+• /acceptall is now /tools trust-all, and q chat --accept-all is now --trust-all-tools
+• Hook settings written in camelCase, e.g. timeoutMs, were ignored in favor of the defaults"
+)]
+pub struct MigrateArgs {
+    /// Scripts, recipes or context configs to check instead of the context configs of the profiles
+    paths: Vec<PathBuf>,
+    /// Rewrite the files without asking
+    #[arg(short, long)]
+    yes: bool,
+}
+
+impl MigrateArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let migrations = match migrate::scan(os, &self.paths).await {
+            Ok(migrations) => migrations,
+            Err(e) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {e:#}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+        };
+
+        if migrations.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print("\nNothing to migrate, no removed commands, flags or hook settings are used.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        execute!(session.stderr, style::Print("\n"))?;
+        for migration in &migrations {
+            print_migration(session, migration)?;
+        }
+
+        let question = match migrations.len() {
+            1 => "This will rewrite 1 file.".to_string(),
+            n => format!("This will rewrite {n} files."),
+        };
+        if !self.yes && !confirm(session, &question)? {
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let mut rewritten = 0;
+        for migration in &migrations {
+            match os.fs.write(&migration.path, &migration.migrated).await {
+                Ok(()) => rewritten += 1,
+                Err(e) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("Failed to write {}: {e}\n", migration.path.display())),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            }
+        }
+        // Use the rewritten hooks from now on.
+        if let Some(context_manager) = session.conversation.context_manager.as_mut() {
+            if let Err(e) = context_manager.reload_config(os).await {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("Failed to reload the context config: {e}\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            }
+        }
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\nRewrote {rewritten} of {} files.\n\n", migrations.len())),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+fn print_migration(session: &mut ChatSession, migration: &FileMigration) -> Result<(), ChatError> {
+    execute!(
+        session.stderr,
+        style::SetAttribute(style::Attribute::Bold),
+        style::Print(format!("{}\n", migration.path.display())),
+        style::SetAttribute(style::Attribute::Reset),
+    )?;
+    for finding in &migration.findings {
+        let hook = match &finding.hook {
+            Some(hook) => format!(" in hook {hook}"),
+            None => String::new(),
+        };
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!("  {}", finding.legacy)),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(" → "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&finding.replacement),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!("{hook}\n")),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("    {}\n", finding.explanation)),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+    Ok(())
+}
@@ -1,31 +1,87 @@
+pub mod analytics;
+pub mod bookmark;
+pub mod checkpoint;
 pub mod clear;
 pub mod compact;
+pub mod config;
 pub mod context;
+pub mod copy;
+pub mod deps;
 pub mod editor;
+pub mod explain;
+pub mod export;
+pub mod gen_docs;
+pub mod gen_tests;
+pub mod history;
 pub mod hooks;
 pub mod knowledge;
+pub mod login;
 pub mod mcp;
+pub mod migrate;
 pub mod model;
 pub mod persist;
+pub mod pin;
+pub mod pr_describe;
 pub mod profile;
 pub mod prompts;
+pub mod quit;
+pub mod refactor;
+pub mod replay;
+pub mod resume;
+pub mod security_review;
+pub mod sessions;
+pub mod settings;
+pub mod speak;
 pub mod subscribe;
+pub mod tag;
 pub mod tools;
+pub mod undo;
 pub mod usage;
+pub mod var;
+pub mod voice;
 
+use analytics::AnalyticsSubcommand;
+use bookmark::BookmarkSubcommand;
+use checkpoint::CheckpointSubcommand;
 use clap::Parser;
 use clear::ClearArgs;
 use compact::CompactArgs;
+use config::ConfigSubcommand;
 use context::ContextSubcommand;
+use copy::CopyArgs;
+use deps::DepsSubcommand;
 use editor::EditorArgs;
+use explain::ExplainArgs;
+use export::ExportArgs;
+use gen_docs::GenDocsArgs;
+use gen_tests::GenTestsArgs;
+use history::HistoryArgs;
 use hooks::HooksArgs;
 use knowledge::KnowledgeSubcommand;
 use mcp::McpArgs;
+use migrate::MigrateArgs;
 use model::ModelArgs;
 use persist::PersistSubcommand;
-use profile::ProfileSubcommand;
+use pin::PinArgs;
+use pr_describe::PrDescribeArgs;
+use profile::ProfileArgs;
 use prompts::PromptsArgs;
+use quit::QuitArgs;
+use refactor::RefactorArgs;
+use replay::ReplayArgs;
+use resume::ResumeArgs;
+use security_review::SecurityReviewArgs;
+use sessions::SessionsSubcommand;
+use settings::SettingsSubcommand;
+use speak::SpeakSubcommand;
+use tag::TagArgs;
 use tools::ToolsArgs;
+use undo::UndoArgs;
+use var::{
+    SetSubcommand,
+    VarSubcommand,
+};
+use voice::VoiceArgs;
 
 use crate::cli::chat::cli::subscribe::SubscribeArgs;
 use crate::cli::chat::cli::usage::UsageArgs;
@@ -35,7 +91,10 @@ use crate::cli::chat::{
     ChatState,
     EXTRA_HELP,
 };
-use crate::cli::issue;
+use crate::cli::{
+    issue,
+    user,
+};
 use crate::os::Os;
 
 /// q (Amazon Q Chat)
@@ -44,28 +103,43 @@ use crate::os::Os;
 pub enum SlashCommand {
     /// Quit the application
     #[command(aliases = ["q", "exit"])]
-    Quit,
-    /// Clear the conversation history
+    Quit(QuitArgs),
+    /// Clear the conversation history, the screen or tool permissions
     Clear(ClearArgs),
-    /// Manage profiles
-    #[command(subcommand)]
-    Profile(ProfileSubcommand),
+    /// Manage profiles, or switch to one picked from a list
+    Profile(ProfileArgs),
     /// Manage context files for the chat session
     #[command(subcommand)]
     Context(ContextSubcommand),
+    /// Inspect the configuration in use
+    #[command(subcommand)]
+    Config(ConfigSubcommand),
+    /// View and change the settings of the current profile, such as the language of responses
+    #[command(subcommand)]
+    Settings(SettingsSubcommand),
     /// (Beta) Manage knowledge base for persistent context storage. Requires "q settings
     /// chat.enableKnowledge true"
     #[command(subcommand, hide = true)]
     Knowledge(KnowledgeSubcommand),
+    /// Copy the last response to the clipboard
+    Copy(CopyArgs),
     /// Open $EDITOR (defaults to vi) to compose a prompt
     #[command(name = "editor")]
     PromptEditor(EditorArgs),
     /// Summarize the conversation to free up context space
     Compact(CompactArgs),
+    /// Remove the last turns from the conversation
+    Undo(UndoArgs),
     /// View and manage tools and permissions
     Tools(ToolsArgs),
     /// Create a new Github issue or make a feature request
     Issue(issue::IssueArgs),
+    /// Log in to Amazon Q without leaving the chat
+    Login(user::LoginArgs),
+    /// Log out of Amazon Q
+    Logout,
+    /// Show the account logged in with and when its token expires
+    Whoami(user::WhoamiArgs),
     /// View and retrieve prompts
     Prompts(PromptsArgs),
     /// View and manage context hooks
@@ -74,12 +148,65 @@ pub enum SlashCommand {
     Usage(UsageArgs),
     /// See mcp server loaded
     Mcp(McpArgs),
+    /// Find and rewrite removed commands, flags and hook settings in configs and scripts
+    Migrate(MigrateArgs),
     /// Select a model for the current conversation session
     Model(ModelArgs),
     /// Upgrade to a Q Developer Pro subscription for increased query limits
     Subscribe(SubscribeArgs),
     #[command(flatten)]
     Persist(PersistSubcommand),
+    /// Resume a previous session
+    Resume(ResumeArgs),
+    /// List stored sessions
+    #[command(subcommand)]
+    Sessions(SessionsSubcommand),
+    /// Tag the conversation to organize stored sessions by project or topic
+    Tag(TagArgs),
+    /// List and search earlier turns
+    History(HistoryArgs),
+    /// Keep a turn in the context, even after /compact
+    Pin(PinArgs),
+    /// Bookmark turns of a long conversation and print it again from a bookmark
+    #[command(subcommand)]
+    Bookmark(BookmarkSubcommand),
+    /// Rewind the conversation and files to before a tool ran
+    #[command(subcommand)]
+    Checkpoint(CheckpointSubcommand),
+    /// Export the conversation as a Markdown or JSON transcript
+    Export(ExportArgs),
+    /// Render a conversation again from its transcript, with its original timing sped up
+    Replay(ReplayArgs),
+    /// Apply a change to every file matching a glob, a batch of files at a time
+    Refactor(RefactorArgs),
+    /// Explain a file or a range of its lines, such as src/main.rs:10-40
+    Explain(ExplainArgs),
+    /// Write tests for a file or directory, optionally targeting code a coverage report marks as
+    /// untested
+    GenTests(GenTestsArgs),
+    /// Write or update the doc comments of a public API, and the README sections describing it
+    GenDocs(GenDocsArgs),
+    /// Review files for security vulnerabilities and report the findings, optionally as SARIF
+    SecurityReview(SecurityReviewArgs),
+    /// Review outdated dependencies and advisories, and plan or apply their upgrades
+    #[command(subcommand)]
+    Deps(DepsSubcommand),
+    /// Draft a pull request description for the current branch and optionally create it
+    PrDescribe(PrDescribeArgs),
+    /// Set and list prompt variables, referenced in prompts as {{name}}
+    #[command(subcommand)]
+    Var(VarSubcommand),
+    /// Set a prompt variable with /set var <name> <value>
+    #[command(subcommand)]
+    Set(SetSubcommand),
+    /// Show usage counts kept on this machine when local analytics are enabled
+    #[command(subcommand)]
+    Analytics(AnalyticsSubcommand),
+    /// Dictate a prompt, transcribed for review before sending
+    Voice(VoiceArgs),
+    /// Read responses aloud with the system's text-to-speech engine
+    #[command(subcommand)]
+    Speak(SpeakSubcommand),
     // #[command(flatten)]
     // Root(RootSubcommand),
 }
@@ -87,16 +214,20 @@ pub enum SlashCommand {
 impl SlashCommand {
     pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         match self {
-            Self::Quit => Ok(ChatState::Exit),
+            Self::Quit(args) => args.execute(os, session).await,
             Self::Clear(args) => args.execute(session).await,
-            Self::Profile(subcommand) => subcommand.execute(os, session).await,
+            Self::Profile(args) => args.execute(os, session).await,
             Self::Context(args) => args.execute(os, session).await,
+            Self::Config(subcommand) => subcommand.execute(os, session).await,
+            Self::Settings(subcommand) => subcommand.execute(os, session).await,
             Self::Knowledge(subcommand) => subcommand.execute(os, session).await,
+            Self::Copy(args) => args.execute(os, session).await,
             Self::PromptEditor(args) => args.execute(session).await,
             Self::Compact(args) => args.execute(os, session).await,
-            Self::Tools(args) => args.execute(session).await,
+            Self::Undo(args) => args.execute(os, session).await,
+            Self::Tools(args) => args.execute(os, session).await,
             Self::Issue(args) => {
-                if let Err(err) = args.execute(os).await {
+                if let Err(err) = args.execute_with_last_error(os, session.last_error.as_deref()).await {
                     return Err(ChatError::Custom(err.to_string().into()));
                 }
 
@@ -104,13 +235,38 @@ impl SlashCommand {
                     skip_printing_tools: true,
                 })
             },
+            Self::Login(args) => login::login(args, os, session).await,
+            Self::Logout => login::logout(os, session).await,
+            Self::Whoami(args) => login::whoami(args, os, session).await,
             Self::Prompts(args) => args.execute(session).await,
             Self::Hooks(args) => args.execute(os, session).await,
             Self::Usage(args) => args.execute(os, session).await,
             Self::Mcp(args) => args.execute(session).await,
+            Self::Migrate(args) => args.execute(os, session).await,
             Self::Model(args) => args.execute(session).await,
             Self::Subscribe(args) => args.execute(os, session).await,
             Self::Persist(subcommand) => subcommand.execute(os, session).await,
+            Self::Resume(args) => args.execute(os, session).await,
+            Self::Sessions(subcommand) => subcommand.execute(os, session).await,
+            Self::Tag(args) => args.execute(os, session).await,
+            Self::History(args) => args.execute(os, session).await,
+            Self::Checkpoint(subcommand) => subcommand.execute(os, session).await,
+            Self::Export(args) => args.execute(os, session).await,
+            Self::Replay(args) => args.execute(os, session).await,
+            Self::Pin(args) => args.execute(session).await,
+            Self::Bookmark(subcommand) => subcommand.execute(session).await,
+            Self::Refactor(args) => args.execute(os, session).await,
+            Self::Explain(args) => args.execute(os, session).await,
+            Self::GenTests(args) => args.execute(os, session).await,
+            Self::GenDocs(args) => args.execute(os, session).await,
+            Self::SecurityReview(args) => args.execute(os, session).await,
+            Self::Deps(subcommand) => subcommand.execute(os, session).await,
+            Self::PrDescribe(args) => args.execute(os, session).await,
+            Self::Var(subcommand) => subcommand.execute(session).await,
+            Self::Set(subcommand) => subcommand.execute(session).await,
+            Self::Analytics(subcommand) => subcommand.execute(os, session).await,
+            Self::Voice(args) => args.execute(os, session).await,
+            Self::Speak(subcommand) => subcommand.execute(os, session).await,
             // Self::Root(subcommand) => {
             //     if let Err(err) = subcommand.execute(os, database, telemetry).await {
             //         return Err(ChatError::Custom(err.to_string().into()));
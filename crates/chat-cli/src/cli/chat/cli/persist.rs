@@ -5,14 +5,144 @@ use crossterm::style::{
     Attribute,
     Color,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
 use crate::cli::ConversationState;
+use crate::cli::chat::cli::clear::confirm;
+use crate::cli::chat::import::{
+    ImportFormat,
+    ImportedConversation,
+};
+use crate::cli::chat::tools::ToolPermissions;
+use crate::cli::chat::util::truncate_safe;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
+    store,
 };
 use crate::os::Os;
+use crate::util::encryption;
+
+/// The file format used by `/save` and `/load`.
+///
+/// Tool permissions live on the session rather than the conversation, so they're stored alongside
+/// it, and `/load` asks before restoring those that trust tools. Files saved before permissions
+/// were included simply don't have the field.
+#[derive(Serialize)]
+pub struct SavedConversation<'a> {
+    #[serde(flatten)]
+    pub conversation: &'a ConversationState,
+    pub tool_permissions: &'a ToolPermissions,
+}
+
+impl<'a> SavedConversation<'a> {
+    pub fn new(session: &'a ChatSession) -> Self {
+        Self {
+            conversation: &session.conversation,
+            tool_permissions: &session.tool_permissions,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoadedConversation {
+    #[serde(flatten)]
+    pub conversation: ConversationState,
+    #[serde(default)]
+    pub tool_permissions: Option<ToolPermissions>,
+}
+
+impl LoadedConversation {
+    /// Parses the contents of a file written by `/save`, which may be encrypted and compressed.
+    pub async fn parse(os: &Os, contents: Vec<u8>) -> eyre::Result<Self> {
+        let contents = store::decompress(encryption::decrypt(os, contents).await?)?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    /// Replaces the session's conversation, keeping its current tools.
+    pub async fn restore(self, os: &Os, session: &mut ChatSession) {
+        let mut new_state = self.conversation;
+        new_state.reload_serialized_state(os).await;
+        std::mem::swap(&mut new_state.tool_manager, &mut session.conversation.tool_manager);
+        session.conversation = new_state;
+        if let Some(tool_permissions) = self.tool_permissions {
+            session.tool_permissions = tool_permissions;
+        }
+    }
+}
+
+/// The tools `permissions` run without confirmation, or `None` if they don't trust any.
+fn trusted_tools(permissions: &ToolPermissions) -> Option<String> {
+    if permissions.trust_all {
+        return Some("all tools".to_string());
+    }
+    let mut tools: Vec<&str> = permissions
+        .permissions
+        .iter()
+        .filter(|(_, permission)| permission.trusted)
+        .map(|(tool_name, _)| tool_name.as_str())
+        .chain(permissions.pending_trusted_tools.iter().map(String::as_str))
+        .collect();
+    tools.sort_unstable();
+    tools.dedup();
+    (!tools.is_empty()).then(|| tools.join(", "))
+}
+
+/// Restores the tool permissions of a file loaded with `/load`. Since the file may come from
+/// anyone, permissions that trust tools are shown and only restored once confirmed.
+fn restore_tool_permissions(session: &mut ChatSession, permissions: ToolPermissions) -> Result<(), ChatError> {
+    let Some(trusted) = trusted_tools(&permissions) else {
+        session.tool_permissions = permissions;
+        return Ok(());
+    };
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Yellow),
+        style::Print(format!("The file trusts {trusted} to run without confirmation.")),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    let (message, color) = if confirm(session, "Trust them in this session too?")? {
+        session.tool_permissions = permissions;
+        ("Restored the tool permissions of the file.", Color::Green)
+    } else {
+        ("Kept the current tool permissions.", Color::Yellow)
+    };
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(color),
+        style::Print(format!("{message}\n\n")),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    Ok(())
+}
+
+/// Where and when the conversation was last saved or loaded, used to detect unsaved changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavePoint {
+    pub path: String,
+    /// The [generation](ConversationState::generation) of the conversation at the time.
+    pub generation: u64,
+}
+
+/// Writes the session's conversation to `path` in the `/save` format, compressed with zstd if the
+/// path ends with `.zst` and encrypted if `storage.encrypt` is on.
+pub async fn save_conversation(os: &Os, session: &mut ChatSession, path: &str) -> eyre::Result<()> {
+    let mut contents = serde_json::to_vec_pretty(&SavedConversation::new(session))?;
+    if path.ends_with(".zst") {
+        contents = store::compress(&contents)?;
+    }
+    let contents = encryption::encrypt_if_enabled(os, contents).await?;
+    os.fs.write(path, contents).await?;
+    session.last_save = Some(SavePoint {
+        path: path.to_string(),
+        generation: session.conversation.generation(),
+    });
+    Ok(())
+}
 
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Subcommand)]
@@ -24,7 +154,12 @@ pub enum PersistSubcommand {
         force: bool,
     },
     /// Load a previous conversation
-    Load { path: String },
+    Load {
+        path: String,
+        /// Import a conversation exported from another assistant instead of one saved with /save
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+    },
 }
 
 impl PersistSubcommand {
@@ -51,7 +186,6 @@ impl PersistSubcommand {
 
         match self {
             Self::Save { path, force } => {
-                let contents = tri!(serde_json::to_string_pretty(&session.conversation), "export to", &path);
                 if os.fs.exists(&path) && !force {
                     execute!(
                         session.stderr,
@@ -66,7 +200,7 @@ impl PersistSubcommand {
                         skip_printing_tools: true,
                     });
                 }
-                tri!(os.fs.write(&path, contents).await, "export to", &path);
+                tri!(save_conversation(os, session, &path).await, "export to", &path);
 
                 execute!(
                     session.stderr,
@@ -75,14 +209,69 @@ impl PersistSubcommand {
                     style::SetAttribute(Attribute::Reset)
                 )?;
             },
-            Self::Load { path } => {
+            Self::Load {
+                path,
+                format: Some(format),
+            } => {
+                let contents = tri!(os.fs.read(&path).await, "import from", &path);
+                let imported = tri!(ImportedConversation::parse(format, &contents), "import from", &path);
+                if imported.turns.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to import from {path}: no exchanges were found\n\n")),
+                        style::SetAttribute(Attribute::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                let history = imported.history();
+                for ((prompt, _), (_, response)) in imported.turns.iter().zip(&history) {
+                    session.conversation.append_user_transcript(prompt);
+                    session.conversation.append_assistant_transcript(response);
+                }
+                session.conversation.restore_history(history, &[], String::new());
+                session.tool_uses.clear();
+                session.pending_tool_index = None;
+                session.last_save = None;
+
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!(
+                        "\n✔ Imported {} exchanges{} from {path}\n",
+                        imported.turns.len(),
+                        imported
+                            .title
+                            .as_ref()
+                            .map(|title| format!(" of '{title}'"))
+                            .unwrap_or_default()
+                    )),
+                    style::SetAttribute(Attribute::Reset)
+                )?;
+                if let Some(prompt) = &imported.unanswered {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!(
+                            "The last prompt had no response and wasn't imported: {}\n",
+                            truncate_safe(prompt.lines().next().unwrap_or_default(), 80)
+                        )),
+                        style::SetAttribute(Attribute::Reset)
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Load { path, format: None } => {
                 // Try the original path first
-                let original_result = os.fs.read_to_string(&path).await;
+                let original_result = os.fs.read(&path).await;
 
                 // If the original path fails and doesn't end with .json, try with .json appended
                 let contents = if original_result.is_err() && !path.ends_with(".json") {
                     let json_path = format!("{}.json", path);
-                    match os.fs.read_to_string(&json_path).await {
+                    match os.fs.read(&json_path).await {
                         Ok(content) => content,
                         Err(_) => {
                             // If both paths fail, return the original error for better user experience
@@ -93,10 +282,13 @@ impl PersistSubcommand {
                     tri!(original_result, "import from", &path)
                 };
 
-                let mut new_state: ConversationState = tri!(serde_json::from_str(&contents), "import from", &path);
-                new_state.reload_serialized_state(os).await;
-                std::mem::swap(&mut new_state.tool_manager, &mut session.conversation.tool_manager);
-                session.conversation = new_state;
+                let mut loaded = tri!(LoadedConversation::parse(os, contents).await, "import from", &path);
+                let tool_permissions = loaded.tool_permissions.take();
+                loaded.restore(os, session).await;
+                session.last_save = Some(SavePoint {
+                    path: path.clone(),
+                    generation: session.conversation.generation(),
+                });
 
                 execute!(
                     session.stderr,
@@ -104,6 +296,9 @@ impl PersistSubcommand {
                     style::Print(format!("\n✔ Imported conversation state from {}\n\n", &path)),
                     style::SetAttribute(Attribute::Reset)
                 )?;
+                if let Some(tool_permissions) = tool_permissions {
+                    restore_tool_permissions(session, tool_permissions)?;
+                }
             },
         }
 
@@ -112,3 +307,55 @@ impl PersistSubcommand {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cli::chat::tool_manager::ToolManager;
+    use crate::cli::chat::tools::ToolSpec;
+
+    #[tokio::test]
+    async fn test_saved_conversation_round_trip() {
+        let mut os = Os::new().await.unwrap();
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("../tools/tool_index.json"))
+            .expect("Tools failed to load");
+        let conversation =
+            ConversationState::new(&mut os, "fake_conv_id", tool_config, None, ToolManager::default(), None).await;
+        let mut tool_permissions = ToolPermissions::new(1);
+        tool_permissions.trust_tool("fs_write");
+
+        let contents = serde_json::to_string(&SavedConversation {
+            conversation: &conversation,
+            tool_permissions: &tool_permissions,
+        })
+        .unwrap();
+        let loaded: LoadedConversation = serde_json::from_str(&contents).unwrap();
+        assert_eq!(loaded.conversation.conversation_id(), "fake_conv_id");
+        assert!(loaded.tool_permissions.unwrap().is_trusted("fs_write"));
+
+        // Files saved before tool permissions were included still load.
+        let contents = serde_json::to_string(&conversation).unwrap();
+        let loaded: LoadedConversation = serde_json::from_str(&contents).unwrap();
+        assert_eq!(loaded.conversation.conversation_id(), "fake_conv_id");
+        assert!(loaded.tool_permissions.is_none());
+    }
+
+    #[test]
+    fn test_trusted_tools() {
+        let mut tool_permissions = ToolPermissions::new(2);
+        tool_permissions.ask_every_use("fs_read");
+        assert_eq!(trusted_tools(&tool_permissions), None);
+
+        tool_permissions.trust_tool("fs_write");
+        tool_permissions.trust_tool("execute_bash");
+        assert_eq!(
+            trusted_tools(&tool_permissions).as_deref(),
+            Some("execute_bash, fs_write")
+        );
+
+        tool_permissions.trust_all = true;
+        assert_eq!(trusted_tools(&tool_permissions).as_deref(), Some("all tools"));
+    }
+}
@@ -0,0 +1,151 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+
+use crate::cli::chat::context::PinnedMessage;
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+
+/// Longest pinned message line shown in a listing before it's truncated.
+const PREVIEW_BYTES: usize = 100;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Pins a turn of the conversation so its prompt and response are always included near the top
+of the context, even after /compact. Turns are numbered as in /history. Run /pin without a turn to
+list the pinned messages."
+)]
+pub struct PinArgs {
+    /// Turn to pin, numbered as in /history
+    pub turn: Option<usize>,
+    /// Pin only the prompt of the turn
+    #[arg(long, conflicts_with = "response", requires = "turn")]
+    pub prompt: bool,
+    /// Pin only the response of the turn
+    #[arg(long, requires = "turn")]
+    pub response: bool,
+    /// Unpin the pinned message with this number
+    #[arg(long, value_name = "NUMBER", conflicts_with = "turn")]
+    pub remove: Option<usize>,
+}
+
+impl PinArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let message = match self.turn {
+            Some(turn) => match session.conversation.history().get(turn.wrapping_sub(1)) {
+                Some((user, assistant)) => Some(PinnedMessage {
+                    turn,
+                    prompt: user.prompt().filter(|_| !self.response).map(str::to_string),
+                    response: Some(assistant.content())
+                        .filter(|content| !self.prompt && !content.trim().is_empty())
+                        .map(str::to_string),
+                }),
+                None => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nThere is no turn {turn}, see /history for the turns\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                },
+            },
+            None => None,
+        };
+
+        let Some(context_manager) = &mut session.conversation.context_manager else {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print("\nContext management is not available.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let result = match (message, self.remove) {
+            (Some(message), _) if message.prompt.is_none() && message.response.is_none() => {
+                Err(eyre::eyre!("Turn {} has nothing to pin", message.turn))
+            },
+            (Some(message), _) => {
+                let turn = message.turn;
+                context_manager
+                    .pin(message)
+                    .map(|_| format!("Pinned turn {turn}, it stays in the context until unpinned"))
+            },
+            (None, Some(number)) => context_manager
+                .unpin(number)
+                .map(|message| format!("Unpinned turn {}", message.turn)),
+            (None, None) => {
+                if context_manager.pinned.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo messages are pinned. Pin a turn with /pin <turn>.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                } else {
+                    execute!(session.stderr, style::Print("\n"))?;
+                    for (i, message) in context_manager.pinned.iter().enumerate() {
+                        execute!(
+                            session.stderr,
+                            style::Print(format!("{:>3}. ", i + 1).cyan()),
+                            style::Print(format!("turn {}\n", message.turn).dark_grey()),
+                        )?;
+                        for (label, text) in [("> ", &message.prompt), ("  ", &message.response)] {
+                            if let Some(text) = text {
+                                let line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+                                execute!(
+                                    session.stderr,
+                                    style::Print(format!(
+                                        "     {label}{}\n",
+                                        truncate_safe(line.trim(), PREVIEW_BYTES)
+                                    ))
+                                )?;
+                            }
+                        }
+                    }
+                    execute!(
+                        session.stderr,
+                        style::Print("\nUse '/pin --remove <number>' to unpin a message.\n\n".dark_grey())
+                    )?;
+                }
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+        };
+
+        match result {
+            Ok(message) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ {message}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nError: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use std::process::Stdio;
+
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::cli::chat::cli::editor::open_editor;
+use crate::cli::chat::dynamic_context::git;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Characters of the diff sent along with the commit log, the rest is summarized by `--stat`.
+const MAX_DIFF_CHARS: usize = 40_000;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Asks Q for a pull request title and description, written from the commits and diff
+between the current branch and the base branch. Once it has answered, run /pr-describe --create to
+edit the description in $EDITOR and open the pull request with the GitHub CLI (gh)."
+)]
+pub struct PrDescribeArgs {
+    /// Branch the pull request merges into, defaults to the remote's default branch
+    pub base: Option<String>,
+    /// Edit the last response and create the pull request from it with gh
+    #[arg(long)]
+    pub create: bool,
+}
+
+impl PrDescribeArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let result = match self.create {
+            true => self.create_pr(os, session).await,
+            false => match self.describe(os).await {
+                Ok(prompt) => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("\nAsking Q to describe the changes...\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    return Ok(ChatState::HandleInput { input: prompt });
+                },
+                Err(err) => Err(err),
+            },
+        };
+
+        if let Err(err) = result {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nError: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    /// Builds the prompt asking for a description of the branch's changes.
+    async fn describe(&self, os: &Os) -> Result<String> {
+        let cwd = os.env.current_dir()?;
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => default_base(os).await,
+        };
+        let range = format!("{base}...HEAD");
+        let log = git(&cwd, &[
+            "log",
+            "--no-color",
+            "--reverse",
+            "--format=- %s%n%n%b",
+            &format!("{base}..HEAD"),
+        ])
+        .await?;
+        if log.trim().is_empty() {
+            bail!("There are no commits on this branch that aren't on {base}");
+        }
+        let stat = git(&cwd, &["diff", "--no-color", "--stat", &range]).await?;
+        let diff = git(&cwd, &["diff", "--no-color", &range]).await?;
+        Ok(describe_prompt(&base, &log, &stat, &diff))
+    }
+
+    /// Lets the user edit the last response and creates a pull request from it.
+    async fn create_pr(&self, os: &Os, session: &mut ChatSession) -> Result<()> {
+        let Some(response) = session
+            .conversation
+            .history()
+            .iter()
+            .rev()
+            .map(|(_, assistant)| assistant.content())
+            .find(|content| !content.trim().is_empty())
+            .map(str::to_string)
+        else {
+            bail!("There is no description yet, run /pr-describe first");
+        };
+
+        let edited = open_editor(Some(response)).map_err(|err| eyre::eyre!("{err}"))?;
+        let Some((title, body)) = parse_description(&edited) else {
+            bail!("The description is empty, not creating a pull request");
+        };
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => default_base(os).await,
+        };
+
+        let mut gh = Command::new("gh");
+        gh.args(["pr", "create", "--base", &base, "--title", &title, "--body-file", "-"])
+            .current_dir(os.env.current_dir()?)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = match gh.spawn() {
+            Ok(child) => child,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                bail!("The GitHub CLI (gh) was not found, install it from https://cli.github.com")
+            },
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(body.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            bail!(
+                "gh pr create failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print("\n✔ Created pull request "),
+            style::SetAttribute(Attribute::Bold),
+            style::Print(String::from_utf8_lossy(&output.stdout).trim()),
+            style::SetAttribute(Attribute::Reset),
+            style::Print("\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        Ok(())
+    }
+}
+
+/// The remote's default branch, or `main` if it isn't known.
+async fn default_base(os: &Os) -> String {
+    let Ok(cwd) = os.env.current_dir() else {
+        return "main".to_string();
+    };
+    match git(&cwd, &["rev-parse", "--abbrev-ref", "origin/HEAD"]).await {
+        Ok(head) => head.trim().trim_start_matches("origin/").to_string(),
+        Err(_) => "main".to_string(),
+    }
+}
+
+fn describe_prompt(base: &str, log: &str, stat: &str, diff: &str) -> String {
+    let diff = match diff.char_indices().nth(MAX_DIFF_CHARS) {
+        Some((end, _)) => format!("{}\n[diff truncated, see the summary above for the rest]", &diff[..end]),
+        None => diff.to_string(),
+    };
+    format!(
+        "Write a pull request title and description for merging the current branch into {base}.
+
+Put the title alone on the first line, under 72 characters and without a prefix. Follow it with a
+blank line and a description in markdown with these sections:
+## Summary
+What the change does and why, in one or two sentences.
+## Changes
+A bullet for each notable change.
+## Testing
+How the change was or should be verified.
+
+Reply with only the title and description.
+
+Commits:
+{log}
+Changed files:
+{stat}
+Diff:
+```diff
+{diff}
+```"
+    )
+}
+
+/// Splits an edited description into its title and body.
+fn parse_description(text: &str) -> Option<(String, String)> {
+    let text = text.trim();
+    let (title, body) = text.split_once('\n').unwrap_or((text, ""));
+    let title = title.trim().trim_start_matches('#').trim();
+    let title = title.strip_prefix("Title:").unwrap_or(title).trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some((title.to_string(), body.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        assert_eq!(
+            parse_description("# Title: Add widgets\n\n## Summary\nAdds widgets.\n"),
+            Some(("Add widgets".to_string(), "## Summary\nAdds widgets.".to_string()))
+        );
+        assert_eq!(
+            parse_description("Fix the build"),
+            Some(("Fix the build".to_string(), String::new()))
+        );
+        assert_eq!(parse_description("  \n"), None);
+    }
+
+    #[test]
+    fn test_describe_prompt_truncates_diff() {
+        let diff = "+".repeat(MAX_DIFF_CHARS + 10);
+        let prompt = describe_prompt("main", "- Add widgets\n", " a.rs | 1 +\n", &diff);
+        assert!(prompt.contains("into main"));
+        assert!(prompt.contains("- Add widgets"));
+        assert!(prompt.contains("[diff truncated"));
+        assert!(!prompt.contains(&diff));
+    }
+}
@@ -1,11 +1,22 @@
-use clap::Subcommand;
+use std::io::IsTerminal;
+
+use clap::{
+    Args,
+    Subcommand,
+};
 use crossterm::execute;
 use crossterm::style::{
     self,
     Color,
+    Stylize,
 };
 use tracing::warn;
 
+use crate::cli::chat::context::{
+    ContextManager,
+    ProfileSummary,
+};
+use crate::cli::chat::skim_integration::select_profile_with_preview;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -14,7 +25,7 @@ use crate::cli::chat::{
 use crate::os::Os;
 
 #[deny(missing_docs)]
-#[derive(Debug, PartialEq, Subcommand)]
+#[derive(Debug, PartialEq, Args)]
 #[command(
     before_long_help = "Profiles allow you to organize and manage different sets of context files for different projects or tasks.
 
@@ -22,8 +33,106 @@ Notes
 • The \"global\" profile contains context files that are available in all profiles
 • The \"default\" profile is used when no profile is specified
 • You can switch between profiles to work on different projects
-• Each profile maintains its own set of context files"
+• Each profile maintains its own set of context files
+• /profile without a subcommand picks the profile to switch to from a list, previewing each one's rules, hooks and size"
 )]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    subcommand: Option<ProfileSubcommand>,
+}
+
+impl ProfileArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.subcommand {
+            Some(subcommand) => subcommand.execute(os, session).await,
+            // The switcher needs a terminal to draw in, otherwise the profiles are listed.
+            None if std::io::stdin().is_terminal() => select_profile(os, session).await,
+            None => ProfileSubcommand::List.execute(os, session).await,
+        }
+    }
+}
+
+/// Switches to the profile picked from a list of them, each previewed with [profile_preview].
+async fn select_profile(os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    let Some(context_manager) = &session.conversation.context_manager else {
+        return Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        });
+    };
+
+    match pick_profile(os, context_manager).await {
+        Ok(Some(name)) if name != context_manager.current_profile => {
+            ProfileSubcommand::Set { name }.execute(os, session).await
+        },
+        Ok(_) => Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        }),
+        Err(e) => {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nError: {}\n\n", e)),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            })
+        },
+    }
+}
+
+/// The profile picked in the switcher, `None` if it was closed without picking one.
+async fn pick_profile(os: &Os, context_manager: &ContextManager) -> eyre::Result<Option<String>> {
+    let mut items = Vec::new();
+    for profile in context_manager.list_profiles(os).await? {
+        let preview = match context_manager.profile_summary(os, &profile).await {
+            Ok(summary) => profile_preview(&summary),
+            Err(e) => format!("Cannot read the profile: {e}").red().to_string(),
+        };
+        let label = if profile == context_manager.current_profile {
+            format!("{profile} (current)")
+        } else {
+            profile.clone()
+        };
+        items.push((profile, label, preview));
+    }
+    select_profile_with_preview(items)
+}
+
+/// What the profile switcher shows beside the list for the highlighted profile.
+fn profile_preview(summary: &ProfileSummary) -> String {
+    let mut preview = format!("{}\n", "Rules".bold());
+    if summary.rules.is_empty() {
+        preview.push_str(&format!("  {}\n", "<none>".dark_grey()));
+    }
+    for rule in &summary.rules {
+        preview.push_str(&format!("  {rule}\n"));
+    }
+
+    preview.push_str(&format!("\n{}\n", "Hooks".bold()));
+    if summary.hooks.is_empty() {
+        preview.push_str(&format!("  {}\n", "<none>".dark_grey()));
+    }
+    for (name, trigger, disabled) in &summary.hooks {
+        let hook = format!("  {name} ({trigger})");
+        match disabled {
+            true => preview.push_str(&format!("{}\n", format!("{hook} disabled").dark_grey())),
+            false => preview.push_str(&format!("{hook}\n")),
+        }
+    }
+
+    preview.push_str(&format!(
+        "\n{}\n  {} files, ~{} tokens\n\n{}\n",
+        "Context".bold(),
+        summary.files,
+        summary.tokens,
+        "Global rules and hooks are used with every profile.".dark_grey()
+    ));
+    preview
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
 pub enum ProfileSubcommand {
     /// List all available profiles
     List,
@@ -128,6 +237,16 @@ impl ProfileSubcommand {
                         style::Print(format!("\nSwitched to profile: {}\n\n", name)),
                         style::SetForegroundColor(Color::Reset)
                     )?;
+                    if let Some(preset) = context_manager.tool_preset() {
+                        preset.apply(&mut session.tool_permissions);
+                    }
+                    // Read the profile's files now, where a progress bar shows for large ones, rather
+                    // than with the next prompt.
+                    context_manager
+                        .preload(os)
+                        .await
+                        .map_err(|e| warn!(?e, "failed to read the files of the profile"))
+                        .ok();
                 },
                 Err(e) => print_err!(e),
             },
@@ -0,0 +1,257 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::cli::persist::{
+    SavePoint,
+    save_conversation,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    autosave,
+};
+use crate::os::Os;
+
+/// Used by the save-and-exit option when the conversation has never been saved.
+const DEFAULT_SAVE_PATH: &str = "amazonq-conversation.json";
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct QuitArgs {
+    /// Exit without asking for confirmation, discarding any unsaved changes
+    #[arg(short, long)]
+    force: bool,
+}
+
+impl QuitArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if self.force {
+            return Ok(ChatState::Exit);
+        }
+        confirm_exit(os, session).await
+    }
+}
+
+/// What exiting now would lose, of a session.
+struct SessionState<'a> {
+    history_len: usize,
+    /// The [generation](crate::cli::chat::conversation::ConversationState::generation) of the
+    /// conversation.
+    generation: u64,
+    last_save: Option<&'a SavePoint>,
+    /// Whether the conversation is autosaved as it is now, so `/resume` can pick it up again.
+    autosaved: bool,
+    pending_tool_uses: usize,
+    checkpoints: usize,
+}
+
+/// Describes the state that would be lost by exiting now, one line per item.
+fn unsaved_state(state: &SessionState<'_>) -> Vec<String> {
+    let SessionState {
+        history_len,
+        generation,
+        last_save,
+        autosaved,
+        pending_tool_uses,
+        checkpoints,
+    } = *state;
+    let mut summary = Vec::new();
+    match last_save {
+        _ if autosaved => (),
+        Some(save) if save.generation != generation => {
+            summary.push(format!("Conversation changed since it was saved to {}", save.path));
+        },
+        None if history_len > 0 => summary.push(format!(
+            "{history_len} {} not saved",
+            if history_len == 1 { "exchange" } else { "exchanges" }
+        )),
+        _ => (),
+    }
+    if pending_tool_uses > 0 {
+        summary.push(format!(
+            "{pending_tool_uses} tool {} awaiting approval",
+            if pending_tool_uses == 1 { "use" } else { "uses" }
+        ));
+    }
+    if checkpoints > 0 {
+        summary.push(format!(
+            "{checkpoints} {} that /checkpoint restore can't rewind to after exiting",
+            if checkpoints == 1 { "checkpoint" } else { "checkpoints" }
+        ));
+    }
+    summary
+}
+
+/// Exits the session, first asking for confirmation if there is anything that would be lost.
+///
+/// The user can choose to save the conversation before exiting. Non-interactive sessions always
+/// exit immediately, as does pressing Ctrl+C or Ctrl+D at the confirmation prompt.
+pub async fn confirm_exit(os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    let generation = session.conversation.generation();
+    let summary = unsaved_state(&SessionState {
+        history_len: session.conversation.history().len(),
+        generation,
+        last_save: session.last_save.as_ref(),
+        autosaved: autosave::is_enabled(os) && session.autosaved_generation == generation,
+        pending_tool_uses: session.tool_uses.len(),
+        checkpoints: session.checkpoints.checkpoints().count(),
+    });
+    if !session.interactive || summary.is_empty() {
+        return Ok(ChatState::Exit);
+    }
+
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Yellow),
+        style::Print("\nBefore you go:\n")
+    )?;
+    for line in &summary {
+        execute!(session.stderr, style::Print(format!("  • {line}\n")))?;
+    }
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Reset),
+        style::Print("\n")
+    )?;
+
+    loop {
+        let choice = match session
+            .input_source
+            .read_line(Some("[s]ave and exit, [e]xit without saving, or [c]ancel? "))
+        {
+            Ok(Some(choice)) => choice,
+            _ => return Ok(ChatState::Exit),
+        };
+
+        match choice.trim().to_lowercase().as_str() {
+            "s" | "save" => break,
+            "e" | "exit" | "y" | "yes" => return Ok(ChatState::Exit),
+            "c" | "cancel" | "n" | "no" => {
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+            _ => (),
+        }
+    }
+
+    let default_path = session
+        .last_save
+        .as_ref()
+        .map_or(DEFAULT_SAVE_PATH.to_string(), |save| save.path.clone());
+    let path = match session
+        .input_source
+        .read_line(Some(&format!("Save to ({default_path}): ")))
+    {
+        Ok(Some(path)) if !path.trim().is_empty() => path.trim().to_string(),
+        Ok(Some(_)) => default_path,
+        _ => return Ok(ChatState::Exit),
+    };
+
+    // Saving again to the file the conversation was saved to is expected, any other file would be
+    // lost, e.g. an earlier conversation saved to the default path.
+    let is_last_save = session.last_save.as_ref().is_some_and(|save| save.path == path);
+    if !is_last_save && os.fs.exists(&path) {
+        let overwrite = session
+            .input_source
+            .read_line(Some(&format!("{path} already exists, overwrite it? [y/N] ")));
+        if !matches!(overwrite, Ok(Some(answer)) if ["y", "yes"].contains(&answer.trim().to_lowercase().as_str())) {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!(
+                    "
+Not saved, {path} was kept.
+
+"
+                )),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+    }
+
+    match save_conversation(os, session, &path).await {
+        Ok(()) => {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ Saved conversation to {path}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            Ok(ChatState::Exit)
+        },
+        Err(err) => {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nFailed to save to {path}: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsaved_state() {
+        let state = SessionState {
+            history_len: 0,
+            generation: 0,
+            last_save: None,
+            autosaved: false,
+            pending_tool_uses: 0,
+            checkpoints: 0,
+        };
+        assert!(unsaved_state(&state).is_empty());
+        let state = SessionState {
+            history_len: 3,
+            generation: 7,
+            pending_tool_uses: 1,
+            ..state
+        };
+        assert_eq!(unsaved_state(&state), vec![
+            "3 exchanges not saved".to_string(),
+            "1 tool use awaiting approval".to_string(),
+        ]);
+        assert_eq!(
+            unsaved_state(&SessionState {
+                autosaved: true,
+                checkpoints: 2,
+                ..state
+            }),
+            vec![
+                "1 tool use awaiting approval".to_string(),
+                "2 checkpoints that /checkpoint restore can't rewind to after exiting".to_string(),
+            ]
+        );
+
+        let save = SavePoint {
+            path: "chat.json".to_string(),
+            generation: 7,
+        };
+        let state = SessionState {
+            last_save: Some(&save),
+            pending_tool_uses: 0,
+            ..state
+        };
+        assert!(unsaved_state(&state).is_empty());
+        // E.g. after `/undo` and a new prompt, which leave as many exchanges as were saved.
+        assert_eq!(unsaved_state(&SessionState { generation: 9, ..state }), vec![
+            "Conversation changed since it was saved to chat.json".to_string()
+        ]);
+    }
+}
@@ -0,0 +1,115 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use tracing::warn;
+
+use crate::cli::chat::refactor::RefactorRun;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(before_long_help = "Applies the same change to every file matching a glob, such as
+/refactor \"src/**/*.rs\" --instruction \"Return errors instead of calling unwrap()\".
+Files are sent to Q a batch at a time, and each edit is shown as a diff for review before it's
+written. Between batches you can stop, and /refactor --resume continues where the run left off,
+even in a later session.")]
+pub struct RefactorArgs {
+    /// Glob of the files to change, relative to the current directory
+    #[arg(required_unless_present_any = ["resume", "cancel"])]
+    pub pattern: Option<String>,
+    /// The change to make in each file
+    #[arg(long, short, required_unless_present_any = ["resume", "cancel"])]
+    pub instruction: Option<String>,
+    /// Number of files sent to Q at a time
+    #[arg(long, default_value_t = 5)]
+    pub batch_size: usize,
+    /// Continue the refactor that was stopped or interrupted
+    #[arg(long, conflicts_with_all = ["pattern", "instruction", "cancel"])]
+    pub resume: bool,
+    /// Forget the refactor that was stopped or interrupted
+    #[arg(long, conflicts_with_all = ["pattern", "instruction"])]
+    pub cancel: bool,
+}
+
+impl RefactorArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let result = match self.cancel {
+            true => RefactorRun::remove(os).await.map(|_| None),
+            false => self.start(os).await.map(Some),
+        };
+
+        match result {
+            Ok(Some(mut run)) => {
+                if let Some((number, prompt)) = run.next_batch() {
+                    if let Err(err) = run.save(os).await {
+                        warn!(?err, "failed to save refactor progress");
+                    }
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("\nRefactor batch {number}/{}\n\n", run.total())),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    session.refactor = Some(run);
+                    return Ok(ChatState::HandleInput { input: prompt });
+                }
+            },
+            Ok(None) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print("\n✔ Forgot the stopped refactor\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nError: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    /// The run to continue with `--resume`, or a new one over the matched files.
+    async fn start(&self, os: &Os) -> Result<RefactorRun> {
+        let cwd = os.env.current_dir()?;
+        if !self.resume {
+            if let Some(run) = RefactorRun::load(os).await? {
+                bail!(
+                    "A refactor of {} batches is still unfinished. Continue it with /refactor --resume or forget it with /refactor --cancel",
+                    run.total()
+                );
+            }
+            let (Some(pattern), Some(instruction)) = (&self.pattern, &self.instruction) else {
+                bail!("A glob and --instruction are required");
+            };
+            return RefactorRun::new(&cwd, pattern, instruction.clone(), self.batch_size);
+        }
+
+        let Some(run) = RefactorRun::load(os).await? else {
+            bail!("There is no refactor to resume");
+        };
+        if run.cwd != cwd {
+            bail!(
+                "The refactor was started in {}, resume it from there",
+                run.cwd.display()
+            );
+        }
+        Ok(run)
+    }
+}
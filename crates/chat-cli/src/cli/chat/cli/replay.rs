@@ -0,0 +1,270 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use tokio::signal::ctrl_c;
+use winnow::Partial;
+use winnow::stream::Offset;
+
+use crate::cli::chat::message::ToolUseResultBlock;
+use crate::cli::chat::parse::{
+    ParseState,
+    interpret_markdown,
+};
+use crate::cli::chat::transcript_log::{
+    RecordedEvent,
+    TranscriptLine,
+    read_sessions,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Longest pause between two events, however long it took when it happened.
+const MAX_PAUSE: Duration = Duration::from_secs(2);
+
+/// Lines of a tool's input and result shown.
+const MAX_TOOL_LINES: usize = 8;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Renders a conversation again turn by turn, with the pauses between prompts, response text
+and tool uses as they happened, sped up. The conversation is read from the transcript written with
+--transcript or the chat.transcriptPath setting: this session's by default, or another with --file.
+Press Ctrl+C to stop."
+)]
+pub struct ReplayArgs {
+    /// How many times faster than it happened, 1 for the original timing
+    #[arg(default_value_t = 10.0)]
+    pub speed: f64,
+    /// Replay a transcript file instead of this session's
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<PathBuf>,
+    /// Which session of the transcript to replay, counting from 1, instead of the last
+    #[arg(long, value_name = "NUMBER")]
+    pub session: Option<usize>,
+}
+
+impl ReplayArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.lines(os, session).await {
+            Ok(lines) => replay(session, &lines, self.speed).await?,
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nError: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    async fn lines(&self, os: &Os, session: &ChatSession) -> eyre::Result<Vec<TranscriptLine>> {
+        if self.speed.is_nan() || self.speed <= 0.0 {
+            eyre::bail!("The speed must be above 0");
+        }
+        let path = match (&self.file, &session.transcript_log) {
+            (Some(file), _) => file.clone(),
+            (None, Some(log)) => log.path().to_path_buf(),
+            (None, None) => eyre::bail!(
+                "This session has no transcript, start one with --transcript or chat.transcriptPath, or replay another with --file"
+            ),
+        };
+        let mut sessions = read_sessions(os, &path).await?;
+
+        let index = match self.session {
+            Some(number) if number == 0 || number > sessions.len() => {
+                eyre::bail!("{} has {} sessions", path.display(), sessions.len())
+            },
+            Some(number) => number - 1,
+            // This session's transcript may be shared with others, its own part starts with its id.
+            None if self.file.is_none() => sessions
+                .iter()
+                .rposition(|lines| {
+                    matches!(&lines[0].event, RecordedEvent::SessionStart { conversation_id }
+                        if conversation_id == session.conversation.conversation_id())
+                })
+                .unwrap_or(sessions.len().saturating_sub(1)),
+            None => sessions.len().saturating_sub(1),
+        };
+        let lines = if index < sessions.len() {
+            sessions.swap_remove(index)
+        } else {
+            Vec::new()
+        };
+        if !lines
+            .iter()
+            .any(|line| matches!(line.event, RecordedEvent::Prompt { .. }))
+        {
+            eyre::bail!("There are no prompts to replay in {}", path.display());
+        }
+        Ok(lines)
+    }
+}
+
+/// The pause before an event that happened `elapsed` after the one before it.
+fn pause(elapsed: time::Duration, speed: f64) -> Duration {
+    Duration::try_from_secs_f64(elapsed.as_seconds_f64().max(0.0) / speed)
+        .unwrap_or(MAX_PAUSE)
+        .min(MAX_PAUSE)
+}
+
+/// The first [MAX_TOOL_LINES] lines of `text`, noting how many more there are.
+fn first_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut shown = lines
+        .iter()
+        .take(MAX_TOOL_LINES)
+        .map(|line| format!("  {line}\n"))
+        .collect::<String>();
+    if lines.len() > MAX_TOOL_LINES {
+        shown.push_str(&format!("  ... {} more lines\n", lines.len() - MAX_TOOL_LINES));
+    }
+    shown
+}
+
+async fn replay(session: &mut ChatSession, lines: &[TranscriptLine], speed: f64) -> Result<(), ChatError> {
+    let mut state = ParseState::new(Some(session.terminal_width()));
+    let mut buf = String::new();
+    let mut offset = 0;
+    let mut previous = None;
+    let mut turns = 0;
+
+    for line in lines {
+        if let Some(previous) = previous {
+            tokio::select! {
+                _ = tokio::time::sleep(pause(line.timestamp - previous, speed)) => (),
+                _ = ctrl_c() => {
+                    execute!(session.stderr, style::ResetColor, style::Print("\n\nReplay stopped.\n\n".dark_grey()))?;
+                    return Ok(());
+                },
+            }
+        }
+        previous = Some(line.timestamp);
+
+        // Any response text is finished once something else happens.
+        if !matches!(line.event, RecordedEvent::ResponseChunk { .. }) && !buf.is_empty() {
+            buf.push('\n');
+            render_markdown(session, &buf, &mut offset, &mut state)?;
+            buf.clear();
+            offset = 0;
+            state = ParseState::new(Some(session.terminal_width()));
+            execute!(session.stdout, style::ResetColor, style::Print("\n"))?;
+        }
+
+        match &line.event {
+            RecordedEvent::Prompt { prompt } => {
+                turns += 1;
+                execute!(
+                    session.stdout,
+                    style::Print(format!("\n{}\n\n", format!("> {prompt}").magenta()))
+                )?;
+            },
+            RecordedEvent::ResponseChunk { text } => {
+                buf.push_str(text);
+                render_markdown(session, &buf, &mut offset, &mut state)?;
+            },
+            RecordedEvent::ToolUse { name, input } => {
+                let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                execute!(
+                    session.stdout,
+                    style::Print(format!("🛠️  Using tool: {name}\n").magenta()),
+                    style::Print(first_lines(&input).dark_grey()),
+                )?;
+            },
+            RecordedEvent::ToolResult { status, content } => {
+                let text = content
+                    .iter()
+                    .map(|block| match block {
+                        ToolUseResultBlock::Text(text) => text.clone(),
+                        ToolUseResultBlock::Json(json) => serde_json::to_string_pretty(json).unwrap_or_default(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let marker = match status.as_str() {
+                    "success" => " ● Completed\n".green(),
+                    _ => " ● Failed\n".red(),
+                };
+                execute!(
+                    session.stdout,
+                    style::Print(marker),
+                    style::Print(first_lines(&text).dark_grey()),
+                    style::Print("\n")
+                )?;
+            },
+            RecordedEvent::SessionStart { .. } | RecordedEvent::Other => (),
+        }
+    }
+
+    if !buf.is_empty() {
+        buf.push('\n');
+        render_markdown(session, &buf, &mut offset, &mut state)?;
+        execute!(session.stdout, style::ResetColor, style::Print("\n"))?;
+    }
+    execute!(
+        session.stderr,
+        style::Print(format!("\nReplayed {turns} turn{}.\n\n", if turns == 1 { "" } else { "s" }).dark_grey())
+    )?;
+    Ok(())
+}
+
+/// Renders the markdown of `buf` from `offset` on, leaving anything incomplete for the next chunk.
+pub fn render_markdown(
+    session: &mut ChatSession,
+    buf: &str,
+    offset: &mut usize,
+    state: &mut ParseState,
+) -> Result<(), ChatError> {
+    loop {
+        let input = Partial::new(&buf[*offset..]);
+        match interpret_markdown(input, &mut session.stdout, state) {
+            Ok(parsed) => {
+                *offset += parsed.offset_from(&input);
+                state.newline = state.set_newline;
+                state.set_newline = false;
+            },
+            Err(err) => match err.into_inner() {
+                Some(err) => return Err(ChatError::Custom(err.to_string().into())),
+                None => break,
+            },
+        }
+    }
+    queue!(session.stdout, style::ResetColor)?;
+    session.stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause() {
+        assert_eq!(pause(time::Duration::seconds(5), 10.0), Duration::from_millis(500));
+        assert_eq!(pause(time::Duration::seconds(60), 10.0), MAX_PAUSE);
+        assert_eq!(pause(time::Duration::seconds(-1), 1.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_first_lines() {
+        assert_eq!(first_lines("a\nb"), "  a\n  b\n");
+        let text = (0..10).map(|i| i.to_string()).collect::<Vec<_>>().join("\n");
+        assert!(first_lines(&text).ends_with("  7\n  ... 2 more lines\n"));
+    }
+}
@@ -0,0 +1,86 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use time::OffsetDateTime;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    autosave,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Sessions are saved as they progress while chat.enableAutosave is on. Resuming a session saves
+the current one first, so it can be resumed again later. Use 'q history list' to see the ids of
+stored sessions."
+)]
+pub struct ResumeArgs {
+    /// Id of the session to resume, or a prefix of one. Defaults to the most recent session in the
+    /// current directory
+    id: Option<String>,
+}
+
+impl ResumeArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        macro_rules! warn_and_return {
+            ($msg:expr) => {{
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!("\n{}\n\n", $msg)),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            }};
+        }
+
+        let current_id = session.conversation.conversation_id().to_string();
+        let stored = match self.id {
+            Some(id) => match autosave::find_by_id(os, &id).await {
+                Ok(stored) => stored,
+                Err(err) => warn_and_return!(err),
+            },
+            None => match autosave::find_latest(os, &os.env.current_dir()?, Some(&current_id)).await {
+                Some(stored) => stored,
+                None => warn_and_return!("There are no other sessions to resume in this directory."),
+            },
+        };
+        if stored.id == current_id {
+            warn_and_return!("That is the current session.");
+        }
+
+        // Keep the session being left so that it can be resumed in turn.
+        session.close_session(os).await;
+        stored.conversation.restore(os, session).await;
+        session.tool_uses.clear();
+        session.pending_tool_index = None;
+        session.last_save = None;
+        // Claim the stored session for this process.
+        session.autosave(os).await;
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!(
+                "\n✔ Resumed session {} with {} exchanges, last active {}\n\n",
+                stored.id,
+                session.conversation.history().len(),
+                autosave::format_age(stored.entry.updated_at, OffsetDateTime::now_utc())
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::Result;
+
+use crate::cli::chat::security_review::SecurityReview;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Directories never reviewed when matching the glob.
+const SKIPPED_DIRS: &[&str] = &[".git", "target", "node_modules", "dist", "build", ".venv"];
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Asks Q to review the files matching a glob for security vulnerabilities. The files are sent
+in chunks that fit the context window, and the findings of every chunk are collected into one
+report of severity, file, line and recommendation. With --sarif, the report is also written as a
+SARIF file for code scanning in CI."
+)]
+pub struct SecurityReviewArgs {
+    /// Glob of the files to review, relative to the current directory
+    #[arg(default_value = "**/*")]
+    pub pattern: String,
+    /// Write the findings to a SARIF file once the review is done
+    #[arg(long, value_name = "FILE")]
+    pub sarif: Option<PathBuf>,
+}
+
+impl SecurityReviewArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let mut review = match self.start(os).await {
+            Ok(review) => review,
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+        };
+
+        let Some((number, prompt)) = review.next_chunk() else {
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "\nReviewing {} files, security review chunk {number}/{}\n\n",
+                review.files,
+                review.total()
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        session.security_review = Some(review);
+        Ok(ChatState::HandleInput { input: prompt })
+    }
+
+    async fn start(&self, os: &Os) -> Result<SecurityReview> {
+        let cwd = os.env.current_dir()?;
+        let mut files = Vec::new();
+        for entry in glob::glob(&cwd.join(&self.pattern).to_string_lossy())? {
+            let path = entry?;
+            let relative = path.strip_prefix(&cwd).unwrap_or(&path).to_path_buf();
+            let skipped = relative
+                .components()
+                .any(|part| SKIPPED_DIRS.iter().any(|dir| part.as_os_str() == *dir));
+            if skipped || !path.is_file() {
+                continue;
+            }
+            // Binary files aren't reviewed.
+            if let Ok(content) = os.fs.read_to_string(&path).await {
+                files.push((relative.to_string_lossy().to_string(), content));
+            }
+        }
+        files.sort();
+        SecurityReview::new(self.pattern.clone(), &files, self.sarif.clone())
+    }
+}
@@ -0,0 +1,91 @@
+use clap::Subcommand;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use time::OffsetDateTime;
+
+use crate::cli::chat::store::ConversationStore;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    autosave,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Sessions are kept in the session store while chat.enableAutosave is on. Use '/resume <id>'
+to continue one, and '/tag <name>' to tag the current session."
+)]
+pub enum SessionsSubcommand {
+    /// List stored sessions, newest first
+    List {
+        /// Only list sessions with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+}
+
+impl SessionsSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Self::List { tag } = self;
+        let store = ConversationStore::new(os).map_err(|err| ChatError::Custom(err.to_string().into()))?;
+        let mut entries = store
+            .entries(os)
+            .await
+            .map_err(|err| ChatError::Custom(format!("Failed to list stored sessions: {err}").into()))?
+            .into_iter()
+            .filter(|(_, entry)| tag.as_ref().is_none_or(|tag| entry.tags.contains(tag)))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(_, a), (_, b)| b.updated_at.cmp(&a.updated_at));
+
+        if entries.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(match &tag {
+                    Some(tag) => format!("\nNo stored sessions are tagged '{tag}'.\n\n"),
+                    None => "\nThere are no stored sessions.\n\n".to_string(),
+                }),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let current_id = session.conversation.conversation_id().to_string();
+        execute!(session.stderr, style::Print("\n"))?;
+        for (id, entry) in entries {
+            execute!(
+                session.stderr,
+                style::Print(id.as_str().cyan()),
+                style::Print(format!("  {}", autosave::format_age(entry.updated_at, now))),
+            )?;
+            if let Some(cwd) = &entry.cwd {
+                execute!(session.stderr, style::Print(format!("  {}", cwd.display()).dark_grey()))?;
+            }
+            if !entry.tags.is_empty() {
+                execute!(
+                    session.stderr,
+                    style::Print(format!("  [{}]", entry.tags.join(", ")).green())
+                )?;
+            }
+            if id == current_id {
+                execute!(session.stderr, style::Print("  (current)".dark_grey()))?;
+            }
+            execute!(session.stderr, style::Print("\n"))?;
+        }
+        execute!(session.stderr, style::Print("\n"))?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use clap::{
+    Subcommand,
+    ValueEnum,
+};
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use regex::Regex;
+
+use crate::cli::chat::context::ContextManager;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// The longest value of a setting, which is added to the context of every conversation.
+const MAX_VALUE_CHARS: usize = 64;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Settings of the current profile that change how Amazon Q responds. They're saved with the profile,
+and used in every conversation with it.
+
+Settings
+• response.language        The language of responses and of the comments in code written, e.g. es
+                           or Brazilian Portuguese
+• response.stop_sequences  Text that ends a response where it appears, each value is a sequence
+• response.filters         Regular expressions of text removed from each line of a response, e.g.
+                           '^(Certainly|Sure)! ?', each value is an expression. Lines left blank
+                           are removed"
+)]
+pub enum SettingsSubcommand {
+    /// Set a setting of the current profile
+    Set {
+        /// Name of the setting
+        key: ProfileSetting,
+        /// Value of the setting, or the values of a list
+        #[arg(required = true, num_args = 1..)]
+        value: Vec<String>,
+    },
+    /// Remove a setting of the current profile, going back to its default
+    Unset {
+        /// Name of the setting
+        key: ProfileSetting,
+    },
+    /// List the settings of the current profile
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProfileSetting {
+    #[value(name = "response.language")]
+    ResponseLanguage,
+    #[value(name = "response.stop_sequences")]
+    StopSequences,
+    #[value(name = "response.filters")]
+    OutputFilters,
+}
+
+impl ProfileSetting {
+    const ALL: [Self; 3] = [Self::ResponseLanguage, Self::StopSequences, Self::OutputFilters];
+
+    fn key(self) -> &'static str {
+        match self {
+            Self::ResponseLanguage => "response.language",
+            Self::StopSequences => "response.stop_sequences",
+            Self::OutputFilters => "response.filters",
+        }
+    }
+
+    /// The values of the setting as given, checked for the setting. A setting that isn't a list
+    /// is given as words, joined into one value.
+    fn parse(self, values: Vec<String>) -> Result<Vec<String>, String> {
+        match self {
+            Self::ResponseLanguage => {
+                let value = values.join(" ").trim().to_string();
+                validate_value(&value)?;
+                Ok(vec![value])
+            },
+            Self::StopSequences => {
+                if values.iter().any(String::is_empty) {
+                    return Err("a stop sequence is empty".to_string());
+                }
+                Ok(values)
+            },
+            Self::OutputFilters => {
+                for value in &values {
+                    Regex::new(value).map_err(|e| format!("'{value}' is not a valid regular expression: {e}"))?;
+                }
+                Ok(values)
+            },
+        }
+    }
+
+    /// The values of the setting in the current profile, empty if it isn't set.
+    fn values(self, context_manager: &ContextManager) -> Vec<String> {
+        match self {
+            Self::ResponseLanguage => context_manager
+                .response_language()
+                .map(str::to_string)
+                .into_iter()
+                .collect(),
+            Self::StopSequences => context_manager.stop_sequences().to_vec(),
+            Self::OutputFilters => context_manager.output_filters().to_vec(),
+        }
+    }
+
+    /// Sets the setting in the current profile, or with no `values` removes it.
+    async fn save(self, os: &Os, context_manager: &mut ContextManager, values: Vec<String>) -> eyre::Result<()> {
+        match self {
+            Self::ResponseLanguage => {
+                context_manager
+                    .set_response_language(os, values.into_iter().next())
+                    .await
+            },
+            Self::StopSequences => context_manager.set_stop_sequences(os, values).await,
+            Self::OutputFilters => context_manager.set_output_filters(os, values).await,
+        }
+    }
+}
+
+impl SettingsSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Some(context_manager) = session.conversation.context_manager.as_mut() else {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print("\nProfile settings are not available.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+        let profile = context_manager.current_profile.clone();
+
+        match self {
+            Self::Set { key, value } => {
+                let values = match key.parse(value) {
+                    Ok(values) => values,
+                    Err(err) => {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\nInvalid value for {}: {err}\n\n", key.key())),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+                let value = display_values(&values);
+                match key.save(os, context_manager, values).await {
+                    Ok(()) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("\nSet {} to {value} for profile {profile}.\n\n", key.key())),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                    Err(err) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to save the setting: {err}\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Unset { key } => match key.save(os, context_manager, Vec::new()).await {
+                Ok(()) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\nRemoved {} from profile {profile}.\n\n", key.key())),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+                Err(err) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to save the setting: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            },
+            Self::List => {
+                execute!(
+                    session.stderr,
+                    style::Print(format!("\nSettings of profile {profile}:\n"))
+                )?;
+                for setting in ProfileSetting::ALL {
+                    let values = setting.values(context_manager);
+                    match values.is_empty() {
+                        false => execute!(
+                            session.stderr,
+                            style::Print(format!("  {} = {}\n", setting.key(), display_values(&values)))
+                        )?,
+                        true => execute!(
+                            session.stderr,
+                            style::Print(format!("  {} ", setting.key())),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print("<not set>\n"),
+                            style::SetForegroundColor(Color::Reset)
+                        )?,
+                    }
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// The values of a setting as shown, quoted and separated by commas.
+fn display_values(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| format!("'{value}'"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Checks that a setting's value fits on a line of the context.
+fn validate_value(value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Err("the value is empty".to_string());
+    }
+    if value.chars().count() > MAX_VALUE_CHARS {
+        return Err(format!("the value is longer than {MAX_VALUE_CHARS} characters"));
+    }
+    if value.chars().any(char::is_control) {
+        return Err("the value contains control characters".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use clap::Subcommand;
+use crossterm::style::Color;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Reads each completed response aloud, skipping code blocks. Speech uses say on macOS,
+espeak on Linux and System.Speech on Windows. Pick a voice with \"q settings chat.speakVoice <VOICE>\"
+and a rate in words per minute with \"q settings chat.speakRate <RATE>\"."
+)]
+pub enum SpeakSubcommand {
+    /// Start reading responses aloud
+    On,
+    /// Stop reading responses aloud
+    Off,
+}
+
+impl SpeakSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::On => {
+                let mut speaker = session.speaker.take().unwrap_or_default();
+                match speaker.speak(os, "Reading responses aloud").await {
+                    Ok(()) => {
+                        session.speaker = Some(speaker);
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Green),
+                            style::Print("\n✔ Responses will be read aloud. Use /speak off to stop.\n\n"),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    },
+                    Err(err) => execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nCouldn't start text-to-speech: {err}\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?,
+                }
+            },
+            Self::Off => {
+                if let Some(mut speaker) = session.speaker.take() {
+                    speaker.stop();
+                }
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("\n✔ Responses will no longer be read aloud\n\n"),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
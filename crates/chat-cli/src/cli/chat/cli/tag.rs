@@ -0,0 +1,96 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use tracing::warn;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+    autosave,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Tags are saved with the conversation, both in the session store and by /save. Use
+'/sessions list --tag <name>' to find stored sessions with a tag."
+)]
+pub struct TagArgs {
+    /// Tag to add, e.g. a project or topic. Lists the conversation's tags if omitted
+    name: Option<String>,
+    /// Remove the tag instead of adding it
+    #[arg(long, requires = "name")]
+    remove: bool,
+}
+
+impl TagArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Some(name) = self.name.map(|name| name.trim().to_string()) else {
+            let message = match session.conversation.tags.is_empty() {
+                true => "\nThis conversation has no tags. Use /tag <name> to add one.\n\n".to_string(),
+                false => format!(
+                    "\nTags: {}\n\n",
+                    session.conversation.tags.iter().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            };
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(message),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print("\nTags must not be empty or contain spaces\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        } else if self.remove {
+            match session.conversation.tags.remove(&name) {
+                true => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\nRemoved tag '{name}'\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+                false => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nThis conversation is not tagged '{name}'\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            }
+        } else {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ Tagged this conversation '{name}'\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            session.conversation.tags.insert(name);
+        }
+
+        // Update the stored session now rather than after the next turn, so that the tag shows up
+        // in /sessions list straight away.
+        if session.interactive && !session.conversation.history().is_empty() && autosave::is_enabled(os) {
+            if let Err(err) = autosave::write(os, session, true).await {
+                warn!(?err, "failed to save the session's tags");
+            }
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
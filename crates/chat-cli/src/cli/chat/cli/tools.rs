@@ -13,16 +13,28 @@ use crossterm::{
     queue,
     style,
 };
+use serde_json::{
+    Value,
+    json,
+};
 
 use crate::api_client::model::Tool as FigTool;
 use crate::cli::chat::consts::DUMMY_TOOL_NAME;
-use crate::cli::chat::tools::ToolOrigin;
+use crate::cli::chat::message::AssistantToolUse;
+use crate::cli::chat::tool_presets::ToolPreset;
+use crate::cli::chat::tools::{
+    PermissionDecision,
+    PermissionRule,
+    ToolOrigin,
+};
+use crate::cli::chat::util::layout;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
     TRUST_ALL_TEXT,
 };
+use crate::os::Os;
 
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
@@ -32,37 +44,14 @@ pub struct ToolsArgs {
 }
 
 impl ToolsArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         if let Some(subcommand) = self.subcommand {
-            return subcommand.execute(session).await;
+            return subcommand.execute(os, session).await;
         }
 
-        // No subcommand - print the current tools and their permissions.
-        // Determine how to format the output nicely.
+        // No subcommand - print the current tools and their permissions, in a table fitted to the
+        // terminal.
         let terminal_width = session.terminal_width();
-        let longest = session
-            .conversation
-            .tools
-            .values()
-            .flatten()
-            .map(|FigTool::ToolSpecification(spec)| spec.name.len())
-            .max()
-            .unwrap_or(0);
-
-        queue!(
-            session.stderr,
-            style::Print("\n"),
-            style::SetAttribute(Attribute::Bold),
-            style::Print({
-                // Adding 2 because of "- " preceding every tool name
-                let width = longest + 2 - "Tool".len() + 4;
-                format!("Tool{:>width$}Permission", "", width = width)
-            }),
-            style::SetAttribute(Attribute::Reset),
-            style::Print("\n"),
-            style::Print("▔".repeat(terminal_width)),
-        )?;
-
         let mut origin_tools: Vec<_> = session.conversation.tools.iter().collect();
 
         // Built in tools always appear first.
@@ -72,32 +61,39 @@ impl ToolsArgs {
             (ToolOrigin::McpServer(name_a), ToolOrigin::McpServer(name_b)) => name_a.cmp(name_b),
         });
 
+        let mut rows = vec![vec!["Tool".to_string(), "Permission".to_string()]];
+        let mut sections = Vec::new();
         for (origin, tools) in origin_tools.iter() {
             let mut sorted_tools: Vec<_> = tools
                 .iter()
-                .filter(|FigTool::ToolSpecification(spec)| spec.name != DUMMY_TOOL_NAME)
+                .map(|FigTool::ToolSpecification(spec)| &spec.name)
+                .filter(|name| *name != DUMMY_TOOL_NAME)
                 .collect();
+            sorted_tools.sort();
 
-            sorted_tools.sort_by_key(|t| match t {
-                FigTool::ToolSpecification(spec) => &spec.name,
-            });
+            sections.push((origin.to_string(), sorted_tools.len()));
+            for name in sorted_tools {
+                rows.push(vec![format!("- {name}"), session.tool_permissions.display_label(name)]);
+            }
+        }
+        let mut table = layout::table(&rows, terminal_width, 4).into_iter();
 
-            let to_display = sorted_tools
-                .iter()
-                .fold(String::new(), |mut acc, FigTool::ToolSpecification(spec)| {
-                    let width = longest - spec.name.len() + 4;
-                    acc.push_str(
-                        format!(
-                            "- {}{:>width$}{}\n",
-                            spec.name,
-                            "",
-                            session.tool_permissions.display_label(&spec.name),
-                            width = width
-                        )
-                        .as_str(),
-                    );
-                    acc
-                });
+        queue!(
+            session.stderr,
+            style::Print("\n"),
+            style::SetAttribute(Attribute::Bold),
+            style::Print(table.next().unwrap_or_default().join("\n")),
+            style::SetAttribute(Attribute::Reset),
+            style::Print("\n"),
+            style::Print("▔".repeat(terminal_width)),
+        )?;
+
+        for (origin, count) in sections {
+            let to_display = table.by_ref().take(count).fold(String::new(), |mut acc, lines| {
+                acc.push_str(&lines.join("\n"));
+                acc.push('\n');
+                acc
+            });
 
             let _ = queue!(
                 session.stderr,
@@ -146,7 +142,8 @@ impl ToolsArgs {
 #[derive(Debug, PartialEq, Subcommand)]
 #[command(
     before_long_help = "By default, Amazon Q will ask for your permission to use certain tools. You can control which tools you
-trust so that no confirmation is required. These settings will last only for this session."
+trust so that no confirmation is required. These settings will last only for this session, apart from
+the presets of /tools preset, which are saved with the profile."
 )]
 pub enum ToolsSubcommand {
     /// Show the input schema for all available tools
@@ -163,14 +160,41 @@ pub enum ToolsSubcommand {
     },
     /// Trust all tools (equivalent to deprecated /acceptall)
     TrustAll,
-    /// Reset all tools to default permission levels
-    Reset,
+    /// Reset a tool, or all tools if none is given, to the default permission level
+    Reset {
+        /// Tool to reset
+        #[arg(conflicts_with = "all")]
+        tool_name: Option<String>,
+        /// Reset all tools
+        #[arg(long)]
+        all: bool,
+    },
     /// Reset a single tool to default permission level
+    #[command(hide = true)]
     ResetSingle { tool_name: String },
+    /// Show which permission rule decides whether a use of a tool runs, is confirmed first or is
+    /// denied, e.g. /tools why execute_bash git push
+    Why {
+        /// The tool
+        tool_name: String,
+        /// The tool's input, as a JSON object or key=value pairs, or the command of execute_bash
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        input: Vec<String>,
+    },
+    /// Set the permissions of the built-in tools to a preset, saved with the profile, showing
+    /// what it changes
+    Preset {
+        /// The preset
+        #[arg(value_enum)]
+        preset: ToolPreset,
+        /// Only show what the preset would change
+        #[arg(long)]
+        preview: bool,
+    },
 }
 
 impl ToolsSubcommand {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         let existing_tools: HashSet<&String> = session
             .conversation
             .tools
@@ -277,7 +301,15 @@ impl ToolsSubcommand {
                     });
                 queue!(session.stderr, style::Print(TRUST_ALL_TEXT), style::Print("\n"))?;
             },
-            Self::Reset => {
+            Self::Reset { tool_name: None, .. } if !session.tool_permissions.is_modified() => {
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("All tools already have their default permission levels.\n"),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+            Self::Reset { tool_name: None, .. } => {
                 session.tool_permissions.reset();
                 queue!(
                     session.stderr,
@@ -286,7 +318,11 @@ impl ToolsSubcommand {
                     style::SetForegroundColor(Color::Reset),
                 )?;
             },
-            Self::ResetSingle { tool_name } => {
+            Self::Reset {
+                tool_name: Some(tool_name),
+                ..
+            }
+            | Self::ResetSingle { tool_name } => {
                 if session.tool_permissions.has(&tool_name) || session.tool_permissions.trust_all {
                     session.tool_permissions.reset_tool(&tool_name);
                     queue!(
@@ -307,6 +343,185 @@ impl ToolsSubcommand {
                     )?;
                 }
             },
+            Self::Why { tool_name, input } => {
+                let tool = match tool_input(&tool_name, &input) {
+                    _ if !existing_tools.contains(&tool_name) => Err("it does not exist".to_string()),
+                    Ok(args) => session
+                        .conversation
+                        .tool_manager
+                        .get_tool_from_tool_use(AssistantToolUse {
+                            id: "tools_why".to_string(),
+                            name: tool_name.clone(),
+                            orig_name: tool_name.clone(),
+                            args: args.clone(),
+                            orig_args: args,
+                        })
+                        .map_err(|_result| "the input doesn't match its schema, see /tools schema".to_string()),
+                    Err(err) => Err(err),
+                };
+                let tool = match tool {
+                    Ok(tool) => tool,
+                    Err(err) => {
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\nCannot explain the permission of '{tool_name}': {err}\n\n")),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                        session.stderr.flush()?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+
+                let requires_acceptance = tool.requires_acceptance(os);
+                let read_only = session.is_read_only(os).await;
+                let permissions = &mut session.tool_permissions;
+                let (decision, decided_by) = permissions.evaluate(&tool_name, requires_acceptance, read_only);
+                let (has, trusted) = (permissions.has(&tool_name), permissions.is_trusted(&tool_name));
+                let (trust_all, always_ask) = (permissions.trust_all, permissions.always_ask.contains(&tool_name));
+
+                queue!(
+                    session.stderr,
+                    style::SetAttribute(Attribute::Bold),
+                    style::Print(format!("\nPermission of '{tool_name}' for this input:\n")),
+                    style::SetAttribute(Attribute::Reset),
+                )?;
+                let mut decided = false;
+                for rule in PermissionRule::ALL {
+                    let (label, detail) = match rule {
+                        PermissionRule::ReadOnlySession => ("Read-only session", match read_only {
+                            true => "another session is editing the workspace",
+                            false => "this session can edit the workspace",
+                        }),
+                        PermissionRule::TrustAll => ("/tools trust-all", match trust_all {
+                            true => "every tool is trusted",
+                            false => "not set",
+                        }),
+                        PermissionRule::SessionTrust => ("/tools trust", match (has, trusted) {
+                            (_, true) => "trusted for this session",
+                            (true, false) => "set to per-request confirmation with /tools untrust",
+                            (false, false) => "not set",
+                        }),
+                        PermissionRule::AskEveryUse => ("Ask every use", match always_ask {
+                            true => "set with the strict preset",
+                            false => "not set",
+                        }),
+                        PermissionRule::ToolDefault => ("Tool default", match requires_acceptance {
+                            true => "asks for confirmation of this input",
+                            false => "runs this input without confirmation",
+                        }),
+                    };
+                    let (symbol, color) = match (decided, rule == decided_by) {
+                        (true, _) => ("·", Color::DarkGrey),
+                        (false, true) => ("✓", Color::Green),
+                        (false, false) => ("✗", Color::DarkGrey),
+                    };
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(color),
+                        style::Print(format!("  {symbol} {label:<20}")),
+                        style::Print(if decided { "not evaluated" } else { detail }),
+                        style::SetForegroundColor(Color::Reset),
+                        style::Print("\n"),
+                    )?;
+                    decided |= rule == decided_by;
+                }
+                let (decision, color) = match decision {
+                    PermissionDecision::Allow => ("runs without confirmation", Color::Green),
+                    PermissionDecision::Ask => ("asks for confirmation first", Color::Yellow),
+                    PermissionDecision::Deny => ("is denied", Color::Red),
+                };
+                queue!(
+                    session.stderr,
+                    style::Print("\nThe tool "),
+                    style::SetForegroundColor(color),
+                    style::Print(decision),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print(".\n\n"),
+                )?;
+            },
+            Self::Preset { preset, preview } => {
+                let changes = preset.changes(&mut session.tool_permissions);
+                let saved = session
+                    .conversation
+                    .context_manager
+                    .as_ref()
+                    .and_then(|context_manager| context_manager.tool_preset());
+                if changes.is_empty() && (preview || saved == Some(preset)) {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!(
+                            "\nThe tools already have the permissions of the '{preset}' preset.\n\n"
+                        )),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    session.stderr.flush()?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                if !changes.is_empty() {
+                    queue!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(format!("\nThe '{preset}' preset changes:\n")),
+                        style::SetAttribute(Attribute::Reset),
+                    )?;
+                    for (tool_name, current, level) in &changes {
+                        queue!(
+                            session.stderr,
+                            style::Print(format!("  {tool_name:<14}")),
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(current.to_string()),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print(" → "),
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(level.to_string()),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print("\n"),
+                        )?;
+                    }
+                }
+
+                if preview {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("\nApply it with /tools preset {preset}.\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                } else {
+                    preset.apply(&mut session.tool_permissions);
+                    let saved = match &mut session.conversation.context_manager {
+                        Some(context_manager) => context_manager
+                            .set_tool_preset(os, preset)
+                            .await
+                            .map(|()| Some(context_manager.current_profile.clone())),
+                        None => Ok(None),
+                    };
+                    let (color, message) = match saved {
+                        Ok(Some(profile)) => (
+                            Color::Green,
+                            format!("Applied the '{preset}' preset, and saved it to profile '{profile}'."),
+                        ),
+                        Ok(None) => (Color::Green, format!("Applied the '{preset}' preset for this session.")),
+                        Err(e) => (
+                            Color::Red,
+                            format!("Applied the '{preset}' preset for this session, but cannot save it: {e}"),
+                        ),
+                    };
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(color),
+                        style::Print(format!("\n{message}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+            },
         };
 
         session.stderr.flush()?;
@@ -316,3 +531,54 @@ impl ToolsSubcommand {
         })
     }
 }
+
+/// The input of a use of `tool_name` from the arguments of `/tools why`: a JSON object, `key=value`
+/// pairs whose values are JSON or else strings, or the command of `execute_bash`.
+fn tool_input(tool_name: &str, args: &[String]) -> Result<Value, String> {
+    if args.is_empty() {
+        return Ok(json!({}));
+    }
+    let joined = args.join(" ");
+    if let Ok(input @ Value::Object(_)) = serde_json::from_str::<Value>(&joined) {
+        return Ok(input);
+    }
+    if args.iter().all(|arg| arg.contains('=')) {
+        let input = args
+            .iter()
+            .filter_map(|arg| arg.split_once('='))
+            .map(|(key, value)| {
+                let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+                (key.to_string(), value)
+            })
+            .collect();
+        return Ok(Value::Object(input));
+    }
+    match tool_name {
+        "execute_bash" | "execute_cmd" => Ok(json!({ "command": joined })),
+        _ => Err("give its input as a JSON object or as key=value pairs".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_input() {
+        let args = |args: &[&str]| args.iter().map(|arg| (*arg).to_string()).collect::<Vec<_>>();
+        assert_eq!(tool_input("fs_read", &[]), Ok(json!({})));
+        assert_eq!(
+            tool_input("fs_write", &args(&[r#"{"command": "create", "path": "a.rs"}"#])),
+            Ok(json!({"command": "create", "path": "a.rs"}))
+        );
+        assert_eq!(
+            tool_input("fs_read", &args(&["mode=Line", "path=src/main.rs", "start_line=3"])),
+            Ok(json!({"mode": "Line", "path": "src/main.rs", "start_line": 3}))
+        );
+        assert_eq!(
+            tool_input("execute_bash", &args(&["git", "log", "--format=%h"])),
+            Ok(json!({"command": "git log --format=%h"}))
+        );
+        assert!(tool_input("fs_write", &args(&["create", "a.rs"])).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use clap::Args;
+use clap::builder::RangedU64ValueParser;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::consts::CONTEXT_WINDOW_SIZE;
+use crate::cli::chat::token_counter::TokenCount;
+use crate::cli::chat::util::format::LocaleFormatter;
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Removes the most recent turns from the conversation, including any tool uses and results that
+followed each prompt. Files changed by tools are not restored."
+)]
+pub struct UndoArgs {
+    /// Number of turns to remove
+    #[arg(default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    turns: usize,
+}
+
+impl UndoArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let removed = session.conversation.undo_turns(self.turns);
+        if removed.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nThere is nothing to undo.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        // Any tool uses awaiting approval belonged to the turn that was just removed.
+        session.tool_uses.clear();
+        session.pending_tool_index = None;
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!(
+                "\nRemoved {} {}:\n",
+                removed.len(),
+                if removed.len() == 1 { "turn" } else { "turns" }
+            )),
+            style::SetForegroundColor(Color::DarkGrey),
+        )?;
+        for prompt in &removed {
+            let first_line = prompt.lines().next().unwrap_or_default();
+            let ellipsis = if first_line.len() > 60 || prompt.lines().nth(1).is_some() {
+                "…"
+            } else {
+                ""
+            };
+            execute!(
+                session.stderr,
+                style::Print(format!("  > {}{ellipsis}\n", truncate_safe(first_line, 60)))
+            )?;
+        }
+
+        let tokens: TokenCount = session.conversation.calculate_char_count(os).await?.into();
+        let fmt = LocaleFormatter::new(os);
+        execute!(
+            session.stderr,
+            style::Print(format!(
+                "\nContext window: {} used (~{} tokens)\n\n",
+                fmt.percent(tokens.value() as f64 / CONTEXT_WINDOW_SIZE as f64 * 100.0, 2),
+                fmt.integer(tokens.value())
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
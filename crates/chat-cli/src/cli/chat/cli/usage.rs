@@ -14,6 +14,7 @@ use crate::cli::chat::token_counter::{
     CharCount,
     TokenCount,
 };
+use crate::cli::chat::util::format::LocaleFormatter;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -58,6 +59,9 @@ impl UsageArgs {
         let tools_token_count: TokenCount = tools_char_count.into(); // CharCount → TokenCount
         let total_token_used: TokenCount =
             (data.context_messages + data.user_messages + data.assistant_messages + tools_char_count).into();
+        let fmt = LocaleFormatter::new(os);
+        let percent_of_window =
+            |count: TokenCount| fmt.percent((count.value() as f64 / CONTEXT_WINDOW_SIZE as f64) * 100.0, 2);
         let window_width = session.terminal_width();
         // set a max width for the progress bar for better aesthetic
         let progress_bar_width = std::cmp::min(window_width, 80);
@@ -84,25 +88,22 @@ impl UsageArgs {
                 session.stderr,
                 style::Print(format!(
                     "\nCurrent context window ({} of {}k tokens used)\n",
-                    total_token_used,
-                    CONTEXT_WINDOW_SIZE / 1000
+                    fmt.integer(total_token_used.value()),
+                    fmt.integer(CONTEXT_WINDOW_SIZE / 1000)
                 )),
                 style::SetForegroundColor(Color::DarkRed),
                 style::Print("█".repeat(progress_bar_width)),
                 style::SetForegroundColor(Color::Reset),
                 style::Print(" "),
-                style::Print(format!(
-                    "{:.2}%",
-                    (total_token_used.value() as f32 / CONTEXT_WINDOW_SIZE as f32) * 100.0
-                )),
+                style::Print(percent_of_window(total_token_used)),
             )?;
         } else {
             queue!(
                 session.stderr,
                 style::Print(format!(
                     "\nCurrent context window ({} of {}k tokens used)\n",
-                    total_token_used,
-                    CONTEXT_WINDOW_SIZE / 1000
+                    fmt.integer(total_token_used.value()),
+                    fmt.integer(CONTEXT_WINDOW_SIZE / 1000)
                 )),
                 // Context files
                 style::SetForegroundColor(Color::DarkCyan),
@@ -138,10 +139,7 @@ impl UsageArgs {
                 style::Print("█".repeat(left_over_width)),
                 style::Print(" "),
                 style::SetForegroundColor(Color::Reset),
-                style::Print(format!(
-                    "{:.2}%",
-                    (total_token_used.value() as f32 / CONTEXT_WINDOW_SIZE as f32) * 100.0
-                )),
+                style::Print(percent_of_window(total_token_used)),
             )?;
         }
 
@@ -153,33 +151,33 @@ impl UsageArgs {
             style::Print("█ Context files: "),
             style::SetForegroundColor(Color::Reset),
             style::Print(format!(
-                "~{} tokens ({:.2}%)\n",
-                context_token_count,
-                (context_token_count.value() as f32 / CONTEXT_WINDOW_SIZE as f32) * 100.0
+                "~{} tokens ({})\n",
+                fmt.integer(context_token_count.value()),
+                percent_of_window(context_token_count)
             )),
             style::SetForegroundColor(Color::DarkRed),
             style::Print("█ Tools:    "),
             style::SetForegroundColor(Color::Reset),
             style::Print(format!(
-                " ~{} tokens ({:.2}%)\n",
-                tools_token_count,
-                (tools_token_count.value() as f32 / CONTEXT_WINDOW_SIZE as f32) * 100.0
+                " ~{} tokens ({})\n",
+                fmt.integer(tools_token_count.value()),
+                percent_of_window(tools_token_count)
             )),
             style::SetForegroundColor(Color::Blue),
             style::Print("█ Q responses: "),
             style::SetForegroundColor(Color::Reset),
             style::Print(format!(
-                "  ~{} tokens ({:.2}%)\n",
-                assistant_token_count,
-                (assistant_token_count.value() as f32 / CONTEXT_WINDOW_SIZE as f32) * 100.0
+                "  ~{} tokens ({})\n",
+                fmt.integer(assistant_token_count.value()),
+                percent_of_window(assistant_token_count)
             )),
             style::SetForegroundColor(Color::Magenta),
             style::Print("█ Your prompts: "),
             style::SetForegroundColor(Color::Reset),
             style::Print(format!(
-                " ~{} tokens ({:.2}%)\n\n",
-                user_token_count,
-                (user_token_count.value() as f32 / CONTEXT_WINDOW_SIZE as f32) * 100.0
+                " ~{} tokens ({})\n\n",
+                fmt.integer(user_token_count.value()),
+                percent_of_window(user_token_count)
             )),
         )?;
 
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use clap::Subcommand;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+use regex::Regex;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+
+static VARIABLE_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_-]*$").unwrap());
+static VARIABLE_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").unwrap());
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Variables are referenced in prompts as {{name}} and replaced with their value before the prompt is sent.
+They are kept for the rest of the session, and are included when the conversation is saved with /save."
+)]
+pub enum VarSubcommand {
+    /// Set a variable
+    Set {
+        /// Name of the variable, referenced as {{name}}
+        name: String,
+        /// Value of the variable
+        #[arg(required = true, num_args = 1..)]
+        value: Vec<String>,
+    },
+    /// List all variables
+    List,
+    /// Remove a variable
+    #[command(name = "rm")]
+    Remove {
+        /// Name of the variable
+        name: String,
+    },
+}
+
+/// `/set var` is the same as `/var set`.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum SetSubcommand {
+    /// Set a variable, same as /var set
+    Var {
+        /// Name of the variable, referenced as {{name}}
+        name: String,
+        /// Value of the variable
+        #[arg(required = true, num_args = 1..)]
+        value: Vec<String>,
+    },
+}
+
+impl SetSubcommand {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::Var { name, value } => VarSubcommand::Set { name, value }.execute(session).await,
+        }
+    }
+}
+
+impl VarSubcommand {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let variables = &mut session.conversation.variables;
+        match self {
+            Self::Set { name, value } => {
+                if !VARIABLE_NAME.is_match(&name) {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!(
+                            "\nInvalid variable name '{name}'. Names may contain letters, numbers, '_' and '-', and must not start with a number.\n\n"
+                        )),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                } else {
+                    let value = value.join(" ");
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("\nSet {{{{{name}}}}} = {value}\n\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                    variables.insert(name, value);
+                }
+            },
+            Self::List => {
+                if variables.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("\nNo variables set. Use /var set <name> <value> to define one.\n\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                } else {
+                    execute!(session.stderr, style::Print("\n"))?;
+                    for (name, value) in variables.iter() {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Cyan),
+                            style::Print(format!("{{{{{name}}}}}")),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print(format!(" = {value}\n")),
+                        )?;
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                }
+            },
+            Self::Remove { name } => match variables.remove(&name) {
+                Some(_) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\nRemoved variable '{name}'\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+                None => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nVariable '{name}' does not exist\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Replaces every `{{name}}` in `input` with the value of the variable `name`.
+///
+/// References to undefined variables are left untouched and returned so they can be reported.
+pub fn interpolate_variables(input: &str, variables: &BTreeMap<String, String>) -> (String, Vec<String>) {
+    let mut undefined = Vec::new();
+    let output = VARIABLE_REFERENCE.replace_all(input, |caps: &regex::Captures<'_>| match variables.get(&caps[1]) {
+        Some(value) => value.clone(),
+        None => {
+            if !undefined.iter().any(|name| name == &caps[1]) {
+                undefined.push(caps[1].to_string());
+            }
+            caps[0].to_string()
+        },
+    });
+
+    (output.into_owned(), undefined)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::chat::cli::SlashCommand;
+
+    #[test]
+    fn test_interpolate_variables() {
+        let variables = BTreeMap::from([
+            ("service".to_string(), "billing-api".to_string()),
+            ("env".to_string(), "prod".to_string()),
+        ]);
+
+        assert_eq!(
+            interpolate_variables("Check {{service}} logs in {{ env }}", &variables),
+            ("Check billing-api logs in prod".to_string(), vec![])
+        );
+        assert_eq!(
+            interpolate_variables("{{region}} {{service}} {{region}}", &variables),
+            ("{{region}} billing-api {{region}}".to_string(), vec![
+                "region".to_string()
+            ])
+        );
+        assert_eq!(
+            interpolate_variables("no variables, {not one}", &variables),
+            ("no variables, {not one}".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_variable_name() {
+        assert!(VARIABLE_NAME.is_match("service"));
+        assert!(VARIABLE_NAME.is_match("my-var_2"));
+        assert!(!VARIABLE_NAME.is_match("2fast"));
+        assert!(!VARIABLE_NAME.is_match("has space"));
+    }
+
+    #[test]
+    fn test_set_var() {
+        assert_eq!(
+            SlashCommand::try_parse_from(["slash_command", "set", "var", "service", "billing", "api"]).unwrap(),
+            SlashCommand::Set(SetSubcommand::Var {
+                name: "service".to_string(),
+                value: vec!["billing".to_string(), "api".to_string()],
+            })
+        );
+    }
+}
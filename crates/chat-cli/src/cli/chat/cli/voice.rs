@@ -0,0 +1,152 @@
+use clap::Args;
+use crossterm::style::Color;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Records speech and transcribes it into the prompt, where it can be edited before sending.
+
+Voice input is only available in builds with the \"voice\" feature. By default it records with sox
+until two seconds of silence and transcribes with whisper.cpp's whisper-cli, using the model set with
+\"q settings chat.voiceModel <PATH>\". To use another recorder or a dictation bridge instead, set
+\"q settings chat.voiceCommand <COMMAND>\" to a shell command that prints the transcript."
+)]
+pub struct VoiceArgs;
+
+impl VoiceArgs {
+    #[cfg(feature = "voice")]
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Cyan),
+            style::Print("\n🎙  Listening... recording stops after two seconds of silence\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        match transcribe::transcribe(os).await {
+            Ok(transcript) if transcript.is_empty() => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nNothing was transcribed.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+            Ok(transcript) => {
+                session.input_source.set_initial_text(transcript);
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("\n✔ Transcribed. Review the prompt and press enter to send it.\n\n"),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            },
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nVoice input failed: {err}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    #[cfg(not(feature = "voice"))]
+    pub async fn execute(self, _os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print("\nVoice input isn't available in this build. Build with "),
+            style::SetForegroundColor(Color::Green),
+            style::Print("--features voice"),
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(" to enable /voice.\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+#[cfg(feature = "voice")]
+mod transcribe {
+    use std::process::Stdio;
+
+    use eyre::{
+        Result,
+        bail,
+    };
+    use tokio::process::Command;
+
+    use crate::database::settings::Setting;
+    use crate::os::Os;
+
+    /// Records until the speaker stops and returns the transcript.
+    pub async fn transcribe(os: &Os) -> Result<String> {
+        let output = match os.database.settings.get_string(Setting::ChatVoiceCommand) {
+            Some(command) => run(shell(&command)).await?,
+            None => {
+                let Some(model) = os.database.settings.get_string(Setting::ChatVoiceModel) else {
+                    bail!(
+                        "Set a whisper.cpp model with 'q settings chat.voiceModel <PATH>', or a transcription command with 'q settings chat.voiceCommand <COMMAND>'"
+                    );
+                };
+                let recording = tempfile::Builder::new().suffix(".wav").tempfile()?;
+                let wav = recording.path().to_string_lossy().to_string();
+
+                let mut record = Command::new("sox");
+                record.args(["-q", "-d", "-r", "16000", "-c", "1", "-b", "16", &wav]);
+                // Start on sound and stop after two seconds of silence.
+                record.args(["silence", "1", "0.1", "1%", "1", "2.0", "1%"]);
+                run(record).await?;
+
+                let mut whisper = Command::new("whisper-cli");
+                whisper.args(["-m", &model, "-f", &wav, "--no-timestamps", "--no-prints"]);
+                run(whisper).await?
+            },
+        };
+        Ok(output.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    fn shell(command: &str) -> Command {
+        #[cfg(unix)]
+        let mut shell = Command::new("bash");
+        #[cfg(unix)]
+        shell.arg("-c");
+        #[cfg(windows)]
+        let mut shell = Command::new("cmd");
+        #[cfg(windows)]
+        shell.arg("/C");
+        shell.arg(command);
+        shell
+    }
+
+    async fn run(mut command: Command) -> Result<String> {
+        let program = command.as_std().get_program().to_string_lossy().to_string();
+        let output = match command.stdin(Stdio::null()).output().await {
+            Ok(output) => output,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                bail!("'{program}' was not found, is it installed and on your PATH?")
+            },
+            Err(err) => return Err(err.into()),
+        };
+        if !output.status.success() {
+            bail!("'{program}' failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
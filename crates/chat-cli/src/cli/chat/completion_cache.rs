@@ -0,0 +1,284 @@
+//! Session values offered by the prompt completer, such as tool names, profiles and tags.
+//!
+//! The session fills a [CompletionCache] before each prompt and hands it to the completer, which
+//! looks values up by [CompletionCategory] rather than by name, so a command can't ask for a
+//! category that nothing fills in.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::cli::chat::context::ContextSnapshot;
+
+/// A kind of value that can be completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionCategory {
+    /// Tools that are, or are not, trusted for this session.
+    Tools { trusted: bool },
+    /// Tools with a permission that differs from their default.
+    ModifiedTools,
+    /// Context files in use, relative to the current directory where possible.
+    ContextFiles,
+    /// Context rules, either global or for the current profile.
+    ContextRules { global: bool },
+    /// Context hooks, either global or for the current profile, that are or are not disabled.
+    Hooks { global: bool, disabled: bool },
+    /// Context profile names.
+    Profiles,
+    /// Tags used by stored sessions.
+    Tags,
+}
+
+/// Where a command's last argument is completed from a category.
+pub struct ArgumentCompletion {
+    /// The words before the argument, e.g. `["/tools", "reset"]`.
+    pub command: &'static [&'static str],
+    /// Fixed values offered before the category's values.
+    pub options: &'static [&'static str],
+    pub category: CompletionCategory,
+}
+
+pub const ARGUMENT_COMPLETIONS: &[ArgumentCompletion] = &[
+    ArgumentCompletion {
+        command: &["/tools", "trust"],
+        options: &[],
+        category: CompletionCategory::Tools { trusted: false },
+    },
+    ArgumentCompletion {
+        command: &["/tools", "untrust"],
+        options: &[],
+        category: CompletionCategory::Tools { trusted: true },
+    },
+    ArgumentCompletion {
+        command: &["/tools", "reset"],
+        options: &["--all"],
+        category: CompletionCategory::ModifiedTools,
+    },
+    ArgumentCompletion {
+        command: &["/context", "rm"],
+        options: &[],
+        category: CompletionCategory::ContextRules { global: false },
+    },
+    ArgumentCompletion {
+        command: &["/context", "rm", "--global"],
+        options: &[],
+        category: CompletionCategory::ContextRules { global: true },
+    },
+    ArgumentCompletion {
+        command: &["/context", "remove"],
+        options: &[],
+        category: CompletionCategory::ContextRules { global: false },
+    },
+    ArgumentCompletion {
+        command: &["/context", "remove", "--global"],
+        options: &[],
+        category: CompletionCategory::ContextRules { global: true },
+    },
+    ArgumentCompletion {
+        command: &["/hooks", "enable"],
+        options: &[],
+        category: CompletionCategory::Hooks {
+            global: false,
+            disabled: true,
+        },
+    },
+    ArgumentCompletion {
+        command: &["/hooks", "enable", "--global"],
+        options: &[],
+        category: CompletionCategory::Hooks {
+            global: true,
+            disabled: true,
+        },
+    },
+    ArgumentCompletion {
+        command: &["/hooks", "disable"],
+        options: &[],
+        category: CompletionCategory::Hooks {
+            global: false,
+            disabled: false,
+        },
+    },
+    ArgumentCompletion {
+        command: &["/hooks", "disable", "--global"],
+        options: &[],
+        category: CompletionCategory::Hooks {
+            global: true,
+            disabled: false,
+        },
+    },
+    ArgumentCompletion {
+        command: &["/profile", "set"],
+        options: &[],
+        category: CompletionCategory::Profiles,
+    },
+    ArgumentCompletion {
+        command: &["/profile", "delete"],
+        options: &[],
+        category: CompletionCategory::Profiles,
+    },
+    ArgumentCompletion {
+        command: &["/profile", "rename"],
+        options: &[],
+        category: CompletionCategory::Profiles,
+    },
+    ArgumentCompletion {
+        command: &["/tag"],
+        options: &[],
+        category: CompletionCategory::Tags,
+    },
+    ArgumentCompletion {
+        command: &["/tag", "--remove"],
+        options: &[],
+        category: CompletionCategory::Tags,
+    },
+    ArgumentCompletion {
+        command: &["/sessions", "list", "--tag"],
+        options: &[],
+        category: CompletionCategory::Tags,
+    },
+];
+
+/// Completion values by category. Categories that were never set have no values.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionCache {
+    values: HashMap<CompletionCategory, Vec<String>>,
+}
+
+impl CompletionCache {
+    pub fn set(&mut self, category: CompletionCategory, values: Vec<String>) {
+        self.values.insert(category, values);
+    }
+
+    pub fn get(&self, category: CompletionCategory) -> &[String] {
+        self.values.get(&category).map_or(&[], Vec::as_slice)
+    }
+
+    /// Sets the categories of the context from `snapshot`, with its files relative to `cwd`
+    /// where possible.
+    pub fn set_context(&mut self, snapshot: &ContextSnapshot, cwd: Option<&Path>) {
+        let files = snapshot
+            .files
+            .iter()
+            .map(|path| {
+                cwd.and_then(|cwd| Path::new(path).strip_prefix(cwd).ok())
+                    .map_or(path.clone(), |relative| relative.to_string_lossy().to_string())
+            })
+            .collect();
+        self.set(CompletionCategory::ContextFiles, files);
+        self.set(
+            CompletionCategory::ContextRules { global: true },
+            snapshot.global_paths.clone(),
+        );
+        self.set(
+            CompletionCategory::ContextRules { global: false },
+            snapshot.profile_paths.clone(),
+        );
+        for global in [true, false] {
+            for disabled in [true, false] {
+                let hooks = snapshot
+                    .hooks
+                    .iter()
+                    .filter(|hook| hook.is_global == global && hook.disabled == disabled)
+                    .map(|hook| hook.name.clone())
+                    .collect();
+                self.set(CompletionCategory::Hooks { global, disabled }, hooks);
+            }
+        }
+        self.set(CompletionCategory::Profiles, snapshot.profiles.clone());
+    }
+
+    /// Completes the last argument of the command in `words`, or returns `None` if the command
+    /// doesn't have one listed in [ARGUMENT_COMPLETIONS].
+    pub fn complete_argument(&self, words: &[&str], prefix: &str) -> Option<Vec<String>> {
+        let completion = ARGUMENT_COMPLETIONS
+            .iter()
+            .find(|completion| completion.command == words)?;
+        Some(
+            completion
+                .options
+                .iter()
+                .copied()
+                .chain(self.get(completion.category).iter().map(String::as_str))
+                .filter(|candidate| candidate.starts_with(prefix))
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::cli::hooks::{
+        Hook,
+        HookTrigger,
+    };
+
+    #[test]
+    fn test_complete_argument() {
+        let mut cache = CompletionCache::default();
+        cache.set(CompletionCategory::Tools { trusted: true }, vec!["fs_read".to_string()]);
+        cache.set(CompletionCategory::Tools { trusted: false }, vec![
+            "execute_bash".to_string(),
+            "fs_write".to_string(),
+        ]);
+
+        assert_eq!(
+            cache.complete_argument(&["/tools", "trust"], "fs"),
+            Some(vec!["fs_write".to_string()])
+        );
+        assert_eq!(
+            cache.complete_argument(&["/tools", "untrust"], ""),
+            Some(vec!["fs_read".to_string()])
+        );
+        assert_eq!(
+            cache.complete_argument(&["/tools", "reset"], ""),
+            Some(vec!["--all".to_string()])
+        );
+        assert_eq!(cache.complete_argument(&["/tools"], ""), None);
+
+        cache.set(
+            CompletionCategory::Hooks {
+                global: false,
+                disabled: true,
+            },
+            vec!["lint".to_string()],
+        );
+        assert_eq!(
+            cache.complete_argument(&["/hooks", "enable"], ""),
+            Some(vec!["lint".to_string()])
+        );
+        assert_eq!(cache.complete_argument(&["/hooks", "disable"], ""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_set_context() {
+        let mut hook = Hook::new_inline_hook(HookTrigger::PerPrompt, "git status".to_string());
+        hook.name = "status".to_string();
+        hook.disabled = true;
+        let snapshot = ContextSnapshot {
+            global_paths: vec!["README.md".to_string()],
+            profile_paths: vec!["src/**/*.rs".to_string()],
+            hooks: vec![hook],
+            profiles: vec!["default".to_string(), "rust".to_string()],
+            files: vec!["/ws/src/main.rs".to_string(), "/etc/hosts".to_string()],
+        };
+
+        let mut cache = CompletionCache::default();
+        cache.set_context(&snapshot, Some(Path::new("/ws")));
+        assert_eq!(cache.get(CompletionCategory::ContextFiles), [
+            "src/main.rs",
+            "/etc/hosts"
+        ]);
+        assert_eq!(cache.get(CompletionCategory::ContextRules { global: true }), [
+            "README.md"
+        ]);
+        assert_eq!(
+            cache.complete_argument(&["/hooks", "enable"], ""),
+            Some(vec!["status".to_string()])
+        );
+        assert_eq!(
+            cache.complete_argument(&["/profile", "set"], "r"),
+            Some(vec!["rust".to_string()])
+        );
+    }
+}
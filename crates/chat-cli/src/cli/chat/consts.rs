@@ -13,6 +13,10 @@ pub const MAX_TOOL_RESPONSE_SIZE: usize = 400_000;
 /// Actual service limit is 600_000
 pub const MAX_USER_MESSAGE_SIZE: usize = 400_000;
 
+/// Bytes of piped stdin sent with the first prompt. Longer input keeps its end, where logs and
+/// command output usually have the interesting part.
+pub const MAX_PIPED_INPUT_SIZE: usize = 100_000;
+
 /// In tokens
 pub const CONTEXT_WINDOW_SIZE: usize = 200_000;
 
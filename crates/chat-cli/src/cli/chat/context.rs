@@ -1,29 +1,77 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{
+    BTreeMap,
+    HashMap,
+    HashSet,
+};
 use std::io::Write;
 use std::path::{
     Path,
     PathBuf,
 };
+use std::sync::Arc;
 
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
 use eyre::{
     Result,
     eyre,
 };
-use glob::glob;
+use glob::{
+    Pattern,
+    glob,
+};
+use indicatif::{
+    ProgressBar,
+    ProgressDrawTarget,
+    ProgressStyle,
+};
 use regex::Regex;
 use serde::{
     Deserialize,
     Serialize,
 };
-use tracing::debug;
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::{
+    debug,
+    warn,
+};
 
 use super::consts::CONTEXT_FILES_MAX_SIZE;
+use super::context_cache::ContentCache;
+use super::context_walk::{
+    WalkFilter,
+    WalkOptions,
+};
+use super::context_watch::ContextWatcher;
+use super::dynamic_context::DynamicSource;
+use super::hook_scripts::{
+    self,
+    HOOK_SCRIPTS_DIR,
+};
+use super::token_counter::TokenCounter;
+use super::tool_presets::ToolPreset;
 use super::util::drop_matched_context_files;
+use super::{
+    s3_context,
+    url_context,
+};
 use crate::cli::chat::ChatError;
 use crate::cli::chat::cli::hooks::{
     Hook,
     HookExecutor,
+    HookFilter,
+    HookImport,
+    HookPack,
+    HookTrigger,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::directories;
 
@@ -38,6 +86,199 @@ pub struct ContextConfig {
 
     /// Map of Hook Name to [`Hook`]. The hook name serves as the hook's ID.
     pub hooks: HashMap<String, Hook>,
+
+    /// Token budgets and priorities of rules in `paths`, keyed by rule.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub rule_options: HashMap<String, RuleOptions>,
+
+    /// Named sets of rules that are turned on and off together, keyed by name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, ContextGroup>,
+
+    /// The language of responses and of the comments in code written, e.g. `es`, set with
+    /// `/settings set response.language`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_language: Option<String>,
+
+    /// Text that ends a response where it appears, set with `/settings set
+    /// response.stop_sequences`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+
+    /// Regular expressions of text removed from each line of a response, e.g. a habitual
+    /// preamble, set with `/settings set response.filters`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output_filters: Vec<String>,
+
+    /// The permissions of the built-in tools, applied when a session starts with the profile or
+    /// switches to it, set with `/tools preset`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_preset: Option<ToolPreset>,
+}
+
+/// A read-only copy of the context, for code that shows it rather than changes it, such as the
+/// prompt's completions. Taken with [ContextManager::snapshot].
+#[derive(Debug, Clone, Default)]
+pub struct ContextSnapshot {
+    /// The rules of the global config.
+    pub global_paths: Vec<String>,
+    /// The rules of the current profile.
+    pub profile_paths: Vec<String>,
+    /// Every hook, with its name and whether it's global set, in the order of
+    /// [ContextManager::list_hooks].
+    pub hooks: Vec<Hook>,
+    /// The names of the profiles.
+    pub profiles: Vec<String>,
+    /// The files the rules match.
+    pub files: Vec<String>,
+}
+
+/// Rules that are used only while the group is enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextGroup {
+    pub paths: Vec<String>,
+    pub enabled: bool,
+}
+
+/// Where [ContextManager::move_path] moves a rule to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RulePosition {
+    First,
+    Last,
+    Before(String),
+    After(String),
+}
+
+/// How much of the context a rule may use, and which rules give way first when the matched files
+/// don't all fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RuleOptions {
+    /// Tokens the rule's files may use in total. Files beyond it are dropped, largest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// Rules with a lower priority are dropped first when the context is over its limit.
+    pub priority: i32,
+    /// Whether files ignored by git are skipped, instead of the `chat.contextRespectGitignore`
+    /// setting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gitignore: Option<bool>,
+    /// Whether symlinks are followed, instead of the `chat.contextFollowSymlinks` setting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_symlinks: Option<bool>,
+}
+
+/// What a profile adds to the context, previewed by `/profile` before switching to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileSummary {
+    /// The profile's rules, then those of its enabled groups.
+    pub rules: Vec<String>,
+    /// The name, trigger and whether it's disabled of each of the profile's hooks, by name.
+    pub hooks: Vec<(String, HookTrigger, bool)>,
+    /// Files matched by the rules, not counting URLs, S3 objects and dynamic sources.
+    pub files: usize,
+    /// Estimated tokens of those files.
+    pub tokens: usize,
+}
+
+/// A prompt or response from earlier in the conversation that is kept in the context, even after
+/// `/compact`. Set with `/pin`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedMessage {
+    /// 1-based position of the turn the message was pinned from, when it was pinned.
+    pub turn: usize,
+    pub prompt: Option<String>,
+    pub response: Option<String>,
+}
+
+impl PinnedMessage {
+    /// The message as it is added to the context.
+    pub fn as_context(&self) -> String {
+        let mut context = format!("[Pinned from turn {}]\n", self.turn);
+        if let Some(prompt) = &self.prompt {
+            context.push_str(&format!("User: {prompt}\n"));
+        }
+        if let Some(response) = &self.response {
+            context.push_str(&format!("Assistant: {response}\n"));
+        }
+        context
+    }
+}
+
+/// A context file as it was sent to the model, kept so `/context diff` can tell what changed since.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextFileDigest {
+    pub path: String,
+    pub sha256: String,
+    pub tokens: usize,
+}
+
+impl ContextFileDigest {
+    pub fn new(path: &str, content: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            sha256: hex::encode(Sha256::digest(content.as_bytes())),
+            tokens: TokenCounter::count_tokens(content),
+        }
+    }
+}
+
+/// A rule checked by `/context validate`, with the files it matches after exclusions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCheck {
+    pub rule: String,
+    pub files: usize,
+    pub tokens: usize,
+    pub problems: Vec<RuleProblem>,
+}
+
+/// Why a rule gives less context than it looks like it should.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleProblem {
+    /// Matches no files, or only files that are excluded or counted towards an earlier rule.
+    NoMatches,
+    /// Names a path outside the current directory.
+    OutsideWorkspace(String),
+    /// Matched files that couldn't be read, with the reason.
+    Unreadable(Vec<(String, String)>),
+    /// Couldn't be fetched or generated, or isn't a valid pattern.
+    Failed(String),
+}
+
+/// How a context file differs from the copy sent with the last request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ContextChange<'a> {
+    Added(&'a ContextFileDigest),
+    Removed(&'a ContextFileDigest),
+    Changed {
+        sent: &'a ContextFileDigest,
+        current: &'a ContextFileDigest,
+    },
+}
+
+/// Compares the context files sent with the last request against the current ones, returning the
+/// changes in the order of the current files, then removed files, along with how many files are
+/// unchanged.
+pub fn context_changes<'a>(
+    sent: &'a [ContextFileDigest],
+    current: &'a [ContextFileDigest],
+) -> (Vec<ContextChange<'a>>, usize) {
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for file in current {
+        match sent.iter().find(|sent| sent.path == file.path) {
+            Some(sent) if sent.sha256 == file.sha256 => unchanged += 1,
+            Some(sent) => changes.push(ContextChange::Changed { sent, current: file }),
+            None => changes.push(ContextChange::Added(file)),
+        }
+    }
+    for file in sent {
+        if !current.iter().any(|current| current.path == file.path) {
+            changes.push(ContextChange::Removed(file));
+        }
+    }
+    (changes, unchanged)
 }
 
 /// Manager for context files and profiles.
@@ -54,8 +295,39 @@ pub struct ContextManager {
     /// Context configuration for the current profile.
     pub profile_config: ContextConfig,
 
+    /// Paths added for this session only (e.g. by a recipe). These are never written to the
+    /// global or profile configuration.
+    #[serde(default)]
+    pub session_paths: Vec<String>,
+
+    /// Messages pinned with `/pin`, included ahead of the context files and summary.
+    #[serde(default)]
+    pub pinned: Vec<PinnedMessage>,
+
     #[serde(skip)]
     pub hook_executor: HookExecutor,
+
+    /// Hooks discovered from the scripts in the workspace's [HOOK_SCRIPTS_DIR], run as profile
+    /// hooks. Hooks of the profile with the same name replace them.
+    #[serde(skip)]
+    pub workspace_hooks: HashMap<String, Hook>,
+
+    /// Whether `per_prompt` hooks are skipped until they're turned on again, set with `/hooks
+    /// off`.
+    #[serde(skip)]
+    pub prompt_hooks_off: bool,
+
+    /// Whether `per_prompt` hooks are skipped for the next prompt, sent with `--no-hooks`.
+    #[serde(skip)]
+    pub skip_next_prompt_hooks: bool,
+
+    /// Caches matched files until they change on disk, set with `/context watch on`.
+    #[serde(skip)]
+    watcher: Option<Arc<ContextWatcher>>,
+
+    /// The content of files read for the rules, reused while they're unchanged.
+    #[serde(skip)]
+    content_cache: Arc<ContentCache>,
 }
 
 impl ContextManager {
@@ -83,13 +355,23 @@ impl ContextManager {
         let global_config = load_global_config(os).await?;
         let current_profile = "default".to_string();
         let profile_config = load_profile_config(os, &current_profile).await?;
+        let workspace_hooks = load_workspace_hooks(os).await?;
+        let mut hook_executor = HookExecutor::new();
+        hook_executor.sandbox_hooks = sandbox_hooks(os);
 
         Ok(Self {
             max_context_files_size,
             global_config,
             current_profile,
             profile_config,
-            hook_executor: HookExecutor::new(),
+            session_paths: Vec::new(),
+            pinned: Vec::new(),
+            hook_executor,
+            workspace_hooks,
+            prompt_hooks_off: false,
+            skip_next_prompt_hooks: false,
+            watcher: None,
+            content_cache: Arc::default(),
         })
     }
 
@@ -122,10 +404,12 @@ impl ContextManager {
         Ok(())
     }
 
-    /// Reloads the global and profile config from disk.
+    /// Reloads the global and profile config from disk, and the workspace's hook scripts.
     pub async fn reload_config(&mut self, os: &Os) -> Result<()> {
         self.global_config = load_global_config(os).await?;
         self.profile_config = load_profile_config(os, &self.current_profile).await?;
+        self.workspace_hooks = load_workspace_hooks(os).await?;
+        self.hook_executor.sandbox_hooks = sandbox_hooks(os);
         Ok(())
     }
 
@@ -145,17 +429,7 @@ impl ContextManager {
 
         // Validate paths exist before adding them
         if !force {
-            let mut context_files = Vec::new();
-
-            // Check each path to make sure it exists or matches at least one file
-            for path in &paths {
-                // We're using a temporary context_files vector just for validation
-                // Pass is_validation=true to ensure we error if glob patterns don't match any files
-                match process_path(os, path, &mut context_files, true).await {
-                    Ok(_) => {}, // Path is valid
-                    Err(e) => return Err(eyre!("Invalid path '{}': {}. Use --force to add anyway.", path, e)),
-                }
-            }
+            validate_rules(os, &paths, RuleOptions::default()).await?;
         }
 
         // Add each path, checking for duplicates
@@ -176,6 +450,75 @@ impl ContextManager {
         Ok(())
     }
 
+    /// Creates a disabled group of rules in the current profile.
+    pub async fn create_group(&mut self, os: &Os, name: &str, paths: Vec<String>, force: bool) -> Result<()> {
+        if self.profile_config.groups.contains_key(name) {
+            return Err(eyre!("Group '{}' already exists.", name));
+        }
+        if !force {
+            validate_rules(os, &paths, RuleOptions::default()).await?;
+        }
+        self.profile_config
+            .groups
+            .insert(name.to_string(), ContextGroup { paths, enabled: false });
+        self.save_config(os, false).await
+    }
+
+    pub async fn delete_group(&mut self, os: &Os, name: &str) -> Result<()> {
+        if self.profile_config.groups.remove(name).is_none() {
+            return Err(eyre!("Group '{}' not found", name));
+        }
+        self.save_config(os, false).await
+    }
+
+    /// Turns the rules of a group in the current profile on or off.
+    pub async fn set_group_enabled(&mut self, os: &Os, name: &str, enabled: bool) -> Result<()> {
+        let Some(group) = self.profile_config.groups.get_mut(name) else {
+            return Err(eyre!("Group '{}' not found", name));
+        };
+        group.enabled = enabled;
+        self.save_config(os, false).await
+    }
+
+    /// Every rule in use: global rules, then profile rules and those of its enabled groups, then
+    /// session rules.
+    fn rules(&self) -> impl Iterator<Item = &String> {
+        let group_rules = self
+            .profile_config
+            .groups
+            .values()
+            .filter(|group| group.enabled)
+            .flat_map(|group| &group.paths);
+        self.global_config
+            .paths
+            .iter()
+            .chain(&self.profile_config.paths)
+            .chain(group_rules)
+            .chain(&self.session_paths)
+    }
+
+    /// Add paths to the context for the current session only, without saving them to any
+    /// configuration.
+    ///
+    /// Like [Self::add_paths], each path must exist or match at least one file.
+    pub async fn add_session_paths(&mut self, os: &Os, paths: Vec<String>) -> Result<()> {
+        let mut context_files = Vec::new();
+        for path in &paths {
+            let walk = WalkOptions::new(os, RuleOptions::default());
+            if let Err(e) = process_path(os, path, &mut context_files, true, walk, &self.content_cache).await {
+                return Err(eyre!("Invalid path '{}': {}", path, e));
+            }
+        }
+
+        for path in paths {
+            if !self.session_paths.contains(&path) {
+                self.session_paths.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Remove paths from the context configuration.
     ///
     /// # Arguments
@@ -193,12 +536,17 @@ impl ContextManager {
         let mut removed_any = false;
 
         // Remove each path if it exists
+        let mut removed_remote = Vec::new();
         for path in paths {
             let original_len = config.paths.len();
             config.paths.retain(|p| p != &path);
+            config.rule_options.remove(&path);
 
             if config.paths.len() < original_len {
                 removed_any = true;
+                if url_context::is_url(&path) || s3_context::is_s3_uri(&path) {
+                    removed_remote.push(path);
+                }
             }
         }
 
@@ -209,9 +557,39 @@ impl ContextManager {
         // Save the updated configuration
         self.save_config(os, global).await?;
 
+        for rule in removed_remote {
+            let removed = match s3_context::is_s3_uri(&rule) {
+                true => s3_context::remove_cached(os, &rule).await,
+                false => url_context::remove_cached(os, &rule).await,
+            };
+            if let Err(err) = removed {
+                warn!(?err, rule, "failed to remove the cached copy of a context rule");
+            }
+        }
+
         Ok(())
     }
 
+    /// The URL rules in use, from the global config, the current profile and this session.
+    pub fn url_rules(&self) -> Vec<&str> {
+        self.rules_matching(url_context::is_url)
+    }
+
+    /// The `s3://` rules in use, from the global config, the current profile and this session.
+    pub fn s3_rules(&self) -> Vec<&str> {
+        self.rules_matching(s3_context::is_s3_uri)
+    }
+
+    fn rules_matching(&self, matches: fn(&str) -> bool) -> Vec<&str> {
+        let mut rules = Vec::new();
+        for path in self.rules() {
+            if matches(path) && !rules.contains(&path.as_str()) {
+                rules.push(path.as_str());
+            }
+        }
+        rules
+    }
+
     /// List all available profiles.
     ///
     /// # Returns
@@ -446,12 +824,19 @@ impl ContextManager {
     /// # Returns
     /// A Result containing a vector of (filename, content) pairs or an error
     pub async fn get_context_files(&self, os: &Os) -> Result<Vec<(String, String)>> {
-        let mut context_files = Vec::new();
+        let mut context_files = self.included_files(os).await?;
+        let exclusions = self.exclusions(os)?;
+        context_files.retain(|(file, _)| !exclusions.iter().any(|pattern| is_excluded(pattern, file)));
+        Ok(context_files)
+    }
 
-        self.collect_context_files(os, &self.global_config.paths, &mut context_files)
-            .await?;
-        self.collect_context_files(os, &self.profile_config.paths, &mut context_files)
-            .await?;
+    /// The files matched by the rules, before exclusions are applied.
+    async fn included_files(&self, os: &Os) -> Result<Vec<(String, String)>> {
+        let mut context_files = Vec::new();
+        for path in self.rules().filter(|path| !is_exclusion(path)) {
+            // Use is_validation=false to handle non-matching globs gracefully
+            context_files.extend(self.rule_files(os, path, false).await?);
+        }
 
         context_files.sort_by(|a, b| a.0.cmp(&b.0));
         context_files.dedup_by(|a, b| a.0 == b.0);
@@ -459,41 +844,342 @@ impl ContextManager {
         Ok(context_files)
     }
 
+    /// The files matched by the rule `path`, without those excluded by other rules.
     pub async fn get_context_files_by_path(&self, os: &Os, path: &str) -> Result<Vec<(String, String)>> {
-        let mut context_files = Vec::new();
-        process_path(os, path, &mut context_files, true).await?;
+        let mut context_files = self.rule_files(os, path, true).await?;
+        let exclusions = self.exclusions(os)?;
+        context_files.retain(|(file, _)| !exclusions.iter().any(|pattern| is_excluded(pattern, file)));
         Ok(context_files)
     }
 
+    /// The files matched by `rule`, read from the watcher's cache while watching.
+    async fn rule_files(&self, os: &Os, rule: &str, is_validation: bool) -> Result<Vec<(String, String)>> {
+        // URLs and S3 objects have their own cache, and dynamic sources are generated for each prompt.
+        let watcher = self.watcher.as_ref().filter(|_| is_file_rule(rule));
+        let cached = watcher.and_then(|watcher| watcher.get(rule));
+        match cached {
+            // Without the file system, an empty cache can't tell validation whether the rule matches.
+            Some(files) if !is_validation || !files.is_empty() => Ok(files),
+            _ => {
+                let mut files = Vec::new();
+                let walk = WalkOptions::new(os, self.rule_options(rule));
+                process_path(os, rule, &mut files, is_validation, walk, &self.content_cache).await?;
+                if let Some(watcher) = watcher {
+                    watcher.insert(rule, &expand_path(os, rule)?, files.clone());
+                }
+                Ok(files)
+            },
+        }
+    }
+
+    /// Starts or stops caching matched files until they change on disk.
+    pub fn set_watching(&mut self, watching: bool) -> Result<()> {
+        match (watching, &self.watcher) {
+            (true, None) => self.watcher = Some(Arc::new(ContextWatcher::new()?)),
+            (false, _) => self.watcher = None,
+            (true, Some(_)) => (),
+        }
+        Ok(())
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    /// Pins `message` so it is kept in the context.
+    pub fn pin(&mut self, message: PinnedMessage) -> Result<()> {
+        if self.pinned.contains(&message) {
+            return Err(eyre!("Turn {} is already pinned", message.turn));
+        }
+        self.pinned.push(message);
+        Ok(())
+    }
+
+    /// Removes the pinned message at the 1-based `number` in [Self::pinned].
+    pub fn unpin(&mut self, number: usize) -> Result<PinnedMessage> {
+        if number == 0 || number > self.pinned.len() {
+            return Err(eyre!(
+                "There is no pinned message {number}, see /pin for the pinned messages"
+            ));
+        }
+        Ok(self.pinned.remove(number - 1))
+    }
+
+    /// Counts the files matched by the other rules that the exclusion `rule` filters out.
+    pub async fn count_excluded_files(&self, os: &Os, rule: &str) -> Result<usize> {
+        let pattern = exclusion_pattern(os, rule)?;
+        let files = self.included_files(os).await?;
+        Ok(files.iter().filter(|(file, _)| is_excluded(&pattern, file)).count())
+    }
+
+    /// Checks every rule in use for matching nothing, pointing outside the current directory and
+    /// matching files that can't be read. Files matched by several rules count towards the first.
+    pub async fn validate(&self, os: &Os) -> Result<Vec<RuleCheck>> {
+        let workspace = normalize_path(Path::new(&expand_path(os, ".")?));
+        let mut exclusions = Vec::new();
+        let mut checks = Vec::new();
+        for rule in self.rules().filter(|path| is_exclusion(path)) {
+            let problems = match exclusion_pattern(os, rule) {
+                Ok(pattern) => {
+                    exclusions.push(pattern);
+                    Vec::new()
+                },
+                Err(err) => vec![RuleProblem::Failed(err.to_string())],
+            };
+            checks.push(RuleCheck {
+                rule: rule.clone(),
+                files: 0,
+                tokens: 0,
+                problems,
+            });
+        }
+
+        let mut seen = HashSet::new();
+        for rule in self.rules().filter(|path| !is_exclusion(path)) {
+            let mut check = RuleCheck {
+                rule: rule.clone(),
+                files: 0,
+                tokens: 0,
+                problems: Vec::new(),
+            };
+            let mut unreadable = Vec::new();
+            if is_file_rule(rule) {
+                let full_path = expand_path(os, rule)?;
+                let base = WalkFilter::new(WalkOptions::default(), &full_path).base().to_path_buf();
+                if !normalize_path(&base).starts_with(&workspace) {
+                    check
+                        .problems
+                        .push(RuleProblem::OutsideWorkspace(base.to_string_lossy().to_string()));
+                }
+                let walk = WalkOptions::new(os, self.rule_options(rule));
+                match matched_paths(os, &full_path, walk).await {
+                    Ok(paths) => {
+                        for path in paths {
+                            let file = path.to_string_lossy().to_string();
+                            if exclusions.iter().any(|pattern| is_excluded(pattern, &file))
+                                || !seen.insert(file.clone())
+                            {
+                                continue;
+                            }
+                            match self.content_cache.read(os, &path).await {
+                                Ok(content) => {
+                                    check.files += 1;
+                                    check.tokens += TokenCounter::count_tokens(&content);
+                                },
+                                Err(err) => unreadable.push((file, err.to_string())),
+                            }
+                        }
+                    },
+                    Err(err) => check.problems.push(RuleProblem::Failed(err.to_string())),
+                }
+            } else {
+                match self.rule_files(os, rule, true).await {
+                    Ok(files) => {
+                        for (file, content) in files {
+                            if !exclusions.iter().any(|pattern| is_excluded(pattern, &file)) && seen.insert(file) {
+                                check.files += 1;
+                                check.tokens += TokenCounter::count_tokens(&content);
+                            }
+                        }
+                    },
+                    Err(err) => check.problems.push(RuleProblem::Failed(err.to_string())),
+                }
+            }
+
+            if !unreadable.is_empty() {
+                check.problems.push(RuleProblem::Unreadable(unreadable));
+            } else if check.files == 0
+                && check
+                    .problems
+                    .iter()
+                    .all(|problem| !matches!(problem, RuleProblem::Failed(_)))
+                && DynamicSource::from_rule(rule).is_none()
+            {
+                // A dynamic source with nothing to say, like git-diff without changes, is fine.
+                check.problems.push(RuleProblem::NoMatches);
+            }
+            checks.push(check);
+        }
+        Ok(checks)
+    }
+
+    /// Reads the files matched by the rules in use ahead of the next prompt, with a progress bar
+    /// when there are many. Returns how many were read. Interrupting it leaves the rest to be read
+    /// with the next prompt.
+    pub async fn preload(&self, os: &Os) -> Result<usize> {
+        let exclusions = self.exclusions(os)?;
+        let mut paths = Vec::new();
+        let mut seen = HashSet::new();
+        for rule in self.rules().filter(|rule| !is_exclusion(rule) && is_file_rule(rule)) {
+            let walk = WalkOptions::new(os, self.rule_options(rule));
+            for path in rule_paths(os, rule, false, walk).await? {
+                let file = path.to_string_lossy().to_string();
+                if !exclusions.iter().any(|pattern| is_excluded(pattern, &file)) && seen.insert(path.clone()) {
+                    paths.push(path);
+                }
+            }
+        }
+        let contents = read_files(os, &paths, &self.content_cache, "Reading context files").await;
+        Ok(contents.iter().filter(|(_, content)| content.is_ok()).count())
+    }
+
+    /// The rules, hooks and size of the profile `name`, without switching to it. Global rules and
+    /// hooks are left out since they're the same in every profile.
+    pub async fn profile_summary(&self, os: &Os, name: &str) -> Result<ProfileSummary> {
+        let config = if name == self.current_profile {
+            self.profile_config.clone()
+        } else {
+            load_profile_config(os, name).await?
+        };
+        let group_rules = config
+            .groups
+            .values()
+            .filter(|group| group.enabled)
+            .flat_map(|group| &group.paths);
+        let rules: Vec<String> = config.paths.iter().chain(group_rules).cloned().collect();
+
+        let exclusions = rules
+            .iter()
+            .filter(|rule| is_exclusion(rule))
+            .map(|rule| exclusion_pattern(os, rule))
+            .collect::<Result<Vec<_>>>()?;
+        let mut paths = Vec::new();
+        let mut seen = HashSet::new();
+        for rule in rules.iter().filter(|rule| !is_exclusion(rule) && is_file_rule(rule)) {
+            let options = config.rule_options.get(rule).copied().unwrap_or_default();
+            for path in rule_paths(os, rule, false, WalkOptions::new(os, options)).await? {
+                let file = path.to_string_lossy().to_string();
+                if !exclusions.iter().any(|pattern| is_excluded(pattern, &file)) && seen.insert(path.clone()) {
+                    paths.push(path);
+                }
+            }
+        }
+        let contents = read_files(os, &paths, &self.content_cache, "Reading profile files").await;
+        let contents: Vec<String> = contents.into_iter().filter_map(|(_, content)| content.ok()).collect();
+
+        let mut hooks: Vec<_> = config
+            .hooks
+            .into_iter()
+            .map(|(name, hook)| (name, hook.trigger, hook.disabled))
+            .collect();
+        hooks.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+        Ok(ProfileSummary {
+            rules,
+            hooks,
+            files: contents.len(),
+            tokens: contents.iter().map(|content| TokenCounter::count_tokens(content)).sum(),
+        })
+    }
+
+    /// The exclusion patterns of every rule in use.
+    fn exclusions(&self, os: &Os) -> Result<Vec<Pattern>> {
+        self.rules()
+            .filter(|path| is_exclusion(path))
+            .map(|path| exclusion_pattern(os, path))
+            .collect()
+    }
+
+    /// Sets the token budget and priority of an existing rule.
+    pub async fn set_rule_options(&mut self, os: &Os, rule: &str, global: bool, options: RuleOptions) -> Result<()> {
+        let config = self.get_config_mut(global);
+        if !config.paths.iter().any(|path| path == rule) {
+            return Err(eyre!("Rule '{}' not found", rule));
+        }
+        if options == RuleOptions::default() {
+            config.rule_options.remove(rule);
+        } else {
+            config.rule_options.insert(rule.to_string(), options);
+        }
+        self.save_config(os, global).await
+    }
+
+    /// Moves an existing rule within its config. Earlier rules are kept first when the context is
+    /// trimmed to fit its limit.
+    pub async fn move_path(&mut self, os: &Os, rule: &str, global: bool, position: RulePosition) -> Result<()> {
+        let config = self.get_config_mut(global);
+        let Some(from) = config.paths.iter().position(|path| path == rule) else {
+            return Err(eyre!("Rule '{}' not found", rule));
+        };
+        let moved = config.paths.remove(from);
+        let to = match &position {
+            RulePosition::First => 0,
+            RulePosition::Last => config.paths.len(),
+            RulePosition::Before(other) | RulePosition::After(other) => {
+                let Some(index) = config.paths.iter().position(|path| path == other) else {
+                    config.paths.insert(from, moved);
+                    return Err(eyre!("Rule '{}' not found", other));
+                };
+                if matches!(position, RulePosition::After(_)) {
+                    index + 1
+                } else {
+                    index
+                }
+            },
+        };
+        config.paths.insert(to, moved);
+        self.save_config(os, global).await
+    }
+
+    /// The options of `rule`, from the profile if it sets any and otherwise from the global config.
+    pub fn rule_options(&self, rule: &str) -> RuleOptions {
+        self.profile_config
+            .rule_options
+            .get(rule)
+            .or_else(|| self.global_config.rule_options.get(rule))
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Collects context files and optionally drops files if the total size exceeds the limit.
     /// Returns (files_to_use, dropped_files)
+    ///
+    /// Each rule's files are first held to its own token budget. If the rest still exceed the
+    /// limit, the files of higher priority rules are kept first.
     pub async fn collect_context_files_with_limit(
         &self,
         os: &Os,
     ) -> Result<(Vec<(String, String)>, Vec<(String, String)>)> {
-        let mut files = self.get_context_files(os).await?;
-
-        let dropped_files = drop_matched_context_files(&mut files, self.max_context_files_size).unwrap_or_default();
+        let exclusions = self.exclusions(os)?;
+        let mut seen = HashSet::new();
+        let mut dropped_files = Vec::new();
+        // Each rule's files by priority, in the order of the rules within a priority.
+        let mut by_priority: BTreeMap<Reverse<i32>, Vec<Vec<(String, String)>>> = BTreeMap::new();
+        for rule in self.rules().filter(|path| !is_exclusion(path)) {
+            let mut files = self.rule_files(os, rule, false).await?;
+            // A file matched by several rules counts towards the first.
+            files.retain(|(file, _)| {
+                !exclusions.iter().any(|pattern| is_excluded(pattern, file)) && seen.insert(file.clone())
+            });
+
+            let options = self.rule_options(rule);
+            if let Some(max_tokens) = options.max_tokens {
+                let over_budget = drop_matched_context_files(&mut files, max_tokens).unwrap_or_default();
+                files.retain(|file| !over_budget.iter().any(|dropped| dropped.0 == file.0));
+                dropped_files.extend(over_budget);
+            }
+            by_priority.entry(Reverse(options.priority)).or_default().push(files);
+        }
 
-        // remove dropped files from files
-        files.retain(|file| !dropped_files.iter().any(|dropped| dropped.0 == file.0));
+        // Higher priorities and then earlier rules take what they need of the limit first.
+        let mut remaining = self.max_context_files_size;
+        let mut files = Vec::new();
+        for mut rule_files in by_priority.into_values().flatten() {
+            let dropped = drop_matched_context_files(&mut rule_files, remaining).unwrap_or_default();
+            rule_files.retain(|file| !dropped.iter().any(|dropped| dropped.0 == file.0));
+            let used = rule_files
+                .iter()
+                .map(|(_, content)| TokenCounter::count_tokens(content))
+                .sum::<usize>();
+            remaining = remaining.saturating_sub(used);
+            dropped_files.extend(dropped);
+            files.extend(rule_files);
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
 
         Ok((files, dropped_files))
     }
 
-    async fn collect_context_files(
-        &self,
-        os: &Os,
-        paths: &[String],
-        context_files: &mut Vec<(String, String)>,
-    ) -> Result<()> {
-        for path in paths {
-            // Use is_validation=false to handle non-matching globs gracefully
-            process_path(os, path, context_files, false).await?;
-        }
-        Ok(())
-    }
-
     fn get_config_mut(&mut self, global: bool) -> &mut ContextConfig {
         if global {
             &mut self.global_config
@@ -522,6 +1208,30 @@ impl ContextManager {
         self.save_config(os, global).await
     }
 
+    /// Adds the hooks of a [HookPack]. Hooks with the name of an existing hook replace it if
+    /// `overwrite` is true, and are skipped otherwise.
+    pub async fn import_hooks(&mut self, os: &Os, pack: HookPack, global: bool, overwrite: bool) -> Result<HookImport> {
+        let config = self.get_config_mut(global);
+
+        let mut import = HookImport::default();
+        for (name, hook) in pack.hooks {
+            if !config.hooks.contains_key(&name) {
+                import.added.push(name.clone());
+            } else if overwrite {
+                import.replaced.push(name.clone());
+            } else {
+                import.skipped.push(name);
+                continue;
+            }
+            config.hooks.insert(name, hook);
+        }
+
+        if !import.added.is_empty() || !import.replaced.is_empty() {
+            self.save_config(os, global).await?;
+        }
+        Ok(import)
+    }
+
     /// Delete hook(s) by name
     /// # Arguments
     /// * `name` - name of the hook to delete
@@ -531,7 +1241,7 @@ impl ContextManager {
         let config = self.get_config_mut(global);
 
         if !config.hooks.contains_key(name) {
-            return Err(eyre!("does not exist."));
+            return Err(self.missing_hook(name, global));
         }
 
         config.hooks.remove(name);
@@ -539,59 +1249,312 @@ impl ContextManager {
         self.save_config(os, global).await
     }
 
+    /// The configured hook `name`.
+    pub fn hook(&self, name: &str, global: bool) -> Result<&Hook> {
+        let config = if global {
+            &self.global_config
+        } else {
+            &self.profile_config
+        };
+        config.hooks.get(name).ok_or_else(|| self.missing_hook(name, global))
+    }
+
+    /// Replaces the hook `name` with `hook`, keeping whether the hook is disabled.
+    pub async fn replace_hook(&mut self, os: &Os, name: &str, mut hook: Hook, global: bool) -> Result<()> {
+        let missing = self.missing_hook(name, global);
+        let config = self.get_config_mut(global);
+
+        let Some(existing) = config.hooks.get_mut(name) else {
+            return Err(missing);
+        };
+        hook.disabled = existing.disabled;
+        *existing = hook;
+
+        self.save_config(os, global).await
+    }
+
     /// Sets the "disabled" field on any [`Hook`] with the given name
     /// # Arguments
     /// * `disable` - Set "disabled" field to this value
-    pub async fn set_hook_disabled(&mut self, os: &Os, name: &str, global: bool, disable: bool) -> Result<()> {
+    /// # Returns
+    /// Whether the hook's state changed, i.e. `false` if it was already enabled or disabled
+    pub async fn set_hook_disabled(&mut self, os: &Os, name: &str, global: bool, disable: bool) -> Result<bool> {
+        let missing = self.missing_hook(name, global);
         let config = self.get_config_mut(global);
 
-        if !config.hooks.contains_key(name) {
-            return Err(eyre!("does not exist."));
-        }
-
-        if let Some(hook) = config.hooks.get_mut(name) {
-            hook.disabled = disable;
+        let Some(hook) = config.hooks.get_mut(name) else {
+            return Err(missing);
+        };
+        if hook.disabled == disable {
+            return Ok(false);
         }
+        hook.disabled = disable;
 
-        self.save_config(os, global).await
+        self.save_config(os, global).await?;
+        Ok(true)
     }
 
     /// Sets the "disabled" field on all [`Hook`]s
     /// # Arguments
     /// * `disable` - Set all "disabled" fields to this value
-    pub async fn set_all_hooks_disabled(&mut self, os: &Os, global: bool, disable: bool) -> Result<()> {
+    /// # Returns
+    /// The number of hooks whose state changed
+    pub async fn set_all_hooks_disabled(&mut self, os: &Os, global: bool, disable: bool) -> Result<usize> {
         let config = self.get_config_mut(global);
 
-        config.hooks.iter_mut().for_each(|(_, h)| h.disabled = disable);
+        let mut changed = 0;
+        for hook in config.hooks.values_mut().filter(|h| h.disabled != disable) {
+            hook.disabled = disable;
+            changed += 1;
+        }
 
-        self.save_config(os, global).await
+        if changed > 0 {
+            self.save_config(os, global).await?;
+        }
+        Ok(changed)
+    }
+
+    /// The language responses are written in, set for the current profile.
+    pub fn response_language(&self) -> Option<&str> {
+        self.profile_config.response_language.as_deref()
+    }
+
+    /// Sets, or with `None` removes, the language responses are written in for the current
+    /// profile.
+    pub async fn set_response_language(&mut self, os: &Os, language: Option<String>) -> Result<()> {
+        self.profile_config.response_language = language;
+        self.save_config(os, false).await
+    }
+
+    /// The text that ends responses where it appears, set for the current profile.
+    pub fn stop_sequences(&self) -> &[String] {
+        &self.profile_config.stop_sequences
+    }
+
+    /// Sets the text that ends responses where it appears for the current profile.
+    pub async fn set_stop_sequences(&mut self, os: &Os, stop_sequences: Vec<String>) -> Result<()> {
+        self.profile_config.stop_sequences = stop_sequences;
+        self.save_config(os, false).await
+    }
+
+    /// The regular expressions of text removed from responses, set for the current profile.
+    pub fn output_filters(&self) -> &[String] {
+        &self.profile_config.output_filters
+    }
+
+    /// Sets the regular expressions of text removed from responses for the current profile.
+    pub async fn set_output_filters(&mut self, os: &Os, filters: Vec<String>) -> Result<()> {
+        self.profile_config.output_filters = filters;
+        self.save_config(os, false).await
+    }
+
+    /// The tool permission preset of the current profile.
+    pub fn tool_preset(&self) -> Option<ToolPreset> {
+        self.profile_config.tool_preset
+    }
+
+    /// Saves the tool permission preset of the current profile.
+    pub async fn set_tool_preset(&mut self, os: &Os, preset: ToolPreset) -> Result<()> {
+        self.profile_config.tool_preset = Some(preset);
+        self.save_config(os, false).await
+    }
+
+    /// Trusts the workspace's hook scripts that aren't, or only that of hook `name`, and enables
+    /// their hooks. Returns the names of the hooks whose scripts were trusted with their paths.
+    pub async fn trust_workspace_hooks(&mut self, os: &Os, name: Option<&str>) -> Result<Vec<(String, PathBuf)>> {
+        if let Some(name) = name {
+            if !self.workspace_hooks.contains_key(name) {
+                return Err(eyre!("is not a hook of a script in {HOOK_SCRIPTS_DIR}."));
+            }
+        }
+        let mut untrusted: Vec<(&String, &Hook)> = self
+            .workspace_hooks
+            .iter()
+            .filter(|(hook_name, _)| name.is_none_or(|name| name == hook_name.as_str()))
+            .filter(|(_, hook)| hook.script.as_ref().is_some_and(|script| !script.trusted))
+            .collect();
+        untrusted.sort_by_key(|(hook_name, _)| *hook_name);
+        hook_scripts::trust(os, untrusted.iter().map(|(_, hook)| *hook))?;
+        let trusted = untrusted
+            .into_iter()
+            .filter_map(|(hook_name, hook)| Some((hook_name.clone(), hook.script.as_ref()?.path.clone())))
+            .collect();
+        self.workspace_hooks = load_workspace_hooks(os).await?;
+        Ok(trusted)
+    }
+
+    /// The error for a hook `name` that isn't in the config, which explains that hooks of scripts
+    /// are changed by editing the script.
+    fn missing_hook(&self, name: &str, global: bool) -> eyre::Report {
+        match self.workspace_hooks.get(name) {
+            Some(Hook {
+                script: Some(script), ..
+            }) if !global && !script.trusted => eyre!(
+                "is discovered from {}, which isn't trusted. Check it and run /hooks trust {name} to run it.",
+                script.path.display()
+            ),
+            Some(Hook {
+                script: Some(script), ..
+            }) if !global => eyre!(
+                "is discovered from {}, edit or remove the script instead.",
+                script.path.display()
+            ),
+            _ => eyre!("does not exist."),
+        }
+    }
+
+    /// A read-only copy of the context. Profiles and files that fail to be listed are left out
+    /// rather than failing the snapshot.
+    pub async fn snapshot(&self, os: &Os) -> ContextSnapshot {
+        let hooks = self
+            .list_hooks(&HookFilter::default())
+            .into_iter()
+            .map(|(name, global, hook)| Hook {
+                name: name.to_string(),
+                is_global: global,
+                ..hook.clone()
+            })
+            .collect();
+        let profiles = self.list_profiles(os).await.unwrap_or_else(|err| {
+            warn!(?err, "failed to list profiles");
+            Vec::new()
+        });
+        let files = match self.get_context_files(os).await {
+            Ok(files) => files.into_iter().map(|(path, _)| path).collect(),
+            Err(err) => {
+                warn!(?err, "failed to list context files");
+                Vec::new()
+            },
+        };
+        ContextSnapshot {
+            global_paths: self.global_config.paths.clone(),
+            profile_paths: self.profile_config.paths.clone(),
+            hooks,
+            profiles,
+            files,
+        }
+    }
+
+    /// Lists the configured hooks and those of the workspace's scripts matching `filter` as
+    /// `(name, is_global, hook)`, global hooks first and then by name.
+    pub fn list_hooks(&self, filter: &HookFilter) -> Vec<(&str, bool, &Hook)> {
+        let workspace_hooks = self
+            .workspace_hooks
+            .iter()
+            .filter(|(name, _)| !self.profile_config.hooks.contains_key(*name));
+        let mut hooks = [(true, &self.global_config), (false, &self.profile_config)]
+            .into_iter()
+            .filter(|(global, _)| filter.global.is_none_or(|g| g == *global))
+            .flat_map(|(global, config)| {
+                config
+                    .hooks
+                    .iter()
+                    .map(move |(name, hook)| (name.as_str(), global, hook))
+            })
+            .chain(
+                workspace_hooks
+                    .filter(|_| filter.global != Some(true))
+                    .map(|(name, hook)| (name.as_str(), false, hook)),
+            )
+            .filter(|(_, _, hook)| filter.matches(hook))
+            .collect::<Vec<_>>();
+        hooks.sort_by(|(a_name, a_global, _), (b_name, b_global, _)| {
+            b_global.cmp(a_global).then_with(|| a_name.cmp(b_name))
+        });
+        hooks
     }
 
     /// Run all the currently enabled hooks from both the global and profile contexts.
-    /// Skipped hooks (disabled) will not appear in the output.
+    /// Skipped hooks (disabled) will not appear in the output, nor will `per_prompt` hooks while
+    /// [Self::prompt_hooks_off] or [Self::skip_next_prompt_hooks] is set.
     /// # Arguments
+    /// * `env` - variables set for the hooks' commands, see
+    ///   [ConversationState::hook_env](super::conversation::ConversationState::hook_env)
     /// * `updates` - output stream to write hook run status to if Some, else do nothing if None
     /// # Returns
     /// A vector containing pairs of a [`Hook`] definition and its execution output
-    pub async fn run_hooks(&mut self, output: &mut impl Write) -> Result<Vec<(Hook, String)>, ChatError> {
-        let mut hooks: Vec<&Hook> = Vec::new();
-
-        // Set internal hook states
-        let configs = [
-            (&mut self.global_config.hooks, true),
-            (&mut self.profile_config.hooks, false),
-        ];
+    pub async fn run_hooks(
+        &mut self,
+        env: &[(&str, String)],
+        output: &mut impl Write,
+    ) -> Result<Vec<(Hook, String)>, ChatError> {
+        let skip_prompt_hooks = self.prompt_hooks_off || std::mem::take(&mut self.skip_next_prompt_hooks);
+        let hooks = named_hooks(
+            &mut self.global_config,
+            &mut self.profile_config,
+            &mut self.workspace_hooks,
+        )
+        .into_iter()
+        .filter(|hook| hook.trigger.runs_with_prompt())
+        .filter(|hook| !(skip_prompt_hooks && hook.trigger == HookTrigger::PerPrompt))
+        .collect::<Vec<_>>();
+        self.hook_executor.retain_watched(&hooks);
+        self.hook_executor.run_hooks(hooks, env, output).await
+    }
 
-        for (hook_list, is_global) in configs {
-            hooks.extend(hook_list.iter_mut().map(|(name, h)| {
-                h.name = name.clone();
-                h.is_global = is_global;
-                &*h
-            }));
+    /// Runs the enabled hooks of an event, such as [HookTrigger::OnError], with `env` set for their
+    /// commands. The output of hooks that only show it is written to `output` as well.
+    pub async fn run_event_hooks(
+        &mut self,
+        trigger: HookTrigger,
+        env: &[(&str, String)],
+        output: &mut impl Write,
+    ) -> Result<Vec<(Hook, String)>, ChatError> {
+        let hooks: Vec<&Hook> = named_hooks(
+            &mut self.global_config,
+            &mut self.profile_config,
+            &mut self.workspace_hooks,
+        )
+        .into_iter()
+        .filter(|hook| hook.trigger == trigger)
+        .collect();
+        if hooks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let results = self.hook_executor.run_hooks(hooks, env, output).await?;
+        if !trigger.adds_context() {
+            for (hook, hook_output) in results.iter().filter(|(_, hook_output)| !hook_output.trim().is_empty()) {
+                execute!(
+                    output,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("{}:\n{}\n\n", hook.name, hook_output.trim_end())),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            }
         }
+        Ok(results)
+    }
+}
 
-        self.hook_executor.run_hooks(hooks, output).await
+/// Every hook, with its name and whether it's global set from the config it's in. Hooks of the
+/// workspace's scripts are profile hooks, unless the profile has a hook with the same name.
+fn named_hooks<'a>(
+    global_config: &'a mut ContextConfig,
+    profile_config: &'a mut ContextConfig,
+    workspace_hooks: &'a mut HashMap<String, Hook>,
+) -> Vec<&'a Hook> {
+    let mut hooks: Vec<&Hook> = Vec::new();
+
+    // Set internal hook states
+    let workspace_hooks = workspace_hooks
+        .iter_mut()
+        .filter(|(name, _)| !profile_config.hooks.contains_key(*name));
+    hooks.extend(workspace_hooks.map(|(name, h)| {
+        h.name = name.clone();
+        h.is_global = false;
+        &*h
+    }));
+
+    let configs = [(&mut global_config.hooks, true), (&mut profile_config.hooks, false)];
+
+    for (hook_list, is_global) in configs {
+        hooks.extend(hook_list.iter_mut().map(|(name, h)| {
+            h.name = name.clone();
+            h.is_global = is_global;
+            &*h
+        }));
     }
+    hooks
 }
 
 fn profile_dir_path(os: &Os, profile_name: &str) -> Result<PathBuf> {
@@ -625,10 +1588,29 @@ async fn load_global_config(os: &Os) -> Result<ContextConfig> {
                 AMAZONQ_FILENAME.to_string(),
             ],
             hooks: HashMap::new(),
+            rule_options: HashMap::new(),
+            groups: BTreeMap::new(),
+            response_language: None,
+            stop_sequences: Vec::new(),
+            output_filters: Vec::new(),
+            tool_preset: None,
         })
     }
 }
 
+/// Whether hooks run sandboxed unless they set otherwise, the `chat.sandboxHooks` setting.
+fn sandbox_hooks(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ChatSandboxHooks)
+        .unwrap_or(false)
+}
+
+/// The hooks of the scripts in the working directory's [HOOK_SCRIPTS_DIR].
+async fn load_workspace_hooks(os: &Os) -> Result<HashMap<String, Hook>> {
+    hook_scripts::discover(os, &os.env.current_dir()?.join(HOOK_SCRIPTS_DIR)).await
+}
+
 /// Load a profile's context configuration.
 ///
 /// If the profile configuration file doesn't exist, creates a default configuration.
@@ -646,6 +1628,92 @@ async fn load_profile_config(os: &Os, profile_name: &str) -> Result<ContextConfi
     }
 }
 
+/// The instruction added to the context for the profile's response language.
+pub fn response_language_instructions(language: &str) -> String {
+    format!(
+        "Always write your responses in this language: {language}. Write the comments and documentation of the code you write in it as well, but keep identifiers, commands, file paths and quoted output as they are. Only use another language when I explicitly ask for it.\n"
+    )
+}
+
+/// Rules starting with `!`, e.g. `!src/**/*.test.ts`, exclude the files they match from the
+/// other rules.
+pub fn is_exclusion(rule: &str) -> bool {
+    rule.starts_with('!')
+}
+
+fn exclusion_pattern(os: &Os, rule: &str) -> Result<Pattern> {
+    let path = expand_path(os, rule.trim_start_matches('!'))?;
+    Ok(Pattern::new(&path)?)
+}
+
+/// Whether `file` matches the exclusion `pattern`, or is inside a directory it names.
+fn is_excluded(pattern: &Pattern, file: &str) -> bool {
+    pattern.matches(file) || Path::new(file).starts_with(pattern.as_str())
+}
+
+/// Expands `~` and makes `path` absolute, relative to the current directory.
+fn expand_path(os: &Os, path: &str) -> Result<String> {
+    // Expand ~ to home directory
+    let expanded_path = if path.starts_with('~') {
+        if let Some(home_dir) = os.env.home() {
+            home_dir.join(&path[2..]).to_string_lossy().to_string()
+        } else {
+            return Err(eyre!("Could not determine home directory"));
+        }
+    } else {
+        path.to_string()
+    };
+
+    // Handle absolute, relative paths, and glob patterns
+    let full_path = if expanded_path.starts_with('/') {
+        expanded_path
+    } else {
+        os.env.current_dir()?.join(&expanded_path).to_string_lossy().to_string()
+    };
+
+    // Required in chroot testing scenarios so that we can use `Path::exists`.
+    Ok(os.fs.chroot_path_str(full_path))
+}
+
+/// Checks that each rule matches at least one file when walked with `options`, or is a valid
+/// exclusion.
+pub async fn validate_rules(os: &Os, paths: &[String], options: RuleOptions) -> Result<()> {
+    let mut context_files = Vec::new();
+    let walk = WalkOptions::new(os, options);
+    let cache = ContentCache::default();
+
+    // Check each path to make sure it exists or matches at least one file, then that the files
+    // can be read.
+    let mut files = Vec::new();
+    for path in paths {
+        if is_exclusion(path) {
+            if let Err(e) = exclusion_pattern(os, path) {
+                return Err(eyre!("Invalid exclusion '{}': {}", path, e));
+            }
+            continue;
+        }
+        // Pass is_validation=true to ensure we error if glob patterns don't match any files
+        let result = if is_file_rule(path) {
+            rule_paths(os, path, true, walk).await.map(|paths| files.extend(paths))
+        } else {
+            process_path(os, path, &mut context_files, true, walk, &cache).await
+        };
+        if let Err(e) = result {
+            return Err(eyre!("Invalid path '{}': {}. Use --force to add anyway.", path, e));
+        }
+    }
+    for (path, content) in read_files(os, &files, &cache, "Validating matched files").await {
+        if let Err(e) = content {
+            return Err(eyre!(
+                "Invalid path '{}': {}. Use --force to add anyway.",
+                path.display(),
+                e
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// Process a path, handling glob patterns and file types.
 ///
 /// This method:
@@ -659,6 +1727,8 @@ async fn load_profile_config(os: &Os, profile_name: &str) -> Result<ContextConfi
 /// * `path` - The path to process
 /// * `context_files` - The collection to add files to
 /// * `is_validation` - If true, error when glob patterns don't match; if false, silently skip
+/// * `walk` - Whether globs and directories skip git-ignored files and symlinks
+/// * `cache` - Content of files read before, used for files that haven't changed since
 ///
 /// # Returns
 /// A Result indicating success or an error
@@ -667,96 +1737,199 @@ async fn process_path(
     path: &str,
     context_files: &mut Vec<(String, String)>,
     is_validation: bool,
+    walk: WalkOptions,
+    cache: &ContentCache,
 ) -> Result<()> {
-    // Expand ~ to home directory
-    let expanded_path = if path.starts_with('~') {
-        if let Some(home_dir) = os.env.home() {
-            home_dir.join(&path[2..]).to_string_lossy().to_string()
-        } else {
-            return Err(eyre!("Could not determine home directory"));
+    if let Some(source) = DynamicSource::from_rule(path) {
+        let content = match source {
+            Ok(source) => source.render(os).await,
+            Err(err) => Err(err),
+        };
+        match content {
+            Ok(content) if content.is_empty() => (),
+            Ok(content) => context_files.push((path.to_string(), content)),
+            Err(err) if is_validation => return Err(err),
+            Err(err) => warn!(?err, path, "failed to generate dynamic context"),
         }
-    } else {
-        path.to_string()
-    };
+        return Ok(());
+    }
 
-    // Handle absolute, relative paths, and glob patterns
-    let full_path = if expanded_path.starts_with('/') {
-        expanded_path
-    } else {
-        os.env.current_dir()?.join(&expanded_path).to_string_lossy().to_string()
-    };
+    if url_context::is_url(path) {
+        match url_context::fetch(os, path, false).await {
+            Ok(content) => context_files.push((path.to_string(), content)),
+            Err(err) if is_validation => return Err(err),
+            Err(err) => warn!(?err, path, "failed to fetch a context URL"),
+        }
+        return Ok(());
+    }
 
-    // Required in chroot testing scenarios so that we can use `Path::exists`.
-    let full_path = os.fs.chroot_path_str(full_path);
-
-    // Check if the path contains glob patterns
-    if full_path.contains('*') || full_path.contains('?') || full_path.contains('[') {
-        // Expand glob pattern
-        match glob(&full_path) {
-            Ok(entries) => {
-                let mut found_any = false;
-
-                for entry in entries {
-                    match entry {
-                        Ok(path) => {
-                            if path.is_file() {
-                                add_file_to_context(os, &path, context_files).await?;
-                                found_any = true;
-                            }
-                        },
-                        Err(e) => return Err(eyre!("Glob error: {}", e)),
-                    }
-                }
+    if s3_context::is_s3_uri(path) {
+        match s3_context::fetch(os, path, false).await {
+            Ok(objects) => context_files.extend(objects),
+            Err(err) if is_validation => return Err(err),
+            Err(err) => warn!(?err, path, "failed to fetch an S3 context rule"),
+        }
+        return Ok(());
+    }
 
-                if !found_any && is_validation {
-                    // When validating paths (e.g., for /context add), error if no files match
-                    return Err(eyre!("No files found matching glob pattern '{}'", full_path));
-                }
-                // When just showing expanded files (e.g., for /context show --expand),
-                // silently skip non-matching patterns (don't add anything to context_files)
-            },
-            Err(e) => return Err(eyre!("Invalid glob pattern '{}': {}", full_path, e)),
+    for path in rule_paths(os, path, is_validation, walk).await? {
+        add_file_to_context(os, &path, context_files, cache).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether `rule` matches files, rather than naming a URL, S3 objects or a dynamic source.
+fn is_file_rule(rule: &str) -> bool {
+    !url_context::is_url(rule) && !s3_context::is_s3_uri(rule) && DynamicSource::from_rule(rule).is_none()
+}
+
+/// The files matched by the file system rule `rule`, see [matched_paths]. With `is_validation`,
+/// a rule that matches nothing is an error.
+async fn rule_paths(os: &Os, rule: &str, is_validation: bool, walk: WalkOptions) -> Result<Vec<PathBuf>> {
+    let full_path = expand_path(os, rule)?;
+    let paths = matched_paths(os, &full_path, walk).await?;
+    if paths.is_empty() && is_validation {
+        // When validating paths (e.g., for /context add), error if the rule matches nothing. When
+        // just showing expanded files (e.g., for /context show --expand), silently skip it.
+        if is_glob(&full_path) {
+            return Err(eyre!("No files found matching glob pattern '{}'", full_path));
+        } else if !Path::new(&full_path).exists() {
+            return Err(eyre!("Path '{}' does not exist", full_path));
         }
-    } else {
-        // Regular path
-        let path = Path::new(&full_path);
-        if path.exists() {
-            if path.is_file() {
-                add_file_to_context(os, path, context_files).await?;
-            } else if path.is_dir() {
-                // For directories, add all files in the directory (non-recursive)
-                let mut read_dir = os.fs.read_dir(path).await?;
-                while let Some(entry) = read_dir.next_entry().await? {
-                    let path = entry.path();
-                    if path.is_file() {
-                        add_file_to_context(os, &path, context_files).await?;
+    }
+    Ok(paths)
+}
+
+/// Reads `paths` through `cache`, with a progress bar on stderr when there are many of them.
+async fn read_files(
+    os: &Os,
+    paths: &[PathBuf],
+    cache: &ContentCache,
+    message: &'static str,
+) -> Vec<(PathBuf, Result<String>)> {
+    let progress = ReadProgress::new(message, paths.len());
+    let mut contents = Vec::with_capacity(paths.len());
+    for path in paths {
+        contents.push((path.clone(), cache.read(os, path).await));
+        progress.inc();
+    }
+    contents
+}
+
+/// Files read before a progress bar is shown, fewer are read quickly enough without one.
+const PROGRESS_MIN_FILES: usize = 100;
+
+/// A progress bar of the files being read, with counts and the time left. It's cleared when
+/// dropped, so that one interrupted with Ctrl+C doesn't stay on the screen.
+struct ReadProgress(Option<ProgressBar>);
+
+impl ReadProgress {
+    fn new(message: &'static str, total: usize) -> Self {
+        if total < PROGRESS_MIN_FILES {
+            return Self(None);
+        }
+        let style = ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} files, {eta} left")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> ");
+        let bar = ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stderr())
+            .with_style(style)
+            .with_message(message);
+        Self(Some(bar))
+    }
+
+    fn inc(&self) {
+        if let Some(bar) = &self.0 {
+            bar.inc(1);
+        }
+    }
+}
+
+impl Drop for ReadProgress {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.0 {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains('*') || path.contains('?') || path.contains('[')
+}
+
+/// The files matched by the file system rule at `full_path`: the files of a glob, the files
+/// directly in a directory, or the file itself. A path that doesn't exist matches nothing.
+async fn matched_paths(os: &Os, full_path: &str, walk: WalkOptions) -> Result<Vec<PathBuf>> {
+    let mut filter = WalkFilter::new(walk, full_path);
+    let mut paths = Vec::new();
+
+    if is_glob(full_path) {
+        let entries = glob(full_path).map_err(|e| eyre!("Invalid glob pattern '{}': {}", full_path, e))?;
+        for entry in entries {
+            match entry {
+                Ok(path) => {
+                    if path.is_file() && filter.includes(&path) {
+                        paths.push(path);
                     }
+                },
+                Err(e) => return Err(eyre!("Glob error: {}", e)),
+            }
+        }
+    } else {
+        let path = Path::new(full_path);
+        if path.is_file() {
+            paths.push(path.to_path_buf());
+        } else if path.is_dir() {
+            // For directories, add all files in the directory (non-recursive)
+            let mut read_dir = os.fs.read_dir(path).await?;
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path = entry.path();
+                if path.is_file() && filter.includes(&path) {
+                    paths.push(path);
                 }
             }
-        } else if is_validation {
-            // When validating paths (e.g., for /context add), error if the path doesn't exist
-            return Err(eyre!("Path '{}' does not exist", full_path));
         }
     }
 
-    Ok(())
+    Ok(paths)
+}
+
+/// `path` with `.` and `..` resolved without touching the file system.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => (),
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
 }
 
 /// Add a file to the context collection.
 ///
 /// This method:
-/// 1. Reads the content of the file
+/// 1. Reads the content of the file, or takes it from `cache` if the file hasn't changed
 /// 2. Adds the (filename, content) pair to the context collection
 ///
 /// # Arguments
 /// * `path` - The path to the file
 /// * `context_files` - The collection to add the file to
+/// * `cache` - Content of files read before
 ///
 /// # Returns
 /// A Result indicating success or an error
-async fn add_file_to_context(os: &Os, path: &Path, context_files: &mut Vec<(String, String)>) -> Result<()> {
+async fn add_file_to_context(
+    os: &Os,
+    path: &Path,
+    context_files: &mut Vec<(String, String)>,
+    cache: &ContentCache,
+) -> Result<()> {
     let filename = path.to_string_lossy().to_string();
-    let content = os.fs.read_to_string(path).await?;
+    let content = cache.read(os, path).await?;
     context_files.push((filename, content));
     Ok(())
 }
@@ -870,6 +2043,404 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_validate() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        os.fs.create_dir_all("docs").await?;
+        os.fs.write("docs/guide.md", "guide").await?;
+        os.fs.write("docs/draft.md", "draft").await?;
+        os.fs.write("docs/logo.txt", [0xff, 0xfe, 0x00]).await?;
+        manager
+            .add_paths(
+                &os,
+                vec![
+                    "docs/*.md".to_string(),
+                    "!docs/draft.md".to_string(),
+                    "docs/guide.md".to_string(),
+                    "docs/logo.txt".to_string(),
+                    "src/**/*.rs".to_string(),
+                    "../elsewhere/*.md".to_string(),
+                ],
+                false,
+                true,
+            )
+            .await?;
+
+        let checks = manager.validate(&os).await?;
+        let check = |rule: &str| checks.iter().find(|check| check.rule == rule).unwrap();
+        assert_eq!(check("!docs/draft.md").problems, vec![]);
+        assert_eq!(check("docs/*.md").files, 1);
+        assert_eq!(check("docs/*.md").problems, vec![]);
+        // Already counted towards the glob before it.
+        assert_eq!(check("docs/guide.md").problems, vec![RuleProblem::NoMatches]);
+        assert!(matches!(&check("docs/logo.txt").problems[..], [RuleProblem::Unreadable(files)] if files.len() == 1));
+        assert_eq!(check("src/**/*.rs").problems, vec![RuleProblem::NoMatches]);
+        assert!(matches!(&check("../elsewhere/*.md").problems[..], [
+            RuleProblem::OutsideWorkspace(_),
+            RuleProblem::NoMatches
+        ]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preload() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        os.fs.create_dir_all("notes").await?;
+        for i in 0..PROGRESS_MIN_FILES {
+            os.fs.write(format!("notes/{i}.md"), "note").await?;
+        }
+        manager
+            .add_paths(
+                &os,
+                vec!["notes/*.md".to_string(), "!notes/0.md".to_string()],
+                false,
+                false,
+            )
+            .await?;
+        assert_eq!(manager.preload(&os).await?, PROGRESS_MIN_FILES - 1);
+
+        // A rule matching nothing is skipped rather than failing the rest.
+        manager
+            .add_paths(&os, vec!["missing/*.md".to_string()], false, true)
+            .await?;
+        assert_eq!(manager.preload(&os).await?, PROGRESS_MIN_FILES - 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_profile_summary() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        os.fs.create_dir_all("docs").await?;
+        os.fs.write("docs/guide.md", "a guide to the project").await?;
+        os.fs.write("docs/draft.md", "draft").await?;
+        manager.create_profile(&os, "docs").await?;
+        manager.switch_profile(&os, "docs").await?;
+        manager
+            .add_paths(
+                &os,
+                vec!["docs/*.md".to_string(), "!docs/draft.md".to_string()],
+                false,
+                false,
+            )
+            .await?;
+        manager
+            .add_hook(
+                &os,
+                "status".to_string(),
+                Hook::new_inline_hook(HookTrigger::PerPrompt, "git status".to_string()),
+                false,
+            )
+            .await?;
+        manager.switch_profile(&os, "default").await?;
+
+        let summary = manager.profile_summary(&os, "docs").await?;
+        assert_eq!(summary.rules, vec!["docs/*.md", "!docs/draft.md"]);
+        assert_eq!(summary.hooks, vec![(
+            "status".to_string(),
+            HookTrigger::PerPrompt,
+            false
+        )]);
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.tokens, TokenCounter::count_tokens("a guide to the project"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exclusions() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        os.fs.create_dir_all("src/gen").await?;
+        os.fs.write("src/app.ts", "app").await?;
+        os.fs.write("src/app.test.ts", "test").await?;
+        os.fs.write("src/gen/api.ts", "generated").await?;
+        manager
+            .add_paths(
+                &os,
+                vec![
+                    "src/**/*.ts".to_string(),
+                    "!src/**/*.test.ts".to_string(),
+                    "!src/gen".to_string(),
+                ],
+                false,
+                false,
+            )
+            .await?;
+
+        let files = manager.get_context_files(&os).await?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].0.ends_with("src/app.ts"));
+        assert_eq!(manager.get_context_files_by_path(&os, "src/**/*.ts").await?.len(), 1);
+        assert_eq!(manager.count_excluded_files(&os, "!src/**/*.test.ts").await?, 1);
+        assert_eq!(manager.count_excluded_files(&os, "!src/gen").await?, 1);
+
+        assert!(
+            manager
+                .add_paths(&os, vec!["!src/[".to_string()], false, false)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gitignored_files() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        os.fs.create_dir_all("repo/.git").await?;
+        os.fs.create_dir_all("repo/vendor").await?;
+        os.fs.write("repo/.gitignore", "vendor/\n").await?;
+        os.fs.write("repo/README.md", "readme").await?;
+        os.fs.write("repo/vendor/LICENSE.md", "license").await?;
+        manager
+            .add_paths(&os, vec!["repo/**/*.md".to_string()], false, false)
+            .await?;
+        assert_eq!(manager.get_context_files(&os).await?.len(), 1);
+
+        // Validation uses the rule's options, so a rule matching only ignored files needs them.
+        let rule = "repo/**/LICENSE.md".to_string();
+        assert!(manager.add_paths(&os, vec![rule.clone()], false, false).await.is_err());
+        let options = RuleOptions {
+            gitignore: Some(false),
+            ..Default::default()
+        };
+        validate_rules(&os, &[rule.clone()], options).await?;
+        manager.add_paths(&os, vec![rule.clone()], false, true).await?;
+        manager.set_rule_options(&os, &rule, false, options).await?;
+        assert_eq!(manager.rule_options(&rule), options);
+        assert_eq!(manager.get_context_files(&os).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rule_budgets_and_priorities() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(Some(10)).await?;
+
+        os.fs.create_dir_all("docs").await?;
+        os.fs.write("docs/small.md", "a".repeat(8)).await?;
+        os.fs.write("docs/large.md", "a".repeat(24)).await?;
+        os.fs.write("notes.md", "a".repeat(24)).await?;
+        manager
+            .add_paths(&os, vec!["notes.md".to_string(), "docs/*.md".to_string()], false, false)
+            .await?;
+
+        // Without options, the larger files are dropped first.
+        let (used, dropped) = manager.collect_context_files_with_limit(&os).await?;
+        assert_eq!(used.len(), 2);
+        assert_eq!(dropped.len(), 1);
+
+        // The budget drops the large doc, and the higher priority keeps the docs over the notes.
+        let options = RuleOptions {
+            max_tokens: Some(4),
+            priority: 1,
+            ..Default::default()
+        };
+        manager.set_rule_options(&os, "docs/*.md", false, options).await?;
+        assert_eq!(manager.rule_options("docs/*.md"), options);
+        let (used, dropped) = manager.collect_context_files_with_limit(&os).await?;
+        assert_eq!(used.len(), 2);
+        assert!(used.iter().any(|(name, _)| name.ends_with("docs/small.md")));
+        assert!(dropped.iter().any(|(name, _)| name.ends_with("docs/large.md")));
+
+        manager.remove_paths(&os, vec!["docs/*.md".to_string()], false).await?;
+        assert_eq!(manager.rule_options("docs/*.md"), RuleOptions::default());
+        assert!(
+            manager
+                .set_rule_options(&os, "docs/*.md", false, options)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_move_path() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(Some(10)).await?;
+        os.fs.write("a.md", "a".repeat(24)).await?;
+        os.fs.write("b.md", "b".repeat(24)).await?;
+        let rules = ["a.md", "b.md", "c.md"].map(String::from);
+        manager.add_paths(&os, rules.to_vec(), false, true).await?;
+
+        // Earlier rules are kept when only one of the files fits.
+        let (used, _) = manager.collect_context_files_with_limit(&os).await?;
+        assert!(used[0].0.ends_with("a.md"));
+
+        manager.move_path(&os, "b.md", false, RulePosition::First).await?;
+        assert_eq!(manager.profile_config.paths, ["b.md", "a.md", "c.md"]);
+        let (used, _) = manager.collect_context_files_with_limit(&os).await?;
+        assert!(used[0].0.ends_with("b.md"));
+
+        manager.move_path(&os, "b.md", false, RulePosition::Last).await?;
+        assert_eq!(manager.profile_config.paths, ["a.md", "c.md", "b.md"]);
+        manager
+            .move_path(&os, "a.md", false, RulePosition::After("c.md".to_string()))
+            .await?;
+        assert_eq!(manager.profile_config.paths, ["c.md", "a.md", "b.md"]);
+        manager
+            .move_path(&os, "b.md", false, RulePosition::Before("a.md".to_string()))
+            .await?;
+        assert_eq!(manager.profile_config.paths, ["c.md", "b.md", "a.md"]);
+
+        assert!(
+            manager
+                .move_path(&os, "d.md", false, RulePosition::First)
+                .await
+                .is_err()
+        );
+        assert!(
+            manager
+                .move_path(&os, "a.md", false, RulePosition::Before("d.md".to_string()))
+                .await
+                .is_err()
+        );
+        assert_eq!(manager.profile_config.paths, ["c.md", "b.md", "a.md"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_groups() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        os.fs.create_dir_all("src/api").await?;
+        os.fs.write("src/api/routes.rs", "routes").await?;
+
+        assert!(
+            manager
+                .create_group(&os, "backend", vec!["src/missing/**".to_string()], false)
+                .await
+                .is_err()
+        );
+        manager
+            .create_group(&os, "backend", vec!["src/api/*.rs".to_string()], false)
+            .await?;
+        assert!(manager.create_group(&os, "backend", vec![], true).await.is_err());
+        assert!(manager.get_context_files(&os).await?.is_empty());
+
+        manager.set_group_enabled(&os, "backend", true).await?;
+        let files = manager.get_context_files(&os).await?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].0.ends_with("src/api/routes.rs"));
+
+        manager.set_group_enabled(&os, "backend", false).await?;
+        assert!(manager.get_context_files(&os).await?.is_empty());
+
+        manager.delete_group(&os, "backend").await?;
+        assert!(manager.profile_config.groups.is_empty());
+        assert!(manager.set_group_enabled(&os, "backend", true).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watching() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        os.fs.write("notes.md", "one").await?;
+        manager.add_paths(&os, vec!["*.md".to_string()], false, false).await?;
+        manager.set_watching(true)?;
+        assert!(manager.is_watching());
+        assert_eq!(manager.get_context_files(&os).await?[0].1, "one");
+
+        // Changes are picked up once the watcher has seen them.
+        os.fs.write("notes.md", "two").await?;
+        let mut content = String::new();
+        for _ in 0..50 {
+            content = manager.get_context_files(&os).await?[0].1.clone();
+            if content == "two" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(content, "two");
+
+        // New files matching a rule are picked up too.
+        os.fs.write("todo.md", "three").await?;
+        let mut count = 0;
+        for _ in 0..50 {
+            count = manager.get_context_files(&os).await?.len();
+            if count == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(count, 2);
+
+        manager.set_watching(false)?;
+        assert!(!manager.is_watching());
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_changes() {
+        let sent = [
+            ContextFileDigest::new("a.md", "one"),
+            ContextFileDigest::new("b.md", "two"),
+            ContextFileDigest::new("c.md", "three"),
+        ];
+        let current = [
+            ContextFileDigest::new("a.md", "one"),
+            ContextFileDigest::new("b.md", "two, edited"),
+            ContextFileDigest::new("d.md", "four"),
+        ];
+        let (changes, unchanged) = context_changes(&sent, &current);
+        assert_eq!(unchanged, 1);
+        assert_eq!(changes, [
+            ContextChange::Changed {
+                sent: &sent[1],
+                current: &current[1]
+            },
+            ContextChange::Added(&current[2]),
+            ContextChange::Removed(&sent[2]),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_pins() -> Result<()> {
+        let mut manager = create_test_context_manager(None).await?;
+        let message = PinnedMessage {
+            turn: 2,
+            prompt: Some("use tabs".to_string()),
+            response: None,
+        };
+        manager.pin(message.clone())?;
+        assert!(manager.pin(message.clone()).is_err());
+        assert_eq!(message.as_context(), "[Pinned from turn 2]\nUser: use tabs\n");
+
+        assert!(manager.unpin(2).is_err());
+        assert_eq!(manager.unpin(1)?, message);
+        assert!(manager.pinned.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_response_language() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        manager.set_response_language(&os, Some("es".to_string())).await?;
+        manager.reload_config(&os).await?;
+        assert_eq!(manager.response_language(), Some("es"));
+
+        // Other profiles keep their own language.
+        manager.create_profile(&os, "other").await?;
+        manager.switch_profile(&os, "other").await?;
+        assert_eq!(manager.response_language(), None);
+        manager.switch_profile(&os, "default").await?;
+        assert_eq!(manager.response_language(), Some("es"));
+
+        manager.set_response_language(&os, None).await?;
+        manager.reload_config(&os).await?;
+        assert_eq!(manager.response_language(), None);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_path_ops() -> Result<()> {
         let os = Os::new().await.unwrap();
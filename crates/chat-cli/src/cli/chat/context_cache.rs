@@ -0,0 +1,119 @@
+//! Keeps the content of context files between prompts, so files that haven't changed aren't read,
+//! or for PDFs and images extracted, again for every prompt.
+//!
+//! A file's cached content is used while its modification time and size are the same as when it
+//! was read.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::SystemTime;
+
+use eyre::Result;
+use parking_lot::Mutex;
+
+use super::util::documents::{
+    DocumentKind,
+    extract_text,
+};
+use crate::os::Os;
+
+/// Files kept in the cache. It's emptied once it holds more, which only matters for rules matching
+/// a great many files.
+const MAX_ENTRIES: usize = 2_000;
+
+#[derive(Debug)]
+struct CachedContent {
+    modified: SystemTime,
+    len: u64,
+    content: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ContentCache {
+    entries: Mutex<HashMap<PathBuf, CachedContent>>,
+}
+
+impl ContentCache {
+    /// Reads the text of the file at `path`, from the cache if it hasn't changed since it was read.
+    pub async fn read(&self, os: &Os, path: &Path) -> Result<String> {
+        // Without a modification time the file can't be told apart from the cached copy, so it's
+        // always read.
+        let stamp = tokio::fs::metadata(path)
+            .await
+            .ok()
+            .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+        if let Some((modified, len)) = stamp {
+            if let Some(cached) = self.entries.lock().get(path) {
+                if cached.modified == modified && cached.len == len {
+                    return Ok(cached.content.clone());
+                }
+            }
+        }
+
+        let content = match DocumentKind::of(path) {
+            Some(kind) => extract_text(os, path, kind).await?,
+            None => os.fs.read_to_string(path).await?,
+        };
+        if let Some((modified, len)) = stamp {
+            let mut entries = self.entries.lock();
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+            entries.insert(path.to_path_buf(), CachedContent {
+                modified,
+                len,
+                content: content.clone(),
+            });
+        }
+        Ok(content)
+    }
+
+    /// Files whose content is cached.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_content_cache() {
+        let os = Os::new().await.unwrap();
+        let cache = ContentCache::default();
+        os.fs.write("notes.md", "first").await.unwrap();
+        let path = os.fs.chroot_path("notes.md");
+        assert_eq!(cache.read(&os, &path).await.unwrap(), "first");
+        assert_eq!(cache.len(), 1);
+
+        // A different file behind the same stamp is taken for the cached one.
+        let modified = tokio::fs::metadata(&path).await.unwrap().modified().unwrap();
+        os.fs.write("notes.md", "other").await.unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(cache.read(&os, &path).await.unwrap(), "first");
+
+        // Changing the modification time or the size reads the file again.
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(cache.read(&os, &path).await.unwrap(), "other");
+        os.fs.write("notes.md", "longer").await.unwrap();
+        assert_eq!(cache.read(&os, &path).await.unwrap(), "longer");
+        assert_eq!(cache.len(), 1);
+    }
+}
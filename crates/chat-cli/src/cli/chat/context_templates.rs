@@ -0,0 +1,142 @@
+//! Curated context rules and hooks for common kinds of projects, installed with `/context init`.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use super::cli::hooks::{
+    Hook,
+    HookTrigger,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProjectType {
+    Rust,
+    Node,
+    Python,
+    Terraform,
+}
+
+/// A hook of a template, named by `name` in the profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateHook {
+    pub name: &'static str,
+    pub trigger: HookTrigger,
+    pub command: &'static str,
+}
+
+impl TemplateHook {
+    pub fn hook(&self) -> Hook {
+        Hook::new_inline_hook(self.trigger.clone(), self.command.to_string())
+    }
+}
+
+impl ProjectType {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "Rust",
+            Self::Node => "Node.js",
+            Self::Python => "Python",
+            Self::Terraform => "Terraform",
+        }
+    }
+
+    /// The kind of project in `dir`, judged by its manifest files.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let has = |name: &str| dir.join(name).is_file();
+        if has("Cargo.toml") {
+            Some(Self::Rust)
+        } else if has("package.json") {
+            Some(Self::Node)
+        } else if has("pyproject.toml") || has("setup.py") || has("requirements.txt") {
+            Some(Self::Python)
+        } else if std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.path().extension().is_some_and(|extension| extension == "tf"))
+        {
+            Some(Self::Terraform)
+        } else {
+            None
+        }
+    }
+
+    /// Rules of the template, with a token budget for those matching source files that could fill
+    /// the context. Rules that don't match any files in a project are left out when it's installed.
+    pub fn rules(self) -> &'static [(&'static str, Option<usize>)] {
+        match self {
+            Self::Rust => &[
+                ("Cargo.toml", None),
+                ("README.md", None),
+                ("src/**/*.rs", Some(40_000)),
+                ("tests/**/*.rs", Some(10_000)),
+            ],
+            Self::Node => &[
+                ("package.json", None),
+                ("tsconfig.json", None),
+                ("README.md", None),
+                ("src/**/*.ts", Some(30_000)),
+                ("src/**/*.tsx", Some(20_000)),
+                ("src/**/*.js", Some(30_000)),
+            ],
+            Self::Python => &[
+                ("pyproject.toml", None),
+                ("setup.py", None),
+                ("requirements.txt", None),
+                ("README.md", None),
+                ("**/*.py", Some(40_000)),
+            ],
+            Self::Terraform => &[
+                ("*.tf", None),
+                (".terraform.lock.hcl", None),
+                ("README.md", None),
+                ("modules/**/*.tf", Some(30_000)),
+            ],
+        }
+    }
+
+    pub fn hooks(self) -> &'static [TemplateHook] {
+        match self {
+            Self::Rust => &[TemplateHook {
+                name: "rust-dependencies",
+                trigger: HookTrigger::ConversationStart,
+                command: "cargo tree --depth 1 --edges normal",
+            }],
+            Self::Node => &[TemplateHook {
+                name: "node-dependencies",
+                trigger: HookTrigger::ConversationStart,
+                command: "npm ls --depth=0",
+            }],
+            Self::Python => &[TemplateHook {
+                name: "python-environment",
+                trigger: HookTrigger::ConversationStart,
+                command: "python3 --version && python3 -m pip list --not-required --format=freeze",
+            }],
+            Self::Terraform => &[TemplateHook {
+                name: "terraform-providers",
+                trigger: HookTrigger::ConversationStart,
+                command: "terraform version && terraform providers",
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(ProjectType::detect(dir.path()), None);
+
+        std::fs::write(dir.path().join("main.tf"), "").unwrap();
+        assert_eq!(ProjectType::detect(dir.path()), Some(ProjectType::Terraform));
+        std::fs::write(dir.path().join("requirements.txt"), "").unwrap();
+        assert_eq!(ProjectType::detect(dir.path()), Some(ProjectType::Python));
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        assert_eq!(ProjectType::detect(dir.path()), Some(ProjectType::Node));
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(ProjectType::detect(dir.path()), Some(ProjectType::Rust));
+    }
+}
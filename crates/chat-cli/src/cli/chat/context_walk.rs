@@ -0,0 +1,241 @@
+//! Which files a context rule's glob or directory picks up. By default, files ignored by git and
+//! anything under `node_modules` are skipped, so `/context add "**/*.md"` doesn't pull in vendored
+//! or generated files, and symlinks are followed.
+//!
+//! Both can be set for every rule with the `chat.contextRespectGitignore` and
+//! `chat.contextFollowSymlinks` settings, and for a single rule with `/context add --gitignore` and
+//! `--follow-symlinks`.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use ignore::Match;
+use ignore::gitignore::Gitignore;
+use tracing::warn;
+
+use super::context::RuleOptions;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Directories skipped along with git-ignored files, even outside a repository.
+const IGNORED_DIRS: &[&str] = &[".git", "node_modules"];
+
+/// How the files of a rule are walked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkOptions {
+    pub respect_gitignore: bool,
+    pub follow_symlinks: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            respect_gitignore: true,
+            follow_symlinks: true,
+        }
+    }
+}
+
+impl WalkOptions {
+    /// The options set by `options`, falling back to the settings for those it leaves unset.
+    pub fn new(os: &Os, options: RuleOptions) -> Self {
+        let settings = &os.database.settings;
+        let default = Self::default();
+        Self {
+            respect_gitignore: options.gitignore.unwrap_or_else(|| {
+                settings
+                    .get_bool(Setting::ChatContextRespectGitignore)
+                    .unwrap_or(default.respect_gitignore)
+            }),
+            follow_symlinks: options.follow_symlinks.unwrap_or_else(|| {
+                settings
+                    .get_bool(Setting::ChatContextFollowSymlinks)
+                    .unwrap_or(default.follow_symlinks)
+            }),
+        }
+    }
+}
+
+/// Decides whether the files found for a rule are included, reading each directory's `.gitignore`
+/// once.
+#[derive(Debug)]
+pub struct WalkFilter {
+    options: WalkOptions,
+    /// The part of the rule's path before any wildcard. Symlinks are only checked below it, since
+    /// the user named the directories above it.
+    base: PathBuf,
+    /// Whether the base is ignored itself, in which case the user asked for ignored files and
+    /// nothing is skipped.
+    base_ignored: Option<bool>,
+    gitignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl WalkFilter {
+    pub fn new(options: WalkOptions, full_path: &str) -> Self {
+        let base = Path::new(full_path)
+            .ancestors()
+            .find(|ancestor| !ancestor.to_string_lossy().contains(['*', '?', '[']))
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        Self {
+            options,
+            base,
+            base_ignored: None,
+            gitignores: HashMap::new(),
+        }
+    }
+
+    /// The part of the rule's path before any wildcard.
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    pub fn includes(&mut self, path: &Path) -> bool {
+        if !self.options.follow_symlinks && self.is_through_symlink(path) {
+            return false;
+        }
+        if !self.options.respect_gitignore {
+            return true;
+        }
+        let base_ignored = match self.base_ignored {
+            Some(ignored) => ignored,
+            None => {
+                let base = self.base.clone();
+                let ignored = self.is_ignored(&base, true);
+                self.base_ignored = Some(ignored);
+                ignored
+            },
+        };
+        base_ignored || !self.is_ignored(path, path.is_dir())
+    }
+
+    fn is_through_symlink(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return path.is_symlink();
+        };
+        let mut current = self.base.clone();
+        relative.components().any(|component| {
+            current.push(component);
+            current.is_symlink()
+        })
+    }
+
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        if path
+            .components()
+            .any(|component| IGNORED_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+        {
+            return true;
+        }
+
+        // The closest `.gitignore` that matches decides, up to the root of the repository. Outside
+        // a repository, `.gitignore` files don't apply.
+        let Some(repo_root) = path.ancestors().skip(1).find(|dir| dir.join(".git").exists()) else {
+            return false;
+        };
+        let repo_root = repo_root.to_path_buf();
+        for dir in path.ancestors().skip(1) {
+            let gitignore = self
+                .gitignores
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| read_gitignore(dir));
+            if let Some(gitignore) = gitignore {
+                match gitignore.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => (),
+                }
+            }
+            if dir == repo_root {
+                break;
+            }
+        }
+        false
+    }
+}
+
+fn read_gitignore(dir: &Path) -> Option<Gitignore> {
+    let path = dir.join(".gitignore");
+    if !path.is_file() {
+        return None;
+    }
+    let (gitignore, err) = Gitignore::new(&path);
+    if let Some(err) = err {
+        warn!(?err, ?path, "failed to parse a .gitignore");
+    }
+    Some(gitignore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_walk_filter() {
+        let os = Os::new().await.unwrap();
+        os.fs.create_dir_all("repo/.git").await.unwrap();
+        os.fs.create_dir_all("repo/docs/generated").await.unwrap();
+        os.fs.create_dir_all("repo/node_modules/pkg").await.unwrap();
+        os.fs.create_dir_all("shared").await.unwrap();
+        os.fs
+            .write("repo/.gitignore", "docs/generated/\n*.log\n")
+            .await
+            .unwrap();
+        os.fs.write("repo/docs/.gitignore", "!keep.log\n").await.unwrap();
+        os.fs.write("repo/docs/guide.md", "guide").await.unwrap();
+        os.fs.write("repo/docs/generated/api.md", "api").await.unwrap();
+        os.fs.write("repo/docs/build.log", "log").await.unwrap();
+        os.fs.write("repo/docs/keep.log", "log").await.unwrap();
+        os.fs.write("repo/node_modules/pkg/README.md", "pkg").await.unwrap();
+        os.fs.write("shared/notes.md", "notes").await.unwrap();
+        os.fs
+            .symlink(os.fs.chroot_path("shared"), "repo/docs/shared")
+            .await
+            .unwrap();
+
+        let root = os.fs.chroot_path("repo");
+        let pattern = root.join("**/*").to_string_lossy().to_string();
+        let included = |options: WalkOptions| {
+            let mut filter = WalkFilter::new(options, &pattern);
+            let mut files: Vec<String> = glob::glob(&pattern)
+                .unwrap()
+                .map(Result::unwrap)
+                .filter(|path| path.is_file() && filter.includes(path))
+                .map(|path| path.strip_prefix(&root).unwrap().to_string_lossy().to_string())
+                .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(included(WalkOptions::default()), vec![
+            ".gitignore",
+            "docs/.gitignore",
+            "docs/guide.md",
+            "docs/keep.log",
+            "docs/shared/notes.md"
+        ]);
+        assert_eq!(
+            included(WalkOptions {
+                respect_gitignore: true,
+                follow_symlinks: false,
+            }),
+            vec![".gitignore", "docs/.gitignore", "docs/guide.md", "docs/keep.log"]
+        );
+        assert_eq!(
+            included(WalkOptions {
+                respect_gitignore: false,
+                follow_symlinks: true,
+            })
+            .len(),
+            8
+        );
+
+        // Files under a directory the rule names are included even if the directory is ignored.
+        let generated = root.join("docs/generated/*").to_string_lossy().to_string();
+        let mut filter = WalkFilter::new(WalkOptions::default(), &generated);
+        assert!(filter.includes(&root.join("docs/generated/api.md")));
+    }
+}
@@ -0,0 +1,141 @@
+//! Caches the files matched by context rules while `/context watch on` is set, and drops a rule's
+//! cached files as soon as a file it matches is changed, created or removed.
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use std::sync::Arc;
+
+use eyre::Result;
+use glob::Pattern;
+use notify::{
+    Event,
+    RecommendedWatcher,
+    RecursiveMode,
+    Watcher,
+};
+use parking_lot::Mutex;
+use tracing::warn;
+
+/// Files matched by a rule, as (filename, content) pairs.
+type RuleFiles = Vec<(String, String)>;
+
+#[derive(Debug)]
+struct CachedRule {
+    /// The rule's expanded path, matching the files it would include.
+    pattern: Pattern,
+    files: RuleFiles,
+}
+
+impl CachedRule {
+    fn is_affected_by(&self, path: &Path) -> bool {
+        self.pattern.matches_path(path)
+            || path.starts_with(self.pattern.as_str())
+            || self.files.iter().any(|(file, _)| Path::new(file) == path)
+    }
+}
+
+pub struct ContextWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    watched: Mutex<HashSet<PathBuf>>,
+    cache: Arc<Mutex<HashMap<String, CachedRule>>>,
+}
+
+impl std::fmt::Debug for ContextWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContextWatcher")
+            .field("watched", &self.watched)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+impl ContextWatcher {
+    pub fn new() -> Result<Self> {
+        let cache: Arc<Mutex<HashMap<String, CachedRule>>> = Arc::default();
+        let handler_cache = Arc::clone(&cache);
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if !event.kind.is_access() => {
+                handler_cache
+                    .lock()
+                    .retain(|_, rule| !event.paths.iter().any(|path| rule.is_affected_by(path)));
+            },
+            Ok(_) => (),
+            Err(err) => {
+                warn!(?err, "context watcher error, dropping all cached context files");
+                handler_cache.lock().clear();
+            },
+        })?;
+
+        Ok(Self {
+            watcher: Mutex::new(watcher),
+            watched: Mutex::default(),
+            cache,
+        })
+    }
+
+    /// The cached files of `rule`, if none of them have changed since they were read.
+    pub fn get(&self, rule: &str) -> Option<RuleFiles> {
+        self.cache.lock().get(rule).map(|cached| cached.files.clone())
+    }
+
+    /// Caches the files matched by `rule`, whose expanded path is `full_path`, and starts watching
+    /// the directory they are under.
+    pub fn insert(&self, rule: &str, full_path: &str, files: RuleFiles) {
+        let pattern = match Pattern::new(full_path) {
+            Ok(pattern) => pattern,
+            Err(_) => return,
+        };
+        let dir = watch_root(Path::new(full_path));
+        if !self.watched.lock().contains(&dir) {
+            if let Err(err) = self.watcher.lock().watch(&dir, RecursiveMode::Recursive) {
+                warn!(?err, ?dir, "failed to watch context files, not caching them");
+                return;
+            }
+            self.watched.lock().insert(dir);
+        }
+        self.cache
+            .lock()
+            .insert(rule.to_string(), CachedRule { pattern, files });
+    }
+}
+
+/// The closest existing directory above the first glob in `path`.
+pub fn watch_root(path: &Path) -> PathBuf {
+    let mut root = PathBuf::new();
+    for component in path.components() {
+        if let Component::Normal(part) = component {
+            if part.to_string_lossy().contains(['*', '?', '[']) {
+                break;
+            }
+        }
+        root.push(component);
+    }
+    while !root.is_dir() {
+        if !root.pop() {
+            return PathBuf::from("/");
+        }
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/api")).unwrap();
+        let root = dir.path().to_path_buf();
+        assert_eq!(watch_root(&root.join("src/**/*.rs")), root.join("src"));
+        assert_eq!(watch_root(&root.join("src/api/routes.rs")), root.join("src/api"));
+        assert_eq!(watch_root(&root.join("missing/a.md")), root);
+    }
+}
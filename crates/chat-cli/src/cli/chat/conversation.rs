@@ -1,10 +1,15 @@
 use std::collections::{
+    BTreeMap,
+    BTreeSet,
     HashMap,
     HashSet,
     VecDeque,
 };
 use std::io::Write;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
 
 use crossterm::style::Color;
 use crossterm::{
@@ -27,7 +32,11 @@ use super::consts::{
     MAX_CONVERSATION_STATE_HISTORY_LEN,
     MAX_USER_MESSAGE_SIZE,
 };
-use super::context::ContextManager;
+use super::context::{
+    ContextFileDigest,
+    ContextManager,
+    response_language_instructions,
+};
 use super::message::{
     AssistantMessage,
     ToolUseResult,
@@ -36,6 +45,7 @@ use super::message::{
     UserMessageContent,
     build_env_state,
 };
+use super::quick_actions;
 use super::token_counter::{
     CharCount,
     CharCounter,
@@ -66,12 +76,15 @@ use crate::api_client::model::{
     UserInputMessageContext,
 };
 use crate::cli::chat::ChatError;
+use crate::cli::chat::cli::bookmark::Bookmark;
 use crate::cli::chat::cli::hooks::{
     Hook,
     HookTrigger,
 };
+use crate::database::settings::Setting;
 use crate::mcp_client::Prompt;
 use crate::os::Os;
+use crate::util::encryption;
 
 const CONTEXT_ENTRY_START_HEADER: &str = "--- CONTEXT ENTRY BEGIN ---\n";
 const CONTEXT_ENTRY_END_HEADER: &str = "--- CONTEXT ENTRY END ---\n\n";
@@ -100,11 +113,32 @@ pub struct ConversationState {
     pub tool_manager: ToolManager,
     /// Cached value representing the length of the user context message.
     context_message_length: Option<usize>,
+    /// The context files sent with the last request, compared against by `/context diff`.
+    #[serde(skip)]
+    sent_context_files: Option<Vec<ContextFileDigest>>,
     /// Stores the latest conversation summary created by /compact
     latest_summary: Option<String>,
     /// Model explicitly selected by the user in this conversation state via `/model`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// Prompt variables defined with `/var set`, interpolated into prompts as `{{name}}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// Tags added with `/tag`, used to find the session again with `/sessions list --tag`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// Turns bookmarked with `/bookmark add`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<Bookmark>,
+    /// Changes whenever the history does, to tell whether it changed since it was saved.
+    #[serde(skip)]
+    generation: u64,
+}
+
+/// A generation no conversation of the process had before.
+fn next_generation() -> u64 {
+    static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
 }
 
 impl ConversationState {
@@ -155,8 +189,13 @@ impl ConversationState {
             context_manager,
             tool_manager,
             context_message_length: None,
+            sent_context_files: None,
             latest_summary: None,
             model: current_model_id,
+            variables: BTreeMap::new(),
+            tags: BTreeSet::new(),
+            bookmarks: Vec::new(),
+            generation: 0,
         }
     }
 
@@ -188,6 +227,12 @@ impl ConversationState {
         if failed {
             self.context_manager.take();
         }
+        self.generation = next_generation();
+    }
+
+    /// Changes whenever the history does, e.g. with a new exchange, `/undo` or `/compact`.
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
     pub fn latest_summary(&self) -> Option<&str> {
@@ -198,15 +243,85 @@ impl ConversationState {
         &self.history
     }
 
+    /// Variables describing the session and the prompt being answered, set for the commands of
+    /// hooks so they can tailor their output to what the user is asking.
+    pub fn hook_env(&self) -> Vec<(&'static str, String)> {
+        let next_prompt = self.next_message.as_ref().and_then(|message| message.prompt());
+        let prompts = self.history.iter().filter(|(user, _)| user.prompt().is_some()).count();
+        let prompt = next_prompt.or_else(|| self.history.iter().rev().find_map(|(user, _)| user.prompt()));
+
+        let mut env = vec![
+            ("Q_SESSION_ID", self.conversation_id.clone()),
+            (
+                "Q_TURN_NUMBER",
+                (prompts + usize::from(next_prompt.is_some())).to_string(),
+            ),
+        ];
+        if let Some(prompt) = prompt {
+            env.push(("Q_USER_PROMPT", prompt.to_string()));
+        }
+        if let Some(context_manager) = &self.context_manager {
+            env.push(("Q_PROFILE", context_manager.current_profile.clone()));
+        }
+        env
+    }
+
     /// Clears the conversation history and optionally the summary.
     pub fn clear(&mut self, preserve_summary: bool) {
         self.next_message = None;
         self.history.clear();
+        self.generation = next_generation();
         if !preserve_summary {
             self.latest_summary = None;
         }
     }
 
+    /// Removes the last `n` turns from the history, where a turn starts with a prompt from the
+    /// user and includes the tool uses and results that followed it.
+    ///
+    /// Returns the prompts of the removed turns, oldest first. Fewer than `n` are removed if the
+    /// history is shorter.
+    pub fn undo_turns(&mut self, n: usize) -> Vec<String> {
+        let mut removed = Vec::new();
+        while removed.len() < n {
+            let Some(start) = self.history.iter().rposition(|(user, _)| user.prompt().is_some()) else {
+                break;
+            };
+            let (user, _) = &self.history[start];
+            removed.push(user.prompt().unwrap_or_default().to_string());
+            self.history.truncate(start);
+        }
+        if !removed.is_empty() {
+            self.next_message = None;
+            self.generation = next_generation();
+        }
+        removed.reverse();
+        removed
+    }
+
+    /// Replaces the history with a snapshot taken while `cancelled` were waiting to run, recording
+    /// that they were cancelled with `reason` so that every tool use in the history has a result.
+    pub fn restore_history(
+        &mut self,
+        history: VecDeque<(UserMessage, AssistantMessage)>,
+        cancelled: &[QueuedTool],
+        reason: String,
+    ) {
+        self.history = history;
+        self.next_message = None;
+        self.generation = next_generation();
+        if !cancelled.is_empty() {
+            self.history.push_back((
+                UserMessage::new_cancelled_tool_uses(Some(reason), cancelled.iter().map(|t| t.id.as_str())),
+                AssistantMessage::new_response(
+                    None,
+                    "Tool uses were cancelled, waiting for the next user prompt".to_string(),
+                ),
+            ));
+        }
+        self.enforce_conversation_invariants();
+    }
+
     /// Appends a collection prompts into history and returns the last message in the collection.
     /// It asserts that the collection ends with a prompt that assumes the role of user.
     pub fn append_prompts(&mut self, mut prompts: VecDeque<Prompt>) -> Option<String> {
@@ -231,6 +346,7 @@ impl ConversationState {
                 let user = candidate_user.take().unwrap();
                 self.append_assistant_transcript(&asst);
                 self.history.push_back((user, asst));
+                self.generation = next_generation();
             }
         }
         Some(last_msg.content.to_string())
@@ -244,6 +360,14 @@ impl ConversationState {
         self.next_message = None;
     }
 
+    /// Adds `context` to the next user message, such as the output of hooks run with its tool
+    /// uses.
+    pub fn add_next_message_context(&mut self, context: &str) {
+        if let Some(next_message) = self.next_message.as_mut() {
+            next_message.additional_context.push_str(context);
+        }
+    }
+
     pub async fn set_next_user_message(&mut self, input: String) {
         debug_assert!(self.next_message.is_none(), "next_message should not exist");
         if let Some(next_message) = self.next_message.as_ref() {
@@ -268,9 +392,17 @@ impl ConversationState {
 
         self.append_assistant_transcript(&message);
         self.history.push_back((next_user_message, message));
+        self.generation = next_generation();
 
+        // The conversations table isn't encrypted, so with `storage.encrypt` on the conversation
+        // is only kept by the session store, and a copy stored before encryption was turned on is
+        // dropped.
         if let Ok(cwd) = std::env::current_dir() {
-            os.database.set_conversation_by_path(cwd, self).ok();
+            if encryption::is_enabled(os) {
+                os.database.delete_conversation_by_path(cwd).ok();
+            } else {
+                os.database.set_conversation_by_path(cwd, self).ok();
+            }
         }
     }
 
@@ -462,9 +594,17 @@ impl ConversationState {
             .ok();
         }
 
-        Ok(context
+        let sent_context_files = context.context_files.clone();
+        let state = context
             .into_fig_conversation_state()
-            .expect("unable to construct conversation state"))
+            .expect("unable to construct conversation state");
+        self.sent_context_files = Some(sent_context_files);
+        Ok(state)
+    }
+
+    /// The context files sent with the last request, or `None` before the first request.
+    pub fn sent_context_files(&self) -> Option<&[ContextFileDigest]> {
+        self.sent_context_files.as_deref()
     }
 
     pub async fn update_state(&mut self, force_update: bool) {
@@ -509,8 +649,9 @@ impl ConversationState {
 
         // Run hooks and add to conversation start and next user message.
         let mut conversation_start_context = None;
+        let hook_env = self.hook_env();
         if let (true, Some(cm)) = (run_hooks, self.context_manager.as_mut()) {
-            let hook_results = cm.run_hooks(output).await?;
+            let hook_results = cm.run_hooks(&hook_env, output).await?;
             conversation_start_context = Some(format_hook_context(hook_results.iter(), HookTrigger::ConversationStart));
 
             // add per prompt content to next_user_message if available
@@ -519,7 +660,8 @@ impl ConversationState {
             }
         }
 
-        let (context_messages, dropped_context_files) = self.context_messages(os, conversation_start_context).await;
+        let (context_messages, context_files, dropped_context_files) =
+            self.context_messages(os, conversation_start_context).await;
 
         Ok(BackendConversationState {
             conversation_id: self.conversation_id.as_str(),
@@ -528,6 +670,7 @@ impl ConversationState {
                 .history
                 .range(self.valid_history_range.0..self.valid_history_range.1),
             context_messages,
+            context_files,
             dropped_context_files,
             tools: &self.tools,
             model_id: self.model.as_deref(),
@@ -632,6 +775,7 @@ impl ConversationState {
     pub fn replace_history_with_summary(&mut self, summary: String) {
         self.history.drain(..(self.history.len().saturating_sub(1)));
         self.latest_summary = Some(summary);
+        self.generation = next_generation();
         // If the last message contains tool results, then we add the results to the content field
         // instead. This is required to avoid validation errors.
         // TODO: this can break since the max user content size is less than the max tool response
@@ -681,9 +825,22 @@ impl ConversationState {
         &mut self,
         os: &Os,
         conversation_start_context: Option<String>,
-    ) -> (Option<Vec<(UserMessage, AssistantMessage)>>, Vec<(String, String)>) {
+    ) -> (
+        Option<Vec<(UserMessage, AssistantMessage)>>,
+        Vec<ContextFileDigest>,
+        Vec<(String, String)>,
+    ) {
         let mut context_content = String::new();
+        let mut context_files = Vec::new();
         let mut dropped_context_files = Vec::new();
+        if let Some(context_manager) = self.context_manager.as_ref().filter(|cm| !cm.pinned.is_empty()) {
+            context_content.push_str(CONTEXT_ENTRY_START_HEADER);
+            context_content.push_str("These messages were pinned from earlier in our conversation. Keep following them for the rest of the conversation.\n\n");
+            for message in &context_manager.pinned {
+                context_content.push_str(&message.as_context());
+            }
+            context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+        }
         if let Some(summary) = &self.latest_summary {
             context_content.push_str(CONTEXT_ENTRY_START_HEADER);
             context_content.push_str("This summary contains ALL relevant information from our previous conversation including tool uses, results, code analysis, and file operations. YOU MUST reference this information when answering questions and explicitly acknowledge specific details from the summary when they're relevant to the current question.\n\n");
@@ -704,6 +861,7 @@ impl ConversationState {
                     if !files_to_use.is_empty() {
                         context_content.push_str(CONTEXT_ENTRY_START_HEADER);
                         for (filename, content) in files_to_use {
+                            context_files.push(ContextFileDigest::new(&filename, &content));
                             context_content.push_str(&format!("[{}]\n{}\n", filename, content));
                         }
                         context_content.push_str(CONTEXT_ENTRY_END_HEADER);
@@ -719,13 +877,34 @@ impl ConversationState {
             context_content.push_str(&context);
         }
 
+        if os
+            .database
+            .settings
+            .get_bool(Setting::ChatEnableQuickActions)
+            .unwrap_or(false)
+        {
+            context_content.push_str(CONTEXT_ENTRY_START_HEADER);
+            context_content.push_str(quick_actions::INSTRUCTIONS);
+            context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+        }
+
+        if let Some(language) = self.context_manager.as_ref().and_then(|cm| cm.response_language()) {
+            context_content.push_str(CONTEXT_ENTRY_START_HEADER);
+            context_content.push_str(&response_language_instructions(language));
+            context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+        }
+
         if !context_content.is_empty() {
             self.context_message_length = Some(context_content.len());
             let user_msg = UserMessage::new_prompt(context_content);
             let assistant_msg = AssistantMessage::new_response(None, "I will fully incorporate this information when generating my responses, and explicitly acknowledge relevant parts of the summary when answering questions.".into());
-            (Some(vec![(user_msg, assistant_msg)]), dropped_context_files)
+            (
+                Some(vec![(user_msg, assistant_msg)]),
+                context_files,
+                dropped_context_files,
+            )
         } else {
-            (None, dropped_context_files)
+            (None, context_files, dropped_context_files)
         }
     }
 
@@ -843,6 +1022,8 @@ pub struct BackendConversationStateImpl<'a, T, U> {
     pub next_user_message: Option<&'a UserMessage>,
     pub history: T,
     pub context_messages: U,
+    /// The context files included in [Self::context_messages].
+    pub context_files: Vec<ContextFileDigest>,
     pub dropped_context_files: Vec<(String, String)>,
     pub tools: &'a HashMap<ToolOrigin, Vec<Tool>>,
     pub model_id: Option<&'a str>,
@@ -943,7 +1124,10 @@ impl From<InputSchema> for ToolInputSchema {
     }
 }
 
-fn format_hook_context<'a>(hook_results: impl IntoIterator<Item = &'a (Hook, String)>, trigger: HookTrigger) -> String {
+pub fn format_hook_context<'a>(
+    hook_results: impl IntoIterator<Item = &'a (Hook, String)>,
+    trigger: HookTrigger,
+) -> String {
     let mut context_content = String::new();
 
     context_content.push_str(CONTEXT_ENTRY_START_HEADER);
@@ -953,7 +1137,10 @@ fn format_hook_context<'a>(hook_results: impl IntoIterator<Item = &'a (Hook, Str
     }
     context_content.push_str("\n\n");
 
-    for (hook, output) in hook_results.into_iter().filter(|(h, _)| h.trigger == trigger) {
+    for (hook, output) in hook_results
+        .into_iter()
+        .filter(|(h, _)| h.trigger.context_trigger() == trigger)
+    {
         context_content.push_str(&format!("'{}': {output}\n\n", &hook.name));
     }
     context_content.push_str(CONTEXT_ENTRY_END_HEADER);
@@ -964,6 +1151,7 @@ fn format_hook_context<'a>(hook_results: impl IntoIterator<Item = &'a (Hook, Str
 mod tests {
     use super::super::context::{
         AMAZONQ_FILENAME,
+        PinnedMessage,
         profile_context_path,
     };
     use super::super::message::AssistantToolUse;
@@ -1260,4 +1448,140 @@ mod tests {
             conversation.set_next_user_message(i.to_string()).await;
         }
     }
+
+    #[tokio::test]
+    async fn test_pinned_messages_survive_summary() {
+        let mut os = Os::new().await.unwrap();
+        let mut conversation = ConversationState::new(
+            &mut os,
+            "fake_conv_id",
+            HashMap::new(),
+            None,
+            ToolManager::default(),
+            None,
+        )
+        .await;
+        conversation
+            .context_manager
+            .as_mut()
+            .unwrap()
+            .pin(PinnedMessage {
+                turn: 1,
+                prompt: Some("always answer in French".to_string()),
+                response: None,
+            })
+            .unwrap();
+
+        conversation.set_next_user_message("first".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "1".to_string()));
+        conversation.set_next_user_message("second".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "2".to_string()));
+        conversation.replace_history_with_summary("the user asked twice".to_string());
+        conversation.set_next_user_message("third".to_string()).await;
+
+        let state = conversation
+            .as_sendable_conversation_state(&os, &mut vec![], true)
+            .await
+            .unwrap();
+        let ChatMessage::UserInputMessage(user) = &state.history.as_ref().unwrap()[0] else {
+            panic!("Expected the first message to be the context message");
+        };
+        let pinned = user.content.find("always answer in French").unwrap();
+        let summary = user.content.find("the user asked twice").unwrap();
+        assert!(pinned < summary, "pinned messages should come before the summary");
+    }
+
+    #[tokio::test]
+    async fn test_not_stored_in_plaintext_when_encrypted() {
+        let mut os = Os::new().await.unwrap();
+        let mut conversation = ConversationState::new(
+            &mut os,
+            "fake_conv_id",
+            HashMap::new(),
+            None,
+            ToolManager::default(),
+            None,
+        )
+        .await;
+        let cwd = std::env::current_dir().unwrap();
+
+        conversation.set_next_user_message("first".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "1".to_string()));
+        assert!(os.database.get_conversation_by_path(&cwd).unwrap().is_some());
+
+        os.database.settings.set(Setting::StorageEncrypt, "on").await.unwrap();
+        conversation.set_next_user_message("second".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "2".to_string()));
+        assert!(os.database.get_conversation_by_path(&cwd).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_undo_turns() {
+        let mut os = Os::new().await.unwrap();
+        let mut conversation = ConversationState::new(
+            &mut os,
+            "fake_conv_id",
+            HashMap::new(),
+            None,
+            ToolManager::default(),
+            None,
+        )
+        .await;
+
+        conversation.set_next_user_message("first".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "1".to_string()));
+        // A turn with a tool use spans several history entries.
+        conversation.set_next_user_message("second".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_tool_use(None, "reading".to_string(), vec![AssistantToolUse {
+                id: "t1".to_string(),
+                name: "fs_read".to_string(),
+                ..Default::default()
+            }]),
+        );
+        conversation.add_tool_results(vec![ToolUseResult {
+            tool_use_id: "t1".to_string(),
+            content: vec![],
+            status: ToolResultStatus::Success,
+        }]);
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "2".to_string()));
+        assert_eq!(conversation.history().len(), 3);
+
+        assert_eq!(conversation.undo_turns(1), vec!["second".to_string()]);
+        assert_eq!(conversation.history().len(), 1);
+        assert_eq!(conversation.undo_turns(5), vec!["first".to_string()]);
+        assert!(conversation.history().is_empty());
+        assert!(conversation.undo_turns(1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hook_env() {
+        let mut os = Os::new().await.unwrap();
+        let mut conversation = ConversationState::new(
+            &mut os,
+            "fake_conv_id",
+            HashMap::new(),
+            None,
+            ToolManager::default(),
+            None,
+        )
+        .await;
+        let env = |conversation: &ConversationState| conversation.hook_env().into_iter().collect::<HashMap<_, _>>();
+
+        conversation.set_next_user_message("first".to_string()).await;
+        let first = env(&conversation);
+        assert_eq!(first["Q_SESSION_ID"], "fake_conv_id");
+        assert_eq!(first["Q_TURN_NUMBER"], "1");
+        assert_eq!(first["Q_USER_PROMPT"], "first");
+
+        // After the response, tool hooks see the prompt being answered.
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "1".to_string()));
+        assert_eq!(env(&conversation)["Q_TURN_NUMBER"], "1");
+        assert_eq!(env(&conversation)["Q_USER_PROMPT"], "first");
+
+        conversation.set_next_user_message("second".to_string()).await;
+        assert_eq!(env(&conversation)["Q_TURN_NUMBER"], "2");
+        assert_eq!(env(&conversation)["Q_USER_PROMPT"], "second");
+    }
 }
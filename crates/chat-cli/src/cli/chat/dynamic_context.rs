@@ -0,0 +1,277 @@
+//! Context rules that are generated for each prompt rather than read from a file, added with
+//! `/context add --dynamic <SOURCE>` and stored as `dynamic:<SOURCE>`.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::process::Command;
+
+use super::context::RuleOptions;
+use super::context_walk::{
+    WalkFilter,
+    WalkOptions,
+};
+use crate::os::Os;
+
+/// How dynamic rules are written in the context config.
+const RULE_PREFIX: &str = "dynamic:";
+
+/// Levels of the current directory listed by `tree` when no depth is given.
+const DEFAULT_TREE_DEPTH: usize = 2;
+
+/// Entries listed for a directory in a `tree`, the rest are counted.
+const MAX_TREE_DIR_ENTRIES: usize = 40;
+
+/// Lines of a `tree`, the rest of the listing is left out.
+const MAX_TREE_LINES: usize = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicSource {
+    /// Staged and unstaged changes in the current git repository.
+    GitDiff,
+    /// The files and directories under the current directory, `depth` levels deep, skipping those
+    /// ignored by git.
+    Tree { depth: usize },
+}
+
+impl DynamicSource {
+    pub const ALL: &[Self] = &[Self::GitDiff, Self::Tree {
+        depth: DEFAULT_TREE_DEPTH,
+    }];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::GitDiff => "git-diff",
+            Self::Tree { .. } => "tree",
+        }
+    }
+
+    /// The rule stored in the context config for this source.
+    pub fn rule(self) -> String {
+        match self {
+            Self::Tree { depth } if depth != DEFAULT_TREE_DEPTH => format!("{RULE_PREFIX}tree:{depth}"),
+            _ => format!("{RULE_PREFIX}{}", self.name()),
+        }
+    }
+
+    /// The source listing `depth` levels, which only applies to `tree`.
+    pub fn with_depth(self, depth: Option<usize>) -> Result<Self> {
+        match (self, depth) {
+            (_, None) => Ok(self),
+            (Self::Tree { .. }, Some(0)) => bail!("The depth of a tree must be at least 1"),
+            (Self::Tree { .. }, Some(depth)) => Ok(Self::Tree { depth }),
+            (_, Some(_)) => bail!("--depth only applies to the tree source"),
+        }
+    }
+
+    /// Reads the source of a rule, or returns `None` if it isn't a dynamic rule.
+    pub fn from_rule(rule: &str) -> Option<Result<Self>> {
+        rule.strip_prefix(RULE_PREFIX).map(str::parse)
+    }
+
+    /// Generates the content for the current prompt. Empty content adds nothing to the context.
+    pub async fn render(self, os: &Os) -> Result<String> {
+        match self {
+            Self::GitDiff => git_diff(&os.env.current_dir()?).await,
+            Self::Tree { depth } => {
+                let cwd = os.env.current_dir()?;
+                let walk = WalkOptions::new(os, RuleOptions::default());
+                Ok(tree(&cwd, depth, walk))
+            },
+        }
+    }
+}
+
+impl FromStr for DynamicSource {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, depth) = match s.split_once(':') {
+            Some(("tree", depth)) => match depth.parse() {
+                Ok(depth) => ("tree", Some(depth)),
+                Err(_) => bail!("'{depth}' is not a depth, expected a number such as tree:3"),
+            },
+            _ => (s, None),
+        };
+        match Self::ALL.iter().find(|source| source.name() == name) {
+            Some(source) => source.with_depth(depth),
+            None => {
+                let names = Self::ALL.iter().map(|source| source.name()).collect::<Vec<_>>();
+                bail!(
+                    "Unknown dynamic context source '{s}', expected one of: {}",
+                    names.join(", ")
+                )
+            },
+        }
+    }
+}
+
+pub async fn git(cwd: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(cwd).output().await?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn git_diff(cwd: &Path) -> Result<String> {
+    let staged = git(cwd, &["diff", "--no-color", "--cached"]).await?;
+    let unstaged = git(cwd, &["diff", "--no-color"]).await?;
+
+    let mut content = String::new();
+    for (title, diff) in [("Staged changes", staged), ("Unstaged changes", unstaged)] {
+        if !diff.trim().is_empty() {
+            content.push_str(&format!("{title}:\n```diff\n{}\n```\n", diff.trim_end()));
+        }
+    }
+    Ok(content)
+}
+
+/// Lists the files and directories under `root` like `tree` does, directories first. Symlinked
+/// directories aren't entered, and long directories and listings are cut short.
+fn tree(root: &Path, depth: usize, walk: WalkOptions) -> String {
+    let mut filter = WalkFilter::new(walk, &root.to_string_lossy());
+    let mut lines = vec![".".to_string()];
+    tree_dir(root, depth, "", &mut filter, &mut lines);
+    if lines.len() > MAX_TREE_LINES {
+        let omitted = lines.len() - MAX_TREE_LINES;
+        lines.truncate(MAX_TREE_LINES);
+        lines.push(format!("... {omitted} more lines"));
+    }
+    format!(
+        "Directory tree of {} ({depth} levels):\n```\n{}\n```\n",
+        root.display(),
+        lines.join("\n")
+    )
+}
+
+fn tree_dir(dir: &Path, depth: usize, prefix: &str, filter: &mut WalkFilter, lines: &mut Vec<String>) {
+    if depth == 0 || lines.len() > MAX_TREE_LINES {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|name| name != ".git") && filter.includes(path))
+        .map(|path| (!path.is_dir() || path.is_symlink(), path))
+        .collect();
+    entries.sort();
+
+    let shown = entries.len().min(MAX_TREE_DIR_ENTRIES);
+    for (i, (is_leaf, path)) in entries.iter().take(shown).enumerate() {
+        let last = i + 1 == entries.len();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let line = match (is_leaf, fs::read_link(path)) {
+            (_, Ok(target)) => format!("{name} -> {}", target.display()),
+            (false, _) => format!("{name}/"),
+            (true, _) => name.to_string(),
+        };
+        lines.push(format!("{prefix}{}{line}", if last { "└── " } else { "├── " }));
+        if !is_leaf {
+            let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            tree_dir(path, depth - 1, &prefix, filter, lines);
+        }
+    }
+    if entries.len() > shown {
+        lines.push(format!("{prefix}└── ... {} more", entries.len() - shown));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rule() {
+        assert_eq!(DynamicSource::GitDiff.rule(), "dynamic:git-diff");
+        assert_eq!(
+            DynamicSource::from_rule("dynamic:git-diff").unwrap().unwrap(),
+            DynamicSource::GitDiff
+        );
+        assert!(DynamicSource::from_rule("dynamic:weather").unwrap().is_err());
+        assert!(DynamicSource::from_rule("README.md").is_none());
+
+        let tree = DynamicSource::Tree { depth: 4 };
+        assert_eq!(tree.rule(), "dynamic:tree:4");
+        assert_eq!(DynamicSource::from_rule("dynamic:tree:4").unwrap().unwrap(), tree);
+        assert_eq!("tree".parse::<DynamicSource>().unwrap().rule(), "dynamic:tree");
+        assert!("tree:0".parse::<DynamicSource>().is_err());
+        assert!(DynamicSource::GitDiff.with_depth(Some(2)).is_err());
+    }
+
+    #[test]
+    fn test_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src/cli/chat")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("Cargo.toml"), "").unwrap();
+        std::fs::write(root.join("src/main.rs"), "").unwrap();
+        std::fs::write(root.join("src/cli/mod.rs"), "").unwrap();
+
+        let listing = tree(root, 2, WalkOptions::default());
+        let expected = "```
+.
+├── src/
+│   ├── cli/
+│   └── main.rs
+├── .gitignore
+└── Cargo.toml
+```
+";
+        assert!(listing.ends_with(expected), "{listing}");
+
+        let listing = tree(root, 3, WalkOptions {
+            respect_gitignore: false,
+            follow_symlinks: true,
+        });
+        assert!(listing.contains("│   │   ├── chat/\n│   │   └── mod.rs\n"), "{listing}");
+        assert!(listing.contains("├── target/\n│   └── debug/\n"), "{listing}");
+    }
+
+    #[tokio::test]
+    async fn test_git_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path();
+        assert!(git_diff(cwd).await.is_err());
+
+        git(cwd, &["init", "--quiet"]).await.unwrap();
+        std::fs::write(cwd.join("a.txt"), "one\n").unwrap();
+        git(cwd, &["add", "a.txt"]).await.unwrap();
+        git(cwd, &[
+            "-c",
+            "user.name=q",
+            "-c",
+            "user.email=q@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "a",
+        ])
+        .await
+        .unwrap();
+        assert_eq!(git_diff(cwd).await.unwrap(), "");
+
+        std::fs::write(cwd.join("a.txt"), "two\n").unwrap();
+        let diff = git_diff(cwd).await.unwrap();
+        assert!(diff.starts_with("Unstaged changes:\n```diff\n"));
+        assert!(diff.contains("-one\n+two"));
+
+        git(cwd, &["add", "a.txt"]).await.unwrap();
+        assert!(git_diff(cwd).await.unwrap().starts_with("Staged changes:"));
+    }
+}
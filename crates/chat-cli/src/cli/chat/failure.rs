@@ -0,0 +1,268 @@
+//! Summaries of failed shell commands, run either by `execute_bash` or with `!`, so that the user
+//! can ask for a fix with a single key instead of describing the failure again.
+
+use std::fmt::Display;
+use std::io::{
+    Read,
+    Write,
+};
+use std::process::{
+    Command,
+    ExitStatus,
+    Stdio,
+};
+
+use crate::cli::chat::util::truncate_safe;
+
+/// Number of output lines kept in a summary.
+const TAIL_LINES: usize = 10;
+/// Longest output line kept in a summary before it's truncated.
+const MAX_LINE_BYTES: usize = 200;
+/// Amount of stderr kept by [run_shell_command].
+const MAX_CAPTURED_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    CommandNotFound,
+    PermissionDenied,
+    MissingDependency,
+    CompileError,
+    TestFailure,
+    Network,
+    Other,
+}
+
+impl FailureKind {
+    /// Guesses the kind of failure from the command, its exit status and its output.
+    fn detect(command: &str, exit_status: Option<i32>, output: &str) -> Self {
+        let output = output.to_lowercase();
+        let contains_any = |patterns: &[&str]| patterns.iter().any(|p| output.contains(p));
+
+        if exit_status == Some(127) || contains_any(&["command not found", "not recognized as an internal"]) {
+            Self::CommandNotFound
+        } else if exit_status == Some(126) || contains_any(&["permission denied", "eacces", "operation not permitted"])
+        {
+            Self::PermissionDenied
+        } else if contains_any(&[
+            "test result: failed",
+            "tests failed",
+            "failed tests",
+            "assertionerror",
+            "assertion failed",
+            "--- fail:",
+            "short test summary info",
+        ]) {
+            Self::TestFailure
+        } else if contains_any(&[
+            "no module named",
+            "modulenotfounderror",
+            "cannot find module",
+            "could not resolve dependencies",
+            "unresolved import",
+            "package not found",
+        ]) {
+            Self::MissingDependency
+        } else if contains_any(&[
+            "error[e",
+            "could not compile",
+            "syntaxerror",
+            "compilation failed",
+            "cannot find symbol",
+            "error ts",
+            "undefined reference",
+        ]) {
+            Self::CompileError
+        } else if contains_any(&[
+            "connection refused",
+            "could not resolve host",
+            "network is unreachable",
+            "timed out",
+        ]) {
+            Self::Network
+        } else if command
+            .split_whitespace()
+            .any(|word| word == "test" || word == "pytest")
+        {
+            Self::TestFailure
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::CommandNotFound => "command not found",
+            Self::PermissionDenied => "permission denied",
+            Self::MissingDependency => "missing dependency",
+            Self::CompileError => "compile error",
+            Self::TestFailure => "test failure",
+            Self::Network => "network error",
+            Self::Other => "error",
+        })
+    }
+}
+
+/// A compact description of a shell command that exited unsuccessfully.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureSummary {
+    pub command: String,
+    /// `None` if the command was killed by a signal.
+    pub exit_status: Option<i32>,
+    /// The last lines of stderr, or of stdout if nothing was written to stderr.
+    pub output_tail: Vec<String>,
+    pub kind: FailureKind,
+}
+
+impl FailureSummary {
+    /// Returns a summary of the command, or `None` if it succeeded.
+    pub fn new(command: &str, exit_status: Option<i32>, stdout: &str, stderr: &str) -> Option<Self> {
+        if exit_status == Some(0) {
+            return None;
+        }
+
+        let output = if stderr.trim().is_empty() { stdout } else { stderr };
+        let mut output_tail = output
+            .lines()
+            .rev()
+            .filter(|line| !line.trim().is_empty())
+            .take(TAIL_LINES)
+            .map(|line| truncate_safe(line.trim_end(), MAX_LINE_BYTES).to_string())
+            .collect::<Vec<_>>();
+        output_tail.reverse();
+
+        Some(Self {
+            command: command.to_string(),
+            exit_status,
+            output_tail,
+            kind: FailureKind::detect(command, exit_status, &format!("{stdout}\n{stderr}")),
+        })
+    }
+
+    /// Builds a summary from the JSON output of the `execute_bash` tool.
+    pub fn from_tool_output(command: &str, output: &serde_json::Value) -> Option<Self> {
+        let field = |name: &str| output.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+        Self::new(
+            command,
+            field("exit_status").parse().ok(),
+            field("stdout"),
+            field("stderr"),
+        )
+    }
+
+    /// A one line description, e.g. ``cargo test` exited with status 101 (test failure)`.
+    pub fn headline(&self) -> String {
+        match self.exit_status {
+            Some(status) => format!("`{}` exited with status {status} ({})", self.command, self.kind),
+            None => format!("`{}` was terminated by a signal ({})", self.command, self.kind),
+        }
+    }
+
+    /// The prompt sent when the user asks for the failure to be fixed.
+    pub fn fix_prompt(&self) -> String {
+        let mut prompt = format!("{}.", self.headline().replacen('`', "The command `", 1));
+        if !self.output_tail.is_empty() {
+            prompt.push_str(&format!(
+                " The last lines of its output were:\n```\n{}\n```",
+                self.output_tail.join("\n")
+            ));
+        }
+        prompt.push_str("\nFind the cause of the failure and fix it.");
+        prompt
+    }
+}
+
+/// Runs a command typed with `!`, with the terminal attached, returning its exit status and the
+/// last part of what it wrote to stderr.
+///
+/// stderr is copied to the terminal as it is written, so prompts without a trailing newline still
+/// show up.
+pub fn run_shell_command(command: &str) -> std::io::Result<(ExitStatus, String)> {
+    let mut child = if cfg!(target_os = "windows") {
+        Command::new("cmd")
+            .args(["/C", command])
+            .stderr(Stdio::piped())
+            .spawn()?
+    } else {
+        Command::new("bash")
+            .args(["-c", command])
+            .stderr(Stdio::piped())
+            .spawn()?
+    };
+
+    let mut captured = Vec::new();
+    if let Some(mut child_stderr) = child.stderr.take() {
+        let mut stderr = std::io::stderr();
+        let mut buf = [0; 4096];
+        loop {
+            let n = match child_stderr.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            stderr.write_all(&buf[..n])?;
+            stderr.flush()?;
+            captured.extend_from_slice(&buf[..n]);
+            if captured.len() > MAX_CAPTURED_BYTES {
+                captured.drain(..captured.len() - MAX_CAPTURED_BYTES);
+            }
+        }
+    }
+
+    Ok((child.wait()?, String::from_utf8_lossy(&captured).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_kind() {
+        let kind = |command: &str, status: i32, output: &str| FailureKind::detect(command, Some(status), output);
+        assert_eq!(
+            kind("foo", 127, "bash: foo: command not found"),
+            FailureKind::CommandNotFound
+        );
+        assert_eq!(kind("./run.sh", 126, ""), FailureKind::PermissionDenied);
+        assert_eq!(
+            kind("cargo test", 101, "test result: FAILED. 3 passed; 1 failed"),
+            FailureKind::TestFailure
+        );
+        assert_eq!(
+            kind("cargo build", 101, "error[E0425]: cannot find value `x`"),
+            FailureKind::CompileError
+        );
+        assert_eq!(
+            kind("python main.py", 1, "ModuleNotFoundError: No module named 'requests'"),
+            FailureKind::MissingDependency
+        );
+        assert_eq!(kind("npm test", 1, "something went wrong"), FailureKind::TestFailure);
+        assert_eq!(kind("make", 2, "something went wrong"), FailureKind::Other);
+    }
+
+    #[test]
+    fn test_summary() {
+        assert!(FailureSummary::new("ls", Some(0), "file", "").is_none());
+
+        let stdout = (1..=20).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let summary = FailureSummary::new("cargo test", Some(101), &stdout, "").unwrap();
+        assert_eq!(summary.output_tail.len(), TAIL_LINES);
+        assert_eq!(summary.output_tail.last().unwrap(), "line 20");
+        assert_eq!(summary.kind, FailureKind::TestFailure);
+
+        let output = serde_json::json!({
+            "exit_status": "1",
+            "stdout": "",
+            "stderr": "error: could not compile `foo`\n",
+        });
+        let summary = FailureSummary::from_tool_output("cargo build", &output).unwrap();
+        assert_eq!(summary.headline(), "`cargo build` exited with status 1 (compile error)");
+        assert_eq!(
+            summary.fix_prompt(),
+            "The command `cargo build` exited with status 1 (compile error). The last lines of its output \
+             were:\n```\nerror: could not compile `foo`\n```\nFind the cause of the failure and fix it."
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! The restricted mode hook commands run in when the hook sets `sandbox`, or by default with the
+//! `chat.sandboxHooks` setting. Hooks shared with a profile or a workspace run their commands
+//! with each prompt, so a sandboxed command runs:
+//!
+//! - without network access
+//! - only able to read the workspace, a temporary directory and the system's directories, such as
+//!   `/usr` and `/etc`, so not the home directory and its credentials
+//! - only able to write to the workspace and a temporary directory
+//! - with limits on CPU time, memory, the size of files written and files open
+//!
+//! On Linux the command runs in [bubblewrap](https://github.com/containers/bubblewrap) (`bwrap`),
+//! and on macOS with `sandbox-exec`. Where neither is available, sandboxed hooks fail rather than
+//! running unrestricted.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::process::Command;
+
+/// Bytes of address space a sandboxed command can use.
+#[cfg(unix)]
+const MEMORY_LIMIT: u64 = 8 << 30;
+
+/// Largest file a sandboxed command can write, in bytes.
+#[cfg(unix)]
+const FILE_SIZE_LIMIT: u64 = 256 << 20;
+
+/// Most files a sandboxed command can have open at once.
+#[cfg(unix)]
+const OPEN_FILES_LIMIT: u64 = 1024;
+
+/// The system's directories a sandboxed command can read on Linux, bound when they exist.
+#[cfg(not(target_os = "macos"))]
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt", "/nix",
+];
+
+/// The profile of `sandbox-exec`, with reads and writes allowed to the `WORKSPACE` parameter.
+/// Metadata can be read everywhere, so that paths resolve, but not the contents of files outside
+/// the workspace and the system's directories.
+#[cfg(target_os = "macos")]
+const SEATBELT_PROFILE: &str = r#"(version 1)
+(allow default)
+(deny network*)
+(deny file-read*)
+(allow file-read-metadata)
+(allow file-read*
+    (literal "/")
+    (subpath (param "WORKSPACE"))
+    (subpath "/usr")
+    (subpath "/bin")
+    (subpath "/sbin")
+    (subpath "/opt")
+    (subpath "/System")
+    (subpath "/Library")
+    (subpath "/private/etc")
+    (subpath "/private/tmp")
+    (subpath "/private/var/folders")
+    (subpath "/dev"))
+(deny file-write*)
+(allow file-write*
+    (subpath (param "WORKSPACE"))
+    (subpath "/private/tmp")
+    (subpath "/private/var/folders")
+    (literal "/dev/null")
+    (literal "/dev/tty"))
+"#;
+
+/// The command running `command` with bash in the sandbox, reading only `workspace` and the
+/// system's directories and writing only to `workspace`. Its CPU time is limited to `timeout`.
+pub fn command(command: &str, workspace: &Path, timeout: Duration) -> io::Result<Command> {
+    #[cfg(target_os = "linux")]
+    let mut sandboxed = {
+        let mut sandboxed = Command::new("bwrap");
+        let home = std::env::var_os("HOME").map(std::path::PathBuf::from);
+        sandboxed.args(bwrap_args(command, workspace, home.as_deref()));
+        sandboxed
+    };
+
+    #[cfg(target_os = "macos")]
+    let mut sandboxed = {
+        let mut sandboxed = Command::new("sandbox-exec");
+        let mut parameter = OsString::from("WORKSPACE=");
+        parameter.push(workspace);
+        sandboxed
+            .arg("-D")
+            .arg(parameter)
+            .args(["-p", SEATBELT_PROFILE, "bash", "-c"])
+            .arg(command);
+        sandboxed
+    };
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = (command, workspace, timeout);
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "sandboxed hooks are only supported on Linux and macOS",
+        ));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        set_limits(&mut sandboxed, timeout);
+        Ok(sandboxed)
+    }
+}
+
+/// The error for a sandboxed command whose sandbox isn't installed.
+pub fn unavailable() -> io::Error {
+    let message = match cfg!(target_os = "macos") {
+        true => "sandboxed hooks run with sandbox-exec, which was not found",
+        false => "sandboxed hooks run with bubblewrap, install it so that bwrap is on the PATH",
+    };
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+/// The arguments of `bwrap`: only the [SYSTEM_DIRS] are bound, read-only, with the workspace and a
+/// new `/tmp`, and `home` is an empty directory. The command has no network and can't see other
+/// processes.
+#[cfg(not(target_os = "macos"))]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn bwrap_args(command: &str, workspace: &Path, home: Option<&Path>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+    for dir in SYSTEM_DIRS {
+        args.extend(["--ro-bind-try", dir, dir].into_iter().map(OsString::from));
+    }
+    args.extend(
+        ["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]
+            .into_iter()
+            .map(OsString::from),
+    );
+    if let Some(home) = home {
+        args.extend(["--tmpfs".into(), home.into()]);
+    }
+    // After /tmp and the home directory, so that a workspace in them is still bound.
+    args.extend(["--bind".into(), workspace.into(), workspace.into()]);
+    args.extend(
+        [
+            "--unshare-net",
+            "--unshare-pid",
+            "--die-with-parent",
+            "--new-session",
+            "--chdir",
+        ]
+        .into_iter()
+        .map(OsString::from),
+    );
+    args.push(workspace.into());
+    args.extend(["--", "bash", "-c", command].into_iter().map(OsString::from));
+    args
+}
+
+/// Limits the resources of the command once it's started.
+#[cfg(unix)]
+fn set_limits(command: &mut Command, timeout: Duration) {
+    let cpu_seconds = timeout.as_secs().saturating_add(1);
+    let limits = [
+        (libc::RLIMIT_CPU, cpu_seconds),
+        (libc::RLIMIT_AS, MEMORY_LIMIT),
+        (libc::RLIMIT_FSIZE, FILE_SIZE_LIMIT),
+        (libc::RLIMIT_NOFILE, OPEN_FILES_LIMIT),
+    ];
+    // SAFETY: getrlimit and setrlimit are async-signal-safe, and nothing is allocated between the
+    // fork and the exec.
+    unsafe {
+        command.pre_exec(move || {
+            for (resource, limit) in limits {
+                let mut current = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                if libc::getrlimit(resource, &mut current) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // Limits can only be lowered without privileges.
+                let limit = limit.min(current.rlim_max);
+                let limited = libc::rlimit {
+                    rlim_cur: limit,
+                    rlim_max: limit,
+                };
+                if libc::setrlimit(resource, &limited) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_bwrap_args() {
+        let args = bwrap_args(
+            "git status",
+            Path::new("/home/dev/workspace"),
+            Some(Path::new("/home/dev")),
+        );
+        let args: Vec<_> = args.iter().map(|arg| arg.to_str().unwrap()).collect();
+        assert!(args.contains(&"--unshare-net"));
+        assert!(args.ends_with(&["--", "bash", "-c", "git status"]));
+        // Only the system's directories are bound, not the whole filesystem.
+        assert!(!args.windows(2).any(|pair| pair == ["--ro-bind", "/"]));
+        assert!(args.windows(3).any(|args| args == ["--ro-bind-try", "/usr", "/usr"]));
+
+        // The home directory is hidden, but the workspace in it is writable.
+        let home = args
+            .windows(2)
+            .position(|pair| pair == ["--tmpfs", "/home/dev"])
+            .unwrap();
+        let bind = args.iter().position(|&arg| arg == "--bind").unwrap();
+        assert!(home < bind);
+        assert_eq!(args[bind + 1..bind + 3], ["/home/dev/workspace", "/home/dev/workspace"]);
+    }
+}
@@ -0,0 +1,283 @@
+//! Hooks discovered from the scripts in a workspace's `.amazonq/hooks/`, so that a team can keep
+//! its hooks in version control next to the code rather than in each profile's config.
+//!
+//! Each script is a hook named after its file, without the extension, and is run with the
+//! interpreter of its shebang, or else the one of its extension, e.g. `python3` for `.py`. The
+//! hook's settings are read from the comments at the top of the script, spelled as in a context
+//! config:
+//!
+//! ```text
+//! #!/usr/bin/env bash
+//! # trigger: conversation_start
+//! # timeout_ms: 5000
+//! git status --short
+//! ```
+//!
+//! Scripts without a `trigger` run with each prompt.
+//!
+//! Since a workspace may come from anyone, e.g. a cloned repository, its scripts don't run until
+//! they're trusted with `/hooks trust`, which keeps the digest of their contents: a script that
+//! changed since is disabled again until it's trusted again. Scripts also always run sandboxed when
+//! the `chat.sandboxHooks` setting is on, whatever their `sandbox` comment.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    eyre,
+};
+use serde_json::Value;
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::warn;
+
+use crate::cli::chat::cli::hooks::Hook;
+use crate::os::Os;
+
+/// The directory of the scripts, relative to the workspace.
+pub const HOOK_SCRIPTS_DIR: &str = ".amazonq/hooks";
+
+/// Interpreters of scripts without a shebang, by extension.
+const EXTENSION_INTERPRETERS: &[(&str, &str)] = &[
+    ("sh", "bash"),
+    ("bash", "bash"),
+    ("zsh", "zsh"),
+    ("py", "python3"),
+    ("js", "node"),
+    ("mjs", "node"),
+    ("rb", "ruby"),
+    ("pl", "perl"),
+    ("ps1", "pwsh"),
+];
+
+/// How the comments holding the settings start, e.g. `//` for JavaScript.
+const COMMENT_PREFIXES: &[&str] = &["#", "//", "--"];
+
+/// The script a hook was discovered from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookScript {
+    pub path: PathBuf,
+    /// The sha256 digest of the script's contents when it was discovered.
+    pub digest: String,
+    /// Whether the script was trusted with these contents. The hooks of other scripts are disabled.
+    pub trusted: bool,
+}
+
+impl HookScript {
+    /// The key of the script among the trusted scripts.
+    fn key(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+}
+
+/// The hooks of the scripts in `dir`, by name. Files that aren't scripts, having neither a shebang
+/// nor a known extension, are skipped, as are scripts with invalid settings. The hooks of scripts
+/// that aren't trusted, or changed since they were, are disabled.
+pub async fn discover(os: &Os, dir: &Path) -> Result<HashMap<String, Hook>> {
+    let mut hooks = HashMap::new();
+    if !os.fs.exists(dir) {
+        return Ok(hooks);
+    }
+    let trusted = os.database.get_trusted_hook_scripts()?;
+
+    let mut paths = Vec::new();
+    let mut read_dir = os.fs.read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let file_name = entry.file_name();
+        if entry.file_type().await?.is_file() && !file_name.to_string_lossy().starts_with('.') {
+            paths.push(dir.join(file_name));
+        }
+    }
+    // Of scripts with the same name, e.g. `status.sh` and `status.py`, the first one is used.
+    paths.sort();
+
+    for path in paths {
+        let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+            continue;
+        };
+        if hooks.contains_key(&name) {
+            warn!(?path, "skipping hook script with the name of another script");
+            continue;
+        }
+        // Files that aren't text, e.g. compiled binaries, aren't scripts.
+        let Ok(contents) = os.fs.read_to_string(&path).await else {
+            continue;
+        };
+        match parse_script(&contents, &path, &os.fs.chroot_path_str(&path)) {
+            Ok(Some(mut hook)) => {
+                let script = HookScript {
+                    path,
+                    digest: hex::encode(Sha256::digest(contents.as_bytes())),
+                    trusted: false,
+                };
+                let trusted = trusted.get(&script.key()) == Some(&script.digest);
+                hook.disabled |= !trusted;
+                hook.script = Some(HookScript { trusted, ..script });
+                hooks.insert(name, hook);
+            },
+            Ok(None) => (),
+            Err(err) => warn!(?path, %err, "skipping hook script with invalid settings"),
+        }
+    }
+    Ok(hooks)
+}
+
+/// The hook of the script at `path`, or `None` if it isn't a script. `command_path` is the path
+/// the hook's command runs it at.
+fn parse_script(contents: &str, path: &Path, command_path: &str) -> Result<Option<Hook>> {
+    let mut lines = contents.lines().peekable();
+    let interpreter = match lines.next_if(|line| line.starts_with("#!")) {
+        Some(shebang) => shebang[2..].trim().to_string(),
+        None => {
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            match EXTENSION_INTERPRETERS.iter().find(|(ext, _)| Some(*ext) == extension) {
+                Some(&(_, interpreter)) => interpreter.to_string(),
+                None => return Ok(None),
+            }
+        },
+    };
+
+    let mut settings = serde_json::Map::new();
+    for line in lines {
+        let line = line.trim();
+        let Some(comment) = COMMENT_PREFIXES.iter().find_map(|prefix| line.strip_prefix(prefix)) else {
+            // The settings end with the first line of code.
+            if line.is_empty() {
+                continue;
+            }
+            break;
+        };
+        let Some((key, value)) = comment.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let value = match key {
+            "trigger" | "when" | "filter" => Value::String(value.to_string()),
+            "timeout_ms" | "max_output_size" | "cache_ttl_seconds" => {
+                Value::from(value.parse::<u64>().map_err(|e| eyre!("{key} must be a number: {e}"))?)
+            },
+            "watch" => value
+                .split(',')
+                .map(|glob| Value::String(glob.trim().to_string()))
+                .collect(),
+            "disabled" | "sandbox" => {
+                Value::Bool(value.parse().map_err(|e| eyre!("{key} must be true or false: {e}"))?)
+            },
+            // Any other comment.
+            _ => continue,
+        };
+        settings.entry(key).or_insert(value);
+    }
+
+    settings.entry("trigger").or_insert("per_prompt".into());
+    settings.insert("type".to_string(), "inline".into());
+    let command_path = shlex::try_quote(command_path).map_err(|e| eyre!("unsupported path: {e}"))?;
+    settings.insert("command".to_string(), format!("{interpreter} {command_path}").into());
+
+    Ok(Some(serde_json::from_value(Value::Object(settings))?))
+}
+
+/// Trusts the scripts of `hooks` with the contents they were discovered with, so that they're
+/// enabled when they're discovered again, unless they changed in the meantime.
+pub fn trust<'a>(os: &Os, hooks: impl IntoIterator<Item = &'a Hook>) -> Result<()> {
+    let mut trusted = os.database.get_trusted_hook_scripts()?;
+    for script in hooks.into_iter().filter_map(|hook| hook.script.as_ref()) {
+        trusted.insert(script.key(), script.digest.clone());
+    }
+    os.database.set_trusted_hook_scripts(&trusted)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::cli::hooks::HookTrigger;
+
+    #[test]
+    fn test_parse_script() {
+        let script = "#!/usr/bin/env python3\n# Shows the status.\n# trigger: conversation_start\n# timeout_ms: 5000\n\nprint('clean')\n# trigger: on_error\n";
+        let hook = parse_script(
+            script,
+            Path::new("/ws/.amazonq/hooks/status"),
+            "/ws/.amazonq/hooks/status",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(hook.trigger, HookTrigger::ConversationStart);
+        assert_eq!(hook.timeout_ms, 5000);
+        assert_eq!(hook.command_line(), "/usr/bin/env python3 /ws/.amazonq/hooks/status");
+
+        // Without a shebang, the extension picks the interpreter.
+        let hook = parse_script("// when: src/**\necho", Path::new("lint.js"), "/my hooks/lint.js")
+            .unwrap()
+            .unwrap();
+        assert_eq!(hook.trigger, HookTrigger::PerPrompt);
+        assert_eq!(hook.when.as_deref(), Some("src/**"));
+        assert_eq!(hook.command_line(), "node '/my hooks/lint.js'");
+
+        assert!(
+            parse_script("notes", Path::new("README.md"), "README.md")
+                .unwrap()
+                .is_none()
+        );
+        assert!(parse_script("# trigger: never\n", Path::new("bad.sh"), "bad.sh").is_err());
+        assert!(parse_script("# timeout_ms: soon\n", Path::new("bad.sh"), "bad.sh").is_err());
+    }
+
+    #[test]
+    fn test_runs_sandboxed() {
+        let mut hook = parse_script("# sandbox: false\n", Path::new("net.sh"), "net.sh")
+            .unwrap()
+            .unwrap();
+        assert!(!hook.runs_sandboxed(false));
+        hook.script = Some(HookScript {
+            path: PathBuf::from("net.sh"),
+            digest: String::new(),
+            trusted: true,
+        });
+        // A script can't turn off the sandbox of every hook.
+        assert!(hook.runs_sandboxed(true));
+        hook.sandbox = Some(true);
+        assert!(hook.runs_sandboxed(false));
+    }
+
+    #[tokio::test]
+    async fn test_discover() {
+        let os = Os::new().await.unwrap();
+        let dir = Path::new("/workspace").join(HOOK_SCRIPTS_DIR);
+        os.fs.create_dir_all(&dir).await.unwrap();
+        os.fs.write(dir.join("status.sh"), "git status").await.unwrap();
+        os.fs.write(dir.join("status.py"), "print()").await.unwrap();
+        os.fs.write(dir.join("README.md"), "Hooks of the team").await.unwrap();
+        os.fs.write(dir.join(".hidden.sh"), "echo").await.unwrap();
+
+        let hooks = discover(&os, &dir).await.unwrap();
+        assert_eq!(hooks.len(), 1);
+        let status = &hooks["status"];
+        assert!(status.command_line().starts_with("python3 "));
+        let script = status.script.as_ref().unwrap();
+        assert_eq!(script.path, dir.join("status.py"));
+        // Scripts don't run until they're trusted.
+        assert!(!script.trusted);
+        assert!(status.disabled);
+
+        trust(&os, hooks.values()).unwrap();
+        let hooks = discover(&os, &dir).await.unwrap();
+        assert!(hooks["status"].script.as_ref().unwrap().trusted);
+        assert!(!hooks["status"].disabled);
+
+        // Changing a script takes its trust back.
+        os.fs.write(dir.join("status.py"), "import os").await.unwrap();
+        let hooks = discover(&os, &dir).await.unwrap();
+        assert!(!hooks["status"].script.as_ref().unwrap().trusted);
+        assert!(hooks["status"].disabled);
+
+        assert!(discover(&os, Path::new("/elsewhere")).await.unwrap().is_empty());
+    }
+}
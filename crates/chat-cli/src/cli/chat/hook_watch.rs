@@ -0,0 +1,248 @@
+//! Refreshes the output of `file_change` hooks in the background. Such a hook runs with the first
+//! prompt like a `per_prompt` hook, then its files are watched and its command runs again as soon
+//! as a file matching its `watch` globs is changed, created or removed. The next prompts get its
+//! latest output without waiting for the command.
+//!
+//! Refreshes run without the variables describing a prompt, such as `Q_USER_PROMPT`, since no
+//! prompt is being sent. While one is running, or after it failed, the hook runs with the prompt
+//! instead, so that a prompt never gets the output of files that have changed since.
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::ffi::OsStr;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+use std::sync::Arc;
+
+use eyre::Result;
+use glob::Pattern;
+use notify::{
+    Event,
+    RecommendedWatcher,
+    RecursiveMode,
+    Watcher,
+};
+use parking_lot::Mutex;
+use tokio::runtime::Handle;
+use tracing::warn;
+
+use crate::cli::chat::cli::hooks::{
+    Hook,
+    command_output,
+    run_hook_command,
+};
+use crate::cli::chat::context_watch::watch_root;
+
+/// Keyed by whether the hook is global, and its name.
+type WatchedHooks = Arc<Mutex<HashMap<(bool, String), WatchedHook>>>;
+
+#[derive(Debug)]
+struct WatchedHook {
+    hook: Hook,
+    /// The hook as configured when it started being watched, to tell when it was changed since.
+    definition: String,
+    /// The hook's `watch` globs, relative to the workspace they were watched in.
+    patterns: Vec<Pattern>,
+    sandbox: bool,
+    /// The output of the last run, `None` if it failed.
+    output: Option<String>,
+    refreshing: bool,
+    /// Whether a file changed while the command was running, so it needs to run again.
+    changed: bool,
+}
+
+impl WatchedHook {
+    fn is_affected_by(&self, path: &Path) -> bool {
+        // Git writes to its own directory for commands such as `git status`, a hook watching the
+        // workspace would otherwise refresh itself again and again.
+        let in_git_dir = path
+            .components()
+            .any(|component| component == Component::Normal(OsStr::new(".git")));
+        !in_git_dir && self.patterns.iter().any(|pattern| pattern.matches_path(path))
+    }
+}
+
+pub struct HookWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    watched: Mutex<HashSet<PathBuf>>,
+    hooks: WatchedHooks,
+}
+
+impl std::fmt::Debug for HookWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HookWatcher")
+            .field("watched", &self.watched)
+            .field("hooks", &self.hooks)
+            .finish()
+    }
+}
+
+impl HookWatcher {
+    /// A watcher refreshing hooks on the current tokio runtime.
+    pub fn new() -> Result<Self> {
+        let runtime = Handle::try_current()?;
+        let hooks: WatchedHooks = Arc::default();
+        let handler_hooks = Arc::clone(&hooks);
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let paths = match event {
+                Ok(event) if !event.kind.is_access() => event.paths,
+                Ok(_) => return,
+                Err(err) => {
+                    warn!(?err, "hook watcher error");
+                    return;
+                },
+            };
+            let mut watched_hooks = handler_hooks.lock();
+            for (key, watched) in watched_hooks.iter_mut() {
+                if !paths.iter().any(|path| watched.is_affected_by(path)) {
+                    continue;
+                }
+                if watched.refreshing {
+                    watched.changed = true;
+                    continue;
+                }
+                watched.refreshing = true;
+                runtime.spawn(refresh(Arc::clone(&handler_hooks), key.clone()));
+            }
+        })?;
+
+        Ok(Self {
+            watcher: Mutex::new(watcher),
+            watched: Mutex::default(),
+            hooks,
+        })
+    }
+
+    /// The latest output of `hook`, if it's watched as it's configured now and its files haven't
+    /// changed since the command last succeeded.
+    pub fn output(&self, hook: &Hook) -> Option<String> {
+        let definition = definition(hook);
+        self.hooks
+            .lock()
+            .get(&(hook.is_global, hook.name.clone()))
+            .filter(|watched| watched.definition == definition && !watched.refreshing)
+            .and_then(|watched| watched.output.clone())
+    }
+
+    /// Keeps `output` as the latest output of `hook`, and starts watching the files matching its
+    /// globs, relative to the current directory, to run its command again when they change.
+    pub fn watch(&self, hook: &Hook, sandbox: bool, output: String) {
+        let workspace = std::env::current_dir().unwrap_or_default();
+        let globs: Vec<PathBuf> = hook.watch.iter().map(|glob| workspace.join(glob)).collect();
+        let patterns: Vec<Pattern> = globs
+            .iter()
+            .filter_map(|glob| Pattern::new(&glob.to_string_lossy()).ok())
+            .collect();
+        if patterns.is_empty() {
+            return;
+        }
+
+        for dir in globs.iter().map(|glob| watch_root(glob)) {
+            if self.watched.lock().contains(&dir) {
+                continue;
+            }
+            if let Err(err) = self.watcher.lock().watch(&dir, RecursiveMode::Recursive) {
+                warn!(
+                    ?err,
+                    ?dir,
+                    "failed to watch the files of a hook, running it with each prompt"
+                );
+                return;
+            }
+            self.watched.lock().insert(dir);
+        }
+
+        let definition = definition(hook);
+        let mut hooks = self.hooks.lock();
+        let key = (hook.is_global, hook.name.clone());
+        // A refresh already running for the same hook keeps running.
+        if let Some(watched) = hooks.get_mut(&key).filter(|watched| watched.definition == definition) {
+            watched.output = Some(output);
+            return;
+        }
+        hooks.insert(key, WatchedHook {
+            hook: hook.clone(),
+            definition,
+            patterns,
+            sandbox,
+            output: Some(output),
+            refreshing: false,
+            changed: false,
+        });
+    }
+
+    /// Stops refreshing the hooks that aren't among `hooks` as they're configured now, e.g. after
+    /// they were removed, disabled or changed.
+    pub fn retain(&self, hooks: &[&Hook]) {
+        let current: HashMap<(bool, &str), String> = hooks
+            .iter()
+            .filter(|hook| !hook.disabled)
+            .map(|hook| ((hook.is_global, hook.name.as_str()), definition(hook)))
+            .collect();
+        self.hooks.lock().retain(|(is_global, name), watched| {
+            current.get(&(*is_global, name.as_str())) == Some(&watched.definition)
+        });
+    }
+}
+
+/// Runs the command of a watched hook again, for as long as its files keep changing while it runs.
+async fn refresh(hooks: WatchedHooks, key: (bool, String)) {
+    loop {
+        let Some((hook, definition, sandbox)) = hooks
+            .lock()
+            .get(&key)
+            .map(|watched| (watched.hook.clone(), watched.definition.clone(), watched.sandbox))
+        else {
+            return;
+        };
+        let output = match run_hook_command(&hook, &[], sandbox).await {
+            Ok(output) => command_output(&hook, &output).ok(),
+            Err(err) => {
+                warn!(%err, hook = hook.name, "failed to refresh a file_change hook");
+                None
+            },
+        };
+
+        let mut hooks = hooks.lock();
+        let Some(watched) = hooks.get_mut(&key).filter(|watched| watched.definition == definition) else {
+            return;
+        };
+        watched.output = output;
+        if !std::mem::take(&mut watched.changed) {
+            watched.refreshing = false;
+            return;
+        }
+    }
+}
+
+/// The configuration of `hook`, compared to tell whether it changed.
+fn definition(hook: &Hook) -> String {
+    serde_json::to_string(hook).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::cli::hooks::HookTrigger;
+
+    #[test]
+    fn test_is_affected_by() {
+        let watched = WatchedHook {
+            hook: Hook::new_inline_hook(HookTrigger::FileChange, "git status".to_string()),
+            definition: String::new(),
+            patterns: vec![Pattern::new("/ws/**/*.rs").unwrap()],
+            sandbox: false,
+            output: None,
+            refreshing: false,
+            changed: false,
+        };
+        assert!(watched.is_affected_by(Path::new("/ws/src/main.rs")));
+        assert!(!watched.is_affected_by(Path::new("/ws/README.md")));
+        assert!(!watched.is_affected_by(Path::new("/ws/.git/hooks/pre-commit.rs")));
+    }
+}
@@ -0,0 +1,318 @@
+//! Conversations exported from other assistants, loaded with `/load --format openai|anthropic`.
+//!
+//! Both the account exports (ChatGPT's and Claude's `conversations.json`) and the `messages` arrays
+//! of their APIs are understood. Only text is imported: attachments, tool calls and system messages
+//! are dropped, and consecutive messages from the same role are joined into one.
+
+use std::collections::VecDeque;
+
+use clap::ValueEnum;
+use eyre::{
+    Result,
+    bail,
+};
+use serde_json::Value;
+
+use crate::cli::chat::message::{
+    AssistantMessage,
+    UserMessage,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// A ChatGPT export or an OpenAI chat completions `messages` array
+    Openai,
+    /// A Claude export or an Anthropic messages API `messages` array
+    Anthropic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    User,
+    Assistant,
+}
+
+/// The title of a conversation and its messages in order.
+type ParsedMessages = (Option<String>, Vec<(Role, String)>);
+
+/// A conversation read from another assistant's export.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportedConversation {
+    pub title: Option<String>,
+    /// Exchanges of a user prompt and the response to it.
+    pub turns: Vec<(String, String)>,
+    /// A final prompt that was never answered, which isn't imported.
+    pub unanswered: Option<String>,
+}
+
+impl ImportedConversation {
+    /// Parses an export. When a file holds several conversations, the most recently updated one is
+    /// imported.
+    pub fn parse(format: ImportFormat, contents: &[u8]) -> Result<Self> {
+        let value = serde_json::from_slice::<Value>(contents)?;
+        let (title, messages) = match format {
+            ImportFormat::Openai => parse_openai(&value)?,
+            ImportFormat::Anthropic => parse_anthropic(&value)?,
+        };
+        Ok(Self::from_messages(title, messages))
+    }
+
+    fn from_messages(title: Option<String>, messages: Vec<(Role, String)>) -> Self {
+        // Join consecutive messages from the same role.
+        let mut merged: Vec<(Role, String)> = Vec::new();
+        for (role, text) in messages {
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            match merged.last_mut() {
+                Some((last_role, last_text)) if *last_role == role => {
+                    last_text.push_str("\n\n");
+                    last_text.push_str(text);
+                },
+                _ => merged.push((role, text.to_string())),
+            }
+        }
+
+        let mut conversation = Self {
+            title,
+            ..Default::default()
+        };
+        let mut prompt = None;
+        for (role, text) in merged {
+            match (role, prompt.take()) {
+                (Role::User, _) => prompt = Some(text),
+                (Role::Assistant, Some(prompt)) => conversation.turns.push((prompt, text)),
+                // A response without a prompt, e.g. a greeting, has nothing to pair with.
+                (Role::Assistant, None) => (),
+            }
+        }
+        conversation.unanswered = prompt;
+        conversation
+    }
+
+    pub fn history(&self) -> VecDeque<(UserMessage, AssistantMessage)> {
+        self.turns
+            .iter()
+            .map(|(prompt, response)| {
+                (
+                    UserMessage::new_prompt(prompt.clone()),
+                    AssistantMessage::new_response(None, response.clone()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Picks the most recently updated conversation if `value` is an array of them.
+fn latest_conversation<'a>(value: &'a Value, updated_at: &str) -> Result<&'a Value> {
+    match value {
+        Value::Array(conversations) => {
+            // Timestamps are either RFC 3339 strings, which sort as text, or numbers.
+            let key = |conversation: &Value| match &conversation[updated_at] {
+                Value::String(s) => (0.0, s.clone()),
+                other => (other.as_f64().unwrap_or_default(), String::new()),
+            };
+            match conversations
+                .iter()
+                .max_by(|a, b| key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal))
+            {
+                Some(conversation) => Ok(conversation),
+                None => bail!("The file doesn't contain any conversations"),
+            }
+        },
+        _ => Ok(value),
+    }
+}
+
+/// The text of an API message's `content`, which is either a string or a list of blocks.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block {
+                Value::String(text) => Some(text.as_str()),
+                _ if block["type"] == "text" => block["text"].as_str(),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Reads an API style `messages` array with `role` and `content` fields.
+fn parse_api_messages(messages: &[Value]) -> Vec<(Role, String)> {
+    messages
+        .iter()
+        .filter_map(|message| {
+            let role = match message["role"].as_str()? {
+                "user" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => return None,
+            };
+            Some((role, content_text(&message["content"])))
+        })
+        .collect()
+}
+
+fn parse_openai(value: &Value) -> Result<ParsedMessages> {
+    if let Some(messages) = value["messages"].as_array() {
+        return Ok((None, parse_api_messages(messages)));
+    }
+
+    let conversation = latest_conversation(value, "update_time")?;
+    let Some(mapping) = conversation["mapping"].as_object() else {
+        bail!("Expected a ChatGPT export with a 'mapping', or a 'messages' array");
+    };
+    // The mapping is a tree of edits and regenerations. Follow the branch that was last shown,
+    // from `current_node` back to the root. The length check stops at a cycle in a malformed file.
+    let mut node_id = conversation["current_node"].as_str();
+    let mut branch = Vec::new();
+    while let Some(node) = node_id
+        .and_then(|id| mapping.get(id))
+        .filter(|_| branch.len() < mapping.len())
+    {
+        branch.push(node);
+        node_id = node["parent"].as_str();
+    }
+    branch.reverse();
+
+    let messages = branch
+        .into_iter()
+        .filter_map(|node| {
+            let message = &node["message"];
+            let role = match message["author"]["role"].as_str()? {
+                "user" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => return None,
+            };
+            if message["content"]["content_type"] != "text" {
+                return None;
+            }
+            Some((role, content_text(&message["content"]["parts"])))
+        })
+        .collect();
+    Ok((conversation["title"].as_str().map(str::to_string), messages))
+}
+
+fn parse_anthropic(value: &Value) -> Result<ParsedMessages> {
+    if let Some(messages) = value["messages"].as_array() {
+        return Ok((None, parse_api_messages(messages)));
+    }
+
+    let conversation = latest_conversation(value, "updated_at")?;
+    let Some(chat_messages) = conversation["chat_messages"].as_array() else {
+        bail!("Expected a Claude export with 'chat_messages', or a 'messages' array");
+    };
+    let messages = chat_messages
+        .iter()
+        .filter_map(|message| {
+            let role = match message["sender"].as_str()? {
+                "human" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => return None,
+            };
+            let text = match message["content"].as_array() {
+                Some(_) => content_text(&message["content"]),
+                None => message["text"].as_str().unwrap_or_default().to_string(),
+            };
+            Some((role, text))
+        })
+        .collect();
+    Ok((conversation["name"].as_str().map(str::to_string), messages))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(format: ImportFormat, value: Value) -> ImportedConversation {
+        ImportedConversation::parse(format, value.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_openai() {
+        let node = |id: &str, parent: Option<&str>, role: &str, text: &str| {
+            (
+                id.to_string(),
+                json!({
+                    "id": id,
+                    "parent": parent,
+                    "message": {
+                        "author": { "role": role },
+                        "content": { "content_type": "text", "parts": [text] },
+                    },
+                }),
+            )
+        };
+        let export = json!([
+            {
+                "title": "Old",
+                "update_time": 1.0,
+                "current_node": null,
+                "mapping": {},
+            },
+            {
+                "title": "Rust lifetimes",
+                "update_time": 2.0,
+                "current_node": "a2",
+                "mapping": serde_json::Map::from_iter([
+                    node("root", None, "system", ""),
+                    node("u1", Some("root"), "user", "What is 'a?"),
+                    node("a1-old", Some("u1"), "assistant", "A regenerated answer"),
+                    node("a1", Some("u1"), "assistant", "A lifetime."),
+                    node("u2", Some("a1"), "user", "Thanks"),
+                    node("a2", Some("u2"), "assistant", "You're welcome."),
+                ]),
+            },
+        ]);
+        let imported = parse(ImportFormat::Openai, export);
+        assert_eq!(imported.title.as_deref(), Some("Rust lifetimes"));
+        assert_eq!(imported.turns, vec![
+            ("What is 'a?".to_string(), "A lifetime.".to_string()),
+            ("Thanks".to_string(), "You're welcome.".to_string()),
+        ]);
+        assert_eq!(imported.history().len(), 2);
+
+        let api = json!({ "messages": [
+            { "role": "system", "content": "Be brief" },
+            { "role": "user", "content": "Hi" },
+            { "role": "user", "content": [{ "type": "text", "text": "Are you there?" }] },
+            { "role": "assistant", "content": "Yes" },
+            { "role": "user", "content": "Bye" },
+        ]});
+        let imported = parse(ImportFormat::Openai, api);
+        assert_eq!(imported.turns, vec![(
+            "Hi\n\nAre you there?".to_string(),
+            "Yes".to_string()
+        )]);
+        assert_eq!(imported.unanswered.as_deref(), Some("Bye"));
+    }
+
+    #[test]
+    fn test_parse_anthropic() {
+        let export = json!([{
+            "name": "Sourdough",
+            "updated_at": "2025-01-02T00:00:00Z",
+            "chat_messages": [
+                { "sender": "human", "text": "How long to proof?", "content": [
+                    { "type": "text", "text": "How long to proof?" },
+                ]},
+                { "sender": "assistant", "text": "Overnight." },
+            ],
+        }]);
+        let imported = parse(ImportFormat::Anthropic, export);
+        assert_eq!(imported.title.as_deref(), Some("Sourdough"));
+        assert_eq!(imported.turns, vec![(
+            "How long to proof?".to_string(),
+            "Overnight.".to_string()
+        )]);
+
+        assert!(ImportedConversation::parse(ImportFormat::Anthropic, b"{}").is_err());
+        assert!(ImportedConversation::parse(ImportFormat::Anthropic, b"[]").is_err());
+    }
+}
@@ -1,13 +1,15 @@
 use eyre::Result;
 use rustyline::error::ReadlineError;
 
+use super::completion_cache::CompletionCache;
 use super::prompt::rl;
 #[cfg(unix)]
 use super::skim_integration::SkimCommandSelector;
 use crate::os::Os;
 
 #[derive(Debug)]
-pub struct InputSource(inner::Inner);
+/// Reads prompts from the terminal, along with text to start the next prompt with, if any.
+pub struct InputSource(inner::Inner, Option<String>);
 
 mod inner {
     use rustyline::Editor;
@@ -33,7 +35,7 @@ impl InputSource {
         sender: std::sync::mpsc::Sender<Option<String>>,
         receiver: std::sync::mpsc::Receiver<Vec<String>>,
     ) -> Result<Self> {
-        Ok(Self(inner::Inner::Readline(rl(os, sender, receiver)?)))
+        Ok(Self(inner::Inner::Readline(rl(os, sender, receiver)?), None))
     }
 
     #[cfg(unix)]
@@ -66,16 +68,44 @@ impl InputSource {
         }
     }
 
+    /// Replaces the session values offered as completions.
+    pub fn set_completion_cache(&mut self, cache: CompletionCache) {
+        if let inner::Inner::Readline(rl) = &mut self.0 {
+            if let Some(helper) = rl.helper_mut() {
+                helper.set_completion_cache(cache);
+            }
+        }
+    }
+
+    /// Returns how many times completions were offered and picked since the last call.
+    pub fn take_completion_counts(&mut self) -> (u64, u64) {
+        if let inner::Inner::Readline(rl) = &mut self.0 {
+            if let Some(helper) = rl.helper_mut() {
+                return helper.take_completion_counts();
+            }
+        }
+        (0, 0)
+    }
+
+    /// Starts the next prompt with `text` in the buffer, to be reviewed before it is sent.
+    #[cfg_attr(not(feature = "voice"), allow(dead_code))]
+    pub fn set_initial_text(&mut self, text: String) {
+        self.1 = Some(text);
+    }
+
     #[allow(dead_code)]
     pub fn new_mock(lines: Vec<String>) -> Self {
-        Self(inner::Inner::Mock { index: 0, lines })
+        Self(inner::Inner::Mock { index: 0, lines }, None)
     }
 
     pub fn read_line(&mut self, prompt: Option<&str>) -> Result<Option<String>, ReadlineError> {
         match &mut self.0 {
             inner::Inner::Readline(rl) => {
                 let prompt = prompt.unwrap_or_default();
-                let curr_line = rl.readline(prompt);
+                let curr_line = match self.1.take() {
+                    Some(initial) => rl.readline_with_initial(prompt, (&initial, "")),
+                    None => rl.readline(prompt),
+                };
                 match curr_line {
                     Ok(line) => {
                         let _ = rl.add_history_entry(line.as_str());
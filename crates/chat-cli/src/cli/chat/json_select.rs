@@ -0,0 +1,209 @@
+//! The jq-style selectors of hook output filters, e.g. `.items[].metadata.name`, picking values out
+//! of a command's JSON output so that only what matters is added to the context.
+//!
+//! A selector is a path of keys (`.name`, or `."some key"`), indexes (`[0]`, or `[-1]` counting
+//! from the end) and iterations over the values of an array or object (`[]`), starting with `.`
+//! for the whole output. Missing keys and indexes select `null`, like with `jq`. Strings are
+//! written without quotes and other values as JSON, one value per line, like with `jq -r`.
+
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Iterate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSelector {
+    steps: Vec<Step>,
+}
+
+impl JsonSelector {
+    /// Whether a hook's filter is a selector rather than a command, i.e. it starts with `.` but
+    /// isn't a relative path such as `./trim.sh`.
+    pub fn is_selector(filter: &str) -> bool {
+        filter.starts_with('.') && !filter.starts_with("./") && !filter.starts_with("..")
+    }
+
+    pub fn parse(selector: &str) -> Result<Self> {
+        let mut chars = selector.trim().chars().peekable();
+        if chars.next() != Some('.') {
+            bail!("a selector starts with '.'");
+        }
+        let mut steps = Vec::new();
+        // Whether the next key follows a '.', which the first one does.
+        let mut after_dot = true;
+        while let Some(&next) = chars.peek() {
+            match next {
+                '.' if !after_dot => {
+                    chars.next();
+                    after_dot = true;
+                },
+                '[' => {
+                    chars.next();
+                    let mut inner = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            closed = true;
+                            break;
+                        }
+                        inner.push(c);
+                    }
+                    if !closed {
+                        bail!("unclosed '[' in selector");
+                    }
+                    let inner = inner.trim();
+                    steps.push(match inner {
+                        "" => Step::Iterate,
+                        quoted if quoted.starts_with('"') => Step::Key(serde_json::from_str(quoted)?),
+                        index => Step::Index(index.parse().map_err(|_err| eyre!("invalid index '{index}'"))?),
+                    });
+                    after_dot = false;
+                },
+                '"' if after_dot => {
+                    let mut quoted = String::from(chars.next().unwrap_or('"'));
+                    let mut escaped = false;
+                    for c in chars.by_ref() {
+                        quoted.push(c);
+                        match c {
+                            '"' if !escaped => break,
+                            '\\' => escaped = !escaped,
+                            _ => escaped = false,
+                        }
+                    }
+                    steps.push(Step::Key(serde_json::from_str(&quoted)?));
+                    after_dot = false;
+                },
+                c if after_dot && (c.is_alphanumeric() || c == '_') => {
+                    let mut key = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                        key.push(c);
+                    }
+                    steps.push(Step::Key(key));
+                    after_dot = false;
+                },
+                c => bail!("unexpected '{c}' in selector"),
+            }
+        }
+        if after_dot && !steps.is_empty() {
+            bail!("a selector can't end with '.'");
+        }
+        Ok(Self { steps })
+    }
+
+    /// The values of `input`, a JSON document, selected by the selector, one per line.
+    pub fn apply(&self, input: &str) -> Result<String> {
+        let document: Value = serde_json::from_str(input).map_err(|e| eyre!("output is not JSON: {e}"))?;
+        let mut values = vec![document];
+        for step in &self.steps {
+            let mut selected = Vec::with_capacity(values.len());
+            for value in values {
+                match (step, value) {
+                    (Step::Key(key), Value::Object(mut object)) => {
+                        selected.push(object.remove(key).unwrap_or(Value::Null));
+                    },
+                    (Step::Index(index), Value::Array(mut array)) => {
+                        let index = match *index {
+                            index if index < 0 => array.len().checked_sub(index.unsigned_abs() as usize),
+                            index => Some(index as usize),
+                        };
+                        let value = index
+                            .filter(|index| *index < array.len())
+                            .map(|index| array.swap_remove(index));
+                        selected.push(value.unwrap_or(Value::Null));
+                    },
+                    (Step::Iterate, Value::Array(array)) => selected.extend(array),
+                    (Step::Iterate, Value::Object(object)) => {
+                        selected.extend(object.into_iter().map(|(_, value)| value));
+                    },
+                    (Step::Key(_) | Step::Index(_), Value::Null) => selected.push(Value::Null),
+                    (step, value) => bail!("cannot select {} of {}", step.describe(), type_name(&value)),
+                }
+            }
+            values = selected;
+        }
+
+        let lines: Vec<String> = values
+            .into_iter()
+            .map(|value| match value {
+                Value::String(string) => Ok(string),
+                value => serde_json::to_string_pretty(&value),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(lines.join("\n"))
+    }
+}
+
+impl Step {
+    fn describe(&self) -> String {
+        match self {
+            Self::Key(key) => format!("key \"{key}\""),
+            Self::Index(index) => format!("index {index}"),
+            Self::Iterate => "the values".to_string(),
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(JsonSelector::parse(".").unwrap().steps, vec![]);
+        assert_eq!(
+            JsonSelector::parse(r#".items[].metadata."app name"[-1]"#)
+                .unwrap()
+                .steps,
+            vec![
+                Step::Key("items".to_string()),
+                Step::Iterate,
+                Step::Key("metadata".to_string()),
+                Step::Key("app name".to_string()),
+                Step::Index(-1),
+            ]
+        );
+        assert!(JsonSelector::parse("items").is_err());
+        assert!(JsonSelector::parse(".items.").is_err());
+        assert!(JsonSelector::parse(".items[x]").is_err());
+        assert!(JsonSelector::parse(".items[0").is_err());
+
+        assert!(JsonSelector::is_selector(".items[0]"));
+        assert!(!JsonSelector::is_selector("./trim.sh"));
+        assert!(!JsonSelector::is_selector("head -n 50"));
+    }
+
+    #[test]
+    fn test_apply() {
+        let input = r#"{"items": [{"name": "api", "replicas": 2}, {"name": "web", "replicas": 1}], "total": 2}"#;
+        let select = |selector: &str| JsonSelector::parse(selector).unwrap().apply(input);
+        assert_eq!(select(".items[].name").unwrap(), "api\nweb");
+        assert_eq!(select(".items[-1].replicas").unwrap(), "1");
+        assert_eq!(select(".items[5].name").unwrap(), "null");
+        assert_eq!(
+            select(".items[0]").unwrap(),
+            "{\n  \"name\": \"api\",\n  \"replicas\": 2\n}"
+        );
+        assert!(select(".total[0]").is_err());
+        assert!(JsonSelector::parse(".").unwrap().apply("not json").is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Commands, flags and hook settings that were removed or renamed, and the rewriting of the
+//! scripts and configs that still use them, run by `/migrate`.
+//!
+//! Removed commands and flags are found in any text, such as a script running `q chat` or the
+//! command of a hook. Hook settings spelled in camelCase, e.g. `timeoutMs`, are found in context
+//! configs, where they were silently ignored in favor of the defaults.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    WrapErr,
+};
+use serde_json::Value;
+
+use crate::cli::chat::context::profile_context_path;
+use crate::os::Os;
+use crate::util::directories;
+
+/// A removed command or flag and what replaced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replacement {
+    pub legacy: &'static str,
+    pub replacement: &'static str,
+    pub explanation: &'static str,
+}
+
+pub const REPLACEMENTS: &[Replacement] = &[
+    Replacement {
+        legacy: "/acceptall",
+        replacement: "/tools trust-all",
+        explanation: "Trusting all tools moved to /tools.",
+    },
+    Replacement {
+        legacy: "--accept-all",
+        replacement: "--trust-all-tools",
+        explanation: "The flag of q chat to trust all tools was renamed.",
+    },
+    Replacement {
+        legacy: "/tools reset-single",
+        replacement: "/tools reset",
+        explanation: "/tools reset resets a single tool when given its name.",
+    },
+    Replacement {
+        legacy: "/context hooks",
+        replacement: "/hooks",
+        explanation: "Hooks are managed with /hooks.",
+    },
+];
+
+/// Hook settings in the spelling they're ignored in, and the one they're read in.
+const HOOK_KEYS: &[(&str, &str)] = &[
+    ("timeoutMs", "timeout_ms"),
+    ("maxOutputSize", "max_output_size"),
+    ("cacheTtlSeconds", "cache_ttl_seconds"),
+];
+
+/// A use of something removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// The hook it's in, for configs.
+    pub hook: Option<String>,
+    pub legacy: String,
+    pub replacement: String,
+    pub explanation: String,
+}
+
+/// The findings of a file and its contents with them rewritten.
+#[derive(Debug, Clone)]
+pub struct FileMigration {
+    pub path: PathBuf,
+    pub findings: Vec<Finding>,
+    pub migrated: String,
+}
+
+/// The removed command `input` starts with, for input that failed to parse as a command.
+pub fn legacy_command(input: &str) -> Option<&'static Replacement> {
+    REPLACEMENTS.iter().find(|replacement| {
+        replacement.legacy.starts_with('/') && input.strip_prefix(replacement.legacy).is_some_and(ends_word)
+    })
+}
+
+/// Whether `rest`, what follows a match, makes it a whole command or flag rather than the start of
+/// a longer one.
+fn ends_word(rest: &str) -> bool {
+    !rest.starts_with(|c: char| c.is_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// `text` with the [REPLACEMENTS] applied, and what was replaced.
+pub fn migrate_text(text: &str) -> (String, Vec<Finding>) {
+    let mut migrated = text.to_string();
+    let mut findings = Vec::new();
+    for replacement in REPLACEMENTS {
+        let mut from = 0;
+        let mut found = false;
+        while let Some(at) = migrated[from..].find(replacement.legacy).map(|at| at + from) {
+            let end = at + replacement.legacy.len();
+            // Matches within a longer word, e.g. docs/acceptall.md or --accept-all-files, are kept.
+            let starts_word = migrated[..at]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !c.is_alphanumeric() && c != '-' && c != '/');
+            if starts_word && ends_word(&migrated[end..]) {
+                migrated.replace_range(at..end, replacement.replacement);
+                from = at + replacement.replacement.len();
+                found = true;
+            } else {
+                from = end;
+            }
+        }
+        if found {
+            findings.push(Finding {
+                hook: None,
+                legacy: replacement.legacy.to_string(),
+                replacement: replacement.replacement.to_string(),
+                explanation: replacement.explanation.to_string(),
+            });
+        }
+    }
+    (migrated, findings)
+}
+
+/// A context config with its hooks' settings respelled and their commands migrated with
+/// [migrate_text], and what was changed. The config is returned as it is when nothing was.
+pub fn migrate_config(contents: &str) -> Result<(String, Vec<Finding>)> {
+    let mut config: Value = serde_json::from_str(contents)?;
+    let mut findings = Vec::new();
+    if let Some(hooks) = config.get_mut("hooks").and_then(Value::as_object_mut) {
+        for (name, hook) in hooks.iter_mut() {
+            let Some(hook) = hook.as_object_mut() else {
+                continue;
+            };
+            for &(legacy, key) in HOOK_KEYS {
+                let Some(value) = hook.remove(legacy) else {
+                    continue;
+                };
+                let explanation = match hook.contains_key(key) {
+                    true => format!("{legacy} was ignored, and {key} is already set."),
+                    false => {
+                        hook.insert(key.to_string(), value);
+                        format!("{legacy} was ignored, hook settings are spelled in snake_case.")
+                    },
+                };
+                findings.push(Finding {
+                    hook: Some(name.clone()),
+                    legacy: legacy.to_string(),
+                    replacement: key.to_string(),
+                    explanation,
+                });
+            }
+            // The command of a hook, or the commands of its pipeline.
+            let commands = match hook.get_mut("command") {
+                Some(Value::String(command)) => vec![command],
+                Some(Value::Array(commands)) => commands
+                    .iter_mut()
+                    .filter_map(|c| match c {
+                        Value::String(command) => Some(command),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            for command in commands {
+                let (migrated, command_findings) = migrate_text(command);
+                *command = migrated;
+                findings.extend(command_findings.into_iter().map(|finding| Finding {
+                    hook: Some(name.clone()),
+                    ..finding
+                }));
+            }
+        }
+    }
+
+    match findings.is_empty() {
+        true => Ok((contents.to_string(), findings)),
+        false => Ok((serde_json::to_string_pretty(&config)?, findings)),
+    }
+}
+
+/// The files of `paths` that use something removed. Without `paths`, the global context config
+/// and those of every profile are checked.
+///
+/// Paths ending in `.json` are read as context configs, anything else as text.
+pub async fn scan(os: &Os, paths: &[PathBuf]) -> Result<Vec<FileMigration>> {
+    let mut configs = Vec::new();
+    let mut texts = Vec::new();
+    if paths.is_empty() {
+        let profiles_dir = directories::chat_profiles_dir(os)?;
+        if os.fs.exists(&profiles_dir) {
+            let mut read_dir = os.fs.read_dir(&profiles_dir).await?;
+            while let Some(entry) = read_dir.next_entry().await? {
+                let name = entry.file_name();
+                configs.push(profile_context_path(os, &name.to_string_lossy())?);
+            }
+        }
+        configs.sort();
+        configs.insert(0, directories::chat_global_context_path(os)?);
+    } else {
+        for path in paths {
+            match path.extension().is_some_and(|extension| extension == "json") {
+                true => configs.push(path.clone()),
+                false => texts.push(path.clone()),
+            }
+        }
+    }
+
+    let mut migrations = Vec::new();
+    for path in configs {
+        // Profiles without a config use the defaults.
+        if paths.is_empty() && !os.fs.exists(&path) {
+            continue;
+        }
+        let contents = read(os, &path).await?;
+        let (migrated, findings) =
+            migrate_config(&contents).wrap_err_with(|| format!("Failed to parse {}", path.display()))?;
+        migrations.push(FileMigration {
+            path,
+            findings,
+            migrated,
+        });
+    }
+    for path in texts {
+        let (migrated, findings) = migrate_text(&read(os, &path).await?);
+        migrations.push(FileMigration {
+            path,
+            findings,
+            migrated,
+        });
+    }
+    migrations.retain(|migration| !migration.findings.is_empty());
+    Ok(migrations)
+}
+
+async fn read(os: &Os, path: &Path) -> Result<String> {
+    os.fs
+        .read_to_string(path)
+        .await
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_text() {
+        let (migrated, findings) =
+            migrate_text("q chat --accept-all \"hi\"\nq chat --accept-all-files\necho /acceptall; /context hooks\n");
+        assert_eq!(
+            migrated,
+            "q chat --trust-all-tools \"hi\"\nq chat --accept-all-files\necho /tools trust-all; /hooks\n"
+        );
+        let legacy: Vec<_> = findings.iter().map(|finding| finding.legacy.as_str()).collect();
+        assert_eq!(legacy, vec!["/acceptall", "--accept-all", "/context hooks"]);
+
+        let (migrated, findings) = migrate_text("see docs/acceptall.md");
+        assert_eq!(migrated, "see docs/acceptall.md");
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_config() {
+        let config = r#"{
+            "paths": ["README.md"],
+            "hooks": {
+                "status": {"trigger": "per_prompt", "type": "inline", "command": "git status", "timeoutMs": 500},
+                "both": {"trigger": "per_prompt", "type": "inline", "command": "ls", "cacheTtlSeconds": 5, "cache_ttl_seconds": 9},
+                "trust": {"trigger": "conversation_start", "type": "inline", "command": "q chat --accept-all"},
+                "chain": {"trigger": "per_prompt", "type": "inline", "command": ["ls", "q chat --accept-all"]}
+            }
+        }"#;
+        let (migrated, findings) = migrate_config(config).unwrap();
+        let migrated: Value = serde_json::from_str(&migrated).unwrap();
+        assert_eq!(migrated["hooks"]["status"]["timeout_ms"], 500);
+        assert!(migrated["hooks"]["status"].get("timeoutMs").is_none());
+        assert_eq!(migrated["hooks"]["both"]["cache_ttl_seconds"], 9);
+        assert!(migrated["hooks"]["both"].get("cacheTtlSeconds").is_none());
+        assert_eq!(migrated["hooks"]["trust"]["command"], "q chat --trust-all-tools");
+        assert_eq!(migrated["hooks"]["chain"]["command"][1], "q chat --trust-all-tools");
+        assert_eq!(migrated["paths"][0], "README.md");
+        assert_eq!(findings.len(), 4);
+
+        let config = r#"{"paths": [], "hooks": {}}"#;
+        assert_eq!(migrate_config(config).unwrap(), (config.to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_legacy_command() {
+        assert_eq!(
+            legacy_command("/acceptall").map(|r| r.replacement),
+            Some("/tools trust-all")
+        );
+        assert_eq!(
+            legacy_command("/acceptall now").map(|r| r.replacement),
+            Some("/tools trust-all")
+        );
+        assert_eq!(legacy_command("/acceptallx"), None);
+        assert_eq!(legacy_command("--accept-all"), None);
+    }
+}
@@ -1,22 +1,54 @@
+mod analytics;
+mod artifacts;
+mod audio;
+mod autosave;
+mod checkpoint;
 mod cli;
+mod completion_cache;
 mod consts;
 mod context;
+mod context_cache;
+mod context_templates;
+mod context_walk;
+mod context_watch;
 mod conversation;
+mod dynamic_context;
 mod error_formatter;
+mod failure;
+mod hook_sandbox;
+mod hook_scripts;
+mod hook_watch;
+mod import;
 mod input_source;
+mod json_select;
 mod message;
+mod migrate;
+mod output;
+mod output_filter;
 mod parse;
 use std::path::MAIN_SEPARATOR;
 mod parser;
 mod prompt;
 mod prompt_parser;
+mod quick_actions;
+pub mod recipe;
+mod refactor;
+mod remote_cache;
+mod response_schema;
+mod s3_context;
+mod security_review;
 mod server_messenger;
 #[cfg(unix)]
 mod skim_integration;
+pub mod store;
 mod token_counter;
 pub mod tool_manager;
+mod tool_presets;
 pub mod tools;
+mod transcript_log;
+mod url_context;
 pub mod util;
+mod workspace_lock;
 
 use std::borrow::Cow;
 use std::collections::{
@@ -29,18 +61,30 @@ use std::io::{
     Read,
     Write,
 };
+use std::path::PathBuf;
 use std::process::ExitCode;
 use std::time::Duration;
 
 use amzn_codewhisperer_client::types::SubscriptionStatus;
+use analytics::ToolConfirmation;
+use audio::Speaker;
+use checkpoint::CheckpointManager;
 use clap::{
     Args,
     CommandFactory,
-    Parser,
+    FromArgMatches,
 };
+use completion_cache::{
+    CompletionCache,
+    CompletionCategory,
+};
+use consts::MAX_PIPED_INPUT_SIZE;
 use context::ContextManager;
 pub use conversation::ConversationState;
-use conversation::TokenWarningLevel;
+use conversation::{
+    TokenWarningLevel,
+    format_hook_context,
+};
 use crossterm::style::{
     Attribute,
     Color,
@@ -59,6 +103,10 @@ use eyre::{
     bail,
     eyre,
 };
+use failure::{
+    FailureSummary,
+    run_shell_command,
+};
 use input_source::InputSource;
 use message::{
     AssistantMessage,
@@ -66,6 +114,9 @@ use message::{
     ToolUseResult,
     ToolUseResultBlock,
 };
+use output::ConversationOutput;
+pub use output::OutputMode;
+use output_filter::OutputFilter;
 use parse::{
     ParseState,
     interpret_markdown,
@@ -74,11 +125,23 @@ use parser::{
     RecvErrorKind,
     ResponseParser,
 };
+use quick_actions::{
+    QuickAction,
+    QuickActionFilter,
+};
+use recipe::{
+    Recipe,
+    RecipeRun,
+};
+use refactor::RefactorRun;
 use regex::Regex;
+use response_schema::ResponseSchema;
+use security_review::SecurityReview;
 use spinners::{
     Spinner,
     Spinners,
 };
+use store::ConversationStore;
 use thiserror::Error;
 use time::OffsetDateTime;
 use token_counter::TokenCounter;
@@ -91,6 +154,7 @@ use tool_manager::{
 use tools::gh_issue::GhIssueContext;
 use tools::{
     OutputKind,
+    PermissionDecision,
     QueuedTool,
     Tool,
     ToolPermissions,
@@ -103,14 +167,25 @@ use tracing::{
     trace,
     warn,
 };
+use transcript_log::{
+    TranscriptEvent,
+    TranscriptLog,
+    json_line,
+};
+use util::glyphs::GlyphWriter;
 use util::images::RichImageBlock;
 use util::ui::draw_box;
 use util::{
     animate_output,
+    notifications_enabled,
     play_notification_bell,
 };
 use winnow::Partial;
 use winnow::stream::Offset;
+use workspace_lock::{
+    Acquired,
+    WorkspaceLock,
+};
 
 use crate::api_client::ApiClientError;
 use crate::api_client::model::{
@@ -119,16 +194,32 @@ use crate::api_client::model::{
 };
 use crate::api_client::send_message_output::SendMessageOutput;
 use crate::auth::AuthError;
-use crate::auth::builder_id::is_idc_user;
-use crate::cli::chat::cli::SlashCommand;
+use crate::auth::builder_id::{
+    is_idc_user,
+    is_sigv4,
+    refresh,
+    refresh_if_expiring,
+};
+use crate::cli::chat::cli::history::HistoryMatch;
+use crate::cli::chat::cli::hooks::{
+    Hook,
+    HookTrigger,
+};
 use crate::cli::chat::cli::model::{
     MODEL_OPTIONS,
     default_model_id,
 };
+use crate::cli::chat::cli::persist::SavePoint;
 use crate::cli::chat::cli::prompts::{
     GetPromptError,
     PromptsSubcommand,
 };
+use crate::cli::chat::cli::var::interpolate_variables;
+use crate::cli::chat::cli::{
+    SlashCommand,
+    quit,
+};
+use crate::cli::user::LoginArgs;
 use crate::database::settings::Setting;
 use crate::mcp_client::Prompt;
 use crate::os::Os;
@@ -156,6 +247,16 @@ pub const EXTRA_HELP: &str = color_print::cstr! {"
                     <black!>Change the keybind using: q settings chat.skimCommandKey x</black!>
 <em>chat.editMode</em>       <black!>The prompt editing mode (vim or emacs)</black!>
                     <black!>Change using: q settings chat.skimCommandKey x</black!>
+<em>chat.remoteMode</em>     <black!>Use OSC 52 for clipboard and disable notifications (detected over SSH)</black!>
+                    <black!>Change using: q settings chat.remoteMode true</black!>
+<em>chat.enableAutosave</em> <black!>Save sessions so they can be resumed with /resume or after a crash (default true)</black!>
+                    <black!>Change using: q settings chat.enableAutosave false</black!>
+<em>chat.enableQuickActions</em> <black!>Offer follow-ups at the end of responses, picked by typing their number (default off)</black!>
+                    <black!>Change using: q settings chat.enableQuickActions true</black!>
+<em>storage.encrypt</em>     <black!>Encrypt stored and saved conversations, artifacts and fetched context (default off)</black!>
+                    <black!>Without Q_STORAGE_PASSPHRASE, the key is kept in the CLI's own database, so anyone</black!>
+                    <black!>who can read that database can decrypt the data: set the passphrase to protect it</black!>
+                    <black!>Change using: q settings storage.encrypt on</black!>
 "};
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Args)]
@@ -163,6 +264,10 @@ pub struct ChatArgs {
     /// Resumes the previous conversation from this directory.
     #[arg(short, long)]
     pub resume: bool,
+    /// Resumes a stored session from any directory by its id, or a prefix of one. See 'q sessions
+    /// list'
+    #[arg(long, value_name = "ID", conflicts_with_all = ["resume", "recipe", "replay"])]
+    pub session: Option<String>,
     /// Context profile to use
     #[arg(long = "profile")]
     pub profile: Option<String>,
@@ -179,6 +284,33 @@ pub struct ChatArgs {
     /// Whether the command should run without expecting user input
     #[arg(long, alias = "non-interactive")]
     pub no_interactive: bool,
+    /// Run the prompts in a recipe YAML file, optionally setting a profile, context files and
+    /// trusted tools
+    #[arg(long, value_name = "FILE", conflicts_with = "resume")]
+    pub recipe: Option<PathBuf>,
+    /// Send the prompts of a conversation saved with /save again, one at a time
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["resume", "recipe"])]
+    pub replay: Option<PathBuf>,
+    /// Trust the tools that ran in the replayed conversation, unless --trust-tools is given
+    #[arg(long, requires = "replay")]
+    pub replay_approved_tools: bool,
+    /// Require the final response to be JSON conforming to this JSON schema file. Exits with an
+    /// error if the model can't produce a conforming response. Requires --no-interactive
+    #[arg(long, value_name = "FILE", requires = "no_interactive")]
+    pub response_schema: Option<PathBuf>,
+    /// How many times to ask the model to correct a response that doesn't match --response-schema
+    /// (default: 2)
+    #[arg(long, value_name = "N", requires = "response_schema")]
+    pub schema_retries: Option<usize>,
+    /// Append every prompt, response chunk, tool use and tool result to this file as JSON lines
+    /// while the session runs. Defaults to the chat.transcriptPath setting
+    #[arg(long, value_name = "FILE")]
+    pub transcript: Option<PathBuf>,
+    /// How the conversation is written to stdout. json-stream writes a JSON line for every prompt,
+    /// response chunk, tool use and tool result, while status messages are still shown on stderr.
+    /// Requires --no-interactive
+    #[arg(long, value_enum, default_value_t, requires = "no_interactive")]
+    pub output: OutputMode,
     /// The first question to ask
     pub input: Option<String>,
 }
@@ -187,26 +319,62 @@ impl ChatArgs {
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
         let mut input = self.input;
 
-        if self.no_interactive && input.is_none() {
-            if !std::io::stdin().is_terminal() {
-                let mut buffer = String::new();
-                match std::io::stdin().read_to_string(&mut buffer) {
-                    Ok(_) => {
-                        if !buffer.trim().is_empty() {
-                            input = Some(buffer.trim().to_string());
+        let recipe = match (&self.recipe, &self.replay) {
+            (Some(path), _) => Some(Recipe::load(os, path).await?),
+            (None, Some(path)) => Some(Recipe::load_replay(os, path, self.replay_approved_tools).await?),
+            (None, None) => None,
+        };
+        let stored_session = match &self.session {
+            Some(id) => Some(autosave::find_by_id(os, id).await?),
+            None => None,
+        };
+
+        // Piped input is read along with a question, as in `cat error.log | q chat "why is this
+        // failing"`, or as the question itself when not interactive.
+        if (self.no_interactive || input.is_some()) && recipe.is_none() && !std::io::stdin().is_terminal() {
+            let mut buffer = String::new();
+            match std::io::stdin().read_to_string(&mut buffer) {
+                Ok(_) => {
+                    if !buffer.trim().is_empty() {
+                        let (prompt, truncated) = with_piped_input(input.take(), buffer.trim());
+                        if let Some(total) = truncated {
+                            eprintln!(
+                                "Piped input is {total} bytes, only its last {MAX_PIPED_INPUT_SIZE} bytes are sent."
+                            );
                         }
-                    },
-                    Err(e) => {
-                        eprintln!("Error reading from stdin: {}", e);
-                    },
-                }
+                        input = Some(prompt);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error reading from stdin: {}", e);
+                },
             }
+        }
 
-            if input.is_none() {
-                bail!("Input must be supplied when running in non-interactive mode");
-            }
+        if self.no_interactive && input.is_none() && recipe.is_none() {
+            bail!("Input must be supplied when running in non-interactive mode");
         }
 
+        let response_schema = match &self.response_schema {
+            Some(path) => {
+                Some(ResponseSchema::load(os, path, self.schema_retries.unwrap_or(DEFAULT_SCHEMA_RETRIES)).await?)
+            },
+            None => None,
+        };
+
+        let transcript_log = match self.transcript.or_else(|| {
+            os.database
+                .settings
+                .get_string(Setting::ChatTranscriptPath)
+                .map(PathBuf::from)
+        }) {
+            Some(path) => Some(
+                TranscriptLog::open(os, &path)
+                    .map_err(|err| eyre::eyre!("Failed to open the transcript at {}: {err}", path.display()))?,
+            ),
+            None => None,
+        };
+
         let stdout = std::io::stdout();
         let mut stderr = std::io::stderr();
 
@@ -229,8 +397,17 @@ impl ChatArgs {
             },
         };
 
+        // Explicit arguments take precedence over the recipe
+        let profile = self
+            .profile
+            .or_else(|| recipe.as_ref().and_then(|recipe| recipe.profile.clone()));
+        let trust_all_tools = self.trust_all_tools || recipe.as_ref().is_some_and(|recipe| recipe.trust_all_tools);
+        let trust_tools = self
+            .trust_tools
+            .or_else(|| recipe.as_ref().and_then(|recipe| recipe.trust_tools.clone()));
+
         // If profile is specified, verify it exists before starting the chat
-        if let Some(ref profile_name) = self.profile {
+        if let Some(ref profile_name) = profile {
             // Create a temporary context manager to check if the profile exists
             match ContextManager::new(os, None).await {
                 Ok(context_manager) => {
@@ -282,12 +459,12 @@ impl ChatArgs {
         let tool_config = tool_manager.load_tools(os, &mut stderr).await?;
         let mut tool_permissions = ToolPermissions::new(tool_config.len());
 
-        if self.trust_all_tools {
+        if trust_all_tools {
             tool_permissions.trust_all = true;
             for tool in tool_config.values() {
                 tool_permissions.trust_tool(&tool.name);
             }
-        } else if let Some(trusted) = self.trust_tools.map(|vec| vec.into_iter().collect::<HashSet<_>>()) {
+        } else if let Some(trusted) = trust_tools.map(|vec| vec.into_iter().collect::<HashSet<_>>()) {
             // --trust-all-tools takes precedence over --trust-tools=...
             for tool_name in &trusted {
                 if !tool_name.is_empty() {
@@ -306,7 +483,7 @@ impl ChatArgs {
             }
         }
 
-        ChatSession::new(
+        let mut session = ChatSession::new(
             os,
             stdout,
             stderr,
@@ -316,16 +493,29 @@ impl ChatArgs {
             self.resume,
             || terminal::window_size().map(|s| s.columns.into()).ok(),
             tool_manager,
-            self.profile,
+            profile,
             model_id,
             tool_config,
             tool_permissions,
             !self.no_interactive,
         )
         .await?
-        .spawn(os)
-        .await
-        .map(|_| ExitCode::SUCCESS)
+        .with_response_schema(response_schema)
+        .with_output_mode(self.output)
+        .with_transcript_log(transcript_log);
+
+        if let Some(stored) = stored_session {
+            stored.conversation.restore(os, &mut session).await;
+            session.existing_conversation = true;
+            // Claim the stored session for this process right away.
+            session.autosave(os).await;
+        }
+
+        if let Some(recipe) = recipe {
+            session.load_recipe(os, recipe).await?;
+        }
+
+        session.spawn(os).await.map(|_| ExitCode::SUCCESS)
     }
 }
 
@@ -378,6 +568,11 @@ const ROTATING_TIPS: [&str; 16] = [
 
 const GREETING_BREAK_POINT: usize = 80;
 
+const DEFAULT_SCHEMA_RETRIES: usize = 2;
+
+/// How long before the login token expires that it's refreshed, ahead of sending a prompt.
+const TOKEN_REFRESH_MARGIN: time::Duration = time::Duration::minutes(10);
+
 const POPULAR_SHORTCUTS: &str = color_print::cstr! {"<black!><green!>/help</green!> all commands  <em>•</em>  <green!>ctrl + j</green!> new lines  <em>•</em>  <green!>ctrl + s</green!> fuzzy search</black!>"};
 const SMALL_SCREEN_POPULAR_SHORTCUTS: &str = color_print::cstr! {"<black!><green!>/help</green!> all commands
 <green!>ctrl + j</green!> new lines
@@ -428,6 +623,8 @@ pub enum ChatError {
     NonInteractiveToolApproval,
     #[error("The conversation history is too large to compact")]
     CompactHistoryFailure,
+    #[error("The response did not conform to the response schema:\n{}", .0.join("\n"))]
+    ResponseSchemaViolation(Vec<String>),
 }
 
 impl ChatError {
@@ -443,6 +640,7 @@ impl ChatError {
             ChatError::GetPromptError(_) => None,
             ChatError::NonInteractiveToolApproval => None,
             ChatError::CompactHistoryFailure => None,
+            ChatError::ResponseSchemaViolation(_) => None,
         }
     }
 }
@@ -460,6 +658,7 @@ impl ReasonCode for ChatError {
             ChatError::Auth(_) => "AuthError".to_string(),
             ChatError::NonInteractiveToolApproval => "NonInteractiveToolApproval".to_string(),
             ChatError::CompactHistoryFailure => "CompactHistoryFailure".to_string(),
+            ChatError::ResponseSchemaViolation(_) => "ResponseSchemaViolation".to_string(),
         }
     }
 }
@@ -477,10 +676,10 @@ impl From<parser::RecvError> for ChatError {
 }
 
 pub struct ChatSession {
-    /// For output read by humans and machine
-    pub stdout: std::io::Stdout,
-    /// For display output, only read by humans
-    pub stderr: std::io::Stderr,
+    /// The conversation, read by humans and machines, see [output]
+    pub stdout: ConversationOutput,
+    /// Command status and diagnostics, only read by humans
+    pub stderr: GlyphWriter<std::io::Stderr>,
     initial_input: Option<String>,
     /// Whether we're starting a new conversation or continuing an old one.
     existing_conversation: bool,
@@ -492,6 +691,8 @@ pub struct ChatSession {
     conversation: ConversationState,
     tool_uses: Vec<QueuedTool>,
     pending_tool_index: Option<usize>,
+    /// Follow-ups offered at the end of the last response, picked by typing their number.
+    quick_actions: Vec<QuickAction>,
     /// State to track tools that need confirmation.
     tool_permissions: ToolPermissions,
     /// Telemetry events to be sent as part of the conversation.
@@ -500,9 +701,44 @@ pub struct ChatSession {
     tool_use_status: ToolUseStatus,
     /// Any failed requests that could be useful for error report/debugging
     failed_request_ids: Vec<String>,
+    /// The last error shown to the user, included in `/issue --diagnostics`.
+    last_error: Option<String>,
     /// Pending prompts to be sent
     pending_prompts: VecDeque<Prompt>,
     interactive: bool,
+    /// Schema the final response must conform to, set with `--response-schema`.
+    response_schema: Option<ResponseSchema>,
+    /// Remaining prompts of the recipe given with `--recipe`.
+    recipe: Option<RecipeRun>,
+    /// The `/refactor` run in progress, advanced a batch at a time.
+    refactor: Option<RefactorRun>,
+    /// The `/security-review` in progress, advanced a chunk at a time.
+    security_review: Option<SecurityReview>,
+    /// The last `/save` or `/load`, if any.
+    last_save: Option<SavePoint>,
+    /// The [generation](ConversationState::generation) of the conversation when it was last
+    /// autosaved.
+    autosaved_generation: u64,
+    /// Results of the last `/history` listing or search, numbered from 1.
+    history_results: Vec<HistoryMatch>,
+    /// Earlier turns added with `/history use`, sent along with the next prompt.
+    pending_context: Vec<String>,
+    /// Snapshots taken before changing tools run, restored with `/checkpoint restore`.
+    checkpoints: CheckpointManager,
+    /// The last shell command that failed, offered to the user as a one-key "ask Q to fix this".
+    last_failure: Option<FailureSummary>,
+    /// Log written with `--transcript`.
+    transcript_log: Option<TranscriptLog>,
+    /// Reads responses aloud while `/speak on` is set.
+    speaker: Option<Speaker>,
+    /// The lock on the working directory, held while no other session is editing it.
+    workspace_lock: Option<WorkspaceLock>,
+    /// Whether tools that change anything are refused, because another session is editing the
+    /// working directory.
+    read_only: bool,
+    /// Whether the request of the turn was sent again after logging in again, so that a request
+    /// refused for an expired login is only retried once.
+    login_retried: bool,
     inner: Option<ChatState>,
 }
 
@@ -521,7 +757,7 @@ impl ChatSession {
         profile: Option<String>,
         model_id: Option<String>,
         tool_config: HashMap<String, ToolSpec>,
-        tool_permissions: ToolPermissions,
+        mut tool_permissions: ToolPermissions,
         interactive: bool,
     ) -> Result<Self> {
         let valid_model_id = match model_id {
@@ -545,12 +781,21 @@ impl ChatSession {
             },
         };
 
-        // Reload prior conversation
+        // Reload prior conversation, preferring the session store over the conversation saved in
+        // the database for the directory. Tool permissions come from the command line rather than
+        // the stored session.
         let mut existing_conversation = false;
-        let previous_conversation = std::env::current_dir()
-            .ok()
-            .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
-            .flatten();
+        let stored_session = match (resume_conversation, os.env.current_dir()) {
+            (true, Ok(cwd)) => autosave::find_latest(os, &cwd, None).await,
+            _ => None,
+        };
+        let previous_conversation = match stored_session {
+            Some(session) => Some(session.conversation.conversation),
+            None => std::env::current_dir()
+                .ok()
+                .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
+                .flatten(),
+        };
 
         // Only restore conversations where there were actual messages.
         // Prevents edge case where user clears conversation then exits without chatting.
@@ -582,6 +827,22 @@ impl ChatSession {
             },
         };
 
+        // The profile's tool preset applies unless the command line set the tools' permissions.
+        let tool_preset = conversation
+            .context_manager
+            .as_ref()
+            .and_then(|context_manager| context_manager.tool_preset());
+        if let Some(preset) = tool_preset.filter(|_| !tool_permissions.is_modified()) {
+            preset.apply(&mut tool_permissions);
+        }
+
+        // With chat.asciiOnly, glyphs are replaced with ASCII on both channels.
+        let ascii_only = os.database.settings.get_bool(Setting::ChatAsciiOnly).unwrap_or(false);
+        let mut stdout = ConversationOutput::new(stdout);
+        stdout.set_ascii_only(ascii_only);
+        let mut stderr = GlyphWriter::new(stderr);
+        stderr.set_ascii_only(ascii_only);
+
         Ok(Self {
             stdout,
             stderr,
@@ -594,15 +855,90 @@ impl ChatSession {
             conversation,
             tool_uses: vec![],
             pending_tool_index: None,
+            quick_actions: Vec::new(),
             tool_use_telemetry_events: HashMap::new(),
             tool_use_status: ToolUseStatus::Idle,
             failed_request_ids: Vec::new(),
+            last_error: None,
             pending_prompts: VecDeque::new(),
             interactive,
+            response_schema: None,
+            recipe: None,
+            refactor: None,
+            security_review: None,
+            last_save: None,
+            autosaved_generation: 0,
+            history_results: Vec::new(),
+            pending_context: Vec::new(),
+            checkpoints: CheckpointManager::default(),
+            last_failure: None,
+            transcript_log: None,
+            speaker: None,
+            workspace_lock: None,
+            read_only: false,
+            login_retried: false,
             inner: Some(ChatState::default()),
         })
     }
 
+    /// Only the response that conforms to `response_schema` is written to stdout, earlier attempts
+    /// and their tool uses are held back and dropped.
+    pub fn with_response_schema(mut self, response_schema: Option<ResponseSchema>) -> Self {
+        if response_schema.is_some() {
+            self.stdout.hold();
+        }
+        self.response_schema = response_schema;
+        self
+    }
+
+    pub fn with_output_mode(mut self, mode: OutputMode) -> Self {
+        self.stdout.set_mode(mode);
+        self
+    }
+
+    pub fn with_transcript_log(mut self, transcript_log: Option<TranscriptLog>) -> Self {
+        self.transcript_log = transcript_log;
+        let conversation_id = self.conversation.conversation_id().to_string();
+        self.log_event(TranscriptEvent::SessionStart {
+            conversation_id: &conversation_id,
+        });
+        self
+    }
+
+    /// Appends `event` to the `--transcript` log, if there is one, and writes it to stdout with
+    /// `--output json-stream`.
+    fn log_event(&mut self, event: TranscriptEvent<'_>) {
+        if self.transcript_log.is_none() && self.stdout.mode() == OutputMode::Text {
+            return;
+        }
+        let line = match json_line(event) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!(?err, "failed to serialize a conversation event");
+                return;
+            },
+        };
+        if let Err(err) = self.stdout.write_event(&line) {
+            warn!(?err, "failed to write a conversation event to stdout");
+        }
+        if let Some(log) = &mut self.transcript_log {
+            log.append_line(&line);
+        }
+    }
+
+    /// Adds the recipe's context files to the session and queues its prompts.
+    pub async fn load_recipe(&mut self, os: &Os, recipe: Recipe) -> Result<()> {
+        if !recipe.context.is_empty() {
+            match self.conversation.context_manager.as_mut() {
+                Some(context_manager) => context_manager.add_session_paths(os, recipe.context).await?,
+                None => bail!("Context files are unavailable, unable to run the recipe"),
+            }
+        }
+
+        self.recipe = Some(RecipeRun::new(recipe.description, recipe.prompts));
+        Ok(())
+    }
+
     pub async fn next(&mut self, os: &mut Os) -> Result<(), ChatError> {
         // Update conversation state with new tool information
         self.conversation.update_state(false).await;
@@ -610,9 +946,25 @@ impl ChatSession {
         let ctrl_c_stream = ctrl_c();
         let result = match self.inner.take().expect("state must always be Some") {
             ChatState::PromptUser { skip_printing_tools } => {
+                self.autosave(os).await;
+                if self.tool_uses.is_empty() {
+                    if let Some(state) = self.next_recipe_step()? {
+                        self.inner = Some(state);
+                        return Ok(());
+                    }
+                    if let Some(state) = self.next_refactor_batch(os).await? {
+                        self.inner = Some(state);
+                        return Ok(());
+                    }
+                    if let Some(state) = self.next_security_review_chunk(os).await? {
+                        self.inner = Some(state);
+                        return Ok(());
+                    }
+                }
+
                 match (self.interactive, self.tool_uses.is_empty()) {
                     (false, true) => {
-                        self.inner = Some(ChatState::Exit);
+                        self.inner = Some(self.check_response_schema()?);
                         return Ok(());
                     },
                     (false, false) => {
@@ -662,7 +1014,7 @@ impl ChatSession {
             ChatState::Exit => return Ok(()),
         };
 
-        let err = match result {
+        let mut err = match result {
             Ok(state) => {
                 self.inner = Some(state);
                 return Ok(());
@@ -670,6 +1022,29 @@ impl ChatSession {
             Err(err) => err,
         };
 
+        // Requests refused for an expired login are sent again once logged in again, rather than
+        // failing the turn.
+        if is_expired_login(&err) && !self.login_retried {
+            self.login_retried = true;
+            if self.spinner.is_some() {
+                drop(self.spinner.take());
+                queue!(
+                    self.stderr,
+                    terminal::Clear(terminal::ClearType::CurrentLine),
+                    cursor::MoveToColumn(0),
+                )?;
+            }
+            if self.log_in_again(os).await? {
+                match self.resend_request(os).await {
+                    Ok(state) => {
+                        self.inner = Some(state);
+                        return Ok(());
+                    },
+                    Err(resend_err) => err = resend_err,
+                }
+            }
+        }
+
         // We encountered an error. Handle it.
         error!(?err, "An error occurred processing the current state");
         let (reason, reason_desc) = get_error_reason(&err);
@@ -738,7 +1113,7 @@ impl ChatSession {
                     });
 
                     execute!(
-                        self.stdout,
+                        self.stderr,
                         style::SetForegroundColor(Color::Yellow),
                         style::Print("The context window has overflowed, summarizing the history..."),
                         style::SetAttribute(Attribute::Reset),
@@ -840,6 +1215,7 @@ impl ChatSession {
             let text = re.replace_all(&format!("{}: {:?}\n", context, report), "").into_owned();
 
             queue!(self.stderr, style::Print(&text),)?;
+            self.last_error = Some(text.trim_end().to_string());
             self.conversation.append_transcript(text);
 
             execute!(
@@ -847,6 +1223,9 @@ impl ChatSession {
                 style::SetAttribute(Attribute::Reset),
                 style::SetForegroundColor(Color::Reset),
             )?;
+
+            let env = [("Q_ERROR", self.last_error.clone().unwrap_or_default())];
+            run_event_hooks(&mut self.conversation, &mut self.stderr, HookTrigger::OnError, &env).await;
         }
 
         self.conversation.enforce_conversation_invariants();
@@ -861,6 +1240,271 @@ impl ChatSession {
     }
 }
 
+impl ChatSession {
+    /// Takes the next prompt from the running recipe, if any, asking for confirmation first when
+    /// the step is marked with `pause`.
+    fn next_recipe_step(&mut self) -> Result<Option<ChatState>, ChatError> {
+        let Some(run) = self.recipe.as_mut() else {
+            return Ok(None);
+        };
+        let total = run.total();
+        let description = run.description.take();
+        let Some((index, step)) = run.next_step() else {
+            self.recipe = None;
+            return Ok(None);
+        };
+
+        if let Some(description) = description {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(format!("\nRecipe: {description}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("\nRecipe step {index}/{total}\n")),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!("> {}\n", step.prompt)),
+        )?;
+
+        if step.pause && self.interactive {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("\nSend this prompt? ["),
+                style::SetForegroundColor(Color::Green),
+                style::Print("y"),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(" to send, "),
+                style::SetForegroundColor(Color::Green),
+                style::Print("s"),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(" to skip this step, "),
+                style::SetForegroundColor(Color::Green),
+                style::Print("q"),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(" to stop the recipe]:\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+
+            let user_input = self
+                .read_user_input("> ".yellow().to_string().as_str(), true)
+                .unwrap_or_default();
+            match user_input.trim() {
+                "y" | "Y" => (),
+                "s" | "S" => {
+                    return Ok(Some(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    }));
+                },
+                _ => {
+                    self.recipe = None;
+                    execute!(
+                        self.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("\nRecipe stopped.\n\n"),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    return Ok(Some(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    }));
+                },
+            }
+        }
+
+        Ok(Some(ChatState::HandleInput { input: step.prompt }))
+    }
+
+    /// Finishes the `/refactor` batch the model was working on and sends the next one, asking for
+    /// confirmation first in interactive sessions. Stopping keeps the run so `/refactor --resume`
+    /// can continue it.
+    async fn next_refactor_batch(&mut self, os: &Os) -> Result<Option<ChatState>, ChatError> {
+        let Some(run) = self.refactor.as_mut() else {
+            return Ok(None);
+        };
+        run.finish_batch();
+        if run.is_done() {
+            let total = run.total();
+            self.refactor = None;
+            if let Err(err) = RefactorRun::remove(os).await {
+                warn!(?err, "failed to remove the finished refactor");
+            }
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ Refactor finished, all {total} batches are done\n\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(Some(ChatState::PromptUser {
+                skip_printing_tools: true,
+            }));
+        }
+        if let Err(err) = run.save(os).await {
+            warn!(?err, "failed to save refactor progress");
+        }
+
+        let (number, total) = (run.batch_number(), run.total());
+        if self.interactive {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("\nContinue with refactor batch {number}/{total}? [")),
+                style::SetForegroundColor(Color::Green),
+                style::Print("y"),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(" to continue, anything else to stop]:\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            let user_input = self
+                .read_user_input("> ".yellow().to_string().as_str(), true)
+                .unwrap_or_default();
+            if !matches!(user_input.trim(), "y" | "Y") {
+                self.refactor = None;
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!(
+                        "\nRefactor stopped before batch {number}/{total}. Continue it with /refactor --resume.\n\n"
+                    )),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                return Ok(Some(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                }));
+            }
+        }
+
+        let Some(run) = self.refactor.as_mut() else {
+            return Ok(None);
+        };
+        let Some((number, prompt)) = run.next_batch() else {
+            return Ok(None);
+        };
+        if let Err(err) = run.save(os).await {
+            warn!(?err, "failed to save refactor progress");
+        }
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("\nRefactor batch {number}/{total}\n\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        Ok(Some(ChatState::HandleInput { input: prompt }))
+    }
+
+    /// Records the findings of the `/security-review` chunk the model just reviewed and sends the
+    /// next one. Once every chunk is reviewed, or a response has no findings block, the report is
+    /// printed and written as SARIF if asked for.
+    async fn next_security_review_chunk(&mut self, os: &Os) -> Result<Option<ChatState>, ChatError> {
+        let Some(review) = self.security_review.as_mut() else {
+            return Ok(None);
+        };
+        let response = self
+            .conversation
+            .history()
+            .back()
+            .map(|(_, assistant)| assistant.content())
+            .unwrap_or_default();
+        let recorded = review.record(response);
+        if let (Ok(_), false) = (&recorded, review.is_done()) {
+            if let Some((number, prompt)) = review.next_chunk() {
+                let total = review.total();
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("\nSecurity review chunk {number}/{total}\n\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                return Ok(Some(ChatState::HandleInput { input: prompt }));
+            }
+        }
+
+        let Some(review) = self.security_review.take() else {
+            return Ok(None);
+        };
+        if let Err(err) = recorded {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!("\nSecurity review stopped early: {err}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\n✔ Security review of {}\n\n", review.target)),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(review.report()),
+        )?;
+        if let Some(path) = &review.sarif {
+            let written = match serde_json::to_string_pretty(&review.sarif()) {
+                Ok(sarif) => os.fs.write(path, sarif).await.map_err(eyre::Report::from),
+                Err(err) => Err(err.into()),
+            };
+            match written {
+                Ok(()) => execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\n✔ Wrote the SARIF report to {}\n", path.display())),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+                Err(err) => execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nError: failed to write {}: {err}\n", path.display())),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+            }
+        }
+        execute!(self.stderr, style::Print("\n"))?;
+        Ok(Some(ChatState::PromptUser {
+            skip_printing_tools: true,
+        }))
+    }
+
+    /// Validates the final response against `--response-schema`, if one was given. Returns the
+    /// state to continue with: either a correction prompt for the model, or [ChatState::Exit].
+    fn check_response_schema(&mut self) -> Result<ChatState, ChatError> {
+        let Some(schema) = self.response_schema.as_mut() else {
+            return Ok(ChatState::Exit);
+        };
+
+        let response = self
+            .conversation
+            .history()
+            .back()
+            .map(|(_, assistant)| assistant.content())
+            .unwrap_or_default();
+        let errors = match schema.validate(response) {
+            Ok(()) => {
+                self.stdout.release()?;
+                return Ok(ChatState::Exit);
+            },
+            Err(errors) => errors,
+        };
+        self.stdout.discard();
+
+        warn!(?errors, "response did not conform to the response schema");
+        match schema.correction_prompt(&errors) {
+            Some(input) => {
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print("\nThe response did not match the response schema, retrying...\n\n"),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                Ok(ChatState::HandleInput { input })
+            },
+            None => Err(ChatError::ResponseSchemaViolation(errors)),
+        }
+    }
+}
+
 impl Drop for ChatSession {
     fn drop(&mut self) {
         if let Some(spinner) = &mut self.spinner {
@@ -1001,6 +1645,11 @@ impl ChatSession {
             }
         }
 
+        self.offer_interrupted_session(os).await?;
+        if !self.lock_workspace(os).await? {
+            return Ok(());
+        }
+
         if let Some(user_input) = self.initial_input.take() {
             self.inner = Some(ChatState::HandleInput { input: user_input });
         }
@@ -1009,9 +1658,238 @@ impl ChatSession {
             self.next(os).await?;
         }
 
+        self.close_session(os).await;
+        if let Some(lock) = self.workspace_lock.take() {
+            lock.release(os).await;
+        }
+        Ok(())
+    }
+
+    /// Writes the conversation to the session store if it changed since the last autosave.
+    async fn autosave(&mut self, os: &Os) {
+        let generation = self.conversation.generation();
+        if !self.interactive || generation == self.autosaved_generation || !autosave::is_enabled(os) {
+            return;
+        }
+
+        match autosave::write(os, self, true).await {
+            Ok(()) => self.autosaved_generation = generation,
+            Err(err) => warn!(?err, "failed to autosave the conversation"),
+        }
+    }
+
+    /// Writes the final state of the conversation to the session store and marks it as no longer
+    /// running, so that it can be picked up again with `/resume`. The workspace stays locked, as
+    /// the process keeps editing it.
+    async fn close_session(&mut self, os: &Os) {
+        let turns = self.conversation.history().len();
+        if !self.interactive || (turns == 0 && self.autosaved_generation == 0) || !autosave::is_enabled(os) {
+            return;
+        }
+
+        if let Err(err) = autosave::write(os, self, false).await {
+            warn!(?err, "failed to save the session");
+        }
+        self.autosaved_generation = 0;
+    }
+
+    /// Offers to resume the most recent session in the current directory that didn't exit
+    /// normally, e.g. because the process crashed or the terminal was closed.
+    async fn offer_interrupted_session(&mut self, os: &Os) -> Result<(), ChatError> {
+        if !self.interactive
+            || self.existing_conversation
+            || self.initial_input.is_some()
+            || self.recipe.is_some()
+            || !autosave::is_enabled(os)
+        {
+            return Ok(());
+        }
+        let Ok(cwd) = os.env.current_dir() else {
+            return Ok(());
+        };
+        let Some(interrupted) = autosave::find_interrupted(os, &cwd).await else {
+            return Ok(());
+        };
+
+        let age = autosave::format_age(interrupted.entry.updated_at, OffsetDateTime::now_utc());
+        let prompt = format!("Resume interrupted session from {age}? [y/n]: ");
+        let resume = loop {
+            match self.input_source.read_line(Some(&prompt)) {
+                Ok(Some(answer)) => match answer.trim().to_lowercase().as_str() {
+                    "y" | "yes" => break true,
+                    "n" | "no" => break false,
+                    _ => (),
+                },
+                _ => break false,
+            }
+        };
+
+        if resume {
+            interrupted.conversation.restore(os, self).await;
+            // Claim the autosave for this process right away.
+            self.autosave(os).await;
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!(
+                    "\n✔ Resumed conversation with {} exchanges\n\n",
+                    self.conversation.history().len()
+                )),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        } else {
+            autosave::release(os, &interrupted.id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Takes the lock of the working directory. When another session holds it, warns that it's
+    /// being edited and asks whether to continue read-only, returning false to quit instead.
+    async fn lock_workspace(&mut self, os: &Os) -> Result<bool, ChatError> {
+        let Ok(cwd) = os.env.current_dir() else {
+            return Ok(true);
+        };
+        let holder = match workspace_lock::acquire(os, &cwd, self.conversation.conversation_id()).await {
+            Ok(Acquired::Held(lock)) => {
+                self.workspace_lock = Some(lock);
+                return Ok(true);
+            },
+            Ok(Acquired::Busy(holder)) => holder,
+            Err(err) => {
+                warn!(?err, "failed to lock the workspace");
+                return Ok(true);
+            },
+        };
+
+        let age = autosave::format_age(holder.since, OffsetDateTime::now_utc());
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!(
+                "⚠ Another q chat session is editing this workspace (process {}, started {age}).\n",
+                holder.pid
+            )),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("Changing files from both sessions can overwrite edits and break /checkpoint restore.\n\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        // Scripts keep going as they were started, the warning is all they get.
+        if !self.interactive {
+            return Ok(true);
+        }
+
+        let prompt = "Continue [r]ead-only, [e]dit anyway or [q]uit? [r/e/q]: ";
+        let read_only = loop {
+            match self.input_source.read_line(Some(prompt)) {
+                Ok(Some(answer)) => match answer.trim().to_lowercase().as_str() {
+                    "r" | "read-only" => break true,
+                    "e" | "edit" => break false,
+                    "q" | "quit" => return Ok(false),
+                    _ => (),
+                },
+                _ => break true,
+            }
+        };
+        if read_only {
+            self.read_only = true;
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print("\nContinuing read-only, tools that change files or run commands will be refused.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+        Ok(true)
+    }
+
+    /// Refreshes the login token ahead of a request when it's about to expire, so that it doesn't
+    /// expire in the middle of a turn's tool uses, and warns when it can't be.
+    async fn refresh_login(&mut self, os: &Os) -> Result<(), ChatError> {
+        if is_sigv4() {
+            return Ok(());
+        }
+        let warning = match refresh_if_expiring(&os.database, TOKEN_REFRESH_MARGIN).await {
+            Ok(Some(_)) => return Ok(()),
+            Ok(None) => "You are not logged in, run /login to continue the conversation.".to_string(),
+            Err(err) => format!("Failed to refresh the login token: {err}. Run /logout and /login if requests fail."),
+        };
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!("{warning}\n\n")),
+            style::SetForegroundColor(Color::Reset)
+        )?;
         Ok(())
     }
 
+    /// Logs in again after a request was refused for an expired login token. The token is
+    /// refreshed when it can be, and otherwise the login flow is run, in interactive sessions.
+    /// When refreshing fails for another reason than the token being refused, e.g. without
+    /// network, the token is kept and the error shown instead.
+    ///
+    /// Returns whether the session is logged in again.
+    async fn log_in_again(&mut self, os: &mut Os) -> Result<bool, ChatError> {
+        if is_sigv4() {
+            return Ok(false);
+        }
+        match refresh(&os.database).await {
+            Ok(Some(_)) => return Ok(true),
+            Ok(None) => (),
+            Err(err) if err.is_invalid_token() => warn!(?err, "failed to refresh the expired login token"),
+            Err(err) => {
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to refresh the login token: {err}\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(false);
+            },
+        }
+        if !self.interactive {
+            return Ok(false);
+        }
+
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(
+                "\nYour login expired and couldn't be refreshed, log in again to continue the conversation.\n\n"
+            ),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        // A token that failed to refresh would stop the login flow as already logged in.
+        if let Err(err) = crate::auth::logout(&mut os.database).await {
+            warn!(?err, "failed to remove the expired login token");
+        }
+        match LoginArgs::default().execute(os).await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to log in: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                Ok(false)
+            },
+        }
+    }
+
+    /// Sends the request of the turn again, e.g. once logged in again after it was refused.
+    async fn resend_request(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
+        execute!(self.stderr, cursor::Hide)?;
+        self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
+        let conv_state = self
+            .conversation
+            .as_sendable_conversation_state(os, &mut self.stderr, false)
+            .await?;
+        Ok(ChatState::HandleResponseStream(
+            os.client.send_message(conv_state).await?,
+        ))
+    }
+
     /// Compacts the conversation history, replacing the history with a summary generated by the
     /// model.
     ///
@@ -1221,6 +2099,7 @@ impl ChatSession {
     /// Read input from the user.
     async fn prompt_user(&mut self, os: &Os, skip_printing_tools: bool) -> Result<ChatState, ChatError> {
         execute!(self.stderr, cursor::Show)?;
+        self.login_retried = false;
 
         // Check token usage and display warnings if needed
         if self.pending_tool_index.is_none() {
@@ -1256,6 +2135,29 @@ impl ChatSession {
             )?;
         }
 
+        if !skip_printing_tools && self.pending_tool_index.is_none() {
+            if let Some(failure) = &self.last_failure {
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\n✗ {}\n", failure.headline())),
+                    style::SetForegroundColor(Color::DarkGrey),
+                )?;
+                for line in failure.output_tail.iter().rev().take(3).rev() {
+                    execute!(self.stderr, style::Print(format!("  {line}\n")))?;
+                }
+                execute!(
+                    self.stderr,
+                    style::Print("Enter '"),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("f"),
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("' to ask Q to fix this.\n"),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            }
+        }
+
         // Do this here so that the skim integration sees an updated view of the context *during the current
         // q session*. (e.g., if I add files to context, that won't show up for skim for the current
         // q session unless we do this in prompt_user... unless you can find a better way)
@@ -1276,6 +2178,8 @@ impl ChatSession {
             self.input_source
                 .put_skim_command_selector(os, Arc::new(context_manager.clone()), tool_names);
         }
+        let completion_cache = self.completion_cache(os).await;
+        self.input_source.set_completion_cache(completion_cache);
 
         execute!(
             self.stderr,
@@ -1285,18 +2189,79 @@ impl ChatSession {
         let prompt = self.generate_tool_trust_prompt();
         let user_input = match self.read_user_input(&prompt, false) {
             Some(input) => input,
-            None => return Ok(ChatState::Exit),
+            None => return quit::confirm_exit(os, self).await,
         };
+        let (shown, accepted) = self.input_source.take_completion_counts();
+        if shown > 0 || accepted > 0 {
+            analytics::record(os, |counts| counts.record_completions(shown, accepted)).await;
+        }
 
         self.conversation.append_user_transcript(&user_input);
         Ok(ChatState::HandleInput { input: user_input })
     }
 
+    /// The session values offered as completions at the next prompt.
+    async fn completion_cache(&mut self, os: &Os) -> CompletionCache {
+        let mut cache = CompletionCache::default();
+
+        let mut tool_names = self
+            .conversation
+            .tool_manager
+            .tn_map
+            .keys()
+            .filter(|name| *name != consts::DUMMY_TOOL_NAME)
+            .cloned()
+            .collect::<Vec<_>>();
+        tool_names.sort();
+        let (trusted, untrusted) = tool_names
+            .into_iter()
+            .partition(|name| self.tool_permissions.is_trusted(name));
+        cache.set(CompletionCategory::Tools { trusted: true }, trusted);
+        cache.set(CompletionCategory::Tools { trusted: false }, untrusted);
+        cache.set(
+            CompletionCategory::ModifiedTools,
+            self.tool_permissions.modified_tools(),
+        );
+
+        if let Some(context_manager) = &self.conversation.context_manager {
+            let snapshot = context_manager.snapshot(os).await;
+            cache.set_context(&snapshot, os.env.current_dir().ok().as_deref());
+        }
+
+        if let Ok(store) = ConversationStore::new(os) {
+            let tags = store::read_tags(&os.fs.chroot_path(store.index_path()));
+            cache.set(CompletionCategory::Tags, tags.into_iter().collect());
+        }
+        cache
+    }
+
     async fn handle_input(&mut self, os: &mut Os, mut user_input: String) -> Result<ChatState, ChatError> {
         queue!(self.stderr, style::Print('\n'))?;
 
+        // A message starting with --no-hooks is sent without the output of per_prompt hooks.
+        if let Some(message) = strip_no_hooks(&user_input) {
+            if let Some(context_manager) = &mut self.conversation.context_manager {
+                context_manager.skip_next_prompt_hooks = true;
+            }
+            user_input = message.to_string();
+        }
+
         let input = user_input.trim();
 
+        // Quick actions are offered for the next input only.
+        let quick_actions = std::mem::take(&mut self.quick_actions);
+        if let Some(action) = quick_actions::picked(&quick_actions, input) {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("{}\n", action.input)),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(ChatState::HandleInput {
+                input: action.input.clone(),
+            });
+        }
+
         // handle image path
         if let Some(chat_state) = does_input_reference_file(input) {
             return Ok(chat_state);
@@ -1309,8 +2274,21 @@ impl ChatSession {
             // replace anytime we error out and print a usage statement.
             args.insert(0, "slash_command".to_owned());
 
-            match SlashCommand::try_parse_from(args) {
+            // Help is wrapped to the terminal's width at the time it's shown.
+            let parsed = SlashCommand::command()
+                .term_width(self.terminal_width())
+                .try_get_matches_from(args)
+                .and_then(|matches| SlashCommand::from_arg_matches(&matches));
+            match parsed {
                 Ok(command) => {
+                    // Count commands by their name rather than the alias used.
+                    if let Some(name) = orig_args.first().and_then(|arg| {
+                        SlashCommand::command()
+                            .find_subcommand(arg)
+                            .map(|cmd| cmd.get_name().to_string())
+                    }) {
+                        analytics::record(os, |counts| counts.record_command(&format!("/{name}"))).await;
+                    }
                     match command.execute(os, self).await {
                         Ok(chat_state)
                             if matches!(chat_state, ChatState::Exit)
@@ -1332,6 +2310,24 @@ impl ChatSession {
                     writeln!(self.stderr)?;
                 },
                 Err(err) => {
+                    // Removed commands are explained rather than reported as unknown.
+                    if let Some(replacement) = migrate::legacy_command(input) {
+                        execute!(
+                            self.stderr,
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print(format!(
+                                "\n{} was removed, use {} instead. {}\n",
+                                replacement.legacy, replacement.replacement, replacement.explanation
+                            )),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print("Run /migrate to update configs and scripts that still use it.\n\n"),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: false,
+                        });
+                    }
+
                     // Replace the dummy name with a slash. Also have to check for an ansi sequence
                     // for invalid slash commands (e.g. on a "/doesntexist" input).
                     let ansi_output = err
@@ -1357,7 +2353,10 @@ impl ChatSession {
                                 None => break,
                             }
                         }
-                        let help = cmd.help_template("{all-args}").render_help();
+                        let help = cmd
+                            .term_width(self.terminal_width())
+                            .help_template("{all-args}")
+                            .render_help();
                         writeln!(self.stderr, "{}", help.ansi())?;
                     }
                 },
@@ -1385,23 +2384,19 @@ impl ChatSession {
             };
             return subcommand.execute(self).await;
         } else if let Some(command) = input.strip_prefix("!") {
-            // Use platform-appropriate shell
-            let result = if cfg!(target_os = "windows") {
-                std::process::Command::new("cmd").args(["/C", command]).status()
-            } else {
-                std::process::Command::new("bash").args(["-c", command]).status()
-            };
-
             // Handle the result and provide appropriate feedback
-            match result {
-                Ok(status) => {
+            match run_shell_command(command) {
+                Ok((status, stderr)) => {
                     if !status.success() {
                         queue!(
                             self.stderr,
                             style::SetForegroundColor(Color::Yellow),
-                            style::Print(format!("Self exited with status: {}\n", status)),
+                            style::Print(format!("Command exited with status: {}\n", status)),
                             style::SetForegroundColor(Color::Reset)
                         )?;
+                        if self.interactive {
+                            self.last_failure = FailureSummary::new(command, status.code(), "", &stderr);
+                        }
                     }
                 },
                 Err(e) => {
@@ -1421,8 +2416,16 @@ impl ChatSession {
             // Check for a pending tool approval
             if let Some(index) = self.pending_tool_index {
                 let is_trust = ["t", "T"].contains(&input);
+                let is_accept = ["y", "Y"].contains(&input);
+                let confirmation = match (is_trust, is_accept) {
+                    (true, _) => ToolConfirmation::Trusted,
+                    (_, true) => ToolConfirmation::Accepted,
+                    _ => ToolConfirmation::Rejected,
+                };
+                let tool_name = self.tool_uses[index].name.clone();
+                analytics::record(os, |counts| counts.record_tool_confirmation(&tool_name, confirmation)).await;
                 let tool_use = &mut self.tool_uses[index];
-                if ["y", "Y"].contains(&input) || is_trust {
+                if is_accept || is_trust {
                     if is_trust {
                         self.tool_permissions.trust_tool(&tool_use.name);
                     }
@@ -1436,10 +2439,33 @@ impl ChatSession {
                     .conversation
                     .append_prompts(prompts)
                     .ok_or(ChatError::Custom("Prompt append failed".into()))?;
+            } else if let Some(failure) = self.last_failure.take() {
+                if ["f", "F"].contains(&input) {
+                    user_input = failure.fix_prompt();
+                }
             }
 
+            let (interpolated, undefined) = interpolate_variables(&user_input, &self.conversation.variables);
+            if !undefined.is_empty() {
+                queue!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::DarkYellow),
+                    style::Print(format!(
+                        "Undefined variables were sent as-is: {}\n\n",
+                        undefined
+                            .iter()
+                            .map(|name| format!("{{{{{name}}}}}"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            }
+            user_input = interpolated;
+
             // Otherwise continue with normal chat on 'n' or other responses
             self.tool_use_status = ToolUseStatus::Idle;
+            self.log_event(TranscriptEvent::Prompt { prompt: &user_input });
 
             if self.pending_tool_index.is_some() {
                 // If the user just enters "n", replace the message we send to the model with
@@ -1454,9 +2480,21 @@ impl ChatSession {
                 };
                 self.conversation.abandon_tool_use(&self.tool_uses, user_input);
             } else {
+                if !self.pending_context.is_empty() {
+                    self.pending_context.push(user_input);
+                    user_input = self.pending_context.drain(..).collect::<Vec<_>>().join("\n\n");
+                }
+                if let Some(instructions) = self
+                    .response_schema
+                    .as_mut()
+                    .and_then(ResponseSchema::take_instructions)
+                {
+                    user_input.push_str(&instructions);
+                }
                 self.conversation.set_next_user_message(user_input).await;
             }
 
+            self.refresh_login(os).await?;
             let conv_state = self
                 .conversation
                 .as_sendable_conversation_state(os, &mut self.stderr, true)
@@ -1487,17 +2525,13 @@ impl ChatSession {
                 continue;
             }
 
-            // If there is an override, we will use it. Otherwise fall back to Tool's default.
-            let allowed = self.tool_permissions.trust_all
-                || (self.tool_permissions.has(&tool.name) && self.tool_permissions.is_trusted(&tool.name))
-                || !tool.tool.requires_acceptance(os);
+            // If there is an override, we will use it. Otherwise fall back to Tool's default. Tools
+            // of read-only sessions were denied already, when they were validated.
+            let requires_acceptance = tool.tool.requires_acceptance(os);
+            let (decision, _) = self.tool_permissions.evaluate(&tool.name, requires_acceptance, false);
+            let allowed = decision == PermissionDecision::Allow;
 
-            if os
-                .database
-                .settings
-                .get_bool(Setting::ChatEnableNotifications)
-                .unwrap_or(false)
-            {
+            if notifications_enabled(os) {
                 play_notification_bell(!allowed);
             }
 
@@ -1518,14 +2552,44 @@ impl ChatSession {
             });
         }
 
+        if let Some(id) = self
+            .checkpoints
+            .create(os, self.conversation.history(), &self.tool_uses)
+            .await
+        {
+            if self.interactive {
+                execute!(
+                    self.stderr,
+                    style::Print(format!("Checkpoint {id} saved, use /checkpoint restore {id} to undo\n").dark_grey())
+                )?;
+            }
+        }
+
         // Execute the requested tools.
         let mut tool_results = vec![];
         let mut image_blocks: Vec<RichImageBlock> = Vec::new();
+        let mut tool_hook_results = Vec::new();
+        let tool_inputs: HashMap<String, String> = self
+            .conversation
+            .history()
+            .back()
+            .and_then(|(_, assistant)| assistant.tool_uses())
+            .unwrap_or_default()
+            .iter()
+            .map(|tool_use| (tool_use.id.clone(), tool_use.args.to_string()))
+            .collect();
 
         for tool in &self.tool_uses {
             let mut tool_telemetry = self.tool_use_telemetry_events.entry(tool.id.clone());
             tool_telemetry = tool_telemetry.and_modify(|ev| ev.is_accepted = true);
 
+            let env = [
+                ("Q_TOOL_NAME", tool.name.clone()),
+                ("Q_TOOL_INPUT", tool_inputs.get(&tool.id).cloned().unwrap_or_default()),
+            ];
+            tool_hook_results
+                .extend(run_event_hooks(&mut self.conversation, &mut self.stderr, HookTrigger::PerToolUse, &env).await);
+
             let tool_start = std::time::Instant::now();
             let invoke_result = tool.tool.invoke(os, &mut self.stdout).await;
 
@@ -1549,7 +2613,12 @@ impl ChatSession {
             }
             let tool_time = format!("{}.{}", tool_time.as_secs(), tool_time.subsec_millis());
             match invoke_result {
-                Ok(result) => {
+                Ok(mut result) => {
+                    // Outputs of the artifact tools are already bounded, storing them again would
+                    // only hand out another id for the same content.
+                    if !matches!(&tool.tool, Tool::ReadArtifact(_) | Tool::SummarizeOutput(_)) {
+                        artifacts::store_large_output(os, &mut result.output).await;
+                    }
                     match result.output {
                         OutputKind::Text(ref text) => {
                             debug!("Output is Text: {}", text);
@@ -1574,6 +2643,12 @@ impl ChatSession {
                         style::Print("\n\n"),
                     )?;
 
+                    if let (Tool::ExecuteCommand(command), OutputKind::Json(output)) = (&tool.tool, &result.output) {
+                        if self.interactive {
+                            self.last_failure = FailureSummary::from_tool_output(&command.command, output);
+                        }
+                    }
+
                     tool_telemetry = tool_telemetry.and_modify(|ev| ev.is_success = Some(true));
                     if let Tool::Custom(_) = &tool.tool {
                         tool_telemetry
@@ -1602,6 +2677,8 @@ impl ChatSession {
                     )?;
 
                     tool_telemetry.and_modify(|ev| ev.is_success = Some(false));
+                    let env = [("Q_TOOL_NAME", tool.name.clone()), ("Q_ERROR", err.to_string())];
+                    run_event_hooks(&mut self.conversation, &mut self.stderr, HookTrigger::OnError, &env).await;
                     tool_results.push(ToolUseResult {
                         tool_use_id: tool.id.clone(),
                         content: vec![ToolUseResultBlock::Text(format!(
@@ -1621,6 +2698,9 @@ impl ChatSession {
             }
         }
 
+        for result in &tool_results {
+            self.log_event(result.into());
+        }
         if !image_blocks.is_empty() {
             let images = image_blocks.into_iter().map(|(block, _)| block).collect();
             self.conversation.add_tool_results_with_images(tool_results, images);
@@ -1633,6 +2713,10 @@ impl ChatSession {
         } else {
             self.conversation.add_tool_results(tool_results);
         }
+        if !tool_hook_results.is_empty() {
+            self.conversation
+                .add_next_message_context(&format_hook_context(&tool_hook_results, HookTrigger::PerToolUse));
+        }
 
         execute!(self.stderr, cursor::Hide)?;
         execute!(self.stderr, style::Print("\n"), style::SetAttribute(Attribute::Reset))?;
@@ -1652,6 +2736,48 @@ impl ChatSession {
         ));
     }
 
+    /// Quick actions are only offered to someone at the terminal to pick them.
+    fn quick_actions_enabled(&self, os: &Os) -> bool {
+        self.interactive
+            && os
+                .database
+                .settings
+                .get_bool(Setting::ChatEnableQuickActions)
+                .unwrap_or(false)
+    }
+
+    fn print_quick_actions(&mut self) -> Result<(), ChatError> {
+        if self.quick_actions.is_empty() {
+            return Ok(());
+        }
+        queue!(self.stderr, style::Print("\n"))?;
+        for (number, action) in self.quick_actions.iter().enumerate() {
+            queue!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("  {}. ", number + 1)),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(&action.label),
+            )?;
+            if action.input.starts_with('/') {
+                queue!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("  {}", action.input)),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            }
+            queue!(self.stderr, style::Print("\n"))?;
+        }
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("Type a number to pick one.\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        Ok(())
+    }
+
     async fn handle_response(&mut self, os: &mut Os, response: SendMessageOutput) -> Result<ChatState, ChatError> {
         let request_id = response.request_id().map(|s| s.to_string());
         let mut buf = String::new();
@@ -1663,6 +2789,10 @@ impl ChatSession {
 
         let mut tool_uses = Vec::new();
         let mut tool_name_being_recvd: Option<String> = None;
+        let mut quick_actions = self.quick_actions_enabled(os).then(QuickActionFilter::default);
+        let mut output_filter = self.conversation.context_manager.as_ref().and_then(|context_manager| {
+            OutputFilter::new(context_manager.stop_sequences(), context_manager.output_filters())
+        });
 
         if self.spinner.is_some() {
             drop(self.spinner.take());
@@ -1687,14 +2817,26 @@ impl ChatSession {
                             tool_name_being_recvd = Some(name);
                         },
                         parser::ResponseEvent::AssistantText(text) => {
+                            let rendered = match &mut output_filter {
+                                Some(filter) => filter.push(&text),
+                                None => text,
+                            };
                             // Add Q response prefix before the first assistant text.
                             // This must be markdown - using a code tick, which is printed
                             // as green.
-                            if !response_prefix_printed && !text.trim().is_empty() {
+                            if !response_prefix_printed && !rendered.trim().is_empty() {
                                 buf.push_str("`>` ");
                                 response_prefix_printed = true;
                             }
-                            buf.push_str(&text);
+                            match &mut quick_actions {
+                                Some(filter) => buf.push_str(&filter.push(&rendered)),
+                                None => buf.push_str(&rendered),
+                            }
+                            // The transcript gets the response as shown, without what the
+                            // output filters removed.
+                            if !rendered.is_empty() {
+                                self.log_event(TranscriptEvent::ResponseChunk { text: &rendered });
+                            }
                         },
                         parser::ResponseEvent::ToolUse(tool_use) => {
                             if self.spinner.is_some() {
@@ -1706,6 +2848,11 @@ impl ChatSession {
                                     cursor::Show
                                 )?;
                             }
+                            self.log_event(TranscriptEvent::ToolUse {
+                                id: &tool_use.id,
+                                name: &tool_use.name,
+                                input: &tool_use.args,
+                            });
                             tool_uses.push(tool_use);
                             tool_name_being_recvd = None;
                         },
@@ -1715,7 +2862,37 @@ impl ChatSession {
                             if message.content() == RESPONSE_TIMEOUT_CONTENT {
                                 error!(?request_id, ?message, "Encountered an unexpected model response");
                             }
+                            let mut held = String::new();
+                            let message = match &mut output_filter {
+                                Some(filter) => {
+                                    held = filter.finish();
+                                    if !held.is_empty() {
+                                        self.log_event(TranscriptEvent::ResponseChunk { text: &held });
+                                    }
+                                    let message = filter.filter_message(message);
+                                    // A stop sequence also ends the tool uses.
+                                    if message.tool_uses().is_none() {
+                                        tool_uses.clear();
+                                    }
+                                    message
+                                },
+                                None => message,
+                            };
+                            if let Some(speaker) = &mut self.speaker {
+                                if let Err(err) = speaker.speak(os, message.content()).await {
+                                    warn!(?err, "failed to read the response aloud");
+                                }
+                            }
                             self.conversation.push_assistant_message(os, message);
+                            match &mut quick_actions {
+                                Some(filter) => {
+                                    buf.push_str(&filter.push(&held));
+                                    let (held, actions) = filter.finish();
+                                    buf.push_str(&held);
+                                    self.quick_actions = actions;
+                                },
+                                None => buf.push_str(&held),
+                            }
                             ended = true;
                         },
                     }
@@ -1789,6 +2966,9 @@ impl ChatSession {
                                     )],
                                     status: ToolResultStatus::Error,
                                 }];
+                            for result in &tool_results {
+                                self.log_event(result.into());
+                            }
                             self.conversation.add_tool_results(tool_results);
                             self.send_tool_use_telemetry(os).await;
                             return Ok(ChatState::HandleResponseStream(
@@ -1801,6 +2981,22 @@ impl ChatSession {
                                     .await?,
                             ));
                         },
+                        RecvErrorKind::Client(ref err) if err.is_expired_token() && !self.login_retried => {
+                            // What was shown of the response is kept, and continued once logged
+                            // in again.
+                            let partial = parser.assistant_text().trim();
+                            if tool_uses.is_empty() && !partial.is_empty() {
+                                let message = AssistantMessage::new_response(None, partial.to_string());
+                                self.conversation.push_assistant_message(os, message);
+                                self.conversation
+                                    .set_next_user_message(
+                                        "Your response was cut off, continue it from where it stopped.".to_string(),
+                                    )
+                                    .await;
+                                execute!(self.stderr, style::Print("\n"))?;
+                            }
+                            return Err(recv_error.into());
+                        },
                         _ => return Err(recv_error.into()),
                     }
                 },
@@ -1823,7 +3019,9 @@ impl ChatSession {
                 )?;
             }
 
-            // Print the response for normal cases
+            // Print the response for normal cases, wrapped to the width of the terminal as it is
+            // now so that a resize mid-response applies to the rest of it.
+            state.terminal_width = Some(self.terminal_width());
             loop {
                 let input = Partial::new(&buf[offset..]);
                 match interpret_markdown(input, &mut self.stdout, &mut state) {
@@ -1856,12 +3054,7 @@ impl ChatSession {
                 self.send_chat_telemetry(os, request_id, TelemetryResult::Succeeded, None, None, None)
                     .await;
 
-                if os
-                    .database
-                    .settings
-                    .get_bool(Setting::ChatEnableNotifications)
-                    .unwrap_or(false)
-                {
+                if notifications_enabled(os) {
                     // For final responses (no tools suggested), always play the bell
                     play_notification_bell(tool_uses.is_empty());
                 }
@@ -1886,10 +3079,13 @@ impl ChatSession {
         }
 
         if !tool_uses.is_empty() {
+            self.quick_actions.clear();
             Ok(ChatState::ValidateTools(tool_uses))
         } else {
             self.tool_uses.clear();
             self.pending_tool_index = None;
+            self.print_quick_actions()?;
+            run_event_hooks(&mut self.conversation, &mut self.stderr, HookTrigger::PostResponse, &[]).await;
 
             Ok(ChatState::PromptUser {
                 skip_printing_tools: false,
@@ -1897,6 +3093,30 @@ impl ChatSession {
         }
     }
 
+    /// Whether the session is read-only, taking the lock of the working directory first in case
+    /// the session that held it exited.
+    async fn is_read_only(&mut self, os: &Os) -> bool {
+        if !self.read_only {
+            return false;
+        }
+        let Ok(cwd) = os.env.current_dir() else {
+            return true;
+        };
+        if let Ok(Acquired::Held(lock)) = workspace_lock::acquire(os, &cwd, self.conversation.conversation_id()).await {
+            self.workspace_lock = Some(lock);
+            self.read_only = false;
+            let _ = execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(
+                    "\nThe other session stopped editing the workspace, this session is no longer read-only.\n"
+                ),
+                style::SetForegroundColor(Color::Reset)
+            );
+        }
+        self.read_only
+    }
+
     async fn validate_tools(&mut self, os: &Os, tool_uses: Vec<AssistantToolUse>) -> Result<ChatState, ChatError> {
         let conv_id = self.conversation.conversation_id().to_owned();
         debug!(?tool_uses, "Validating tool uses");
@@ -1917,6 +3137,16 @@ impl ChatSession {
                     self.contextualize_tool(&mut tool);
 
                     match tool.validate(os).await {
+                        Ok(()) if tool.requires_acceptance(os) && self.is_read_only(os).await => {
+                            tool_telemetry.is_valid = Some(false);
+                            tool_results.push(ToolUseResult {
+                                tool_use_id: tool_use_id.clone(),
+                                content: vec![ToolUseResultBlock::Text(
+                                    "This session is read-only, because another q chat session is editing the workspace. Only tools that don't change anything can be used.".to_string(),
+                                )],
+                                status: ToolResultStatus::Error,
+                            });
+                        },
                         Ok(()) => {
                             tool_telemetry.is_valid = Some(true);
                             queued_tools.push(QueuedTool {
@@ -1975,6 +3205,9 @@ impl ChatSession {
                     }
                 }
             }
+            for result in &tool_results {
+                self.log_event(result.into());
+            }
             self.conversation.add_tool_results(tool_results);
             self.send_tool_use_telemetry(os).await;
             if let ToolUseStatus::Idle = self.tool_use_status {
@@ -2196,6 +3429,16 @@ impl ChatSession {
     }
 }
 
+/// Whether `err` is a request refused for an expired login token, see
+/// [ApiClientError::is_expired_token].
+fn is_expired_login(err: &ChatError) -> bool {
+    match err {
+        ChatError::Client(err) => err.is_expired_token(),
+        ChatError::ResponseStream(err) => matches!(&err.source, RecvErrorKind::Client(err) if err.is_expired_token()),
+        _ => false,
+    }
+}
+
 /// Replaces amzn_codewhisperer_client::types::SubscriptionStatus with a more descriptive type.
 /// See response expectations in [`get_subscription_status`] for reasoning.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -2220,6 +3463,31 @@ enum ActualSubscriptionStatus {
 //    - We return ActualSubscriptionStatus::Active since they don’t need to subscribe again.
 //
 // Also, it is currently not possible to subscribe or re-subscribe via console, only IDE/CLI.
+/// Runs the hooks of an event of the conversation, see [ContextManager::run_event_hooks]. Hooks
+/// that can't be run are logged rather than failing what triggered them.
+async fn run_event_hooks(
+    conversation: &mut ConversationState,
+    output: &mut impl Write,
+    trigger: HookTrigger,
+    env: &[(&str, String)],
+) -> Vec<(Hook, String)> {
+    let mut hook_env: Vec<(&str, String)> = conversation.hook_env();
+    hook_env.extend(env.iter().map(|(key, value)| (*key, value.clone())));
+    let Some(context_manager) = conversation.context_manager.as_mut() else {
+        return Vec::new();
+    };
+    match context_manager
+        .run_event_hooks(trigger.clone(), &hook_env, output)
+        .await
+    {
+        Ok(results) => results,
+        Err(err) => {
+            warn!(?err, %trigger, "failed to run hooks");
+            Vec::new()
+        },
+    }
+}
+
 async fn get_subscription_status(os: &mut Os) -> Result<ActualSubscriptionStatus> {
     if is_idc_user(&os.database).await? {
         return Ok(ActualSubscriptionStatus::Active);
@@ -2276,6 +3544,41 @@ where
     result
 }
 
+/// Adds `piped` input to the first prompt, or makes it the prompt if there is no `input`. Input
+/// over [MAX_PIPED_INPUT_SIZE] keeps only its end, and its full size is returned.
+fn with_piped_input(input: Option<String>, piped: &str) -> (String, Option<usize>) {
+    let (piped, truncated) = if piped.len() > MAX_PIPED_INPUT_SIZE {
+        let mut start = piped.len() - MAX_PIPED_INPUT_SIZE;
+        while !piped.is_char_boundary(start) {
+            start += 1;
+        }
+        (
+            format!("[... the first {start} bytes were cut]\n{}", &piped[start..]),
+            Some(piped.len()),
+        )
+    } else {
+        (piped.to_string(), None)
+    };
+
+    match input {
+        Some(input) => (
+            format!("{input}\n\n--- Piped input ---\n{piped}\n--- End of piped input ---"),
+            truncated,
+        ),
+        None => (piped, truncated),
+    }
+}
+
+/// The message of an input starting with `--no-hooks`, which is sent without running
+/// `per_prompt` hooks.
+fn strip_no_hooks(input: &str) -> Option<&str> {
+    input
+        .trim_start()
+        .strip_prefix("--no-hooks")
+        .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        .map(str::trim_start)
+}
+
 /// Checks if an input may be referencing a file and should not be handled as a typical slash
 /// command. If true, then return [Option::Some<ChatState>], otherwise [Option::None].
 fn does_input_reference_file(input: &str) -> Option<ChatState> {
@@ -2352,6 +3655,52 @@ mod tests {
         assert_eq!(os.fs.read_to_string("/file.txt").await.unwrap(), "Hello, world!\n");
     }
 
+    #[tokio::test]
+    async fn test_quit_keeps_an_existing_save() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ChatEnableAutosave, false)
+            .await
+            .unwrap();
+        os.client.set_mock_output(serde_json::json!([["Hello!"]]));
+        os.fs.write("/saved.json", "earlier").await.unwrap();
+
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        ChatSession::new(
+            &mut os,
+            std::io::stdout(),
+            std::io::stderr(),
+            "fake_conv_id",
+            None,
+            InputSource::new_mock(vec![
+                "hi".to_string(),
+                "/quit".to_string(),
+                "s".to_string(),
+                "/saved.json".to_string(),
+                "n".to_string(),
+                "/quit".to_string(),
+                "e".to_string(),
+            ]),
+            false,
+            || Some(80),
+            ToolManager::default(),
+            None,
+            None,
+            tool_config,
+            ToolPermissions::new(0),
+            true,
+        )
+        .await
+        .unwrap()
+        .spawn(&mut os)
+        .await
+        .unwrap();
+
+        assert_eq!(os.fs.read_to_string("/saved.json").await.unwrap(), "earlier");
+    }
+
     #[tokio::test]
     async fn test_flow_tool_permissions() {
         let mut os = Os::new().await.unwrap();
@@ -2708,6 +4057,28 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_with_piped_input() {
+        assert_eq!(
+            with_piped_input(Some("why is this failing".to_string()), "error: boom"),
+            (
+                "why is this failing\n\n--- Piped input ---\nerror: boom\n--- End of piped input ---".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            with_piped_input(None, "explain this"),
+            ("explain this".to_string(), None)
+        );
+
+        let long = format!("{}tail", "é".repeat(MAX_PIPED_INPUT_SIZE));
+        let (prompt, truncated) = with_piped_input(None, &long);
+        assert_eq!(truncated, Some(long.len()));
+        assert!(prompt.starts_with("[... the first "));
+        assert!(prompt.ends_with("tail"));
+        assert!(prompt.len() < MAX_PIPED_INPUT_SIZE + 50);
+    }
+
     #[test]
     fn test_does_input_reference_file() {
         let tests = &[
@@ -2724,4 +4095,12 @@ mod tests {
             assert_eq!(actual, *expected, "expected {} for input {}", expected, input);
         }
     }
+
+    #[test]
+    fn test_strip_no_hooks() {
+        assert_eq!(strip_no_hooks("--no-hooks what is this?"), Some("what is this?"));
+        assert_eq!(strip_no_hooks("  --no-hooks"), Some(""));
+        assert_eq!(strip_no_hooks("--no-hooksy"), None);
+        assert_eq!(strip_no_hooks("why --no-hooks"), None);
+    }
 }
@@ -0,0 +1,115 @@
+//! The channels the chat writes to:
+//!
+//! - The conversation: responses, tool uses and the output of tools, written to stdout through
+//!   [ConversationOutput].
+//! - Command status: the results of slash commands and messages about the session, such as
+//!   "summarizing the history", written to stderr.
+//! - Diagnostics: errors and warnings, written to stderr.
+//!
+//! With `--output json-stream` the conversation is written as JSON lines of
+//! [TranscriptEvent](super::transcript_log::TranscriptEvent)s instead of being rendered, the same
+//! lines as a `--transcript` log, so scripts read only the conversation from stdout while the
+//! terminal still shows status and diagnostics.
+//!
+//! With `--response-schema`, the conversation is held back until a response conforms to the
+//! schema, so that stdout only gets the attempt that does.
+
+use std::io::{
+    self,
+    Stdout,
+    Write,
+};
+
+use clap::ValueEnum;
+
+use super::util::glyphs::GlyphWriter;
+
+/// How the conversation is written to stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputMode {
+    /// Rendered for reading in a terminal
+    #[default]
+    Text,
+    /// A JSON object for every prompt, response chunk, tool use and tool result
+    JsonStream,
+}
+
+/// The conversation channel. Text written to it is the rendered conversation, which is dropped in
+/// [OutputMode::JsonStream] where the conversation's events are written instead.
+pub struct ConversationOutput {
+    mode: OutputMode,
+    stdout: GlyphWriter<Stdout>,
+    /// Text written since [Self::hold], until it's released or discarded.
+    held: Option<Vec<u8>>,
+}
+
+impl ConversationOutput {
+    pub fn new(stdout: Stdout) -> Self {
+        Self {
+            mode: OutputMode::default(),
+            stdout: GlyphWriter::new(stdout),
+            held: None,
+        }
+    }
+
+    pub fn mode(&self) -> OutputMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: OutputMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_ascii_only(&mut self, ascii_only: bool) {
+        self.stdout.set_ascii_only(ascii_only);
+    }
+
+    /// Keeps the text written from now on instead of writing it, until [Self::release].
+    pub fn hold(&mut self) {
+        self.held.get_or_insert_with(Vec::new);
+    }
+
+    /// Writes the text held since [Self::hold], and stops holding text back.
+    pub fn release(&mut self) -> io::Result<()> {
+        if let Some(held) = self.held.take() {
+            self.stdout.write_all(&held)?;
+            self.stdout.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Drops the text held since [Self::hold], still holding back what is written next.
+    pub fn discard(&mut self) {
+        if let Some(held) = &mut self.held {
+            held.clear();
+        }
+    }
+
+    /// Writes a JSON line for an event of the conversation, if the conversation is streamed as
+    /// JSON.
+    pub fn write_event(&mut self, line: &str) -> io::Result<()> {
+        if self.mode == OutputMode::JsonStream {
+            // Events are data, written as they are even with chat.asciiOnly.
+            writeln!(self.stdout.inner_mut(), "{line}")?;
+            self.stdout.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for ConversationOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match (self.mode, &mut self.held) {
+            (OutputMode::Text, Some(held)) => {
+                held.extend_from_slice(buf);
+                Ok(buf.len())
+            },
+            (OutputMode::Text, None) => self.stdout.write(buf),
+            (OutputMode::JsonStream, _) => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
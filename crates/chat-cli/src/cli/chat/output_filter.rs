@@ -0,0 +1,167 @@
+//! Stop sequences and output filters of a profile, set with `/settings`, applied to responses as
+//! they're streamed, before they're rendered and added to the conversation.
+//!
+//! A response ends at the first stop sequence in it, and nothing after it is shown or kept, not
+//! even the tools it would use. Output filters are regular expressions whose matches are removed
+//! from each line of a response, e.g. `^(Certainly|Sure)! ?` for a habitual preamble, and a line
+//! left blank by a filter is removed. Since both work on whole lines, each line of a response is
+//! rendered once it's complete.
+
+use regex::Regex;
+use tracing::warn;
+
+use crate::cli::chat::message::AssistantMessage;
+
+#[derive(Debug, Clone)]
+pub struct OutputFilter {
+    stop_sequences: Vec<String>,
+    filters: Vec<Regex>,
+    /// The line being streamed, held back until it's complete.
+    line: String,
+    /// Whether a stop sequence ended the response.
+    stopped: bool,
+}
+
+impl OutputFilter {
+    /// A filter applying `stop_sequences` and `filters`, or `None` without either. Filters that
+    /// aren't valid regular expressions, e.g. in a config edited by hand, are skipped.
+    pub fn new(stop_sequences: &[String], filters: &[String]) -> Option<Self> {
+        let filters: Vec<Regex> = filters
+            .iter()
+            .filter_map(|filter| match Regex::new(filter) {
+                Ok(regex) => Some(regex),
+                Err(err) => {
+                    warn!(%filter, %err, "skipping invalid output filter");
+                    None
+                },
+            })
+            .collect();
+        let stop_sequences: Vec<String> = stop_sequences.iter().filter(|s| !s.is_empty()).cloned().collect();
+        if filters.is_empty() && stop_sequences.is_empty() {
+            return None;
+        }
+        Some(Self {
+            stop_sequences,
+            filters,
+            line: String::new(),
+            stopped: false,
+        })
+    }
+
+    /// The part of `text` to render, the lines it completes with the filters applied, up to a stop
+    /// sequence.
+    pub fn push(&mut self, text: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.line.push_str(text);
+        let stop = self.stop_sequences.iter().filter_map(|stop| self.line.find(stop)).min();
+        if let Some(stop) = stop {
+            self.line.truncate(stop);
+            self.stopped = true;
+            let rest = std::mem::take(&mut self.line);
+            return self.filter_lines(&rest);
+        }
+        match self.line.rfind('\n') {
+            Some(end) => {
+                let rest = self.line.split_off(end + 1);
+                let complete = std::mem::replace(&mut self.line, rest);
+                self.filter_lines(&complete)
+            },
+            None => String::new(),
+        }
+    }
+
+    /// Ends the response, returning the rest of the line held back.
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.line);
+        match self.stopped {
+            true => String::new(),
+            false => self.filter_lines(&rest),
+        }
+    }
+
+    /// `message` as it was rendered, without what came after a stop sequence, including its tool
+    /// uses.
+    pub fn filter_message(&self, message: AssistantMessage) -> AssistantMessage {
+        let mut filter = Self {
+            line: String::new(),
+            stopped: false,
+            ..self.clone()
+        };
+        let mut content = filter.push(message.content());
+        content.push_str(&filter.finish());
+        match message {
+            AssistantMessage::ToolUse {
+                message_id, tool_uses, ..
+            } if !filter.stopped => AssistantMessage::new_tool_use(message_id, content, tool_uses),
+            AssistantMessage::Response { message_id, .. } | AssistantMessage::ToolUse { message_id, .. } => {
+                AssistantMessage::new_response(message_id, content)
+            },
+        }
+    }
+
+    /// Removes the matches of the filters from each line of `text`.
+    fn filter_lines(&self, text: &str) -> String {
+        let mut filtered = String::new();
+        for line in text.split_inclusive('\n') {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (line, ""),
+            };
+            let mut result = content.to_string();
+            for filter in &self.filters {
+                result = filter.replace_all(&result, "").into_owned();
+            }
+            if result.trim().is_empty() && !content.trim().is_empty() {
+                continue;
+            }
+            filtered.push_str(&result);
+            filtered.push_str(newline);
+        }
+        filtered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(filter: &mut OutputFilter, response: &str) -> String {
+        let mut rendered = String::new();
+        for chunk in response.as_bytes().chunks(3) {
+            rendered.push_str(&filter.push(std::str::from_utf8(chunk).unwrap()));
+        }
+        rendered.push_str(&filter.finish());
+        rendered
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut filter = OutputFilter::new(&[], &["^Certainly! ?".to_string(), r"\s+$".to_string()]).unwrap();
+        let response = "Certainly!\nCertainly! The tests pass.   \n\nDone";
+        assert_eq!(stream(&mut filter, response), "The tests pass.\n\nDone");
+
+        // Invalid filters are skipped, leaving nothing to apply.
+        assert!(OutputFilter::new(&[], &["(".to_string()]).is_none());
+        assert!(OutputFilter::new(&[String::new()], &[]).is_none());
+    }
+
+    #[test]
+    fn test_stop_sequences() {
+        let stops = ["<END>".to_string(), "###".to_string()];
+        let mut filter = OutputFilter::new(&stops, &[]).unwrap();
+        let response = "First line\nSecond <END> third\n### more\n";
+        assert_eq!(stream(&mut filter, response), "First line\nSecond ");
+        assert!(filter.push("after").is_empty());
+
+        // Tool uses after a stop sequence are dropped with it.
+        let message = AssistantMessage::new_tool_use(Some("id".to_string()), response.to_string(), vec![]);
+        let filtered = filter.filter_message(message);
+        assert_eq!(filtered.content(), "First line\nSecond ");
+        assert!(filtered.tool_uses().is_none());
+
+        let message = AssistantMessage::new_tool_use(None, "No stop\n".to_string(), vec![]);
+        assert!(filter.filter_message(message).tool_uses().is_some());
+    }
+}
@@ -129,6 +129,12 @@ impl ResponseParser {
         }
     }
 
+    /// The text received since the start of the response or its last tool use, e.g. to keep what
+    /// was shown of a response whose stream failed.
+    pub fn assistant_text(&self) -> &str {
+        &self.assistant_text
+    }
+
     /// Consumes the associated [ConverseStreamResponse] until a valid [ResponseEvent] is parsed.
     pub async fn recv(&mut self) -> Result<ResponseEvent, RecvError> {
         if let Some((id, name)) = self.parsing_tool_use.take() {
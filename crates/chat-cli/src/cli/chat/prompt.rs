@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::cell::Cell;
 
 use eyre::Result;
 use rustyline::completion::{
@@ -13,12 +14,14 @@ use rustyline::highlight::{
 };
 use rustyline::hint::Hinter as RustylineHinter;
 use rustyline::history::DefaultHistory;
+use rustyline::line_buffer::LineBuffer;
 use rustyline::validate::{
     ValidationContext,
     ValidationResult,
     Validator,
 };
 use rustyline::{
+    Changeset,
     Cmd,
     Completer,
     CompletionType,
@@ -35,6 +38,10 @@ use rustyline::{
 };
 use winnow::stream::AsChar;
 
+use super::completion_cache::{
+    CompletionCache,
+    CompletionCategory,
+};
 pub use super::prompt_parser::generate_prompt;
 use super::prompt_parser::parse_prompt_components;
 use crate::database::settings::Setting;
@@ -42,16 +49,31 @@ use crate::os::Os;
 
 pub const COMMANDS: &[&str] = &[
     "/clear",
+    "/clear screen",
+    "/clear history",
+    "/clear tools",
+    "/clear all",
     "/help",
+    "/copy",
     "/editor",
+    "/editor --last",
     "/issue",
+    "/issue --diagnostics",
+    "/login",
+    "/logout",
+    "/whoami",
     "/quit",
+    "/quit --force",
     "/tools",
     "/tools trust",
     "/tools untrust",
     "/tools trust-all",
     "/tools reset",
+    "/tools reset --all",
+    "/tools why",
+    "/tools preset",
     "/mcp",
+    "/migrate",
     "/model",
     "/profile",
     "/profile help",
@@ -67,24 +89,110 @@ pub const COMMANDS: &[&str] = &[
     "/context show --expand",
     "/context add",
     "/context add --global",
+    "/context add --dynamic git-diff",
+    "/context add --dynamic tree",
+    "/context add --max-tokens",
+    "/context add --priority",
+    "/context add --types",
+    "/context add --gitignore",
+    "/context add --follow-symlinks",
+    "/context prioritize",
+    "/context group create",
+    "/context group delete",
+    "/context group enable",
+    "/context group disable",
+    "/context watch on",
+    "/context watch off",
     "/context rm",
     "/context rm --global",
     "/context clear",
     "/context clear --global",
+    "/context refresh",
+    "/context diff",
+    "/context init",
+    "/context usage",
+    "/context validate",
+    "/config",
+    "/config effective",
+    "/settings",
+    "/settings set",
+    "/settings unset",
+    "/settings list",
     "/hooks",
     "/hooks help",
     "/hooks add",
     "/hooks rm",
+    "/hooks edit",
     "/hooks enable",
     "/hooks disable",
+    "/hooks off",
+    "/hooks on",
     "/hooks enable-all",
     "/hooks disable-all",
+    "/hooks list",
+    "/hooks log",
+    "/hooks test",
+    "/hooks export",
+    "/hooks import",
     "/compact",
     "/compact help",
+    "/undo",
     "/usage",
     "/save",
     "/load",
+    "/load --format openai",
+    "/load --format anthropic",
+    "/resume",
+    "/history",
+    "/history search",
+    "/history search --all",
+    "/history use",
+    "/tag",
+    "/tag --remove",
+    "/sessions list",
+    "/sessions list --tag",
+    "/checkpoint list",
+    "/checkpoint restore",
+    "/checkpoint diff",
+    "/export",
+    "/export --format md",
+    "/export --format json",
+    "/export --format html",
+    "/export --share",
+    "/replay",
+    "/replay --file",
+    "/replay --session",
+    "/explain",
+    "/refactor",
+    "/refactor --resume",
+    "/refactor --cancel",
+    "/pin",
+    "/pin --remove",
+    "/bookmark add",
+    "/bookmark list",
+    "/bookmark goto",
+    "/bookmark rm",
+    "/gen-tests",
+    "/gen-tests --coverage",
+    "/gen-docs",
+    "/security-review",
+    "/security-review --sarif",
+    "/deps review",
+    "/deps review --apply",
+    "/pr-describe",
+    "/pr-describe --create",
     "/subscribe",
+    "/var",
+    "/var set",
+    "/var list",
+    "/var rm",
+    "/set var",
+    "/analytics show",
+    "/analytics export",
+    "/analytics reset",
+    "/voice",
+    "/speak on",
+    "/speak off",
 ];
 
 /// Complete commands that start with a slash
@@ -162,9 +270,20 @@ impl PromptCompleter {
     }
 }
 
+/// Categories completed anywhere in a prompt, not just as a command's argument.
+const PROMPT_WORD_CATEGORIES: &[CompletionCategory] = &[
+    CompletionCategory::Tools { trusted: true },
+    CompletionCategory::Tools { trusted: false },
+    CompletionCategory::ContextFiles,
+];
+
 pub struct ChatCompleter {
     path_completer: PathCompleter,
     prompt_completer: PromptCompleter,
+    cache: CompletionCache,
+    /// Completions offered and picked since the counts were last taken, for local analytics.
+    shown: Cell<u64>,
+    accepted: Cell<u64>,
 }
 
 impl ChatCompleter {
@@ -172,21 +291,38 @@ impl ChatCompleter {
         Self {
             path_completer: PathCompleter::new(),
             prompt_completer: PromptCompleter::new(sender, receiver),
+            cache: CompletionCache::default(),
+            shown: Cell::new(0),
+            accepted: Cell::new(0),
         }
     }
-}
 
-impl Completer for ChatCompleter {
-    type Candidate = String;
+    /// Completes tool names and context files starting with `word`, ignoring case.
+    fn complete_prompt_word(&self, word: &str) -> Vec<String> {
+        if word.is_empty() {
+            return Vec::new();
+        }
+        let word = word.to_lowercase();
+        let mut completions = PROMPT_WORD_CATEGORIES
+            .iter()
+            .flat_map(|category| self.cache.get(*category))
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word))
+            .cloned()
+            .collect::<Vec<_>>();
+        completions.sort();
+        completions
+    }
+}
 
-    fn complete(
-        &self,
-        line: &str,
-        pos: usize,
-        _os: &Context<'_>,
-    ) -> Result<(usize, Vec<Self::Candidate>), ReadlineError> {
+impl ChatCompleter {
+    fn completions(&self, line: &str, pos: usize, _os: &Context<'_>) -> Result<(usize, Vec<String>), ReadlineError> {
         let (start, word) = extract_word(line, pos, None, |c| c.is_space());
 
+        let words = line[..start].split_whitespace().collect::<Vec<_>>();
+        if let Some(completions) = self.cache.complete_argument(&words, word) {
+            return Ok((start, completions));
+        }
+
         // Handle command completion
         if word.starts_with('/') {
             return Ok(complete_command(word, start));
@@ -201,18 +337,54 @@ impl Completer for ChatCompleter {
             }
         }
 
-        // Handle file path completion as fallback
-        if let Ok((pos, completions)) = self.path_completer.complete_path(line, pos, _os) {
-            if !completions.is_empty() {
-                return Ok((pos, completions));
+        // Tool names and context files can be completed mid-sentence, alongside any paths
+        let mut completions = self.complete_prompt_word(word);
+        if let Ok((path_start, paths)) = self.path_completer.complete_path(line, pos, _os) {
+            if completions.is_empty() {
+                if !paths.is_empty() {
+                    return Ok((path_start, paths));
+                }
+            } else if path_start == start {
+                completions.extend(
+                    paths
+                        .into_iter()
+                        .filter(|path| !completions.contains(path))
+                        .collect::<Vec<_>>(),
+                );
             }
         }
+        if !completions.is_empty() {
+            return Ok((start, completions));
+        }
 
         // Default: no completions
         Ok((start, Vec::new()))
     }
 }
 
+impl Completer for ChatCompleter {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        os: &Context<'_>,
+    ) -> Result<(usize, Vec<Self::Candidate>), ReadlineError> {
+        let completions = self.completions(line, pos, os)?;
+        if !completions.1.is_empty() {
+            self.shown.set(self.shown.get() + 1);
+        }
+        Ok(completions)
+    }
+
+    fn update(&self, line: &mut LineBuffer, start: usize, elected: &str, cl: &mut Changeset) {
+        self.accepted.set(self.accepted.get() + 1);
+        let end = line.pos();
+        line.replace(start..end, elected, cl);
+    }
+}
+
 /// Custom hinter that provides shadowtext suggestions
 pub struct ChatHinter {
     /// Command history for providing suggestions based on past commands
@@ -304,6 +476,16 @@ impl ChatHelper {
     pub fn update_hinter_history(&mut self, command: &str) {
         self.hinter.update_history(command);
     }
+
+    /// Replaces the session values offered as completions.
+    pub fn set_completion_cache(&mut self, cache: CompletionCache) {
+        self.completer.cache = cache;
+    }
+
+    /// Returns how many times completions were offered and picked, and resets the counts.
+    pub fn take_completion_counts(&mut self) -> (u64, u64) {
+        (self.completer.shown.take(), self.completer.accepted.take())
+    }
 }
 
 impl Validator for ChatHelper {
@@ -425,6 +607,86 @@ mod tests {
         assert!(completions.contains(&"/help".to_string()));
     }
 
+    #[test]
+    fn test_chat_completer_tag_completion() {
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
+        let mut completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver);
+        completer.cache.set(CompletionCategory::Tags, vec![
+            "docs".to_string(),
+            "infra".to_string(),
+            "infra-prod".to_string(),
+        ]);
+        let empty_history = DefaultHistory::new();
+        let os = Context::new(&empty_history);
+
+        let line = "/sessions list --tag in";
+        let (start, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert_eq!(start, line.len() - 2);
+        assert_eq!(completions, vec!["infra".to_string(), "infra-prod".to_string()]);
+
+        let line = "/tag ";
+        let (_, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert_eq!(completions.len(), 3);
+    }
+
+    #[test]
+    fn test_chat_completer_prompt_word_completion() {
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
+        let mut completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver);
+        completer
+            .cache
+            .set(CompletionCategory::Tools { trusted: true }, vec!["fs_read".to_string()]);
+        completer.cache.set(CompletionCategory::Tools { trusted: false }, vec![
+            "fs_write".to_string(),
+        ]);
+        completer
+            .cache
+            .set(CompletionCategory::ContextFiles, vec!["AmazonQ.md".to_string()]);
+        let empty_history = DefaultHistory::new();
+        let os = Context::new(&empty_history);
+
+        let line = "please use the fs_w";
+        let (start, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert_eq!(start, line.len() - 4);
+        assert_eq!(completions, vec!["fs_write".to_string()]);
+
+        let line = "summarize amaz";
+        let (_, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert_eq!(completions, vec!["AmazonQ.md".to_string()]);
+    }
+
+    #[test]
+    fn test_chat_completer_tools_reset_completion() {
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
+        let mut completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver);
+        completer.cache.set(CompletionCategory::Tools { trusted: false }, vec![
+            "fs_read".to_string(),
+            "fs_write".to_string(),
+        ]);
+        completer
+            .cache
+            .set(CompletionCategory::ModifiedTools, vec!["fs_write".to_string()]);
+        let empty_history = DefaultHistory::new();
+        let os = Context::new(&empty_history);
+
+        let line = "/tools reset ";
+        let (start, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert_eq!(start, line.len());
+        assert_eq!(completions, vec!["--all".to_string(), "fs_write".to_string()]);
+
+        let line = "/tools reset fs";
+        let (_, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert_eq!(completions, vec!["fs_write".to_string()]);
+
+        completer.cache.set(CompletionCategory::ModifiedTools, Vec::new());
+        let line = "/tools reset f";
+        let (_, completions) = completer.complete(line, line.len(), &os).unwrap();
+        assert!(completions.is_empty());
+    }
+
     #[test]
     fn test_chat_completer_no_completion() {
         let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
@@ -0,0 +1,185 @@
+//! Follow-ups the assistant offers at the end of a response, such as running the tests or applying
+//! a change, shown as a numbered list so that typing a number runs one. Enabled with the
+//! `chat.enableQuickActions` setting.
+//!
+//! The assistant is asked to list them in a block at the end of its response:
+//!
+//! ```text
+//! <quick-actions>
+//! Run the tests | Run the test suite and fix any failures
+//! Show context usage | /usage
+//! </quick-actions>
+//! ```
+//!
+//! The block is held back from the rendered response. Each line is a label and the input sent
+//! when it's picked, either a prompt or one of [SAFE_COMMANDS]. Anything else, such as a shell
+//! command, is dropped, since a picked action runs without asking.
+
+const START: &str = "<quick-actions>";
+const END: &str = "</quick-actions>";
+
+/// Most actions shown, so each is picked with a single digit.
+const MAX_ACTIONS: usize = 9;
+
+/// Slash commands an action may run, those that only show information or that ask before
+/// changing anything.
+const SAFE_COMMANDS: &[&str] = &[
+    "/usage",
+    "/context show",
+    "/context validate",
+    "/tools",
+    "/hooks list",
+    "/history",
+    "/compact",
+];
+
+/// Added to the context when quick actions are enabled.
+pub const INSTRUCTIONS: &str = "When you end a response by offering follow-ups the user is likely to want next, such as running the tests or applying a change, also list them at the very end of the response, one per line and at most 5, in this format:
+<quick-actions>
+Short label | The message the user would send to ask for it
+</quick-actions>
+The user picks one by its number instead of typing it. Instead of a message, an action can be one of these commands: /usage, /context show, /tools, /compact. Leave the block out when there is nothing to follow up on.
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickAction {
+    pub label: String,
+    /// Handled as if the user had typed it.
+    pub input: String,
+}
+
+impl QuickAction {
+    /// Parses a line of a block, `label | input`, or just the input.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        // Numbers and bullets the assistant may add anyway.
+        let line = line.trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*'));
+        let (label, input) = line.split_once('|').unwrap_or((line, line));
+        let action = Self {
+            label: label.trim().to_string(),
+            input: input.trim().to_string(),
+        };
+        (!action.label.is_empty() && !action.input.is_empty() && action.is_safe()).then_some(action)
+    }
+
+    /// Whether the action can run without asking: prompts go through the usual tool approval,
+    /// and [SAFE_COMMANDS] don't change anything without asking.
+    fn is_safe(&self) -> bool {
+        match self.input.chars().next() {
+            Some('/') => SAFE_COMMANDS.iter().any(|command| {
+                self.input
+                    .strip_prefix(command)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+            }),
+            Some('!') => false,
+            _ => !self.input.contains('\n'),
+        }
+    }
+}
+
+/// Separates the quick actions block from a response as it's streamed.
+#[derive(Debug, Default)]
+pub struct QuickActionFilter {
+    /// Text that may be the start of a block, held back until it's known whether it is.
+    held: String,
+    /// The block so far, once it started.
+    block: Option<String>,
+}
+
+impl QuickActionFilter {
+    /// The part of `text` to render, holding back the block and what may be its start.
+    pub fn push(&mut self, text: &str) -> String {
+        if let Some(block) = &mut self.block {
+            block.push_str(text);
+            return String::new();
+        }
+        self.held.push_str(text);
+        if let Some(start) = self.held.find(START) {
+            let block = self.held.split_off(start);
+            self.block = Some(block[START.len()..].to_string());
+            return std::mem::take(&mut self.held);
+        }
+
+        // Keep the longest end of the text that START begins with.
+        let keep = (1..START.len().min(self.held.len() + 1))
+            .rev()
+            .find(|&n| {
+                let at = self.held.len() - n;
+                self.held.is_char_boundary(at) && START.starts_with(&self.held[at..])
+            })
+            .unwrap_or(0);
+        let rest = self.held.split_off(self.held.len() - keep);
+        std::mem::replace(&mut self.held, rest)
+    }
+
+    /// Ends the response, returning the text still held back and the actions of the block.
+    pub fn finish(&mut self) -> (String, Vec<QuickAction>) {
+        let held = std::mem::take(&mut self.held);
+        let actions = match self.block.take() {
+            Some(block) => block
+                .split(END)
+                .next()
+                .unwrap_or_default()
+                .lines()
+                .filter_map(QuickAction::parse)
+                .take(MAX_ACTIONS)
+                .collect(),
+            None => Vec::new(),
+        };
+        (held, actions)
+    }
+}
+
+/// The action picked by `input`, its number in `actions`.
+pub fn picked<'a>(actions: &'a [QuickAction], input: &str) -> Option<&'a QuickAction> {
+    let number: usize = input.trim().parse().ok()?;
+    actions.get(number.checked_sub(1)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let response = "Done.\n<quick-actions>\n1. Run the tests | Run cargo test\nUsage | /usage\nClean | !rm -rf target\nWipe | /clear all\n</quick-actions>\n";
+        // Streamed in small chunks, splitting the markers.
+        let mut filter = QuickActionFilter::default();
+        let mut rendered = String::new();
+        for chunk in response.as_bytes().chunks(3) {
+            rendered.push_str(&filter.push(std::str::from_utf8(chunk).unwrap()));
+        }
+        let (held, actions) = filter.finish();
+        rendered.push_str(&held);
+        assert_eq!(rendered, "Done.\n");
+        assert_eq!(actions, vec![
+            QuickAction {
+                label: "Run the tests".to_string(),
+                input: "Run cargo test".to_string(),
+            },
+            QuickAction {
+                label: "Usage".to_string(),
+                input: "/usage".to_string(),
+            },
+        ]);
+
+        // Text that only looks like the start of a block is rendered.
+        let mut filter = QuickActionFilter::default();
+        assert_eq!(filter.push("a <quick"), "a ");
+        assert_eq!(filter.push(" fix"), "<quick fix");
+        assert_eq!(filter.push(" <q"), " ");
+        assert_eq!(filter.finish(), ("<q".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_picked() {
+        let actions = vec![QuickAction {
+            label: "Usage".to_string(),
+            input: "/usage".to_string(),
+        }];
+        assert_eq!(picked(&actions, " 1 "), Some(&actions[0]));
+        assert_eq!(picked(&actions, "0"), None);
+        assert_eq!(picked(&actions, "2"), None);
+        assert_eq!(picked(&actions, "yes"), None);
+    }
+}
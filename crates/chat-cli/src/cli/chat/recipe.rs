@@ -0,0 +1,342 @@
+//! Recipes automate recurring multi-step conversations.
+//!
+//! A recipe is a YAML file run with `q chat --recipe <file>`:
+//!
+//! ```yaml
+//! description: Draft release notes
+//! profile: release
+//! context:
+//!   - CHANGELOG.md
+//! trust_tools: [fs_read, execute_bash]
+//! prompts:
+//!   - Summarize the commits since the last tag
+//!   - prompt: Draft release notes from the summary
+//!     pause: true
+//! ```
+//!
+//! Environment variables in context paths, such as `$HOME` or `${REVIEW_DIR}`, are expanded when
+//! the recipe is loaded, and unset ones are left as they are.
+//!
+//! Prompts are sent in order, each one after the previous response (and any tool uses) have
+//! finished. A step with `pause: true` asks for confirmation before it is sent; pauses are
+//! skipped in non-interactive mode.
+//!
+//! `q chat --replay <file>` runs the user prompts of a conversation saved with `/save` the same
+//! way, for example to check how a profile or set of prompts holds up against a new model.
+
+use std::collections::{
+    BTreeSet,
+    VecDeque,
+};
+use std::path::Path;
+
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+};
+use serde::Deserialize;
+
+use crate::cli::chat::cli::persist::LoadedConversation;
+use crate::cli::chat::message::{
+    AssistantMessage,
+    UserMessage,
+    UserMessageContent,
+};
+use crate::os::Os;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    /// Shown when the recipe starts.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Context profile to use, unless `--profile` is given.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Paths or globs added to the context for this session only, with environment variables
+    /// expanded.
+    #[serde(default)]
+    pub context: Vec<String>,
+    /// Tools to trust, unless `--trust-tools` is given.
+    #[serde(default)]
+    pub trust_tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub trust_all_tools: bool,
+    pub prompts: Vec<RecipeStep>,
+}
+
+impl Recipe {
+    pub async fn load(os: &Os, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = os
+            .fs
+            .read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("Failed to read recipe {}", path.display()))?;
+        let mut recipe: Self =
+            serde_yaml::from_str(&content).wrap_err_with(|| format!("Invalid recipe {}", path.display()))?;
+        if recipe.prompts.is_empty() {
+            bail!("Recipe {} does not define any prompts", path.display());
+        }
+        for path in &mut recipe.context {
+            *path = shellexpand::env_with_context_no_errors(path, |var| os.env.get(var).ok()).into_owned();
+        }
+
+        Ok(recipe)
+    }
+
+    /// Whether running the recipe trusts any tools, so that they run without confirmation.
+    pub fn trusts_tools(&self) -> bool {
+        self.trust_all_tools || self.trust_tools.as_ref().is_some_and(|tools| !tools.is_empty())
+    }
+
+    /// Loads a conversation saved with `/save` as a recipe that sends its prompts again.
+    ///
+    /// With `trust_approved_tools`, the tools that ran in the saved conversation are trusted so
+    /// that the replay doesn't ask to approve them again.
+    pub async fn load_replay(os: &Os, path: impl AsRef<Path>, trust_approved_tools: bool) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = os
+            .fs
+            .read(path)
+            .await
+            .wrap_err_with(|| format!("Failed to read conversation {}", path.display()))?;
+        let loaded = LoadedConversation::parse(os, contents)
+            .await
+            .wrap_err_with(|| format!("Invalid conversation {}", path.display()))?;
+        let recipe = Self::from_history(loaded.conversation.history(), trust_approved_tools);
+        if recipe.prompts.is_empty() {
+            bail!("Conversation {} does not contain any prompts", path.display());
+        }
+
+        Ok(Self {
+            description: Some(format!(
+                "Replaying {} prompts from {}",
+                recipe.prompts.len(),
+                path.display()
+            )),
+            ..recipe
+        })
+    }
+
+    fn from_history(history: &VecDeque<(UserMessage, AssistantMessage)>, trust_approved_tools: bool) -> Self {
+        let prompts = history
+            .iter()
+            .filter_map(|(user, _)| match user.content() {
+                UserMessageContent::Prompt { prompt } => Some(RecipeStep {
+                    prompt: prompt.clone(),
+                    pause: false,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        // A tool use was approved if its results, rather than a cancellation, were sent back.
+        let trust_tools = trust_approved_tools.then(|| {
+            history
+                .iter()
+                .zip(history.iter().skip(1))
+                .filter(|(_, (next_user, _))| matches!(next_user.content(), UserMessageContent::ToolUseResults { .. }))
+                .flat_map(|((_, assistant), _)| assistant.tool_uses().unwrap_or_default())
+                .map(|tool_use| tool_use.name.clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        });
+
+        Self {
+            description: None,
+            profile: None,
+            context: Vec::new(),
+            trust_tools,
+            trust_all_tools: false,
+            prompts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RecipeStepDef")]
+pub struct RecipeStep {
+    pub prompt: String,
+    /// Ask for confirmation before sending this prompt.
+    pub pause: bool,
+}
+
+/// Steps can be written either as a plain string or as a map.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecipeStepDef {
+    Prompt(String),
+    Step {
+        prompt: String,
+        #[serde(default)]
+        pause: bool,
+    },
+}
+
+impl From<RecipeStepDef> for RecipeStep {
+    fn from(value: RecipeStepDef) -> Self {
+        match value {
+            RecipeStepDef::Prompt(prompt) => Self { prompt, pause: false },
+            RecipeStepDef::Step { prompt, pause } => Self { prompt, pause },
+        }
+    }
+}
+
+/// Progress through a running recipe's prompts.
+#[derive(Debug, Clone)]
+pub struct RecipeRun {
+    pub description: Option<String>,
+    steps: VecDeque<RecipeStep>,
+    total: usize,
+}
+
+impl RecipeRun {
+    pub fn new(description: Option<String>, steps: Vec<RecipeStep>) -> Self {
+        Self {
+            description,
+            total: steps.len(),
+            steps: steps.into(),
+        }
+    }
+
+    /// Returns the next step along with its 1-based position.
+    pub fn next_step(&mut self) -> Option<(usize, RecipeStep)> {
+        let step = self.steps.pop_front()?;
+        Some((self.total - self.steps.len(), step))
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPE: &str = "
+description: Draft release notes
+profile: release
+context:
+  - CHANGELOG.md
+trust_tools: [fs_read]
+prompts:
+  - Summarize the commits since the last tag
+  - prompt: Draft release notes
+    pause: true
+";
+
+    #[tokio::test]
+    async fn test_load_recipe() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/release-notes.yaml", RECIPE).await.unwrap();
+
+        let recipe = Recipe::load(&os, "/release-notes.yaml").await.unwrap();
+        assert_eq!(recipe, Recipe {
+            description: Some("Draft release notes".to_string()),
+            profile: Some("release".to_string()),
+            context: vec!["CHANGELOG.md".to_string()],
+            trust_tools: Some(vec!["fs_read".to_string()]),
+            trust_all_tools: false,
+            prompts: vec![
+                RecipeStep {
+                    prompt: "Summarize the commits since the last tag".to_string(),
+                    pause: false,
+                },
+                RecipeStep {
+                    prompt: "Draft release notes".to_string(),
+                    pause: true,
+                },
+            ],
+        });
+
+        os.fs.write("/empty.yaml", "prompts: []").await.unwrap();
+        assert!(Recipe::load(&os, "/empty.yaml").await.is_err());
+        os.fs.write("/typo.yaml", "promts: [hi]").await.unwrap();
+        assert!(Recipe::load(&os, "/typo.yaml").await.is_err());
+
+        os.fs
+            .write(
+                "/env.yaml",
+                "context:\n  - $HOME/notes.md\n  - ${UNSET}/a.md\nprompts: [hi]",
+            )
+            .await
+            .unwrap();
+        let home = os.env.get("HOME").unwrap();
+        let recipe = Recipe::load(&os, "/env.yaml").await.unwrap();
+        assert!(!recipe.trusts_tools());
+        assert!(Recipe::load(&os, "/release-notes.yaml").await.unwrap().trusts_tools());
+        assert_eq!(recipe.context, vec![
+            format!("{home}/notes.md"),
+            "${UNSET}/a.md".to_string()
+        ]);
+    }
+
+    #[test]
+    fn test_replay_from_history() {
+        use crate::api_client::model::ToolResultStatus;
+        use crate::cli::chat::message::{
+            AssistantToolUse,
+            ToolUseResult,
+        };
+
+        let tool_use = |id: &str, name: &str| AssistantToolUse {
+            id: id.to_string(),
+            name: name.to_string(),
+            orig_name: name.to_string(),
+            args: serde_json::json!({}),
+            orig_args: serde_json::json!({}),
+        };
+        let history = VecDeque::from([
+            (
+                UserMessage::new_prompt("list the files".to_string()),
+                AssistantMessage::new_tool_use(None, String::new(), vec![tool_use("1", "fs_read")]),
+            ),
+            (
+                UserMessage::new_tool_use_results(vec![ToolUseResult {
+                    tool_use_id: "1".to_string(),
+                    content: Vec::new(),
+                    status: ToolResultStatus::Success,
+                }]),
+                AssistantMessage::new_tool_use(None, String::new(), vec![tool_use("2", "execute_bash")]),
+            ),
+            (
+                UserMessage::new_cancelled_tool_uses(Some("don't".to_string()), ["2"].into_iter()),
+                AssistantMessage::new_response(None, "ok".to_string()),
+            ),
+            (
+                UserMessage::new_prompt("thanks".to_string()),
+                AssistantMessage::new_response(None, "welcome".to_string()),
+            ),
+        ]);
+
+        let recipe = Recipe::from_history(&history, true);
+        assert_eq!(
+            recipe
+                .prompts
+                .iter()
+                .map(|step| step.prompt.as_str())
+                .collect::<Vec<_>>(),
+            vec!["list the files", "thanks"]
+        );
+        assert_eq!(recipe.trust_tools, Some(vec!["fs_read".to_string()]));
+        assert_eq!(Recipe::from_history(&history, false).trust_tools, None);
+    }
+
+    #[test]
+    fn test_recipe_run() {
+        let step = |prompt: &str| RecipeStep {
+            prompt: prompt.to_string(),
+            pause: false,
+        };
+        let mut run = RecipeRun::new(None, vec![step("a"), step("b"), step("c")]);
+        assert_eq!(run.total(), 3);
+        assert_eq!(run.next_step(), Some((1, step("a"))));
+        assert_eq!(run.next_step(), Some((2, step("b"))));
+        assert_eq!(run.next_step(), Some((3, step("c"))));
+        assert_eq!(run.next_step(), None);
+    }
+}
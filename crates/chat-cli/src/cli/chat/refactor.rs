@@ -0,0 +1,186 @@
+//! Progress through a `/refactor` run. Files are sent to the model in batches, and the run is saved
+//! after each batch starts so `/refactor --resume` can pick it up after an interrupted session.
+
+use std::collections::VecDeque;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+use glob::glob;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::os::Os;
+use crate::util::directories;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefactorRun {
+    /// Directory the refactor was started in, which the files are relative to.
+    pub cwd: PathBuf,
+    pub instruction: String,
+    /// Batch sent to the model that hasn't finished yet.
+    current: Option<Vec<String>>,
+    pending: VecDeque<Vec<String>>,
+    total: usize,
+}
+
+impl RefactorRun {
+    /// Splits the files matching `pattern` under `cwd` into batches of `batch_size`.
+    pub fn new(cwd: &Path, pattern: &str, instruction: String, batch_size: usize) -> Result<Self> {
+        let mut files = Vec::new();
+        for entry in glob(&cwd.join(pattern).to_string_lossy())? {
+            let path = entry?;
+            if path.is_file() {
+                let relative = path.strip_prefix(cwd).unwrap_or(&path);
+                files.push(relative.to_string_lossy().to_string());
+            }
+        }
+        if files.is_empty() {
+            bail!("No files match {pattern}");
+        }
+        files.sort();
+
+        let pending: VecDeque<Vec<String>> = files.chunks(batch_size.max(1)).map(<[String]>::to_vec).collect();
+        Ok(Self {
+            cwd: cwd.to_path_buf(),
+            instruction,
+            current: None,
+            total: pending.len(),
+            pending,
+        })
+    }
+
+    /// Loads the run interrupted in an earlier session, if any.
+    pub async fn load(os: &Os) -> Result<Option<Self>> {
+        let path = directories::chat_refactor_path(os)?;
+        if !os.fs.exists(&path) {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&os.fs.read_to_string(&path).await?)?))
+    }
+
+    pub async fn save(&self, os: &Os) -> Result<()> {
+        let path = directories::chat_refactor_path(os)?;
+        if let Some(parent) = path.parent() {
+            os.fs.create_dir_all(parent).await?;
+        }
+        os.fs.write(&path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// Forgets the saved run.
+    pub async fn remove(os: &Os) -> Result<()> {
+        let path = directories::chat_refactor_path(os)?;
+        if os.fs.exists(&path) {
+            os.fs.remove_file(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// Marks the batch in progress as done.
+    pub fn finish_batch(&mut self) {
+        self.current = None;
+    }
+
+    /// Starts the next batch unless one is already in progress, returning its prompt along with
+    /// its 1-based position, or `None` once every batch is done.
+    pub fn next_batch(&mut self) -> Option<(usize, String)> {
+        if self.current.is_none() {
+            self.current = Some(self.pending.pop_front()?);
+        }
+        let batch = self.current.as_ref()?;
+        Some((self.total - self.pending.len(), self.batch_prompt(batch)))
+    }
+
+    /// Whether every batch has been finished.
+    pub fn is_done(&self) -> bool {
+        self.current.is_none() && self.pending.is_empty()
+    }
+
+    /// 1-based position of the batch that is in progress, or would start next.
+    pub fn batch_number(&self) -> usize {
+        self.total - self.pending.len() + usize::from(self.current.is_none())
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    fn batch_prompt(&self, files: &[String]) -> String {
+        let files = files
+            .iter()
+            .map(|file| format!("- {file}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "Apply this change to each of the files below: {}
+
+{files}
+
+Work through the files one at a time. Read each file with fs_read, then edit it with fs_write's
+str_replace command so every edit can be reviewed as a diff. Only change the files listed. If a file
+needs no change, or was already changed, say so and move on. Finish with a one-line summary per
+file.",
+            self.instruction
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refactor_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs", "notes.md"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert!(RefactorRun::new(dir.path(), "*.py", "x".into(), 2).is_err());
+
+        let mut run = RefactorRun::new(dir.path(), "*.rs", "use anyhow".into(), 2).unwrap();
+        assert_eq!(run.total(), 2);
+        assert_eq!(run.batch_number(), 1);
+
+        let (number, prompt) = run.next_batch().unwrap();
+        assert_eq!(number, 1);
+        assert!(prompt.contains("use anyhow"));
+        assert!(prompt.contains("- a.rs\n- b.rs\n"));
+        // An unfinished batch is sent again, as when resuming.
+        assert_eq!(run.next_batch().unwrap().0, 1);
+
+        run.finish_batch();
+        assert_eq!(run.batch_number(), 2);
+        let (number, prompt) = run.next_batch().unwrap();
+        assert_eq!(number, 2);
+        assert!(prompt.contains("- c.rs\n"));
+        assert!(!run.is_done());
+
+        run.finish_batch();
+        assert!(run.is_done());
+        assert!(run.next_batch().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refactor_save_and_load() {
+        let os = Os::new().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "").unwrap();
+        assert!(RefactorRun::load(&os).await.unwrap().is_none());
+
+        let mut run = RefactorRun::new(dir.path(), "*.rs", "rename foo".into(), 5).unwrap();
+        run.next_batch();
+        run.save(&os).await.unwrap();
+        assert_eq!(RefactorRun::load(&os).await.unwrap(), Some(run));
+
+        RefactorRun::remove(&os).await.unwrap();
+        assert!(RefactorRun::load(&os).await.unwrap().is_none());
+    }
+}
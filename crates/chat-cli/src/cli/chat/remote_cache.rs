@@ -0,0 +1,143 @@
+//! The cache of context rules read from elsewhere than the file system, i.e. URL and `s3://`
+//! rules.
+//!
+//! Each kind of rule is cached in a directory of its own, one file per rule, encrypted when
+//! `storage.encrypt` is on. A cached copy is reused until it is older than the
+//! `chat.urlContextTtlSeconds` setting, and is still used after that if the rule can't be read
+//! again, rather than dropping the rule from the context.
+
+use std::future::Future;
+use std::path::PathBuf;
+
+use eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::encryption;
+
+/// How long the content of a rule is used before it is read again, unless set otherwise.
+const DEFAULT_TTL_SECONDS: i64 = 60 * 60;
+
+/// The content of a rule as stored in the cache.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedRule<T> {
+    pub rule: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub fetched_at: OffsetDateTime,
+    pub content: T,
+}
+
+#[derive(Debug)]
+pub struct RemoteCache {
+    dir: PathBuf,
+}
+
+impl RemoteCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, rule: &str) -> PathBuf {
+        let digest = hex::encode(Sha256::digest(rule.as_bytes()));
+        self.dir.join(format!("{digest}.json"))
+    }
+
+    /// Reads the cached content of `rule`, however old it is.
+    pub async fn cached<T: DeserializeOwned>(&self, os: &Os, rule: &str) -> Option<CachedRule<T>> {
+        let contents = os.fs.read(self.path(rule)).await.ok()?;
+        let contents = encryption::decrypt(os, contents).await.ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    /// Drops the cached content of `rule`, if there is any.
+    pub async fn remove(&self, os: &Os, rule: &str) -> Result<()> {
+        let path = self.path(rule);
+        if os.fs.exists(&path) {
+            os.fs.remove_file(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the content of `rule`, from the cache if the copy there is recent enough, or else
+    /// read with `read` and cached.
+    ///
+    /// With `refresh`, `read` is always called. If it fails, an older cached copy is used.
+    pub async fn fetch<T, F>(&self, os: &Os, rule: &str, refresh: bool, read: impl FnOnce() -> F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T>>,
+    {
+        let cached = self.cached::<T>(os, rule).await;
+        let ttl = os
+            .database
+            .settings
+            .get_int(Setting::ChatUrlContextTtl)
+            .unwrap_or(DEFAULT_TTL_SECONDS);
+        let cached = match cached {
+            Some(cached) if !refresh && (OffsetDateTime::now_utc() - cached.fetched_at).whole_seconds() < ttl => {
+                return Ok(cached.content);
+            },
+            cached => cached,
+        };
+
+        let content = match read().await {
+            Ok(content) => content,
+            Err(err) => match cached {
+                Some(cached) => {
+                    warn!(?err, rule, "failed to read a context rule, using the cached copy");
+                    return Ok(cached.content);
+                },
+                None => return Err(err),
+            },
+        };
+
+        let cached = CachedRule {
+            rule: rule.to_string(),
+            fetched_at: OffsetDateTime::now_utc(),
+            content,
+        };
+        os.fs.create_dir_all(&self.dir).await?;
+        let contents = encryption::encrypt_if_enabled(os, serde_json::to_vec(&cached)?).await?;
+        os.fs.write(self.path(rule), contents).await?;
+        Ok(cached.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::eyre;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let os = Os::new().await.unwrap();
+        let cache = RemoteCache::new(PathBuf::from("/cache"));
+        let rule = "https://example.com/design.md";
+        assert!(cache.cached::<String>(&os, rule).await.is_none());
+
+        let read = |content: &'static str| async move { Ok(content.to_string()) };
+        assert_eq!(cache.fetch(&os, rule, false, || read("one")).await.unwrap(), "one");
+        // A recent copy is used without reading the rule.
+        assert_eq!(cache.fetch(&os, rule, false, || read("two")).await.unwrap(), "one");
+        assert_eq!(cache.fetch(&os, rule, true, || read("two")).await.unwrap(), "two");
+        // Also when reading fails.
+        let failed = || async { Err::<String, _>(eyre!("offline")) };
+        assert_eq!(cache.fetch(&os, rule, true, failed).await.unwrap(), "two");
+
+        cache.remove(&os, rule).await.unwrap();
+        assert!(cache.cached::<String>(&os, rule).await.is_none());
+        assert!(cache.fetch(&os, rule, true, failed).await.is_err());
+    }
+}
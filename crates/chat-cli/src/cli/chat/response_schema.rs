@@ -0,0 +1,345 @@
+//! Structured output contracts for non-interactive runs.
+//!
+//! `q chat --no-interactive --response-schema <file>` asks the model to answer with a JSON
+//! document matching a JSON schema, validates the final response locally, and asks the model to
+//! correct itself when validation fails.
+//!
+//! Only the commonly used subset of JSON schema is validated: `type`, `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+//! `minLength`, `maxLength`, `minimum`, `maximum`, `anyOf`, `oneOf` and `allOf`. Other keywords
+//! are ignored.
+
+use std::path::Path;
+
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+};
+use serde_json::Value;
+
+use crate::os::Os;
+
+#[derive(Debug, Clone)]
+pub struct ResponseSchema {
+    schema: Value,
+    /// How many correction prompts may still be sent before giving up.
+    retries_remaining: usize,
+    /// Whether the instructions were added to a prompt already.
+    instructed: bool,
+}
+
+impl ResponseSchema {
+    pub async fn load(os: &Os, path: impl AsRef<Path>, retries: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let content = os
+            .fs
+            .read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("Failed to read response schema {}", path.display()))?;
+        let schema: Value = serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Response schema {} is not valid JSON", path.display()))?;
+        if !schema.is_object() {
+            bail!("Response schema {} must be a JSON object", path.display());
+        }
+
+        Ok(Self {
+            schema,
+            retries_remaining: retries,
+            instructed: false,
+        })
+    }
+
+    /// Instructions describing the expected output, to append to the first prompt sent, whether
+    /// it was given as an argument, piped or sent by a recipe. Returns [None] once they were taken.
+    pub fn take_instructions(&mut self) -> Option<String> {
+        if std::mem::replace(&mut self.instructed, true) {
+            return None;
+        }
+        Some(format!(
+            "\n\nRespond with only a single JSON document that conforms to the JSON schema below. Do not include any \
+             other text or markdown.\n{}",
+            serde_json::to_string_pretty(&self.schema).unwrap_or_default()
+        ))
+    }
+
+    /// Validates `response`, returning the list of problems found.
+    pub fn validate(&self, response: &str) -> Result<(), Vec<String>> {
+        let value: Value = match serde_json::from_str(extract_json(response)) {
+            Ok(value) => value,
+            Err(err) => return Err(vec![format!("the response is not valid JSON: {err}")]),
+        };
+
+        let mut errors = Vec::new();
+        validate_value(&self.schema, &value, "$", &mut errors);
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Returns a prompt asking the model to fix its response, or [None] if no retries remain.
+    pub fn correction_prompt(&mut self, errors: &[String]) -> Option<String> {
+        if self.retries_remaining == 0 {
+            return None;
+        }
+        self.retries_remaining -= 1;
+
+        Some(format!(
+            "Your previous response did not conform to the required JSON schema:\n{}\n\nRespond again with only \
+             the corrected JSON document.",
+            errors.iter().map(|e| format!("- {e}")).collect::<Vec<_>>().join("\n")
+        ))
+    }
+}
+
+/// Strips a surrounding markdown code fence, since models tend to add one regardless.
+fn extract_json(response: &str) -> &str {
+    let trimmed = response.trim();
+    let Some(inner) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let Some(inner) = inner.strip_suffix("```") else {
+        return trimmed;
+    };
+    // Skip the language tag, e.g. ```json
+    match inner.split_once('\n') {
+        Some((_, body)) => body.trim(),
+        None => inner.trim(),
+    }
+}
+
+fn type_matches(ty: &str, value: &Value) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true`/`false` schemas
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{path}: no value is allowed here"));
+        }
+        return;
+    };
+
+    if let Some(ty) = schema.get("type") {
+        let types: Vec<&str> = match ty {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(tys) => tys.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|ty| type_matches(ty, value)) {
+            errors.push(format!("{path}: expected {}", types.join(" or ")));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!("{path}: must be one of {}", Value::Array(options.clone())));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{path}: must equal {expected}"));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        errors.push(format!("{path}: missing required property \"{key}\""));
+                    }
+                }
+            }
+            for (key, item) in map {
+                let item_path = format!("{path}.{key}");
+                match properties.and_then(|p| p.get(key)) {
+                    Some(item_schema) => validate_value(item_schema, item, &item_path, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{path}: unexpected property \"{key}\""));
+                        },
+                        Some(additional) => validate_value(additional, item, &item_path, errors),
+                        None => (),
+                    },
+                }
+            }
+        },
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{path}: expected at least {min} items"));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if (items.len() as u64) > max {
+                    errors.push(format!("{path}: expected at most {max} items"));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{path}[{i}]"), errors);
+                }
+            }
+        },
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{path}: expected at least {min} characters"));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{path}: expected at most {max} characters"));
+                }
+            }
+        },
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!("{path}: must be at least {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!("{path}: must be at most {max}"));
+                }
+            }
+        },
+        _ => (),
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            validate_value(sub, value, path, errors);
+        }
+    }
+
+    let matching = |subs: &[Value]| {
+        subs.iter()
+            .filter(|sub| {
+                let mut sub_errors = Vec::new();
+                validate_value(sub, value, path, &mut sub_errors);
+                sub_errors.is_empty()
+            })
+            .count()
+    };
+    if let Some(Value::Array(any)) = schema.get("anyOf") {
+        if matching(any) == 0 {
+            errors.push(format!("{path}: does not match any of the allowed schemas"));
+        }
+    }
+    if let Some(Value::Array(one)) = schema.get("oneOf") {
+        if matching(one) != 1 {
+            errors.push(format!("{path}: must match exactly one of the allowed schemas"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(schema: Value, retries: usize) -> ResponseSchema {
+        ResponseSchema {
+            schema,
+            retries_remaining: retries,
+            instructed: false,
+        }
+    }
+
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json(" {\"a\": 1} "), "{\"a\": 1}");
+        assert_eq!(extract_json("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(extract_json("```\n[1]\n```"), "[1]");
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = schema(
+            json!({
+                "type": "object",
+                "required": ["name", "tags"],
+                "additionalProperties": false,
+                "properties": {
+                    "name": { "type": "string", "minLength": 1 },
+                    "count": { "type": "integer", "minimum": 0 },
+                    "tags": { "type": "array", "items": { "enum": ["a", "b"] }, "maxItems": 2 },
+                }
+            }),
+            0,
+        );
+
+        assert!(schema.validate(r#"{"name": "x", "tags": ["a"]}"#).is_ok());
+        assert!(
+            schema
+                .validate("```json\n{\"name\": \"x\", \"count\": 3, \"tags\": []}\n```")
+                .is_ok()
+        );
+
+        let errors = schema
+            .validate(r#"{"name": "", "count": -1, "tags": ["c"], "extra": 1}"#)
+            .unwrap_err();
+        assert_eq!(errors, vec![
+            "$.count: must be at least 0".to_string(),
+            "$: unexpected property \"extra\"".to_string(),
+            "$.name: expected at least 1 characters".to_string(),
+            "$.tags[0]: must be one of [\"a\",\"b\"]".to_string(),
+        ]);
+
+        let errors = schema.validate(r#"{"name": 1}"#).unwrap_err();
+        assert_eq!(errors, vec![
+            "$: missing required property \"tags\"".to_string(),
+            "$.name: expected string".to_string(),
+        ]);
+
+        assert!(schema.validate("not json").unwrap_err()[0].starts_with("the response is not valid JSON"));
+    }
+
+    #[test]
+    fn test_validate_combinators() {
+        let schema = schema(json!({ "anyOf": [{ "type": "string" }, { "type": "integer" }] }), 0);
+        assert!(schema.validate("\"x\"").is_ok());
+        assert!(schema.validate("1").is_ok());
+        assert!(schema.validate("1.5").is_err());
+    }
+
+    #[test]
+    fn test_take_instructions() {
+        let mut schema = schema(json!({ "type": "object" }), 0);
+        assert!(schema.take_instructions().unwrap().contains("\"type\": \"object\""));
+        assert!(schema.take_instructions().is_none());
+    }
+
+    #[test]
+    fn test_correction_prompt_retries() {
+        let mut schema = schema(json!({ "type": "object" }), 1);
+        let errors = vec!["$: expected object".to_string()];
+        assert!(
+            schema
+                .correction_prompt(&errors)
+                .unwrap()
+                .contains("- $: expected object")
+        );
+        assert!(schema.correction_prompt(&errors).is_none());
+    }
+}
@@ -0,0 +1,274 @@
+//! S3 objects used as context rules, e.g. `/context add s3://team-runbooks/oncall/**.md`.
+//!
+//! Objects are read with the `aws` CLI, so rules use the same credentials and profile as the
+//! `use_aws` tool. Like URL rules, the objects of a rule are cached under
+//! [directories::chat_s3_cache_dir], see [RemoteCache], and reused until they are older than the
+//! `chat.urlContextTtlSeconds` setting, and `/context refresh`, or `/context add --refresh`, reads
+//! them again.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use futures::{
+    StreamExt,
+    TryStreamExt,
+    stream,
+};
+use glob::{
+    MatchOptions,
+    Pattern,
+};
+use serde::Deserialize;
+use tokio::process::Command;
+use tracing::warn;
+
+use crate::cli::chat::consts::CONTEXT_FILES_MAX_SIZE;
+use crate::cli::chat::remote_cache::{
+    CachedRule,
+    RemoteCache,
+};
+use crate::os::Os;
+use crate::util::directories;
+
+/// Objects read for a single rule, the rest are skipped. Objects larger than the context are
+/// skipped too.
+const MAX_OBJECTS: usize = 100;
+
+/// Objects of a rule read at the same time.
+const CONCURRENT_READS: usize = 8;
+
+/// How long a single `aws` command may take, e.g. when the credentials prompt for MFA.
+const AWS_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn is_s3_uri(path: &str) -> bool {
+    path.starts_with("s3://")
+}
+
+/// An object as listed by `aws s3api list-objects-v2`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListedObject {
+    key: String,
+    /// In bytes.
+    size: u64,
+}
+
+/// The bucket and keys an `s3://` rule matches.
+#[derive(Debug, PartialEq)]
+struct S3Rule {
+    bucket: String,
+    /// The part of the key before any wildcard, used to list the bucket.
+    prefix: String,
+    /// Keys must match this pattern when the rule has wildcards.
+    pattern: Option<Pattern>,
+}
+
+impl S3Rule {
+    fn parse(rule: &str) -> Result<Self> {
+        let Some(rest) = rule.strip_prefix("s3://") else {
+            bail!("{rule} is not an s3:// URI");
+        };
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            bail!("{rule} has no bucket");
+        }
+
+        let Some(wildcard) = key.find(['*', '?', '[']) else {
+            return Ok(Self {
+                bucket: bucket.to_string(),
+                prefix: key.to_string(),
+                pattern: None,
+            });
+        };
+        let prefix = key[..wildcard].rfind('/').map_or("", |end| &key[..=end]);
+        // `**` is allowed in the middle of a component, e.g. `docs/**.md`, as shorthand for `docs/**/*.md`.
+        let pattern = key.replace("**/", "\0").replace("**", "**/*").replace('\0', "**/");
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            pattern: Some(Pattern::new(&pattern)?),
+        })
+    }
+
+    /// Without wildcards, a rule is a single object, or everything under a prefix ending in `/`.
+    fn matches(&self, key: &str) -> bool {
+        if key.ends_with('/') {
+            return false;
+        }
+        match &self.pattern {
+            Some(pattern) => pattern.matches_with(key, MatchOptions {
+                require_literal_separator: true,
+                ..Default::default()
+            }),
+            None if self.prefix.is_empty() || self.prefix.ends_with('/') => key.starts_with(&self.prefix),
+            None => key == self.prefix,
+        }
+    }
+}
+
+fn cache(os: &Os) -> Result<RemoteCache> {
+    Ok(RemoteCache::new(directories::chat_s3_cache_dir(os)?))
+}
+
+/// Reads the cached objects of `rule` as `(uri, content)` pairs, however old they are.
+pub async fn cached(os: &Os, rule: &str) -> Option<CachedRule<Vec<(String, String)>>> {
+    cache(os).ok()?.cached(os, rule).await
+}
+
+/// Drops the cached objects of `rule`, if there are any.
+pub async fn remove_cached(os: &Os, rule: &str) -> Result<()> {
+    cache(os)?.remove(os, rule).await
+}
+
+/// Returns the objects matching `rule` as `(uri, content)` pairs, from the cache if the copy there
+/// is recent enough.
+///
+/// With `refresh`, the objects are always fetched. If fetching fails, older cached objects are used
+/// rather than dropping the rule from the context.
+pub async fn fetch(os: &Os, rule: &str, refresh: bool) -> Result<Vec<(String, String)>> {
+    cache(os)?.fetch(os, rule, refresh, || fetch_objects(rule)).await
+}
+
+async fn fetch_objects(rule: &str) -> Result<Vec<(String, String)>> {
+    let s3_rule = S3Rule::parse(rule)?;
+    let listing = aws(&[
+        "s3api",
+        "list-objects-v2",
+        "--bucket",
+        &s3_rule.bucket,
+        "--prefix",
+        &s3_rule.prefix,
+        "--query",
+        "Contents[].{Key: Key, Size: Size}",
+        "--output",
+        "json",
+    ])
+    .await?;
+    let listed: Option<Vec<ListedObject>> = serde_json::from_slice(&listing)?;
+    let (objects, too_large): (Vec<ListedObject>, Vec<ListedObject>) = listed
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|object| s3_rule.matches(&object.key))
+        .partition(|object| object.size <= CONTEXT_FILES_MAX_SIZE as u64);
+    for object in &too_large {
+        warn!(
+            rule,
+            key = object.key,
+            size = object.size,
+            "an S3 object is too large for the context, skipping it"
+        );
+    }
+    if objects.is_empty() && !too_large.is_empty() {
+        bail!(
+            "The S3 objects matching {rule} are larger than the context's {} bytes",
+            CONTEXT_FILES_MAX_SIZE
+        );
+    }
+    if objects.is_empty() {
+        bail!("No S3 objects match {rule}");
+    }
+    if objects.len() > MAX_OBJECTS {
+        warn!(
+            rule,
+            count = objects.len(),
+            "an S3 context rule matches too many objects, skipping the rest"
+        );
+    }
+
+    let bucket = &s3_rule.bucket;
+    stream::iter(objects.into_iter().take(MAX_OBJECTS))
+        .map(|object| async move {
+            let uri = format!("s3://{bucket}/{}", object.key);
+            let content = aws(&["s3", "cp", &uri, "-"]).await?;
+            Ok((uri, String::from_utf8_lossy(&content).to_string()))
+        })
+        .buffered(CONCURRENT_READS)
+        .try_collect()
+        .await
+}
+
+/// Runs the `aws` CLI, returning its output.
+async fn aws(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("aws")
+        .args(args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(AWS_TIMEOUT, output)
+        .await
+        .map_err(|_err| eyre!("aws {} timed out after {}s", args[..2].join(" "), AWS_TIMEOUT.as_secs()))?;
+    let output = match output {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            bail!("The aws CLI was not found, install it to use s3:// context rules")
+        },
+        Err(err) => return Err(err.into()),
+    };
+    if !output.status.success() {
+        bail!(
+            "aws {} failed: {}",
+            args[..2].join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_s3_rule() {
+        let rule = S3Rule::parse("s3://runbooks/oncall/**.md").unwrap();
+        assert_eq!(rule.bucket, "runbooks");
+        assert_eq!(rule.prefix, "oncall/");
+        assert!(rule.matches("oncall/paging.md"));
+        assert!(rule.matches("oncall/db/failover.md"));
+        assert!(!rule.matches("oncall/paging.txt"));
+        assert!(!rule.matches("other/paging.md"));
+
+        let rule = S3Rule::parse("s3://runbooks/oncall/*.md").unwrap();
+        assert!(rule.matches("oncall/paging.md"));
+        assert!(!rule.matches("oncall/db/failover.md"));
+
+        let rule = S3Rule::parse("s3://runbooks/oncall/").unwrap();
+        assert!(rule.matches("oncall/db/failover.md"));
+        assert!(!rule.matches("oncall/"));
+
+        let rule = S3Rule::parse("s3://runbooks/oncall/paging.md").unwrap();
+        assert!(rule.matches("oncall/paging.md"));
+        assert!(!rule.matches("oncall/paging.md.bak"));
+
+        assert!(S3Rule::parse("s3:///key").is_err());
+    }
+
+    #[test]
+    fn test_listed_object() {
+        let listed: Vec<ListedObject> = serde_json::from_str(r#"[{"Key": "oncall/paging.md", "Size": 1024}]"#).unwrap();
+        assert_eq!(listed[0].key, "oncall/paging.md");
+        assert_eq!(listed[0].size, 1024);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_uses_cache() {
+        let os = Os::new().await.unwrap();
+        let rule = "s3://runbooks/oncall/**.md";
+        assert!(cached(&os, rule).await.is_none());
+
+        let objects = vec![("s3://runbooks/oncall/paging.md".to_string(), "# Paging".to_string())];
+        let read = || async { Ok(objects.clone()) };
+        cache(&os).unwrap().fetch(&os, rule, false, read).await.unwrap();
+
+        // Recent objects are used without calling the aws CLI.
+        assert_eq!(fetch(&os, rule, false).await.unwrap(), objects);
+
+        remove_cached(&os, rule).await.unwrap();
+        assert!(cached(&os, rule).await.is_none());
+    }
+}
@@ -0,0 +1,423 @@
+//! Progress through a `/security-review` run. The matched files are split into chunks that fit
+//! the context window, each chunk is reviewed by the model in its own turn, and the findings of
+//! every turn are collected into one report that can also be written as SARIF.
+
+use std::collections::{
+    BTreeSet,
+    VecDeque,
+};
+use std::path::PathBuf;
+
+use eyre::{
+    Result,
+    bail,
+};
+use serde::Deserialize;
+use serde_json::{
+    Value,
+    json,
+};
+use tracing::warn;
+
+/// Characters of code sent in a single chunk.
+const MAX_CHUNK_CHARS: usize = 60_000;
+
+/// Chunks a single review may take, larger reviews need a narrower glob.
+const MAX_CHUNKS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Severity {
+    Critical,
+    High,
+    Medium,
+    Low,
+    Info,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Critical => "critical",
+            Self::High => "high",
+            Self::Medium => "medium",
+            Self::Low => "low",
+            Self::Info => "info",
+        }
+    }
+
+    /// The SARIF result level, and the `security-severity` score code scanning tools sort by.
+    fn sarif_level(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Critical => ("error", "9.5"),
+            Self::High => ("error", "8.0"),
+            Self::Medium => ("warning", "5.5"),
+            Self::Low => ("note", "3.0"),
+            Self::Info => ("note", "1.0"),
+        }
+    }
+}
+
+impl TryFrom<String> for Severity {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "critical" => Ok(Self::Critical),
+            "high" => Ok(Self::High),
+            "medium" => Ok(Self::Medium),
+            "low" => Ok(Self::Low),
+            "info" | "informational" => Ok(Self::Info),
+            _ => Err(format!("unknown severity {value}")),
+        }
+    }
+}
+
+/// A problem reported by the model.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: String,
+    #[serde(default)]
+    pub line: Option<usize>,
+    pub title: String,
+    /// Short kebab-case identifier of the kind of problem, e.g. `sql-injection`.
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub recommendation: String,
+}
+
+#[derive(Debug)]
+pub struct SecurityReview {
+    /// What was reviewed, as given to `/security-review`.
+    pub target: String,
+    /// Where to write the SARIF report once the review is done.
+    pub sarif: Option<PathBuf>,
+    pub files: usize,
+    pending: VecDeque<String>,
+    total: usize,
+    findings: Vec<Finding>,
+}
+
+impl SecurityReview {
+    /// Splits the `(path, content)` of the matched files into chunks.
+    pub fn new(target: String, files: &[(String, String)], sarif: Option<PathBuf>) -> Result<Self> {
+        if files.is_empty() {
+            bail!("No files match {target}");
+        }
+        let pending: VecDeque<String> = chunks(files).into();
+        if pending.len() > MAX_CHUNKS {
+            bail!(
+                "{target} is {} chunks of code, review at most {MAX_CHUNKS} at a time with a narrower glob",
+                pending.len()
+            );
+        }
+        Ok(Self {
+            target,
+            sarif,
+            files: files.len(),
+            total: pending.len(),
+            pending,
+            findings: Vec::new(),
+        })
+    }
+
+    /// The prompt of the next chunk, along with its 1-based position.
+    pub fn next_chunk(&mut self) -> Option<(usize, String)> {
+        let code = self.pending.pop_front()?;
+        let number = self.total - self.pending.len();
+        Some((number, chunk_prompt(&self.target, number, self.total, &code)))
+    }
+
+    /// Adds the findings of the model's review of a chunk, returning how many there were.
+    pub fn record(&mut self, response: &str) -> Result<usize> {
+        let findings = parse_findings(response)?;
+        let count = findings.len();
+        self.findings.extend(findings);
+        Ok(count)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The findings, most severe first.
+    pub fn findings(&self) -> Vec<&Finding> {
+        let mut findings: Vec<&Finding> = self.findings.iter().collect();
+        findings.sort_by(|a, b| (a.severity, &a.file, a.line).cmp(&(b.severity, &b.file, b.line)));
+        findings
+    }
+
+    /// The findings as a plain text report.
+    pub fn report(&self) -> String {
+        let findings = self.findings();
+        if findings.is_empty() {
+            return format!("No findings in {} files.\n", self.files);
+        }
+
+        let mut counts: Vec<(Severity, usize)> = Vec::new();
+        for finding in &findings {
+            match counts.last_mut() {
+                Some((severity, count)) if *severity == finding.severity => *count += 1,
+                _ => counts.push((finding.severity, 1)),
+            }
+        }
+        let counts = counts
+            .iter()
+            .map(|(severity, count)| format!("{count} {}", severity.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut report = format!("{} findings ({counts}) in {} files:\n\n", findings.len(), self.files);
+        for finding in findings {
+            let location = match finding.line {
+                Some(line) => format!("{}:{line}", finding.file),
+                None => finding.file.clone(),
+            };
+            report.push_str(&format!(
+                "{:<9}{location}  {}\n",
+                finding.severity.name().to_uppercase(),
+                finding.title
+            ));
+            if !finding.recommendation.is_empty() {
+                report.push_str(&format!("         {}\n", finding.recommendation));
+            }
+        }
+        report
+    }
+
+    /// The findings as a SARIF 2.1.0 log.
+    pub fn sarif(&self) -> Value {
+        let findings = self.findings();
+        let rules: BTreeSet<&str> = findings.iter().map(|finding| rule_id(finding)).collect();
+        let results: Vec<Value> = findings
+            .iter()
+            .map(|finding| {
+                let (level, score) = finding.severity.sarif_level();
+                let mut location = json!({ "artifactLocation": { "uri": finding.file } });
+                if let Some(line) = finding.line {
+                    location["region"] = json!({ "startLine": line.max(1) });
+                }
+                let message = match finding.recommendation.is_empty() {
+                    true => finding.title.clone(),
+                    false => format!("{}. {}", finding.title.trim_end_matches('.'), finding.recommendation),
+                };
+                json!({
+                    "ruleId": rule_id(finding),
+                    "level": level,
+                    "message": { "text": message },
+                    "locations": [{ "physicalLocation": location }],
+                    "properties": { "security-severity": score },
+                })
+            })
+            .collect();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "Amazon Q Developer CLI security review",
+                        "informationUri": "https://github.com/aws/amazon-q-developer-cli",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules
+                            .iter()
+                            .map(|id| json!({ "id": id, "shortDescription": { "text": id } }))
+                            .collect::<Vec<_>>(),
+                    }
+                },
+                "results": results,
+            }],
+        })
+    }
+}
+
+fn rule_id(finding: &Finding) -> &str {
+    finding.category.as_deref().unwrap_or("security")
+}
+
+/// Groups the files into chunks of about [MAX_CHUNK_CHARS], with numbered lines. Files too large
+/// for the room left in a chunk are split across chunks by lines.
+fn chunks(files: &[(String, String)]) -> Vec<String> {
+    // Room kept for the header and fences of each file.
+    const HEADER_CHARS: usize = 200;
+
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    for (path, content) in files {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut start = 0;
+        loop {
+            let whole_file: usize = lines[start..].iter().map(|line| line.len() + 8).sum();
+            let mut room = MAX_CHUNK_CHARS.saturating_sub(chunk.len() + HEADER_CHARS);
+            // Start a new chunk rather than splitting a file that would fit in one, or leaving a
+            // sliver of a file at the end of a chunk.
+            if !chunk.is_empty() && (whole_file > room && (whole_file < MAX_CHUNK_CHARS || room < MAX_CHUNK_CHARS / 4))
+            {
+                chunks.push(std::mem::take(&mut chunk));
+                room = MAX_CHUNK_CHARS - HEADER_CHARS;
+            }
+
+            let mut end = start;
+            let mut part = String::new();
+            while end < lines.len() {
+                let line = format!("{:>5} | {}\n", end + 1, lines[end]);
+                if end > start && part.len() + line.len() > room {
+                    break;
+                }
+                part.push_str(&line);
+                end += 1;
+            }
+            match (start, end == lines.len()) {
+                (0, true) => chunk.push_str(&format!("File: {path}\n")),
+                _ => chunk.push_str(&format!("File: {path} (lines {}-{end})\n", start + 1)),
+            }
+            chunk.push_str(&format!("```\n{part}```\n\n"));
+            if end == lines.len() {
+                break;
+            }
+            chunks.push(std::mem::take(&mut chunk));
+            start = end;
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+fn chunk_prompt(target: &str, number: usize, total: usize, code: &str) -> String {
+    format!(
+        "Review the code below for security vulnerabilities. This is part {number} of {total} of a security review \
+of {target}. Look for problems such as injection, hard-coded secrets, unsafe deserialization, path traversal,
+missing authentication or authorization checks, weak cryptography, unsafe memory handling and leaking
+sensitive data. Only report problems with a plausible path to harm, not style issues. Don't use any
+tools, the code is all below, with line numbers on the left.
+
+Answer with a short summary, then every finding in a JSON block like this one, with an empty list if
+there are none:
+```json
+{{\"findings\": [{{\"severity\": \"critical|high|medium|low|info\", \"file\": \"src/auth.rs\", \"line\": 42, \"title\": \"SQL built from user input\", \"category\": \"sql-injection\", \"recommendation\": \"Use a parameterized query.\"}}]}}
+```
+
+{code}"
+    )
+}
+
+/// Reads the findings from the last JSON block of a response, or from the response itself if it
+/// is only JSON. Findings that don't have the expected fields are skipped.
+fn parse_findings(response: &str) -> Result<Vec<Finding>> {
+    let json = match response.rfind("```json") {
+        Some(start) => {
+            let block = &response[start + "```json".len()..];
+            block.find("```").map_or(block, |end| &block[..end])
+        },
+        None => {
+            let start = response.find(['{', '[']).unwrap_or(0);
+            let end = response.rfind(['}', ']']).map_or(response.len(), |end| end + 1);
+            response.get(start..end).unwrap_or_default()
+        },
+    };
+    let value: Value = match serde_json::from_str(json.trim()) {
+        Ok(value) => value,
+        Err(err) => bail!("The review had no findings block: {err}"),
+    };
+    let findings = match value {
+        Value::Array(findings) => findings,
+        Value::Object(mut object) => match object.remove("findings") {
+            Some(Value::Array(findings)) => findings,
+            _ => bail!("The review's findings block has no findings list"),
+        },
+        _ => bail!("The review's findings block has no findings list"),
+    };
+    Ok(findings
+        .into_iter()
+        .filter_map(|finding| match serde_json::from_value(finding) {
+            Ok(finding) => Some(finding),
+            Err(err) => {
+                warn!(?err, "skipping a malformed security finding");
+                None
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks() {
+        let small = ("src/a.rs".to_string(), "fn a() {}\n".to_string());
+        let chunks = chunks(&[small.clone(), ("src/b.rs".to_string(), "fn b() {}".to_string())]);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].contains("File: src/a.rs\n```\n    1 | fn a() {}\n```\n\nFile: src/b.rs\n"));
+
+        let line = "x".repeat(99);
+        let large = ("src/big.rs".to_string(), vec![line.as_str(); 1000].join("\n"));
+        let chunks = super::chunks(&[small, large]);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= MAX_CHUNK_CHARS));
+        // The large file starts in the room left after the small one.
+        assert!(chunks[0].contains("File: src/a.rs\n"));
+        assert!(chunks[0].contains("File: src/big.rs (lines 1-"));
+        assert!(chunks.last().unwrap().contains("-1000)\n"));
+    }
+
+    #[test]
+    fn test_parse_findings() {
+        let response = r#"One problem found.
+```json
+{"findings": [
+    {"severity": "High", "file": "src/db.rs", "line": 12, "title": "SQL built from user input", "category": "sql-injection", "recommendation": "Use a parameterized query."},
+    {"severity": "medium", "file": "src/auth.rs", "title": "Token compared with =="},
+    {"severity": "bogus", "file": "src/x.rs", "title": "skipped"}
+]}
+```"#;
+        let findings = parse_findings(response).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(findings[0].line, Some(12));
+        assert_eq!(findings[1].line, None);
+
+        assert_eq!(
+            parse_findings("No issues.\n```json\n{\"findings\": []}\n```").unwrap(),
+            []
+        );
+        assert!(parse_findings("I couldn't review this.").is_err());
+    }
+
+    #[test]
+    fn test_report_and_sarif() {
+        let files = vec![("src/db.rs".to_string(), "query()".to_string())];
+        let mut review = SecurityReview::new("src/**/*.rs".to_string(), &files, None).unwrap();
+        let (number, prompt) = review.next_chunk().unwrap();
+        assert_eq!(number, 1);
+        assert!(prompt.contains("part 1 of 1 of a security review of src/**/*.rs"));
+        assert!(review.is_done());
+
+        review
+            .record(r#"{"findings": [
+                {"severity": "low", "file": "src/db.rs", "line": 3, "title": "Verbose errors"},
+                {"severity": "critical", "file": "src/db.rs", "line": 1, "title": "SQL injection", "category": "sql-injection", "recommendation": "Bind parameters."}
+            ]}"#)
+            .unwrap();
+        let report = review.report();
+        assert!(report.starts_with("2 findings (1 critical, 1 low) in 1 files:\n\n"));
+        assert!(report.contains("CRITICAL src/db.rs:1  SQL injection\n         Bind parameters.\n"));
+
+        let sarif = review.sarif();
+        let results = &sarif["runs"][0]["results"];
+        assert_eq!(results[0]["ruleId"], "sql-injection");
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[0]["message"]["text"], "SQL injection. Bind parameters.");
+        assert_eq!(results[0]["locations"][0]["physicalLocation"]["region"]["startLine"], 1);
+        assert_eq!(results[1]["ruleId"], "security");
+        assert_eq!(sarif["runs"][0]["tool"]["driver"]["rules"].as_array().unwrap().len(), 2);
+    }
+}
@@ -33,6 +33,46 @@ pub fn select_profile_with_skim(os: &Os, context_manager: &ContextManager) -> Re
         .map(|selected| selected.and_then(|s| s.into_iter().next()))
 }
 
+/// An item of a selector with its own preview, such as a profile and what it adds to the context.
+struct PreviewItem {
+    text: String,
+    output: String,
+    preview: String,
+}
+
+impl SkimItem for PreviewItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.text)
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.output)
+    }
+
+    fn preview(&self, _context: PreviewContext<'_>) -> ItemPreview {
+        ItemPreview::AnsiText(self.preview.clone())
+    }
+}
+
+/// Select one of `profiles`, given as their name, label and preview, showing the preview of the
+/// highlighted one beside the list.
+pub fn select_profile_with_preview(profiles: Vec<(String, String, String)>) -> Result<Option<String>> {
+    let mut options = create_skim_options("Switch to profile: ", false)?;
+    // An empty preview command shows the items' own previews.
+    options.preview = Some(String::new());
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    for (output, text, preview) in profiles {
+        let _ = tx.send(Arc::new(PreviewItem { text, output, preview }));
+    }
+    drop(tx);
+
+    match run_skim_with_options(&options, rx)? {
+        Some(items) => Ok(extract_selections(items).into_iter().next()),
+        None => Ok(None),
+    }
+}
+
 pub struct SkimCommandSelector {
     os: Os,
     context_manager: Arc<ContextManager>,
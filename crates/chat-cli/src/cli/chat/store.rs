@@ -0,0 +1,534 @@
+//! The conversation store: a directory of zstd-compressed conversations written by the CLI itself,
+//! with an index of their sizes and dates. Conversations are also encrypted when `storage.encrypt`
+//! is on, see [encryption].
+//!
+//! The index (`index.json`) is only a cache. Files missing from it - for example because two
+//! sessions updated it at the same time - are picked up again from the directory listing, so
+//! nothing in the store escapes `q history gc`.
+
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+};
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::os::Os;
+use crate::util::directories::chat_sessions_dir;
+use crate::util::encryption;
+
+const INDEX_FILE: &str = "index.json";
+const EXTENSION: &str = ".json.zst";
+const COMPRESSION_LEVEL: i32 = 3;
+/// The four magic bytes every zstd frame starts with.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub fn compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    Ok(zstd::encode_all(bytes, COMPRESSION_LEVEL)?)
+}
+
+/// Decompresses `bytes` if they are zstd compressed, otherwise returns them unchanged.
+pub fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    match bytes.starts_with(&ZSTD_MAGIC) {
+        true => Ok(zstd::decode_all(bytes.as_slice())?),
+        false => Ok(bytes),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreEntry {
+    /// Compressed size on disk, in bytes.
+    pub size: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    /// Directory the session was started in.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// The process writing to this entry, while its session is running.
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Tags of the stored conversation, copied here so sessions can be listed by tag without
+    /// reading them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The start of the first prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Estimated size of the conversation history, in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<usize>,
+}
+
+/// What [ConversationStore::write] records in the index alongside a conversation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntryMetadata {
+    pub cwd: Option<PathBuf>,
+    pub pid: Option<u32>,
+    pub tags: Vec<String>,
+    pub title: Option<String>,
+    pub model: Option<String>,
+    pub tokens: Option<usize>,
+}
+
+impl StoreEntry {
+    /// Whether the session that wrote this entry is still running, in this or another process.
+    pub fn is_running(&self, os: &Os) -> bool {
+        self.pid
+            .is_some_and(|pid| pid == std::process::id() || os.sysinfo.is_pid_running(pid))
+    }
+}
+
+/// The part of a stored conversation that is also kept in its index entry.
+#[derive(Debug, Default, Deserialize)]
+struct StoredMetadata {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default)]
+    entries: BTreeMap<String, StoreEntry>,
+}
+
+/// Limits applied by [ConversationStore::gc]. Entries are removed oldest first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Maximum number of entries to keep.
+    pub keep: Option<usize>,
+    /// Maximum total size of the store, in bytes.
+    pub max_size: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConversationStore {
+    dir: PathBuf,
+}
+
+impl ConversationStore {
+    pub fn new(os: &Os) -> Result<Self> {
+        Ok(Self {
+            dir: chat_sessions_dir(os)?,
+        })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}{EXTENSION}"))
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    async fn read_index(&self, os: &Os) -> Index {
+        match os.fs.read_to_string(self.index_path()).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                warn!(?err, "conversation store index is invalid, rebuilding it");
+                Index::default()
+            }),
+            Err(_) => Index::default(),
+        }
+    }
+
+    async fn write_index(&self, os: &Os, index: &Index) -> Result<()> {
+        let path = self.index_path();
+        let tmp_path = self.dir.join(format!("{INDEX_FILE}.tmp"));
+        os.fs.write(&tmp_path, serde_json::to_vec_pretty(index)?).await?;
+        os.fs.rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+
+    /// Compresses and writes `value` under `id`, creating or updating its index entry.
+    pub async fn write(
+        &self,
+        os: &Os,
+        id: &str,
+        value: &impl Serialize,
+        metadata: EntryMetadata,
+    ) -> Result<StoreEntry> {
+        if !os.fs.exists(&self.dir) {
+            os.fs.create_dir_all(&self.dir).await?;
+        }
+
+        let contents = encryption::encrypt_if_enabled(os, compress(&serde_json::to_vec(value)?)?).await?;
+        let path = self.path(id);
+        // Write to a temporary file first so a crash mid-write doesn't corrupt the previous version.
+        let tmp_path = path.with_extension("zst.tmp");
+        os.fs.write(&tmp_path, &contents).await?;
+        os.fs.rename(&tmp_path, &path).await?;
+
+        let mut index = self.read_index(os).await;
+        let now = OffsetDateTime::now_utc();
+        let entry = StoreEntry {
+            size: contents.len() as u64,
+            created_at: index.entries.get(id).map_or(now, |entry| entry.created_at),
+            updated_at: now,
+            cwd: metadata.cwd,
+            pid: metadata.pid,
+            tags: metadata.tags,
+            title: metadata.title,
+            model: metadata.model,
+            tokens: metadata.tokens,
+        };
+        index.entries.insert(id.to_string(), entry.clone());
+        self.write_index(os, &index).await?;
+        Ok(entry)
+    }
+
+    pub async fn read<T: DeserializeOwned>(&self, os: &Os, id: &str) -> Result<T> {
+        let path = self.path(id);
+        let bytes = os
+            .fs
+            .read(&path)
+            .await
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        let bytes = encryption::decrypt(os, bytes)
+            .await
+            .wrap_err_with(|| format!("Failed to decrypt {}", path.display()))?;
+        serde_json::from_slice(&decompress(bytes)?).wrap_err_with(|| format!("Invalid conversation {}", path.display()))
+    }
+
+    /// Clears the process id from an entry, marking its session as no longer running.
+    pub async fn release(&self, os: &Os, id: &str) -> Result<()> {
+        let mut index = self.read_index(os).await;
+        if let Some(entry) = index.entries.get_mut(id) {
+            if entry.pid.take().is_some() {
+                self.write_index(os, &index).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn remove(&self, os: &Os, id: &str) -> Result<()> {
+        let path = self.path(id);
+        if os.fs.exists(&path) {
+            os.fs.remove_file(&path).await?;
+        }
+        let mut index = self.read_index(os).await;
+        if index.entries.remove(id).is_some() {
+            self.write_index(os, &index).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns every entry in the store, reconciling the index with the files on disk.
+    pub async fn entries(&self, os: &Os) -> Result<BTreeMap<String, StoreEntry>> {
+        let mut index = self.read_index(os).await;
+        let Ok(mut read_dir) = os.fs.read_dir(&self.dir).await else {
+            return Ok(BTreeMap::new());
+        };
+
+        let mut on_disk = BTreeMap::new();
+        while let Some(entry) = read_dir.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(id) = file_name.strip_suffix(EXTENSION) {
+                on_disk.insert(id.to_string(), self.dir.join(&file_name));
+            }
+        }
+
+        let mut changed = false;
+        index.entries.retain(|id, _| {
+            let exists = on_disk.contains_key(id);
+            changed |= !exists;
+            exists
+        });
+        for (id, path) in on_disk {
+            if index.entries.contains_key(&id) {
+                continue;
+            }
+            let metadata = os.fs.symlink_metadata(&path).await?;
+            let modified = metadata
+                .modified()
+                .map_or(OffsetDateTime::now_utc(), OffsetDateTime::from);
+            let tags = match self.read::<StoredMetadata>(os, &id).await {
+                Ok(stored) => stored.tags,
+                Err(err) => {
+                    warn!(?err, id, "failed to read the tags of a stored conversation");
+                    Vec::new()
+                },
+            };
+            index.entries.insert(id, StoreEntry {
+                size: metadata.len(),
+                created_at: modified,
+                updated_at: modified,
+                cwd: None,
+                pid: None,
+                tags,
+                title: None,
+                model: None,
+                tokens: None,
+            });
+            changed = true;
+        }
+
+        if changed {
+            if let Err(err) = self.write_index(os, &index).await {
+                warn!(?err, "failed to update the conversation store index");
+            }
+        }
+        Ok(index.entries)
+    }
+
+    /// Finds an entry by its id, or by a prefix matching exactly one id.
+    pub async fn find(&self, os: &Os, id: &str) -> Result<(String, StoreEntry)> {
+        let mut entries = self.entries(os).await?;
+        if let Some(entry) = entries.remove(id) {
+            return Ok((id.to_string(), entry));
+        }
+        let mut matches = entries.into_iter().filter(|(candidate, _)| candidate.starts_with(id));
+        match (matches.next(), matches.next()) {
+            (Some(found), None) => Ok(found),
+            (None, _) => bail!("No stored session with id {id}"),
+            (Some(_), Some(_)) => bail!("More than one stored session starts with {id}"),
+        }
+    }
+
+    /// Removes the oldest entries until the store satisfies `policy`, returning the removed ids.
+    ///
+    /// Entries belonging to a session that is still running are never removed, but still count
+    /// towards the limits.
+    pub async fn gc(&self, os: &Os, policy: RetentionPolicy) -> Result<Vec<String>> {
+        let (running, mut entries): (Vec<_>, Vec<_>) = self
+            .entries(os)
+            .await?
+            .into_iter()
+            .partition(|(_, entry)| entry.is_running(os));
+        // Newest first
+        entries.sort_by(|(_, a), (_, b)| b.updated_at.cmp(&a.updated_at));
+
+        let mut kept = running.len();
+        let mut kept_size = running.iter().map(|(_, entry)| entry.size).sum::<u64>();
+        let mut size_exceeded = false;
+        let mut removed = Vec::new();
+        for (id, entry) in entries {
+            size_exceeded |= policy
+                .max_size
+                .is_some_and(|max_size| kept_size + entry.size > max_size);
+            let over_count = policy.keep.is_some_and(|keep| kept >= keep);
+            if !(over_count || size_exceeded) {
+                kept += 1;
+                kept_size += entry.size;
+                continue;
+            }
+
+            self.remove(os, &id).await?;
+            removed.push(id);
+        }
+        Ok(removed)
+    }
+}
+
+/// Every tag used by an entry of the index at `index_path`, read synchronously for completion.
+pub fn read_tags(index_path: &Path) -> BTreeSet<String> {
+    std::fs::read_to_string(index_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Index>(&content).ok())
+        .map(|index| index.entries.into_values().flat_map(|entry| entry.tags).collect())
+        .unwrap_or_default()
+}
+
+/// Parses a size such as `200MB`, `1.5GiB` or `4096`. Plain numbers are bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = match number.parse() {
+        Ok(number) => number,
+        Err(_) => bail!("Invalid size '{s}'"),
+    };
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "kib" => 1 << 10,
+        "m" | "mb" => 1000 * 1000,
+        "mib" => 1 << 20,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "gib" => 1 << 30,
+        other => bail!("Unknown size unit '{other}' in '{s}'"),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Formats a number of bytes for display, e.g. `1.2 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{
+        Value,
+        json,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let data = b"{\"hello\": \"world\"}".repeat(100);
+        let compressed = compress(&data).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(compressed).unwrap(), data);
+        // Uncompressed data is passed through.
+        assert_eq!(decompress(data.clone()).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_store() {
+        let os = Os::new().await.unwrap();
+        let store = ConversationStore::new(&os).unwrap();
+        assert!(store.entries(&os).await.unwrap().is_empty());
+
+        let entry = store
+            .write(&os, "a", &json!({ "n": 1 }), EntryMetadata {
+                cwd: Some("/project".into()),
+                pid: Some(1),
+                tags: vec!["infra".to_string()],
+                title: Some("Fix the build".to_string()),
+                model: Some("claude".to_string()),
+                tokens: Some(120),
+            })
+            .await
+            .unwrap();
+        assert_eq!(store.read::<Value>(&os, "a").await.unwrap(), json!({ "n": 1 }));
+        assert_eq!(store.entries(&os).await.unwrap().get("a"), Some(&entry));
+
+        store.release(&os, "a").await.unwrap();
+        assert_eq!(store.entries(&os).await.unwrap()["a"].pid, None);
+
+        // Files missing from the index are still found.
+        os.fs
+            .write(store.path("orphan"), compress(br#"{"tags": ["docs"]}"#).unwrap())
+            .await
+            .unwrap();
+        let entries = store.entries(&os).await.unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), vec!["a", "orphan"]);
+        assert_eq!(entries["orphan"].tags, vec!["docs".to_string()]);
+        assert_eq!(
+            read_tags(&os.fs.chroot_path(store.index_path())),
+            BTreeSet::from(["docs".to_string(), "infra".to_string()])
+        );
+
+        assert_eq!(store.find(&os, "orp").await.unwrap().0, "orphan");
+        assert!(store.find(&os, "b").await.is_err());
+
+        store.remove(&os, "a").await.unwrap();
+        assert_eq!(store.entries(&os).await.unwrap().keys().collect::<Vec<_>>(), vec![
+            "orphan"
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(crate::database::settings::Setting::StorageEncrypt, "on")
+            .await
+            .unwrap();
+        let store = ConversationStore::new(&os).unwrap();
+        store
+            .write(&os, "a", &json!({ "n": 1 }), EntryMetadata::default())
+            .await
+            .unwrap();
+        assert!(encryption::is_encrypted(&os.fs.read(store.path("a")).await.unwrap()));
+        assert_eq!(store.read::<Value>(&os, "a").await.unwrap(), json!({ "n": 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_gc() {
+        let os = Os::new().await.unwrap();
+        let store = ConversationStore::new(&os).unwrap();
+        for id in ["1", "2", "3", "4"] {
+            store
+                .write(&os, id, &json!({ "id": id }), EntryMetadata::default())
+                .await
+                .unwrap();
+        }
+        // A running session is never removed.
+        store
+            .write(&os, "0", &json!({ "id": "0" }), EntryMetadata {
+                pid: Some(4242),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        os.sysinfo.add_running_pids(&[4242]);
+        let mut index = store.read_index(&os).await;
+        for (i, entry) in index.entries.values_mut().enumerate() {
+            entry.updated_at = OffsetDateTime::UNIX_EPOCH + time::Duration::days(i as i64);
+        }
+        store.write_index(&os, &index).await.unwrap();
+
+        let removed = store
+            .gc(&os, RetentionPolicy {
+                keep: Some(3),
+                max_size: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed, vec!["2".to_string(), "1".to_string()]);
+
+        let entries = store.entries(&os).await.unwrap();
+        let removed = store
+            .gc(&os, RetentionPolicy {
+                keep: None,
+                max_size: Some(entries["0"].size + entries["4"].size),
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed, vec!["3".to_string()]);
+        assert_eq!(store.entries(&os).await.unwrap().keys().collect::<Vec<_>>(), vec![
+            "0", "4"
+        ]);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("200MB").unwrap(), 200_000_000);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_size("10k").unwrap(), 10_000);
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1_500), "1.5 KB");
+        assert_eq!(format_size(200_000_000), "200.0 MB");
+    }
+}
@@ -79,6 +79,8 @@ use crate::cli::chat::tools::fs_read::FsRead;
 use crate::cli::chat::tools::fs_write::FsWrite;
 use crate::cli::chat::tools::gh_issue::GhIssue;
 use crate::cli::chat::tools::knowledge::Knowledge;
+use crate::cli::chat::tools::read_artifact::ReadArtifact;
+use crate::cli::chat::tools::summarize_output::SummarizeOutput;
 use crate::cli::chat::tools::thinking::Thinking;
 use crate::cli::chat::tools::use_aws::UseAws;
 use crate::cli::chat::tools::{
@@ -976,6 +978,10 @@ impl ToolManager {
             "report_issue" => Tool::GhIssue(serde_json::from_value::<GhIssue>(value.args).map_err(map_err)?),
             "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(value.args).map_err(map_err)?),
             "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(value.args).map_err(map_err)?),
+            "summarize_output" => {
+                Tool::SummarizeOutput(serde_json::from_value::<SummarizeOutput>(value.args).map_err(map_err)?)
+            },
+            "read_artifact" => Tool::ReadArtifact(serde_json::from_value::<ReadArtifact>(value.args).map_err(map_err)?),
             // Note that this name is namespaced with server_name{DELIMITER}tool_name
             name => {
                 // Note: tn_map also has tools that underwent no transformation. In otherwords, if
@@ -0,0 +1,137 @@
+//! Named sets of permissions for the built-in tools, applied with `/tools preset` and saved with
+//! the profile, so that a session starting with the profile, or switching to it, applies them
+//! again.
+//!
+//! - `strict`: tools that can change anything ask for confirmation of every use, even of read-only
+//!   commands
+//! - `balanced`: the tools' defaults, e.g. only read-only commands run without confirmation
+//! - `permissive`: the built-in tools run without confirmation
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::cli::chat::tools::ToolPermissions;
+
+#[cfg(not(windows))]
+const EXECUTE_TOOL: &str = "execute_bash";
+#[cfg(windows)]
+const EXECUTE_TOOL: &str = "execute_cmd";
+
+/// The built-in tools whose permissions presets set. The others never ask for confirmation.
+fn preset_tools() -> [&'static str; 4] {
+    ["fs_read", "fs_write", EXECUTE_TOOL, "use_aws"]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolPreset {
+    /// Ask for confirmation of every use of the tools that can change anything
+    Strict,
+    /// The tools' defaults, only read-only commands run without confirmation
+    Balanced,
+    /// Run the built-in tools without confirmation
+    Permissive,
+}
+
+/// The permission of a tool, as set by a preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolLevel {
+    Trusted,
+    /// The tool's own default for each input.
+    Default,
+    AskEveryUse,
+}
+
+impl std::fmt::Display for ToolLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Trusted => "trusted",
+            Self::Default => "tool default",
+            Self::AskEveryUse => "ask every use",
+        })
+    }
+}
+
+impl std::fmt::Display for ToolPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Strict => "strict",
+            Self::Balanced => "balanced",
+            Self::Permissive => "permissive",
+        })
+    }
+}
+
+impl ToolPreset {
+    /// The level the preset sets for a tool of [preset_tools].
+    fn level(&self, tool_name: &str) -> ToolLevel {
+        match (self, tool_name) {
+            (Self::Strict, "fs_read") => ToolLevel::Default,
+            (Self::Strict, _) => ToolLevel::AskEveryUse,
+            (Self::Balanced, _) => ToolLevel::Default,
+            (Self::Permissive, _) => ToolLevel::Trusted,
+        }
+    }
+
+    /// The tools whose level the preset changes, with their current level and the preset's.
+    pub fn changes(&self, permissions: &mut ToolPermissions) -> Vec<(&'static str, ToolLevel, ToolLevel)> {
+        preset_tools()
+            .into_iter()
+            .map(|tool_name| (tool_name, current_level(permissions, tool_name), self.level(tool_name)))
+            .filter(|(_, current, level)| current != level)
+            .collect()
+    }
+
+    /// Sets the permissions of the tools to the preset's. The permissions of other tools are kept.
+    pub fn apply(&self, permissions: &mut ToolPermissions) {
+        for (tool_name, _, level) in self.changes(permissions) {
+            match level {
+                ToolLevel::Trusted => permissions.trust_tool(tool_name),
+                ToolLevel::Default => permissions.reset_tool(tool_name),
+                ToolLevel::AskEveryUse => permissions.ask_every_use(tool_name),
+            }
+        }
+    }
+}
+
+fn current_level(permissions: &mut ToolPermissions, tool_name: &str) -> ToolLevel {
+    if permissions.trust_all || (permissions.has(tool_name) && permissions.is_trusted(tool_name)) {
+        ToolLevel::Trusted
+    } else if permissions.always_ask.contains(tool_name) {
+        ToolLevel::AskEveryUse
+    } else {
+        ToolLevel::Default
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut permissions = ToolPermissions::new(0);
+        assert!(ToolPreset::Balanced.changes(&mut permissions).is_empty());
+        assert_eq!(ToolPreset::Strict.changes(&mut permissions), vec![
+            ("fs_write", ToolLevel::Default, ToolLevel::AskEveryUse),
+            (EXECUTE_TOOL, ToolLevel::Default, ToolLevel::AskEveryUse),
+            ("use_aws", ToolLevel::Default, ToolLevel::AskEveryUse),
+        ]);
+
+        ToolPreset::Strict.apply(&mut permissions);
+        assert!(ToolPreset::Strict.changes(&mut permissions).is_empty());
+        assert!(permissions.always_ask.contains(EXECUTE_TOOL));
+
+        // Other tools keep their permissions.
+        permissions.trust_tool("@git/status");
+        ToolPreset::Permissive.apply(&mut permissions);
+        assert!(permissions.always_ask.is_empty());
+        assert!(permissions.is_trusted("fs_write"));
+        assert!(permissions.is_trusted("@git/status"));
+
+        ToolPreset::Balanced.apply(&mut permissions);
+        assert_eq!(permissions.modified_tools(), vec!["@git/status"]);
+    }
+}
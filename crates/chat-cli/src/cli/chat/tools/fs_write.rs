@@ -73,6 +73,16 @@ pub enum FsWrite {
 }
 
 impl FsWrite {
+    /// The path the tool writes to, as given by the model.
+    pub fn path(&self) -> &str {
+        match self {
+            FsWrite::Create { path, .. }
+            | FsWrite::StrReplace { path, .. }
+            | FsWrite::Insert { path, .. }
+            | FsWrite::Append { path, .. } => path,
+        }
+    }
+
     pub async fn invoke(&self, os: &Os, output: &mut impl Write) -> Result<InvokeOutput> {
         let cwd = os.env.current_dir()?;
         match self {
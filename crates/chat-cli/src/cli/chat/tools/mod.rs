@@ -4,6 +4,8 @@ pub mod fs_read;
 pub mod fs_write;
 pub mod gh_issue;
 pub mod knowledge;
+pub mod read_artifact;
+pub mod summarize_output;
 pub mod thinking;
 pub mod use_aws;
 
@@ -30,10 +32,12 @@ use fs_read::FsRead;
 use fs_write::FsWrite;
 use gh_issue::GhIssue;
 use knowledge::Knowledge;
+use read_artifact::ReadArtifact;
 use serde::{
     Deserialize,
     Serialize,
 };
+use summarize_output::SummarizeOutput;
 use thinking::Thinking;
 use use_aws::UseAws;
 
@@ -53,6 +57,8 @@ pub enum Tool {
     GhIssue(GhIssue),
     Knowledge(Knowledge),
     Thinking(Thinking),
+    SummarizeOutput(SummarizeOutput),
+    ReadArtifact(ReadArtifact),
 }
 
 impl Tool {
@@ -70,6 +76,8 @@ impl Tool {
             Tool::GhIssue(_) => "gh_issue",
             Tool::Knowledge(_) => "knowledge",
             Tool::Thinking(_) => "thinking (prerelease)",
+            Tool::SummarizeOutput(_) => "summarize_output",
+            Tool::ReadArtifact(_) => "read_artifact",
         }
         .to_owned()
     }
@@ -85,6 +93,8 @@ impl Tool {
             Tool::GhIssue(_) => false,
             Tool::Knowledge(_) => false,
             Tool::Thinking(_) => false,
+            Tool::SummarizeOutput(_) => false,
+            Tool::ReadArtifact(_) => false,
         }
     }
 
@@ -99,6 +109,8 @@ impl Tool {
             Tool::GhIssue(gh_issue) => gh_issue.invoke(os, stdout).await,
             Tool::Knowledge(knowledge) => knowledge.invoke(os, stdout).await,
             Tool::Thinking(think) => think.invoke(stdout).await,
+            Tool::SummarizeOutput(summarize_output) => summarize_output.invoke(os, stdout).await,
+            Tool::ReadArtifact(read_artifact) => read_artifact.invoke(os, stdout).await,
         }
     }
 
@@ -113,6 +125,8 @@ impl Tool {
             Tool::GhIssue(gh_issue) => gh_issue.queue_description(output),
             Tool::Knowledge(knowledge) => knowledge.queue_description(os, output).await,
             Tool::Thinking(thinking) => thinking.queue_description(output),
+            Tool::SummarizeOutput(summarize_output) => summarize_output.queue_description(output),
+            Tool::ReadArtifact(read_artifact) => read_artifact.queue_description(output),
         }
     }
 
@@ -127,16 +141,54 @@ impl Tool {
             Tool::GhIssue(gh_issue) => gh_issue.validate(os).await,
             Tool::Knowledge(knowledge) => knowledge.validate(os).await,
             Tool::Thinking(think) => think.validate(os).await,
+            Tool::SummarizeOutput(summarize_output) => summarize_output.validate(os).await,
+            Tool::ReadArtifact(read_artifact) => read_artifact.validate(os).await,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Whether a tool use runs, as decided by [ToolPermissions::evaluate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionDecision {
+    Allow,
+    Ask,
+    Deny,
+}
+
+/// The rules deciding whether a tool use runs, in the order they're evaluated. The first one that
+/// applies decides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionRule {
+    /// Tools that need confirmation are denied while another session is editing the workspace.
+    ReadOnlySession,
+    /// Every tool is trusted, with `/tools trust-all`.
+    TrustAll,
+    /// The tool is trusted for the session, with `/tools trust` or `--trust-tools`.
+    SessionTrust,
+    /// The tool asks for confirmation of every use, whatever its default, with the `strict`
+    /// preset of `/tools preset`.
+    AskEveryUse,
+    /// The tool's own default for its input, e.g. read-only commands of `execute_bash` run without
+    /// confirmation.
+    ToolDefault,
+}
+
+impl PermissionRule {
+    pub const ALL: [Self; 5] = [
+        Self::ReadOnlySession,
+        Self::TrustAll,
+        Self::SessionTrust,
+        Self::AskEveryUse,
+        Self::ToolDefault,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolPermission {
     pub trusted: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Holds overrides for tool permissions.
 /// Tools that do not have an associated ToolPermission should use
 /// their default logic to determine to permission.
@@ -146,6 +198,10 @@ pub struct ToolPermissions {
     pub permissions: HashMap<String, ToolPermission>,
     // Store pending trust-tool patterns for MCP tools that may be loaded later
     pub pending_trusted_tools: HashSet<String>,
+    /// Tools that ask for confirmation of every use, even of inputs their default runs without
+    /// confirmation, e.g. read-only commands.
+    #[serde(default)]
+    pub always_ask: HashSet<String>,
 }
 
 impl ToolPermissions {
@@ -154,6 +210,31 @@ impl ToolPermissions {
             trust_all: false,
             permissions: HashMap::with_capacity(capacity),
             pending_trusted_tools: HashSet::new(),
+            always_ask: HashSet::new(),
+        }
+    }
+
+    /// Decides whether a use of `tool_name` runs, and which [PermissionRule] decided it.
+    /// `requires_acceptance` is the tool's default for the use's input, and `read_only` whether
+    /// the session is read-only.
+    pub fn evaluate(
+        &mut self,
+        tool_name: &str,
+        requires_acceptance: bool,
+        read_only: bool,
+    ) -> (PermissionDecision, PermissionRule) {
+        if read_only && requires_acceptance {
+            (PermissionDecision::Deny, PermissionRule::ReadOnlySession)
+        } else if self.trust_all {
+            (PermissionDecision::Allow, PermissionRule::TrustAll)
+        } else if self.has(tool_name) && self.is_trusted(tool_name) {
+            (PermissionDecision::Allow, PermissionRule::SessionTrust)
+        } else if self.always_ask.contains(tool_name) {
+            (PermissionDecision::Ask, PermissionRule::AskEveryUse)
+        } else if requires_acceptance {
+            (PermissionDecision::Ask, PermissionRule::ToolDefault)
+        } else {
+            (PermissionDecision::Allow, PermissionRule::ToolDefault)
         }
     }
 
@@ -175,11 +256,13 @@ impl ToolPermissions {
         match (has_setting, is_trusted) {
             (true, true) => format!("  {}", "trusted".dark_green().bold()),
             (true, false) => format!("  {}", "not trusted".dark_grey()),
+            _ if self.always_ask.contains(tool_name) => format!("  {}", "ask every use".dark_grey()),
             _ => self.default_permission_label(tool_name),
         }
     }
 
     pub fn trust_tool(&mut self, tool_name: &str) {
+        self.always_ask.remove(tool_name);
         self.permissions
             .insert(tool_name.to_string(), ToolPermission { trusted: true });
     }
@@ -187,20 +270,52 @@ impl ToolPermissions {
     pub fn untrust_tool(&mut self, tool_name: &str) {
         self.trust_all = false;
         self.pending_trusted_tools.remove(tool_name);
+        self.always_ask.remove(tool_name);
         self.permissions
             .insert(tool_name.to_string(), ToolPermission { trusted: false });
     }
 
+    /// Asks for confirmation of every use of the tool, see [PermissionRule::AskEveryUse].
+    pub fn ask_every_use(&mut self, tool_name: &str) {
+        self.reset_tool(tool_name);
+        self.always_ask.insert(tool_name.to_string());
+    }
+
     pub fn reset(&mut self) {
         self.trust_all = false;
         self.permissions.clear();
         self.pending_trusted_tools.clear();
+        self.always_ask.clear();
     }
 
     pub fn reset_tool(&mut self, tool_name: &str) {
         self.trust_all = false;
         self.permissions.remove(tool_name);
         self.pending_trusted_tools.remove(tool_name);
+        self.always_ask.remove(tool_name);
+    }
+
+    /// Whether any tool's permission differs from its default.
+    pub fn is_modified(&self) -> bool {
+        self.trust_all
+            || !self.permissions.is_empty()
+            || !self.pending_trusted_tools.is_empty()
+            || !self.always_ask.is_empty()
+    }
+
+    /// Names of the tools with a permission set for this session, i.e. the tools that
+    /// `/tools reset <tool>` would change. Sorted by name.
+    pub fn modified_tools(&self) -> Vec<String> {
+        let mut tools = self
+            .permissions
+            .keys()
+            .chain(self.pending_trusted_tools.iter())
+            .chain(self.always_ask.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        tools.sort();
+        tools.dedup();
+        tools
     }
 
     /// Add a pending trust pattern for tools that may be loaded later
@@ -238,6 +353,8 @@ impl ToolPermissions {
             "report_issue" => "trusted".dark_green().bold(),
             "knowledge" => "trusted".dark_green().bold(),
             "thinking" => "trusted (prerelease)".dark_green().bold(),
+            "summarize_output" => "trusted".dark_green().bold(),
+            "read_artifact" => "trusted".dark_green().bold(),
             _ if self.trust_all => "trusted".dark_grey().bold(),
             _ => "not trusted".dark_grey(),
         };
@@ -445,6 +562,76 @@ mod tests {
     use super::*;
     use crate::os::ACTIVE_USER_HOME;
 
+    #[test]
+    fn test_modified_tools() {
+        let mut permissions = ToolPermissions::new(0);
+        assert!(!permissions.is_modified());
+        assert!(permissions.modified_tools().is_empty());
+
+        permissions.trust_tool("fs_write");
+        permissions.untrust_tool("execute_bash");
+        permissions.add_pending_trust_tool("@git/status".to_string());
+        assert!(permissions.is_modified());
+        assert_eq!(permissions.modified_tools(), vec![
+            "@git/status",
+            "execute_bash",
+            "fs_write"
+        ]);
+
+        permissions.reset_tool("fs_write");
+        assert_eq!(permissions.modified_tools(), vec!["@git/status", "execute_bash"]);
+        permissions.reset();
+        assert!(!permissions.is_modified());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut permissions = ToolPermissions::new(0);
+        assert_eq!(
+            permissions.evaluate("fs_write", true, false),
+            (PermissionDecision::Ask, PermissionRule::ToolDefault)
+        );
+        assert_eq!(
+            permissions.evaluate("fs_read", false, true),
+            (PermissionDecision::Allow, PermissionRule::ToolDefault)
+        );
+
+        permissions.trust_tool("fs_write");
+        assert_eq!(
+            permissions.evaluate("fs_write", true, false),
+            (PermissionDecision::Allow, PermissionRule::SessionTrust)
+        );
+        assert_eq!(
+            permissions.evaluate("fs_write", true, true),
+            (PermissionDecision::Deny, PermissionRule::ReadOnlySession)
+        );
+
+        // Untrusting a tool only asks for the inputs its default asks for.
+        permissions.untrust_tool("fs_read");
+        assert_eq!(
+            permissions.evaluate("fs_read", false, false),
+            (PermissionDecision::Allow, PermissionRule::ToolDefault)
+        );
+
+        // Even inputs the default runs without confirmation are confirmed first.
+        permissions.ask_every_use("execute_bash");
+        assert_eq!(
+            permissions.evaluate("execute_bash", false, false),
+            (PermissionDecision::Ask, PermissionRule::AskEveryUse)
+        );
+        permissions.trust_tool("execute_bash");
+        assert_eq!(
+            permissions.evaluate("execute_bash", false, false),
+            (PermissionDecision::Allow, PermissionRule::SessionTrust)
+        );
+
+        permissions.trust_all = true;
+        assert_eq!(
+            permissions.evaluate("@git/push", true, false),
+            (PermissionDecision::Allow, PermissionRule::TrustAll)
+        );
+    }
+
     #[tokio::test]
     async fn test_tilde_path_expansion() {
         let os = Os::new().await.unwrap();
@@ -0,0 +1,136 @@
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::Result;
+use serde::Deserialize;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::cli::chat::artifacts::{
+    self,
+    ARTIFACT_THRESHOLD,
+    cut_line,
+};
+use crate::os::Os;
+
+/// Lines returned when the model doesn't ask for a limit.
+const DEFAULT_LIMIT: usize = 200;
+
+/// Reads a page of lines from an artifact stored for a large tool output.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadArtifact {
+    pub artifact_id: String,
+    /// Lines to skip from the start of the artifact.
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl ReadArtifact {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        artifacts::read(os, &self.artifact_id).await?;
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Reading stored output "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.artifact_id),
+            style::ResetColor,
+            style::Print(format!(" from line {}\n", self.offset + 1)),
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
+        let text = artifacts::read(os, &self.artifact_id).await?;
+        Ok(InvokeOutput {
+            output: OutputKind::Text(page(&text, self.offset, self.limit.unwrap_or(DEFAULT_LIMIT))),
+        })
+    }
+}
+
+/// Up to `limit` lines of `text` after `offset`, cut short if they'd be as large as an output that
+/// is stored as an artifact, and a note on where the next page starts.
+fn page(text: &str, offset: usize, limit: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    if offset >= lines.len() {
+        return format!(
+            "The artifact has {} lines, there are none after line {offset}.",
+            lines.len()
+        );
+    }
+
+    let mut page = String::new();
+    let mut end = offset;
+    for line in lines.iter().skip(offset).take(limit.max(1)) {
+        let line = cut_line(line);
+        if end > offset && page.len() + line.len() > ARTIFACT_THRESHOLD {
+            break;
+        }
+        page.push_str(line);
+        page.push('\n');
+        end += 1;
+    }
+    let mut header = format!("Lines {}-{end} of {}", offset + 1, lines.len());
+    if end < lines.len() {
+        header.push_str(&format!(", read on with offset {end}"));
+    }
+    format!("{header}:\n{page}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_artifact() {
+        let os = Os::new().await.unwrap();
+        let text = (0..500).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let id = artifacts::store(&os, &text).await.unwrap();
+
+        let mut tool = ReadArtifact {
+            artifact_id: id,
+            offset: 0,
+            limit: None,
+        };
+        tool.validate(&os).await.unwrap();
+        let OutputKind::Text(output) = tool.invoke(&os, std::io::sink()).await.unwrap().output else {
+            panic!("the page should be text");
+        };
+        assert!(output.starts_with("Lines 1-200 of 500, read on with offset 200:\nline 0\n"));
+        assert!(output.ends_with("line 199\n"));
+
+        tool.offset = 450;
+        tool.limit = Some(100);
+        let OutputKind::Text(output) = tool.invoke(&os, std::io::sink()).await.unwrap().output else {
+            panic!("the page should be text");
+        };
+        assert!(output.starts_with("Lines 451-500 of 500:\nline 450\n"));
+
+        tool.offset = 500;
+        let OutputKind::Text(output) = tool.invoke(&os, std::io::sink()).await.unwrap().output else {
+            panic!("the page should be text");
+        };
+        assert_eq!(output, "The artifact has 500 lines, there are none after line 500.");
+
+        tool.artifact_id = "0000".to_string();
+        assert!(tool.validate(&os).await.is_err());
+    }
+
+    #[test]
+    fn test_page_size() {
+        let text = vec!["x".repeat(1_000); 1_000].join("\n");
+        let output = page(&text, 0, 1_000);
+        assert!(output.len() <= ARTIFACT_THRESHOLD + 100);
+        assert!(output.contains("read on with offset"));
+    }
+}
@@ -0,0 +1,255 @@
+//! Condensed versions of large outputs, for artifacts stored by [artifacts] or large files such as
+//! logs.
+//!
+//! Summaries are made locally: errors, warnings and the model's focus terms are kept, similar lines
+//! are folded together, and diffs are reduced to their changed files.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::chat::artifacts::{
+    self,
+    cut_line,
+};
+use crate::os::Os;
+
+/// Lines a summary keeps besides the first and last lines, unless the model asks for another limit.
+const DEFAULT_MAX_LINES: usize = 80;
+
+static NOTABLE_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(error|errors|fail|failed|failure|failures|fatal|panic|panicked|exception|traceback|warn|warning|denied|refused|timeout|timed out|cannot|could not|not found|segmentation fault)\b",
+    )
+    .unwrap()
+});
+static NUMBERS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SummarizeOutput {
+    /// Id of a stored output, given in the preview of a large tool result.
+    pub artifact_id: Option<String>,
+    /// A file to summarize instead of an artifact.
+    pub path: Option<String>,
+    /// Terms whose lines are kept, in addition to errors and warnings.
+    #[serde(default)]
+    pub focus: Vec<String>,
+    pub max_lines: Option<usize>,
+}
+
+impl SummarizeOutput {
+    fn source(&self, os: &Os) -> Result<PathBuf> {
+        match (&self.artifact_id, &self.path) {
+            (Some(id), None) => artifacts::artifact_path(os, id),
+            (None, Some(path)) => Ok(sanitize_path_tool_arg(os, path)),
+            _ => bail!("Exactly one of artifact_id or path must be given"),
+        }
+    }
+
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        let source = self.source(os)?;
+        if !os.fs.exists(&source) {
+            match &self.artifact_id {
+                Some(id) => bail!("There is no artifact '{id}', it may have expired"),
+                None => bail!("'{}' does not exist", source.display()),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        let source = match (&self.artifact_id, &self.path) {
+            (Some(id), _) => format!("stored output {id}"),
+            (_, Some(path)) => path.clone(),
+            _ => String::new(),
+        };
+        queue!(
+            output,
+            style::Print("Summarizing "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(source),
+            style::ResetColor,
+        )?;
+        if !self.focus.is_empty() {
+            queue!(output, style::Print(format!(", focusing on {}", self.focus.join(", "))))?;
+        }
+        queue!(output, style::Print("\n"))?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
+        let bytes = os.fs.read(self.source(os)?).await?;
+        let text = String::from_utf8_lossy(&bytes);
+        Ok(InvokeOutput {
+            output: OutputKind::Text(summarize(
+                &text,
+                &self.focus,
+                self.max_lines.unwrap_or(DEFAULT_MAX_LINES),
+            )),
+        })
+    }
+}
+
+/// A condensed version of `text`: its size, first and last lines, and the notable lines in
+/// between, with similar lines folded together. Diffs are summarized by file instead.
+fn summarize(text: &str, focus: &[String], max_lines: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut summary = format!("{} lines, {} bytes.\n\n", lines.len(), text.len());
+    if lines
+        .iter()
+        .any(|line| line.starts_with("diff --git ") || line.starts_with("+++ "))
+    {
+        summary.push_str(&diff_summary(&lines));
+    }
+
+    let head = lines.len().min(10);
+    let tail = lines.len().saturating_sub(20).max(head);
+    summary.push_str("First lines:\n");
+    for line in &lines[..head] {
+        summary.push_str(&format!("{}\n", cut_line(line)));
+    }
+
+    let focus: Vec<String> = focus.iter().map(|term| term.to_lowercase()).collect();
+    // Lines that differ only in numbers, such as timestamps or counters, are folded together.
+    let mut notable: Vec<(usize, &str, usize)> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (index, line) in lines.iter().enumerate().take(tail).skip(head) {
+        let lower = line.to_lowercase();
+        if !NOTABLE_LINE.is_match(line) && !focus.iter().any(|term| lower.contains(term)) {
+            continue;
+        }
+        let key = NUMBERS.replace_all(line.trim(), "#").to_string();
+        match seen.get(&key) {
+            Some(&position) => notable[position].2 += 1,
+            None => {
+                seen.insert(key, notable.len());
+                notable.push((index + 1, line, 1));
+            },
+        }
+    }
+    if !notable.is_empty() {
+        summary.push_str(&format!(
+            "\nErrors, warnings and focus terms ({} of {} distinct lines):\n",
+            notable.len().min(max_lines),
+            notable.len()
+        ));
+        for (number, line, count) in notable.iter().take(max_lines) {
+            let repeats = if *count > 1 {
+                format!(" ({count} similar lines)")
+            } else {
+                String::new()
+            };
+            summary.push_str(&format!("{number}: {}{repeats}\n", cut_line(line)));
+        }
+    }
+
+    if tail < lines.len() {
+        summary.push_str("\nLast lines:\n");
+        for line in &lines[tail..] {
+            summary.push_str(&format!("{}\n", cut_line(line)));
+        }
+    }
+    summary
+}
+
+/// The files a diff changes, with the lines added and removed in each.
+fn diff_summary(lines: &[&str]) -> String {
+    let mut files: Vec<(String, usize, usize)> = Vec::new();
+    for line in lines {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            let path = path.strip_prefix("b/").unwrap_or(path);
+            files.push((path.to_string(), 0, 0));
+        } else if let Some((_, added, removed)) = files.last_mut() {
+            if line.starts_with('+') {
+                *added += 1;
+            } else if line.starts_with('-') && !line.starts_with("--- ") {
+                *removed += 1;
+            }
+        }
+    }
+    let mut summary = format!("Changed files ({}):\n", files.len());
+    for (path, added, removed) in files {
+        summary.push_str(&format!("{path} +{added} -{removed}\n"));
+    }
+    summary.push('\n');
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let mut log = vec!["starting build".to_string()];
+        for i in 0..200 {
+            log.push(format!("compiling crate{i}"));
+        }
+        for i in 0..5 {
+            log.push(format!("warning: unused variable at line {i}"));
+        }
+        log.push("error[E0308]: mismatched types".to_string());
+        log.push("note: retrying slow_mirror".to_string());
+        for i in 0..30 {
+            log.push(format!("finishing {i}"));
+        }
+        let summary = summarize(&log.join("\n"), &["slow_mirror".to_string()], 80);
+        assert!(summary.starts_with("238 lines,"));
+        assert!(summary.contains("First lines:\nstarting build\n"));
+        assert!(summary.contains("202: warning: unused variable at line 0 (5 similar lines)\n"));
+        assert!(summary.contains("207: error[E0308]: mismatched types\n"));
+        assert!(summary.contains("208: note: retrying slow_mirror\n"));
+        assert!(!summary.contains("compiling crate100"));
+        assert!(summary.ends_with("finishing 29\n"));
+
+        let summary = summarize(&log.join("\n"), &[], 1);
+        assert!(summary.contains("(1 of 2 distinct lines)"));
+    }
+
+    #[test]
+    fn test_diff_summary() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n-old\n+new\n+more\n";
+        assert!(summarize(diff, &[], 80).contains("Changed files (1):\nsrc/lib.rs +2 -1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_summarize_artifact() {
+        let os = Os::new().await.unwrap();
+        let text = (0..5000).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        let id = artifacts::store(&os, &text).await.unwrap();
+
+        let mut tool = SummarizeOutput {
+            artifact_id: Some(id),
+            path: None,
+            focus: vec!["line 2500".to_string()],
+            max_lines: None,
+        };
+        tool.validate(&os).await.unwrap();
+        let OutputKind::Text(summary) = tool.invoke(&os, std::io::sink()).await.unwrap().output else {
+            panic!("the summary should be text");
+        };
+        assert!(summary.starts_with("5000 lines,"));
+        assert!(summary.contains("2501: line 2500\n"));
+
+        tool.artifact_id = Some("../secrets".to_string());
+        assert!(tool.validate(&os).await.is_err());
+    }
+}
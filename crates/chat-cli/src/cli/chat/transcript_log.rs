@@ -0,0 +1,231 @@
+//! A JSON lines log of a chat session, written as it happens with `--transcript <FILE>` or the
+//! `chat.transcriptPath` setting.
+//!
+//! Each line is one [TranscriptEvent] with a `timestamp` and a `type`. The file is appended to, so
+//! several sessions can share one log; `session_start` lines separate them. `/replay` reads the
+//! log back as [TranscriptLine]s.
+
+use std::fs::{
+    File,
+    OpenOptions,
+};
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::Result;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::warn;
+
+use crate::api_client::model::ToolResultStatus;
+use crate::cli::chat::message::{
+    ToolUseResult,
+    ToolUseResultBlock,
+};
+use crate::os::Os;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptEvent<'a> {
+    SessionStart {
+        conversation_id: &'a str,
+    },
+    /// A prompt sent to the model, as entered.
+    Prompt {
+        prompt: &'a str,
+    },
+    /// Part of a response, as it is streamed.
+    ResponseChunk {
+        text: &'a str,
+    },
+    ToolUse {
+        id: &'a str,
+        name: &'a str,
+        input: &'a Value,
+    },
+    ToolResult {
+        tool_use_id: &'a str,
+        status: &'static str,
+        content: &'a [ToolUseResultBlock],
+    },
+}
+
+impl<'a> From<&'a ToolUseResult> for TranscriptEvent<'a> {
+    fn from(result: &'a ToolUseResult) -> Self {
+        Self::ToolResult {
+            tool_use_id: &result.tool_use_id,
+            status: match result.status {
+                ToolResultStatus::Success => "success",
+                ToolResultStatus::Error => "error",
+            },
+            content: &result.content,
+        }
+    }
+}
+
+/// A line of a transcript as read back.
+#[derive(Debug, Deserialize)]
+pub struct TranscriptLine {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    #[serde(flatten)]
+    pub event: RecordedEvent,
+}
+
+/// The owned counterpart of [TranscriptEvent].
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    SessionStart {
+        conversation_id: String,
+    },
+    Prompt {
+        prompt: String,
+    },
+    ResponseChunk {
+        text: String,
+    },
+    ToolUse {
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        status: String,
+        content: Vec<ToolUseResultBlock>,
+    },
+    /// Events written by a newer version.
+    #[serde(other)]
+    Other,
+}
+
+/// Reads the transcript at `path`, split into its sessions. Lines that can't be read, such as a
+/// line cut short by a crash, are skipped.
+pub async fn read_sessions(os: &Os, path: impl AsRef<Path>) -> Result<Vec<Vec<TranscriptLine>>> {
+    let contents = os.fs.read_to_string(path).await?;
+    let mut sessions: Vec<Vec<TranscriptLine>> = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let line = match serde_json::from_str::<TranscriptLine>(line) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!(?err, "skipping an unreadable transcript line");
+                continue;
+            },
+        };
+        match sessions.last_mut() {
+            Some(session) if !matches!(line.event, RecordedEvent::SessionStart { .. }) => session.push(line),
+            _ => sessions.push(vec![line]),
+        }
+    }
+    Ok(sessions)
+}
+
+#[derive(Debug)]
+pub struct TranscriptLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl TranscriptLog {
+    /// Opens `path` for appending, creating it and its parent directories if needed.
+    pub fn open(os: &Os, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = os.fs.chroot_path(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `event` as a line. Failures are logged rather than interrupting the session.
+    #[cfg(test)]
+    pub fn append(&mut self, event: TranscriptEvent<'_>) {
+        match json_line(event) {
+            Ok(line) => self.append_line(&line),
+            Err(err) => warn!(?err, "failed to serialize a transcript event"),
+        }
+    }
+
+    /// Writes a line made with [json_line]. Failures are logged rather than interrupting the
+    /// session.
+    pub fn append_line(&mut self, line: &str) {
+        if let Err(err) = writeln!(self.file, "{line}") {
+            warn!(?err, path = ?self.path, "failed to write to the transcript");
+        }
+    }
+}
+
+/// `event` as a line of a transcript, stamped with the current time.
+pub fn json_line(event: TranscriptEvent<'_>) -> serde_json::Result<String> {
+    #[derive(Serialize)]
+    struct Line<'a> {
+        timestamp: String,
+        #[serde(flatten)]
+        event: TranscriptEvent<'a>,
+    }
+
+    serde_json::to_string(&Line {
+        timestamp: OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default(),
+        event,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_append() {
+        let os = Os::new().await.unwrap();
+        let mut log = TranscriptLog::open(&os, "/logs/session.jsonl").unwrap();
+        log.append(TranscriptEvent::Prompt { prompt: "hello" });
+        log.append(TranscriptEvent::ResponseChunk { text: "Hi" });
+        let result = ToolUseResult {
+            tool_use_id: "t1".to_string(),
+            content: vec![ToolUseResultBlock::Text("done".to_string())],
+            status: ToolResultStatus::Error,
+        };
+        log.append((&result).into());
+
+        // Reopening appends rather than truncating.
+        let mut log = TranscriptLog::open(&os, "/logs/session.jsonl").unwrap();
+        log.append(TranscriptEvent::SessionStart { conversation_id: "id" });
+
+        let contents = os.fs.read_to_string("/logs/session.jsonl").await.unwrap();
+        let lines = contents
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["type"], "prompt");
+        assert_eq!(lines[0]["prompt"], "hello");
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(lines[2]["type"], "tool_result");
+        assert_eq!(lines[2]["status"], "error");
+        assert_eq!(lines[3]["type"], "session_start");
+
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(os.fs.chroot_path("/logs/session.jsonl"))
+            .unwrap()
+            .write_all(b"{\"timestamp\": \"2025-06-01T10:00:00Z\", \"type\": \"future_event\"}\n{\"trunc")
+            .unwrap();
+        let sessions = read_sessions(&os, "/logs/session.jsonl").await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].len(), 3);
+        assert!(matches!(&sessions[0][0].event, RecordedEvent::Prompt { prompt } if prompt == "hello"));
+        assert!(matches!(&sessions[0][2].event, RecordedEvent::ToolResult { status, .. } if status == "error"));
+        assert!(matches!(sessions[1][1].event, RecordedEvent::Other));
+    }
+}
@@ -0,0 +1,378 @@
+//! Web pages used as context rules, e.g. `/context add https://example.com/design.md`.
+//!
+//! Pages are fetched when a rule is added and cached under [directories::chat_url_cache_dir], see
+//! [RemoteCache], so later prompts reuse the copy until it is older than the
+//! `chat.urlContextTtlSeconds` setting. Pages are only fetched over HTTPS, and up to the size of
+//! the context. HTML is converted to markdown; other text is used as is.
+//! `/context refresh`, or `/context add --refresh`, fetches pages again.
+
+use std::time::Duration;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tracing::warn;
+
+use crate::cli::chat::consts::CONTEXT_FILES_MAX_SIZE;
+use crate::cli::chat::remote_cache::{
+    CachedRule,
+    RemoteCache,
+};
+use crate::os::Os;
+use crate::request::new_client;
+use crate::util::directories;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bytes of a page that are read, the rest wouldn't fit in the context anyway.
+const MAX_PAGE_SIZE: usize = CONTEXT_FILES_MAX_SIZE;
+
+/// Whether `path` is a URL rule. Only `https://` URLs are fetched, `http://` ones are recognized to
+/// tell why.
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+fn cache(os: &Os) -> Result<RemoteCache> {
+    Ok(RemoteCache::new(directories::chat_url_cache_dir(os)?))
+}
+
+/// Reads the cached copy of `url` as markdown, however old it is.
+pub async fn cached(os: &Os, url: &str) -> Option<CachedRule<String>> {
+    cache(os).ok()?.cached(os, url).await
+}
+
+/// Drops the cached copy of `url`, if there is one.
+pub async fn remove_cached(os: &Os, url: &str) -> Result<()> {
+    cache(os)?.remove(os, url).await
+}
+
+/// Returns the content of `url` as markdown, from the cache if the copy there is recent enough.
+///
+/// With `refresh`, the page is always fetched. If fetching fails, an older cached copy is used
+/// rather than dropping the rule from the context.
+pub async fn fetch(os: &Os, url: &str, refresh: bool) -> Result<String> {
+    cache(os)?.fetch(os, url, refresh, || fetch_page(url)).await
+}
+
+async fn fetch_page(url: &str) -> Result<String> {
+    if url.starts_with("http://") {
+        bail!("{url} would be fetched without encryption, use an https:// URL");
+    }
+    let mut response = new_client()?.get(url).timeout(FETCH_TIMEOUT).send().await?;
+    if !response.status().is_success() {
+        bail!("Fetching {url} failed with status {}", response.status());
+    }
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("html"));
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_SIZE {
+            warn!(
+                url,
+                "a context URL is too large for the context, dropping the rest of the page"
+            );
+            body.truncate(MAX_PAGE_SIZE);
+            break;
+        }
+    }
+    let body = String::from_utf8_lossy(&body).into_owned();
+    Ok(if is_html { html_to_markdown(&body) } else { body })
+}
+
+/// Elements whose content is never shown.
+const HIDDEN_ELEMENTS: &[&str] = &["head", "script", "style", "noscript", "template", "svg"];
+
+/// Elements that start a new paragraph.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "blockquote",
+    "table",
+    "tr",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "figure",
+    "hr",
+];
+
+/// Converts HTML to markdown, keeping headings, lists, links, emphasis and code. Other markup is
+/// dropped and only its text kept.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut out = String::new();
+    let mut hidden: Option<String> = None;
+    let mut pre = false;
+    let mut links: Vec<Option<String>> = Vec::new();
+    // The next item number of each open list, or `None` for unordered lists.
+    let mut lists: Vec<Option<usize>> = Vec::new();
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            if hidden.is_none() {
+                push_text(&mut out, rest, pre);
+            }
+            break;
+        };
+        if hidden.is_none() {
+            push_text(&mut out, &rest[..start], pre);
+        }
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/');
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if let Some(hidden_name) = &hidden {
+            if closing && *hidden_name == name {
+                hidden = None;
+            }
+            continue;
+        }
+        if !closing && HIDDEN_ELEMENTS.contains(&name.as_str()) && !tag.ends_with('/') {
+            hidden = Some(name);
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                start_block(&mut out);
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            },
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => start_block(&mut out),
+            ("br", _) => out.push('\n'),
+            ("li", false) => {
+                end_line(&mut out);
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        out.push_str(&format!("{number}. "));
+                        *number += 1;
+                    },
+                    _ => out.push_str("- "),
+                }
+            },
+            ("ul", false) => {
+                start_block(&mut out);
+                lists.push(None);
+            },
+            ("ol", false) => {
+                start_block(&mut out);
+                lists.push(Some(1));
+            },
+            ("ul" | "ol", true) => {
+                lists.pop();
+                start_block(&mut out);
+            },
+            ("pre", false) => {
+                start_block(&mut out);
+                out.push_str("```\n");
+                pre = true;
+            },
+            ("pre", true) => {
+                end_line(&mut out);
+                out.push_str("```");
+                start_block(&mut out);
+                pre = false;
+            },
+            ("code", _) if !pre => out.push('`'),
+            ("strong" | "b", _) => out.push_str("**"),
+            ("em" | "i", _) => out.push('_'),
+            ("a", false) => {
+                let href = attribute(tag, "href").filter(|href| !href.starts_with('#') && !href.is_empty());
+                if href.is_some() {
+                    out.push('[');
+                }
+                links.push(href);
+            },
+            ("a", true) => {
+                if let Some(Some(href)) = links.pop() {
+                    out.push_str(&format!("]({href})"));
+                }
+            },
+            (name, _) if BLOCK_ELEMENTS.contains(&name) => start_block(&mut out),
+            _ => (),
+        }
+    }
+
+    // Collapse the blank lines left by nested blocks.
+    let mut markdown = String::new();
+    let mut blank = 0;
+    for line in out.lines() {
+        let line = line.trim_end();
+        blank = if line.is_empty() { blank + 1 } else { 0 };
+        if blank < 2 {
+            markdown.push_str(line);
+            markdown.push('\n');
+        }
+    }
+    markdown.trim().to_string()
+}
+
+fn push_text(out: &mut String, text: &str, pre: bool) {
+    let text = decode_entities(text);
+    if pre {
+        out.push_str(&text);
+        return;
+    }
+    // Whitespace is collapsed, but kept at the edges so that text around inline elements stays
+    // apart, e.g. `the <b>rest</b>` but `<b>rest</b>.`.
+    if text.starts_with(char::is_whitespace) && !out.is_empty() && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+    if text.ends_with(char::is_whitespace) && !out.is_empty() && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+fn end_line(out: &mut String) {
+    let len = out.trim_end_matches(' ').len();
+    out.truncate(len);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn start_block(out: &mut String) {
+    end_line(out);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+/// The value of attribute `name` in the inside of a tag, e.g. `a href="/docs"`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    // Only a whole attribute name, e.g. not the end of `data-href=`.
+    let (start, _) = lower
+        .match_indices(&format!("{name}="))
+        .find(|(start, _)| lower[..*start].ends_with(char::is_whitespace))?;
+    let start = start + name.len() + 1;
+    let value = &tag[start..];
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split(|c: char| c.is_whitespace()).next()?,
+    };
+    Some(decode_entities(value))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => entity
+                        .strip_prefix('#')
+                        .and_then(|decimal| decimal.parse().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r##"<!DOCTYPE html>
+<html><head><title>Design</title><style>p { color: red; }</style></head>
+<body>
+  <h1>Design &amp; plan</h1>
+  <p>Read the <a href="/docs">docs</a> and <a href="#top">skip</a> the <strong>rest</strong>.</p>
+  <!-- a comment -->
+  <ul><li>One</li><li>Two<ol><li>Nested</li></ol></li></ul>
+  <pre><code>fn main() {
+    println!("&lt;hi&gt;");
+}</code></pre>
+  <script>alert("hidden")</script>
+  <p>Use <code>cargo</code>&nbsp;daily.</p>
+</body></html>"##;
+        assert_eq!(
+            html_to_markdown(r#"<a data-href="/wrong" href='/right'>docs</a>"#),
+            "[docs](/right)"
+        );
+        assert_eq!(
+            html_to_markdown(html),
+            "# Design & plan\n\nRead the [docs](/docs) and skip the **rest**.\n\n- One\n- Two\n\n  1. Nested\n\n```\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\nUse `cargo` daily."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_uses_cache() {
+        let os = Os::new().await.unwrap();
+        let url = "https://example.com/design.md";
+        assert!(cached(&os, url).await.is_none());
+
+        let read = || async { Ok("# Design".to_string()) };
+        cache(&os).unwrap().fetch(&os, url, false, read).await.unwrap();
+
+        // A recent copy is used without fetching.
+        assert_eq!(fetch(&os, url, false).await.unwrap(), "# Design");
+
+        remove_cached(&os, url).await.unwrap();
+        assert!(cached(&os, url).await.is_none());
+
+        assert!(fetch(&os, "http://example.com/design.md", false).await.is_err());
+    }
+}
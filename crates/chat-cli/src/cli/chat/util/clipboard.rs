@@ -0,0 +1,76 @@
+use std::io::Write;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use super::is_remote_session;
+use crate::cli::chat::ChatError;
+use crate::os::Os;
+
+/// How a piece of text ended up on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMethod {
+    /// The local system clipboard.
+    System,
+    /// An OSC 52 escape sequence, which asks the terminal emulator to set its clipboard. This
+    /// works across SSH connections as long as the local terminal supports it.
+    Osc52,
+}
+
+/// Copies `text` to the clipboard.
+///
+/// In a remote session (see [is_remote_session]) the system clipboard belongs to the remote
+/// machine, so the text is sent to the user's terminal with OSC 52 instead. Locally we try the
+/// system clipboard first and fall back to OSC 52 if it isn't available (e.g. no display server).
+pub fn copy_to_clipboard(os: &Os, output: &mut impl Write, text: &str) -> Result<ClipboardMethod, ChatError> {
+    if !is_remote_session(os) {
+        let copied = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text));
+        match copied {
+            Ok(()) => return Ok(ClipboardMethod::System),
+            Err(err) => tracing::debug!(?err, "system clipboard unavailable, falling back to OSC 52"),
+        }
+    }
+
+    output.write_all(osc52_sequence(text, os.env.get_os("TMUX").is_some()).as_bytes())?;
+    output.flush()?;
+    Ok(ClipboardMethod::Osc52)
+}
+
+/// Builds the OSC 52 sequence that sets the clipboard selection to `text`.
+///
+/// tmux swallows unknown escape sequences, so inside tmux the sequence is wrapped in a DCS
+/// passthrough.
+fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let osc = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    match in_tmux {
+        true => format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b")),
+        false => osc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hello", false), "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(
+            osc52_sequence("hello", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGVsbG8=\x07\x1b\\"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_copy_to_clipboard_remote_uses_osc52() {
+        let os = Os::new().await.unwrap();
+        unsafe {
+            os.env.set_var("SSH_CONNECTION", "10.0.0.1 22 10.0.0.2 22");
+        }
+
+        let mut output = Vec::new();
+        let method = copy_to_clipboard(&os, &mut output, "hello").unwrap();
+        assert_eq!(method, ClipboardMethod::Osc52);
+        assert_eq!(String::from_utf8(output).unwrap(), "\x1b]52;c;aGVsbG8=\x07");
+    }
+}
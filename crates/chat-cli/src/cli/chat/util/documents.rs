@@ -0,0 +1,172 @@
+//! Text extraction for context files that aren't plain text. PDFs are read from their text layer,
+//! and images are read with `tesseract` when OCR is turned on with `chat.contextOcr`.
+
+use std::path::Path;
+
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::process::Command;
+
+use super::images::is_supported_image_type;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// A kind of file whose text is extracted before it's added to the context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Pdf,
+    Image,
+}
+
+impl DocumentKind {
+    pub fn of(path: &Path) -> Option<Self> {
+        let path = path.to_string_lossy();
+        if path.to_lowercase().ends_with(".pdf") {
+            Some(Self::Pdf)
+        } else if is_supported_image_type(&path) {
+            Some(Self::Image)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pdf => "PDF",
+            Self::Image => "image",
+        }
+    }
+}
+
+/// The text of the document at `path`.
+pub async fn extract_text(os: &Os, path: &Path, kind: DocumentKind) -> Result<String> {
+    match kind {
+        DocumentKind::Pdf => {
+            let bytes = os.fs.read(path).await?;
+            tokio::task::spawn_blocking(move || pdf_text(&bytes)).await?
+        },
+        DocumentKind::Image => {
+            if !os.database.settings.get_bool(Setting::ChatContextOcr).unwrap_or(false) {
+                return Ok(
+                    "[Image without extracted text, turn on OCR with chat.contextOcr to include its text]".into(),
+                );
+            }
+            ocr_text(&os.fs.chroot_path(path)).await
+        },
+    }
+}
+
+/// The text layer of a PDF, page by page.
+fn pdf_text(bytes: &[u8]) -> Result<String> {
+    let document = lopdf::Document::load_mem(bytes)?;
+    let mut text = String::new();
+    for page in document.get_pages().into_keys() {
+        let page_text = document.extract_text(&[page]).unwrap_or_default();
+        let page_text = page_text.trim();
+        if !page_text.is_empty() {
+            text.push_str(&format!("[Page {page}]\n{page_text}\n\n"));
+        }
+    }
+    if text.is_empty() {
+        return Ok("[PDF without a text layer, it may only contain scanned images]".into());
+    }
+    Ok(text.trim_end().to_string())
+}
+
+async fn ocr_text(path: &Path) -> Result<String> {
+    let output = match Command::new("tesseract").arg(path).arg("stdout").output().await {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            bail!("chat.contextOcr is on, but tesseract was not found, install it to read text from images")
+        },
+        Err(err) => return Err(err.into()),
+    };
+    if !output.status.success() {
+        bail!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::content::{
+        Content,
+        Operation,
+    };
+    use lopdf::{
+        Object,
+        Stream,
+        dictionary,
+    };
+
+    use super::*;
+
+    fn pdf_with_pages(pages: &[&str]) -> Vec<u8> {
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let mut kids = Vec::new();
+        for text in pages {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![100.into(), 600.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            kids.push(
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into(),
+            );
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_document_kind() {
+        assert_eq!(DocumentKind::of(Path::new("docs/Spec.PDF")), Some(DocumentKind::Pdf));
+        assert_eq!(DocumentKind::of(Path::new("diagram.png")), Some(DocumentKind::Image));
+        assert_eq!(DocumentKind::of(Path::new("README.md")), None);
+    }
+
+    #[test]
+    fn test_pdf_text() {
+        let text = pdf_text(&pdf_with_pages(&["Hello", "World"])).unwrap();
+        assert_eq!(text, "[Page 1]\nHello\n\n[Page 2]\nWorld");
+        assert!(pdf_text(b"not a pdf").is_err());
+    }
+}
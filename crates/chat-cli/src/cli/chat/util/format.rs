@@ -0,0 +1,201 @@
+use time::Date;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// The order in which the parts of a date are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateOrder {
+    /// `2025-06-30`
+    Iso,
+    /// `06/30/2025`
+    MonthDayYear,
+    /// `30/06/2025`, `30.06.2025`
+    DayMonthYear(char),
+    /// `2025/06/30`
+    YearMonthDay,
+}
+
+/// Formats numbers and dates for display according to the user's locale.
+///
+/// The locale comes from the `format.locale` setting, falling back to the standard `LC_ALL`,
+/// `LC_NUMERIC` and `LANG` environment variables. Unknown locales use English conventions.
+///
+/// There is no currency formatting, since no report shows costs: usage is reported in tokens and
+/// requests, which [integer](Self::integer) and [percent](Self::percent) cover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleFormatter {
+    group_separator: &'static str,
+    decimal_separator: &'static str,
+    /// Placed between a number and its percent sign, e.g. `12,5 %` in French.
+    percent_separator: &'static str,
+    date_order: DateOrder,
+}
+
+impl LocaleFormatter {
+    pub fn new(os: &Os) -> Self {
+        let locale = os
+            .database
+            .settings
+            .get_string(Setting::FormatLocale)
+            .or_else(|| {
+                ["LC_ALL", "LC_NUMERIC", "LANG"]
+                    .into_iter()
+                    .filter_map(|var| os.env.get(var).ok())
+                    .find(|value| !value.is_empty())
+            })
+            .unwrap_or_default();
+
+        Self::for_locale(&locale)
+    }
+
+    /// Accepts POSIX (`de_DE.UTF-8`) and BCP 47 (`de-DE`) style locale names.
+    pub fn for_locale(locale: &str) -> Self {
+        let locale = locale.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+        let mut parts = locale.split('-');
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        let region = parts.next().unwrap_or_default().to_uppercase();
+
+        let (group_separator, decimal_separator, percent_separator) = match (language.as_str(), region.as_str()) {
+            ("c" | "posix", _) => ("", ".", ""),
+            ("de" | "it", "CH") => ("’", ".", ""),
+            ("fr", "CH") => ("\u{202f}", ".", "\u{a0}"),
+            ("fr", _) => ("\u{202f}", ",", "\u{a0}"),
+            ("de" | "es" | "da", _) => (".", ",", "\u{a0}"),
+            ("it" | "pt" | "nl" | "id" | "tr" | "el", _) => (".", ",", ""),
+            ("sv" | "nb" | "no" | "fi" | "cs" | "sk" | "pl" | "ru" | "uk", _) => ("\u{a0}", ",", "\u{a0}"),
+            _ => (",", ".", ""),
+        };
+
+        let date_order = match (language.as_str(), region.as_str()) {
+            ("c" | "posix", _) => DateOrder::Iso,
+            ("en", "US" | "") | ("", _) => DateOrder::MonthDayYear,
+            ("ja" | "zh" | "ko", _) => DateOrder::YearMonthDay,
+            ("de" | "fi" | "cs" | "sk" | "pl" | "ru" | "uk" | "nb" | "no" | "da" | "tr", _) => {
+                DateOrder::DayMonthYear('.')
+            },
+            ("sv" | "lt", _) => DateOrder::Iso,
+            ("nl", _) => DateOrder::DayMonthYear('-'),
+            _ => DateOrder::DayMonthYear('/'),
+        };
+
+        Self {
+            group_separator,
+            decimal_separator,
+            percent_separator,
+            date_order,
+        }
+    }
+
+    /// Formats an integer with digit grouping, e.g. `1,234,567`.
+    pub fn integer(&self, value: usize) -> String {
+        self.group(&value.to_string())
+    }
+
+    /// Formats a decimal number with a fixed number of fractional digits.
+    pub fn decimal(&self, value: f64, precision: usize) -> String {
+        let formatted = format!("{:.*}", precision, value.abs());
+        let (int_part, frac_part) = match formatted.split_once('.') {
+            Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut result = String::new();
+        if value.is_sign_negative() && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            result.push('-');
+        }
+        result.push_str(&self.group(int_part));
+        if let Some(frac_part) = frac_part {
+            result.push_str(self.decimal_separator);
+            result.push_str(frac_part);
+        }
+        result
+    }
+
+    /// Formats a percentage, where `value` is already scaled to 0-100.
+    pub fn percent(&self, value: f64, precision: usize) -> String {
+        format!("{}{}%", self.decimal(value, precision), self.percent_separator)
+    }
+
+    /// Formats a calendar date, e.g. `06/30/2025` or `30.06.2025`.
+    pub fn date(&self, date: Date) -> String {
+        let (year, month, day) = (date.year(), u8::from(date.month()), date.day());
+        match self.date_order {
+            DateOrder::Iso => format!("{year:04}-{month:02}-{day:02}"),
+            DateOrder::MonthDayYear => format!("{month:02}/{day:02}/{year:04}"),
+            DateOrder::DayMonthYear(sep) => format!("{day:02}{sep}{month:02}{sep}{year:04}"),
+            DateOrder::YearMonthDay => format!("{year:04}/{month:02}/{day:02}"),
+        }
+    }
+
+    fn group(&self, digits: &str) -> String {
+        let len = digits.len();
+        let mut result = String::with_capacity(len + len / 3 * self.group_separator.len());
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (len - i) % 3 == 0 {
+                result.push_str(self.group_separator);
+            }
+            result.push(c);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer() {
+        let en = LocaleFormatter::for_locale("en_US.UTF-8");
+        assert_eq!(en.integer(0), "0");
+        assert_eq!(en.integer(999), "999");
+        assert_eq!(en.integer(1000), "1,000");
+        assert_eq!(en.integer(1234567), "1,234,567");
+
+        assert_eq!(LocaleFormatter::for_locale("de-DE").integer(1234567), "1.234.567");
+        assert_eq!(LocaleFormatter::for_locale("fr_FR").integer(1234), "1\u{202f}234");
+        assert_eq!(LocaleFormatter::for_locale("C").integer(1234), "1234");
+        assert_eq!(LocaleFormatter::for_locale("").integer(1234), "1,234");
+    }
+
+    #[test]
+    fn test_decimal_and_percent() {
+        let en = LocaleFormatter::for_locale("en");
+        assert_eq!(en.decimal(1234.5, 2), "1,234.50");
+        assert_eq!(en.decimal(-1234.5, 0), "-1,234");
+        assert_eq!(en.decimal(-0.001, 2), "0.00");
+        assert_eq!(en.percent(12.345, 2), "12.35%");
+
+        let de = LocaleFormatter::for_locale("de_DE.UTF-8");
+        assert_eq!(de.decimal(1234.5, 2), "1.234,50");
+        assert_eq!(de.percent(12.5, 1), "12,5\u{a0}%");
+
+        let de_ch = LocaleFormatter::for_locale("de_CH");
+        assert_eq!(de_ch.decimal(1234.5, 2), "1’234.50");
+    }
+
+    #[test]
+    fn test_date() {
+        let date = time::macros::date!(2025 - 06 - 30);
+        assert_eq!(LocaleFormatter::for_locale("en_US").date(date), "06/30/2025");
+        assert_eq!(LocaleFormatter::for_locale("en_GB").date(date), "30/06/2025");
+        assert_eq!(LocaleFormatter::for_locale("de_DE").date(date), "30.06.2025");
+        assert_eq!(LocaleFormatter::for_locale("ja_JP").date(date), "2025/06/30");
+        assert_eq!(LocaleFormatter::for_locale("C").date(date), "2025-06-30");
+    }
+
+    #[tokio::test]
+    async fn test_locale_resolution() {
+        let mut os = Os::new().await.unwrap();
+        assert_eq!(LocaleFormatter::new(&os), LocaleFormatter::for_locale("en"));
+
+        unsafe {
+            os.env.set_var("LANG", "de_DE.UTF-8");
+        }
+        assert_eq!(LocaleFormatter::new(&os), LocaleFormatter::for_locale("de"));
+
+        os.database.settings.set(Setting::FormatLocale, "fr-FR").await.unwrap();
+        assert_eq!(LocaleFormatter::new(&os), LocaleFormatter::for_locale("fr"));
+    }
+}
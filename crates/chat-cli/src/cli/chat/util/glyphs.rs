@@ -0,0 +1,138 @@
+//! ASCII replacements for the glyphs the chat draws with, for terminals and fonts that render them
+//! poorly. With the `chat.asciiOnly` setting, everything written to the terminal goes through a
+//! [GlyphWriter] that replaces tool bullets, arrows, check marks and box-drawing characters with
+//! ASCII and leaves out emoji.
+
+use std::borrow::Cow;
+use std::io::{
+    self,
+    Write,
+};
+
+/// Glyphs and their ASCII replacements. Single characters are replaced by a single character
+/// where possible, so that columns still line up.
+const REPLACEMENTS: &[(char, &str)] = &[
+    ('●', "*"),
+    ('•', "*"),
+    ('⋮', ":"),
+    ('↳', ">"),
+    ('▸', ">"),
+    ('→', "->"),
+    ('←', "<-"),
+    ('✔', "+"),
+    ('✓', "+"),
+    ('✘', "x"),
+    ('✗', "x"),
+    ('⚠', "!"),
+    ('─', "-"),
+    ('━', "-"),
+    ('▔', "-"),
+    ('│', "|"),
+    ('┃', "|"),
+    ('╭', "+"),
+    ('╮', "+"),
+    ('╰', "+"),
+    ('╯', "+"),
+    ('┌', "+"),
+    ('┐', "+"),
+    ('└', "+"),
+    ('┘', "+"),
+    ('├', "+"),
+    ('┤', "+"),
+    ('┬', "+"),
+    ('┴', "+"),
+    ('┼', "+"),
+    ('█', "#"),
+    ('░', "."),
+];
+
+/// Emoji, and the joiners and variation selectors they're built with.
+fn is_emoji(c: char) -> bool {
+    matches!(c, '\u{1F000}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' | '\u{FE0E}' | '\u{FE0F}' | '\u{200D}')
+}
+
+/// `text` with the glyphs of [REPLACEMENTS] replaced and emoji left out.
+pub fn to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        match REPLACEMENTS.iter().find(|&&(glyph, _)| glyph == c) {
+            Some((_, replacement)) => ascii.push_str(replacement),
+            None if is_emoji(c) => (),
+            None => ascii.push(c),
+        }
+    }
+    Cow::Owned(ascii)
+}
+
+/// Writes to `W`, replacing glyphs with ASCII when `ascii_only` is set.
+pub struct GlyphWriter<W> {
+    inner: W,
+    ascii_only: bool,
+}
+
+impl<W: Write> GlyphWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            ascii_only: false,
+        }
+    }
+
+    pub fn set_ascii_only(&mut self, ascii_only: bool) {
+        self.ascii_only = ascii_only;
+    }
+
+    /// The writer underneath, for output that must be written as it is.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for GlyphWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.ascii_only {
+            return self.inner.write(buf);
+        }
+        // Text is written in whole pieces, one that isn't valid UTF-8 isn't text to replace.
+        match std::str::from_utf8(buf) {
+            Ok(text) => {
+                self.inner.write_all(to_ascii(text).as_bytes())?;
+                Ok(buf.len())
+            },
+            Err(_) => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ascii() {
+        assert!(matches!(to_ascii("plain text"), Cow::Borrowed("plain text")));
+        assert_eq!(to_ascii(" ● Completed in 0.2s"), " * Completed in 0.2s");
+        assert_eq!(to_ascii("╭── hi ──╮"), "+-- hi --+");
+        assert_eq!(to_ascii("~10 → ~12 tkns"), "~10 -> ~12 tkns");
+        assert_eq!(to_ascii("🛠️  Using tool"), "  Using tool");
+        assert_eq!(to_ascii("⚠️ Warning"), "! Warning");
+        // Text that isn't a glyph is kept.
+        assert_eq!(to_ascii("café 日本"), "café 日本");
+    }
+
+    #[test]
+    fn test_glyph_writer() {
+        let mut writer = GlyphWriter::new(Vec::new());
+        write!(writer, "✔ ").unwrap();
+        writer.set_ascii_only(true);
+        write!(writer, "✔ done").unwrap();
+        assert_eq!(String::from_utf8(writer.inner).unwrap(), "✔ + done");
+    }
+}
@@ -0,0 +1,177 @@
+//! Fitting output to the width of the terminal. Text is wrapped at word boundaries, and tables
+//! narrow their widest columns and wrap those cells when the terminal is narrower than the table.
+//!
+//! Widths are measured in terminal columns, ignoring ANSI escape sequences, so styled text lines
+//! up.
+
+use strip_ansi_escapes::strip_str;
+use unicode_width::{
+    UnicodeWidthChar,
+    UnicodeWidthStr,
+};
+
+/// Narrowest a table column is made to fit the terminal.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// The columns `text` takes up in a terminal.
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(strip_str(text).as_str())
+}
+
+/// Wraps each line of `text` at word boundaries into lines of at most `width` columns, breaking
+/// words longer than a line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        if display_width(paragraph) <= width {
+            lines.push(paragraph.to_string());
+            continue;
+        }
+
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word = word.to_string();
+            let mut word_width = display_width(&word);
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            while word_width > width {
+                if line_width > 0 {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0;
+                }
+                let (head, tail) = split_at_width(&word, width);
+                lines.push(head);
+                word = tail;
+                word_width = display_width(&word);
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(&word);
+            line_width += word_width;
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Splits `text` after the characters that fit in `width` columns, at least one. Escape sequences
+/// take no columns and are never split.
+fn split_at_width(text: &str, width: usize) -> (String, String) {
+    let mut used = 0;
+    let mut in_escape = false;
+    for (i, c) in text.char_indices() {
+        if in_escape {
+            in_escape = !c.is_ascii_alphabetic() && c != '~';
+            continue;
+        }
+        if c == '\x1b' {
+            in_escape = true;
+            continue;
+        }
+        let char_width = c.width().unwrap_or(0);
+        if used > 0 && used + char_width > width {
+            return (text[..i].to_string(), text[i..].to_string());
+        }
+        used += char_width;
+    }
+    (text.to_string(), String::new())
+}
+
+/// Lays out `rows` as columns `gap` spaces apart within `width` columns. Returns the lines of each
+/// row, which are several when one of its cells is wrapped.
+pub fn table<R: AsRef<[String]>>(rows: &[R], width: usize, gap: usize) -> Vec<Vec<String>> {
+    let columns = rows.iter().map(|row| row.as_ref().len()).max().unwrap_or(0);
+    let mut widths = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.as_ref().get(column))
+                .map(|cell| display_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let available = width.saturating_sub(gap * columns.saturating_sub(1));
+    while widths.iter().sum::<usize>() > available {
+        match widths.iter_mut().max() {
+            Some(widest) if *widest > MIN_COLUMN_WIDTH => *widest -= 1,
+            _ => break,
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let cells = row
+                .as_ref()
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| wrap(cell, *width))
+                .collect::<Vec<_>>();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+            (0..height)
+                .map(|line| {
+                    let mut text = String::new();
+                    for (column, cell) in cells.iter().enumerate() {
+                        if column > 0 {
+                            text.push_str(&" ".repeat(gap));
+                        }
+                        let part = cell.get(line).map(String::as_str).unwrap_or_default();
+                        text.push_str(part);
+                        text.push_str(&" ".repeat(widths[column].saturating_sub(display_width(part))));
+                    }
+                    text.trim_end().to_string()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::style::Stylize;
+
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("fits on a line", 20), vec!["fits on a line"]);
+        assert_eq!(wrap("the quick brown fox jumps", 10), vec![
+            "the quick",
+            "brown fox",
+            "jumps"
+        ]);
+        assert_eq!(wrap("a verylongword b", 5), vec!["a", "veryl", "ongwo", "rd b"]);
+        assert_eq!(wrap("one\ntwo three", 5), vec!["one", "two", "three"]);
+        assert_eq!(wrap("日本語の文", 4), vec!["日本", "語の", "文"]);
+
+        let styled = format!("{} words", "styled".bold());
+        assert_eq!(wrap(&styled, 12), vec![styled.clone()]);
+        assert_eq!(wrap(&styled, 6).len(), 2);
+    }
+
+    #[test]
+    fn test_table() {
+        let rows = vec![vec!["Name".to_string(), "Command".to_string()], vec![
+            "lint".to_string(),
+            "cargo clippy --all-targets".to_string(),
+        ]];
+        assert_eq!(table(&rows, 80, 2), vec![vec!["Name  Command"], vec![
+            "lint  cargo clippy --all-targets"
+        ]]);
+        assert_eq!(table(&rows, 20, 2), vec![vec!["Name  Command"], vec![
+            "lint  cargo clippy",
+            "      --all-targets"
+        ]]);
+
+        // Columns aren't narrowed below the minimum, the terminal wraps what's left.
+        assert_eq!(table(&rows, 5, 2)[1][0], "lint  cargo");
+    }
+}
@@ -1,5 +1,10 @@
+pub mod clipboard;
+pub mod documents;
+pub mod format;
+pub mod glyphs;
 pub mod images;
 pub mod issue;
+pub mod layout;
 #[cfg(test)]
 pub mod test;
 pub mod ui;
@@ -15,6 +20,8 @@ use eyre::Result;
 
 use super::ChatError;
 use super::token_counter::TokenCounter;
+use crate::database::settings::Setting;
+use crate::os::Os;
 
 pub fn truncate_safe(s: &str, max_bytes: usize) -> &str {
     if s.len() <= max_bytes {
@@ -47,6 +54,27 @@ pub fn animate_output(output: &mut impl Write, bytes: &[u8]) -> Result<(), ChatE
     Ok(())
 }
 
+/// Whether we're running on a remote machine (e.g. over SSH), where things like the system
+/// clipboard and desktop notifications don't reach the user.
+///
+/// `chat.remoteMode` overrides the detection when set.
+pub fn is_remote_session(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ChatRemoteMode)
+        .unwrap_or_else(|| os.env.in_ssh())
+}
+
+/// Whether notifications are enabled for this session. Notifications are always off in remote
+/// sessions.
+pub fn notifications_enabled(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ChatEnableNotifications)
+        .unwrap_or(false)
+        && !is_remote_session(os)
+}
+
 /// Play the terminal bell notification sound
 pub fn play_notification_bell(requires_confirmation: bool) {
     // Don't play bell for tools that don't require confirmation
@@ -230,4 +258,27 @@ mod tests {
         }
         assert_eq!(files.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_is_remote_session() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ChatEnableNotifications, true)
+            .await
+            .unwrap();
+        assert!(!is_remote_session(&os));
+        assert!(notifications_enabled(&os));
+
+        unsafe {
+            os.env.set_var("SSH_TTY", "/dev/pts/0");
+        }
+        assert!(is_remote_session(&os));
+        assert!(!notifications_enabled(&os));
+
+        // The setting overrides detection in both directions.
+        os.database.settings.set(Setting::ChatRemoteMode, false).await.unwrap();
+        assert!(!is_remote_session(&os));
+        assert!(notifications_enabled(&os));
+    }
 }
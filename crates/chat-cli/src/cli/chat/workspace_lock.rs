@@ -0,0 +1,190 @@
+//! Advisory locks on the workspace a session edits, so that two `q chat` sessions in the same
+//! directory don't overwrite each other's files and checkpoints.
+//!
+//! A session takes the lock of its working directory when it starts, a file in
+//! [directories::chat_workspace_locks_dir] holding its process id, and removes it when it exits.
+//! A session that finds the lock held by a running process warns about it and can continue
+//! read-only, refusing the tools that change anything. Locks of processes that are no longer
+//! running, e.g. after a crash, are taken over.
+
+use std::io::ErrorKind;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::os::Os;
+use crate::util::directories;
+
+/// The session holding a lock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub conversation_id: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub since: OffsetDateTime,
+}
+
+/// A lock held by this process, released with [WorkspaceLock::release].
+#[derive(Debug)]
+pub struct WorkspaceLock {
+    path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum Acquired {
+    Held(WorkspaceLock),
+    /// Held by another running session.
+    Busy(LockHolder),
+}
+
+fn lock_path(os: &Os, workspace: &Path) -> Result<PathBuf> {
+    let digest = hex::encode(Sha256::digest(workspace.to_string_lossy().as_bytes()));
+    Ok(directories::chat_workspace_locks_dir(os)?.join(format!("{digest}.json")))
+}
+
+/// Takes the lock of `workspace` for the session `conversation_id`, unless another running
+/// session holds it.
+pub async fn acquire(os: &Os, workspace: &Path, conversation_id: &str) -> Result<Acquired> {
+    let path = lock_path(os, workspace)?;
+    if let Some(parent) = path.parent() {
+        os.fs.create_dir_all(parent).await?;
+    }
+    let holder = LockHolder {
+        pid: std::process::id(),
+        conversation_id: conversation_id.to_string(),
+        since: OffsetDateTime::now_utc(),
+    };
+
+    // The holder is written to a file of this process first and then linked into place, so that
+    // other sessions never read a lock that is only partly written.
+    let written = path.with_extension(format!("{}.tmp", holder.pid));
+    os.fs.write(&written, serde_json::to_vec(&holder)?).await?;
+    let result = link_lock(os, &written, &path, &holder).await;
+    if let Err(err) = os.fs.remove_file(&written).await {
+        warn!(?err, "failed to remove the written workspace lock");
+    }
+    result
+}
+
+/// Links the lock `written` for `holder` to `path`, unless another running session holds it.
+async fn link_lock(os: &Os, written: &Path, path: &Path, holder: &LockHolder) -> Result<Acquired> {
+    // Once more after removing a stale lock, in case another session took it in between.
+    for _ in 0..2 {
+        match os.fs.hard_link(written, path).await {
+            Ok(()) => {
+                return Ok(Acquired::Held(WorkspaceLock {
+                    path: path.to_path_buf(),
+                }));
+            },
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        // A lock that can't be read is corrupt, e.g. written by a session that crashed.
+        let current = os
+            .fs
+            .read(&path)
+            .await
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<LockHolder>(&contents).ok());
+        match current {
+            Some(current) if current.pid != holder.pid && os.sysinfo.is_pid_running(current.pid) => {
+                return Ok(Acquired::Busy(current));
+            },
+            _ => os.fs.remove_file(path).await?,
+        }
+    }
+    bail!("Failed to take the workspace lock {}", path.display())
+}
+
+impl WorkspaceLock {
+    /// Removes the lock, unless another session took it over in the meantime.
+    pub async fn release(self, os: &Os) {
+        let held = os
+            .fs
+            .read(&self.path)
+            .await
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<LockHolder>(&contents).ok())
+            .is_some_and(|holder| holder.pid == std::process::id());
+        if held {
+            if let Err(err) = os.fs.remove_file(&self.path).await {
+                warn!(?err, "failed to release the workspace lock");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire() {
+        let os = Os::new().await.unwrap();
+        let workspace = Path::new("/workspace");
+
+        let Acquired::Held(lock) = acquire(&os, workspace, "first").await.unwrap() else {
+            panic!("the lock should be free");
+        };
+        // Taken again by the same process, e.g. after /resume.
+        assert!(matches!(
+            acquire(&os, workspace, "second").await.unwrap(),
+            Acquired::Held(_)
+        ));
+        lock.release(&os).await;
+        assert!(!os.fs.exists(lock_path(&os, workspace).unwrap()));
+
+        // Nothing but the lock is left behind.
+        let locks_dir = directories::chat_workspace_locks_dir(&os).unwrap();
+        let mut entries = os.fs.read_dir(&locks_dir).await.unwrap();
+        let mut count = 0;
+        while entries.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 0);
+
+        // Held by another running session.
+        let other = LockHolder {
+            pid: 4242,
+            conversation_id: "other".to_string(),
+            since: OffsetDateTime::now_utc(),
+        };
+        let path = lock_path(&os, workspace).unwrap();
+        os.fs.write(&path, serde_json::to_vec(&other).unwrap()).await.unwrap();
+        os.sysinfo.add_running_pids(&[4242]);
+        match acquire(&os, workspace, "mine").await.unwrap() {
+            Acquired::Busy(holder) => assert_eq!(holder, other),
+            Acquired::Held(_) => panic!("the lock is held by another session"),
+        }
+        // Other workspaces are free.
+        assert!(matches!(
+            acquire(&os, Path::new("/elsewhere"), "mine").await.unwrap(),
+            Acquired::Held(_)
+        ));
+
+        // Taken over once the other session is gone.
+        let gone = LockHolder { pid: 4343, ..other };
+        os.fs.write(&path, serde_json::to_vec(&gone).unwrap()).await.unwrap();
+        assert!(matches!(
+            acquire(&os, workspace, "mine").await.unwrap(),
+            Acquired::Held(_)
+        ));
+    }
+}
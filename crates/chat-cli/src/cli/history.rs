@@ -0,0 +1,145 @@
+use std::process::ExitCode;
+
+use anstream::println;
+use clap::Subcommand;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+};
+use serde_json::json;
+
+use super::OutputFormat;
+use crate::cli::chat::ChatArgs;
+use crate::cli::chat::store::{
+    ConversationStore,
+    RetentionPolicy,
+    format_size,
+    parse_size,
+};
+use crate::os::Os;
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum HistorySubcommand {
+    /// List stored conversations
+    List {
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Delete stored conversations
+    #[command(alias = "rm")]
+    Delete {
+        /// Ids of the conversations, or prefixes of them
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Continue a stored conversation in a new chat session
+    Open {
+        /// Id of the conversation, or a prefix of one
+        id: String,
+    },
+    /// Remove the oldest stored conversations
+    Gc {
+        /// Number of conversations to keep
+        #[arg(long)]
+        keep: Option<usize>,
+        /// Maximum total size of stored conversations, e.g. 200MB
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+    },
+}
+
+impl HistorySubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let store = ConversationStore::new(os)?;
+        match self {
+            Self::List { format } => {
+                let mut entries = store.entries(os).await?.into_iter().collect::<Vec<_>>();
+                entries.sort_by(|(_, a), (_, b)| b.updated_at.cmp(&a.updated_at));
+                format.print(
+                    || {
+                        if entries.is_empty() {
+                            return "No stored conversations".to_string();
+                        }
+                        let total = entries.iter().map(|(_, entry)| entry.size).sum::<u64>();
+                        let mut out = String::new();
+                        for (id, entry) in &entries {
+                            out.push_str(&format!(
+                                "{id}  {}  {:>9}  {:>13}  {}\n",
+                                entry.updated_at.date(),
+                                format_size(entry.size),
+                                entry
+                                    .tokens
+                                    .map(|tokens| format!("{tokens} tokens"))
+                                    .unwrap_or_default(),
+                                entry.model.as_deref().unwrap_or_default(),
+                            ));
+                            out.push_str(&format!(
+                                "    {}  {}\n",
+                                entry.title.as_deref().unwrap_or("(untitled)"),
+                                entry
+                                    .cwd
+                                    .as_ref()
+                                    .map(|cwd| cwd.display().to_string())
+                                    .unwrap_or_default()
+                                    .dark_grey()
+                            ));
+                        }
+                        out.push_str(&format!("\n{} conversations, {}", entries.len(), format_size(total)));
+                        out
+                    },
+                    || {
+                        entries
+                            .iter()
+                            .map(|(id, entry)| {
+                                let mut value = json!(entry);
+                                value["id"] = json!(id);
+                                value
+                            })
+                            .collect::<Vec<_>>()
+                    },
+                );
+            },
+            Self::Delete { ids } => {
+                let mut found = Vec::new();
+                for id in ids {
+                    let (id, entry) = store.find(os, &id).await?;
+                    if entry.is_running(os) {
+                        bail!("Conversation {id} is open in a running q chat session");
+                    }
+                    found.push(id);
+                }
+                for id in &found {
+                    store.remove(os, id).await?;
+                    println!("Deleted {id}");
+                }
+            },
+            Self::Open { id } => {
+                return ChatArgs {
+                    session: Some(id),
+                    ..Default::default()
+                }
+                .execute(os)
+                .await;
+            },
+            Self::Gc { keep, max_size } => {
+                if keep.is_none() && max_size.is_none() {
+                    bail!("Specify at least one of --keep or --max-size");
+                }
+                let removed = store.gc(os, RetentionPolicy { keep, max_size }).await?;
+                println!(
+                    "Removed {} {}",
+                    removed.len(),
+                    if removed.len() == 1 {
+                        "conversation"
+                    } else {
+                        "conversations"
+                    }
+                );
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -0,0 +1,290 @@
+//! `q install-hooks` writes a git hook that has Q review changes before they are committed or
+//! pushed.
+//!
+//! The hook writes the changes to `changes.diff` in the `q-review` directory of the repository's
+//! git directory, which it passes to the recipe as `Q_REVIEW_DIR`, and runs
+//! `q chat --no-interactive --recipe .amazonq/review-staged.yaml`, whose prompt asks for the most
+//! severe finding on a final `SEVERITY:` line. The hook fails when that severity is at or above
+//! `--block-on`. Reviews are cached by the hash of the diff, the recipe and `--block-on`, so
+//! running the hook again on the same changes doesn't call the API.
+//!
+//! The review runs unattended on changes that may carry instructions aimed at the model, so an
+//! existing recipe that trusts tools is only used with `--force`.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+use anstream::println;
+use clap::{
+    Args,
+    ValueEnum,
+};
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+};
+use tokio::process::Command;
+
+use crate::cli::chat::recipe::Recipe;
+use crate::os::Os;
+use crate::util::CLI_BINARY_NAME;
+
+/// Recipe run by the hook, relative to the repository root.
+const RECIPE_PATH: &str = ".amazonq/review-staged.yaml";
+
+const RECIPE: &str = r#"description: Review the changes about to be committed or pushed
+context:
+  - ${Q_REVIEW_DIR}/changes.diff
+prompts:
+  - >-
+    Review the diff in changes.diff for bugs, security issues and risky changes.
+    List each finding with its file, a severity of LOW, MEDIUM, HIGH or CRITICAL, and a short
+    explanation. End with a single line "SEVERITY: <level>" naming the most severe finding, or
+    "SEVERITY: NONE" if there are none.
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GitHook {
+    /// Review staged changes before each commit
+    PreCommit,
+    /// Review the commits being pushed
+    PrePush,
+}
+
+impl GitHook {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::PreCommit => "pre-commit",
+            Self::PrePush => "pre-push",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Severities at or above `self`, as named in the review.
+    fn and_above(self) -> Vec<&'static str> {
+        Self::value_variants()
+            .iter()
+            .filter(|severity| **severity >= self)
+            .map(|severity| match severity {
+                Self::Low => "LOW",
+                Self::Medium => "MEDIUM",
+                Self::High => "HIGH",
+                Self::Critical => "CRITICAL",
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct InstallHooksArgs {
+    /// Git hook to install
+    #[arg(long, value_enum, default_value_t = GitHook::PreCommit)]
+    pub hook: GitHook,
+    /// Lowest severity of a finding that blocks the commit or push
+    #[arg(long, value_enum, default_value_t = Severity::High)]
+    pub block_on: Severity,
+    /// Overwrite an existing hook, and use an existing review recipe even if it trusts tools
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+impl InstallHooksArgs {
+    pub async fn execute(self, os: &Os) -> Result<ExitCode> {
+        let cwd = os.env.current_dir()?;
+        let root = PathBuf::from(git(&cwd, &["rev-parse", "--show-toplevel"]).await?);
+        let hooks_dir = cwd.join(git(&cwd, &["rev-parse", "--git-path", "hooks"]).await?);
+
+        let recipe_path = root.join(RECIPE_PATH);
+        let existing_recipe = os.fs.exists(&recipe_path);
+        if existing_recipe && !self.force && Recipe::load(os, &recipe_path).await?.trusts_tools() {
+            bail!(
+                "{} trusts tools, which would run without confirmation on every review. Remove its \
+                 trust_tools and trust_all_tools, or use it anyway with {}",
+                recipe_path.display(),
+                "--force".bold()
+            );
+        }
+
+        let hook_path = hooks_dir.join(self.hook.file_name());
+        if os.fs.exists(&hook_path) && !self.force {
+            bail!(
+                "{} already exists. To overwrite it, use {}",
+                hook_path.display(),
+                "--force".bold()
+            );
+        }
+        os.fs.create_dir_all(&hooks_dir).await?;
+        os.fs.write(&hook_path, hook_script(self.hook, self.block_on)).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            os.fs
+                .set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))
+                .await?;
+        }
+        println!("Installed {}", hook_path.display());
+
+        if existing_recipe {
+            println!("Using the existing review recipe at {}", recipe_path.display());
+        } else {
+            if let Some(parent) = recipe_path.parent() {
+                os.fs.create_dir_all(parent).await?;
+            }
+            os.fs.write(&recipe_path, RECIPE).await?;
+            println!("Wrote the review recipe to {}", recipe_path.display());
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+async fn git(cwd: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(cwd).output().await?;
+    if !output.status.success() {
+        bail!(
+            "Couldn't find a git repository: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The shell script for `hook`, blocking on findings at or above `block_on`.
+fn hook_script(hook: GitHook, block_on: Severity) -> String {
+    let write_diff = match hook {
+        GitHook::PreCommit => "git diff --cached --no-color > \"$review_dir/changes.diff\"",
+        GitHook::PrePush => {
+            r#"zero=0000000000000000000000000000000000000000
+: > "$review_dir/changes.diff"
+while read -r local_ref local_sha remote_ref remote_sha; do
+    [ "$local_sha" = "$zero" ] && continue
+    if [ "$remote_sha" = "$zero" ]; then
+        remote_sha=$(git merge-base "$local_sha" "$(git rev-parse --abbrev-ref origin/HEAD 2>/dev/null || echo HEAD)" 2>/dev/null) || continue
+    fi
+    git diff --no-color "$remote_sha" "$local_sha" >> "$review_dir/changes.diff"
+done"#
+        },
+    };
+
+    format!(
+        r#"#!/bin/sh
+# Installed by `{CLI_BINARY_NAME} install-hooks`: has Amazon Q review changes and blocks on
+# findings of severity {blocking}. Skip it once with `git {action} --no-verify`.
+cd "$(git rev-parse --show-toplevel)" || exit 1
+review_dir=$(git rev-parse --git-path q-review) || exit 1
+mkdir -p "$review_dir/cache"
+export Q_REVIEW_DIR="$review_dir"
+
+{write_diff}
+[ -s "$review_dir/changes.diff" ] || exit 0
+
+key=$( (cat "$review_dir/changes.diff" {RECIPE_PATH} 2>/dev/null; echo "block-on {blocking}") | git hash-object --stdin)
+review="$review_dir/cache/$key.txt"
+if [ ! -f "$review" ]; then
+    if ! {CLI_BINARY_NAME} chat --no-interactive --recipe {RECIPE_PATH} > "$review.tmp"; then
+        rm -f "$review.tmp"
+        echo "Amazon Q review failed, not blocking the {action}." >&2
+        exit 0
+    fi
+    mv "$review.tmp" "$review"
+fi
+
+cat "$review" >&2
+severity=$(grep -o 'SEVERITY: *[A-Z]*' "$review" | tail -n 1 | sed 's/SEVERITY: *//')
+for blocked in {blocking}; do
+    if [ "$severity" = "$blocked" ]; then
+        echo "Amazon Q found a $severity severity issue, blocking the {action}." >&2
+        exit 1
+    fi
+done
+"#,
+        blocking = block_on.and_above().join(" "),
+        action = match hook {
+            GitHook::PreCommit => "commit",
+            GitHook::PrePush => "push",
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_and_above() {
+        assert_eq!(Severity::High.and_above(), ["HIGH", "CRITICAL"]);
+        assert_eq!(Severity::Low.and_above(), ["LOW", "MEDIUM", "HIGH", "CRITICAL"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hook_script_blocks_on_severity() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        git(repo, &["init", "--quiet"]).await.unwrap();
+
+        // Stand in for q, counting how often the review runs.
+        let bin = repo.join("bin");
+        std::fs::create_dir(&bin).unwrap();
+        std::fs::write(
+            bin.join(CLI_BINARY_NAME),
+            "#!/bin/sh\necho run >> \"$RUNS\"\necho \"$REVIEW\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(bin.join(CLI_BINARY_NAME), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let hook = repo.join("hook.sh");
+        std::fs::write(&hook, hook_script(GitHook::PreCommit, Severity::High)).unwrap();
+
+        let runs = repo.join("runs");
+        let run_hook = async |review: &str| {
+            let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+            Command::new("sh")
+                .arg(&hook)
+                .current_dir(repo)
+                .env("PATH", path)
+                .env("RUNS", &runs)
+                .env("REVIEW", review)
+                .output()
+                .await
+                .unwrap()
+                .status
+                .success()
+        };
+
+        // Nothing staged, nothing to review.
+        assert!(run_hook("SEVERITY: CRITICAL").await);
+        assert!(!runs.exists());
+
+        std::fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(repo, &["add", "a.txt"]).await.unwrap();
+        assert!(!run_hook("SEVERITY: HIGH").await);
+        // The cached review is used for the same diff.
+        assert!(!run_hook("SEVERITY: NONE").await);
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 1);
+        assert!(repo.join(".git/q-review/changes.diff").exists());
+        // But not once the recipe changed.
+        std::fs::create_dir_all(repo.join(".amazonq")).unwrap();
+        std::fs::write(repo.join(RECIPE_PATH), RECIPE).unwrap();
+        assert!(run_hook("SEVERITY: NONE").await);
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 2);
+
+        std::fs::write(repo.join("a.txt"), "two\n").unwrap();
+        git(repo, &["add", "a.txt"]).await.unwrap();
+        assert!(run_hook("SEVERITY: MEDIUM").await);
+    }
+}
@@ -4,18 +4,29 @@ use clap::Args;
 use eyre::Result;
 
 use crate::os::Os;
+use crate::os::diagnostics::DiagnosticsBundle;
+use crate::util::redact::Redactor;
 
 #[derive(Clone, Debug, Args, PartialEq, Eq)]
 pub struct IssueArgs {
     /// Force issue creation
     #[arg(long, short = 'f')]
     force: bool,
+    /// Attach a redacted diagnostics bundle: version, last error, changed settings and recent logs
+    #[arg(long, short = 'd')]
+    diagnostics: bool,
     /// Issue description
     description: Vec<String>,
 }
 
 impl IssueArgs {
     pub async fn execute(&self, os: &Os) -> Result<ExitCode> {
+        self.execute_with_last_error(os, None).await
+    }
+
+    /// Like [Self::execute], with the last error shown in the chat session for the diagnostics
+    /// bundle.
+    pub async fn execute_with_last_error(&self, os: &Os, last_error: Option<&str>) -> Result<ExitCode> {
         let joined_description = self.description.join(" ").trim().to_owned();
 
         let issue_title = match joined_description.len() {
@@ -25,12 +36,21 @@ impl IssueArgs {
             _ => joined_description,
         };
 
+        let additional_environment = match self.diagnostics {
+            true => Some(
+                DiagnosticsBundle::new(os, last_error)
+                    .await
+                    .to_markdown(&Redactor::new(os)),
+            ),
+            false => None,
+        };
+
         let _ = crate::cli::chat::util::issue::IssueCreator {
             title: Some(issue_title),
             expected_behavior: None,
             actual_behavior: None,
             steps_to_reproduce: None,
-            additional_environment: None,
+            additional_environment,
         }
         .create_url(os)
         .await;
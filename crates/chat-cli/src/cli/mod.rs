@@ -1,7 +1,10 @@
+mod changelog;
 mod chat;
 mod debug;
 mod diagnostics;
 mod feed;
+mod history;
+mod install_hooks;
 mod issue;
 mod mcp;
 mod settings;
@@ -35,7 +38,10 @@ use tracing::{
     debug,
 };
 
+use crate::cli::changelog::ChangelogArgs;
 use crate::cli::chat::ChatArgs;
+use crate::cli::history::HistorySubcommand;
+use crate::cli::install_hooks::InstallHooksArgs;
 use crate::cli::mcp::McpSubcommand;
 use crate::cli::user::{
     LoginArgs,
@@ -112,6 +118,13 @@ pub enum RootSubcommand {
     /// Model Context Protocol (MCP)
     #[command(subcommand)]
     Mcp(McpSubcommand),
+    /// Manage stored chat sessions
+    #[command(subcommand, visible_alias = "sessions")]
+    History(HistorySubcommand),
+    /// Install a git hook that has Amazon Q review changes before they are committed or pushed
+    InstallHooks(InstallHooksArgs),
+    /// Draft release notes from the commits since a tag and add them to the changelog
+    Changelog(ChangelogArgs),
 }
 
 impl RootSubcommand {
@@ -123,7 +136,10 @@ impl RootSubcommand {
     }
 
     pub fn requires_auth(&self) -> bool {
-        matches!(self, Self::Chat(_) | Self::Profile)
+        matches!(
+            self,
+            Self::Chat(_) | Self::Profile | Self::History(HistorySubcommand::Open { .. }) | Self::Changelog(_)
+        )
     }
 
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
@@ -151,6 +167,9 @@ impl RootSubcommand {
             Self::Version { changelog } => Cli::print_version(changelog),
             Self::Chat(args) => args.execute(os).await,
             Self::Mcp(args) => args.execute(os, &mut std::io::stderr()).await,
+            Self::History(subcommand) => subcommand.execute(os).await,
+            Self::InstallHooks(args) => args.execute(os).await,
+            Self::Changelog(args) => args.execute(os).await,
         }
     }
 }
@@ -174,6 +193,9 @@ impl Display for RootSubcommand {
             Self::Issue(_) => "issue",
             Self::Version { .. } => "version",
             Self::Mcp(_) => "mcp",
+            Self::History(_) => "history",
+            Self::InstallHooks(_) => "install-hooks",
+            Self::Changelog(_) => "changelog",
         };
 
         write!(f, "{name}")
@@ -317,7 +339,10 @@ impl Cli {
 
 #[cfg(test)]
 mod test {
+    use std::path::PathBuf;
+
     use super::*;
+    use crate::cli::chat::OutputMode;
     use crate::util::CHAT_BINARY_NAME;
     use crate::util::test::assert_parse;
 
@@ -350,18 +375,81 @@ mod test {
         assert_eq!(Cli::parse_from([CHAT_BINARY_NAME, "chat", "-vv"]), Cli {
             subcommand: Some(RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })),
             verbose: 2,
             help_all: false,
         });
     }
 
+    #[test]
+    fn test_history_gc() {
+        assert_parse!(
+            ["history", "gc", "--keep", "50", "--max-size", "200MB"],
+            RootSubcommand::History(HistorySubcommand::Gc {
+                keep: Some(50),
+                max_size: Some(200_000_000),
+            })
+        );
+    }
+
+    #[test]
+    fn test_sessions() {
+        assert_parse!(
+            ["sessions", "rm", "a1", "b2"],
+            RootSubcommand::History(HistorySubcommand::Delete {
+                ids: vec!["a1".to_string(), "b2".to_string()],
+            })
+        );
+        assert_parse!(
+            ["sessions", "open", "a1"],
+            RootSubcommand::History(HistorySubcommand::Open { id: "a1".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_install_hooks() {
+        use crate::cli::install_hooks::{
+            GitHook,
+            Severity,
+        };
+
+        assert_parse!(
+            ["install-hooks", "--hook", "pre-push", "--block-on", "medium"],
+            RootSubcommand::InstallHooks(InstallHooksArgs {
+                hook: GitHook::PrePush,
+                block_on: Severity::Medium,
+                force: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_changelog() {
+        assert_parse!(
+            ["changelog", "--since", "v1.0.0", "--version", "1.1.0", "-y"],
+            RootSubcommand::Changelog(ChangelogArgs {
+                since: Some("v1.0.0".to_string()),
+                version: "1.1.0".to_string(),
+                file: PathBuf::from("CHANGELOG.md"),
+                yes: true,
+            })
+        );
+    }
+
     #[test]
     fn test_version_changelog() {
         assert_parse!(["version", "--changelog"], RootSubcommand::Version {
@@ -389,12 +477,20 @@ mod test {
             ["chat", "--profile", "my-profile"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: None,
                 profile: Some("my-profile".to_string()),
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
     }
@@ -405,12 +501,20 @@ mod test {
             ["chat", "--profile", "my-profile", "Hello"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: Some("Hello".to_string()),
                 profile: Some("my-profile".to_string()),
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
     }
@@ -421,12 +525,20 @@ mod test {
             ["chat", "--profile", "my-profile", "--trust-all-tools"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: None,
                 profile: Some("my-profile".to_string()),
                 model: None,
                 trust_all_tools: true,
                 trust_tools: None,
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
     }
@@ -437,26 +549,199 @@ mod test {
             ["chat", "--no-interactive", "--resume"],
             RootSubcommand::Chat(ChatArgs {
                 resume: true,
+                session: None,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                no_interactive: true
+                no_interactive: true,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
         assert_parse!(
             ["chat", "--non-interactive", "-r"],
             RootSubcommand::Chat(ChatArgs {
                 resume: true,
+                session: None,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: true,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_with_recipe() {
+        assert_parse!(
+            ["chat", "--recipe", "release-notes.yaml"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                session: None,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: false,
+                recipe: Some("release-notes.yaml".into()),
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--recipe", "r.yaml", "--resume"]).is_err());
+    }
+
+    #[test]
+    fn test_chat_with_replay() {
+        assert_parse!(
+            ["chat", "--replay", "session.json", "--replay-approved-tools"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                session: None,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                no_interactive: true
+                no_interactive: false,
+                recipe: None,
+                replay: Some("session.json".into()),
+                replay_approved_tools: true,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--replay", "a.json", "--recipe", "r.yaml"]).is_err());
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--replay-approved-tools"]).is_err());
+    }
+
+    #[test]
+    fn test_chat_with_session() {
+        assert_parse!(
+            ["chat", "--session", "a1"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                session: Some("a1".to_string()),
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--session", "a1", "--resume"]).is_err());
+    }
+
+    #[test]
+    fn test_chat_with_transcript() {
+        assert_parse!(
+            ["chat", "--transcript", "session.jsonl"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                session: None,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: Some("session.jsonl".into()),
+                output: OutputMode::Text,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_with_response_schema() {
+        assert_parse!(
+            [
+                "chat",
+                "--no-interactive",
+                "--response-schema",
+                "schema.json",
+                "--schema-retries",
+                "3"
+            ],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                session: None,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: true,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: Some("schema.json".into()),
+                schema_retries: Some(3),
+                transcript: None,
+                output: OutputMode::Text,
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--response-schema", "schema.json"]).is_err());
+    }
+
+    #[test]
+    fn test_chat_with_json_stream_output() {
+        assert_parse!(
+            ["chat", "--no-interactive", "--output", "json-stream", "hello"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                session: None,
+                input: Some("hello".to_string()),
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: true,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::JsonStream,
             })
         );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--output", "json-stream"]).is_err());
     }
 
     #[test]
@@ -465,12 +750,20 @@ mod test {
             ["chat", "--trust-all-tools"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: true,
                 trust_tools: None,
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
     }
@@ -481,12 +774,20 @@ mod test {
             ["chat", "--trust-tools="],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: Some(vec!["".to_string()]),
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
     }
@@ -497,12 +798,20 @@ mod test {
             ["chat", "--trust-tools=fs_read,fs_write"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                session: None,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: Some(vec!["fs_read".to_string(), "fs_write".to_string()]),
-                no_interactive: false
+                no_interactive: false,
+                recipe: None,
+                replay: None,
+                replay_approved_tools: false,
+                response_schema: None,
+                schema_retries: None,
+                transcript: None,
+                output: OutputMode::Text,
             })
         );
     }
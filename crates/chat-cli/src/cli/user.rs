@@ -21,6 +21,8 @@ use eyre::{
     bail,
 };
 use serde_json::json;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 use tokio::signal::ctrl_c;
 use tracing::{
     error,
@@ -194,19 +196,35 @@ pub struct WhoamiArgs {
 
 impl WhoamiArgs {
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        if crate::auth::builder_id::is_sigv4() {
+            self.format.print(
+                || "Using IAM credentials from the environment (AMAZON_Q_SIGV4)",
+                || json!({ "accountType": "Iam" }),
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+
         let builder_id = BuilderIdToken::load(&os.database).await;
 
         match builder_id {
             Ok(Some(token)) => {
+                let expiry = format_expiry(token.expires_at, OffsetDateTime::now_utc());
                 self.format.print(
-                    || match token.token_type() {
-                        TokenType::BuilderId => "Logged in with Builder ID".into(),
-                        TokenType::IamIdentityCenter => {
-                            format!(
-                                "Logged in with IAM Identity Center ({})",
-                                token.start_url.as_ref().unwrap()
-                            )
-                        },
+                    || {
+                        let account = match token.token_type() {
+                            TokenType::BuilderId => "Logged in with Builder ID".into(),
+                            TokenType::IamIdentityCenter => {
+                                format!(
+                                    "Logged in with IAM Identity Center ({})",
+                                    token.start_url.as_ref().unwrap()
+                                )
+                            },
+                        };
+                        let refresh = match token.refresh_token {
+                            Some(_) => ", and is refreshed automatically",
+                            None => "",
+                        };
+                        format!("{account}\nThe token expires {expiry}{refresh}")
                     },
                     || {
                         json!({
@@ -216,6 +234,7 @@ impl WhoamiArgs {
                             },
                             "startUrl": token.start_url,
                             "region": token.region,
+                            "expiresAt": token.expires_at.format(&Rfc3339).ok(),
                         })
                     },
                 );
@@ -236,6 +255,19 @@ impl WhoamiArgs {
     }
 }
 
+/// Describes when a token expires, e.g. `in 42 minutes`.
+pub fn format_expiry(expires_at: OffsetDateTime, now: OffsetDateTime) -> String {
+    let minutes = (expires_at - now).whole_minutes();
+    let (value, unit) = match minutes {
+        ..0 => return "now".to_string(),
+        0 => return "in less than a minute".to_string(),
+        1..60 => (minutes, "minute"),
+        60..1440 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+    format!("in {value} {unit}{}", if value == 1 { "" } else { "s" })
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum LicenseType {
     /// Free license with Builder ID
@@ -424,3 +456,21 @@ async fn select_profile_interactive(os: &mut Os, whoami: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_expiry() {
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(format_expiry(now - Duration::minutes(5), now), "now");
+        assert_eq!(format_expiry(now + Duration::seconds(30), now), "in less than a minute");
+        assert_eq!(format_expiry(now + Duration::minutes(1), now), "in 1 minute");
+        assert_eq!(format_expiry(now + Duration::minutes(42), now), "in 42 minutes");
+        assert_eq!(format_expiry(now + Duration::hours(8), now), "in 8 hours");
+        assert_eq!(format_expiry(now + Duration::days(30), now), "in 30 days");
+    }
+}
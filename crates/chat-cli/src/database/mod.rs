@@ -1,5 +1,6 @@
 pub mod settings;
 
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::path::Path;
 use std::str::FromStr;
@@ -58,6 +59,7 @@ const CLIENT_ID_KEY: &str = "telemetryClientId";
 const CODEWHISPERER_PROFILE_KEY: &str = "api.codewhisperer.profile";
 const START_URL_KEY: &str = "auth.idc.start-url";
 const IDC_REGION_KEY: &str = "auth.idc.region";
+const TRUSTED_HOOK_SCRIPTS_KEY: &str = "chat.trustedHookScripts";
 // We include this key to remove for backwards compatibility
 const CUSTOMIZATION_STATE_KEY: &str = "api.selectedCustomization";
 
@@ -300,6 +302,19 @@ impl Database {
         self.set_json_entry(Table::State, IDC_REGION_KEY, region)
     }
 
+    /// Get the workspace hook scripts trusted to run, by path, with the sha256 digest of their
+    /// contents when they were trusted.
+    pub fn get_trusted_hook_scripts(&self) -> Result<HashMap<String, String>, DatabaseError> {
+        Ok(self
+            .get_json_entry(Table::State, TRUSTED_HOOK_SCRIPTS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Set the workspace hook scripts trusted to run.
+    pub fn set_trusted_hook_scripts(&self, scripts: &HashMap<String, String>) -> Result<usize, DatabaseError> {
+        self.set_json_entry(Table::State, TRUSTED_HOOK_SCRIPTS_KEY, scripts)
+    }
+
     // /// Get the model id used for last conversation state.
     // pub fn get_last_used_model_id(&self) -> Result<Option<String>, DatabaseError> {
     //     self.get_json_entry::<String>(Table::State, LAST_USED_MODEL_ID)
@@ -344,6 +359,14 @@ impl Database {
         self.set_json_entry(Table::Conversations, path, state)
     }
 
+    /// Delete the chat conversation stored for a path, if there is one.
+    pub fn delete_conversation_by_path(&mut self, path: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        match path.as_ref().to_str() {
+            Some(path) => self.delete_entry(Table::Conversations, path),
+            None => Ok(()),
+        }
+    }
+
     pub async fn get_secret(&self, key: &str) -> Result<Option<Secret>, DatabaseError> {
         trace!(key, "getting secret");
         Ok(self.get_entry::<String>(Table::Auth, key)?.map(Into::into))
@@ -27,12 +27,30 @@ pub enum Setting {
     ApiTimeout,
     ChatEditMode,
     ChatEnableNotifications,
+    ChatRemoteMode,
+    ChatEnableAutosave,
+    ChatAsciiOnly,
+    ChatEnableQuickActions,
+    ChatSandboxHooks,
+    ChatContextFollowSymlinks,
+    ChatContextOcr,
+    ChatContextRespectGitignore,
+    ChatEnableLocalAnalytics,
+    ChatExplainLevel,
+    ChatSpeakRate,
+    ChatSpeakVoice,
+    ChatTranscriptPath,
+    ChatUrlContextTtl,
+    ChatVoiceCommand,
+    ChatVoiceModel,
+    StorageEncrypt,
     ApiCodeWhispererService,
     ApiQService,
     McpInitTimeout,
     McpNoInteractiveTimeout,
     McpLoadedBefore,
     ChatDefaultModel,
+    FormatLocale,
 }
 
 impl AsRef<str> for Setting {
@@ -48,12 +66,30 @@ impl AsRef<str> for Setting {
             Self::ApiTimeout => "api.timeout",
             Self::ChatEditMode => "chat.editMode",
             Self::ChatEnableNotifications => "chat.enableNotifications",
+            Self::ChatRemoteMode => "chat.remoteMode",
+            Self::ChatEnableAutosave => "chat.enableAutosave",
+            Self::ChatAsciiOnly => "chat.asciiOnly",
+            Self::ChatEnableQuickActions => "chat.enableQuickActions",
+            Self::ChatSandboxHooks => "chat.sandboxHooks",
+            Self::ChatContextFollowSymlinks => "chat.contextFollowSymlinks",
+            Self::ChatContextOcr => "chat.contextOcr",
+            Self::ChatContextRespectGitignore => "chat.contextRespectGitignore",
+            Self::ChatEnableLocalAnalytics => "chat.enableLocalAnalytics",
+            Self::ChatExplainLevel => "chat.explainLevel",
+            Self::ChatSpeakRate => "chat.speakRate",
+            Self::ChatSpeakVoice => "chat.speakVoice",
+            Self::ChatTranscriptPath => "chat.transcriptPath",
+            Self::ChatUrlContextTtl => "chat.urlContextTtlSeconds",
+            Self::ChatVoiceCommand => "chat.voiceCommand",
+            Self::ChatVoiceModel => "chat.voiceModel",
+            Self::StorageEncrypt => "storage.encrypt",
             Self::ApiCodeWhispererService => "api.codewhisperer.service",
             Self::ApiQService => "api.q.service",
             Self::McpInitTimeout => "mcp.initTimeout",
             Self::McpNoInteractiveTimeout => "mcp.noInteractiveTimeout",
             Self::McpLoadedBefore => "mcp.loadedBefore",
             Self::ChatDefaultModel => "chat.defaultModel",
+            Self::FormatLocale => "format.locale",
         }
     }
 }
@@ -79,12 +115,30 @@ impl TryFrom<&str> for Setting {
             "api.timeout" => Ok(Self::ApiTimeout),
             "chat.editMode" => Ok(Self::ChatEditMode),
             "chat.enableNotifications" => Ok(Self::ChatEnableNotifications),
+            "chat.remoteMode" => Ok(Self::ChatRemoteMode),
+            "chat.enableAutosave" => Ok(Self::ChatEnableAutosave),
+            "chat.asciiOnly" => Ok(Self::ChatAsciiOnly),
+            "chat.enableQuickActions" => Ok(Self::ChatEnableQuickActions),
+            "chat.sandboxHooks" => Ok(Self::ChatSandboxHooks),
+            "chat.contextFollowSymlinks" => Ok(Self::ChatContextFollowSymlinks),
+            "chat.contextOcr" => Ok(Self::ChatContextOcr),
+            "chat.contextRespectGitignore" => Ok(Self::ChatContextRespectGitignore),
+            "chat.enableLocalAnalytics" => Ok(Self::ChatEnableLocalAnalytics),
+            "chat.explainLevel" => Ok(Self::ChatExplainLevel),
+            "chat.speakRate" => Ok(Self::ChatSpeakRate),
+            "chat.speakVoice" => Ok(Self::ChatSpeakVoice),
+            "chat.transcriptPath" => Ok(Self::ChatTranscriptPath),
+            "chat.urlContextTtlSeconds" => Ok(Self::ChatUrlContextTtl),
+            "chat.voiceCommand" => Ok(Self::ChatVoiceCommand),
+            "chat.voiceModel" => Ok(Self::ChatVoiceModel),
+            "storage.encrypt" => Ok(Self::StorageEncrypt),
             "api.codewhisperer.service" => Ok(Self::ApiCodeWhispererService),
             "api.q.service" => Ok(Self::ApiQService),
             "mcp.initTimeout" => Ok(Self::McpInitTimeout),
             "mcp.noInteractiveTimeout" => Ok(Self::McpNoInteractiveTimeout),
             "mcp.loadedBefore" => Ok(Self::McpLoadedBefore),
             "chat.defaultModel" => Ok(Self::ChatDefaultModel),
+            "format.locale" => Ok(Self::FormatLocale),
             _ => Err(DatabaseError::InvalidSetting(value.to_string())),
         }
     }
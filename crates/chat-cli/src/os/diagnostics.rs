@@ -10,9 +10,14 @@ use sysinfo::{
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
 
-use crate::os::Env;
+use crate::os::{
+    Env,
+    Os,
+};
 use crate::telemetry::InstallMethod;
 use crate::util::consts::build::HASH;
+use crate::util::directories::logs_dir;
+use crate::util::redact::Redactor;
 use crate::util::system_info::{
     OSVersion,
     os_version,
@@ -222,10 +227,123 @@ impl Diagnostics {
     }
 }
 
+/// Number of lines from the end of the chat log included in a [DiagnosticsBundle].
+const BUNDLE_LOG_LINES: usize = 40;
+/// GitHub rejects issue urls that are too long, so the bundle is truncated to stay well under it.
+const BUNDLE_MAX_BYTES: usize = 4000;
+
+/// Extra diagnostics attached to an issue with `/issue --diagnostics`, redacted with [Redactor]
+/// before being included in the report.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsBundle {
+    pub build_details: BuildDetails,
+    /// The last error shown in the chat session, including its chain of causes.
+    pub last_error: Option<String>,
+    /// Settings that differ from their defaults, i.e. every setting that has been set.
+    pub settings: BTreeMap<String, String>,
+    /// The end of the chat log.
+    pub recent_logs: Vec<String>,
+}
+
+impl DiagnosticsBundle {
+    pub async fn new(os: &Os, last_error: Option<&str>) -> Self {
+        let recent_logs = match logs_dir() {
+            Ok(dir) => match os.fs.read_to_string(dir.join("qchat.log")).await {
+                Ok(log) => {
+                    let lines = log.lines().collect::<Vec<_>>();
+                    lines[lines.len().saturating_sub(BUNDLE_LOG_LINES)..]
+                        .iter()
+                        .map(|line| (*line).to_string())
+                        .collect()
+                },
+                Err(_) => Vec::new(),
+            },
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            build_details: BuildDetails::new(),
+            last_error: last_error.map(str::to_string),
+            settings: os
+                .database
+                .settings
+                .map()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_string()))
+                .collect(),
+            recent_logs,
+        }
+    }
+
+    /// Formats the bundle as markdown, redacting it and dropping the oldest log lines if it
+    /// would otherwise be too long to include in an issue url.
+    pub fn to_markdown(&self, redactor: &Redactor) -> String {
+        let mut out = format!(
+            "### Diagnostics bundle\n\nVersion: {}{}\n",
+            self.build_details.version,
+            self.build_details
+                .hash
+                .map(|hash| format!(" ({hash})"))
+                .unwrap_or_default()
+        );
+        if let Some(error) = &self.last_error {
+            out.push_str(&format!("\nLast error:\n```\n{}\n```\n", error.trim_end()));
+        }
+        out.push_str("\nSettings changed from the defaults:\n");
+        if self.settings.is_empty() {
+            out.push_str("(none)\n");
+        }
+        for (key, value) in &self.settings {
+            out.push_str(&format!("- {key} = {value}\n"));
+        }
+        let mut out = redactor.redact(&out);
+
+        let mut logs = self
+            .recent_logs
+            .iter()
+            .map(|line| redactor.redact(line))
+            .collect::<Vec<_>>();
+        let budget = BUNDLE_MAX_BYTES.saturating_sub(out.len());
+        let mut size = logs.iter().map(|line| line.len() + 1).sum::<usize>();
+        let mut skip = 0;
+        while size > budget && skip < logs.len() {
+            size -= logs[skip].len() + 1;
+            skip += 1;
+        }
+        logs.drain(..skip);
+        if !logs.is_empty() {
+            out.push_str(&format!("\nRecent logs:\n```\n{}\n```\n", logs.join("\n")));
+        }
+        out
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_diagnostics_bundle_to_markdown() {
+        let bundle = DiagnosticsBundle {
+            build_details: BuildDetails {
+                version: "1.2.3".to_string(),
+                hash: None,
+                date: None,
+            },
+            last_error: Some("Amazon Q is having trouble responding right now: dispatch failure".to_string()),
+            settings: BTreeMap::from([("chat.defaultModel".to_string(), "\"claude\"".to_string())]),
+            recent_logs: (0..1000).map(|i| format!("line {i} token=abcdefghijkl")).collect(),
+        };
+        let markdown = bundle.to_markdown(&Redactor::default());
+        assert!(markdown.starts_with("### Diagnostics bundle\n\nVersion: 1.2.3\n"));
+        assert!(markdown.contains("dispatch failure"));
+        assert!(markdown.contains("- chat.defaultModel = \"claude\"\n"));
+        // Secrets in logs are redacted, and the oldest lines are dropped to fit.
+        assert!(markdown.contains("line 999 token=<redacted>"));
+        assert!(!markdown.contains("line 0 "));
+        assert!(markdown.len() <= BUNDLE_MAX_BYTES + "\nRecent logs:\n```\n\n```\n".len());
+    }
+
     #[tokio::test]
     async fn test_diagnostics_user_readable() {
         let env = Env::new();
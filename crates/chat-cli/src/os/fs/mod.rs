@@ -270,6 +270,17 @@ impl Fs {
         }
     }
 
+    /// Creates a new hard link `link` to the file `original`, failing if `link` already exists.
+    ///
+    /// This is a proxy to [`tokio::fs::hard_link`].
+    pub async fn hard_link(&self, original: impl AsRef<Path>, link: impl AsRef<Path>) -> io::Result<()> {
+        match self {
+            Self::Real => fs::hard_link(original, link).await,
+            Self::Chroot(root) => fs::hard_link(append(root.path(), original), append(root.path(), link)).await,
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
     /// Copies the contents of one file to another. This function will also copy the permission bits
     /// of the original file to the destination file.
     /// This function will overwrite the contents of to.
@@ -24,6 +24,7 @@ mod inner {
     #[derive(Debug, Clone, Default)]
     pub struct Fake {
         pub process_names: HashSet<String>,
+        pub pids: HashSet<u32>,
     }
 }
 
@@ -48,6 +49,20 @@ impl SysInfo {
         }
     }
 
+    /// Returns whether a process with the given pid is running.
+    pub fn is_pid_running(&self, pid: u32) -> bool {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real => {
+                let pid = sysinfo::Pid::from_u32(pid);
+                let mut system = sysinfo::System::new();
+                system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+                system.process(pid).is_some()
+            },
+            Inner::Fake(fake) => fake.lock().unwrap().pids.contains(&pid),
+        }
+    }
+
     pub fn add_running_processes(&self, process_names: &[&str]) {
         use inner::Inner;
         match &self.0 {
@@ -60,4 +75,12 @@ impl SysInfo {
             },
         }
     }
+
+    pub fn add_running_pids(&self, pids: &[u32]) {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real => panic!("unimplemented"),
+            Inner::Fake(fake) => fake.lock().unwrap().pids.extend(pids),
+        }
+    }
 }
@@ -64,7 +64,10 @@ pub mod env_var {
         Q_USING_ZSH_AUTOSUGGESTIONS = "Q_USING_ZSH_AUTOSUGGESTIONS",
 
         /// Overrides the path to the bundle metadata released with certain desktop builds.
-        Q_BUNDLE_METADATA_PATH = "Q_BUNDLE_METADATA_PATH"
+        Q_BUNDLE_METADATA_PATH = "Q_BUNDLE_METADATA_PATH",
+
+        /// Passphrase used to encrypt stored conversations instead of a key kept in the secret store
+        Q_STORAGE_PASSPHRASE = "Q_STORAGE_PASSPHRASE"
     }
 }
 
@@ -139,6 +139,41 @@ pub fn chat_profiles_dir(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(".aws").join("amazonq").join("profiles"))
 }
 
+/// The directory of the conversation store, which holds autosaved `q chat` sessions.
+pub fn chat_sessions_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("sessions"))
+}
+
+/// The directory of pages fetched for URL context rules.
+pub fn chat_url_cache_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("url_cache"))
+}
+
+/// The directory of objects fetched for `s3://` context rules.
+pub fn chat_s3_cache_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("s3_cache"))
+}
+
+/// Large tool outputs stored for `summarize_output`.
+pub fn chat_artifacts_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("artifacts"))
+}
+
+/// Locks held by `q chat` sessions on the workspaces they edit.
+pub fn chat_workspace_locks_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("locks"))
+}
+
+/// The `/refactor` run that was interrupted, saved so it can be resumed.
+pub fn chat_refactor_path(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("refactor.json"))
+}
+
+/// The file of usage counts kept with the `chat.enableLocalAnalytics` setting.
+pub fn chat_analytics_path(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("analytics.json"))
+}
+
 /// The path to the fig settings file
 pub fn settings_path() -> Result<PathBuf> {
     Ok(fig_data_dir()?.join("settings.json"))
@@ -0,0 +1,232 @@
+//! Encryption at rest for data the CLI stores on the user's behalf, such as saved conversations.
+//!
+//! Encryption is turned on with `q settings storage.encrypt on`. Data is encrypted with
+//! AES-256-GCM, using a random key kept in the secret store or, when [Q_STORAGE_PASSPHRASE] is
+//! set, a key derived from the passphrase. Encrypted data starts with a header recording which
+//! kind of key was used, so it is decrypted transparently whatever the current setting is.
+//!
+//! The secret store is the CLI's own database, not the platform's keychain, so the random key is
+//! only as protected as the user's files: it keeps data out of copies made without the database,
+//! such as backups of the data's directory, but anyone able to read the database can decrypt it.
+//! Only a passphrase, which is never stored, protects the data at rest.
+//!
+//! The database's own tables aren't encrypted, so while encryption is on the conversation of each
+//! directory is no longer stored there, only in the encrypted session store.
+//!
+//! [Q_STORAGE_PASSPHRASE]: crate::util::consts::env_var::Q_STORAGE_PASSPHRASE
+
+use std::num::NonZeroU32;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use ring::aead::{
+    AES_256_GCM,
+    Aad,
+    LessSafeKey,
+    NONCE_LEN,
+    Nonce,
+    UnboundKey,
+};
+use ring::error::Unspecified;
+use ring::pbkdf2;
+use ring::rand::{
+    SecureRandom,
+    SystemRandom,
+};
+use serde_json::Value;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::consts::env_var::Q_STORAGE_PASSPHRASE;
+
+const MAGIC: &[u8; 4] = b"QENC";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 2 + SALT_LEN + NONCE_LEN;
+const KEY_LEN: usize = 32;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SECRET_KEY: &str = "amazonq:storage-encryption-key";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeySource {
+    SecretStore = 0,
+    Passphrase  = 1,
+}
+
+/// Whether data should be encrypted when it is written. Accepts `on` as well as `true`.
+pub fn is_enabled(os: &Os) -> bool {
+    match os.database.settings.get(Setting::StorageEncrypt) {
+        Some(Value::Bool(enabled)) => *enabled,
+        Some(Value::String(value)) => matches!(value.to_lowercase().as_str(), "on" | "true"),
+        _ => false,
+    }
+}
+
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// `ring` errors carry no information, so they're replaced with `message`.
+fn crypto_error(message: &'static str) -> impl FnOnce(Unspecified) -> eyre::Report {
+    move |_| eyre!(message)
+}
+
+fn passphrase(os: &Os) -> Option<String> {
+    os.env.get(Q_STORAGE_PASSPHRASE).ok().filter(|p| !p.is_empty())
+}
+
+/// Returns the key for `source`, creating the secret store key if `create` is set and there
+/// isn't one yet.
+async fn key(os: &Os, source: KeySource, salt: &[u8], create: bool) -> Result<LessSafeKey> {
+    let mut key = [0; KEY_LEN];
+    match source {
+        KeySource::Passphrase => {
+            let Some(passphrase) = passphrase(os) else {
+                bail!("This data is encrypted with a passphrase, set {Q_STORAGE_PASSPHRASE} to decrypt it");
+            };
+            pbkdf2::derive(
+                pbkdf2::PBKDF2_HMAC_SHA256,
+                NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero"),
+                salt,
+                passphrase.as_bytes(),
+                &mut key,
+            );
+        },
+        KeySource::SecretStore => match os.database.get_secret(SECRET_KEY).await? {
+            Some(secret) => {
+                let decoded = STANDARD.decode(secret.0)?;
+                if decoded.len() != KEY_LEN {
+                    bail!("The stored encryption key is invalid");
+                }
+                key.copy_from_slice(&decoded);
+            },
+            None if create => {
+                SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(crypto_error("Failed to generate an encryption key"))?;
+                os.database.set_secret(SECRET_KEY, &STANDARD.encode(key)).await?;
+            },
+            None => bail!("The key this data was encrypted with is missing from the secret store"),
+        },
+    }
+
+    Ok(LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, &key).map_err(crypto_error("Invalid encryption key"))?,
+    ))
+}
+
+/// Encrypts `plaintext`, with the passphrase if one is set and otherwise with the secret store key.
+pub async fn encrypt(os: &Os, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let source = match passphrase(os) {
+        Some(_) => KeySource::Passphrase,
+        None => KeySource::SecretStore,
+    };
+    let rng = SystemRandom::new();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(crypto_error("Failed to generate a nonce"))?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + AES_256_GCM.tag_len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[VERSION, source as u8]);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+
+    let mut in_out = plaintext.to_vec();
+    key(os, source, &salt, true)
+        .await?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(&out), &mut in_out)
+        .map_err(crypto_error("Failed to encrypt"))?;
+    out.extend_from_slice(&in_out);
+    Ok(out)
+}
+
+/// Encrypts `bytes` if encryption is enabled, otherwise returns them unchanged.
+pub async fn encrypt_if_enabled(os: &Os, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    match is_enabled(os) {
+        true => encrypt(os, &bytes).await,
+        false => Ok(bytes),
+    }
+}
+
+/// Decrypts `bytes` if they were encrypted by [encrypt], otherwise returns them unchanged.
+pub async fn decrypt(os: &Os, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_encrypted(&bytes) {
+        return Ok(bytes);
+    }
+    if bytes.len() < HEADER_LEN {
+        bail!("Encrypted data is truncated");
+    }
+    let (header, ciphertext) = bytes.split_at(HEADER_LEN);
+    if header[MAGIC.len()] != VERSION {
+        bail!("Unsupported encryption version {}", header[MAGIC.len()]);
+    }
+    let source = match header[MAGIC.len() + 1] {
+        0 => KeySource::SecretStore,
+        1 => KeySource::Passphrase,
+        other => bail!("Unknown encryption key source {other}"),
+    };
+    let salt = &header[MAGIC.len() + 2..MAGIC.len() + 2 + SALT_LEN];
+    let nonce =
+        Nonce::try_assume_unique_for_key(&header[HEADER_LEN - NONCE_LEN..]).map_err(crypto_error("Invalid nonce"))?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key(os, source, salt, false)
+        .await?
+        .open_in_place(nonce, Aad::from(header), &mut in_out)
+        .map_err(crypto_error(
+            "Failed to decrypt, the key or passphrase is wrong or the data is corrupted",
+        ))?;
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secret_store_round_trip() {
+        let os = Os::new().await.unwrap();
+        let encrypted = encrypt(&os, b"proprietary code").await.unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.windows(11).any(|w| w == b"proprietary"));
+        assert_eq!(decrypt(&os, encrypted.clone()).await.unwrap(), b"proprietary code");
+
+        // Tampering is detected.
+        let mut tampered = encrypted;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&os, tampered).await.is_err());
+
+        // Plaintext passes through.
+        assert_eq!(decrypt(&os, b"{}".to_vec()).await.unwrap(), b"{}");
+    }
+
+    #[tokio::test]
+    async fn test_passphrase_round_trip() {
+        let os = Os::new().await.unwrap();
+        unsafe { os.env.set_var(Q_STORAGE_PASSPHRASE, "hunter2") };
+        let encrypted = encrypt(&os, b"proprietary code").await.unwrap();
+        assert_eq!(encrypted[MAGIC.len() + 1], KeySource::Passphrase as u8);
+        assert_eq!(decrypt(&os, encrypted.clone()).await.unwrap(), b"proprietary code");
+
+        unsafe { os.env.set_var(Q_STORAGE_PASSPHRASE, "wrong") };
+        assert!(decrypt(&os, encrypted).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_is_enabled() {
+        let mut os = Os::new().await.unwrap();
+        assert!(!is_enabled(&os));
+        os.database.settings.set(Setting::StorageEncrypt, "on").await.unwrap();
+        assert!(is_enabled(&os));
+        os.database.settings.set(Setting::StorageEncrypt, false).await.unwrap();
+        assert!(!is_enabled(&os));
+    }
+}
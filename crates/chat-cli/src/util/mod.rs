@@ -1,8 +1,10 @@
 pub mod consts;
 pub mod directories;
+pub mod encryption;
 pub mod knowledge_store;
 pub mod open;
 pub mod process;
+pub mod redact;
 pub mod spinner;
 pub mod system_info;
 #[cfg(test)]